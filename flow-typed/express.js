@@ -0,0 +1,18 @@
+declare module 'express' {
+  declare type Handler = (req: any, res: any, next?: () => void) => mixed;
+  declare type Router = {
+    use(...handlers: Array<Handler>): Router;
+    get(path: string, handler: Handler): Router;
+    post(path: string, handler: Handler): Router;
+  };
+  declare module.exports: {
+    (): any;
+    Router(): Router;
+  };
+}
+
+declare module 'body-parser' {
+  declare module.exports: {
+    json(): (req: any, res: any, next: () => void) => void;
+  };
+}
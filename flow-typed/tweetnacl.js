@@ -0,0 +1,14 @@
+declare module 'tweetnacl' {
+  declare module.exports: {
+    sign: {
+      detached: {
+        (message: Uint8Array, secretKey: Uint8Array): Uint8Array;
+        verify(
+          message: Uint8Array,
+          signature: Uint8Array,
+          publicKey: Uint8Array,
+        ): boolean;
+      };
+    };
+  };
+}
@@ -0,0 +1,498 @@
+use crate::{
+    error::{BenchError, Result},
+    instruction::{self, SubmitAccounts},
+    report::{compute_units, Report, Summary},
+};
+use serde::Serialize;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcSendTransactionConfig, RpcTransactionConfig},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction, system_program,
+    transaction::VersionedTransaction,
+};
+use solana_transaction_status::UiTransactionEncoding;
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Size of the state and config accounts, as created by the JS client.
+const STATE_ACCOUNT_SIZE: u64 = 1024;
+
+/// Maximum number of signatures accepted by one `getSignatureStatuses` call.
+const MAX_SIGNATURE_STATUS_QUERY: usize = 256;
+
+/// Latencies are measured when a status poll sees the relay confirmed.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const BLOCKHASH_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Nonce lanes supported by the program.
+const MAX_NONCE_LANES: u64 = 16;
+
+/// Load generated by a benchmark run.
+#[derive(Clone, Debug, Serialize)]
+pub struct LoadConfig {
+    /// Consumers relaying in parallel
+    pub consumers: usize,
+    /// Nonce lanes used per consumer; each lane has at most one relay in flight
+    pub lanes: u64,
+    /// Relays sent per second
+    pub rate: f64,
+    pub duration_secs: u64,
+    /// Lamports transferred by each relay
+    pub amount: u64,
+    /// Top-up credited to each consumer for fees
+    pub topup: u64,
+    /// Relays not confirmed after this long are counted as dropped
+    pub confirm_timeout_secs: u64,
+    /// Confirmed relays fetched to measure compute units
+    pub cu_samples: usize,
+}
+
+impl LoadConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.consumers == 0 {
+            return Err(BenchError::Setup(
+                "at least one consumer is required".into(),
+            ));
+        }
+        if self.lanes == 0 || self.lanes > MAX_NONCE_LANES {
+            return Err(BenchError::Setup(format!(
+                "lanes must be between 1 and {}",
+                MAX_NONCE_LANES
+            )));
+        }
+        if !self.rate.is_finite() || self.rate <= 0.0 {
+            return Err(BenchError::Setup("rate must be positive".into()));
+        }
+        Ok(())
+    }
+
+    /// Upper bound of the relays a single consumer sends, used to fund it.
+    pub fn relays_per_consumer(&self) -> u64 {
+        (self.rate * self.duration_secs as f64 / self.consumers as f64).ceil() as u64 + 1
+    }
+}
+
+/// Accounts created for a run.
+struct Deployment {
+    gsn: Keypair,
+    config: Keypair,
+    consumers: Vec<Keypair>,
+    receiver: Pubkey,
+}
+
+/// One nonce lane of one consumer.
+#[derive(Clone, Copy)]
+struct Slot {
+    consumer: usize,
+    lane: u64,
+    nonce: u64,
+    in_flight: bool,
+}
+
+struct Pending {
+    signature: Signature,
+    signed_at: Instant,
+    slot: usize,
+}
+
+/// State shared by the sending and confirming threads.
+#[derive(Default)]
+struct Progress {
+    slots: Vec<Slot>,
+    cursor: usize,
+    pending: Vec<Pending>,
+    confirmed: Vec<Signature>,
+    latencies_ms: Vec<u64>,
+    sent: usize,
+    failed: usize,
+    dropped: usize,
+    skipped: usize,
+    sending_done: bool,
+}
+
+impl Progress {
+    /// Claim the next idle slot, round robin over consumers and lanes.
+    fn claim_slot(&mut self) -> Option<(usize, Slot)> {
+        let len = self.slots.len();
+        for offset in 0..len {
+            let index = (self.cursor + offset) % len;
+            if !self.slots[index].in_flight {
+                self.slots[index].in_flight = true;
+                self.cursor = (index + 1) % len;
+                return Some((index, self.slots[index]));
+            }
+        }
+        None
+    }
+}
+
+/// Drives a SolGSN deployment on a local validator with relay load.
+pub struct Bench {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    /// Funds the run and acts as the executor of every relay
+    payer: Keypair,
+    config: LoadConfig,
+}
+
+impl Bench {
+    pub fn new(rpc_url: &str, program_id: Pubkey, payer: Keypair, config: LoadConfig) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed()),
+            program_id,
+            payer,
+            config,
+        }
+    }
+
+    /// Create a fresh deployment, relay at the configured rate and report
+    /// latencies and compute units.
+    pub fn run(&self) -> Result<Report> {
+        self.config.validate()?;
+
+        let mut compute: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+        let deployment = self.setup(&mut compute)?;
+        println!(
+            "[BENCH] deployment ready gsn={} consumers={}",
+            deployment.gsn.pubkey(),
+            deployment.consumers.len()
+        );
+
+        let (progress, elapsed) = self.load(&deployment)?;
+
+        let samples = self.config.cu_samples.min(progress.confirmed.len());
+        if let Some(step) = progress.confirmed.len().checked_div(samples) {
+            let units = compute.entry("submit".to_string()).or_default();
+            for signature in progress.confirmed.iter().step_by(step).take(samples) {
+                units.extend(self.transaction_compute_units(signature)?);
+            }
+        }
+
+        let elapsed_secs = elapsed.as_secs_f64();
+        Ok(Report {
+            config: self.config.clone(),
+            sent: progress.sent,
+            confirmed: progress.confirmed.len(),
+            failed: progress.failed,
+            dropped: progress.dropped,
+            skipped: progress.skipped,
+            elapsed_secs,
+            throughput: progress.confirmed.len() as f64 / elapsed_secs,
+            latency_ms: Summary::from_samples(&progress.latencies_ms),
+            compute_units: compute
+                .iter()
+                .map(|(name, units)| (name.clone(), Summary::from_samples(units)))
+                .collect(),
+        })
+    }
+
+    /// Create and initialize the GSN accounts, then fund and top up every consumer.
+    fn setup(&self, compute: &mut BTreeMap<String, Vec<u64>>) -> Result<Deployment> {
+        let payer = self.payer.pubkey();
+        let state_rent = self
+            .rpc
+            .get_minimum_balance_for_rent_exemption(STATE_ACCOUNT_SIZE as usize)?;
+        let account_rent = self.rpc.get_minimum_balance_for_rent_exemption(0)?;
+        let consumer_funds = self.config.topup
+            + self.config.relays_per_consumer() * self.config.amount
+            + account_rent;
+
+        let required =
+            2 * state_rent + account_rent + consumer_funds * self.config.consumers as u64;
+        let balance = self.rpc.get_balance(&payer)?;
+        if balance < required {
+            return Err(BenchError::Setup(format!(
+                "payer {} has {} lamports, the run needs at least {}",
+                payer, balance, required
+            )));
+        }
+
+        let deployment = Deployment {
+            gsn: Keypair::new(),
+            config: Keypair::new(),
+            consumers: (0..self.config.consumers).map(|_| Keypair::new()).collect(),
+            receiver: Keypair::new().pubkey(),
+        };
+        let gsn = deployment.gsn.pubkey();
+        let config = deployment.config.pubkey();
+
+        let signature = self.send_and_confirm(
+            &[
+                system_instruction::create_account(
+                    &payer,
+                    &gsn,
+                    state_rent,
+                    STATE_ACCOUNT_SIZE,
+                    &self.program_id,
+                ),
+                system_instruction::create_account(
+                    &payer,
+                    &config,
+                    state_rent,
+                    STATE_ACCOUNT_SIZE,
+                    &self.program_id,
+                ),
+                instruction::initialize(&self.program_id, &gsn, &config),
+                // Keeps the receiver rent exempt whatever the relay amount
+                system_instruction::transfer(&payer, &deployment.receiver, account_rent),
+            ],
+            &[&self.payer, &deployment.gsn, &deployment.config],
+        )?;
+        compute
+            .entry("initialize".to_string())
+            .or_default()
+            .extend(self.transaction_compute_units(&signature)?);
+
+        for consumer in &deployment.consumers {
+            let signature = self.send_and_confirm(
+                &[
+                    system_instruction::transfer(&payer, &consumer.pubkey(), consumer_funds),
+                    instruction::topup(
+                        &self.program_id,
+                        &gsn,
+                        &consumer.pubkey(),
+                        &config,
+                        self.config.topup,
+                    ),
+                ],
+                &[&self.payer, consumer],
+            )?;
+            compute
+                .entry("topup".to_string())
+                .or_default()
+                .extend(self.transaction_compute_units(&signature)?);
+        }
+
+        Ok(deployment)
+    }
+
+    /// Send relays for the configured duration and wait for all of them to settle.
+    fn load(&self, deployment: &Deployment) -> Result<(Progress, Duration)> {
+        let lanes = self.config.lanes;
+        let progress = Mutex::new(Progress {
+            slots: (0..self.config.consumers)
+                .flat_map(|consumer| {
+                    (0..lanes).map(move |lane| Slot {
+                        consumer,
+                        lane,
+                        nonce: 0,
+                        in_flight: false,
+                    })
+                })
+                .collect(),
+            ..Progress::default()
+        });
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_secs(self.config.duration_secs);
+        thread::scope(|scope| {
+            let sender = scope.spawn(|| {
+                let result = self.send_relays(deployment, &progress, deadline);
+                progress.lock().unwrap().sending_done = true;
+                result
+            });
+            let confirmed = self.confirm_relays(&progress);
+            let sent = sender.join().expect("relay sender panicked");
+            sent.and(confirmed)
+        })?;
+
+        Ok((progress.into_inner().unwrap(), started.elapsed()))
+    }
+
+    /// Send one relay per tick on the next idle (consumer, lane) slot.
+    fn send_relays(
+        &self,
+        deployment: &Deployment,
+        progress: &Mutex<Progress>,
+        deadline: Instant,
+    ) -> Result<()> {
+        let interval = Duration::from_secs_f64(1.0 / self.config.rate);
+        let mut blockhash = self.rpc.get_latest_blockhash()?;
+        let mut blockhash_at = Instant::now();
+        let mut next = Instant::now();
+
+        while next < deadline {
+            let now = Instant::now();
+            if next > now {
+                thread::sleep(next - now);
+            }
+            next += interval;
+
+            if blockhash_at.elapsed() >= BLOCKHASH_REFRESH_INTERVAL {
+                blockhash = self.rpc.get_latest_blockhash()?;
+                blockhash_at = Instant::now();
+            }
+
+            let claimed = progress.lock().unwrap().claim_slot();
+            let (index, slot) = match claimed {
+                Some(claimed) => claimed,
+                None => {
+                    progress.lock().unwrap().skipped += 1;
+                    continue;
+                }
+            };
+
+            let signed_at = Instant::now();
+            let result = self.relay(deployment, &slot, blockhash);
+
+            let mut progress = progress.lock().unwrap();
+            match result {
+                Ok(signature) => {
+                    progress.sent += 1;
+                    progress.pending.push(Pending {
+                        signature,
+                        signed_at,
+                        slot: index,
+                    });
+                }
+                Err(error) => {
+                    eprintln!("[BENCH] relay rejected error={}", error);
+                    progress.failed += 1;
+                    progress.slots[index].in_flight = false;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Poll the status of pending relays until the sender is done and none is left.
+    fn confirm_relays(&self, progress: &Mutex<Progress>) -> Result<()> {
+        let timeout = Duration::from_secs(self.config.confirm_timeout_secs);
+        loop {
+            thread::sleep(STATUS_POLL_INTERVAL);
+
+            let (signatures, sending_done) = {
+                let progress = progress.lock().unwrap();
+                let signatures: Vec<Signature> =
+                    progress.pending.iter().map(|p| p.signature).collect();
+                (signatures, progress.sending_done)
+            };
+            if signatures.is_empty() {
+                if sending_done {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            let mut statuses = Vec::with_capacity(signatures.len());
+            for chunk in signatures.chunks(MAX_SIGNATURE_STATUS_QUERY) {
+                statuses.extend(self.rpc.get_signature_statuses(chunk)?.value);
+            }
+
+            // Relays sent since the snapshot have no status yet and stay pending
+            let now = Instant::now();
+            let mut progress = progress.lock().unwrap();
+            let mut still_pending = Vec::new();
+            for (i, pending) in std::mem::take(&mut progress.pending)
+                .into_iter()
+                .enumerate()
+            {
+                match statuses.get(i).cloned().flatten() {
+                    Some(status) if status.err.is_some() => {
+                        progress.failed += 1;
+                        progress.slots[pending.slot].in_flight = false;
+                    }
+                    Some(status) if status.satisfies_commitment(CommitmentConfig::confirmed()) => {
+                        progress
+                            .latencies_ms
+                            .push(now.duration_since(pending.signed_at).as_millis() as u64);
+                        progress.confirmed.push(pending.signature);
+                        let slot = &mut progress.slots[pending.slot];
+                        slot.nonce += 1;
+                        slot.in_flight = false;
+                    }
+                    _ if now.duration_since(pending.signed_at) >= timeout => {
+                        progress.dropped += 1;
+                        progress.slots[pending.slot].in_flight = false;
+                    }
+                    _ => still_pending.push(pending),
+                }
+            }
+            progress.pending = still_pending;
+        }
+    }
+
+    /// Sign and send a v0 relay of the slot's next nonce, paid by the executor.
+    fn relay(&self, deployment: &Deployment, slot: &Slot, blockhash: Hash) -> Result<Signature> {
+        let consumer = &deployment.consumers[slot.consumer];
+        let accounts = SubmitAccounts {
+            target: system_program::id(),
+            sender: consumer.pubkey(),
+            receiver: deployment.receiver,
+            fee_payer: self.payer.pubkey(),
+            gsn: deployment.gsn.pubkey(),
+            config: deployment.config.pubkey(),
+        };
+        let transaction = self.sign(
+            &[instruction::submit(
+                &self.program_id,
+                &accounts,
+                self.config.amount,
+                slot.nonce,
+                slot.lane,
+            )],
+            &[&self.payer, consumer],
+            blockhash,
+        )?;
+        Ok(self.rpc.send_transaction_with_config(
+            &transaction,
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..RpcSendTransactionConfig::default()
+            },
+        )?)
+    }
+
+    fn sign(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+        blockhash: Hash,
+    ) -> Result<VersionedTransaction> {
+        let message = v0::Message::try_compile(&self.payer.pubkey(), instructions, &[], blockhash)?;
+        Ok(VersionedTransaction::try_new(
+            VersionedMessage::V0(message),
+            signers,
+        )?)
+    }
+
+    fn send_and_confirm(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = self.sign(instructions, signers, blockhash)?;
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+
+    /// Compute units of each SolGSN instruction of a confirmed transaction.
+    fn transaction_compute_units(&self, signature: &Signature) -> Result<Vec<u64>> {
+        let transaction = self.rpc.get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        let logs: Vec<String> = match transaction.transaction.meta {
+            Some(meta) => Option::from(meta.log_messages).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        Ok(compute_units(&logs, &self.program_id))
+    }
+}
@@ -0,0 +1,28 @@
+use solana_client::client_error::ClientError as RpcError;
+use thiserror::Error;
+
+/// Errors that may be returned by the benchmark.
+#[derive(Debug, Error)]
+pub enum BenchError {
+    /// Boxed, the RPC error is much larger than the other variants
+    #[error("RPC error: {0}")]
+    Rpc(Box<RpcError>),
+    #[error("Message compile error: {0}")]
+    Compile(#[from] solana_sdk::message::CompileError),
+    #[error("Signer error: {0}")]
+    Signer(#[from] solana_sdk::signer::SignerError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid keypair: {0}")]
+    Keypair(String),
+    #[error("Setup failed: {0}")]
+    Setup(String),
+}
+
+impl From<RpcError> for BenchError {
+    fn from(error: RpcError) -> Self {
+        Self::Rpc(Box::new(error))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, BenchError>;
@@ -0,0 +1,104 @@
+//! SolGSN instructions used by the benchmark, encoded like the JS client.
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+pub const INITIALIZE: u8 = 0;
+pub const TOPUP: u8 = 1;
+pub const SUBMIT_TRANSACTION: u8 = 2;
+pub const SUBMIT_TRANSACTION_IN_LANE: u8 = 8;
+
+/// Seed of the vault holding a deployment's top-ups
+pub const VAULT_SEED: &[u8] = b"vault";
+
+/// Accounts of a `SubmitTransaction`, in instruction order.
+#[derive(Clone, Copy, Debug)]
+pub struct SubmitAccounts {
+    /// Program invoked with the consumer's transfer
+    pub target: Pubkey,
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    /// Executor paying the transaction fee
+    pub fee_payer: Pubkey,
+    pub gsn: Pubkey,
+    pub config: Pubkey,
+}
+
+/// `Initialize` for freshly created state and config accounts.
+pub fn initialize(program_id: &Pubkey, gsn: &Pubkey, config: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[INITIALIZE],
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new(*config, false),
+        ],
+    )
+}
+
+/// Vault of the GSN state account `gsn`.
+pub fn vault_address(program_id: &Pubkey, gsn: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[VAULT_SEED, gsn.as_ref()], program_id).0
+}
+
+/// `Topup` moving `amount` lamports from `consumer`, which signs, into the
+/// vault and crediting them to its balance.
+///
+/// The benchmark's deployments don't run in compliance mode, so no credential is passed.
+pub fn topup(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    consumer: &Pubkey,
+    config: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![TOPUP];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new(*consumer, true),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new(vault_address(program_id, gsn), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// `SubmitTransaction` for lane 0, `SubmitTransactionInLane` otherwise.
+pub fn submit(
+    program_id: &Pubkey,
+    accounts: &SubmitAccounts,
+    amount: u64,
+    nonce: u64,
+    lane: u64,
+) -> Instruction {
+    let mut data = vec![if lane == 0 {
+        SUBMIT_TRANSACTION
+    } else {
+        SUBMIT_TRANSACTION_IN_LANE
+    }];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&nonce.to_le_bytes());
+    if lane != 0 {
+        data.extend_from_slice(&lane.to_le_bytes());
+    }
+
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(accounts.target, false),
+            AccountMeta::new(accounts.sender, true),
+            AccountMeta::new(accounts.receiver, false),
+            AccountMeta::new(accounts.fee_payer, true),
+            AccountMeta::new(accounts.gsn, false),
+            AccountMeta::new_readonly(accounts.config, false),
+        ],
+    )
+}
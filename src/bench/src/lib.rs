@@ -0,0 +1,8 @@
+pub mod driver;
+pub mod error;
+pub mod instruction;
+pub mod report;
+
+pub use driver::{Bench, LoadConfig};
+pub use error::BenchError;
+pub use report::Report;
@@ -0,0 +1,121 @@
+use clap::Parser;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use solgsn_bench::{
+    error::{BenchError, Result},
+    report::ReportFormat,
+    Bench, LoadConfig,
+};
+use std::{fs, process::exit};
+
+/// Relay load against a local validator, reporting end-to-end latency and compute units.
+#[derive(Parser, Debug)]
+#[command(name = "solgsn-bench", version)]
+struct Args {
+    /// RPC endpoint of the validator
+    #[arg(long, default_value = "http://localhost:8899")]
+    url: String,
+
+    /// SolGSN program id
+    #[arg(long)]
+    program_id: Pubkey,
+
+    /// Keypair funding the run and executing every relay; a new one is airdropped when omitted
+    #[arg(long)]
+    keypair: Option<String>,
+
+    /// Lamports airdropped to a generated payer
+    #[arg(long, default_value_t = 100_000_000_000)]
+    airdrop: u64,
+
+    /// Consumers relaying in parallel
+    #[arg(long, default_value_t = 10)]
+    consumers: usize,
+
+    /// Nonce lanes per consumer (1-16); each lane has at most one relay in flight
+    #[arg(long, default_value_t = 4)]
+    lanes: u64,
+
+    /// Relays sent per second
+    #[arg(long, default_value_t = 10.0)]
+    rate: f64,
+
+    /// Seconds to send relays for
+    #[arg(long, default_value_t = 30)]
+    duration: u64,
+
+    /// Lamports transferred by each relay
+    #[arg(long, default_value_t = 1_000_000)]
+    amount: u64,
+
+    /// Lamports topped up per consumer to cover fees
+    #[arg(long, default_value_t = 1_000_000_000)]
+    topup: u64,
+
+    /// Seconds after which an unconfirmed relay is counted as dropped
+    #[arg(long, default_value_t = 90)]
+    confirm_timeout: u64,
+
+    /// Confirmed relays fetched to measure compute units
+    #[arg(long, default_value_t = 200)]
+    cu_samples: usize,
+
+    /// text or json
+    #[arg(long, default_value = "text")]
+    format: ReportFormat,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long)]
+    output: Option<String>,
+}
+
+fn payer(args: &Args) -> Result<Keypair> {
+    if let Some(path) = &args.keypair {
+        return read_keypair_file(path).map_err(|error| BenchError::Keypair(error.to_string()));
+    }
+    let payer = Keypair::new();
+    let rpc = RpcClient::new(args.url.clone());
+    let signature = rpc.request_airdrop(&payer.pubkey(), args.airdrop)?;
+    rpc.poll_for_signature(&signature)?;
+    println!(
+        "[BENCH] airdropped payer={} lamports={}",
+        payer.pubkey(),
+        args.airdrop
+    );
+    Ok(payer)
+}
+
+fn run(args: Args) -> Result<()> {
+    let config = LoadConfig {
+        consumers: args.consumers,
+        lanes: args.lanes,
+        rate: args.rate,
+        duration_secs: args.duration,
+        amount: args.amount,
+        topup: args.topup,
+        confirm_timeout_secs: args.confirm_timeout,
+        cu_samples: args.cu_samples,
+    };
+    let bench = Bench::new(&args.url, args.program_id, payer(&args)?, config);
+    let report = bench.run()?;
+
+    let rendered = match args.format {
+        ReportFormat::Text => report.to_text(),
+        ReportFormat::Json => report.to_json() + "\n",
+    };
+    match &args.output {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(error) = run(Args::parse()) {
+        eprintln!("[BENCH] error={}", error);
+        exit(1);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::driver::LoadConfig;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
+
+/// Output format of a report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("expected `text` or `json`, got `{}`", s)),
+        }
+    }
+}
+
+/// Distribution of a set of samples.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: u64,
+    pub p50: u64,
+    pub p99: u64,
+    pub max: u64,
+    pub mean: f64,
+}
+
+impl Summary {
+    /// Summarize `samples`; all fields are zero when there are none.
+    pub fn from_samples(samples: &[u64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        Self {
+            count: sorted.len(),
+            min: sorted[0],
+            p50: percentile(&sorted, 50.0),
+            p99: percentile(&sorted, 99.0),
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<u64>() as f64 / sorted.len() as f64,
+        }
+    }
+}
+
+/// Nearest-rank percentile of non-empty, ascending `sorted` samples.
+pub fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Compute units consumed by each top-level invocation of `program_id`, from
+/// the `Program <id> consumed <n> of <m> compute units` log lines.
+pub fn compute_units(logs: &[String], program_id: &Pubkey) -> Vec<u64> {
+    let prefix = format!("Program {} consumed ", program_id);
+    logs.iter()
+        .filter_map(|line| line.strip_prefix(&prefix))
+        .filter_map(|rest| rest.split_whitespace().next()?.parse().ok())
+        .collect()
+}
+
+/// Result of a benchmark run.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub config: LoadConfig,
+    /// Relays sent to the cluster
+    pub sent: usize,
+    pub confirmed: usize,
+    /// Rejected by the RPC node or failed on-chain
+    pub failed: usize,
+    /// Not confirmed within the confirmation timeout
+    pub dropped: usize,
+    /// Ticks where every (consumer, lane) slot still had a relay in flight
+    pub skipped: usize,
+    /// Time from the first relay until the last one settled
+    pub elapsed_secs: f64,
+    /// Confirmed relays per second over `elapsed_secs`
+    pub throughput: f64,
+    /// Time from signing the payload until it was confirmed, in milliseconds
+    pub latency_ms: Summary,
+    /// Compute units per SolGSN instruction, keyed by instruction name
+    pub compute_units: BTreeMap<String, Summary>,
+}
+
+impl Report {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report serializes to JSON")
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let c = &self.config;
+        writeln!(
+            out,
+            "load: {} consumers x {} lanes, {} relays/s for {}s",
+            c.consumers, c.lanes, c.rate, c.duration_secs
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "relays: sent={} confirmed={} failed={} dropped={} skipped={}",
+            self.sent, self.confirmed, self.failed, self.dropped, self.skipped
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "throughput: {:.2} confirmed/s over {:.1}s",
+            self.throughput, self.elapsed_secs
+        )
+        .unwrap();
+        writeln!(out, "latency (ms): {}", format_summary(&self.latency_ms)).unwrap();
+        for (name, summary) in &self.compute_units {
+            writeln!(out, "compute units {}: {}", name, format_summary(summary)).unwrap();
+        }
+        out
+    }
+}
+
+fn format_summary(summary: &Summary) -> String {
+    format!(
+        "n={} min={} p50={} p99={} max={} mean={:.1}",
+        summary.count, summary.min, summary.p50, summary.p99, summary.max, summary.mean
+    )
+}
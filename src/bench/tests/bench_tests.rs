@@ -0,0 +1,168 @@
+// Tests for instruction encoding, statistics and reports of the SolGSN benchmark
+
+use solana_sdk::{pubkey::Pubkey, system_program};
+use solgsn_bench::{
+    instruction::{self, SubmitAccounts},
+    report::{compute_units, percentile, ReportFormat, Summary},
+    LoadConfig, Report,
+};
+use std::collections::BTreeMap;
+
+fn load_config() -> LoadConfig {
+    LoadConfig {
+        consumers: 4,
+        lanes: 2,
+        rate: 10.0,
+        duration_secs: 30,
+        amount: 1_000_000,
+        topup: 1_000_000_000,
+        confirm_timeout_secs: 90,
+        cu_samples: 200,
+    }
+}
+
+#[test]
+fn test_submit_encoding_matches_client() {
+    let program_id = Pubkey::new_unique();
+    let accounts = SubmitAccounts {
+        target: system_program::id(),
+        sender: Pubkey::new_unique(),
+        receiver: Pubkey::new_unique(),
+        fee_payer: Pubkey::new_unique(),
+        gsn: Pubkey::new_unique(),
+        config: Pubkey::new_unique(),
+    };
+
+    let lane_zero = instruction::submit(&program_id, &accounts, 5, 7, 0);
+    assert_eq!(lane_zero.data.len(), 17);
+    assert_eq!(lane_zero.data[0], instruction::SUBMIT_TRANSACTION);
+    assert_eq!(&lane_zero.data[1..9], &5u64.to_le_bytes());
+    assert_eq!(&lane_zero.data[9..17], &7u64.to_le_bytes());
+
+    let lane_three = instruction::submit(&program_id, &accounts, 5, 7, 3);
+    assert_eq!(lane_three.data.len(), 25);
+    assert_eq!(lane_three.data[0], instruction::SUBMIT_TRANSACTION_IN_LANE);
+    assert_eq!(&lane_three.data[17..25], &3u64.to_le_bytes());
+
+    let signers: Vec<Pubkey> = lane_zero
+        .accounts
+        .iter()
+        .filter(|meta| meta.is_signer)
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(signers, vec![accounts.sender, accounts.fee_payer]);
+    assert!(!lane_zero.accounts[5].is_writable, "config is read-only");
+}
+
+#[test]
+fn test_topup_encoding() {
+    let topup = instruction::topup(
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        42,
+    );
+    assert_eq!(topup.data[0], instruction::TOPUP);
+    assert_eq!(&topup.data[1..], &42u64.to_le_bytes());
+    assert!(topup.accounts[0].is_writable);
+    assert!(topup.accounts[1].is_signer && topup.accounts[1].is_writable);
+    assert!(!topup.accounts[2].is_writable);
+    assert!(topup.accounts[3].is_writable);
+    assert_eq!(topup.accounts.len(), 5);
+}
+
+#[test]
+fn test_percentiles_use_nearest_rank() {
+    let samples: Vec<u64> = (1..=100).collect();
+    assert_eq!(percentile(&samples, 50.0), 50);
+    assert_eq!(percentile(&samples, 99.0), 99);
+    assert_eq!(percentile(&samples, 100.0), 100);
+    assert_eq!(percentile(&[7], 99.0), 7);
+
+    let summary = Summary::from_samples(&[30, 10, 20]);
+    assert_eq!(summary.count, 3);
+    assert_eq!(
+        (summary.min, summary.p50, summary.p99, summary.max),
+        (10, 20, 30, 30)
+    );
+    assert_eq!(summary.mean, 20.0);
+    assert_eq!(Summary::from_samples(&[]), Summary::default());
+}
+
+#[test]
+fn test_compute_units_only_counts_program() {
+    let program_id = Pubkey::new_unique();
+    let logs = vec![
+        format!("Program {} invoke [1]", program_id),
+        "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+        "Program 11111111111111111111111111111111 success".to_string(),
+        format!(
+            "Program {} consumed 12345 of 200000 compute units",
+            program_id
+        ),
+        format!("Program {} success", program_id),
+        format!(
+            "Program {} consumed 7 of 200000 compute units",
+            Pubkey::new_unique()
+        ),
+    ];
+    assert_eq!(compute_units(&logs, &program_id), vec![12345]);
+}
+
+#[test]
+fn test_load_config_validation() {
+    assert!(load_config().validate().is_ok());
+    assert!(LoadConfig {
+        consumers: 0,
+        ..load_config()
+    }
+    .validate()
+    .is_err());
+    assert!(LoadConfig {
+        lanes: 17,
+        ..load_config()
+    }
+    .validate()
+    .is_err());
+    assert!(LoadConfig {
+        rate: 0.0,
+        ..load_config()
+    }
+    .validate()
+    .is_err());
+
+    // 300 relays over 4 consumers, plus one for rounding
+    assert_eq!(load_config().relays_per_consumer(), 76);
+}
+
+#[test]
+fn test_report_rendering() {
+    let mut compute_units = BTreeMap::new();
+    compute_units.insert("submit".to_string(), Summary::from_samples(&[9000, 11000]));
+    let report = Report {
+        config: load_config(),
+        sent: 300,
+        confirmed: 298,
+        failed: 1,
+        dropped: 1,
+        skipped: 0,
+        elapsed_secs: 31.5,
+        throughput: 298.0 / 31.5,
+        latency_ms: Summary::from_samples(&[400, 600, 800]),
+        compute_units,
+    };
+
+    let text = report.to_text();
+    assert!(text.contains("relays: sent=300 confirmed=298 failed=1 dropped=1 skipped=0"));
+    assert!(text.contains("latency (ms): n=3 min=400 p50=600 p99=800 max=800"));
+    assert!(text.contains("compute units submit: n=2 min=9000 p50=9000 p99=11000"));
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["latency_ms"]["p99"], 800);
+    assert_eq!(json["config"]["lanes"], 2);
+    assert_eq!(json["compute_units"]["submit"]["max"], 11000);
+
+    assert_eq!("json".parse::<ReportFormat>(), Ok(ReportFormat::Json));
+    assert!("yaml".parse::<ReportFormat>().is_err());
+}
@@ -0,0 +1,58 @@
+//! Parsing of the CLI's cluster and fee arguments.
+
+use solgsn::instruction::UpdateFeeParamsArgs;
+use std::str::FromStr;
+
+/// RPC endpoint of `cluster`, a moniker like the `solana` CLI's or a URL.
+pub fn cluster_url(cluster: &str) -> String {
+    match cluster {
+        "localhost" | "l" => "http://localhost:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "testnet" | "t" => "https://api.testnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+    .to_string()
+}
+
+/// Fee mode set by `set-fee`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeeMode {
+    /// Lamports per submission
+    Fixed,
+    /// Basis points of the transferred amount
+    Percent,
+    /// Micro-lamports per compute unit
+    Compute,
+}
+
+impl FromStr for FeeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(Self::Fixed),
+            "percent" => Ok(Self::Percent),
+            "compute" => Ok(Self::Compute),
+            _ => Err(format!(
+                "expected `fixed`, `percent` or `compute`, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// `UpdateFeeParams` arguments of `mode` charging `value`. `extra` holds the
+/// minimum and maximum fee of `Percent`, or the base fee and maximum compute
+/// units of `Compute`.
+pub fn fee_params(mode: FeeMode, value: u64, extra: [u64; 2]) -> UpdateFeeParamsArgs {
+    UpdateFeeParamsArgs {
+        fee_mode_type: match mode {
+            FeeMode::Fixed => 0,
+            FeeMode::Percent => 1,
+            FeeMode::Compute => 2,
+        },
+        fee_value: value,
+        extra: if mode == FeeMode::Fixed { [0; 2] } else { extra },
+    }
+}
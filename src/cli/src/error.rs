@@ -0,0 +1,20 @@
+use solana_client::client_error::ClientError as RpcError;
+use solgsn_client::ClientError;
+use thiserror::Error;
+
+/// Errors that may be returned by the CLI.
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("{0}")]
+    Client(#[from] ClientError),
+    #[error("Invalid keypair: {0}")]
+    Keypair(String),
+}
+
+impl From<RpcError> for CliError {
+    fn from(error: RpcError) -> Self {
+        Self::Client(error.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CliError>;
@@ -0,0 +1,4 @@
+pub mod args;
+pub mod error;
+
+pub use error::CliError;
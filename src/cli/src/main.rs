@@ -0,0 +1,238 @@
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    system_instruction,
+};
+use solgsn::instruction::{self, SubmitArgs};
+use solgsn_cli::{
+    args::{cluster_url, fee_params, FeeMode},
+    error::{CliError, Result},
+};
+use solgsn_client::GsnClient;
+use std::process::exit;
+
+/// Size of the state and config accounts `init` creates, as the JS client does.
+const ACCOUNT_SIZE: usize = 1024;
+
+/// Operate a SolGSN deployment.
+#[derive(Parser, Debug)]
+#[command(name = "solgsn", version)]
+struct Args {
+    /// Cluster to talk to: localhost, devnet, testnet, mainnet-beta or an RPC URL
+    #[arg(long, short = 'u', default_value = "localhost", global = true)]
+    url: String,
+
+    /// Keypair signing and paying for transactions [default: ~/.config/solana/id.json]
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<String>,
+
+    /// SolGSN program id
+    #[arg(long, env = "SOLGSN_PROGRAM_ID")]
+    program_id: Pubkey,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create and initialize a deployment, governed by the keypair
+    Init,
+    /// Top up the keypair's balance
+    Topup {
+        /// GSN state account
+        #[arg(long)]
+        gsn: Pubkey,
+        /// Lamports to top up
+        amount: u64,
+    },
+    /// Show the top-up balance and executor earnings of an account
+    Balance {
+        /// GSN state account
+        #[arg(long)]
+        gsn: Pubkey,
+        /// Account to show, the keypair's when omitted
+        account: Option<Pubkey>,
+    },
+    /// Relay a transfer of the sender's, the keypair executing it
+    Submit {
+        /// GSN state account
+        #[arg(long)]
+        gsn: Pubkey,
+        /// Keypair of the consumer sending the transfer
+        #[arg(long)]
+        sender: String,
+        #[arg(long)]
+        receiver: Pubkey,
+        /// Lamports transferred
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        nonce: u64,
+        /// Highest fee the consumer accepts
+        #[arg(long)]
+        max_fee: Option<u64>,
+        /// Lamports paid to the executor on top of the fee
+        #[arg(long, default_value_t = 0)]
+        tip: u64,
+    },
+    /// Claim the keypair's executor earnings
+    Claim {
+        /// GSN state account
+        #[arg(long)]
+        gsn: Pubkey,
+        /// Account receiving the earnings, the keypair or its payout address
+        #[arg(long)]
+        destination: Option<Pubkey>,
+    },
+    /// Change the fee mode, signed by the governance authority
+    SetFee {
+        /// GSN state account
+        #[arg(long)]
+        gsn: Pubkey,
+        /// fixed, percent or compute
+        #[arg(long)]
+        mode: FeeMode,
+        /// Lamports for fixed, basis points for percent, micro-lamports per
+        /// compute unit for compute
+        #[arg(long)]
+        value: u64,
+        /// percent: minimum and maximum fee; compute: base fee and maximum
+        /// compute units
+        #[arg(long, num_args = 2, value_names = ["A", "B"])]
+        extra: Option<Vec<u64>>,
+        /// Propose the change, to apply after the timelock; fee increases
+        /// can't apply right away
+        #[arg(long)]
+        propose: bool,
+    },
+    /// Allow fees in a token mint, signed by the governance authority
+    AllowToken {
+        /// GSN state account
+        #[arg(long)]
+        gsn: Pubkey,
+        mint: Pubkey,
+    },
+}
+
+fn keypair(path: &Option<String>) -> Result<Keypair> {
+    let path = match path {
+        Some(path) => path.clone(),
+        None => format!(
+            "{}/.config/solana/id.json",
+            std::env::var("HOME").unwrap_or_default()
+        ),
+    };
+    read_keypair_file(&path).map_err(|error| CliError::Keypair(format!("{}: {}", path, error)))
+}
+
+fn run(args: Args) -> Result<()> {
+    let program_id = args.program_id;
+    let rpc = RpcClient::new_with_commitment(cluster_url(&args.url), CommitmentConfig::confirmed());
+    let client = GsnClient::new(rpc, program_id);
+    let signer = keypair(&args.keypair)?;
+
+    match args.command {
+        Command::Init => {
+            let (gsn, config) = (Keypair::new(), Keypair::new());
+            let lamports = client
+                .rpc()
+                .get_minimum_balance_for_rent_exemption(ACCOUNT_SIZE)?;
+            let create = |account: &Keypair| {
+                system_instruction::create_account(
+                    &signer.pubkey(),
+                    &account.pubkey(),
+                    lamports,
+                    ACCOUNT_SIZE as u64,
+                    &program_id,
+                )
+            };
+            let instructions = [
+                create(&gsn),
+                create(&config),
+                instruction::initialize(
+                    &program_id,
+                    &gsn.pubkey(),
+                    &config.pubkey(),
+                    Some(&signer.pubkey()),
+                ),
+            ];
+            let signature = client.send(&instructions, &signer, &[&signer, &gsn, &config])?;
+            println!("GSN account: {}", gsn.pubkey());
+            println!("Config account: {}", config.pubkey());
+            println!("Signature: {}", signature);
+        }
+        Command::Topup { gsn, amount } => {
+            println!("Signature: {}", client.topup(&gsn, &signer, amount)?);
+        }
+        Command::Balance { gsn, account } => {
+            let account = account.unwrap_or_else(|| signer.pubkey());
+            let state = client.get_state(&gsn)?;
+            let balance = state.consumer.get(&account).copied().unwrap_or(0);
+            let earnings = state.executor.get(&account).copied().unwrap_or(0);
+            println!("Top-up balance: {} lamports", balance);
+            println!("Executor earnings: {} lamports", earnings);
+        }
+        Command::Submit {
+            gsn,
+            sender,
+            receiver,
+            amount,
+            nonce,
+            max_fee,
+            tip,
+        } => {
+            let sender = keypair(&Some(sender))?;
+            let args = SubmitArgs {
+                amount,
+                nonce,
+                expiry_slot: None,
+                max_fee,
+                tip,
+                simulate: false,
+                quote: None,
+            };
+            let signature = client.submit_transaction(&gsn, &sender, &receiver, &signer, &args)?;
+            println!("Signature: {}", signature);
+        }
+        Command::Claim { gsn, destination } => {
+            let destination = destination.unwrap_or_else(|| signer.pubkey());
+            let claim = instruction::claim_fees(&program_id, &gsn, &signer.pubkey(), &destination);
+            println!("Signature: {}", client.send(&[claim], &signer, &[&signer])?);
+        }
+        Command::SetFee {
+            gsn,
+            mode,
+            value,
+            extra,
+            propose,
+        } => {
+            let config = client.get_state(&gsn)?.config;
+            let extra = extra.map_or([0; 2], |extra| [extra[0], extra[1]]);
+            let args = fee_params(mode, value, extra);
+            let set_fee = if propose {
+                instruction::propose_fee_params(&program_id, &config, &signer.pubkey(), &args)
+            } else {
+                instruction::update_fee_params(&program_id, &config, &signer.pubkey(), &args)
+            };
+            println!("Signature: {}", client.send(&[set_fee], &signer, &[&signer])?);
+        }
+        Command::AllowToken { gsn, mint } => {
+            let config = client.get_state(&gsn)?.config;
+            let allow =
+                instruction::add_allowed_token(&program_id, &config, &signer.pubkey(), &mint);
+            println!("Signature: {}", client.send(&[allow], &signer, &[&signer])?);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(error) = run(Args::parse()) {
+        eprintln!("error: {}", error);
+        exit(1);
+    }
+}
@@ -0,0 +1,30 @@
+// Tests for the cluster and fee arguments of the SolGSN CLI
+
+use solgsn::instruction::UpdateFeeParamsArgs;
+use solgsn_cli::args::{cluster_url, fee_params, FeeMode};
+
+#[test]
+fn test_cluster_monikers() {
+    assert_eq!(cluster_url("localhost"), "http://localhost:8899");
+    assert_eq!(cluster_url("d"), "https://api.devnet.solana.com");
+    assert_eq!(cluster_url("mainnet-beta"), "https://api.mainnet-beta.solana.com");
+    assert_eq!(cluster_url("http://10.0.0.2:8899"), "http://10.0.0.2:8899");
+}
+
+#[test]
+fn test_fee_params() {
+    assert_eq!("percent".parse::<FeeMode>(), Ok(FeeMode::Percent));
+    assert!("flat".parse::<FeeMode>().is_err());
+
+    assert_eq!(
+        fee_params(FeeMode::Percent, 100, [1_000, 50_000]),
+        UpdateFeeParamsArgs {
+            fee_mode_type: 1,
+            fee_value: 100,
+            extra: [1_000, 50_000],
+        }
+    );
+    // A fixed fee takes no extra values
+    assert_eq!(fee_params(FeeMode::Fixed, 5_000, [1, 2]).extra, [0; 2]);
+    assert_eq!(fee_params(FeeMode::Compute, 10, [0, 200_000]).fee_mode_type, 2);
+}
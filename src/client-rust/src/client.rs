@@ -0,0 +1,220 @@
+//! Typed access to a SolGSN deployment over RPC.
+
+use crate::{
+    error::{gsn_error, ClientError, Result},
+    fee::{self, FeeEstimate, FeeState},
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, SubmitArgs},
+    oracle,
+    state::{ExecutionReceipt, GsnConfig},
+    GsnInfo,
+};
+use std::collections::HashMap;
+
+/// Client of the SolGSN program deployed at `program_id`.
+pub struct GsnClient {
+    rpc: RpcClient,
+    program_id: Pubkey,
+}
+
+impl GsnClient {
+    pub fn new(rpc: RpcClient, program_id: Pubkey) -> Self {
+        Self { rpc, program_id }
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    /// Fetch and decode the GSN state account `gsn`.
+    pub fn get_state(&self, gsn: &Pubkey) -> Result<GsnInfo> {
+        decode_state(&self.program_id, &self.get_account(gsn)?)
+    }
+
+    /// Fetch and decode the config account `config`.
+    pub fn get_config(&self, config: &Pubkey) -> Result<GsnConfig> {
+        let account = self.get_account(config)?;
+        check_owner(&self.program_id, &account)?;
+        Ok(GsnConfig::deserialize(&account.data)?)
+    }
+
+    /// Top-up balance of `consumer`, 0 if it never topped up.
+    pub fn get_balance(&self, gsn: &Pubkey, consumer: &Pubkey) -> Result<u64> {
+        let state = self.get_state(gsn)?;
+        Ok(state.consumer.get(consumer).copied().unwrap_or(0))
+    }
+
+    /// Every balance of `consumer` by mint, lamports under `NATIVE_FEE_TOKEN`,
+    /// see [`GsnInfo::consumer_balances`].
+    pub fn get_balances(&self, gsn: &Pubkey, consumer: &Pubkey) -> Result<Vec<(Pubkey, u64)>> {
+        Ok(self.get_state(gsn)?.consumer_balances(consumer))
+    }
+
+    /// Every balance `executor` earned by mint, like [`Self::get_balances`].
+    pub fn get_earnings(&self, gsn: &Pubkey, executor: &Pubkey) -> Result<Vec<(Pubkey, u64)>> {
+        Ok(self.get_state(gsn)?.executor_balances(executor))
+    }
+
+    /// Fetch and decode the receipt of `consumer`'s submission with `nonce`
+    /// in `lane`, written if the executor passed its account.
+    pub fn get_receipt(
+        &self,
+        gsn: &Pubkey,
+        consumer: &Pubkey,
+        lane: u64,
+        nonce: u64,
+    ) -> Result<ExecutionReceipt> {
+        let (receipt, _) = ExecutionReceipt::find_address(&self.program_id, gsn, consumer, lane, nonce);
+        let account = self.get_account(&receipt)?;
+        check_owner(&self.program_id, &account)?;
+        Ok(ExecutionReceipt::deserialize(&account.data)?)
+    }
+
+    /// Fetch what `consumer`'s fees on deployment `gsn` depend on, at the
+    /// current epoch. Price feeds that don't decode to a price are left out
+    /// of the prices, the program rejecting them too.
+    pub fn get_fee_state(&self, gsn: &Pubkey, consumer: &Pubkey) -> Result<FeeState> {
+        let state = self.get_state(gsn)?;
+        let config = self.get_config(&state.config)?;
+        let epoch = self.rpc.get_epoch_info()?.epoch;
+        let mut prices = HashMap::new();
+        for (mint, feed) in config.price_feeds.iter() {
+            let account = self.get_account(&feed.feed)?;
+            if let Some(price) = oracle::unpack_price(feed.kind, &account.data) {
+                prices.insert(*mint, price);
+            }
+        }
+        Ok(FeeState {
+            gsn: state,
+            config,
+            consumer: *consumer,
+            epoch,
+            prices,
+        })
+    }
+
+    /// Fee `consumer` would be charged for a submission of `amount`, paid in
+    /// `mint` or in lamports, see [`fee::estimate_fee`].
+    pub fn estimate_fee(
+        &self,
+        gsn: &Pubkey,
+        consumer: &Pubkey,
+        amount: u64,
+        mint: Option<&Pubkey>,
+    ) -> Result<FeeEstimate> {
+        fee::estimate_fee(&self.get_fee_state(gsn, consumer)?, amount, mint)
+    }
+
+    /// Top up `consumer`'s balance by `amount` lamports; the consumer signs
+    /// and pays for the transaction.
+    pub fn topup(&self, gsn: &Pubkey, consumer: &Keypair, amount: u64) -> Result<Signature> {
+        let config = self.get_state(gsn)?.config;
+        let topup = instruction::topup(&self.program_id, gsn, &config, &consumer.pubkey(), amount);
+        self.send(&[topup], consumer, &[consumer])
+    }
+
+    /// Top up `beneficiary`'s balance by `amount` lamports; `payer` signs and
+    /// pays for the top-up and the transaction.
+    pub fn topup_for(
+        &self,
+        gsn: &Pubkey,
+        payer: &Keypair,
+        beneficiary: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        let config = self.get_state(gsn)?.config;
+        let topup =
+            instruction::topup_for(&self.program_id, gsn, &config, &payer.pubkey(), beneficiary, amount);
+        self.send(&[topup], payer, &[payer])
+    }
+
+    /// Relay a transfer of `args.amount` lamports from `sender` to
+    /// `receiver`; `executor` pays for the transaction and earns the fee.
+    pub fn submit_transaction(
+        &self,
+        gsn: &Pubkey,
+        sender: &Keypair,
+        receiver: &Pubkey,
+        executor: &Keypair,
+        args: &SubmitArgs,
+    ) -> Result<Signature> {
+        let config = self.get_state(gsn)?.config;
+        let submit = instruction::submit_transaction(
+            &self.program_id,
+            gsn,
+            &config,
+            &sender.pubkey(),
+            receiver,
+            &executor.pubkey(),
+            args,
+        );
+        self.send(&[submit], executor, &[executor, sender])
+    }
+
+    /// Sign `instructions` with `signers`, `payer` paying, and send them.
+    pub fn send(
+        &self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            signers,
+            blockhash,
+        );
+        self.send_transaction(&transaction)
+    }
+
+    /// Send a signed `transaction` and wait for its confirmation. Errors
+    /// returned by the program come back as `ClientError::Program`.
+    pub fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        self.rpc
+            .send_and_confirm_transaction(transaction)
+            .map_err(|error| {
+                let program_error = error.get_transaction_error().and_then(|transaction_error| {
+                    gsn_error(&transaction_error, &self.program_id, &transaction.message)
+                });
+                match program_error {
+                    Some(program_error) => ClientError::Program(program_error),
+                    None => error.into(),
+                }
+            })
+    }
+
+    fn get_account(&self, key: &Pubkey) -> Result<Account> {
+        self.rpc
+            .get_account_with_commitment(key, self.rpc.commitment())?
+            .value
+            .ok_or(ClientError::AccountNotFound(*key))
+    }
+}
+
+/// Decode the GSN state held by `account`, which `program_id` must own.
+pub fn decode_state(program_id: &Pubkey, account: &Account) -> Result<GsnInfo> {
+    check_owner(program_id, account)?;
+    Ok(GsnInfo::deserialize(&account.data)?)
+}
+
+fn check_owner(program_id: &Pubkey, account: &Account) -> Result<()> {
+    if account.owner != *program_id {
+        return Err(GsnError::InvalidAccountOwner.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use num_traits::FromPrimitive;
+use solana_client::client_error::ClientError as RpcError;
+use solana_sdk::{
+    instruction::InstructionError,
+    message::Message,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    transaction::TransactionError,
+};
+use solgsn::GsnError;
+use thiserror::Error;
+
+/// Errors that may be returned by the client.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Program error: {0}")]
+    Program(#[from] GsnError),
+    /// Boxed, the RPC error is much larger than the other variants
+    #[error("RPC error: {0}")]
+    Rpc(Box<RpcError>),
+    #[error("Account not found: {0}")]
+    AccountNotFound(Pubkey),
+    #[error("Invalid account data: {0}")]
+    InvalidAccountData(#[from] ProgramError),
+}
+
+impl From<RpcError> for ClientError {
+    fn from(error: RpcError) -> Self {
+        Self::Rpc(Box::new(error))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// The `GsnError` a transaction of `message` failed with, if one of
+/// `program_id`'s instructions returned it. Custom codes of other programs
+/// are left alone.
+pub fn gsn_error(
+    error: &TransactionError,
+    program_id: &Pubkey,
+    message: &Message,
+) -> Option<GsnError> {
+    match error {
+        TransactionError::InstructionError(index, InstructionError::Custom(code))
+            if message.program_id(*index as usize) == Some(program_id) =>
+        {
+            GsnError::from_u32(*code)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,42 @@
+//! Fee estimates computed from fetched accounts.
+//!
+//! Estimates go through `solgsn::query::estimate_fee`, the rules the program
+//! charges submissions by, so fee modes, discounts and subsidies added to the
+//! program reach the client without a second implementation to keep in sync.
+//! Token fees of mints with a price feed are converted at the price fetched
+//! with the state, as the program converts them at the feed it is passed.
+
+use crate::error::Result;
+use solana_sdk::pubkey::Pubkey;
+use solgsn::{oracle::OraclePrice, query, state::GsnConfig, GsnInfo};
+use std::collections::HashMap;
+
+pub use solgsn::query::FeeEstimate;
+
+/// Everything a consumer's fees depend on.
+pub struct FeeState {
+    pub gsn: GsnInfo,
+    pub config: GsnConfig,
+    pub consumer: Pubkey,
+    /// Epoch volume discounts count the consumer's submissions in
+    pub epoch: u64,
+    /// Oracle prices of the mints with a price feed, by mint
+    pub prices: HashMap<Pubkey, OraclePrice>,
+}
+
+/// Fee of a submission of `amount` for `state.consumer`, paid in `mint` or in
+/// lamports when `None`, before tips. Fails with `TokenNotAllowed` for a mint
+/// that can't pay fees, and with `InvalidPriceFeed` for a mint whose feed has
+/// no price in `state.prices`.
+pub fn estimate_fee(state: &FeeState, amount: u64, mint: Option<&Pubkey>) -> Result<FeeEstimate> {
+    let estimate = query::estimate_fee(
+        &state.gsn,
+        &state.config,
+        &state.consumer,
+        amount,
+        mint,
+        mint.and_then(|mint| state.prices.get(mint)),
+        state.epoch,
+    )?;
+    Ok(estimate)
+}
@@ -0,0 +1,7 @@
+pub mod client;
+pub mod error;
+pub mod fee;
+
+pub use client::GsnClient;
+pub use error::ClientError;
+pub use fee::{estimate_fee, FeeEstimate, FeeState};
@@ -0,0 +1,54 @@
+// Tests for decoding accounts and program errors in the SolGSN client
+
+use solana_sdk::{
+    account::Account,
+    instruction::{Instruction, InstructionError},
+    message::Message,
+    pubkey::Pubkey,
+    transaction::TransactionError,
+};
+use solgsn::{GsnError, GsnInfo};
+use solgsn_client::{client::decode_state, error::gsn_error, ClientError};
+
+fn failed(index: u8, code: u32) -> TransactionError {
+    TransactionError::InstructionError(index, InstructionError::Custom(code))
+}
+
+#[test]
+fn test_custom_codes_map_to_gsn_errors() {
+    let program_id = Pubkey::new_unique();
+    let message = Message::new(
+        &[
+            Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]),
+            Instruction::new_with_bytes(program_id, &[], vec![]),
+        ],
+        Some(&Pubkey::new_unique()),
+    );
+    let code = GsnError::InsufficientBalance as u32;
+    assert_eq!(
+        gsn_error(&failed(1, code), &program_id, &message),
+        Some(GsnError::InsufficientBalance)
+    );
+    // The same code from another program isn't SolGSN's
+    assert_eq!(gsn_error(&failed(0, code), &program_id, &message), None);
+    assert_eq!(gsn_error(&failed(1, u32::MAX), &program_id, &message), None);
+}
+
+#[test]
+fn test_decode_state() {
+    let program_id = Pubkey::new_unique();
+    let consumer = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, 1_000);
+    let mut account = Account::new(1, 1024, &program_id);
+    gsn.serialize(&mut account.data).unwrap();
+
+    let decoded = decode_state(&program_id, &account).unwrap();
+    assert_eq!(decoded.consumer.get(&consumer), Some(&1_000));
+
+    account.owner = Pubkey::new_unique();
+    assert!(matches!(
+        decode_state(&program_id, &account),
+        Err(ClientError::Program(GsnError::InvalidAccountOwner))
+    ));
+}
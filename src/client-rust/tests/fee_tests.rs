@@ -0,0 +1,331 @@
+// Golden tests holding client fee estimates to what the program charges
+
+use solana_sdk::{
+    account_info::AccountInfo,
+    clock::Clock,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::SubmitTokenFeeArgs,
+    oracle,
+    state::{
+        FeeMode, FreeTier, GsnConfig, GsnInfo, OracleKind, PriceFeed, SandboxConfig, VolumeDiscountTier,
+    },
+    Processor,
+};
+use solgsn_client::{estimate_fee, ClientError, FeeEstimate, FeeState};
+use std::collections::HashMap;
+
+/// Epoch of the stubbed clock
+const EPOCH: u64 = 7;
+
+struct ClockStubs;
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            epoch: EPOCH,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+}
+
+/// Pyth v2 price account publishing `price * 10^expo`
+fn price_account(price: i64, conf: u64, expo: i32) -> Vec<u8> {
+    let mut data = vec![0u8; 3312];
+    data[..4].copy_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    data[4..8].copy_from_slice(&2u32.to_le_bytes());
+    data[8..12].copy_from_slice(&3u32.to_le_bytes());
+    data[20..24].copy_from_slice(&expo.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[216..224].copy_from_slice(&conf.to_le_bytes());
+    // Trading
+    data[224..228].copy_from_slice(&1u32.to_le_bytes());
+    data
+}
+
+/// A deployment and a consumer with a lamport and a token balance
+struct Scenario {
+    gsn: Vec<u8>,
+    config: Vec<u8>,
+    consumer: Pubkey,
+    mint: Pubkey,
+    subsidy: Pubkey,
+    /// Price account of the mint's feed, if the config has one
+    feed: Pubkey,
+    feed_data: Vec<u8>,
+}
+
+impl Scenario {
+    fn new(setup: impl FnOnce(&mut GsnInfo, &mut GsnConfig, &Scenario)) -> Self {
+        let mut scenario = Self {
+            gsn: vec![0u8; 2048],
+            config: vec![0u8; 1024],
+            consumer: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            subsidy: Pubkey::new_unique(),
+            feed: Pubkey::new_unique(),
+            feed_data: vec![],
+        };
+        let mut gsn = GsnInfo::new();
+        gsn.config = Pubkey::new_unique();
+        gsn.add_consumer(scenario.consumer, 1_000_000);
+        gsn.token_consumer
+            .insert((scenario.consumer, scenario.mint), 1_000_000);
+        let mut config = GsnConfig::new(Pubkey::new_unique());
+        config.initialize_governance(Pubkey::new_unique());
+        setup(&mut gsn, &mut config, &scenario);
+        gsn.serialize(&mut scenario.gsn).unwrap();
+        config.serialize(&mut scenario.config).unwrap();
+        scenario
+    }
+
+    /// Publish `data` as the price account of the mint's feed
+    fn with_feed_data(mut self, data: Vec<u8>) -> Self {
+        self.feed_data = data;
+        self
+    }
+
+    fn estimate(&self, amount: u64, mint: Option<&Pubkey>) -> Result<FeeEstimate, ClientError> {
+        let config = GsnConfig::deserialize(&self.config).unwrap();
+        let prices: HashMap<Pubkey, _> = config
+            .price_feeds
+            .iter()
+            .filter_map(|(mint, feed)| Some((*mint, oracle::unpack_price(feed.kind, &self.feed_data)?)))
+            .collect();
+        let state = FeeState {
+            gsn: GsnInfo::deserialize(&self.gsn).unwrap(),
+            config,
+            consumer: self.consumer,
+            epoch: EPOCH,
+            prices,
+        };
+        estimate_fee(&state, amount, mint)
+    }
+
+    /// Submit `amount` with the program and read the fee it charged off the
+    /// balances it changed. A fee of 0 reads as charged to nobody.
+    fn charge(&self, amount: u64, mint: Option<&Pubkey>) -> Result<FeeEstimate, ProgramError> {
+        set_syscall_stubs(Box::new(ClockStubs));
+        let program_id = Pubkey::new_unique();
+        let config_key = GsnInfo::deserialize(&self.gsn).unwrap().config;
+        // target, sender, receiver, fee payer, GSN state, config, price feed
+        let keys = [
+            Pubkey::new_unique(),
+            self.consumer,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            config_key,
+            self.feed,
+        ];
+        let mut data = [
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            self.gsn.clone(),
+            self.config.clone(),
+            self.feed_data.clone(),
+        ];
+        let mut lamports = [1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000, 1];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, index < 6, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        match mint {
+            Some(mint) => {
+                let args = SubmitTokenFeeArgs {
+                    amount,
+                    nonce: 0,
+                    lane: 0,
+                    mint: mint.to_bytes(),
+                };
+                Processor::process_submit_token_fee(&program_id, args, &accounts)?
+            }
+            None => Processor::process_submit_tx(&program_id, amount, 0, 0, None, None, 0, None, &accounts)?,
+        }
+        drop(accounts);
+
+        let (before, after) = (
+            GsnInfo::deserialize(&self.gsn).unwrap(),
+            GsnInfo::deserialize(&data[4]).unwrap(),
+        );
+        let balance = |gsn: &GsnInfo, key: &Pubkey| match mint {
+            Some(mint) => gsn.token_consumer.get(&(*key, *mint)).copied().unwrap_or(0),
+            None => gsn.consumer.get(key).copied().unwrap_or(0),
+        };
+        let charged = [self.consumer, self.subsidy]
+            .iter()
+            .copied()
+            .find(|key| balance(&after, key) < balance(&before, key));
+        let earned = match mint {
+            Some(mint) => after.token_executor.get(&(keys[3], *mint)).copied(),
+            None => after.executor.get(&keys[3]).copied(),
+        };
+        Ok(FeeEstimate {
+            fee: charged.map_or(0, |key| balance(&before, &key) - balance(&after, &key)),
+            charged,
+            executor_fee: earned.unwrap_or(0),
+        })
+    }
+
+    /// Estimates of a submission of each amount match what the program charges
+    fn assert_parity(&self, amounts: &[u64], mint: Option<&Pubkey>) {
+        for &amount in amounts {
+            let estimate = self.estimate(amount, mint).unwrap();
+            assert_eq!(self.charge(amount, mint), Ok(estimate), "amount {}", amount);
+        }
+    }
+}
+
+const AMOUNTS: [u64; 4] = [1, 1_000, 250_000, 9_000_000];
+
+fn set_fee_mode(config: &mut GsnConfig, fee_mode: FeeMode) {
+    config.governance.as_mut().unwrap().fee_mode = fee_mode;
+}
+
+#[test]
+fn test_fee_modes_match_the_program() {
+    Scenario::new(|_, _, _| {}).assert_parity(&AMOUNTS, None);
+    Scenario::new(|_, config, _| {
+        set_fee_mode(config, FeeMode::Fixed(7_000));
+        config.protocol_fee_bps = 250;
+    })
+    .assert_parity(&AMOUNTS, None);
+    Scenario::new(|_, config, _| {
+        set_fee_mode(
+            config,
+            FeeMode::Percent {
+                basis_points: 150,
+                min_fee: Some(2_000),
+                max_fee: Some(90_000),
+            },
+        );
+        config.protocol_fee_bps = 1_000;
+    })
+    .assert_parity(&AMOUNTS, None);
+    Scenario::new(|_, config, _| {
+        set_fee_mode(
+            config,
+            FeeMode::ComputeBased {
+                price_per_cu: 3,
+                base: 1_000,
+                max_compute_units: 40_000,
+            },
+        );
+    })
+    .assert_parity(&AMOUNTS, None);
+}
+
+#[test]
+fn test_discounts_and_subsidies_match_the_program() {
+    // Volume discounts count the consumer's submissions in the epoch
+    Scenario::new(|gsn, config, scenario| {
+        set_fee_mode(config, FeeMode::Fixed(10_000));
+        config.volume_discounts = vec![
+            VolumeDiscountTier {
+                min_txs: 2,
+                discount_bps: 1_000,
+            },
+            VolumeDiscountTier {
+                min_txs: 3,
+                discount_bps: 2_500,
+            },
+        ];
+        for _ in 0..3 {
+            gsn.record_epoch_tx(&scenario.consumer, EPOCH);
+        }
+    })
+    .assert_parity(&AMOUNTS, None);
+
+    // The free tier pays while its subsidy covers the fee
+    for subsidy_balance in [1_000_000, 10] {
+        Scenario::new(|gsn, config, scenario| {
+            gsn.add_consumer(scenario.subsidy, subsidy_balance);
+            config.free_tier = Some(FreeTier {
+                free_tx_quota: 1,
+                subsidy: scenario.subsidy,
+            });
+        })
+        .assert_parity(&AMOUNTS, None);
+    }
+
+    // Sandbox mode charges its subsidy, or waives the fee
+    for funded in [true, false] {
+        Scenario::new(|gsn, config, scenario| {
+            gsn.add_consumer(scenario.subsidy, 1_000_000);
+            config.sandbox = Some(SandboxConfig {
+                subsidy: Some(scenario.subsidy).filter(|_| funded),
+                reset_epoch: 0,
+            });
+        })
+        .assert_parity(&AMOUNTS, None);
+    }
+}
+
+#[test]
+fn test_token_fees_match_the_program() {
+    let scenario = Scenario::new(|_, config, _| {
+        set_fee_mode(config, FeeMode::Fixed(5_000));
+        config.protocol_fee_bps = 1_000;
+    });
+    scenario.assert_parity(&AMOUNTS, Some(&scenario.mint));
+
+    // Mints off the allowlist are rejected by both
+    let scenario = Scenario::new(|_, config, _| {
+        let governance = config.governance.as_mut().unwrap();
+        governance.allowed_tokens.insert(Pubkey::new_unique(), true);
+    });
+    assert!(matches!(
+        scenario.estimate(1_000, Some(&scenario.mint)),
+        Err(ClientError::Program(GsnError::TokenNotAllowed))
+    ));
+    assert_eq!(
+        scenario.charge(1_000, Some(&scenario.mint)),
+        Err(GsnError::TokenNotAllowed.into())
+    );
+}
+
+#[test]
+fn test_oracle_priced_token_fees_match_the_program() {
+    // 0.005 SOL per token of 6 decimals
+    let scenario = Scenario::new(|_, config, scenario| {
+        set_fee_mode(config, FeeMode::Fixed(50_000));
+        config.price_feeds.insert(
+            scenario.mint,
+            PriceFeed {
+                feed: scenario.feed,
+                decimals: 6,
+                max_age_slots: 25,
+                max_confidence_bps: 100,
+                kind: OracleKind::Pyth,
+            },
+        );
+    })
+    .with_feed_data(price_account(500_000, 400, -8));
+    scenario.assert_parity(&AMOUNTS, Some(&scenario.mint));
+    assert_eq!(scenario.estimate(1_000, Some(&scenario.mint)).unwrap().fee, 10_000);
+
+    // A feed without a price fails both
+    let scenario = scenario.with_feed_data(vec![0u8; 3312]);
+    assert!(matches!(
+        scenario.estimate(1_000, Some(&scenario.mint)),
+        Err(ClientError::Program(GsnError::InvalidPriceFeed))
+    ));
+    assert_eq!(
+        scenario.charge(1_000, Some(&scenario.mint)),
+        Err(GsnError::InvalidPriceFeed.into())
+    );
+}
@@ -4,7 +4,7 @@ import {
     Account,
     Connection,
     BpfLoader,
-    BPF_LOADER_DEPRECATED_PROGRAM_ID,
+    BPF_LOADER_PROGRAM_ID,
     PublicKey,
     LAMPORTS_PER_SOL,
     SystemProgram,
@@ -18,9 +18,21 @@ import assert from 'assert';
 
 import { url, urlTls } from '../../url';
 import { Store } from './util/store';
+import { currentDeployment } from './util/deployments';
 import { newAccountWithLamports } from './util/new-account-with-lamports';
 import { sendAndConfirmTransaction } from './util/send-and-confirm-transaction';
+import {
+    buildTransaction,
+    deserializePayload,
+    serializePayload,
+    signPayload,
+    simulateTransaction,
+} from './util/versioned-transaction';
+import type { BuildOptions } from './util/versioned-transaction';
 import { deserialize } from './util/borsh';
+import { findVaultAddress } from './util/gsn-state';
+import { newRequestId, requestIdMemo, traced, SpanNames } from './util/tracing';
+import { LifecycleTracker, TxStatus } from './util/lifecycle';
 
 /**
  * Connection to the network
@@ -43,6 +55,11 @@ let programAccount: Account;
  */
 let gsnAccount: Account;
 
+/**
+ * GSN config account (governance, fee mode, metrics)
+ */
+let configAccount: Account;
+
 /**
  * Sender Account
  */
@@ -65,7 +82,7 @@ const topupLayout = BufferLayout.struct([
  * Establish a connection to the cluster
  */
 export async function establishConnection(): Promise<void> {
-    connection = new Connection(url, 'recent');
+    connection = new Connection(url, currentDeployment().limits.commitment);
     const version = await connection.getVersion();
     console.log('Connection to cluster established:', url, version);
 }
@@ -104,6 +121,14 @@ export async function establishPayer(): Promise<void> {
 export async function loadProgram(): Promise<void> {
     const store = new Store();
 
+    // Use the cluster's published deployment when there is one
+    const { programId: deployedProgramId } = currentDeployment();
+    if (deployedProgramId && (await connection.getAccountInfo(deployedProgramId))) {
+        programId = deployedProgramId;
+        console.log('Using deployed program', programId.toBase58());
+        return;
+    }
+
     // Check if the program has already been loaded
     try {
         let config = await store.load('config.json');
@@ -125,7 +150,7 @@ export async function loadProgram(): Promise<void> {
         payerAccount,
         programAccount,
         data,
-        BPF_LOADER_DEPRECATED_PROGRAM_ID,
+        BPF_LOADER_PROGRAM_ID,
     );
 
     programId = programAccount.publicKey;
@@ -143,6 +168,7 @@ export async function loadProgram(): Promise<void> {
  */
 export async function initialize(): Promise<void> {
     const newProgram = new Account();
+    const newConfig = new Account();
 
     const tx = new Transaction().add(
         SystemProgram.createAccount({
@@ -152,6 +178,13 @@ export async function initialize(): Promise<void> {
             space: 1024,
             programId,
         }),
+        SystemProgram.createAccount({
+            fromPubkey: payerAccount.publicKey,
+            newAccountPubkey: newConfig.publicKey,
+            lamports: 1000000,
+            space: 1024,
+            programId,
+        }),
     );
 
     await sendAndConfirmTransaction(
@@ -160,12 +193,14 @@ export async function initialize(): Promise<void> {
         tx,
         payerAccount,
         newProgram,
+        newConfig,
     );
 
     console.log('\nInitializing program...');
 
     const keys = [
         { pubkey: newProgram.publicKey, isSigner: false, isWritable: true },
+        { pubkey: newConfig.publicKey, isSigner: false, isWritable: true },
     ];
 
     const initLayout = BufferLayout.struct([BufferLayout.u8('instruction')]);
@@ -194,20 +229,27 @@ export async function initialize(): Promise<void> {
     );
 
     gsnAccount = newProgram;
+    configAccount = newConfig;
 
     console.log('Program succesfully initialized.\n');
 }
 
 /**
- * Topup Account with parameters
+ * Topup Account with parameters: moves `amount` lamports from the consumer,
+ * which signs, into the deployment's vault. With a `beneficiary`, the
+ * consumer pays and the beneficiary's balance is credited.
  */
 export async function topupWithParams(
     connection: Connection,
     gsnAccount: Account,
+    configPubkey: PublicKey,
     consumerAccount: Account,
     amount: u64,
     payerAccount: Account,
     programIdParam?: PublicKey,
+    // Consumer's credential token account, required in compliance mode
+    credentialPubkey?: ?PublicKey,
+    beneficiary?: ?PublicKey,
 ): Promise<void> {
     const pid = programIdParam || programId;
     if (!pid) {
@@ -216,18 +258,31 @@ export async function topupWithParams(
 
     const keys = [
         { pubkey: gsnAccount.publicKey, isSigner: false, isWritable: true },
-        { pubkey: consumerAccount.publicKey, isSigner: false, isWritable: false },
+        { pubkey: consumerAccount.publicKey, isSigner: true, isWritable: true },
+        { pubkey: configPubkey, isSigner: false, isWritable: false },
+        {
+            pubkey: await findVaultAddress(pid, gsnAccount.publicKey),
+            isSigner: false,
+            isWritable: true,
+        },
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ];
+    if (credentialPubkey) {
+        keys.push({ pubkey: credentialPubkey, isSigner: false, isWritable: false });
+    }
 
-    const data = Buffer.alloc(topupLayout.span);
+    const encoded = Buffer.alloc(topupLayout.span);
 
     topupLayout.encode(
         {
             instruction: 1,
             amount: amount.toBuffer(),
         },
-        data,
+        encoded,
     );
+    const data = beneficiary
+        ? Buffer.concat([encoded, beneficiary.toBuffer()])
+        : encoded;
 
     const instruction = new TransactionInstruction({
         keys,
@@ -240,6 +295,7 @@ export async function topupWithParams(
         connection,
         new Transaction().add(instruction),
         payerAccount,
+        consumerAccount,
     );
 }
 
@@ -251,7 +307,14 @@ export async function topup(): Promise<void> {
 
     const keys = [
         { pubkey: gsnAccount.publicKey, isSigner: false, isWritable: true },
-        { pubkey: senderAccount.publicKey, isSigner: false, isWritable: true },
+        { pubkey: senderAccount.publicKey, isSigner: true, isWritable: true },
+        { pubkey: configAccount.publicKey, isSigner: false, isWritable: false },
+        {
+            pubkey: await findVaultAddress(programId, gsnAccount.publicKey),
+            isSigner: false,
+            isWritable: true,
+        },
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ];
 
     const data = Buffer.alloc(topupLayout.span);
@@ -286,6 +349,7 @@ export async function topup(): Promise<void> {
             connection,
             new Transaction().add(instruction),
             payerAccount,
+            senderAccount,
         );
         
         const newBalance = await connection.getBalance(senderAccount.publicKey).catch(() => 0);
@@ -320,6 +384,7 @@ export async function submitTx(): Promise<void> {
         { pubkey: recieverAccount.publicKey, isSigner: false, isWritable: true },
         { pubkey: feePayerAccount.publicKey, isSigner: false, isWritable: true },
         { pubkey: gsnAccount.publicKey, isSigner: false, isWritable: true },
+        { pubkey: configAccount.publicKey, isSigner: false, isWritable: false },
     ];
 
     const submitLayout = BufferLayout.struct([
@@ -443,80 +508,296 @@ export async function submitTx(): Promise<void> {
 }
 
 /**
- * Submit Transaction with parameters
+ * SubmitTransaction (or SubmitTransactionInLane) instruction followed by the
+ * request id memo.
  */
-export async function submitTxWithParams(
-    connection: Connection,
+function submitInstructions(
     targetProgram: PublicKey,
-    senderAccount: Account,
-    receiverAccount: Account,
-    feePayerAccount: Account,
-    gsnAccount: Account,
+    senderPubkey: PublicKey,
+    receiverPubkey: PublicKey,
+    feePayerPubkey: PublicKey,
+    gsnPubkey: PublicKey,
+    configPubkey: PublicKey,
     amount: u64,
     nonce: u64,
-    programIdParam: PublicKey,
-): Promise<string> {
-    const pid = programIdParam;
+    pid: PublicKey,
+    requestId: string,
+    lane: number | string,
+    expirySlot?: u64,
+    maxFee?: u64,
+    tip?: u64,
+): Array<TransactionInstruction> {
     const keys = [
         { pubkey: targetProgram, isSigner: false, isWritable: true },
-        { pubkey: senderAccount.publicKey, isSigner: true, isWritable: true },
-        { pubkey: receiverAccount.publicKey, isSigner: false, isWritable: true },
-        { pubkey: feePayerAccount.publicKey, isSigner: true, isWritable: true },
-        { pubkey: gsnAccount.publicKey, isSigner: false, isWritable: true },
+        { pubkey: senderPubkey, isSigner: true, isWritable: true },
+        { pubkey: receiverPubkey, isSigner: false, isWritable: true },
+        { pubkey: feePayerPubkey, isSigner: true, isWritable: true },
+        { pubkey: gsnPubkey, isSigner: false, isWritable: true },
+        // Read-only: submissions sharing the config don't lock each other out of it
+        { pubkey: configPubkey, isSigner: false, isWritable: false },
     ];
 
+    // Lane 0 keeps the original SubmitTransaction encoding, other lanes add
+    // the lane. The expiry slot trails it, then the maximum fee with an expiry
+    // slot of 0 standing for none, then the tip with a maximum fee of u64::MAX
+    // standing for none
     const submitLayout = BufferLayout.struct([
         BufferLayout.u8('instruction'),
         uint64('amount'),
         uint64('nonce'),
+        ...(lane === 0 ? [] : [uint64('lane')]),
+        ...(expirySlot || maxFee || tip ? [uint64('expirySlot')] : []),
+        ...(maxFee || tip ? [uint64('maxFee')] : []),
+        ...(tip ? [uint64('tip')] : []),
     ]);
 
     const data = Buffer.alloc(submitLayout.span);
 
     submitLayout.encode(
         {
-            instruction: 2,
+            instruction: lane === 0 ? 2 : 8,
             amount: amount.toBuffer(),
             nonce: nonce.toBuffer(),
+            lane: new u64(lane).toBuffer(),
+            expirySlot: (expirySlot || new u64(0)).toBuffer(),
+            maxFee: (maxFee || new u64('ffffffffffffffff', 16)).toBuffer(),
+            tip: tip && tip.toBuffer(),
         },
         data,
     );
 
-    const instruction = new TransactionInstruction({
-        keys,
-        programId,
-        data,
+    return [
+        new TransactionInstruction({
+            keys,
+            programId: pid,
+            data,
+        }),
+        requestIdMemo(requestId),
+    ];
+}
+
+/**
+ * Submit Transaction with parameters
+ *
+ * `configPubkey` is the config account created with the GSN account; it is
+ * passed read-only.
+ *
+ * The whole relay is traced under a `solgsn.relay` span. `requestId` (generated
+ * when omitted) is attached to the spans and echoed on-chain in a memo so the
+ * transaction can be matched with relayer and client traces.
+ *
+ * When a `tracker` is given the transaction is recorded as queued, then
+ * confirmed or failed, so its status can be queried by (consumer, nonce).
+ *
+ * `lane` selects one of the consumer's independent nonce lanes; transactions
+ * in different lanes may be relayed concurrently. Lane 0 is the default.
+ * Tracker records of other lanes are keyed by `${lane}:${nonce}`. In
+ * `UNORDERED_NONCE_LANE` nonces may be used in any order, see
+ * `freeUnorderedNonces`. In `CHANNEL_NONCE_LANE` they count up per fee
+ * payer, see `getChannelNonce`.
+ *
+ * `expirySlot` is the last slot the transaction may execute in, so a delayed
+ * or leaked payload fails with `SubmissionExpired` rather than executing
+ * late.
+ *
+ * `maxFee` is the highest fee in lamports the consumer agrees to pay, so a
+ * fee raised between signing and relaying fails with `FeeTooHigh` rather
+ * than overcharging the consumer.
+ *
+ * `tip` is paid to the executor in lamports on top of the fee, out of the
+ * consumer's balance, so relayers can prioritize tipped transactions. It
+ * doesn't count towards `maxFee`.
+ */
+export async function submitTxWithParams(
+    connection: Connection,
+    targetProgram: PublicKey,
+    senderAccount: Account,
+    receiverAccount: Account,
+    feePayerAccount: Account,
+    gsnAccount: Account,
+    configPubkey: PublicKey,
+    amount: u64,
+    nonce: u64,
+    programIdParam: PublicKey,
+    requestId?: string,
+    tracker?: LifecycleTracker,
+    lane: number | string = 0,
+    expirySlot?: u64,
+    maxFee?: u64,
+    tip?: u64,
+): Promise<string> {
+    const relayRequestId = requestId || newRequestId();
+    const consumer = senderAccount.publicKey.toBase58();
+    const trackedNonce = lane === 0 ? nonce : `${lane}:${nonce.toString()}`;
+    const attributes = {
+        'solgsn.request_id': relayRequestId,
+        'solgsn.consumer': consumer,
+        'solgsn.executor': feePayerAccount.publicKey.toBase58(),
+        'solgsn.amount': amount.toString(),
+        'solgsn.nonce': nonce.toString(),
+        'solgsn.lane': lane,
+    };
+
+    return traced(SpanNames.RELAY, attributes, async span => {
+        const trans = new Transaction({
+            feePayer: feePayerAccount.publicKey,
+        }).add(
+            ...submitInstructions(
+                targetProgram,
+                senderAccount.publicKey,
+                receiverAccount.publicKey,
+                feePayerAccount.publicKey,
+                gsnAccount.publicKey,
+                configPubkey,
+                amount,
+                nonce,
+                programIdParam,
+                relayRequestId,
+                lane,
+                expirySlot,
+                maxFee,
+                tip,
+            ),
+        );
+
+        if (tracker) {
+            await tracker.transition(consumer, trackedNonce, TxStatus.QUEUED, {
+                requestId: relayRequestId,
+                executor: feePayerAccount.publicKey.toBase58(),
+            });
+        }
+
+        let signature;
+        try {
+            signature = await sendAndConfirmTransaction(
+                'submitTx',
+                connection,
+                trans,
+                senderAccount,
+                feePayerAccount,
+            );
+        } catch (error) {
+            if (tracker) {
+                await tracker.transition(consumer, trackedNonce, TxStatus.FAILED, {
+                    reason: error.message || String(error),
+                });
+            }
+            throw error;
+        }
+
+        if (tracker) {
+            await tracker.transition(consumer, trackedNonce, TxStatus.CONFIRMED, {
+                signature,
+            });
+        }
+
+        span.setAttribute('solgsn.signature', signature);
+        return signature;
     });
+}
 
-    const trans = new Transaction({
-        feePayer: feePayerAccount.publicKey,
-    }).add(instruction);
+/**
+ * Build a submission payload signed by the consumer only.
+ *
+ * The payload is a base64 v0 transaction (legacy when `version` is
+ * `'legacy'`) paid by `feePayerPubkey`; the executor countersigns and
+ * submits it with `relaySubmitPayload`. `lookupTables` are passed to the v0
+ * message compiler.
+ */
+export async function buildSubmitTxPayload(
+    connection: Connection,
+    targetProgram: PublicKey,
+    senderAccount: Account,
+    receiverPubkey: PublicKey,
+    feePayerPubkey: PublicKey,
+    gsnPubkey: PublicKey,
+    configPubkey: PublicKey,
+    amount: u64,
+    nonce: u64,
+    programIdParam: PublicKey,
+    requestId?: string,
+    lane: number = 0,
+    options: BuildOptions = {},
+): Promise<string> {
+    const { transaction } = await buildTransaction(
+        connection,
+        feePayerPubkey,
+        submitInstructions(
+            targetProgram,
+            senderAccount.publicKey,
+            receiverPubkey,
+            feePayerPubkey,
+            gsnPubkey,
+            configPubkey,
+            amount,
+            nonce,
+            programIdParam,
+            requestId || newRequestId(),
+            lane,
+        ),
+        options,
+    );
 
-    const signature = await sendAndConfirmTransaction(
+    return serializePayload(signPayload(transaction, senderAccount));
+}
+
+/**
+ * Countersign a payload from `buildSubmitTxPayload` as fee payer and submit it.
+ *
+ * The payload is simulated first (signatures are not verified, the consumer's
+ * is checked on submission) and rejected if simulation fails, so the executor
+ * doesn't pay for a transaction that can't succeed.
+ */
+export async function relaySubmitPayload(
+    connection: Connection,
+    payload: string,
+    feePayerAccount: Account,
+): Promise<string> {
+    const transaction = deserializePayload(payload);
+
+    const simulation = await simulateTransaction('submitTx', connection, transaction);
+    if (simulation.err) {
+        throw new Error(
+            `Simulation failed: ${JSON.stringify(simulation.err)}\n` +
+                (simulation.logs || []).join('\n'),
+        );
+    }
+
+    return sendAndConfirmTransaction(
         'submitTx',
         connection,
-        trans,
-        senderAccount,
+        transaction,
         feePayerAccount,
     );
-
-    return signature;
 }
 
 /**
  * Claim Fees
+ *
+ * The fees go to the executor, or to `destination` when it is the
+ * executor's payout address, see `setPayoutAddressInstruction`.
  */
 export async function claimFees(
     connection: Connection,
     gsnAccount: Account,
     executorAccount: Account,
     programIdParam: PublicKey,
+    destination?: PublicKey,
 ): Promise<string> {
     const pid = programIdParam;
     const keys = [
         { pubkey: gsnAccount.publicKey, isSigner: false, isWritable: true },
         { pubkey: executorAccount.publicKey, isSigner: true, isWritable: true },
-        { pubkey: executorAccount.publicKey, isSigner: false, isWritable: true }, // destination (same as executor)
+        {
+            pubkey: destination || executorAccount.publicKey,
+            isSigner: false,
+            isWritable: true,
+        },
+        {
+            pubkey: await findVaultAddress(pid, gsnAccount.publicKey),
+            isSigner: false,
+            isWritable: true,
+        },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ];
 
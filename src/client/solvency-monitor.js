@@ -0,0 +1,75 @@
+/**
+ * Vault solvency watchdog
+ *
+ * Periodically checks that the GSN account holds enough lamports to cover all
+ * consumer balances and unclaimed executor fees, and raises an alert when the
+ * invariant breaks (and again once it recovers).
+ *
+ * Configuration (environment):
+ *   GSN_ACCOUNT            GSN state account to watch (required)
+ *   SOLVENCY_INTERVAL_MS   Delay between checks, defaults to 30000
+ *   SOLVENCY_WEBHOOK_URL   Optional URL receiving alerts as JSON POSTs
+ *
+ * @flow
+ */
+
+import {Connection, PublicKey} from '@solana/web3.js';
+
+import {url} from '../../url';
+import {sleep} from './util/sleep';
+import {
+    checkSolvency,
+    formatSolvencyReport,
+    sendSolvencyAlert,
+} from './util/solvency';
+
+async function alert(report) {
+    const details = JSON.stringify(formatSolvencyReport(report));
+    if (report.solvent) {
+        console.log(`[SOLVENCY_RECOVERED] ${details}`);
+    } else {
+        console.error(`[SOLVENCY_ALERT] ${details}`);
+    }
+
+    const webhookUrl = process.env.SOLVENCY_WEBHOOK_URL;
+    if (webhookUrl) {
+        try {
+            await sendSolvencyAlert(webhookUrl, report);
+        } catch (err) {
+            console.error('[SOLVENCY_MONITOR] webhook failed:', err.message);
+        }
+    }
+}
+
+async function main() {
+    if (!process.env.GSN_ACCOUNT) {
+        throw new Error('GSN_ACCOUNT is not set');
+    }
+    const gsnAccountPubkey = new PublicKey(process.env.GSN_ACCOUNT);
+    const interval = parseInt(process.env.SOLVENCY_INTERVAL_MS || '30000', 10);
+    const connection = new Connection(url, 'recent');
+    console.log('Watching vault solvency of', gsnAccountPubkey.toBase58(), 'on', url);
+
+    let solvent = true;
+    for (;;) {
+        try {
+            const report = await checkSolvency(connection, gsnAccountPubkey);
+            console.log(
+                `[SOLVENCY_CHECK] solvent=${String(report.solvent)} surplus=${report.surplus.toString()}`,
+            );
+            // Alert on every state change, not on every check
+            if (report.solvent !== solvent) {
+                solvent = report.solvent;
+                await alert(report);
+            }
+        } catch (err) {
+            console.error('[SOLVENCY_MONITOR] check failed:', err.message);
+        }
+        await sleep(interval);
+    }
+}
+
+main().catch(err => {
+    console.error(err);
+    process.exit(1);
+});
@@ -0,0 +1,63 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const ADD_ALLOWED_PROGRAM = 31;
+const REMOVE_ALLOWED_PROGRAM = 32;
+
+function allowedProgramInstruction(
+  tag: number,
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  targetProgram: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([Buffer.from([tag]), targetProgram.toBuffer()]),
+  });
+}
+
+/**
+ * Encode an `AddAllowedProgram` approving `targetProgram` for submissions.
+ * Once the list has an entry, submissions to other programs fail with
+ * `ProgramNotAllowed`. The governance authority signs it.
+ */
+export function addAllowedProgramInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  targetProgram: PublicKey,
+): TransactionInstruction {
+  return allowedProgramInstruction(
+    ADD_ALLOWED_PROGRAM,
+    programId,
+    configAccount,
+    authority,
+    targetProgram,
+  );
+}
+
+/**
+ * Encode a `RemoveAllowedProgram`. Removing the last entry allows every
+ * program again.
+ */
+export function removeAllowedProgramInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  targetProgram: PublicKey,
+): TransactionInstruction {
+  return allowedProgramInstruction(
+    REMOVE_ALLOWED_PROGRAM,
+    programId,
+    configAccount,
+    authority,
+    targetProgram,
+  );
+}
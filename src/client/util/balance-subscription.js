@@ -0,0 +1,88 @@
+// @flow
+
+import {Connection, PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {decodeGsnState, getGsnState} from './gsn-state';
+
+/**
+ * Balance and nonce of a consumer as recorded in the GSN state
+ */
+export type ConsumerSnapshot = {
+  balance: BN,
+  nonce: number,
+};
+
+/**
+ * Change reported to `subscribeConsumer` callbacks
+ */
+export type ConsumerChange = {
+  consumer: string,
+  slot: number,
+  previous: ConsumerSnapshot,
+  current: ConsumerSnapshot,
+  balanceChanged: boolean,
+  nonceChanged: boolean,
+};
+
+function snapshot(gsnInfo: Object, consumerKey: string): ConsumerSnapshot {
+  const balance = gsnInfo.consumer.get(consumerKey);
+  const nonce = gsnInfo.consumer_nonces.get(consumerKey);
+  return {
+    balance: balance ? new BN(balance.toString()) : new BN(0),
+    nonce: nonce ? nonce.toNumber() : 0,
+  };
+}
+
+/**
+ * Watch a consumer's balance and nonce over the websocket account subscription.
+ *
+ * `onChange` is invoked whenever either value differs from the last one seen,
+ * e.g. after a top-up or a relayed transaction, so wallets can keep a
+ * "remaining gas credit" display current. Updates to other consumers are
+ * ignored. Returns a function that removes the subscription.
+ */
+export async function subscribeConsumer(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+  onChange: (change: ConsumerChange) => void,
+  onError: (error: Error) => void = error =>
+    console.warn('Failed to decode GSN state update:', error),
+): Promise<() => Promise<void>> {
+  const consumer = consumerPubkey.toBase58();
+  let last = snapshot(await getGsnState(connection, gsnAccountPubkey), consumer);
+
+  const subscriptionId = connection.onAccountChange(
+    gsnAccountPubkey,
+    (accountInfo, context) => {
+      let current;
+      try {
+        current = snapshot(decodeGsnState(accountInfo.data), consumer);
+      } catch (error) {
+        onError(error);
+        return;
+      }
+
+      const balanceChanged = !current.balance.eq(last.balance);
+      const nonceChanged = current.nonce !== last.nonce;
+      if (!balanceChanged && !nonceChanged) {
+        return;
+      }
+
+      const previous = last;
+      last = current;
+      onChange({
+        consumer,
+        slot: context.slot,
+        previous,
+        current,
+        balanceChanged,
+        nonceChanged,
+      });
+    },
+    'recent',
+  );
+
+  return () => connection.removeAccountChangeListener(subscriptionId);
+}
@@ -0,0 +1,270 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {
+  Account,
+  AddressLookupTableAccount,
+  Connection,
+  PublicKey,
+  VersionedTransaction,
+} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {currentDeployment} from './deployments';
+import {getGsnConfig} from './gsn-state';
+import {sendAndConfirmTransaction} from './send-and-confirm-transaction';
+import {buildTransaction, simulateTransaction} from './versioned-transaction';
+
+/**
+ * Largest batch accepted by the program (`MAX_BATCH_SIZE`)
+ */
+export const MAX_BATCH_SIZE = 8;
+
+/**
+ * Fee the program allows per signature of a batch (`LAMPORTS_PER_SIGNATURE`)
+ */
+const LAMPORTS_PER_SIGNATURE = 5000;
+
+const SUBMIT_BATCH = 9;
+
+/**
+ * A transfer a consumer asks the relayer to execute.
+ *
+ * The consumer doesn't sign a payload up front: the batch message is only
+ * known once the window closes, so `sign` is called with its bytes and must
+ * resolve to the consumer's ed25519 signature over them.
+ */
+export type RelayIntent = {
+  consumer: PublicKey,
+  receiver: PublicKey,
+  amount: BN | number,
+  nonce: BN | number,
+  lane?: number,
+  sign: (message: Uint8Array) => Promise<Uint8Array>,
+};
+
+export type BatchRelayerOptions = {
+  gsnPubkey: PublicKey,
+  configPubkey: PublicKey,
+  programId: PublicKey,
+  targetProgram: PublicKey,
+  windowMs?: number,
+  maxBatchSize?: number,
+  lookupTables?: Array<AddressLookupTableAccount>,
+};
+
+type Pending = {
+  intent: RelayIntent,
+  resolve: string => void,
+  reject: Error => void,
+};
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Encode a `SubmitBatch` instruction; fee payer and consumers must sign
+ */
+export function submitBatchInstruction(
+  programId: PublicKey,
+  targetProgram: PublicKey,
+  feePayerPubkey: PublicKey,
+  gsnPubkey: PublicKey,
+  configPubkey: PublicKey,
+  metricsPubkey: ?PublicKey,
+  intents: Array<RelayIntent>,
+  networkFee: number,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: targetProgram, isSigner: false, isWritable: true},
+    {pubkey: feePayerPubkey, isSigner: true, isWritable: true},
+    {pubkey: gsnPubkey, isSigner: false, isWritable: true},
+    {pubkey: configPubkey, isSigner: false, isWritable: false},
+  ];
+  if (metricsPubkey) {
+    keys.push({pubkey: metricsPubkey, isSigner: false, isWritable: true});
+  }
+
+  const data = [
+    Buffer.from([SUBMIT_BATCH]),
+    u64Bytes(networkFee),
+    Buffer.from([intents.length]),
+  ];
+  for (const intent of intents) {
+    keys.push(
+      {pubkey: intent.consumer, isSigner: true, isWritable: true},
+      {pubkey: intent.receiver, isSigner: false, isWritable: true},
+    );
+    data.push(
+      u64Bytes(intent.amount),
+      u64Bytes(intent.nonce),
+      u64Bytes(intent.lane || 0),
+    );
+  }
+
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat(data),
+  });
+}
+
+/**
+ * Relayer mode that executes consumer transfers in `SubmitBatch` transactions.
+ *
+ * Intents submitted within `windowMs` of the first pending one are sent
+ * together, or as soon as `maxBatchSize` are pending. Both default to the
+ * limits of the current cluster's deployment preset. The network fee of the
+ * batch transaction is quoted before it is signed and split evenly across
+ * its consumers on top of their GSN fees, so the executor recovers it instead
+ * of paying one signature fee per relay.
+ *
+ * A relayer is bound to one GSN deployment and target program, which is what
+ * makes its intents compatible. Intents of the same consumer lane are batched
+ * in submission order. A batch executes atomically: if one intent fails, every
+ * intent of the batch is rejected with the error.
+ */
+export class BatchRelayer {
+  connection: Connection;
+  feePayerAccount: Account;
+  options: BatchRelayerOptions;
+  windowMs: number;
+  maxBatchSize: number;
+  pending: Array<Pending> = [];
+  timer: ?TimeoutID = null;
+  metricsPubkey: ?PublicKey = undefined;
+
+  constructor(
+    connection: Connection,
+    feePayerAccount: Account,
+    options: BatchRelayerOptions,
+  ) {
+    this.connection = connection;
+    this.feePayerAccount = feePayerAccount;
+    this.options = options;
+    const {limits} = currentDeployment();
+    this.windowMs =
+      options.windowMs === undefined ? limits.batchWindowMs : options.windowMs;
+    this.maxBatchSize = Math.min(
+      options.maxBatchSize || limits.maxBatchSize,
+      MAX_BATCH_SIZE,
+    );
+  }
+
+  /**
+   * Queue `intent`; resolves to the signature of the batch that executed it
+   */
+  submit(intent: RelayIntent): Promise<string> {
+    return new Promise((resolve, reject) => {
+      this.pending.push({intent, resolve, reject});
+      if (this.pending.length >= this.maxBatchSize) {
+        this.flush();
+      } else if (this.timer === null) {
+        this.timer = setTimeout(() => this.flush(), this.windowMs);
+      }
+    });
+  }
+
+  /**
+   * Send every pending intent now, in batches of at most `maxBatchSize`
+   */
+  async flush(): Promise<void> {
+    if (this.timer !== null) {
+      clearTimeout(this.timer);
+      this.timer = null;
+    }
+    const sends = [];
+    while (this.pending.length > 0) {
+      sends.push(this.send(this.pending.splice(0, this.maxBatchSize)));
+    }
+    await Promise.all(sends);
+  }
+
+  async send(batch: Array<Pending>): Promise<void> {
+    try {
+      const signature = await this.relay(batch.map(p => p.intent));
+      batch.forEach(p => p.resolve(signature));
+    } catch (error) {
+      batch.forEach(p => p.reject(error));
+    }
+  }
+
+  /**
+   * Metrics account the program expects after the config, fetched once
+   */
+  async metrics(): Promise<?PublicKey> {
+    if (this.metricsPubkey === undefined) {
+      const config = await getGsnConfig(
+        this.connection,
+        this.options.configPubkey,
+      );
+      this.metricsPubkey = config.metrics;
+    }
+    return this.metricsPubkey;
+  }
+
+  async build(
+    intents: Array<RelayIntent>,
+    networkFee: number,
+  ): Promise<VersionedTransaction> {
+    const {programId, targetProgram, gsnPubkey, configPubkey} = this.options;
+    const feePayerPubkey = this.feePayerAccount.publicKey;
+    const instruction = submitBatchInstruction(
+      programId,
+      targetProgram,
+      feePayerPubkey,
+      gsnPubkey,
+      configPubkey,
+      await this.metrics(),
+      intents,
+      networkFee,
+    );
+    const {transaction} = await buildTransaction(
+      this.connection,
+      feePayerPubkey,
+      [instruction],
+      {lookupTables: this.options.lookupTables},
+    );
+    return transaction;
+  }
+
+  async relay(intents: Array<RelayIntent>): Promise<string> {
+    // The fee depends on the signature count, not on the encoded network fee
+    const quote = await this.connection.getFeeForMessage(
+      (await this.build(intents, 0)).message,
+    );
+    const networkFee = Math.min(
+      quote.value || 0,
+      LAMPORTS_PER_SIGNATURE * (intents.length + 1),
+    );
+
+    const transaction = await this.build(intents, networkFee);
+    const message = transaction.message.serialize();
+    const signatures = await Promise.all(
+      intents.map(intent => intent.sign(message)),
+    );
+    intents.forEach((intent, i) =>
+      transaction.addSignature(intent.consumer, signatures[i]),
+    );
+
+    const simulation = await simulateTransaction(
+      'submitBatch',
+      this.connection,
+      transaction,
+    );
+    if (simulation.err) {
+      throw new Error(
+        `Simulation failed: ${JSON.stringify(simulation.err)}\n` +
+          (simulation.logs || []).join('\n'),
+      );
+    }
+
+    return sendAndConfirmTransaction(
+      'submitBatch',
+      this.connection,
+      transaction,
+      this.feePayerAccount,
+    );
+  }
+}
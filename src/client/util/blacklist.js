@@ -0,0 +1,68 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const BLACKLIST_ADDRESS = 73;
+const UNBLACKLIST_ADDRESS = 74;
+
+function blacklistInstruction(
+  tag: number,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  address: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([Buffer.from([tag]), address.toBuffer()]),
+  });
+}
+
+/**
+ * Encode a `BlacklistAddress`, barring `address` from topping up, submitting
+ * as consumer or executor, and claiming fees. The governance authority signs
+ * it.
+ */
+export function blacklistAddressInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  address: PublicKey,
+): TransactionInstruction {
+  return blacklistInstruction(
+    BLACKLIST_ADDRESS,
+    programId,
+    gsnAccount,
+    configAccount,
+    authority,
+    address,
+  );
+}
+
+/**
+ * Encode an `UnblacklistAddress`
+ */
+export function unblacklistAddressInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  address: PublicKey,
+): TransactionInstruction {
+  return blacklistInstruction(
+    UNBLACKLIST_ADDRESS,
+    programId,
+    gsnAccount,
+    configAccount,
+    authority,
+    address,
+  );
+}
@@ -0,0 +1,39 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const CANCEL_NONCE = 54;
+
+/**
+ * Encode a `CancelNonce` burning `nonce` of `consumer`'s nonce lane, so a
+ * submission signed for it can't be relayed anymore. In a counted lane the
+ * nonces before it are burnt too. Cancelling in `CHANNEL_NONCE_LANE` names
+ * the channel's `executor`. The consumer signs it.
+ */
+export function cancelNonceInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  nonce: BN | number,
+  lane: number | string = 0,
+  executor: ?PublicKey = null,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: consumer, isSigner: true, isWritable: false},
+  ];
+  if (executor) {
+    keys.push({pubkey: executor, isSigner: false, isWritable: false});
+  }
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([CANCEL_NONCE]),
+      new BN(nonce.toString()).toArrayLike(Buffer, 'le', 8),
+      new BN(lane.toString()).toArrayLike(Buffer, 'le', 8),
+    ]),
+  });
+}
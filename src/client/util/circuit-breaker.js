@@ -0,0 +1,68 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_CIRCUIT_BREAKER = 75;
+const REPORT_FAILURE = 81;
+
+/**
+ * Encode a `SetCircuitBreaker` pausing the deployment once more than
+ * `maxFailureBps` basis points of an epoch's executions failed, judged from
+ * `minExecutions` executions on. 0 basis points turns it off. The governance
+ * authority signs it.
+ */
+export function setCircuitBreakerInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  maxFailureBps: number,
+  minExecutions: BN | number,
+): TransactionInstruction {
+  const bps = Buffer.alloc(2);
+  bps.writeUInt16LE(maxFailureBps, 0);
+  const data = Buffer.concat([
+    Buffer.from([SET_CIRCUIT_BREAKER]),
+    bps,
+    new BN(minExecutions.toString()).toArrayLike(Buffer, 'le', 8),
+  ]);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
+
+/**
+ * Encode a `ReportFailure` counting the relay of `nonce` on `consumer`'s
+ * `lane`, which failed in the target program, towards the circuit breaker.
+ * The registered executor that sent it signs it.
+ */
+export function reportFailureInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  consumer: PublicKey,
+  lane: BN | number,
+  nonce: BN | number,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: executor, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([REPORT_FAILURE]),
+      consumer.toBuffer(),
+      new BN(lane.toString()).toArrayLike(Buffer, 'le', 8),
+      new BN(nonce.toString()).toArrayLike(Buffer, 'le', 8),
+    ]),
+  });
+}
@@ -0,0 +1,143 @@
+// @flow
+
+import {PublicKey, SystemProgram, TransactionInstruction} from '@solana/web3.js';
+import BN from 'bn.js';
+import {createHash, randomBytes} from 'crypto';
+
+const COMMIT_SUBMISSION = 13;
+const REVEAL_SUBMISSION = 14;
+
+/**
+ * Slots between a commitment and its reveal (`REVEAL_DELAY_SLOTS`)
+ */
+export const REVEAL_DELAY_SLOTS = 2;
+
+/**
+ * Slots after which a commitment can't be revealed (`COMMITMENT_EXPIRY_SLOTS`)
+ */
+export const COMMITMENT_EXPIRY_SLOTS = 150;
+
+/**
+ * A submission an executor commits to before revealing it
+ */
+export type CommittedSubmission = {
+  targetProgram: PublicKey,
+  sender: PublicKey,
+  receiver: PublicKey,
+  amount: BN | number,
+  nonce: BN | number,
+  lane: number,
+  // 32 random bytes, from `newSalt()`
+  salt: Buffer,
+};
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Fresh salt for a commitment; keep it secret until the reveal
+ */
+export function newSalt(): Buffer {
+  return randomBytes(32);
+}
+
+/**
+ * Hash committed to on-chain, as computed by
+ * `SubmissionCommitment::hash_submission`
+ */
+export function submissionCommitment(submission: CommittedSubmission): Buffer {
+  return createHash('sha256')
+    .update(submission.targetProgram.toBuffer())
+    .update(submission.sender.toBuffer())
+    .update(submission.receiver.toBuffer())
+    .update(u64Bytes(submission.amount))
+    .update(u64Bytes(submission.nonce))
+    .update(u64Bytes(submission.lane))
+    .update(submission.salt)
+    .digest();
+}
+
+/**
+ * Address of the commitment account of `hash`
+ */
+export async function findCommitmentAddress(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  hash: Buffer,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [Buffer.from('commit'), gsnAccount.toBuffer(), hash],
+    programId,
+  );
+  return address;
+}
+
+/**
+ * Encode a `CommitSubmission`; the executor signs and funds the commitment
+ */
+export async function commitSubmissionInstruction(
+  programId: PublicKey,
+  executor: PublicKey,
+  gsnAccount: PublicKey,
+  submission: CommittedSubmission,
+): Promise<TransactionInstruction> {
+  const hash = submissionCommitment(submission);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: executor, isSigner: true, isWritable: true},
+      {pubkey: gsnAccount, isSigner: false, isWritable: false},
+      {
+        pubkey: await findCommitmentAddress(programId, gsnAccount, hash),
+        isSigner: false,
+        isWritable: true,
+      },
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([Buffer.from([COMMIT_SUBMISSION]), hash]),
+  });
+}
+
+/**
+ * Encode a `RevealSubmission` of a committed submission; the consumer and
+ * the committing executor sign it
+ */
+export async function revealSubmissionInstruction(
+  programId: PublicKey,
+  executor: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  submission: CommittedSubmission,
+): Promise<TransactionInstruction> {
+  const hash = submissionCommitment(submission);
+  const keys = [
+    {pubkey: submission.targetProgram, isSigner: false, isWritable: true},
+    {pubkey: submission.sender, isSigner: true, isWritable: true},
+    {pubkey: submission.receiver, isSigner: false, isWritable: true},
+    {pubkey: executor, isSigner: true, isWritable: true},
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+  ];
+  if (metricsAccount) {
+    keys.push({pubkey: metricsAccount, isSigner: false, isWritable: true});
+  }
+  keys.push({
+    pubkey: await findCommitmentAddress(programId, gsnAccount, hash),
+    isSigner: false,
+    isWritable: true,
+  });
+
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([REVEAL_SUBMISSION]),
+      u64Bytes(submission.amount),
+      u64Bytes(submission.nonce),
+      u64Bytes(submission.lane),
+      submission.salt,
+    ]),
+  });
+}
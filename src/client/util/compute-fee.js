@@ -0,0 +1,68 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {Connection, PublicKey, Transaction} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SUBMIT_METERED = 51;
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Compute units a transaction may use, bounding `ComputeBased` fees
+ */
+export const MAX_COMPUTE_UNITS = 1400000;
+
+/**
+ * Compute units `transaction` consumes, measured by simulating it. A
+ * relayer passes them to `submitMeteredInstruction`; the consumer signs
+ * them with the transaction.
+ */
+export async function measureComputeUnits(
+  connection: Connection,
+  transaction: Transaction,
+): Promise<number> {
+  const {value} = await connection.simulateTransaction(transaction);
+  if (value.err || value.unitsConsumed == null) {
+    throw new Error(`Simulation failed: ${JSON.stringify(value.err)}`);
+  }
+  return value.unitsConsumed;
+}
+
+/**
+ * Encode a `SubmitMetered`: a `SubmitTransaction` whose `ComputeBased` fee
+ * is priced by `computeUnits` instead of the mode's maximum. Above the
+ * maximum it fails with `InvalidComputeUnits`.
+ */
+export function submitMeteredInstruction(
+  programId: PublicKey,
+  targetProgram: PublicKey,
+  sender: PublicKey,
+  receiver: PublicKey,
+  feePayer: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  amount: BN | number,
+  nonce: BN | number,
+  computeUnits: BN | number,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: targetProgram, isSigner: false, isWritable: true},
+      {pubkey: sender, isSigner: true, isWritable: true},
+      {pubkey: receiver, isSigner: false, isWritable: true},
+      {pubkey: feePayer, isSigner: true, isWritable: true},
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SUBMIT_METERED]),
+      u64Bytes(amount),
+      u64Bytes(nonce),
+      u64Bytes(computeUnits),
+    ]),
+  });
+}
@@ -0,0 +1,48 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {Connection, PublicKey} from '@solana/web3.js';
+
+const SET_CREDENTIAL_MINT = 15;
+
+/**
+ * Encode a `SetCredentialMint`: the governance authority turns compliance
+ * mode on with the credential `mint`, or off when `mint` is `null`
+ */
+export function setCredentialMintInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  mint: ?PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SET_CREDENTIAL_MINT, mint ? 1 : 0]),
+      mint ? mint.toBuffer() : Buffer.alloc(32),
+    ]),
+  });
+}
+
+/**
+ * Token account of `mint` through which `consumer` holds a credential, to
+ * pass after a top-up's or submission's accounts in compliance mode.
+ * `null` when the consumer holds none.
+ */
+export async function findCredentialAccount(
+  connection: Connection,
+  mint: PublicKey,
+  consumer: PublicKey,
+): Promise<?PublicKey> {
+  const {value} = await connection.getParsedTokenAccountsByOwner(consumer, {
+    mint,
+  });
+  const held = value.find(
+    ({account}) => account.data.parsed.info.tokenAmount.amount !== '0',
+  );
+  return held ? held.pubkey : null;
+}
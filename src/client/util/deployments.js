@@ -0,0 +1,152 @@
+// @flow
+
+import {PublicKey, clusterApiUrl} from '@solana/web3.js';
+
+import {cluster as selectedCluster} from '../../../url';
+
+/**
+ * Cluster a deployment preset applies to
+ */
+export type DeploymentCluster = 'localnet' | 'devnet' | 'testnet' | 'mainnet-beta';
+
+/**
+ * Fee charged per submission by a freshly initialized config account, which
+ * governance can change with `ProposeFeeParams`
+ */
+export type DefaultFee = {fixed: number} | {percentBps: number};
+
+/**
+ * Limits relayers and executors are advised to stay within on a cluster
+ */
+export type DeploymentLimits = {
+  commitment: string,
+  // Milliseconds a batching relayer waits for more intents
+  batchWindowMs: number,
+  maxBatchSize: number,
+  // Nonce lanes a consumer relays on in parallel (at most 16)
+  nonceLanes: number,
+  // Smallest top-up worth accepting, in lamports
+  minTopup: number,
+  // Seconds after which an unconfirmed relay is given up on
+  confirmTimeoutSecs: number,
+};
+
+/**
+ * Known addresses and defaults of a SolGSN deployment.
+ *
+ * `programId` is the deployed program, `gsnAccount` the state account of
+ * the balances, whose vault holds the consumers' lamports, and
+ * `configAccount` its governance config. They are `null` until the deployment is published, and
+ * are then resolved from the store or the environment.
+ */
+export type Deployment = {
+  cluster: DeploymentCluster,
+  rpcUrl: string,
+  programId: ?PublicKey,
+  gsnAccount: ?PublicKey,
+  configAccount: ?PublicKey,
+  defaultFee: DefaultFee,
+  limits: DeploymentLimits,
+};
+
+const DEFAULT_FEE: DefaultFee = {fixed: 50000};
+
+const PRESETS: {[DeploymentCluster]: Deployment} = {
+  localnet: {
+    cluster: 'localnet',
+    rpcUrl: 'http://localhost:8899',
+    programId: null,
+    gsnAccount: null,
+    configAccount: null,
+    defaultFee: DEFAULT_FEE,
+    limits: {
+      commitment: 'recent',
+      batchWindowMs: 50,
+      maxBatchSize: 8,
+      nonceLanes: 16,
+      minTopup: 0,
+      confirmTimeoutSecs: 30,
+    },
+  },
+  devnet: {
+    cluster: 'devnet',
+    rpcUrl: clusterApiUrl('devnet', true),
+    programId: null,
+    gsnAccount: null,
+    configAccount: null,
+    defaultFee: DEFAULT_FEE,
+    limits: {
+      commitment: 'confirmed',
+      batchWindowMs: 200,
+      maxBatchSize: 8,
+      nonceLanes: 4,
+      minTopup: 1000000,
+      confirmTimeoutSecs: 90,
+    },
+  },
+  testnet: {
+    cluster: 'testnet',
+    rpcUrl: clusterApiUrl('testnet', true),
+    programId: null,
+    gsnAccount: null,
+    configAccount: null,
+    defaultFee: DEFAULT_FEE,
+    limits: {
+      commitment: 'confirmed',
+      batchWindowMs: 200,
+      maxBatchSize: 8,
+      nonceLanes: 4,
+      minTopup: 1000000,
+      confirmTimeoutSecs: 90,
+    },
+  },
+  'mainnet-beta': {
+    cluster: 'mainnet-beta',
+    rpcUrl: clusterApiUrl('mainnet-beta', true),
+    programId: null,
+    gsnAccount: null,
+    configAccount: null,
+    defaultFee: DEFAULT_FEE,
+    limits: {
+      commitment: 'confirmed',
+      batchWindowMs: 400,
+      maxBatchSize: 8,
+      nonceLanes: 4,
+      minTopup: 10000000,
+      confirmTimeoutSecs: 90,
+    },
+  },
+};
+
+function envKey(name: string, fallback: ?PublicKey): ?PublicKey {
+  const value = process.env[name];
+  return value ? new PublicKey(value) : fallback;
+}
+
+/**
+ * Preset of `cluster` with the environment applied on top:
+ * `RPC_URL`, `SOLGSN_PROGRAM_ID`, `SOLGSN_GSN_ACCOUNT` and
+ * `SOLGSN_CONFIG_ACCOUNT` override the preset's values.
+ */
+export function getDeployment(cluster: DeploymentCluster): Deployment {
+  const preset = PRESETS[cluster];
+  if (!preset) {
+    throw new Error(`Unknown cluster "${cluster}"`);
+  }
+  return {
+    ...preset,
+    rpcUrl: process.env.RPC_URL || preset.rpcUrl,
+    programId: envKey('SOLGSN_PROGRAM_ID', preset.programId),
+    gsnAccount: envKey('SOLGSN_GSN_ACCOUNT', preset.gsnAccount),
+    configAccount: envKey('SOLGSN_CONFIG_ACCOUNT', preset.configAccount),
+    limits: {...preset.limits},
+  };
+}
+
+/**
+ * Deployment of the cluster selected in `.env` (`LIVE`, `CLUSTER`), localnet
+ * when `LIVE` is unset
+ */
+export function currentDeployment(): Deployment {
+  return getDeployment(selectedCluster || 'localnet');
+}
@@ -0,0 +1,122 @@
+// @flow
+
+import {SystemProgram, TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SUBMIT_ESCROWED = 10;
+const RELEASE_ESCROW = 11;
+const DISPUTE_ESCROW = 12;
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Accounts of a `SubmitEscrowed`, in instruction order
+ */
+export type EscrowedSubmitAccounts = {
+  targetProgram: PublicKey,
+  sender: PublicKey,
+  receiver: PublicKey,
+  feePayer: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  // Registered metrics account of the config, if any
+  metricsAccount?: ?PublicKey,
+  // From `findFeeEscrowAddress(programId, gsnAccount, sender, lane, nonce)`
+  escrow: PublicKey,
+  // From `findVaultAddress(programId, gsnAccount)`
+  vault: PublicKey,
+};
+
+/**
+ * Encode a `SubmitEscrowed`: the submission's fee is moved from the vault into
+ * `escrow`, whose rent the fee payer funds, instead of the executor's balance
+ */
+export function submitEscrowedInstruction(
+  programId: PublicKey,
+  accounts: EscrowedSubmitAccounts,
+  amount: BN | number,
+  nonce: BN | number,
+  lane: number = 0,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: accounts.targetProgram, isSigner: false, isWritable: true},
+    {pubkey: accounts.sender, isSigner: true, isWritable: true},
+    {pubkey: accounts.receiver, isSigner: false, isWritable: true},
+    {pubkey: accounts.feePayer, isSigner: true, isWritable: true},
+    {pubkey: accounts.gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: accounts.configAccount, isSigner: false, isWritable: false},
+  ];
+  if (accounts.metricsAccount) {
+    keys.push({
+      pubkey: accounts.metricsAccount,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+  keys.push(
+    {pubkey: accounts.escrow, isSigner: false, isWritable: true},
+    {pubkey: accounts.vault, isSigner: false, isWritable: true},
+    {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+  );
+
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SUBMIT_ESCROWED]),
+      u64Bytes(amount),
+      u64Bytes(nonce),
+      u64Bytes(lane),
+    ]),
+  });
+}
+
+/**
+ * Encode a `ReleaseEscrow`: pays the fee and the escrow's rent to its
+ * executor once the dispute window has passed
+ */
+export function releaseEscrowInstruction(
+  programId: PublicKey,
+  escrow: PublicKey,
+  executor: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: escrow, isSigner: false, isWritable: true},
+      {pubkey: executor, isSigner: true, isWritable: true},
+    ],
+    programId,
+    data: Buffer.from([RELEASE_ESCROW]),
+  });
+}
+
+/**
+ * Encode a `DisputeEscrow`: the governance authority refunds the fee to the
+ * consumer's top-up balance, and its lamports to `vault`, during the dispute
+ * window
+ */
+export function disputeEscrowInstruction(
+  programId: PublicKey,
+  escrow: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  executor: PublicKey,
+  vault: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: escrow, isSigner: false, isWritable: true},
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+      {pubkey: executor, isSigner: false, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: true},
+    ],
+    programId,
+    data: Buffer.from([DISPUTE_ESCROW]),
+  });
+}
@@ -0,0 +1,162 @@
+// @flow
+
+import {
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Secp256k1Program,
+  SystemProgram,
+  TransactionInstruction,
+} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {intentMessage} from './intent';
+import type {ConsumerIntent} from './intent';
+
+const REGISTER_ETH_ADDRESS = 27;
+const SUBMIT_ETH_INTENT = 28;
+
+/**
+ * Prefix of every registration message (`ETH_REGISTER_DOMAIN`)
+ */
+const ETH_REGISTER_DOMAIN = Buffer.from('solgsn-eth-register');
+
+/**
+ * Signature as `personal_sign` returns it: r, s, then v (27 or 28)
+ */
+export type EthSignature = {
+  ethAddress: string,
+  signature: Buffer,
+};
+
+/**
+ * `message` as `personal_sign` (EIP-191) signs it, which the Secp256k1
+ * program is given to hash
+ */
+export function personalMessage(message: Buffer): Buffer {
+  return Buffer.concat([
+    Buffer.from(`\x19Ethereum Signed Message:\n${message.length}`),
+    message,
+  ]);
+}
+
+/**
+ * Message the wallet signs with `personal_sign` to register with `consumer`.
+ * `nonce` is the address's next registration nonce, see
+ * `GsnState.eth_registration_nonces`.
+ */
+export function ethRegistrationMessage(
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  nonce: BN | number,
+): Buffer {
+  return Buffer.concat([
+    ETH_REGISTER_DOMAIN,
+    gsnAccount.toBuffer(),
+    consumer.toBuffer(),
+    new BN(nonce.toString()).toArrayLike(Buffer, 'le', 8),
+  ]);
+}
+
+/**
+ * Secp256k1 program instruction verifying `signed` over `message`. It must be
+ * the transaction's `instructionIndex`th instruction.
+ */
+function secp256k1Instruction(
+  message: Buffer,
+  signed: EthSignature,
+  instructionIndex: number,
+): TransactionInstruction {
+  return Secp256k1Program.createInstructionWithEthAddress({
+    ethAddress: signed.ethAddress,
+    message: personalMessage(message),
+    signature: signed.signature.slice(0, 64),
+    recoveryId: signed.signature[64] - 27,
+    instructionIndex,
+  });
+}
+
+/**
+ * Instructions registering the Ethereum address of `signed`, a
+ * `personal_sign` signature over `ethRegistrationMessage` with `nonce`, with `consumer`,
+ * which signs the transaction. The Secp256k1 instruction comes first.
+ */
+export function registerEthAddressInstructions(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  nonce: BN | number,
+  signed: EthSignature,
+): Array<TransactionInstruction> {
+  return [
+    secp256k1Instruction(
+      ethRegistrationMessage(gsnAccount, consumer, nonce),
+      signed,
+      0,
+    ),
+    new TransactionInstruction({
+      keys: [
+        {pubkey: gsnAccount, isSigner: false, isWritable: true},
+        {pubkey: consumer, isSigner: true, isWritable: false},
+        {pubkey: SYSVAR_INSTRUCTIONS_PUBKEY, isSigner: false, isWritable: false},
+      ],
+      programId,
+      data: Buffer.concat([
+        Buffer.from([REGISTER_ETH_ADDRESS]),
+        new BN(nonce.toString()).toArrayLike(Buffer, 'le', 8),
+      ]),
+    }),
+  ];
+}
+
+/**
+ * Instructions relaying an intent a registered Ethereum address signed with
+ * `personal_sign` over `intentMessage`: the Secp256k1 instruction, then
+ * `SubmitEthIntent`. Only the fee payer signs the transaction; the transfer
+ * is paid out of the consumer's balance in `vault`.
+ */
+export function submitEthIntentInstructions(
+  programId: PublicKey,
+  feePayer: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  vault: PublicKey,
+  intent: ConsumerIntent,
+  signed: EthSignature,
+): Array<TransactionInstruction> {
+  const keys = [
+    {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    {pubkey: intent.consumer, isSigner: false, isWritable: false},
+    {pubkey: intent.receiver, isSigner: false, isWritable: true},
+    {pubkey: feePayer, isSigner: true, isWritable: true},
+    {pubkey: intent.gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+  ];
+  if (metricsAccount) {
+    keys.push({pubkey: metricsAccount, isSigner: false, isWritable: true});
+  }
+  if (credential) {
+    keys.push({pubkey: credential, isSigner: false, isWritable: false});
+  }
+  keys.push({pubkey: vault, isSigner: false, isWritable: true});
+  keys.push({
+    pubkey: SYSVAR_INSTRUCTIONS_PUBKEY,
+    isSigner: false,
+    isWritable: false,
+  });
+
+  const u64 = value => new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+  return [
+    secp256k1Instruction(intentMessage(intent), signed, 0),
+    new TransactionInstruction({
+      keys,
+      programId,
+      data: Buffer.concat([
+        Buffer.from([SUBMIT_ETH_INTENT]),
+        u64(intent.amount),
+        u64(intent.nonce),
+        u64(intent.lane),
+      ]),
+    }),
+  ];
+}
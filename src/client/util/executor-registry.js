@@ -0,0 +1,88 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const REGISTER_EXECUTOR = 38;
+const DEREGISTER_EXECUTOR = 39;
+const SET_EXECUTOR_REGISTRY = 40;
+
+function executorInstruction(
+  tag: number,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  executor: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([Buffer.from([tag]), executor.toBuffer()]),
+  });
+}
+
+/**
+ * Encode a `RegisterExecutor`, letting `executor` execute and earn fees
+ * while the registry is required. The governance authority signs it.
+ */
+export function registerExecutorInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  executor: PublicKey,
+): TransactionInstruction {
+  return executorInstruction(
+    REGISTER_EXECUTOR,
+    programId,
+    gsnAccount,
+    configAccount,
+    authority,
+    executor,
+  );
+}
+
+/**
+ * Encode a `DeregisterExecutor`
+ */
+export function deregisterExecutorInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  executor: PublicKey,
+): TransactionInstruction {
+  return executorInstruction(
+    DEREGISTER_EXECUTOR,
+    programId,
+    gsnAccount,
+    configAccount,
+    authority,
+    executor,
+  );
+}
+
+/**
+ * Encode a `SetExecutorRegistry`. While `required`, submissions by
+ * unregistered executors fail with `ExecutorNotRegistered`.
+ */
+export function setExecutorRegistryInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  required: boolean,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([SET_EXECUTOR_REGISTRY, required ? 1 : 0]),
+  });
+}
@@ -0,0 +1,132 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const UPDATE_FEE_PARAMS = 3;
+const PROPOSE_FEE_PARAMS = 36;
+const APPLY_FEE_PARAMS = 37;
+
+/**
+ * Slots between `ProposeFeeParams` and the earliest `ApplyFeeParams`
+ */
+export const FEE_PARAMS_TIMELOCK_SLOTS = 216000;
+
+/**
+ * `Fixed` lamports per submission, `Percent` in basis points, optionally
+ * raised to `minFee` and lowered to `maxFee` lamports, or `ComputeBased`:
+ * `base` lamports plus `pricePerCu` micro-lamports per compute unit,
+ * measured up to `maxComputeUnits`
+ */
+export type FeeParams =
+  | {fixed: BN | number}
+  | {percentBps: number, minFee?: BN | number, maxFee?: BN | number}
+  | {
+      pricePerCu: BN | number,
+      base: BN | number,
+      maxComputeUnits: BN | number,
+    };
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+// The mode byte is padded to the u64 value, followed by the extra values of
+// the modes taking them
+function feeParamsData(tag: number, params: FeeParams): Buffer {
+  if (params.pricePerCu !== undefined) {
+    return Buffer.concat([
+      Buffer.from([tag, 2, 0, 0, 0, 0, 0, 0, 0]),
+      u64Bytes(params.pricePerCu),
+      u64Bytes(params.base),
+      u64Bytes(params.maxComputeUnits),
+    ]);
+  }
+  if (params.fixed !== undefined) {
+    return Buffer.concat([
+      Buffer.from([tag, 0, 0, 0, 0, 0, 0, 0, 0]),
+      u64Bytes(params.fixed),
+    ]);
+  }
+  const data = [
+    Buffer.from([tag, 1, 0, 0, 0, 0, 0, 0, 0]),
+    u64Bytes(params.percentBps),
+  ];
+  // A bound of 0 is no bound
+  if (params.minFee !== undefined || params.maxFee !== undefined) {
+    data.push(u64Bytes(params.minFee || 0), u64Bytes(params.maxFee || 0));
+  }
+  return Buffer.concat(data);
+}
+
+function governanceFeeInstruction(
+  tag: number,
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  params: FeeParams,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: feeParamsData(tag, params),
+  });
+}
+
+/**
+ * Encode an `UpdateFeeParams`. It takes effect at once but may only lower
+ * the fee within the current mode; anything else fails with
+ * `FeeChangeTimelocked` and must be proposed.
+ */
+export function updateFeeParamsInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  params: FeeParams,
+): TransactionInstruction {
+  return governanceFeeInstruction(
+    UPDATE_FEE_PARAMS,
+    programId,
+    configAccount,
+    authority,
+    params,
+  );
+}
+
+/**
+ * Encode a `ProposeFeeParams`, applicable `FEE_PARAMS_TIMELOCK_SLOTS` later.
+ * A new proposal replaces the pending one and restarts the timelock.
+ */
+export function proposeFeeParamsInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  params: FeeParams,
+): TransactionInstruction {
+  return governanceFeeInstruction(
+    PROPOSE_FEE_PARAMS,
+    programId,
+    configAccount,
+    authority,
+    params,
+  );
+}
+
+/**
+ * Encode an `ApplyFeeParams`. Anyone may send it once the proposal's
+ * activation slot was reached.
+ */
+export function applyFeeParamsInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [{pubkey: configAccount, isSigner: false, isWritable: true}],
+    programId,
+    data: Buffer.from([APPLY_FEE_PARAMS]),
+  });
+}
@@ -0,0 +1,220 @@
+// @flow
+
+import {
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  TransactionInstruction,
+} from '@solana/web3.js';
+import type {Account, PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+import {createHash} from 'crypto';
+import nacl from 'tweetnacl';
+
+const SUBMIT_TRANSACTION = 2;
+const SUBMIT_QUOTED = 19;
+
+/**
+ * Prefix of every quote message (`QUOTE_DOMAIN`)
+ */
+const QUOTE_DOMAIN = Buffer.from('solgsn-fee-quote');
+
+/**
+ * Submission an executor quotes a fee for
+ */
+export type QuotedSubmission = {
+  targetProgram: PublicKey,
+  sender: PublicKey,
+  receiver: PublicKey,
+  amount: BN | number,
+  nonce: BN | number,
+  lane: number,
+};
+
+/**
+ * Fee an executor agrees to charge for one submission, until `expirySlot`
+ */
+export type FeeQuote = {
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  fee: BN | number,
+  expirySlot: BN | number,
+  submission: QuotedSubmission,
+};
+
+/**
+ * A quote with the executor's signature, handed to the consumer
+ */
+export type SignedFeeQuote = {
+  quote: FeeQuote,
+  signature: Buffer,
+};
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Hash identifying the quoted submission, as computed by
+ * `FeeQuote::payload_hash`
+ */
+export function quotePayloadHash(submission: QuotedSubmission): Buffer {
+  return createHash('sha256')
+    .update(submission.targetProgram.toBuffer())
+    .update(submission.sender.toBuffer())
+    .update(submission.receiver.toBuffer())
+    .update(u64Bytes(submission.amount))
+    .update(u64Bytes(submission.nonce))
+    .update(u64Bytes(submission.lane))
+    .digest();
+}
+
+/**
+ * Message the executor signs, as built by `FeeQuote::message`
+ */
+export function quoteMessage(quote: FeeQuote): Buffer {
+  return Buffer.concat([
+    QUOTE_DOMAIN,
+    quote.gsnAccount.toBuffer(),
+    quotePayloadHash(quote.submission),
+    u64Bytes(quote.fee),
+    u64Bytes(quote.expirySlot),
+  ]);
+}
+
+/**
+ * Sign `quote` with the executor's key, off-chain
+ */
+export function signFeeQuote(executor: Account, quote: FeeQuote): SignedFeeQuote {
+  if (!executor.publicKey.equals(quote.executor)) {
+    throw new Error('The quote must be signed by its executor');
+  }
+  const signature = nacl.sign.detached(quoteMessage(quote), executor.secretKey);
+  return {quote, signature: Buffer.from(signature)};
+}
+
+/**
+ * Whether `signed` carries a valid signature of its executor, for consumers
+ * to check before embedding it
+ */
+export function verifyFeeQuote(signed: SignedFeeQuote): boolean {
+  return nacl.sign.detached.verify(
+    quoteMessage(signed.quote),
+    signed.signature,
+    signed.quote.executor.toBuffer(),
+  );
+}
+
+/**
+ * Accounts of a quoted submission: the submit accounts, `[metrics]`,
+ * `[credential]`, then the Instructions sysvar
+ */
+function quotedSubmitKeys(
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  quote: FeeQuote,
+) {
+  const {submission} = quote;
+  const keys = [
+    {pubkey: submission.targetProgram, isSigner: false, isWritable: true},
+    {pubkey: submission.sender, isSigner: true, isWritable: true},
+    {pubkey: submission.receiver, isSigner: false, isWritable: true},
+    {pubkey: quote.executor, isSigner: true, isWritable: true},
+    {pubkey: quote.gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+  ];
+  if (metricsAccount) {
+    keys.push({pubkey: metricsAccount, isSigner: false, isWritable: true});
+  }
+  if (credential) {
+    keys.push({pubkey: credential, isSigner: false, isWritable: false});
+  }
+  keys.push({
+    pubkey: SYSVAR_INSTRUCTIONS_PUBKEY,
+    isSigner: false,
+    isWritable: false,
+  });
+  return keys;
+}
+
+/**
+ * Ed25519 program instruction verifying the executor's signature over the quote
+ */
+function verifyQuoteInstruction(signed: SignedFeeQuote): TransactionInstruction {
+  return Ed25519Program.createInstructionWithPublicKey({
+    publicKey: signed.quote.executor.toBytes(),
+    message: quoteMessage(signed.quote),
+    signature: signed.signature,
+  });
+}
+
+/**
+ * Instructions executing a submission at its quoted fee: the Ed25519 program
+ * instruction verifying the executor's signature, then `SubmitQuoted`. The
+ * consumer and the executor (the fee payer) sign the transaction.
+ */
+export function submitQuotedInstructions(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  signed: SignedFeeQuote,
+): Array<TransactionInstruction> {
+  const {quote} = signed;
+  const {submission} = quote;
+  return [
+    verifyQuoteInstruction(signed),
+    new TransactionInstruction({
+      keys: quotedSubmitKeys(configAccount, metricsAccount, credential, quote),
+      programId,
+      data: Buffer.concat([
+        Buffer.from([SUBMIT_QUOTED]),
+        u64Bytes(submission.amount),
+        u64Bytes(submission.nonce),
+        u64Bytes(submission.lane),
+        u64Bytes(quote.fee),
+        u64Bytes(quote.expirySlot),
+      ]),
+    }),
+  ];
+}
+
+/**
+ * Like `submitQuotedInstructions`, with a lane 0 `SubmitTransaction` that
+ * also carries the consumer's `expirySlot`, `maxFee` and `tip`
+ */
+export function submitTransactionQuotedInstructions(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  signed: SignedFeeQuote,
+  expirySlot: BN | number = 0,
+  maxFee: BN | number = new BN('ffffffffffffffff', 16),
+  tip: BN | number = 0,
+): Array<TransactionInstruction> {
+  const {quote} = signed;
+  const {submission} = quote;
+  if (submission.lane !== 0) {
+    throw new Error('SubmitTransaction quotes are for lane 0');
+  }
+  return [
+    verifyQuoteInstruction(signed),
+    new TransactionInstruction({
+      keys: quotedSubmitKeys(configAccount, metricsAccount, credential, quote),
+      programId,
+      data: Buffer.concat([
+        Buffer.from([SUBMIT_TRANSACTION]),
+        u64Bytes(submission.amount),
+        u64Bytes(submission.nonce),
+        u64Bytes(expirySlot),
+        u64Bytes(maxFee),
+        u64Bytes(tip),
+        // Not a dry run
+        Buffer.from([0]),
+        u64Bytes(quote.fee),
+        u64Bytes(quote.expirySlot),
+      ]),
+    }),
+  ];
+}
@@ -0,0 +1,94 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SUBMIT_SPLIT = 16;
+
+/**
+ * Basis points of a whole fee (`FEE_SPLIT_DENOMINATOR`)
+ */
+export const FEE_SPLIT_DENOMINATOR = 10000;
+
+/**
+ * Accounts of a `SubmitSplit`, in instruction order
+ */
+export type SplitSubmitAccounts = {
+  targetProgram: PublicKey,
+  sender: PublicKey,
+  receiver: PublicKey,
+  // Executor landing the transaction
+  feePayer: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  // Registered metrics account of the config, if any
+  metricsAccount?: ?PublicKey,
+  // Sender's credential, in compliance mode
+  credential?: ?PublicKey,
+  // Executor sharing the fee, e.g. the one that built and simulated the transaction
+  coExecutor: PublicKey,
+};
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Fee payer's and co-executor's shares of `fee`, as the program splits it
+ */
+export function feeShares(fee: BN | number, feePayerBps: number): [BN, BN] {
+  const total = new BN(fee.toString());
+  const feePayerShare = total.muln(feePayerBps).divn(FEE_SPLIT_DENOMINATOR);
+  return [feePayerShare, total.sub(feePayerShare)];
+}
+
+/**
+ * Encode a `SubmitSplit`: the submission's fee is credited `feePayerBps`
+ * basis points to the fee payer and the rest to the co-executor. The
+ * consumer, the fee payer and the co-executor must all sign.
+ */
+export function submitSplitInstruction(
+  programId: PublicKey,
+  accounts: SplitSubmitAccounts,
+  amount: BN | number,
+  nonce: BN | number,
+  lane: number,
+  feePayerBps: number,
+): TransactionInstruction {
+  if (feePayerBps < 0 || feePayerBps > FEE_SPLIT_DENOMINATOR) {
+    throw new Error(`Fee split must be within 0-${FEE_SPLIT_DENOMINATOR} bps`);
+  }
+
+  const keys = [
+    {pubkey: accounts.targetProgram, isSigner: false, isWritable: true},
+    {pubkey: accounts.sender, isSigner: true, isWritable: true},
+    {pubkey: accounts.receiver, isSigner: false, isWritable: true},
+    {pubkey: accounts.feePayer, isSigner: true, isWritable: true},
+    {pubkey: accounts.gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: accounts.configAccount, isSigner: false, isWritable: false},
+  ];
+  if (accounts.metricsAccount) {
+    keys.push({
+      pubkey: accounts.metricsAccount,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+  if (accounts.credential) {
+    keys.push({pubkey: accounts.credential, isSigner: false, isWritable: false});
+  }
+  keys.push({pubkey: accounts.coExecutor, isSigner: true, isWritable: false});
+
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SUBMIT_SPLIT]),
+      u64Bytes(amount),
+      u64Bytes(nonce),
+      u64Bytes(lane),
+      u64Bytes(feePayerBps),
+    ]),
+  });
+}
@@ -0,0 +1,33 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_FREE_TIER = 50;
+
+/**
+ * Encode a `SetFreeTier` charging the first `freeTxQuota` submissions of
+ * every consumer to the top-up balance of `subsidy`. A quota of 0 turns the
+ * free tier off. The governance authority signs it.
+ */
+export function setFreeTierInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  freeTxQuota: BN | number,
+  subsidy: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SET_FREE_TIER]),
+      new BN(freeTxQuota.toString()).toArrayLike(Buffer, 'le', 8),
+      subsidy.toBuffer(),
+    ]),
+  });
+}
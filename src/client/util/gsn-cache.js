@@ -0,0 +1,157 @@
+// @flow
+
+import {Connection, PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {decodeGsnState, getGsnConfig} from './gsn-state';
+
+/**
+ * Decoded GSN state together with the slot it was observed at
+ */
+export type CachedGsnState = {
+  slot: number,
+  gsnInfo: Object,
+};
+
+/**
+ * In-memory cache of the GSN state account for relayers.
+ *
+ * The state is fetched once, then kept current from websocket account
+ * notifications, so relay requests read balances, nonces and fee config
+ * without an RPC round trip. Every entry carries the slot it was observed at:
+ * notifications older than the cached slot are ignored, and callers that
+ * need state at least as new as a given slot (e.g. the slot their last
+ * submission landed in) pass it as `minSlot` to force a refresh.
+ *
+ * The config account only changes through governance instructions, so it is
+ * fetched once and kept until `invalidate()`.
+ */
+export class GsnStateCache {
+  connection: Connection;
+  gsnAccountPubkey: PublicKey;
+  commitment: string;
+  cached: ?CachedGsnState = null;
+  config: ?Object = null;
+  subscriptionId: ?number = null;
+
+  constructor(
+    connection: Connection,
+    gsnAccountPubkey: PublicKey,
+    commitment: string = 'recent',
+  ) {
+    this.connection = connection;
+    this.gsnAccountPubkey = gsnAccountPubkey;
+    this.commitment = commitment;
+  }
+
+  /**
+   * Load the state and subscribe to account updates
+   */
+  async start(): Promise<void> {
+    if (this.subscriptionId === null) {
+      this.subscriptionId = this.connection.onAccountChange(
+        this.gsnAccountPubkey,
+        (accountInfo, context) => {
+          try {
+            this.update(context.slot, decodeGsnState(accountInfo.data));
+          } catch (error) {
+            // Drop the entry so the next read refetches
+            console.warn('Failed to decode GSN state update:', error);
+            this.cached = null;
+          }
+        },
+        this.commitment,
+      );
+    }
+    await this.refresh();
+  }
+
+  /**
+   * Remove the account subscription, keeping the last cached state
+   */
+  async stop(): Promise<void> {
+    if (this.subscriptionId !== null) {
+      const id = this.subscriptionId;
+      this.subscriptionId = null;
+      await this.connection.removeAccountChangeListener(id);
+    }
+  }
+
+  /**
+   * Fetch the state over RPC, bypassing the cache
+   */
+  async refresh(): Promise<CachedGsnState> {
+    const {context, value} = await this.connection.getAccountInfoAndContext(
+      this.gsnAccountPubkey,
+      this.commitment,
+    );
+    if (!value) {
+      throw new Error('GSN account not found');
+    }
+    this.update(context.slot, decodeGsnState(value.data));
+    return (this.cached: any);
+  }
+
+  /**
+   * Store state observed at `slot` unless newer state is already cached
+   */
+  update(slot: number, gsnInfo: Object): void {
+    if (!this.cached || slot >= this.cached.slot) {
+      this.cached = {slot, gsnInfo};
+    }
+  }
+
+  /**
+   * Drop the cached state and config, the next read goes to RPC
+   */
+  invalidate(): void {
+    this.cached = null;
+    this.config = null;
+  }
+
+  /**
+   * Cached state, refetched when missing or older than `minSlot`
+   */
+  async get(minSlot: number = 0): Promise<CachedGsnState> {
+    const cached = this.cached;
+    if (cached && cached.slot >= minSlot) {
+      return cached;
+    }
+    return this.refresh();
+  }
+
+  /**
+   * Top-up balance and next nonce of a consumer, in nonce lane `lane`
+   */
+  async getConsumer(
+    consumerPubkey: PublicKey,
+    lane: number = 0,
+    minSlot: number = 0,
+  ): Promise<{balance: BN, nonce: number, slot: number}> {
+    const {slot, gsnInfo} = await this.get(minSlot);
+    const consumerKey = consumerPubkey.toBase58();
+    const balance = gsnInfo.consumer.get(consumerKey);
+    const nonce =
+      lane === 0
+        ? gsnInfo.consumer_nonces.get(consumerKey)
+        : gsnInfo.lane_nonces.get(`${consumerKey}:${lane}`);
+    return {
+      balance: balance ? new BN(balance.toString()) : new BN(0),
+      nonce: nonce ? nonce.toNumber() : 0,
+      slot,
+    };
+  }
+
+  /**
+   * Governance configuration (authority, fee mode, allowed tokens), if set
+   */
+  async getConfig(minSlot: number = 0): Promise<?Object> {
+    let config = this.config;
+    if (!config) {
+      const {gsnInfo} = await this.get(minSlot);
+      config = await getGsnConfig(this.connection, gsnInfo.config);
+      this.config = config;
+    }
+    return config.governance;
+  }
+}
@@ -1,35 +1,1279 @@
 // @flow
 
 import { Connection, PublicKey } from '@solana/web3.js';
-import { deserialize as borshDeserialize } from 'borsh';
+import { deserialize as borshDeserialize, deserializeUnchecked } from 'borsh';
 import BN from 'bn.js';
 
 /**
  * GSN State schema for Borsh deserialization
  */
 class GsnInfo {
+  constructor(fields) {
+    // The program stores its maps as key-sorted entry vectors; expose them as
+    // Maps keyed by base58 strings
+    this.version = fields.version;
+    this.is_initialized = fields.version !== 0;
+    this.config = new PublicKey(fields.config);
+    this.consumer = keyedMap(fields.consumer);
+    this.executor = keyedMap(fields.executor);
+    this.consumer_nonces = keyedMap(fields.consumer_nonces);
+    // Receipts of lane 0 are keyed `consumer:nonce`, other lanes `consumer:lane:nonce`
+    this.transaction_executor = new Map(
+      (fields.transaction_executor || []).map(
+        ({consumer, lane, nonce, executor}) => [
+          lane.isZero()
+            ? `${base58(consumer)}:${nonce.toString()}`
+            : `${base58(consumer)}:${lane.toString()}:${nonce.toString()}`,
+          base58(executor),
+        ],
+      ),
+    );
+    // Keyed `consumer:lane`
+    this.lane_nonces = new Map(
+      (fields.lane_nonces || []).map(({consumer, lane, nonce}) => [
+        `${base58(consumer)}:${lane.toString()}`,
+        nonce,
+      ]),
+    );
+    // Token balances and earnings, keyed `owner:mint`
+    this.token_consumer = tokenBalanceMap(fields.token_consumer);
+    this.token_executor = tokenBalanceMap(fields.token_executor);
+    // Registered Ethereum addresses, keyed by lowercase hex without `0x`
+    this.eth_consumers = new Map(
+      (fields.eth_consumers || []).map(({address, consumer}) => [
+        Buffer.from(address).toString('hex'),
+        new PublicKey(consumer),
+      ]),
+    );
+    this.paused = fields.paused;
+    // Fee limits set by governance, keyed by base58 consumer
+    this.spending_limits = new Map(
+      (fields.spending_limits || []).map(({consumer, ...limit}) => [
+        base58(consumer),
+        limit,
+      ]),
+    );
+    // Executors registered by governance, see `RegisterExecutor`
+    this.registered_executors = (fields.registered_executors || []).map(
+      ({key}) => new PublicKey(key),
+    );
+    // Bonded stakes, keyed by base58 executor
+    this.executor_stakes = new Map(
+      (fields.executor_stakes || []).map(({executor, ...stake}) => [
+        base58(executor),
+        stake,
+      ]),
+    );
+    // Lamports of the protocol's cut of fees
+    this.treasury = fields.treasury;
+    // Sponsored submissions of users of sponsors with a quota, keyed
+    // `sponsor:consumer`
+    this.sponsor_usage = new Map(
+      (fields.sponsor_usage || []).map(({sponsor, consumer, submissions}) => [
+        `${base58(sponsor)}:${base58(consumer)}`,
+        submissions,
+      ]),
+    );
+    // Free submissions used, keyed by base58 consumer
+    this.free_txs = keyedMap(fields.free_txs);
+    // Submissions in `epoch` counted for volume discounts, keyed by base58
+    // consumer
+    this.epoch_volumes = new Map(
+      (fields.epoch_volumes || []).map(({consumer, ...volume}) => [
+        base58(consumer),
+        volume,
+      ]),
+    );
+    // Addresses fees may be claimed to, keyed by base58 executor
+    this.payout_addresses = new Map(
+      (fields.payout_addresses || []).map(({executor, payout}) => [
+        base58(executor),
+        new PublicKey(payout),
+      ]),
+    );
+    // Windows of unordered nonces, keyed by base58 consumer
+    this.nonce_windows = new Map(
+      (fields.nonce_windows || []).map(({consumer, ...window}) => [
+        base58(consumer),
+        window,
+      ]),
+    );
+    // Next nonce of every channel, keyed `consumer:executor`
+    this.channel_nonces = new Map(
+      (fields.channel_nonces || []).map(({consumer, executor, nonce}) => [
+        `${base58(consumer)}:${base58(executor)}`,
+        nonce,
+      ]),
+    );
+    // Hashes of the last executed payloads, see `SetPayloadRetention`
+    this.recent_payloads = fields.recent_payloads
+      ? fields.recent_payloads.hashes.map(hash => Buffer.from(hash))
+      : [];
+    // Ledger account flags, keyed by base58 consumer or executor
+    this.ledger_accounts = new Map(
+      (fields.ledger_accounts || []).map(({key, flags}) => [base58(key), flags]),
+    );
+    // Cooldown withdrawals wait out, see `SetWithdrawalCooldown`
+    this.withdrawal_policy = fields.withdrawal_policy || {
+      cooldown_slots: 0,
+      emergency: 0,
+    };
+    // Pending withdrawal requests, keyed by base58 consumer
+    this.withdrawal_requests = new Map(
+      (fields.withdrawal_requests || []).map(({consumer, ...request}) => [
+        base58(consumer),
+        request,
+      ]),
+    );
+    // Fees held while their submission executes, keyed `charged:mint`;
+    // empty between instructions
+    this.escrowed_fees = tokenBalanceMap(fields.escrowed_fees);
+    // Set while a submission's CPI runs
+    this.in_flight = fields.in_flight;
+    // Submissions a consumer may make per window, see `SetRateLimit`
+    this.rate_limit = fields.rate_limit || {
+      max_submissions: 0,
+      window_slots: 0,
+    };
+    // Current rate limit window of every consumer, keyed by base58 consumer
+    this.rate_windows = new Map(
+      (fields.rate_windows || []).map(({consumer, ...window}) => [
+        base58(consumer),
+        window,
+      ]),
+    );
+    // Addresses barred by governance, see `BlacklistAddress`
+    this.blacklist = (fields.blacklist || []).map(({key}) => new PublicKey(key));
+    // Failure threshold pausing the deployment, and the epoch's executions
+    this.circuit_breaker = fields.circuit_breaker || {
+      max_failure_bps: 0,
+      min_executions: 0,
+      epoch: 0,
+      succeeded: 0,
+      failed: 0,
+    };
+    // Executions counted by epoch, see `SetStatsTracking`; null on accounts
+    // written before they existed
+    this.stats = fields.stats || null;
+    // Last epoch `DistributeRewards` rewarded, null before the first
+    this.rewarded_epoch = fields.rewarded_epoch || null;
+    // Lamports of the insurance cut of fees, see `Compensate`
+    this.insurance_pool = fields.insurance_pool || 0;
+    // Nonce and reporting executor of every lane whose failed relay was
+    // reported in the circuit breaker's epoch, keyed `consumer:lane`
+    this.failure_reports = new Map(
+      (fields.failure_reports || []).map(({consumer, lane, nonce, executor}) => [
+        `${base58(consumer)}:${lane.toString()}`,
+        {nonce, executor: base58(executor)},
+      ]),
+    );
+    // Lamports `Compensate` paid for every execution, keyed
+    // `consumer:lane:nonce:executor`
+    this.compensations = new Map(
+      (fields.compensations || []).map(({consumer, lane, nonce, executor, amount}) => [
+        `${base58(consumer)}:${lane.toString()}:${nonce.toString()}:${base58(executor)}`,
+        amount,
+      ]),
+    );
+    // Next registration nonce of every Ethereum address, keyed by lowercase
+    // hex without `0x`; missing addresses are at zero
+    this.eth_registration_nonces = new Map(
+      (fields.eth_registration_nonces || []).map(({address, nonce}) => [
+        Buffer.from(address).toString('hex'),
+        nonce,
+      ]),
+    );
+  }
+}
+
+/**
+ * Configuration account of a GSN deployment, changed only by governance
+ */
+class GsnConfig {
   constructor(fields) {
     this.is_initialized = fields.is_initialized;
-    this.consumer = fields.consumer || new Map();
-    this.executor = fields.executor || new Map();
+    this.gsn_account = new PublicKey(fields.gsn_account);
     this.governance = fields.governance || null;
-    this.consumer_nonces = fields.consumer_nonces || new Map();
-    this.transaction_executor = fields.transaction_executor || new Map();
+    this.metrics = fields.metrics ? new PublicKey(fields.metrics) : null;
+    this.credential_mint = fields.credential_mint
+      ? new PublicKey(fields.credential_mint)
+      : null;
+    this.sandbox = fields.sandbox || null;
+    this.shutdown = fields.shutdown || null;
+    // Empty when every target program is allowed
+    this.allowed_programs = (fields.allowed_programs || []).map(
+      ({key}) => new PublicKey(key),
+    );
+    this.multisig = fields.multisig || null;
+    this.pending_fee_params = fields.pending_fee_params || null;
+    this.registered_executors_only = Boolean(fields.registered_executors_only);
+    this.min_executor_stake = fields.min_executor_stake;
+    this.protocol_fee_bps = fields.protocol_fee_bps;
+    this.free_tier = fields.free_tier || null;
+    // Tiers in increasing order of `min_txs`, empty without discounts
+    this.volume_discounts = fields.volume_discounts || [];
+    // Genesis hash bound into intents' domain separator, null until set
+    this.genesis_hash = fields.genesis_hash
+      ? Buffer.from(fields.genesis_hash)
+      : null;
+    this.record_retention = fields.record_retention || 0;
+    // Oracles pricing token fees, keyed by base58 mint
+    this.price_feeds = new Map(
+      (fields.price_feeds || []).map(({mint, feed, ...limits}) => [
+        base58(mint),
+        {feed: new PublicKey(feed), ...limits},
+      ]),
+    );
+    this.insurance_fee_bps = fields.insurance_fee_bps || 0;
+  }
+}
+
+/**
+ * Oracle price account pricing a mint's token fees, see `SetPriceFeed`.
+ * `kind` is 0 for Pyth, 1 for Switchboard.
+ */
+class PriceFeedEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Free submissions of every consumer, charged to the subsidy's balance
+ */
+class FreeTier {
+  constructor(fields) {
+    this.free_tx_quota = fields.free_tx_quota;
+    this.subsidy = new PublicKey(fields.subsidy);
+  }
+}
+
+/**
+ * Fee mode waiting out the timelock until `activation_slot`
+ */
+class PendingFeeParams {
+  constructor(fields) {
+    this.fee_mode = fields.fee_mode;
+    this.activation_slot = fields.activation_slot;
+  }
+}
+
+/**
+ * M-of-N signers that replace the governance authority
+ */
+class MultisigConfig {
+  constructor(fields) {
+    this.signers = fields.signers.map(key => new PublicKey(key));
+    this.threshold = fields.threshold;
+  }
+}
+
+/**
+ * Developer sandbox of a devnet deployment
+ */
+class SandboxConfig {
+  constructor(fields) {
+    this.subsidy = fields.subsidy ? new PublicKey(fields.subsidy) : null;
+    this.reset_epoch = fields.reset_epoch;
+  }
+}
+
+/**
+ * Terminal shutdown of a deployment, timestamps in Unix seconds
+ */
+class ShutdownState {
+  constructor(fields) {
+    this.recovery = new PublicKey(fields.recovery);
+    this.effective_at = fields.effective_at;
+    this.sweep_at = fields.sweep_at;
+  }
+}
+
+/**
+ * Governance authority, fee mode and allowed fee tokens
+ */
+class GovernanceConfig {
+  constructor(fields) {
+    this.authority = new PublicKey(fields.authority);
+    this.fee_mode = fields.fee_mode;
+    this.allowed_tokens = (fields.allowed_tokens || []).map(
+      ({key}) => new PublicKey(key),
+    );
+  }
+}
+
+/**
+ * `Fixed(lamports)`, `Percent(basis points)`, `ComputeBased` or a bounded
+ * percentage, only one field is set
+ */
+class FeeMode {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * `FeeMode::ComputeBased`, prices in micro-lamports per compute unit
+ */
+class ComputeBasedFee {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * `FeeMode::Percent` with a minimum or maximum fee in lamports
+ */
+class BoundedPercentFee {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Allowed token, target program, registered executor or blacklist entry, the
+ * flag is always set
+ */
+class TokenEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Entry of a sorted map keyed by a public key
+ */
+class KeyEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Executor receipt keyed by (consumer, lane, nonce)
+ */
+class ReceiptEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Lane nonce keyed by (consumer, lane)
+ */
+class LaneEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Token balance keyed by (owner, mint)
+ */
+class TokenBalanceEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Consumer keyed by the Ethereum address signing for it
+ */
+class EthConsumerEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Fee limits of a consumer and what it spent in `epoch`
+ */
+class SpendingLimitEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Lamports an executor bonded, `unbonding_at` is 0 while bonded
+ */
+class ExecutorStakeEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+class SponsorUsageEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Submissions of a consumer in `epoch`
+ */
+class EpochVolumeEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Unordered nonces of a consumer: `bits` holds bit `i` of byte `i / 8` set
+ * when nonce `base + i` is used
+ */
+class NonceWindowEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Next nonce of the channel between a consumer and an executor
+ */
+class ChannelNonceEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Ring of the hashes of the last executed payloads
+ */
+class RecentPayloads {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Which ledger accounts a consumer or executor has opened
+ */
+class LedgerAccountEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Slots withdrawals wait out, and whether emergency withdrawals are enabled
+ */
+class WithdrawalPolicy {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Submissions a consumer may make within a window of slots
+ */
+class RateLimit {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Failure ratio pausing the deployment, and the epoch's execution counts
+ */
+class CircuitBreaker {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Executions counted by epoch while enabled, see `SetStatsTracking`
+ */
+class Stats {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Counters of an epoch's executions
+ */
+class EpochStats {
+  constructor(fields) {
+    Object.assign(this, fields);
+    // Executions, keyed by base58 consumer
+    this.consumers = keyedMap(fields.consumers);
+    // Executions and lamport fees, keyed by base58 executor
+    this.executor_volumes = new Map(
+      fields.executor_volumes.map(({executor, ...volume}) => [
+        base58(executor),
+        volume,
+      ]),
+    );
+  }
+}
+
+/**
+ * Executions an executor relayed in an epoch, and the lamport fees it earned
+ */
+class ExecutorVolumeEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Start slot and submissions of a consumer's rate limit window
+ */
+class RateWindowEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Failed relay of a consumer lane's nonce, and the executor that reported it
+ */
+class FailureReportEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Lamports `Compensate` paid for a consumer's execution
+ */
+class CompensationEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Registration nonce an Ethereum address signs next
+ */
+class EthRegistrationNonceEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Lamports a consumer asked to withdraw, and the slot they unlock at
+ */
+class WithdrawalRequestEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Address an executor's fees may be claimed to
+ */
+class PayoutAddressEntry {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Basis points taken off the fees of consumers from their `min_txs`th
+ * submission in an epoch
+ */
+class VolumeDiscountTier {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+const EntrySchema = [
+  [
+    KeyEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['key', [32]],
+        ['value', 'u64'],
+      ],
+    },
+  ],
+  [
+    ReceiptEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['lane', 'u64'],
+        ['nonce', 'u64'],
+        ['executor', [32]],
+      ],
+    },
+  ],
+  [
+    LaneEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['lane', 'u64'],
+        ['nonce', 'u64'],
+      ],
+    },
+  ],
+  [
+    TokenBalanceEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['owner', [32]],
+        ['mint', [32]],
+        ['value', 'u64'],
+      ],
+    },
+  ],
+  [
+    EthConsumerEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['address', [20]],
+        ['consumer', [32]],
+      ],
+    },
+  ],
+  [
+    SpendingLimitEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['max_fee_per_tx', 'u64'],
+        ['max_fees_per_epoch', 'u64'],
+        ['epoch', 'u64'],
+        ['spent', 'u64'],
+      ],
+    },
+  ],
+  [
+    ExecutorStakeEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['executor', [32]],
+        ['amount', 'u64'],
+        ['unbonding_at', 'u64'],
+      ],
+    },
+  ],
+  [
+    SponsorUsageEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['sponsor', [32]],
+        ['consumer', [32]],
+        ['submissions', 'u64'],
+      ],
+    },
+  ],
+  [
+    EpochVolumeEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['epoch', 'u64'],
+        ['txs', 'u64'],
+      ],
+    },
+  ],
+  [
+    PayoutAddressEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['executor', [32]],
+        ['payout', [32]],
+      ],
+    },
+  ],
+  [
+    NonceWindowEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['base', 'u64'],
+        ['bits', [32]],
+      ],
+    },
+  ],
+  [
+    ChannelNonceEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['executor', [32]],
+        ['nonce', 'u64'],
+      ],
+    },
+  ],
+  [
+    RecentPayloads,
+    {
+      kind: 'struct',
+      fields: [
+        ['cursor', 'u16'],
+        ['hashes', [[32]]],
+      ],
+    },
+  ],
+  [
+    LedgerAccountEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['key', [32]],
+        ['flags', 'u8'],
+      ],
+    },
+  ],
+  [
+    WithdrawalPolicy,
+    {
+      kind: 'struct',
+      fields: [
+        ['cooldown_slots', 'u64'],
+        ['emergency', 'u8'],
+      ],
+    },
+  ],
+  [
+    WithdrawalRequestEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['amount', 'u64'],
+        ['unlock_slot', 'u64'],
+      ],
+    },
+  ],
+  [
+    RateLimit,
+    {
+      kind: 'struct',
+      fields: [
+        ['max_submissions', 'u64'],
+        ['window_slots', 'u64'],
+      ],
+    },
+  ],
+  [
+    CircuitBreaker,
+    {
+      kind: 'struct',
+      fields: [
+        ['max_failure_bps', 'u16'],
+        ['min_executions', 'u64'],
+        ['epoch', 'u64'],
+        ['succeeded', 'u64'],
+        ['failed', 'u64'],
+      ],
+    },
+  ],
+  [
+    Stats,
+    {
+      kind: 'struct',
+      fields: [
+        ['enabled', 'u8'],
+        ['current', EpochStats],
+        ['previous', EpochStats],
+      ],
+    },
+  ],
+  [
+    EpochStats,
+    {
+      kind: 'struct',
+      fields: [
+        ['epoch', 'u64'],
+        ['executions', 'u64'],
+        ['total_fees', 'u64'],
+        ['consumers', [KeyEntry]],
+        ['executor_volumes', [ExecutorVolumeEntry]],
+      ],
+    },
+  ],
+  [
+    ExecutorVolumeEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['executor', [32]],
+        ['executions', 'u64'],
+        ['fees', 'u64'],
+      ],
+    },
+  ],
+  [
+    RateWindowEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['start_slot', 'u64'],
+        ['submissions', 'u64'],
+      ],
+    },
+  ],
+  [
+    FailureReportEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['lane', 'u64'],
+        ['nonce', 'u64'],
+        ['executor', [32]],
+      ],
+    },
+  ],
+  [
+    CompensationEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['consumer', [32]],
+        ['lane', 'u64'],
+        ['nonce', 'u64'],
+        ['executor', [32]],
+        ['amount', 'u64'],
+      ],
+    },
+  ],
+  [
+    EthRegistrationNonceEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['address', [20]],
+        ['nonce', 'u64'],
+      ],
+    },
+  ],
+];
+
+function base58(bytes: Uint8Array): string {
+  return new PublicKey(bytes).toBase58();
+}
+
+/**
+ * Sorted key/value entries as a Map keyed by base58 public key
+ */
+function keyedMap(entries: ?Array<KeyEntry>): Map<string, BN> {
+  return new Map((entries || []).map(({key, value}) => [base58(key), value]));
+}
+
+/**
+ * Token balance entries as a Map keyed `owner:mint`
+ */
+function tokenBalanceMap(
+  entries: ?Array<TokenBalanceEntry>,
+): Map<string, BN> {
+  return new Map(
+    (entries || []).map(({owner, mint, value}) => [
+      `${base58(owner)}:${base58(mint)}`,
+      value,
+    ]),
+  );
+}
+
+/**
+ * Aggregates for a single epoch
+ */
+class EpochMetrics {
+  constructor(fields) {
+    Object.assign(this, fields);
+    this.fees_by_token = keyedMap(fields.fees_by_token);
+    this.executions_by_program = keyedMap(fields.executions_by_program);
+    this.executor_fees = keyedMap(fields.executor_fees);
+  }
+}
+
+/**
+ * Protocol metrics account, rolled over every epoch
+ */
+class ProtocolMetrics {
+  constructor(fields) {
+    Object.assign(this, fields);
   }
 }
 
-const GsnInfoSchema = new Map([
+/**
+ * Fee of one submission held in its own escrow account until it is released
+ * to the executor or refunded to the consumer
+ */
+class FeeEscrow {
+  constructor(fields) {
+    Object.assign(this, fields);
+    this.gsn_account = new PublicKey(fields.gsn_account);
+    this.consumer = new PublicKey(fields.consumer);
+    this.executor = new PublicKey(fields.executor);
+  }
+}
+
+/**
+ * Receipt of an executed submission, written when its executor passed it
+ */
+class ExecutionReceipt {
+  constructor(fields) {
+    Object.assign(this, fields);
+    this.gsn_account = new PublicKey(fields.gsn_account);
+    this.consumer = new PublicKey(fields.consumer);
+    this.executor = new PublicKey(fields.executor);
+    this.target_program = new PublicKey(fields.target_program);
+  }
+}
+
+/**
+ * Balance and lane 0 nonce of a consumer that opened its ledger account
+ */
+class ConsumerAccount {
+  constructor(fields) {
+    Object.assign(this, fields);
+    this.gsn_account = new PublicKey(fields.gsn_account);
+    this.consumer = new PublicKey(fields.consumer);
+  }
+}
+
+/**
+ * Earnings of an executor that opened its ledger account
+ */
+class ExecutorAccount {
+  constructor(fields) {
+    Object.assign(this, fields);
+    this.gsn_account = new PublicKey(fields.gsn_account);
+    this.executor = new PublicKey(fields.executor);
+  }
+}
+
+/**
+ * Flags of `GsnInfo.ledger_accounts`
+ */
+export const CONSUMER_LEDGER = 1;
+export const EXECUTOR_LEDGER = 2;
+
+const LedgerAccountSchema = new Map([
+  [
+    ConsumerAccount,
+    {
+      kind: 'struct',
+      fields: [
+        ['is_initialized', 'u8'],
+        ['gsn_account', [32]],
+        ['consumer', [32]],
+        ['balance', 'u64'],
+        ['nonce', 'u64'],
+        ['bump', 'u8'],
+      ],
+    },
+  ],
+  [
+    ExecutorAccount,
+    {
+      kind: 'struct',
+      fields: [
+        ['is_initialized', 'u8'],
+        ['gsn_account', [32]],
+        ['executor', [32]],
+        ['earned', 'u64'],
+        ['bump', 'u8'],
+      ],
+    },
+  ],
+]);
+
+/**
+ * Slots an escrowed fee can be disputed for (`ESCROW_DISPUTE_WINDOW_SLOTS`)
+ */
+export const ESCROW_DISPUTE_WINDOW_SLOTS = 150;
+
+const FeeEscrowSchema = new Map([
+  [
+    FeeEscrow,
+    {
+      kind: 'struct',
+      fields: [
+        ['is_initialized', 'u8'],
+        ['gsn_account', [32]],
+        ['consumer', [32]],
+        ['executor', [32]],
+        ['lane', 'u64'],
+        ['nonce', 'u64'],
+        ['fee', 'u64'],
+        ['slot', 'u64'],
+      ],
+    },
+  ],
+]);
+
+const ExecutionReceiptSchema = new Map([
+  [
+    ExecutionReceipt,
+    {
+      kind: 'struct',
+      fields: [
+        ['is_initialized', 'u8'],
+        ['gsn_account', [32]],
+        ['consumer', [32]],
+        ['executor', [32]],
+        ['target_program', [32]],
+        ['lane', 'u64'],
+        ['nonce', 'u64'],
+        ['amount', 'u64'],
+        ['fee', 'u64'],
+        ['tip', 'u64'],
+        ['slot', 'u64'],
+      ],
+    },
+  ],
+]);
+
+const GsnInfoSchema = new Map([
+  ...EntrySchema,
+  [
+    GsnInfo,
+    {
+      kind: 'struct',
+      fields: [
+        ['version', 'u8'],
+        ['config', [32]],
+        ['consumer', [KeyEntry]],
+        ['executor', [KeyEntry]],
+        ['consumer_nonces', [KeyEntry]],
+        ['transaction_executor', [ReceiptEntry]],
+        ['lane_nonces', [LaneEntry]],
+        ['token_consumer', [TokenBalanceEntry]],
+        ['token_executor', [TokenBalanceEntry]],
+        ['eth_consumers', [EthConsumerEntry]],
+        ['spending_limits', [SpendingLimitEntry]],
+        ['paused', 'u8'],
+        ['registered_executors', [TokenEntry]],
+        ['executor_stakes', [ExecutorStakeEntry]],
+        ['treasury', 'u64'],
+        ['sponsor_usage', [SponsorUsageEntry]],
+        ['free_txs', [KeyEntry]],
+        ['epoch_volumes', [EpochVolumeEntry]],
+        ['payout_addresses', [PayoutAddressEntry]],
+        ['nonce_windows', [NonceWindowEntry]],
+        ['channel_nonces', [ChannelNonceEntry]],
+        ['recent_payloads', RecentPayloads],
+        ['ledger_accounts', [LedgerAccountEntry]],
+        ['withdrawal_policy', WithdrawalPolicy],
+        ['withdrawal_requests', [WithdrawalRequestEntry]],
+        ['escrowed_fees', [TokenBalanceEntry]],
+        ['in_flight', 'u8'],
+        ['rate_limit', RateLimit],
+        ['rate_windows', [RateWindowEntry]],
+        ['blacklist', [TokenEntry]],
+        ['circuit_breaker', CircuitBreaker],
+        ['stats', Stats],
+        ['rewarded_epoch', { kind: 'option', type: 'u64' }],
+        ['insurance_pool', 'u64'],
+        ['failure_reports', [FailureReportEntry]],
+        ['compensations', [CompensationEntry]],
+        ['eth_registration_nonces', [EthRegistrationNonceEntry]],
+      ],
+    },
+  ],
+]);
+
+const GsnConfigSchema = new Map([
   [
-    GsnInfo,
+    GsnConfig,
+    {
+      kind: 'struct',
+      fields: [
+        ['is_initialized', 'u8'],
+        ['gsn_account', [32]],
+        ['governance', { kind: 'option', type: GovernanceConfig }],
+        ['metrics', { kind: 'option', type: [32] }],
+        ['credential_mint', { kind: 'option', type: [32] }],
+        ['sandbox', { kind: 'option', type: SandboxConfig }],
+        ['shutdown', { kind: 'option', type: ShutdownState }],
+        ['allowed_programs', [TokenEntry]],
+        ['multisig', { kind: 'option', type: MultisigConfig }],
+        ['pending_fee_params', { kind: 'option', type: PendingFeeParams }],
+        ['registered_executors_only', 'u8'],
+        ['min_executor_stake', 'u64'],
+        ['protocol_fee_bps', 'u16'],
+        ['free_tier', { kind: 'option', type: FreeTier }],
+        ['volume_discounts', [VolumeDiscountTier]],
+        ['genesis_hash', { kind: 'option', type: [32] }],
+        ['record_retention', 'u16'],
+        ['price_feeds', [PriceFeedEntry]],
+        ['insurance_fee_bps', 'u16'],
+      ],
+    },
+  ],
+  [
+    PriceFeedEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['mint', [32]],
+        ['feed', [32]],
+        ['decimals', 'u8'],
+        ['max_age_slots', 'u64'],
+        ['max_confidence_bps', 'u16'],
+        ['kind', 'u8'],
+      ],
+    },
+  ],
+  [
+    VolumeDiscountTier,
+    {
+      kind: 'struct',
+      fields: [
+        ['min_txs', 'u64'],
+        ['discount_bps', 'u16'],
+      ],
+    },
+  ],
+  [
+    FreeTier,
+    {
+      kind: 'struct',
+      fields: [
+        ['free_tx_quota', 'u64'],
+        ['subsidy', [32]],
+      ],
+    },
+  ],
+  [
+    PendingFeeParams,
+    {
+      kind: 'struct',
+      fields: [
+        ['fee_mode', FeeMode],
+        ['activation_slot', 'u64'],
+      ],
+    },
+  ],
+  [
+    MultisigConfig,
+    {
+      kind: 'struct',
+      fields: [
+        ['signers', [[32]]],
+        ['threshold', 'u8'],
+      ],
+    },
+  ],
+  [
+    ShutdownState,
+    {
+      kind: 'struct',
+      fields: [
+        ['recovery', [32]],
+        // i64 on-chain; borsh 0.7 has no signed integers and timestamps are positive
+        ['effective_at', 'u64'],
+        ['sweep_at', 'u64'],
+      ],
+    },
+  ],
+  [
+    SandboxConfig,
+    {
+      kind: 'struct',
+      fields: [
+        ['subsidy', { kind: 'option', type: [32] }],
+        ['reset_epoch', 'u64'],
+      ],
+    },
+  ],
+  [
+    GovernanceConfig,
+    {
+      kind: 'struct',
+      fields: [
+        ['authority', [32]],
+        ['fee_mode', FeeMode],
+        ['allowed_tokens', [TokenEntry]],
+      ],
+    },
+  ],
+  [
+    FeeMode,
+    {
+      kind: 'enum',
+      field: 'enum',
+      values: [
+        ['fixed', 'u64'],
+        ['percent', 'u16'],
+        ['compute_based', ComputeBasedFee],
+        ['percent_bounded', BoundedPercentFee],
+      ],
+    },
+  ],
+  [
+    BoundedPercentFee,
+    {
+      kind: 'struct',
+      fields: [
+        ['basis_points', 'u16'],
+        ['min_fee', { kind: 'option', type: 'u64' }],
+        ['max_fee', { kind: 'option', type: 'u64' }],
+      ],
+    },
+  ],
+  [
+    ComputeBasedFee,
+    {
+      kind: 'struct',
+      fields: [
+        ['price_per_cu', 'u64'],
+        ['base', 'u64'],
+        ['max_compute_units', 'u64'],
+      ],
+    },
+  ],
+  [
+    TokenEntry,
+    {
+      kind: 'struct',
+      fields: [
+        ['key', [32]],
+        ['allowed', 'u8'],
+      ],
+    },
+  ],
+]);
+
+const ProtocolMetricsSchema = new Map([
+  ...EntrySchema,
+  [
+    EpochMetrics,
+    {
+      kind: 'struct',
+      fields: [
+        ['epoch', 'u64'],
+        ['executions', 'u64'],
+        ['total_fees', 'u64'],
+        ['fees_by_token', [KeyEntry]],
+        ['executions_by_program', [KeyEntry]],
+        ['executor_fees', [KeyEntry]],
+      ],
+    },
+  ],
+  [
+    ProtocolMetrics,
     {
       kind: 'struct',
       fields: [
         ['is_initialized', 'u8'],
-        ['consumer', { kind: 'map', key: 'string', value: 'u64' }],
-        ['executor', { kind: 'map', key: 'string', value: 'u64' }],
-        ['governance', { kind: 'option', type: 'object' }],
-        ['consumer_nonces', { kind: 'map', key: 'string', value: 'u64' }],
-        ['transaction_executor', { kind: 'map', key: 'string', value: 'string' }],
+        ['gsn_account', [32]],
+        ['current', EpochMetrics],
+        ['previous', { kind: 'option', type: EpochMetrics }],
       ],
     },
   ],
@@ -48,8 +1292,15 @@ export async function getGsnState(
     throw new Error('GSN account not found');
   }
 
+  return decodeGsnState(accountInfo.data);
+}
+
+/**
+ * Decode raw GSN account data
+ */
+export function decodeGsnState(accountData: Buffer | Uint8Array): GsnInfo {
   // Convert account data to Uint8Array if needed
-  let data = accountInfo.data;
+  let data = accountData;
   if (Buffer.isBuffer(data)) {
     data = new Uint8Array(data);
   }
@@ -64,6 +1315,54 @@ export async function getGsnState(
   }
 }
 
+/**
+ * Get the configuration account of a GSN deployment
+ */
+export async function getGsnConfig(
+  connection: Connection,
+  configAccountPubkey: PublicKey,
+): Promise<GsnConfig> {
+  const accountInfo = await connection.getAccountInfo(configAccountPubkey);
+
+  if (!accountInfo) {
+    throw new Error('GSN config account not found');
+  }
+
+  return decodeGsnConfig(accountInfo.data);
+}
+
+/**
+ * Length of the discriminator the data of every program account but the
+ * state account starts with
+ */
+export const DISCRIMINATOR_LEN = 8;
+
+/**
+ * Data of an account past its discriminator, which must be `discriminator`
+ */
+function accountBody(
+  accountData: Buffer | Uint8Array,
+  discriminator: string,
+): Buffer {
+  const data = Buffer.from(accountData);
+  if (data.slice(0, DISCRIMINATOR_LEN).toString('latin1') !== discriminator) {
+    throw new Error(`Not a ${discriminator} account`);
+  }
+  return data.slice(DISCRIMINATOR_LEN);
+}
+
+/**
+ * Decode raw GSN config account data
+ */
+export function decodeGsnConfig(accountData: Buffer | Uint8Array): GsnConfig {
+  // The account is allocated larger than the encoded config, ignore the tail
+  return deserializeUnchecked(
+    GsnConfigSchema,
+    GsnConfig,
+    accountBody(accountData, 'GSNCONFG'),
+  );
+}
+
 /**
  * Get consumer balance from GSN state
  */
@@ -74,6 +1373,14 @@ export async function getConsumerBalance(
 ): Promise<BN> {
   const gsnInfo = await getGsnState(connection, gsnAccountPubkey);
   const consumerKey = consumerPubkey.toBase58();
+  if (hasLedgerAccount(gsnInfo, consumerKey, CONSUMER_LEDGER)) {
+    const account = await getConsumerAccount(
+      connection,
+      gsnAccountPubkey,
+      consumerPubkey,
+    );
+    return new BN(account.balance.toString());
+  }
   const balance = gsnInfo.consumer.get(consumerKey);
   return balance ? new BN(balance.toString()) : new BN(0);
 }
@@ -88,20 +1395,386 @@ export async function getExecutorEarnings(
 ): Promise<BN> {
   const gsnInfo = await getGsnState(connection, gsnAccountPubkey);
   const executorKey = executorPubkey.toBase58();
+  if (hasLedgerAccount(gsnInfo, executorKey, EXECUTOR_LEDGER)) {
+    const account = await getExecutorAccount(
+      connection,
+      gsnAccountPubkey,
+      executorPubkey,
+    );
+    return new BN(account.earned.toString());
+  }
   const earnings = gsnInfo.executor.get(executorKey);
   return earnings ? new BN(earnings.toString()) : new BN(0);
 }
 
 /**
- * Get next nonce for a consumer
+ * Mint standing for native SOL in per-mint balances (the all-zero key)
+ */
+export const NATIVE_MINT = '11111111111111111111111111111111';
+
+/**
+ * Every balance of a consumer, keyed by base58 mint: lamports under
+ * `NATIVE_MINT` first if it has a lamport balance, then its token balances
+ */
+export async function getConsumerBalances(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+): Promise<Map<string, BN>> {
+  const gsnInfo = await getGsnState(connection, gsnAccountPubkey);
+  const consumerKey = consumerPubkey.toBase58();
+  const balances = new Map();
+  if (
+    gsnInfo.consumer.has(consumerKey) ||
+    hasLedgerAccount(gsnInfo, consumerKey, CONSUMER_LEDGER)
+  ) {
+    balances.set(
+      NATIVE_MINT,
+      await getConsumerBalance(connection, gsnAccountPubkey, consumerPubkey),
+    );
+  }
+  return mintBalances(balances, gsnInfo.token_consumer, consumerKey);
+}
+
+/**
+ * Every balance an executor earned, keyed by base58 mint, like
+ * `getConsumerBalances`
+ */
+export async function getExecutorBalances(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  executorPubkey: PublicKey,
+): Promise<Map<string, BN>> {
+  const gsnInfo = await getGsnState(connection, gsnAccountPubkey);
+  const executorKey = executorPubkey.toBase58();
+  const balances = new Map();
+  if (
+    gsnInfo.executor.has(executorKey) ||
+    hasLedgerAccount(gsnInfo, executorKey, EXECUTOR_LEDGER)
+  ) {
+    balances.set(
+      NATIVE_MINT,
+      await getExecutorEarnings(connection, gsnAccountPubkey, executorPubkey),
+    );
+  }
+  return mintBalances(balances, gsnInfo.token_executor, executorKey);
+}
+
+/**
+ * Add the token balances of `owner` in `tokenBalances`, keyed `owner:mint`,
+ * to `balances`
+ */
+function mintBalances(
+  balances: Map<string, BN>,
+  tokenBalances: Map<string, BN>,
+  owner: string,
+): Map<string, BN> {
+  for (const [key, value] of tokenBalances) {
+    const [tokenOwner, mint] = key.split(':');
+    if (tokenOwner === owner) {
+      balances.set(mint, new BN(value.toString()));
+    }
+  }
+  return balances;
+}
+
+/**
+ * Get next nonce for a consumer, in nonce lane `lane` (0 is the default lane)
  */
 export async function getConsumerNonce(
   connection: Connection,
   gsnAccountPubkey: PublicKey,
   consumerPubkey: PublicKey,
+  lane: number = 0,
 ): Promise<number> {
   const gsnInfo = await getGsnState(connection, gsnAccountPubkey);
   const consumerKey = consumerPubkey.toBase58();
-  const nonce = gsnInfo.consumer_nonces.get(consumerKey);
+  if (lane === 0 && hasLedgerAccount(gsnInfo, consumerKey, CONSUMER_LEDGER)) {
+    const account = await getConsumerAccount(
+      connection,
+      gsnAccountPubkey,
+      consumerPubkey,
+    );
+    return account.nonce.toNumber();
+  }
+  const nonce =
+    lane === 0
+      ? gsnInfo.consumer_nonces.get(consumerKey)
+      : gsnInfo.lane_nonces.get(`${consumerKey}:${lane}`);
+  return nonce ? nonce.toNumber() : 0;
+}
+
+/**
+ * Nonce lane whose nonces count up per (consumer, executor) channel
+ */
+export const CHANNEL_NONCE_LANE = '18446744073709551614';
+
+/**
+ * Get next nonce of the channel between a consumer and an executor, for
+ * submissions in `CHANNEL_NONCE_LANE` that `executorPubkey` pays for
+ */
+export async function getChannelNonce(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+  executorPubkey: PublicKey,
+): Promise<number> {
+  const gsnInfo = await getGsnState(connection, gsnAccountPubkey);
+  const nonce = gsnInfo.channel_nonces.get(
+    `${consumerPubkey.toBase58()}:${executorPubkey.toBase58()}`,
+  );
   return nonce ? nonce.toNumber() : 0;
 }
+
+/**
+ * Get the per-epoch protocol metrics registered for a GSN account
+ */
+export async function getProtocolMetrics(
+  connection: Connection,
+  metricsAccountPubkey: PublicKey,
+): Promise<ProtocolMetrics> {
+  const accountInfo = await connection.getAccountInfo(metricsAccountPubkey);
+
+  if (!accountInfo) {
+    throw new Error('Metrics account not found');
+  }
+
+  // The account is allocated larger than the encoded metrics, ignore the tail
+  return deserializeUnchecked(
+    ProtocolMetricsSchema,
+    ProtocolMetrics,
+    accountBody(accountInfo.data, 'GSNMETRC'),
+  );
+}
+
+/**
+ * Top executors of an epoch by fees earned, highest first
+ */
+export function topExecutors(
+  epochMetrics: EpochMetrics,
+  limit: number = 10,
+): Array<{executor: string, fees: BN}> {
+  return [...epochMetrics.executor_fees.entries()]
+    .map(([executor, fees]) => ({executor, fees: new BN(fees.toString())}))
+    .sort((a, b) => b.fees.cmp(a.fees))
+    .slice(0, limit);
+}
+
+/**
+ * Address of the vault holding the lamports that back a GSN state account's
+ * balances: top-ups move into it, claims and withdrawals are paid out of it
+ */
+export async function findVaultAddress(
+  programId: PublicKey,
+  gsnAccountPubkey: PublicKey,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [Buffer.from('vault'), gsnAccountPubkey.toBuffer()],
+    programId,
+  );
+  return address;
+}
+
+/**
+ * Address of the system account holding an executor's stake
+ */
+export async function findStakeAddress(
+  programId: PublicKey,
+  gsnAccountPubkey: PublicKey,
+  executor: PublicKey,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [Buffer.from('stake'), gsnAccountPubkey.toBuffer(), executor.toBuffer()],
+    programId,
+  );
+  return address;
+}
+
+/**
+ * Address of the fee escrow of a submission settled with `SubmitEscrowed`
+ */
+export async function findFeeEscrowAddress(
+  programId: PublicKey,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+  lane: number,
+  nonce: number,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [
+      Buffer.from('escrow'),
+      gsnAccountPubkey.toBuffer(),
+      consumerPubkey.toBuffer(),
+      new BN(lane).toArrayLike(Buffer, 'le', 8),
+      new BN(nonce).toArrayLike(Buffer, 'le', 8),
+    ],
+    programId,
+  );
+  return address;
+}
+
+/**
+ * Get an open fee escrow, `null` once it has been released or refunded
+ */
+export async function getFeeEscrow(
+  connection: Connection,
+  escrowPubkey: PublicKey,
+): Promise<?FeeEscrow> {
+  const accountInfo = await connection.getAccountInfo(escrowPubkey);
+  if (!accountInfo || accountInfo.data.every(byte => byte === 0)) {
+    return null;
+  }
+  return borshDeserialize(
+    FeeEscrowSchema,
+    FeeEscrow,
+    accountBody(accountInfo.data, 'GSNESCRW'),
+  );
+}
+
+/**
+ * Address of the receipt of a submission, appended to its accounts to have
+ * the executor write it
+ */
+export async function findExecutionReceiptAddress(
+  programId: PublicKey,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+  lane: number,
+  nonce: number,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [
+      Buffer.from('receipt'),
+      gsnAccountPubkey.toBuffer(),
+      consumerPubkey.toBuffer(),
+      new BN(lane).toArrayLike(Buffer, 'le', 8),
+      new BN(nonce).toArrayLike(Buffer, 'le', 8),
+    ],
+    programId,
+  );
+  return address;
+}
+
+/**
+ * Get the receipt of a submission, `null` if none was written
+ */
+export async function getExecutionReceipt(
+  connection: Connection,
+  receiptPubkey: PublicKey,
+): Promise<?ExecutionReceipt> {
+  const accountInfo = await connection.getAccountInfo(receiptPubkey);
+  if (!accountInfo) {
+    return null;
+  }
+  return borshDeserialize(
+    ExecutionReceiptSchema,
+    ExecutionReceipt,
+    accountBody(accountInfo.data, 'GSNRECPT'),
+  );
+}
+
+function hasLedgerAccount(
+  gsnInfo: GsnInfo,
+  key: string,
+  flag: number,
+): boolean {
+  return ((gsnInfo.ledger_accounts.get(key) || 0) & flag) !== 0;
+}
+
+/**
+ * Address of the ledger account holding a consumer's balance and lane 0 nonce
+ */
+export async function findConsumerAccountAddress(
+  programId: PublicKey,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [
+      Buffer.from('consumer'),
+      gsnAccountPubkey.toBuffer(),
+      consumerPubkey.toBuffer(),
+    ],
+    programId,
+  );
+  return address;
+}
+
+/**
+ * Address of the ledger account holding an executor's earnings
+ */
+export async function findExecutorAccountAddress(
+  programId: PublicKey,
+  gsnAccountPubkey: PublicKey,
+  executorPubkey: PublicKey,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [
+      Buffer.from('executor'),
+      gsnAccountPubkey.toBuffer(),
+      executorPubkey.toBuffer(),
+    ],
+    programId,
+  );
+  return address;
+}
+
+async function getLedgerAccount(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  findAddress: (PublicKey, PublicKey, PublicKey) => Promise<PublicKey>,
+  key: PublicKey,
+  type: Class<any>,
+  discriminator: string,
+): Promise<any> {
+  const gsnAccount = await connection.getAccountInfo(gsnAccountPubkey);
+  if (!gsnAccount) {
+    throw new Error('GSN account not found');
+  }
+  const address = await findAddress(gsnAccount.owner, gsnAccountPubkey, key);
+  const accountInfo = await connection.getAccountInfo(address);
+  if (!accountInfo) {
+    throw new Error('Ledger account not found');
+  }
+  return borshDeserialize(
+    LedgerAccountSchema,
+    type,
+    accountBody(accountInfo.data, discriminator),
+  );
+}
+
+/**
+ * Get the ledger account of a consumer that opened one
+ */
+export function getConsumerAccount(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+): Promise<ConsumerAccount> {
+  return getLedgerAccount(
+    connection,
+    gsnAccountPubkey,
+    findConsumerAccountAddress,
+    consumerPubkey,
+    ConsumerAccount,
+    'GSNCONSM',
+  );
+}
+
+/**
+ * Get the ledger account of an executor that opened one
+ */
+export function getExecutorAccount(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  executorPubkey: PublicKey,
+): Promise<ExecutorAccount> {
+  return getLedgerAccount(
+    connection,
+    gsnAccountPubkey,
+    findExecutorAccountAddress,
+    executorPubkey,
+    ExecutorAccount,
+    'GSNEXECU',
+  );
+}
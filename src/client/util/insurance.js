@@ -0,0 +1,74 @@
+// @flow
+
+import {SystemProgram, TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_INSURANCE_FEE = 79;
+const COMPENSATE = 80;
+
+/**
+ * Highest insurance fee governance can set, in basis points
+ */
+export const MAX_INSURANCE_FEE_BPS = 1000;
+
+/**
+ * Encode a `SetInsuranceFee` keeping `insuranceFeeBps` of every lamport fee
+ * for the insurance pool. The governance authority signs it.
+ */
+export function setInsuranceFeeInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  insuranceFeeBps: number,
+): TransactionInstruction {
+  const data = Buffer.alloc(3);
+  data.writeUInt8(SET_INSURANCE_FEE, 0);
+  data.writeUInt16LE(insuranceFeeBps, 1);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
+
+/**
+ * Encode a `Compensate` paying `amount` lamports of the insurance pool out of
+ * the vault to `consumer`, for its faulty execution of `nonce` on `lane`.
+ * A channel lane execution also names the channel's `channelExecutor`. The
+ * governance authority signs it.
+ */
+export function compensateInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  consumer: PublicKey,
+  vault: PublicKey,
+  amount: BN | number,
+  lane: BN | number,
+  nonce: BN | number,
+  channelExecutor: ?PublicKey = null,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+      {pubkey: consumer, isSigner: false, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([COMPENSATE]),
+      ...[amount, lane, nonce].map(value =>
+        new BN(value.toString()).toArrayLike(Buffer, 'le', 8),
+      ),
+      channelExecutor ? channelExecutor.toBuffer() : Buffer.alloc(0),
+    ]),
+  });
+}
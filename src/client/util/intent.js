@@ -0,0 +1,175 @@
+// @flow
+
+import {
+  Ed25519Program,
+  PublicKey,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  TransactionInstruction,
+} from '@solana/web3.js';
+import type {Account, Connection} from '@solana/web3.js';
+import BN from 'bn.js';
+import {createHash} from 'crypto';
+import nacl from 'tweetnacl';
+
+const SUBMIT_INTENT = 26;
+const SET_GENESIS_HASH = 56;
+
+/**
+ * Prefix of every intent message (`INTENT_DOMAIN`)
+ */
+const INTENT_DOMAIN = Buffer.from('solgsn-intent');
+
+/**
+ * Transfer a consumer approves for relaying
+ */
+export type ConsumerIntent = {
+  // See `domainSeparator`
+  domainSeparator: Buffer,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  targetProgram: PublicKey,
+  receiver: PublicKey,
+  amount: BN | number,
+  nonce: BN | number,
+  lane: number,
+};
+
+/**
+ * An intent with the consumer's signature, handed to the relayer
+ */
+export type SignedIntent = {
+  intent: ConsumerIntent,
+  signature: Buffer,
+};
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Domain separator of the intents of `programId` on the cluster with
+ * `genesisHash`, as built by `domain_separator`. Pass the genesis hash set
+ * in the config account, or null when it isn't set.
+ */
+export function domainSeparator(
+  programId: PublicKey,
+  genesisHash: ?Buffer,
+): Buffer {
+  return createHash('sha256')
+    .update(INTENT_DOMAIN)
+    .update(programId.toBuffer())
+    .update(genesisHash || Buffer.alloc(32))
+    .digest();
+}
+
+/**
+ * Genesis hash of the cluster `connection` talks to, as raw bytes
+ */
+export async function clusterGenesisHash(
+  connection: Connection,
+): Promise<Buffer> {
+  // Base58 like a public key, and 32 bytes long as well
+  return new PublicKey(await connection.getGenesisHash()).toBuffer();
+}
+
+/**
+ * Encode a `SetGenesisHash` binding intents to the cluster with
+ * `genesisHash`, so intents signed for other clusters stop verifying. The
+ * governance authority signs it.
+ */
+export function setGenesisHashInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  genesisHash: Buffer,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([Buffer.from([SET_GENESIS_HASH]), genesisHash]),
+  });
+}
+
+/**
+ * Message the consumer signs, as built by `ConsumerIntent::message`
+ */
+export function intentMessage(intent: ConsumerIntent): Buffer {
+  return Buffer.concat([
+    INTENT_DOMAIN,
+    intent.domainSeparator,
+    intent.gsnAccount.toBuffer(),
+    intent.consumer.toBuffer(),
+    intent.targetProgram.toBuffer(),
+    intent.receiver.toBuffer(),
+    u64Bytes(intent.amount),
+    u64Bytes(intent.nonce),
+    u64Bytes(intent.lane),
+  ]);
+}
+
+/**
+ * Sign `intent` with the consumer's key, off-chain
+ */
+export function signIntent(consumer: Account, intent: ConsumerIntent): SignedIntent {
+  if (!consumer.publicKey.equals(intent.consumer)) {
+    throw new Error('The intent must be signed by its consumer');
+  }
+  const signature = nacl.sign.detached(intentMessage(intent), consumer.secretKey);
+  return {intent, signature: Buffer.from(signature)};
+}
+
+/**
+ * Instructions relaying a signed intent: the Ed25519 program instruction
+ * verifying the consumer's signature, then `SubmitIntent`. The consumer,
+ * whose lamports are transferred, and the fee payer sign the transaction.
+ */
+export function submitIntentInstructions(
+  programId: PublicKey,
+  feePayer: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  signed: SignedIntent,
+): Array<TransactionInstruction> {
+  const {intent} = signed;
+  const keys = [
+    {pubkey: intent.targetProgram, isSigner: false, isWritable: true},
+    {pubkey: intent.consumer, isSigner: true, isWritable: true},
+    {pubkey: intent.receiver, isSigner: false, isWritable: true},
+    {pubkey: feePayer, isSigner: true, isWritable: true},
+    {pubkey: intent.gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+  ];
+  if (metricsAccount) {
+    keys.push({pubkey: metricsAccount, isSigner: false, isWritable: true});
+  }
+  if (credential) {
+    keys.push({pubkey: credential, isSigner: false, isWritable: false});
+  }
+  keys.push({
+    pubkey: SYSVAR_INSTRUCTIONS_PUBKEY,
+    isSigner: false,
+    isWritable: false,
+  });
+
+  return [
+    Ed25519Program.createInstructionWithPublicKey({
+      publicKey: intent.consumer.toBytes(),
+      message: intentMessage(intent),
+      signature: signed.signature,
+    }),
+    new TransactionInstruction({
+      keys,
+      programId,
+      data: Buffer.concat([
+        Buffer.from([SUBMIT_INTENT]),
+        u64Bytes(intent.amount),
+        u64Bytes(intent.nonce),
+        u64Bytes(intent.lane),
+      ]),
+    }),
+  ];
+}
@@ -0,0 +1,127 @@
+// @flow
+
+import {SystemProgram, TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+import {
+  findConsumerAccountAddress,
+  findExecutorAccountAddress,
+} from './gsn-state';
+
+const OPEN_CONSUMER_ACCOUNT = 57;
+const OPEN_EXECUTOR_ACCOUNT = 58;
+const CLOSE_CONSUMER = 65;
+
+async function openLedgerAccountInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  owner: PublicKey,
+  ledgerAccount: PublicKey,
+  instruction: number,
+): Promise<TransactionInstruction> {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: owner, isSigner: true, isWritable: true},
+      {pubkey: ledgerAccount, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([instruction]),
+  });
+}
+
+/**
+ * Encode an `OpenConsumerAccount`, moving the consumer's balance and lane 0
+ * nonce out of the GSN state into its own ledger account. The consumer signs
+ * and pays the account's rent.
+ */
+export async function openConsumerAccountInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+): Promise<TransactionInstruction> {
+  return openLedgerAccountInstruction(
+    programId,
+    gsnAccount,
+    consumer,
+    await findConsumerAccountAddress(programId, gsnAccount, consumer),
+    OPEN_CONSUMER_ACCOUNT,
+  );
+}
+
+/**
+ * Encode an `OpenExecutorAccount`, moving the executor's earnings out of the
+ * GSN state into its own ledger account. The executor signs and pays the
+ * account's rent.
+ */
+export async function openExecutorAccountInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+): Promise<TransactionInstruction> {
+  return openLedgerAccountInstruction(
+    programId,
+    gsnAccount,
+    executor,
+    await findExecutorAccountAddress(programId, gsnAccount, executor),
+    OPEN_EXECUTOR_ACCOUNT,
+  );
+}
+
+/**
+ * Encode a `CloseConsumer`, removing a consumer whose balances are all empty
+ * from the GSN state. Its ledger account, if it opened one, is closed and the
+ * rent refunded to the consumer, who signs. Its nonces stay.
+ */
+export async function closeConsumerInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+): Promise<TransactionInstruction> {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: consumer, isSigner: true, isWritable: true},
+      {
+        pubkey: await findConsumerAccountAddress(programId, gsnAccount, consumer),
+        isSigner: false,
+        isWritable: true,
+      },
+    ],
+    programId,
+    data: Buffer.from([CLOSE_CONSUMER]),
+  });
+}
+
+/**
+ * Append the ledger accounts of the consumers and executors an instruction
+ * touches. Once one has opened its account, every instruction that reads or
+ * changes its balance, nonce or earnings has to carry it.
+ */
+export async function withLedgerAccounts(
+  instruction: TransactionInstruction,
+  gsnAccount: PublicKey,
+  {consumers = [], executors = []}: {
+    consumers?: Array<PublicKey>,
+    executors?: Array<PublicKey>,
+  },
+): Promise<TransactionInstruction> {
+  const {programId} = instruction;
+  const addresses = [
+    ...(await Promise.all(
+      consumers.map(key =>
+        findConsumerAccountAddress(programId, gsnAccount, key),
+      ),
+    )),
+    ...(await Promise.all(
+      executors.map(key =>
+        findExecutorAccountAddress(programId, gsnAccount, key),
+      ),
+    )),
+  ];
+  instruction.keys.push(
+    ...addresses.map(pubkey => ({pubkey, isSigner: false, isWritable: true})),
+  );
+  return instruction;
+}
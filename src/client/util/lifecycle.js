@@ -0,0 +1,185 @@
+// @flow
+
+import {Connection, PublicKey} from '@solana/web3.js';
+
+import {getGsnState} from './gsn-state';
+import {Store} from './store';
+
+/**
+ * Lifecycle of a relayed meta-transaction
+ *
+ *   queued -> submitted -> confirmed -> finalized -> settled
+ *      \         \              \           \
+ *       +---------+--> failed    +-----------+--> disputed -> settled
+ *
+ * Steps along the main path may be skipped (e.g. a transaction first seen
+ * once it is already finalized), but a transaction never moves backwards.
+ */
+export const TxStatus = {
+  QUEUED: 'queued',
+  SUBMITTED: 'submitted',
+  CONFIRMED: 'confirmed',
+  FINALIZED: 'finalized',
+  DISPUTED: 'disputed',
+  SETTLED: 'settled',
+  FAILED: 'failed',
+};
+
+const MAIN_PATH = [
+  TxStatus.QUEUED,
+  TxStatus.SUBMITTED,
+  TxStatus.CONFIRMED,
+  TxStatus.FINALIZED,
+  TxStatus.SETTLED,
+];
+
+const SIDE_TRANSITIONS = {
+  [TxStatus.QUEUED]: [TxStatus.FAILED],
+  [TxStatus.SUBMITTED]: [TxStatus.FAILED],
+  [TxStatus.CONFIRMED]: [TxStatus.DISPUTED],
+  [TxStatus.FINALIZED]: [TxStatus.DISPUTED],
+  [TxStatus.DISPUTED]: [TxStatus.SETTLED],
+};
+
+/**
+ * Whether a transaction may move from `from` (null when untracked) to `to`
+ */
+export function canTransition(from: ?string, to: string): boolean {
+  if (!from) {
+    return MAIN_PATH.includes(to) || to === TxStatus.FAILED;
+  }
+  const fromIndex = MAIN_PATH.indexOf(from);
+  const toIndex = MAIN_PATH.indexOf(to);
+  if (fromIndex !== -1 && toIndex > fromIndex) {
+    return true;
+  }
+  return (SIDE_TRANSITIONS[from] || []).includes(to);
+}
+
+function recordKey(consumer: string, nonce: number | string): string {
+  return `${consumer}:${nonce.toString()}`;
+}
+
+/**
+ * Persistent record of relayed transactions keyed by (consumer, nonce)
+ */
+export class LifecycleTracker {
+  store: Store;
+  uri: string;
+  pending: Promise<void> = Promise.resolve();
+
+  constructor(store: Store = new Store(), uri: string = 'lifecycle.json') {
+    this.store = store;
+    this.uri = uri;
+  }
+
+  async loadAll(): Promise<Object> {
+    try {
+      return await this.store.load(this.uri);
+    } catch (err) {
+      return {};
+    }
+  }
+
+  async get(consumer: string, nonce: number | string): Promise<?Object> {
+    await this.pending;
+    const records = await this.loadAll();
+    return records[recordKey(consumer, nonce)] || null;
+  }
+
+  /**
+   * Move a transaction to `status`, merging `details` (signature, executor,
+   * request id, reason...) into its record. Throws on an invalid transition.
+   */
+  transition(
+    consumer: string,
+    nonce: number | string,
+    status: string,
+    details: Object = {},
+  ): Promise<Object> {
+    // Serialize read-modify-write cycles on the backing file
+    const result = this.pending.then(async () => {
+      const records = await this.loadAll();
+      const key = recordKey(consumer, nonce);
+      const current = records[key];
+      const from = current ? current.status : null;
+      if (from === status) {
+        return current;
+      }
+      if (!canTransition(from, status)) {
+        throw new Error(
+          `Invalid lifecycle transition for ${key}: ${String(from)} -> ${status}`,
+        );
+      }
+
+      const record = {
+        ...(current || {consumer, nonce: nonce.toString(), history: []}),
+        ...details,
+        status,
+      };
+      record.history = [...record.history, {status, at: Date.now()}];
+      records[key] = record;
+      await this.store.save(this.uri, records);
+      return record;
+    });
+    this.pending = result.then(
+      () => undefined,
+      () => undefined,
+    );
+    return result;
+  }
+}
+
+/**
+ * Query the status of the transaction a consumer signed with `nonce`.
+ *
+ * Local tracker records are reconciled with on-chain evidence: the executor
+ * receipt in the GSN state proves execution, and the signature status tells
+ * whether the transaction is finalized or failed. Returns `status: null` for
+ * transactions that are neither tracked nor executed. Transactions relayed in
+ * a nonce lane other than 0 are identified by `${lane}:${nonce}`.
+ */
+export async function getTransactionStatus(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+  consumerPubkey: PublicKey,
+  nonce: number | string,
+  tracker: LifecycleTracker = new LifecycleTracker(),
+): Promise<Object> {
+  const consumer = consumerPubkey.toBase58();
+  const record = await tracker.get(consumer, nonce);
+  const gsnInfo = await getGsnState(connection, gsnAccountPubkey);
+  const executor = gsnInfo.transaction_executor.get(recordKey(consumer, nonce));
+
+  let status = record ? record.status : null;
+  let observed = executor ? TxStatus.CONFIRMED : null;
+
+  if (record && record.signature) {
+    const {value} = await connection.getSignatureStatus(record.signature);
+    if (value && value.err) {
+      observed = TxStatus.FAILED;
+    } else if (
+      value &&
+      (value.confirmationStatus === 'finalized' || value.confirmations === null)
+    ) {
+      observed = TxStatus.FINALIZED;
+    } else if (value) {
+      observed = observed || TxStatus.CONFIRMED;
+    }
+  }
+
+  if (observed && canTransition(status, observed)) {
+    const updated = await tracker.transition(consumer, nonce, observed, {
+      ...(executor ? {executor} : {}),
+    });
+    status = updated.status;
+  }
+
+  return {
+    consumer,
+    nonce,
+    status,
+    executor: executor || (record && record.executor) || null,
+    signature: record ? record.signature || null : null,
+  };
+}
@@ -0,0 +1,51 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const SET_MULTISIG = 35;
+
+/**
+ * Encode a `SetMultisig` handing governance to `threshold` of `signers`.
+ * It needs the approval of the current governance: the authority, or the
+ * threshold of the current multisig passed as `approvers`. A threshold of
+ * 0 with no signers hands governance back to the authority.
+ */
+export function setMultisigInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  threshold: number,
+  signers: Array<PublicKey>,
+  approvers: Array<PublicKey> = [],
+): TransactionInstruction {
+  return withMultisigSigners(
+    new TransactionInstruction({
+      keys: [
+        {pubkey: configAccount, isSigner: false, isWritable: true},
+        {pubkey: authority, isSigner: true, isWritable: false},
+      ],
+      programId,
+      data: Buffer.concat([
+        Buffer.from([SET_MULTISIG, threshold, signers.length]),
+        ...signers.map(signer => signer.toBuffer()),
+      ]),
+    }),
+    approvers,
+  );
+}
+
+/**
+ * Append multisig signers to a governance instruction. The program counts
+ * every signing account, so the signer in the authority slot can be one of
+ * them.
+ */
+export function withMultisigSigners(
+  instruction: TransactionInstruction,
+  signers: Array<PublicKey>,
+): TransactionInstruction {
+  signers.forEach(pubkey =>
+    instruction.keys.push({pubkey, isSigner: true, isWritable: false}),
+  );
+  return instruction;
+}
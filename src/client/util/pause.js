@@ -0,0 +1,50 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const PAUSE = 33;
+const UNPAUSE = 34;
+
+function setPausedInstruction(
+  tag: number,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([tag]),
+  });
+}
+
+/**
+ * Encode a `Pause`, halting top-ups, submissions, withdrawals and fee claims
+ * until `Unpause`. The governance authority signs it.
+ */
+export function pauseInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+): TransactionInstruction {
+  return setPausedInstruction(PAUSE, programId, gsnAccount, configAccount, authority);
+}
+
+/**
+ * Encode an `Unpause`, resuming a paused deployment
+ */
+export function unpauseInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+): TransactionInstruction {
+  return setPausedInstruction(UNPAUSE, programId, gsnAccount, configAccount, authority);
+}
@@ -0,0 +1,33 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const SET_PAYLOAD_RETENTION = 55;
+
+/**
+ * Encode a `SetPayloadRetention` retaining the hashes of the last
+ * `retention` executed payloads, at most 256, so exact duplicates of them
+ * are rejected. A retention of 0 turns the check off. The governance
+ * authority signs it.
+ */
+export function setPayloadRetentionInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  retention: number,
+): TransactionInstruction {
+  const data = Buffer.alloc(3);
+  data.writeUInt8(SET_PAYLOAD_RETENTION, 0);
+  data.writeUInt16LE(retention, 1);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
@@ -0,0 +1,33 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const SET_PAYOUT_ADDRESS = 53;
+
+/**
+ * Encode a `SetPayoutAddress` letting `executor`'s fees be claimed to
+ * `payout`, signed by the executor. Replacing a registered payout address
+ * takes its signature too, pass it as `currentPayout`.
+ */
+export function setPayoutAddressInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  payout: PublicKey,
+  currentPayout: ?PublicKey = null,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: executor, isSigner: true, isWritable: false},
+    {pubkey: payout, isSigner: false, isWritable: false},
+  ];
+  if (currentPayout) {
+    keys.push({pubkey: currentPayout, isSigner: true, isWritable: false});
+  }
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.from([SET_PAYOUT_ADDRESS]),
+  });
+}
@@ -0,0 +1,40 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_RATE_LIMIT = 72;
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Encode a `SetRateLimit` letting every consumer make `maxSubmissions`
+ * submissions per window of `windowSlots` slots. A limit of 0 lifts it. The
+ * governance authority signs it.
+ */
+export function setRateLimitInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  maxSubmissions: BN | number,
+  windowSlots: BN | number,
+): TransactionInstruction {
+  const data = Buffer.concat([
+    Buffer.from([SET_RATE_LIMIT]),
+    u64Bytes(maxSubmissions),
+    u64Bytes(windowSlots),
+  ]);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
@@ -0,0 +1,50 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const SET_RECORD_RETENTION = 63;
+const PRUNE_RECORDS = 64;
+
+/**
+ * Encode a `SetRecordRetention` keeping the executor records of the last
+ * `retention` nonces of every consumer's lane when pruned. A retention of 0
+ * keeps them all. The governance authority signs it.
+ */
+export function setRecordRetentionInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  retention: number,
+): TransactionInstruction {
+  const data = Buffer.alloc(3);
+  data.writeUInt8(SET_RECORD_RETENTION, 0);
+  data.writeUInt16LE(retention, 1);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
+
+/**
+ * Encode a `PruneRecords` dropping the executor records past the retention.
+ * Anyone may send it.
+ */
+export function pruneRecordsInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([PRUNE_RECORDS]),
+  });
+}
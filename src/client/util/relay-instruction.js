@@ -0,0 +1,83 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SUBMIT_INSTRUCTION = 29;
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Encode `instruction` as `SubmitInstructionArgs` carries it: program id,
+ * account metas, then the data
+ */
+export function encodeTargetInstruction(
+  instruction: TransactionInstruction,
+): Buffer {
+  if (instruction.keys.length > 255 || instruction.data.length > 0xffff) {
+    throw new Error('The instruction has too many accounts or too much data');
+  }
+  const dataLength = Buffer.alloc(2);
+  dataLength.writeUInt16LE(instruction.data.length, 0);
+  return Buffer.concat([
+    instruction.programId.toBuffer(),
+    Buffer.from([instruction.keys.length]),
+    ...instruction.keys.map(({pubkey, isSigner, isWritable}) =>
+      Buffer.concat([
+        pubkey.toBuffer(),
+        Buffer.from([isSigner ? 1 : 0, isWritable ? 1 : 0]),
+      ]),
+    ),
+    dataLength,
+    instruction.data,
+  ]);
+}
+
+/**
+ * Encode a `SubmitInstruction` relaying `instruction` for `sender`, which
+ * signs the transaction along with the fee payer. The instruction may not
+ * use the fee payer's signature. The metrics account and the sender's
+ * credential follow when the deployment uses them, then the instruction's
+ * accounts.
+ */
+export function submitInstructionInstruction(
+  programId: PublicKey,
+  sender: PublicKey,
+  feePayer: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  instruction: TransactionInstruction,
+  nonce: BN | number,
+  lane: number = 0,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: instruction.programId, isSigner: false, isWritable: false},
+    {pubkey: sender, isSigner: true, isWritable: true},
+    {pubkey: feePayer, isSigner: true, isWritable: true},
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+  ];
+  if (metricsAccount) {
+    keys.push({pubkey: metricsAccount, isSigner: false, isWritable: true});
+  }
+  if (credential) {
+    keys.push({pubkey: credential, isSigner: false, isWritable: false});
+  }
+  keys.push(...instruction.keys);
+
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SUBMIT_INSTRUCTION]),
+      u64Bytes(nonce),
+      u64Bytes(lane),
+      encodeTargetInstruction(instruction),
+    ]),
+  });
+}
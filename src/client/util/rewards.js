@@ -0,0 +1,43 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {findExecutorAccountAddress} from './gsn-state';
+
+const DISTRIBUTE_REWARDS = 78;
+
+/**
+ * Encode a `DistributeRewards` crediting up to `amount` lamports of the
+ * treasury to the executors of the last epoch the stats counted, weighted by
+ * stake times executions. The ledger accounts of `executors` are passed in
+ * case they opened one. The governance authority signs it.
+ */
+export async function distributeRewardsInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  amount: BN | number,
+  executors: Array<PublicKey>,
+): Promise<TransactionInstruction> {
+  const ledgers = await Promise.all(
+    executors.map(executor =>
+      findExecutorAccountAddress(programId, gsnAccount, executor),
+    ),
+  );
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+      ...ledgers.map(pubkey => ({pubkey, isSigner: false, isWritable: true})),
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([DISTRIBUTE_REWARDS]),
+      new BN(amount.toString()).toArrayLike(Buffer, 'le', 8),
+    ]),
+  });
+}
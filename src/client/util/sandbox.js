@@ -0,0 +1,129 @@
+// @flow
+
+import {Transaction, TransactionInstruction} from '@solana/web3.js';
+import type {Account, Connection, PublicKey} from '@solana/web3.js';
+
+import {getGsnConfig} from './gsn-state';
+import {sendAndConfirmTransaction} from './send-and-confirm-transaction';
+
+const SET_SANDBOX = 17;
+const RESET_SANDBOX = 18;
+
+/**
+ * Genesis hash of devnet, the only cluster sandbox mode may be turned on for
+ */
+export const DEVNET_GENESIS_HASH =
+  'EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG';
+
+/**
+ * Fail unless `connection` is connected to devnet. The program can't read
+ * the genesis hash, so this check, together with the `sandbox` build
+ * feature, is what keeps sandbox mode off other clusters.
+ */
+export async function assertDevnet(connection: Connection): Promise<void> {
+  const genesisHash = await connection.getGenesisHash();
+  if (genesisHash !== DEVNET_GENESIS_HASH) {
+    throw new Error(
+      `Sandbox mode is devnet-only, cluster genesis hash is ${genesisHash}`,
+    );
+  }
+}
+
+/**
+ * Encode a `SetSandbox`: the governance authority turns sandbox mode on,
+ * charging fees to `subsidy`'s balance or waiving them when it is `null`
+ */
+export function setSandboxInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  enabled: boolean,
+  subsidy: ?PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SET_SANDBOX, enabled ? 1 : 0, subsidy ? 1 : 0]),
+      subsidy ? subsidy.toBuffer() : Buffer.alloc(32),
+    ]),
+  });
+}
+
+/**
+ * Encode a `ResetSandbox`, which anyone may send once per epoch
+ */
+export function resetSandboxInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+    ],
+    programId,
+    data: Buffer.from([RESET_SANDBOX]),
+  });
+}
+
+/**
+ * Turn sandbox mode on for a devnet deployment
+ */
+export async function enableSandbox(
+  connection: Connection,
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: Account,
+  subsidy: ?PublicKey = null,
+): Promise<string> {
+  await assertDevnet(connection);
+  return sendAndConfirmTransaction(
+    'setSandbox',
+    connection,
+    new Transaction().add(
+      setSandboxInstruction(
+        programId,
+        configAccount,
+        authority.publicKey,
+        true,
+        subsidy,
+      ),
+    ),
+    authority,
+  );
+}
+
+/**
+ * Reset the sandbox's registries if they weren't reset this epoch yet.
+ * Relayers of a sandbox call it before relaying so registries reset without
+ * anyone having to schedule it; resolves to `null` when nothing was due.
+ */
+export async function ensureSandboxReset(
+  connection: Connection,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  payer: Account,
+): Promise<?string> {
+  const config = await getGsnConfig(connection, configAccount);
+  if (!config.sandbox) {
+    return null;
+  }
+  const {epoch} = await connection.getEpochInfo();
+  if (config.sandbox.reset_epoch.toNumber() >= epoch) {
+    return null;
+  }
+  return sendAndConfirmTransaction(
+    'resetSandbox',
+    connection,
+    new Transaction().add(
+      resetSandboxInstruction(programId, gsnAccount, configAccount),
+    ),
+    payer,
+  );
+}
@@ -1,9 +1,22 @@
 // @flow
 
-import {sendAndConfirmTransaction as realSendAndConfirmTransaction} from '@solana/web3.js';
-import type {Account, Connection, Transaction} from '@solana/web3.js';
+import {TransactionMessage, VersionedTransaction} from '@solana/web3.js';
+import type {
+  Account,
+  Connection,
+  Transaction,
+  TransactionInstruction,
+} from '@solana/web3.js';
 import YAML from 'json-to-pretty-yaml';
 
+import {traced, SpanNames} from './tracing';
+import {
+  buildTransaction,
+  isVersionUnsupported,
+  markLegacyOnly,
+  signPayload,
+} from './versioned-transaction';
+
 type TransactionNotification = (string, string) => void;
 
 let notify: TransactionNotification = () => undefined;
@@ -36,6 +49,78 @@ function parseError(error: Error): string {
         6: 'Replay attack detected: nonce already used',
         7: 'Invalid nonce: expected next nonce',
         8: 'Unauthorized fee claim: only the executor who executed the transaction can claim',
+        9: 'Invalid metrics account: does not match the metrics account registered in the GSN config',
+        10: 'Invalid nonce lane: lane must be below the supported number of lanes',
+        11: 'Invalid config account: does not belong to the GSN state account',
+        12: 'Consumer registry full: the GSN state account has no room for a new consumer',
+        13: 'Execution history full: the GSN state account has no room for another receipt',
+        14: 'Invalid network fee: batch network fee exceeds the signature fees of the batch',
+        15: 'Invalid escrow account: not the fee escrow of this submission or GSN account',
+        16: 'Escrow in dispute window: the fee can only be released once the dispute window has passed',
+        17: 'Escrow dispute window closed: the fee can no longer be disputed',
+        18: 'Invalid commitment: the revealed submission or executor does not match the commitment',
+        19: 'Reveal too early: the reveal delay since the commitment has not passed yet',
+        20: 'Commitment expired: the submission can no longer be revealed',
+        21: 'Missing credential: compliance mode requires a credential of the configured mint held by the consumer',
+        22: 'Invalid fee split: the ratio exceeds 10000 bps or the co-executor is the fee payer',
+        23: 'Sandbox unavailable: the program is not a sandbox build, or sandbox mode is off',
+        24: 'Invalid fee quote: no Ed25519 instruction verifies the executor signature over the quote',
+        25: 'Fee quote expired: the quote expiry slot has passed',
+        26: 'Shut down: the deployment no longer accepts top-ups or submissions',
+        27: 'Sweep unavailable: the deployment is not shut down or its grace period has not passed',
+        28: 'Invalid vault account: the vault is not the one derived from the GSN account',
+        29: 'Token not allowed: the mint is not on the allowed token list',
+        30: 'Invalid token account: wrong mint, owner or token program',
+        31: 'Invalid intent: no Ed25519 instruction verifies the consumer signature over the intent',
+        32: 'Invalid Ethereum signature: no Secp256k1 instruction verifies a signature by an Ethereum address registered to the consumer',
+        33: 'Invalid target instruction: the relayed instruction targets another program, the GSN program, or uses the fee payer signature',
+        34: 'Submission expired: the current slot is past the submission expiry slot',
+        35: 'Limit exceeded: the fee is above the consumer spending limit for the transaction or the epoch',
+        36: 'Program not allowed: the target program is not on the deployment allowlist',
+        37: 'Protocol paused: the governance authority paused top-ups, submissions, withdrawals and fee claims',
+        38: 'Invalid multisig: the threshold must be between 1 and the number of distinct signers (at most 11)',
+        39: 'Fee change timelocked: UpdateFeeParams may only lower fees, propose other changes with ProposeFeeParams',
+        40: 'Timelock not elapsed: the proposed fee parameters are not applicable yet',
+        41: 'No pending fee params: ApplyFeeParams without a proposal',
+        42: 'Executor not registered: this deployment only accepts executors registered by governance',
+        43: 'Executor not bonded: the executor must stake the minimum set by governance and not be unbonding',
+        44: 'Invalid stake account: not the executor\'s stake address',
+        45: 'Stake unbonding: the unbonding period has not passed yet',
+        46: 'Invalid protocol fee: at most 5000 basis points',
+        47: 'Invalid sponsor account: not a sponsor policy of this GSN account',
+        48: 'Sponsor policy violation: the sponsor does not cover this submission',
+        49: 'Invalid compute units: above the fee mode\'s maximum',
+        50: 'Invalid volume discounts: tiers out of order or above 100%',
+        51: 'Fee too high: above the maximum fee the consumer signed',
+        52: 'Invalid tip: only consumers paying their own fee can tip',
+        53: 'Duplicate payload: the same submission executed recently',
+        54: 'Invalid payload retention: at most 256 payloads',
+        55: 'Missing ledger account: pass the consumer or executor account',
+        56: 'Invalid ledger account: not the PDA or not writable',
+        57: 'Unsupported state version: the state account was written by a newer program',
+        58: 'Invalid account type: the account is another kind of program account',
+        59: 'Invalid account owner: the account is not owned by the GSN program',
+        60: 'Account not writable: pass the account as writable',
+        61: 'Invalid system program: pass SystemProgram.programId',
+        62: 'Balance not empty: withdraw every lamport and token balance first',
+        63: 'Withdrawal cooldown: request the withdrawal and wait until it unlocks',
+        64: 'Protocol not paused: pause the deployment first',
+        65: 'Invalid price feed: pass the price account configured for the fee mint',
+        66: 'Price feed stale: wait for the oracle to publish a new price',
+        67: 'Vault token account missing: pass the mint and the accounts that open it',
+        68: 'Swap slippage exceeded: quote a new route or lower the minimum output',
+        69: 'Reentrant call: the target program cannot be SolGSN or call back into it',
+        70: 'Target not executable: pass a deployed program as the target program',
+        71: 'Rate limited: wait for the consumer\'s rate limit window to pass',
+        72: 'Blacklisted: governance barred this consumer or executor',
+        73: 'Rewards already distributed: this epoch\'s executors were rewarded',
+        74: 'No reward recipients: no registered, bonded executor relayed in the epoch',
+        75: 'Invalid insurance fee: above MAX_INSURANCE_FEE_BPS',
+        76: 'Invalid failure report: not the lane\'s next nonce, or reported already',
+        77: 'Unknown execution: no execution recorded for the consumer\'s lane and nonce within the record retention',
+        78: 'Failure report limit reached: the executor reported MAX_FAILURE_REPORTS_PER_EXECUTOR failures this epoch',
+        79: 'Execution already compensated: Compensate paid for it before',
+        80: 'Stale Ethereum registration: sign the address\'s next registration nonce',
       };
       
       if (gsnErrors[errorCode] !== undefined) {
@@ -88,30 +173,124 @@ function parseError(error: Error): string {
   return errorMsg;
 }
 
+/**
+ * Instructions of a versioned transaction, when they can be resolved without
+ * fetching its lookup tables
+ */
+function instructionsOf(
+  transaction: VersionedTransaction,
+): Array<TransactionInstruction> {
+  try {
+    return TransactionMessage.decompile(transaction.message).instructions;
+  } catch (error) {
+    return [];
+  }
+}
+
+function submit(
+  title: string,
+  connection: Connection,
+  transaction: VersionedTransaction,
+): Promise<string> {
+  return traced(
+    SpanNames.SUBMITTED,
+    {'solgsn.title': title, 'solgsn.tx_version': String(transaction.version)},
+    () =>
+      connection.sendTransaction(transaction, {
+        skipPreflight: true,
+      }),
+  );
+}
+
+/**
+ * Send `transaction` and wait for it to be confirmed.
+ *
+ * A legacy `Transaction` is rebuilt as a v0 `VersionedTransaction` (fee payer
+ * defaults to the first signer) and signed by `signers`. If the cluster can't
+ * decode v0 transactions it is rebuilt as a legacy message and the connection
+ * sticks to legacy from then on.
+ *
+ * A `VersionedTransaction` is sent as-is after adding the signatures of
+ * `signers` to those already present, so payloads signed elsewhere can be
+ * countersigned and submitted.
+ */
 export async function sendAndConfirmTransaction(
   title: string,
   connection: Connection,
-  transaction: Transaction,
+  transaction: Transaction | VersionedTransaction,
   ...signers: Array<Account>
-): Promise<void> {
+): Promise<string> {
   const when = Date.now();
 
   try {
-    const signature = await realSendAndConfirmTransaction(
-      connection,
-      transaction,
-      signers,
-      {
-        skipPreflight: true,
-        commitment: 'recent',
-        preflightCommitment: null,
+    let signature;
+    let instructions;
+    let strategy;
+
+    if (transaction instanceof VersionedTransaction) {
+      signature = await submit(
+        title,
+        connection,
+        signPayload(transaction, ...signers),
+      );
+      instructions = instructionsOf(transaction);
+      // The payload doesn't carry its expiry; the latest one bounds it
+      const {lastValidBlockHeight} = await connection.getLatestBlockhash();
+      strategy = {
+        signature,
+        blockhash: transaction.message.recentBlockhash,
+        lastValidBlockHeight,
+      };
+    } else {
+      const payerKey = transaction.feePayer || signers[0].publicKey;
+      instructions = transaction.instructions;
+
+      let built = await buildTransaction(connection, payerKey, instructions);
+      try {
+        signature = await submit(
+          title,
+          connection,
+          signPayload(built.transaction, ...signers),
+        );
+      } catch (error) {
+        if (built.transaction.version !== 0 || !isVersionUnsupported(error)) {
+          throw error;
+        }
+        markLegacyOnly(connection);
+        built = await buildTransaction(connection, payerKey, instructions, {
+          version: 'legacy',
+        });
+        signature = await submit(
+          title,
+          connection,
+          signPayload(built.transaction, ...signers),
+        );
+      }
+      strategy = {
+        signature,
+        blockhash: built.blockhash,
+        lastValidBlockHeight: built.lastValidBlockHeight,
+      };
+    }
+
+    await traced(
+      SpanNames.CONFIRMED,
+      {'solgsn.title': title, 'solgsn.signature': signature},
+      async () => {
+        const status = (await connection.confirmTransaction(strategy, 'recent'))
+          .value;
+        if (status && status.err) {
+          throw new Error(
+            `Transaction ${signature} failed (${JSON.stringify(status)})`,
+          );
+        }
       },
     );
 
     const body = {
       time: new Date(when).toString(),
       signature,
-      instructions: transaction.instructions.map(i => {
+      instructions: instructions.map(i => {
         return {
           keys: i.keys.map(keyObj => keyObj.pubkey.toBase58()),
           programId: i.programId.toBase58(),
@@ -0,0 +1,144 @@
+// @flow
+
+import {SystemProgram, Transaction, TransactionInstruction} from '@solana/web3.js';
+import type {Account, Connection, PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {findVaultAddress, getGsnConfig} from './gsn-state';
+import {sendAndConfirmTransaction} from './send-and-confirm-transaction';
+
+const INITIATE_SHUTDOWN = 20;
+const WITHDRAW = 21;
+const SWEEP = 22;
+
+/**
+ * Encode an `InitiateShutdown`: the governance authority starts the terminal
+ * shutdown, whose residue is eventually swept to `recovery`
+ */
+export function initiateShutdownInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  recovery: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([Buffer.from([INITIATE_SHUTDOWN]), recovery.toBuffer()]),
+  });
+}
+
+/**
+ * Encode a `Withdraw`: pays `amount` lamports of the consumer's unspent
+ * top-up balance back to it out of `vault`
+ */
+export function withdrawInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  vault: PublicKey,
+  amount: BN | number,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: consumer, isSigner: true, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([WITHDRAW]),
+      new BN(amount.toString()).toArrayLike(Buffer, 'le', 8),
+    ]),
+  });
+}
+
+/**
+ * Encode a `Sweep`, which anyone may send once the shutdown's grace period
+ * has passed: empties `vault` into `recovery`
+ */
+export function sweepInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  recovery: PublicKey,
+  vault: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: recovery, isSigner: false, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([SWEEP]),
+  });
+}
+
+/**
+ * Withdraw `amount` lamports of `consumerAccount`'s top-up balance
+ */
+export async function withdraw(
+  connection: Connection,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumerAccount: Account,
+  amount: BN | number,
+): Promise<string> {
+  return sendAndConfirmTransaction(
+    'withdraw',
+    connection,
+    new Transaction().add(
+      withdrawInstruction(
+        programId,
+        gsnAccount,
+        consumerAccount.publicKey,
+        await findVaultAddress(programId, gsnAccount),
+        amount,
+      ),
+    ),
+    consumerAccount,
+  );
+}
+
+/**
+ * Sweep a shut down deployment's residue to its recovery address if the
+ * grace period has passed; resolves to `null` when no sweep is due
+ */
+export async function sweepIfDue(
+  connection: Connection,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  payer: Account,
+): Promise<?string> {
+  const {shutdown} = await getGsnConfig(connection, configAccount);
+  if (!shutdown) {
+    return null;
+  }
+  const slot = await connection.getSlot('confirmed');
+  const now = await connection.getBlockTime(slot);
+  if (now === null || now < shutdown.sweep_at.toNumber()) {
+    return null;
+  }
+  return sendAndConfirmTransaction(
+    'sweep',
+    connection,
+    new Transaction().add(
+      sweepInstruction(
+        programId,
+        gsnAccount,
+        configAccount,
+        shutdown.recovery,
+        await findVaultAddress(programId, gsnAccount),
+      ),
+    ),
+    payer,
+  );
+}
@@ -0,0 +1,258 @@
+// @flow
+
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from '@solana/web3.js';
+import type {Connection} from '@solana/web3.js';
+import BN from 'bn.js';
+import bodyParser from 'body-parser';
+import express from 'express';
+import type {Router} from 'express';
+
+import {findVaultAddress} from './gsn-state';
+import {sleep} from './sleep';
+
+const TOPUP = 1;
+
+/**
+ * GSN deployment a Solana Pay top-up funds
+ */
+export type TopupTarget = {
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+};
+
+/**
+ * A top-up a wallet is asked to pay for: `amount` lamports of gas credit for
+ * `consumer`, tracked by the one-off `reference` key. `Topup` is paid by the
+ * consumer itself, so the consumer is the wallet that scans the request.
+ */
+export type TopupRequest = {
+  consumer: PublicKey,
+  amount: BN | number,
+  reference: PublicKey,
+  // Consumer's credential token account, required in compliance mode
+  credential?: ?PublicKey,
+};
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Fresh reference key of a top-up request; it is never a signer and only
+ * serves to find the transaction once it lands
+ */
+export function newReference(): PublicKey {
+  return Keypair.generate().publicKey;
+}
+
+/**
+ * Solana Pay transaction request URL for a top-up of `consumer`, served by a
+ * `topupRequestHandler` mounted at `endpoint` (an https URL). Render it as a
+ * QR code for wallets to scan.
+ */
+export function topupRequestUrl(
+  endpoint: string,
+  request: TopupRequest,
+): string {
+  const link = new URL(endpoint);
+  link.searchParams.set('consumer', request.consumer.toBase58());
+  link.searchParams.set('amount', request.amount.toString());
+  link.searchParams.set('reference', request.reference.toBase58());
+  if (request.credential) {
+    link.searchParams.set('credential', request.credential.toBase58());
+  }
+  // Links with a query must be URL-encoded, see the Solana Pay specification
+  return `solana:${encodeURIComponent(link.toString())}`;
+}
+
+/**
+ * Unsigned top-up transaction for the consumer, the scanning wallet, to sign.
+ *
+ * `Topup` moves the lamports from the consumer into the deployment's vault.
+ * The reference key is appended to the `Topup` accounts, which the program
+ * ignores.
+ */
+export async function buildTopupTransaction(
+  connection: Connection,
+  target: TopupTarget,
+  request: TopupRequest,
+): Promise<Transaction> {
+  const keys = [
+    {pubkey: target.gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: request.consumer, isSigner: true, isWritable: true},
+    {pubkey: target.configAccount, isSigner: false, isWritable: false},
+    {
+      pubkey: await findVaultAddress(target.programId, target.gsnAccount),
+      isSigner: false,
+      isWritable: true,
+    },
+    {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+  ];
+  if (request.credential) {
+    keys.push({pubkey: request.credential, isSigner: false, isWritable: false});
+  }
+  keys.push({pubkey: request.reference, isSigner: false, isWritable: false});
+
+  const transaction = new Transaction().add(
+    new TransactionInstruction({
+      keys,
+      programId: target.programId,
+      data: Buffer.concat([Buffer.from([TOPUP]), u64Bytes(request.amount)]),
+    }),
+  );
+  transaction.feePayer = request.consumer;
+  transaction.recentBlockhash = (
+    await connection.getLatestBlockhash('confirmed')
+  ).blockhash;
+  return transaction;
+}
+
+function parseRequest(query: {[string]: string}): TopupRequest {
+  const amount = new BN(query.amount || '0');
+  if (amount.isZero() || amount.isNeg()) {
+    throw new Error('amount must be a positive number of lamports');
+  }
+  return {
+    consumer: new PublicKey(query.consumer),
+    amount,
+    reference: new PublicKey(query.reference),
+    credential: query.credential ? new PublicKey(query.credential) : null,
+  };
+}
+
+/**
+ * Express router serving the Solana Pay transaction requests of
+ * `topupRequestUrl`: `GET` describes the merchant, `POST` returns the
+ * top-up transaction for the wallet's `account` to sign and send.
+ */
+export function topupRequestHandler(
+  connection: Connection,
+  target: TopupTarget,
+  options: {label: string, icon: string},
+): Router {
+  const router = express.Router();
+  router.use(bodyParser.json());
+
+  router.get('/', (req, res) => {
+    res.json({label: options.label, icon: options.icon});
+  });
+
+  router.post('/', async (req, res) => {
+    let request;
+    let payer;
+    try {
+      request = parseRequest(req.query);
+      payer = new PublicKey(req.body.account);
+    } catch (error) {
+      res.status(400).json({error: error.message || String(error)});
+      return;
+    }
+    if (!payer.equals(request.consumer)) {
+      res.status(400).json({error: 'top-ups must be paid by the consumer'});
+      return;
+    }
+
+    try {
+      const transaction = await buildTopupTransaction(
+        connection,
+        target,
+        request,
+      );
+      res.json({
+        transaction: transaction
+          .serialize({requireAllSignatures: false, verifySignatures: false})
+          .toString('base64'),
+        message: `Top up ${request.amount.toString()} lamports of gas credit`,
+      });
+    } catch (error) {
+      res.status(500).json({error: error.message || String(error)});
+    }
+  });
+
+  return router;
+}
+
+/**
+ * Signature of the confirmed top-up tracked by `reference`, `null` while
+ * none has landed.
+ *
+ * The reference only finds the transaction: whoever knows it can reference
+ * it from any transaction, so the top-up's program, accounts and amount are
+ * checked against `request` before it is reported.
+ */
+export async function findTopup(
+  connection: Connection,
+  target: TopupTarget,
+  request: TopupRequest,
+): Promise<?string> {
+  const signatures = await connection.getSignaturesForAddress(
+    request.reference,
+    {},
+    'confirmed',
+  );
+  const expectedData = Buffer.concat([
+    Buffer.from([TOPUP]),
+    u64Bytes(request.amount),
+  ]).toString('hex');
+
+  for (const {signature, err} of signatures) {
+    if (err) {
+      continue;
+    }
+    const response = await connection.getTransaction(signature, {
+      commitment: 'confirmed',
+      maxSupportedTransactionVersion: 0,
+    });
+    if (!response || (response.meta && response.meta.err)) {
+      continue;
+    }
+
+    const {message} = response.transaction;
+    const accountKeys = message.getAccountKeys().staticAccountKeys;
+    const isTopup = message.compiledInstructions.some(
+      instruction =>
+        accountKeys[instruction.programIdIndex].equals(target.programId) &&
+        Buffer.from(instruction.data).toString('hex') === expectedData &&
+        accountKeys[instruction.accountKeyIndexes[0]].equals(
+          target.gsnAccount,
+        ) &&
+        accountKeys[instruction.accountKeyIndexes[1]].equals(request.consumer),
+    );
+    if (isTopup) {
+      return signature;
+    }
+  }
+  return null;
+}
+
+/**
+ * Poll `findTopup` until the top-up lands or `timeoutMs` passes
+ */
+export async function waitForTopup(
+  connection: Connection,
+  target: TopupTarget,
+  request: TopupRequest,
+  timeoutMs: number = 120000,
+  intervalMs: number = 1000,
+): Promise<string> {
+  const deadline = Date.now() + timeoutMs;
+  for (;;) {
+    const signature = await findTopup(connection, target, request);
+    if (signature) {
+      return signature;
+    }
+    if (Date.now() >= deadline) {
+      throw new Error(
+        `Top-up ${request.reference.toBase58()} not confirmed after ${timeoutMs}ms`,
+      );
+    }
+    await sleep(intervalMs);
+  }
+}
@@ -0,0 +1,97 @@
+// @flow
+
+import {Connection, PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+import fetch from 'node-fetch';
+
+import {decodeGsnState, findVaultAddress} from './gsn-state';
+
+/**
+ * Result of comparing the vault against what the protocol owes
+ */
+export type SolvencyReport = {
+  gsnAccount: string,
+  vault: string,
+  vaultLamports: BN,
+  consumerLiabilities: BN,
+  executorLiabilities: BN,
+  liabilities: BN,
+  // Negative when the vault cannot cover every balance
+  surplus: BN,
+  solvent: boolean,
+  checkedAt: number,
+};
+
+function sum(values: Map<string, any>): BN {
+  let total = new BN(0);
+  for (const value of values.values()) {
+    total = total.add(new BN(value.toString()));
+  }
+  return total;
+}
+
+/**
+ * Check the solvency invariant of a GSN account: the lamports of its vault
+ * must cover every consumer balance plus every unclaimed executor fee.
+ */
+export async function checkSolvency(
+  connection: Connection,
+  gsnAccountPubkey: PublicKey,
+): Promise<SolvencyReport> {
+  const accountInfo = await connection.getAccountInfo(gsnAccountPubkey);
+  if (!accountInfo) {
+    throw new Error('GSN account not found');
+  }
+
+  const gsnInfo = decodeGsnState(accountInfo.data);
+  // The program owns the state account and derives the vault from it
+  const vault = await findVaultAddress(accountInfo.owner, gsnAccountPubkey);
+  const vaultLamports = new BN(await connection.getBalance(vault));
+  const consumerLiabilities = sum(gsnInfo.consumer);
+  const executorLiabilities = sum(gsnInfo.executor);
+  const liabilities = consumerLiabilities.add(executorLiabilities);
+  const surplus = vaultLamports.sub(liabilities);
+
+  return {
+    gsnAccount: gsnAccountPubkey.toBase58(),
+    vault: vault.toBase58(),
+    vaultLamports,
+    consumerLiabilities,
+    executorLiabilities,
+    liabilities,
+    surplus,
+    solvent: !surplus.isNeg(),
+    checkedAt: Date.now(),
+  };
+}
+
+/**
+ * JSON-friendly copy of a report, lamport amounts as decimal strings
+ */
+export function formatSolvencyReport(report: SolvencyReport): Object {
+  const formatted = {};
+  for (const [key, value] of Object.entries(report)) {
+    formatted[key] = BN.isBN(value) ? (value: any).toString() : value;
+  }
+  return formatted;
+}
+
+/**
+ * POST a solvency report to an alerting webhook
+ */
+export async function sendSolvencyAlert(
+  webhookUrl: string,
+  report: SolvencyReport,
+): Promise<void> {
+  const response = await fetch(webhookUrl, {
+    method: 'POST',
+    headers: {'Content-Type': 'application/json'},
+    body: JSON.stringify({
+      alert: report.solvent ? 'solgsn.vault.recovered' : 'solgsn.vault.insolvent',
+      ...formatSolvencyReport(report),
+    }),
+  });
+  if (!response.ok) {
+    throw new Error(`Alert webhook responded with ${response.status}`);
+  }
+}
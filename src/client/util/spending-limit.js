@@ -0,0 +1,66 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_SPENDING_LIMIT = 30;
+
+/**
+ * Limit value that caps nothing; setting both limits to it lifts the cap
+ */
+export const NO_LIMIT: BN = new BN('ffffffffffffffff', 16);
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Encode a `SetSpendingLimit` capping the lamport fees `consumer` may be
+ * charged per submission and per epoch. The governance authority signs it.
+ */
+export function setSpendingLimitInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  consumer: PublicKey,
+  maxFeePerTx: BN | number,
+  maxFeesPerEpoch: BN | number,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SET_SPENDING_LIMIT]),
+      u64Bytes(maxFeePerTx),
+      u64Bytes(maxFeesPerEpoch),
+      consumer.toBuffer(),
+    ]),
+  });
+}
+
+/**
+ * Encode a `SetSpendingLimit` lifting `consumer`'s cap
+ */
+export function clearSpendingLimitInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  consumer: PublicKey,
+): TransactionInstruction {
+  return setSpendingLimitInstruction(
+    programId,
+    gsnAccount,
+    configAccount,
+    authority,
+    consumer,
+    NO_LIMIT,
+    NO_LIMIT,
+  );
+}
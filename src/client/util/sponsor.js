@@ -0,0 +1,227 @@
+// @flow
+
+import {
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {encodeTargetInstruction} from './relay-instruction';
+
+const REGISTER_SPONSOR = 47;
+const SUBMIT_SPONSORED = 48;
+const SUBMIT_SPONSORED_INSTRUCTION = 49;
+
+/**
+ * Most target programs a sponsor policy lists
+ */
+export const MAX_SPONSOR_PROGRAMS = 16;
+
+/**
+ * Most target instructions a sponsor policy lists
+ */
+export const MAX_SPONSOR_INSTRUCTIONS = 8;
+
+/**
+ * Instructions of `programId` whose data starts with `discriminator`
+ */
+export type InstructionFilter = {
+  programId: PublicKey,
+  discriminator: number,
+};
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Address of the policy of `sponsor` for the GSN state account
+ */
+export async function findSponsorAddress(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  sponsor: PublicKey,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [Buffer.from('sponsor'), gsnAccount.toBuffer(), sponsor.toBuffer()],
+    programId,
+  );
+  return address;
+}
+
+/**
+ * Encode a `RegisterSponsor` creating or replacing the policy of `sponsor`,
+ * who pays its rent the first time. The sponsor pays for every instruction
+ * of `allowedPrograms` and for the relayed `allowedInstructions`, or for
+ * everything when both are empty. A `maxSubmissionsPerUser` of 0 sets no
+ * quota.
+ */
+export function registerSponsorInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  sponsor: PublicKey,
+  policy: PublicKey,
+  maxFeePerTx: BN | number,
+  allowedPrograms: Array<PublicKey> = [],
+  allowedInstructions: Array<InstructionFilter> = [],
+  maxSubmissionsPerUser: BN | number = 0,
+): TransactionInstruction {
+  if (allowedPrograms.length > MAX_SPONSOR_PROGRAMS) {
+    throw new Error(
+      `A sponsor policy lists at most ${MAX_SPONSOR_PROGRAMS} programs`,
+    );
+  }
+  if (allowedInstructions.length > MAX_SPONSOR_INSTRUCTIONS) {
+    throw new Error(
+      `A sponsor policy lists at most ${MAX_SPONSOR_INSTRUCTIONS} instructions`,
+    );
+  }
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: false},
+      {pubkey: sponsor, isSigner: true, isWritable: true},
+      {pubkey: policy, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([REGISTER_SPONSOR]),
+      u64Bytes(maxFeePerTx),
+      Buffer.from([allowedPrograms.length]),
+      ...allowedPrograms.map(program => program.toBuffer()),
+      Buffer.from([allowedInstructions.length]),
+      ...allowedInstructions.map(({programId, discriminator}) =>
+        Buffer.concat([programId.toBuffer(), Buffer.from([discriminator])]),
+      ),
+      u64Bytes(maxSubmissionsPerUser),
+    ]),
+  });
+}
+
+/**
+ * Accounts of a `SubmitSponsored`, in instruction order
+ */
+export type SponsoredSubmitAccounts = {
+  targetProgram: PublicKey,
+  sender: PublicKey,
+  receiver: PublicKey,
+  feePayer: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  // Registered metrics account of the config, if any
+  metricsAccount?: ?PublicKey,
+  // Sender's credential token account, in compliance mode
+  credentialAccount?: ?PublicKey,
+  // From `findSponsorAddress(programId, gsnAccount, sponsor)`
+  sponsorPolicy: PublicKey,
+};
+
+/**
+ * Encode a `SubmitSponsored`: the fee is charged to the balance of the
+ * sponsor whose policy is passed, so the sender needs no balance
+ */
+export function submitSponsoredInstruction(
+  programId: PublicKey,
+  accounts: SponsoredSubmitAccounts,
+  amount: BN | number,
+  nonce: BN | number,
+  expirySlot: ?(BN | number) = null,
+  maxFee: ?(BN | number) = null,
+  tip: ?(BN | number) = null,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: accounts.targetProgram, isSigner: false, isWritable: true},
+    {pubkey: accounts.sender, isSigner: true, isWritable: true},
+    {pubkey: accounts.receiver, isSigner: false, isWritable: true},
+    {pubkey: accounts.feePayer, isSigner: true, isWritable: true},
+    {pubkey: accounts.gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: accounts.configAccount, isSigner: false, isWritable: false},
+  ];
+  if (accounts.metricsAccount) {
+    keys.push({
+      pubkey: accounts.metricsAccount,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+  if (accounts.credentialAccount) {
+    keys.push({
+      pubkey: accounts.credentialAccount,
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+  keys.push({
+    pubkey: accounts.sponsorPolicy,
+    isSigner: false,
+    isWritable: false,
+  });
+
+  const data = [
+    Buffer.from([SUBMIT_SPONSORED]),
+    u64Bytes(amount),
+    u64Bytes(nonce),
+  ];
+  // Followed by a maximum fee, an expiry slot of 0 stands for none, and
+  // followed by a tip, a maximum fee of u64::MAX
+  if (expirySlot != null || maxFee != null || tip != null) {
+    data.push(u64Bytes(expirySlot || 0));
+  }
+  if (maxFee != null || tip != null) {
+    data.push(maxFee != null ? u64Bytes(maxFee) : Buffer.alloc(8, 0xff));
+  }
+  if (tip != null) {
+    data.push(u64Bytes(tip));
+  }
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat(data),
+  });
+}
+
+/**
+ * Encode a `SubmitSponsoredInstruction`: like `submitInstructionInstruction`,
+ * with the fee charged to the sponsor whose policy follows the credential
+ */
+export function submitSponsoredInstructionInstruction(
+  programId: PublicKey,
+  sender: PublicKey,
+  feePayer: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  sponsorPolicy: PublicKey,
+  instruction: TransactionInstruction,
+  nonce: BN | number,
+  lane: number = 0,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: instruction.programId, isSigner: false, isWritable: false},
+    {pubkey: sender, isSigner: true, isWritable: true},
+    {pubkey: feePayer, isSigner: true, isWritable: true},
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+  ];
+  if (metricsAccount) {
+    keys.push({pubkey: metricsAccount, isSigner: false, isWritable: true});
+  }
+  if (credential) {
+    keys.push({pubkey: credential, isSigner: false, isWritable: false});
+  }
+  keys.push({pubkey: sponsorPolicy, isSigner: false, isWritable: false});
+  keys.push(...instruction.keys);
+
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SUBMIT_SPONSORED_INSTRUCTION]),
+      u64Bytes(nonce),
+      u64Bytes(lane),
+      encodeTargetInstruction(instruction),
+    ]),
+  });
+}
@@ -0,0 +1,129 @@
+// @flow
+
+import {SystemProgram, TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const STAKE_EXECUTOR = 41;
+const UNSTAKE_EXECUTOR = 42;
+const SLASH_EXECUTOR = 43;
+const SET_MIN_EXECUTOR_STAKE = 44;
+
+/**
+ * Slots between asking to unstake and withdrawing the stake
+ */
+export const STAKE_UNBONDING_SLOTS = 432000;
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+function executorStakeInstruction(
+  data: Buffer,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  stakeAccount: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: executor, isSigner: true, isWritable: true},
+      {pubkey: stakeAccount, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
+
+/**
+ * Encode a `StakeExecutor` bonding `amount` more lamports. `stakeAccount` is
+ * `findStakeAddress(programId, gsnAccount, executor)`; the first stake must
+ * cover its rent exemption.
+ */
+export function stakeExecutorInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  stakeAccount: PublicKey,
+  amount: BN | number,
+): TransactionInstruction {
+  return executorStakeInstruction(
+    Buffer.concat([Buffer.from([STAKE_EXECUTOR]), u64Bytes(amount)]),
+    programId,
+    gsnAccount,
+    executor,
+    stakeAccount,
+  );
+}
+
+/**
+ * Encode an `UnstakeExecutor`. The first one starts unbonding, one sent
+ * `STAKE_UNBONDING_SLOTS` later pays the stake back.
+ */
+export function unstakeExecutorInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  stakeAccount: PublicKey,
+): TransactionInstruction {
+  return executorStakeInstruction(
+    Buffer.from([UNSTAKE_EXECUTOR]),
+    programId,
+    gsnAccount,
+    executor,
+    stakeAccount,
+  );
+}
+
+/**
+ * Encode a `SlashExecutor` paying up to `amount` of `executor`'s stake to
+ * `recipient`. The governance authority signs it.
+ */
+export function slashExecutorInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  stakeAccount: PublicKey,
+  recipient: PublicKey,
+  executor: PublicKey,
+  amount: BN | number,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+      {pubkey: stakeAccount, isSigner: false, isWritable: true},
+      {pubkey: recipient, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SLASH_EXECUTOR]),
+      u64Bytes(amount),
+      executor.toBuffer(),
+    ]),
+  });
+}
+
+/**
+ * Encode a `SetMinExecutorStake`; 0 stops requiring a stake
+ */
+export function setMinExecutorStakeInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  amount: BN | number,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([Buffer.from([SET_MIN_EXECUTOR_STAKE]), u64Bytes(amount)]),
+  });
+}
@@ -0,0 +1,23 @@
+// @flow
+
+import {SystemProgram} from '@solana/web3.js';
+import type {TransactionInstruction} from '@solana/web3.js';
+
+/**
+ * Let a submission grow the state account when it no longer fits, with the
+ * executor paying the rent: appends the system program unless the
+ * instruction carries it already. Top-ups always carry it, the consumer pays.
+ */
+export function withStateGrowth(
+  instruction: TransactionInstruction,
+): TransactionInstruction {
+  const {programId} = SystemProgram;
+  if (!instruction.keys.some(({pubkey}) => pubkey.equals(programId))) {
+    instruction.keys.push({
+      pubkey: programId,
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+  return instruction;
+}
@@ -0,0 +1,34 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+
+const MIGRATE_STATE = 59;
+
+/**
+ * Layout version of the state accounts the program writes, see
+ * `GsnInfo.version`
+ */
+export const STATE_VERSION = 1;
+
+/**
+ * Encode a `MigrateState`, rewriting the state account in the layout of the
+ * deployed program and tagging a config written before discriminators. The
+ * governance authority signs it.
+ */
+export function migrateStateInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([MIGRATE_STATE]),
+  });
+}
@@ -0,0 +1,119 @@
+// @flow
+
+import {PublicKey, TransactionInstruction} from '@solana/web3.js';
+import {deserialize} from 'borsh';
+
+const SET_STATS_TRACKING = 76;
+const GET_STATS = 77;
+
+/**
+ * Encode a `SetStatsTracking` turning the counting of executions by epoch
+ * on, or off, dropping the counters. The governance authority signs it.
+ */
+export function setStatsTrackingInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  enabled: boolean,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([SET_STATS_TRACKING, enabled ? 1 : 0]),
+  });
+}
+
+/**
+ * Encode a `GetStats`, to simulate and decode with `decodeDeploymentStats`
+ */
+export function getStatsInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [{pubkey: gsnAccount, isSigner: false, isWritable: false}],
+    programId,
+    data: Buffer.from([GET_STATS]),
+  });
+}
+
+/**
+ * Answer of `GetStats` (`query::DeploymentStats`)
+ */
+class DeploymentStats {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+/**
+ * Counters of an epoch's executions (`query::EpochSummary`)
+ */
+class EpochSummary {
+  constructor(fields) {
+    Object.assign(this, fields);
+    // Executions and lamport fees, keyed by base58 executor
+    this.executor_volumes = new Map(
+      fields.executor_volumes.map(({executor, ...volume}) => [
+        new PublicKey(executor).toBase58(),
+        volume,
+      ]),
+    );
+  }
+}
+
+class ExecutorVolume {
+  constructor(fields) {
+    Object.assign(this, fields);
+  }
+}
+
+const DeploymentStatsSchema = new Map([
+  [
+    DeploymentStats,
+    {
+      kind: 'struct',
+      fields: [
+        ['enabled', 'u8'],
+        ['current', EpochSummary],
+        ['previous', EpochSummary],
+      ],
+    },
+  ],
+  [
+    EpochSummary,
+    {
+      kind: 'struct',
+      fields: [
+        ['epoch', 'u64'],
+        ['executions', 'u64'],
+        ['total_fees', 'u64'],
+        ['unique_consumers', 'u64'],
+        ['executor_volumes', [ExecutorVolume]],
+      ],
+    },
+  ],
+  [
+    ExecutorVolume,
+    {
+      kind: 'struct',
+      fields: [
+        ['executor', [32]],
+        ['executions', 'u64'],
+        ['fees', 'u64'],
+      ],
+    },
+  ],
+]);
+
+/**
+ * Decode the return data of a simulated `GetStats`
+ */
+export function decodeDeploymentStats(returnData: Buffer): DeploymentStats {
+  return deserialize(DeploymentStatsSchema, DeploymentStats, returnData);
+}
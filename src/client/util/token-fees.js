@@ -0,0 +1,520 @@
+// @flow
+
+import {
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from '@solana/web3.js';
+import type {Account, Connection} from '@solana/web3.js';
+import BN from 'bn.js';
+
+import {findVaultAddress, getGsnState} from './gsn-state';
+import {sendAndConfirmTransaction} from './send-and-confirm-transaction';
+
+const TOPUP_TOKEN = 23;
+const SUBMIT_TOKEN_FEE = 24;
+const CLAIM_TOKEN_FEES = 25;
+const SET_PRICE_FEED = 69;
+const CLAIM_WRAPPED_SOL = 70;
+const CLAIM_TOKEN_FEES_AS_SOL = 71;
+
+/** Oracle kinds of a price feed */
+export const ORACLE_PYTH = 0;
+export const ORACLE_SWITCHBOARD = 1;
+
+export const TOKEN_PROGRAM_ID = new PublicKey(
+  'TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA',
+);
+export const TOKEN_2022_PROGRAM_ID = new PublicKey(
+  'TokenzQdBNbLqP5VEhdkAS6EPFLC1PT3PkDeYJaoWHf',
+);
+export const WRAPPED_SOL_MINT = new PublicKey(
+  'So11111111111111111111111111111111111111112',
+);
+export const JUPITER_PROGRAM_ID = new PublicKey(
+  'JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4',
+);
+export const ASSOCIATED_TOKEN_PROGRAM_ID = new PublicKey(
+  'ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL',
+);
+
+function u64(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Associated token account of `owner` for `mint`
+ */
+export async function findAssociatedTokenAddress(
+  owner: PublicKey,
+  mint: PublicKey,
+  tokenProgramId: PublicKey = TOKEN_PROGRAM_ID,
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [owner.toBuffer(), tokenProgramId.toBuffer(), mint.toBuffer()],
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+  );
+  return address;
+}
+
+/**
+ * Token account of `mint` held by the vault of `gsnAccount`, which token
+ * top-ups are paid into and token claims out of
+ */
+export async function findVaultTokenAddress(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  mint: PublicKey,
+  tokenProgramId: PublicKey = TOKEN_PROGRAM_ID,
+): Promise<PublicKey> {
+  return findAssociatedTokenAddress(
+    await findVaultAddress(programId, gsnAccount),
+    mint,
+    tokenProgramId,
+  );
+}
+
+/**
+ * Encode a `CreateIdempotent` of the associated token program, which anyone
+ * may send to open the vault's token account of a mint
+ */
+export function createVaultTokenAccountInstruction(
+  payer: PublicKey,
+  vaultToken: PublicKey,
+  vault: PublicKey,
+  mint: PublicKey,
+  tokenProgramId: PublicKey = TOKEN_PROGRAM_ID,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: payer, isSigner: true, isWritable: true},
+      {pubkey: vaultToken, isSigner: false, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: false},
+      {pubkey: mint, isSigner: false, isWritable: false},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+      {pubkey: tokenProgramId, isSigner: false, isWritable: false},
+    ],
+    programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+    data: Buffer.from([1]),
+  });
+}
+
+/**
+ * Encode a `TopupToken`: moves `amount` tokens from the consumer's token
+ * account into the vault's and credits its balance in their mint, or the
+ * balance of `beneficiary`. The credited consumer's credential follows in
+ * compliance mode. Passing the `mint` makes the transfer checked, which
+ * Token-2022 mints with a transfer fee require; the amount net of the fee is
+ * credited. With the `mint` and a `vaultTokenPayer`, the program opens the
+ * vault's associated token account of the mint if it doesn't exist yet, the
+ * payer (the consumer, or another signer such as a relaying executor)
+ * paying its rent.
+ */
+export function topupTokenInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  configAccount: PublicKey,
+  consumerToken: PublicKey,
+  vaultToken: PublicKey,
+  vault: PublicKey,
+  amount: BN | number,
+  credential: ?PublicKey,
+  tokenProgramId: PublicKey = TOKEN_PROGRAM_ID,
+  beneficiary: ?PublicKey = null,
+  mint: ?PublicKey = null,
+  vaultTokenPayer: ?PublicKey = null,
+): TransactionInstruction {
+  const consumerPays = !!vaultTokenPayer && vaultTokenPayer.equals(consumer);
+  const keys = [
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: consumer, isSigner: true, isWritable: consumerPays},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+    {pubkey: consumerToken, isSigner: false, isWritable: true},
+    {pubkey: vaultToken, isSigner: false, isWritable: true},
+    {pubkey: vault, isSigner: false, isWritable: false},
+    {pubkey: tokenProgramId, isSigner: false, isWritable: false},
+  ];
+  if (credential) {
+    keys.push({pubkey: credential, isSigner: false, isWritable: false});
+  }
+  if (mint) {
+    keys.push({pubkey: mint, isSigner: false, isWritable: false});
+    if (vaultTokenPayer) {
+      keys.push(
+        {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+        {
+          pubkey: ASSOCIATED_TOKEN_PROGRAM_ID,
+          isSigner: false,
+          isWritable: false,
+        },
+      );
+      if (!consumerPays) {
+        keys.push({pubkey: vaultTokenPayer, isSigner: true, isWritable: true});
+      }
+    }
+  }
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([TOPUP_TOKEN]),
+      u64(amount),
+      ...(beneficiary ? [beneficiary.toBuffer()] : []),
+    ]),
+  });
+}
+
+/**
+ * Encode a `SubmitTokenFee`: like `SubmitTransactionInLane`, charging the
+ * fee to the sender's balance in `mint`. Mints priced by an oracle pass the
+ * price account configured for them as `priceFeed`.
+ */
+export function submitTokenFeeInstruction(
+  programId: PublicKey,
+  targetProgram: PublicKey,
+  sender: PublicKey,
+  receiver: PublicKey,
+  feePayer: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  metricsAccount: ?PublicKey,
+  credential: ?PublicKey,
+  args: {amount: BN | number, nonce: BN | number, lane: BN | number},
+  mint: PublicKey,
+  priceFeed: ?PublicKey = null,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: targetProgram, isSigner: false, isWritable: false},
+    {pubkey: sender, isSigner: true, isWritable: true},
+    {pubkey: receiver, isSigner: false, isWritable: true},
+    {pubkey: feePayer, isSigner: true, isWritable: true},
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: configAccount, isSigner: false, isWritable: false},
+  ];
+  if (metricsAccount) {
+    keys.push({pubkey: metricsAccount, isSigner: false, isWritable: true});
+  }
+  if (credential) {
+    keys.push({pubkey: credential, isSigner: false, isWritable: false});
+  }
+  if (priceFeed) {
+    keys.push({pubkey: priceFeed, isSigner: false, isWritable: false});
+  }
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SUBMIT_TOKEN_FEE]),
+      u64(args.amount),
+      u64(args.nonce),
+      u64(args.lane),
+      mint.toBuffer(),
+    ]),
+  });
+}
+
+/**
+ * Encode a `SetPriceFeed` pricing the token fees of `mint` with the oracle
+ * account `feed`, quoting the token in SOL: a Pyth price account, or a
+ * Switchboard aggregator with `kind` `ORACLE_SWITCHBOARD`. Prices older than
+ * `maxAgeSlots` or with a confidence interval wider than `maxConfidenceBps`
+ * of the price are rejected. A null `feed` charges the fees in base units
+ * again. The governance authority signs it.
+ */
+export function setPriceFeedInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  mint: PublicKey,
+  feed: ?{
+    feed: PublicKey,
+    decimals: number,
+    maxAgeSlots: BN | number,
+    maxConfidenceBps: number,
+    kind?: number,
+  },
+): TransactionInstruction {
+  const limits = Buffer.alloc(4);
+  if (feed) {
+    limits.writeUInt8(feed.decimals, 0);
+    limits.writeUInt16LE(feed.maxConfidenceBps, 1);
+    limits.writeUInt8(feed.kind || ORACLE_PYTH, 3);
+  }
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SET_PRICE_FEED]),
+      mint.toBuffer(),
+      feed ? feed.feed.toBuffer() : Buffer.alloc(32),
+      limits.slice(0, 1),
+      u64(feed ? feed.maxAgeSlots : 0),
+      limits.slice(1),
+    ]),
+  });
+}
+
+/**
+ * Encode a `ClaimTokenFees`: pays the executor's earnings in the mint of
+ * `vaultToken` to its own token account of that mint. Token-2022 mints with
+ * a transfer fee pass their `mint`, the executor bearing the fee.
+ */
+export function claimTokenFeesInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  executorToken: PublicKey,
+  vaultToken: PublicKey,
+  vault: PublicKey,
+  tokenProgramId: PublicKey = TOKEN_PROGRAM_ID,
+  mint: ?PublicKey = null,
+): TransactionInstruction {
+  const keys = [
+    {pubkey: gsnAccount, isSigner: false, isWritable: true},
+    {pubkey: executor, isSigner: true, isWritable: false},
+    {pubkey: executorToken, isSigner: false, isWritable: true},
+    {pubkey: vaultToken, isSigner: false, isWritable: true},
+    {pubkey: vault, isSigner: false, isWritable: false},
+    {pubkey: tokenProgramId, isSigner: false, isWritable: false},
+  ];
+  if (mint) {
+    keys.push({pubkey: mint, isSigner: false, isWritable: false});
+  }
+  return new TransactionInstruction({
+    keys,
+    programId,
+    data: Buffer.from([CLAIM_TOKEN_FEES]),
+  });
+}
+
+/**
+ * Encode a `ClaimWrappedSol`: pays the executor's wrapped SOL earnings into
+ * its own wrapped SOL account `executorToken`, then closes it so the lamports
+ * reach `destination`, the executor or its payout address
+ */
+export function claimWrappedSolInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  executorToken: PublicKey,
+  vaultToken: PublicKey,
+  vault: PublicKey,
+  destination: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: executor, isSigner: true, isWritable: false},
+      {pubkey: executorToken, isSigner: false, isWritable: true},
+      {pubkey: vaultToken, isSigner: false, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: false},
+      {pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
+      {pubkey: destination, isSigner: false, isWritable: true},
+    ],
+    programId,
+    data: Buffer.from([CLAIM_WRAPPED_SOL]),
+  });
+}
+
+/**
+ * Encode a `ClaimTokenFeesAsSol`: pays the executor's earnings in the mint of
+ * `vaultToken` into its own token account `executorToken`, swaps them with
+ * the Jupiter `route` the executor quoted into its wrapped SOL account
+ * `executorWsol`, and unwraps them to `destination`. The claim fails unless
+ * the swap delivered `minLamports`. `route` is the swap instruction built for
+ * the executor, of which the data and accounts are forwarded.
+ */
+export function claimTokenFeesAsSolInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executor: PublicKey,
+  executorToken: PublicKey,
+  vaultToken: PublicKey,
+  vault: PublicKey,
+  executorWsol: PublicKey,
+  destination: PublicKey,
+  minLamports: BN | number,
+  route: TransactionInstruction,
+  tokenProgramId: PublicKey = TOKEN_PROGRAM_ID,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: executor, isSigner: true, isWritable: true},
+      {pubkey: executorToken, isSigner: false, isWritable: true},
+      {pubkey: vaultToken, isSigner: false, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: false},
+      {pubkey: tokenProgramId, isSigner: false, isWritable: false},
+      {pubkey: executorWsol, isSigner: false, isWritable: true},
+      {pubkey: destination, isSigner: false, isWritable: true},
+      {pubkey: JUPITER_PROGRAM_ID, isSigner: false, isWritable: false},
+      ...route.keys,
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([CLAIM_TOKEN_FEES_AS_SOL]),
+      u64(minLamports),
+      route.data,
+    ]),
+  });
+}
+
+/**
+ * Balance of `consumer` in `mint`, which `SubmitTokenFee` charges fees to
+ */
+export async function getConsumerTokenBalance(
+  connection: Connection,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  mint: PublicKey,
+): Promise<BN> {
+  const gsnInfo = await getGsnState(connection, gsnAccount);
+  const balance = gsnInfo.token_consumer.get(
+    `${consumer.toBase58()}:${mint.toBase58()}`,
+  );
+  return balance ? new BN(balance.toString()) : new BN(0);
+}
+
+/**
+ * Token program owning `mint`, SPL Token or Token-2022
+ */
+export async function getMintTokenProgram(
+  connection: Connection,
+  mint: PublicKey,
+): Promise<PublicKey> {
+  const account = await connection.getAccountInfo(mint);
+  if (
+    !account ||
+    !(
+      account.owner.equals(TOKEN_PROGRAM_ID) ||
+      account.owner.equals(TOKEN_2022_PROGRAM_ID)
+    )
+  ) {
+    throw new Error(`${mint.toBase58()} is not a token mint`);
+  }
+  return account.owner;
+}
+
+/**
+ * Top up `amount` tokens of `mint` from `consumerAccount`'s associated token
+ * account. The program opens the vault's token account of the mint if needed,
+ * the consumer paying its rent. The token program is read from the mint,
+ * whose transfers are checked.
+ */
+export async function topupToken(
+  connection: Connection,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  consumerAccount: Account,
+  mint: PublicKey,
+  amount: BN | number,
+  credential: ?PublicKey,
+): Promise<string> {
+  const consumer = consumerAccount.publicKey;
+  const tokenProgramId = await getMintTokenProgram(connection, mint);
+  const vault = await findVaultAddress(programId, gsnAccount);
+  const vaultToken = await findAssociatedTokenAddress(vault, mint, tokenProgramId);
+  return sendAndConfirmTransaction(
+    'topupToken',
+    connection,
+    new Transaction().add(
+      topupTokenInstruction(
+        programId,
+        gsnAccount,
+        consumer,
+        configAccount,
+        await findAssociatedTokenAddress(consumer, mint, tokenProgramId),
+        vaultToken,
+        vault,
+        amount,
+        credential,
+        tokenProgramId,
+        null,
+        mint,
+        consumer,
+      ),
+    ),
+    consumerAccount,
+  );
+}
+
+/**
+ * Claim `executorAccount`'s earnings in `mint` to its associated token
+ * account, of the token program owning the mint
+ */
+export async function claimTokenFees(
+  connection: Connection,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executorAccount: Account,
+  mint: PublicKey,
+): Promise<string> {
+  const executor = executorAccount.publicKey;
+  const tokenProgramId = await getMintTokenProgram(connection, mint);
+  const vault = await findVaultAddress(programId, gsnAccount);
+  return sendAndConfirmTransaction(
+    'claimTokenFees',
+    connection,
+    new Transaction().add(
+      claimTokenFeesInstruction(
+        programId,
+        gsnAccount,
+        executor,
+        await findAssociatedTokenAddress(executor, mint, tokenProgramId),
+        await findAssociatedTokenAddress(vault, mint, tokenProgramId),
+        vault,
+        tokenProgramId,
+        mint,
+      ),
+    ),
+    executorAccount,
+  );
+}
+
+/**
+ * Claim `executorAccount`'s wrapped SOL earnings as lamports to
+ * `destination`, by default the executor, in one transaction: its wrapped
+ * SOL account is opened if needed and closed by the claim
+ */
+export async function claimWrappedSol(
+  connection: Connection,
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  executorAccount: Account,
+  destination: ?PublicKey = null,
+): Promise<string> {
+  const executor = executorAccount.publicKey;
+  const vault = await findVaultAddress(programId, gsnAccount);
+  const executorToken = await findAssociatedTokenAddress(
+    executor,
+    WRAPPED_SOL_MINT,
+  );
+  return sendAndConfirmTransaction(
+    'claimWrappedSol',
+    connection,
+    new Transaction().add(
+      createVaultTokenAccountInstruction(
+        executor,
+        executorToken,
+        executor,
+        WRAPPED_SOL_MINT,
+      ),
+      claimWrappedSolInstruction(
+        programId,
+        gsnAccount,
+        executor,
+        executorToken,
+        await findAssociatedTokenAddress(vault, WRAPPED_SOL_MINT),
+        vault,
+        destination || executor,
+      ),
+    ),
+    executorAccount,
+  );
+}
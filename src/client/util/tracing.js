@@ -0,0 +1,115 @@
+// @flow
+
+import {
+  context,
+  propagation,
+  trace,
+  SpanStatusCode,
+} from '@opentelemetry/api';
+import {PublicKey, TransactionInstruction} from '@solana/web3.js';
+import crypto from 'crypto';
+
+/**
+ * Tracer used by the client SDK. Spans are dropped unless the application
+ * registers an OpenTelemetry SDK/exporter.
+ */
+export const tracer = trace.getTracer('solgsn-client', '0.0.1');
+
+/**
+ * Span names for each stage of a relayed transaction, shared with relayers
+ * so traces line up across services.
+ */
+export const SpanNames = {
+  RELAY: 'solgsn.relay',
+  RECEIVED: 'solgsn.payload.received',
+  VALIDATED: 'solgsn.payload.validated',
+  SIMULATED: 'solgsn.payload.simulated',
+  SUBMITTED: 'solgsn.transaction.submitted',
+  CONFIRMED: 'solgsn.transaction.confirmed',
+};
+
+/**
+ * SPL Memo program, used to echo the request id on-chain
+ */
+export const MEMO_PROGRAM_ID = new PublicKey(
+  'MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr',
+);
+
+const REQUEST_ID_MEMO_PREFIX = 'solgsn:request_id=';
+
+/**
+ * Generate a request id for correlating client, relayer and on-chain activity
+ */
+export function newRequestId(): string {
+  return crypto.randomBytes(16).toString('hex');
+}
+
+/**
+ * Memo instruction carrying the request id
+ */
+export function requestIdMemo(requestId: string): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [],
+    programId: MEMO_PROGRAM_ID,
+    data: Buffer.from(REQUEST_ID_MEMO_PREFIX + requestId, 'utf8'),
+  });
+}
+
+/**
+ * Extract the request id from transaction log messages, if a memo carried one
+ */
+export function requestIdFromLogs(logs: Array<string>): ?string {
+  for (const line of logs) {
+    const start = line.indexOf(REQUEST_ID_MEMO_PREFIX);
+    if (start !== -1) {
+      const match = line
+        .slice(start + REQUEST_ID_MEMO_PREFIX.length)
+        .match(/^[0-9a-f]+/);
+      if (match) {
+        return match[0];
+      }
+    }
+  }
+  return null;
+}
+
+/**
+ * Run `fn` inside an active span, recording errors and ending the span
+ */
+export function traced<T>(
+  name: string,
+  attributes: {[string]: string | number | boolean},
+  fn: (span: any) => Promise<T>,
+): Promise<T> {
+  return tracer.startActiveSpan(name, {attributes}, async span => {
+    try {
+      const result = await fn(span);
+      span.setStatus({code: SpanStatusCode.OK});
+      return result;
+    } catch (error) {
+      span.recordException(error);
+      span.setStatus({
+        code: SpanStatusCode.ERROR,
+        message: error.message || String(error),
+      });
+      throw error;
+    } finally {
+      span.end();
+    }
+  });
+}
+
+/**
+ * Inject the active trace context into outgoing request headers (W3C traceparent)
+ */
+export function injectTraceContext(headers: Object = {}): Object {
+  propagation.inject(context.active(), headers);
+  return headers;
+}
+
+/**
+ * Run `fn` with the trace context carried by incoming request headers
+ */
+export function withTraceContext<T>(headers: Object, fn: () => T): T {
+  return context.with(propagation.extract(context.active(), headers), fn);
+}
@@ -0,0 +1,66 @@
+// @flow
+
+import {SystemProgram, TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_PROTOCOL_FEE = 45;
+const WITHDRAW_TREASURY = 46;
+
+/**
+ * Highest protocol fee governance can set, in basis points
+ */
+export const MAX_PROTOCOL_FEE_BPS = 5000;
+
+/**
+ * Encode a `SetProtocolFee` keeping `protocolFeeBps` of every lamport fee
+ * for the treasury. The governance authority signs it.
+ */
+export function setProtocolFeeInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  protocolFeeBps: number,
+): TransactionInstruction {
+  const data = Buffer.alloc(3);
+  data.writeUInt8(SET_PROTOCOL_FEE, 0);
+  data.writeUInt16LE(protocolFeeBps, 1);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
+
+/**
+ * Encode a `WithdrawTreasury` paying `amount` lamports of the treasury out
+ * of the vault to `destination`. The governance authority signs it.
+ */
+export function withdrawTreasuryInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  destination: PublicKey,
+  vault: PublicKey,
+  amount: BN | number,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+      {pubkey: destination, isSigner: false, isWritable: true},
+      {pubkey: vault, isSigner: false, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([WITHDRAW_TREASURY]),
+      new BN(amount.toString()).toArrayLike(Buffer, 'le', 8),
+    ]),
+  });
+}
@@ -0,0 +1,35 @@
+// @flow
+
+import BN from 'bn.js';
+import type {PublicKey} from '@solana/web3.js';
+
+/**
+ * Nonce lane whose nonces may be submitted in any order, as long as each is
+ * unused and not below the consumer's window of the last 256 nonces
+ */
+export const UNORDERED_NONCE_LANE = '18446744073709551615';
+
+const WINDOW_SIZE = 256;
+
+/**
+ * `count` unordered nonces of `consumer` that are free to sign, lowest
+ * first, from the decoded `GsnInfo`. Nonces past the window are free too,
+ * but using one slides the window up and burns the free nonces left below.
+ */
+export function freeUnorderedNonces(
+  gsnInfo: Object,
+  consumer: PublicKey,
+  count: number,
+): Array<BN> {
+  const window = gsnInfo.nonce_windows.get(consumer.toBase58());
+  const base = window ? new BN(window.base.toString()) : new BN(0);
+  const nonces = [];
+  for (let i = 0; nonces.length < count; i++) {
+    const used =
+      window && i < WINDOW_SIZE && (window.bits[i >> 3] >> (i & 7)) & 1;
+    if (!used) {
+      nonces.push(base.addn(i));
+    }
+  }
+  return nonces;
+}
@@ -0,0 +1,180 @@
+// @flow
+
+import {
+  TransactionMessage,
+  VersionedTransaction,
+} from '@solana/web3.js';
+import type {
+  AddressLookupTableAccount,
+  Connection,
+  PublicKey,
+  Signer,
+  SimulatedTransactionResponse,
+  TransactionInstruction,
+} from '@solana/web3.js';
+
+import {traced, SpanNames} from './tracing';
+
+/**
+ * Message version: `0` for v0 messages (lookup tables), `'legacy'` for the
+ * original format
+ */
+export type TransactionVersion = 'legacy' | 0;
+
+export type BuildOptions = {
+  version?: TransactionVersion,
+  lookupTables?: Array<AddressLookupTableAccount>,
+};
+
+export type BuiltTransaction = {
+  transaction: VersionedTransaction,
+  blockhash: string,
+  lastValidBlockHeight: number,
+};
+
+let defaultVersion: TransactionVersion = 0;
+
+/**
+ * Connections whose cluster rejected a v0 transaction; they are sent legacy
+ * messages from then on
+ */
+const legacyOnly: WeakSet<Connection> = new WeakSet();
+
+/**
+ * Set the message version used when a caller doesn't ask for one
+ */
+export function setDefaultTransactionVersion(version: TransactionVersion) {
+  defaultVersion = version;
+}
+
+/**
+ * Version to build for `connection`, honouring an earlier legacy fallback
+ */
+export function transactionVersionFor(
+  connection: Connection,
+  requested?: TransactionVersion,
+): TransactionVersion {
+  const version = requested === undefined ? defaultVersion : requested;
+  return legacyOnly.has(connection) ? 'legacy' : version;
+}
+
+/**
+ * Remember that the cluster behind `connection` only accepts legacy messages
+ */
+export function markLegacyOnly(connection: Connection) {
+  legacyOnly.add(connection);
+}
+
+/**
+ * Whether an RPC error means the node can't decode versioned transactions
+ */
+export function isVersionUnsupported(error: Error): boolean {
+  const message = error.message || String(error);
+  return /transaction version|versioned transaction|failed to deserialize/i.test(
+    message,
+  );
+}
+
+/**
+ * Build an unsigned transaction from `instructions`, paid by `payerKey`.
+ *
+ * v0 is the default; lookup tables are only used by v0 messages and building
+ * a legacy message with lookup tables is an error.
+ */
+export async function buildTransaction(
+  connection: Connection,
+  payerKey: PublicKey,
+  instructions: Array<TransactionInstruction>,
+  options: BuildOptions = {},
+): Promise<BuiltTransaction> {
+  const version = transactionVersionFor(connection, options.version);
+  const lookupTables = options.lookupTables || [];
+  if (version === 'legacy' && lookupTables.length > 0) {
+    throw new Error('Address lookup tables require a v0 transaction');
+  }
+
+  const {blockhash, lastValidBlockHeight} = await connection.getLatestBlockhash();
+  const message = new TransactionMessage({
+    payerKey,
+    instructions,
+    recentBlockhash: blockhash,
+  });
+
+  return {
+    transaction: new VersionedTransaction(
+      version === 'legacy'
+        ? message.compileToLegacyMessage()
+        : message.compileToV0Message(lookupTables),
+    ),
+    blockhash,
+    lastValidBlockHeight,
+  };
+}
+
+/**
+ * Add the signatures of `signers`, keeping any already present.
+ *
+ * Used for payloads signed by several parties: the consumer signs first and
+ * the executor adds the fee payer signature before submitting.
+ */
+export function signPayload(
+  transaction: VersionedTransaction,
+  ...signers: Array<Signer>
+): VersionedTransaction {
+  if (signers.length > 0) {
+    transaction.sign(signers);
+  }
+  return transaction;
+}
+
+/**
+ * Required signers that haven't signed `transaction` yet
+ */
+export function missingSigners(
+  transaction: VersionedTransaction,
+): Array<PublicKey> {
+  const {header, staticAccountKeys} = transaction.message;
+  return staticAccountKeys
+    .slice(0, header.numRequiredSignatures)
+    .filter((_, i) => transaction.signatures[i].every(byte => byte === 0));
+}
+
+/**
+ * Encode a (partially) signed payload for transport between services
+ */
+export function serializePayload(transaction: VersionedTransaction): string {
+  return Buffer.from(transaction.serialize()).toString('base64');
+}
+
+/**
+ * Decode a payload produced by `serializePayload`; legacy and v0 messages are
+ * both accepted
+ */
+export function deserializePayload(payload: string): VersionedTransaction {
+  return VersionedTransaction.deserialize(Buffer.from(payload, 'base64'));
+}
+
+/**
+ * Simulate `transaction` under the payload `simulated` span.
+ *
+ * Unless `sigVerify` is set the blockhash is replaced by the node, so an
+ * unsigned or partially signed payload can be simulated.
+ */
+export async function simulateTransaction(
+  title: string,
+  connection: Connection,
+  transaction: VersionedTransaction,
+  sigVerify: boolean = false,
+): Promise<SimulatedTransactionResponse> {
+  return traced(
+    SpanNames.SIMULATED,
+    {'solgsn.title': title, 'solgsn.tx_version': String(transaction.version)},
+    async () => {
+      const {value} = await connection.simulateTransaction(transaction, {
+        sigVerify,
+        replaceRecentBlockhash: !sigVerify,
+      });
+      return value;
+    },
+  );
+}
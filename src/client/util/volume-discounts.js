@@ -0,0 +1,58 @@
+// @flow
+
+import {TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_VOLUME_DISCOUNTS = 52;
+
+/**
+ * Most tiers of a `SetVolumeDiscounts`
+ */
+export const MAX_VOLUME_DISCOUNT_TIERS = 8;
+
+/**
+ * `discountBps` off the lamport fees of a consumer from its `minTxs`th
+ * submission in an epoch
+ */
+export type VolumeDiscountTier = {
+  minTxs: BN | number,
+  discountBps: number,
+};
+
+/**
+ * Encode a `SetVolumeDiscounts` replacing the tiers, in increasing order of
+ * `minTxs` with discounts that don't decrease. No tiers turn the discounts
+ * off. The governance authority signs it.
+ */
+export function setVolumeDiscountsInstruction(
+  programId: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  tiers: Array<VolumeDiscountTier>,
+): TransactionInstruction {
+  if (tiers.length > MAX_VOLUME_DISCOUNT_TIERS) {
+    throw new Error(
+      `At most ${MAX_VOLUME_DISCOUNT_TIERS} volume discount tiers are allowed`,
+    );
+  }
+  const encoded = tiers.map(({minTxs, discountBps}) => {
+    const discount = Buffer.alloc(2);
+    discount.writeUInt16LE(discountBps, 0);
+    return Buffer.concat([
+      new BN(minTxs.toString()).toArrayLike(Buffer, 'le', 8),
+      discount,
+    ]);
+  });
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: configAccount, isSigner: false, isWritable: true},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.concat([
+      Buffer.from([SET_VOLUME_DISCOUNTS, tiers.length]),
+      ...encoded,
+    ]),
+  });
+}
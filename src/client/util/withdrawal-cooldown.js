@@ -0,0 +1,82 @@
+// @flow
+
+import {SystemProgram, TransactionInstruction} from '@solana/web3.js';
+import type {PublicKey} from '@solana/web3.js';
+import BN from 'bn.js';
+
+const SET_WITHDRAWAL_COOLDOWN = 66;
+const REQUEST_WITHDRAWAL = 67;
+const ENABLE_EMERGENCY_WITHDRAWALS = 68;
+
+function u64Bytes(value: BN | number): Buffer {
+  return new BN(value.toString()).toArrayLike(Buffer, 'le', 8);
+}
+
+/**
+ * Encode a `SetWithdrawalCooldown` making withdrawals wait `slots` slots
+ * after their request. A cooldown of 0 lets them through at once. The
+ * governance authority signs it.
+ */
+export function setWithdrawalCooldownInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+  slots: BN | number,
+): TransactionInstruction {
+  const data = Buffer.concat([Buffer.from([SET_WITHDRAWAL_COOLDOWN]), u64Bytes(slots)]);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
+
+/**
+ * Encode a `RequestWithdrawal` of `amount` lamports of the consumer's
+ * balance, withdrawable once the cooldown passed. The consumer signs it and
+ * pays for any state growth.
+ */
+export function requestWithdrawalInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  consumer: PublicKey,
+  amount: BN | number,
+): TransactionInstruction {
+  const data = Buffer.concat([Buffer.from([REQUEST_WITHDRAWAL]), u64Bytes(amount)]);
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: consumer, isSigner: true, isWritable: true},
+      {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
+    ],
+    programId,
+    data,
+  });
+}
+
+/**
+ * Encode an `EnableEmergencyWithdrawals`, letting consumers withdraw from a
+ * paused deployment without waiting out the cooldown until `Unpause`. The
+ * governance authority signs it.
+ */
+export function enableEmergencyWithdrawalsInstruction(
+  programId: PublicKey,
+  gsnAccount: PublicKey,
+  configAccount: PublicKey,
+  authority: PublicKey,
+): TransactionInstruction {
+  return new TransactionInstruction({
+    keys: [
+      {pubkey: gsnAccount, isSigner: false, isWritable: true},
+      {pubkey: configAccount, isSigner: false, isWritable: false},
+      {pubkey: authority, isSigner: true, isWritable: false},
+    ],
+    programId,
+    data: Buffer.from([ENABLE_EMERGENCY_WITHDRAWALS]),
+  });
+}
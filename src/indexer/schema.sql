@@ -0,0 +1,93 @@
+-- SolGSN indexer schema, shared by the SQLite and Postgres backends.
+
+-- One row per GSN event log line. `kind` is the tag printed by the program
+-- (TOPUP, EXECUTION_SUCCESS, FEE_DEDUCTION, EXECUTOR_CLAIM_COMPLETE, ...).
+-- Lamport amounts are stored as BIGINT; fields an event does not carry are NULL.
+-- `succeeded` is false for events logged by a transaction that was rolled back.
+CREATE TABLE IF NOT EXISTS gsn_events (
+    signature        TEXT    NOT NULL,
+    slot             BIGINT  NOT NULL,
+    block_time       BIGINT,
+    log_index        INTEGER NOT NULL,
+    succeeded        BOOLEAN NOT NULL,
+    kind             TEXT    NOT NULL,
+    consumer         TEXT,
+    executor         TEXT,
+    program          TEXT,
+    amount           BIGINT,
+    fee              BIGINT,
+    nonce            BIGINT,
+    previous_balance BIGINT,
+    new_balance      BIGINT,
+    raw              TEXT    NOT NULL,
+    PRIMARY KEY (signature, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS gsn_events_kind_slot ON gsn_events (kind, slot);
+CREATE INDEX IF NOT EXISTS gsn_events_kind_time ON gsn_events (kind, block_time);
+CREATE INDEX IF NOT EXISTS gsn_events_consumer ON gsn_events (consumer);
+CREATE INDEX IF NOT EXISTS gsn_events_executor ON gsn_events (executor);
+
+-- Newest signature indexed per program, used to resume backfill.
+CREATE TABLE IF NOT EXISTS gsn_cursor (
+    program_id     TEXT   PRIMARY KEY,
+    last_signature TEXT   NOT NULL,
+    last_slot      BIGINT NOT NULL
+);
+
+-- Projections of committed events, kept up to date as events are indexed.
+-- Amounts are lamports; fees and top-ups paid in SPL tokens are left out of
+-- the totals but still counted as executions and kept in receipts.
+
+-- Activity per consumer.
+CREATE TABLE IF NOT EXISTS gsn_consumers (
+    consumer   TEXT   PRIMARY KEY,
+    executions BIGINT NOT NULL,
+    fees_paid  BIGINT NOT NULL,
+    topped_up  BIGINT NOT NULL,
+    first_slot BIGINT NOT NULL,
+    last_slot  BIGINT NOT NULL
+);
+
+-- Activity per executor.
+CREATE TABLE IF NOT EXISTS gsn_executors (
+    executor    TEXT   PRIMARY KEY,
+    executions  BIGINT NOT NULL,
+    fees_earned BIGINT NOT NULL,
+    claimed     BIGINT NOT NULL,
+    first_slot  BIGINT NOT NULL,
+    last_slot   BIGINT NOT NULL
+);
+
+-- Latest known balance of consumers and executors, per mint ('' for lamports).
+CREATE TABLE IF NOT EXISTS gsn_balances (
+    account TEXT   NOT NULL,
+    role    TEXT   NOT NULL,
+    mint    TEXT   NOT NULL,
+    balance BIGINT NOT NULL,
+    slot    BIGINT NOT NULL,
+    PRIMARY KEY (account, role, mint)
+);
+
+-- One row per committed execution.
+CREATE TABLE IF NOT EXISTS gsn_receipts (
+    signature      TEXT    NOT NULL,
+    log_index      INTEGER NOT NULL,
+    slot           BIGINT  NOT NULL,
+    block_time     BIGINT,
+    consumer       TEXT    NOT NULL,
+    executor       TEXT,
+    target_program TEXT,
+    amount         BIGINT,
+    lane           BIGINT,
+    nonce          BIGINT,
+    fee            BIGINT,
+    tip            BIGINT,
+    fee_mint       TEXT,
+    charged        TEXT,
+    executor_fee   BIGINT,
+    PRIMARY KEY (signature, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS gsn_receipts_consumer ON gsn_receipts (consumer, slot);
+CREATE INDEX IF NOT EXISTS gsn_receipts_executor ON gsn_receipts (executor, slot);
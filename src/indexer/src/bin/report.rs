@@ -0,0 +1,49 @@
+use clap::Parser;
+use solgsn_indexer::{
+    report::{self, GroupBy, ReportFormat},
+    Store,
+};
+use std::process::exit;
+
+/// Summarize fees paid per consumer or per program from an indexer database.
+#[derive(Parser, Debug)]
+#[command(name = "solgsn-report", version)]
+struct Args {
+    /// SQLite file path or postgres:// connection URL
+    #[arg(long, default_value = "solgsn-index.db")]
+    database: String,
+
+    /// Start of the range (inclusive), unix seconds or YYYY-MM-DD
+    #[arg(long, value_parser = |s: &str| report::parse_time(s, false), default_value = "0")]
+    from: i64,
+
+    /// End of the range, unix seconds (exclusive) or YYYY-MM-DD (inclusive)
+    #[arg(long, value_parser = |s: &str| report::parse_time(s, true))]
+    to: Option<i64>,
+
+    /// consumer or program
+    #[arg(long, default_value = "consumer")]
+    group_by: GroupBy,
+
+    /// csv or json
+    #[arg(long, default_value = "csv")]
+    format: ReportFormat,
+}
+
+fn run(args: Args) -> solgsn_indexer::error::Result<()> {
+    let mut store = Store::open(&args.database)?;
+    let rows = store.fee_rows(args.from, args.to.unwrap_or(i64::MAX))?;
+    let lines = report::summarize(&rows, args.group_by);
+    match args.format {
+        ReportFormat::Csv => print!("{}", report::to_csv(&lines, args.group_by)),
+        ReportFormat::Json => println!("{}", report::to_json(&lines)),
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(error) = run(Args::parse()) {
+        eprintln!("[REPORT] error={}", error);
+        exit(1);
+    }
+}
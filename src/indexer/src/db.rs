@@ -0,0 +1,545 @@
+use crate::{
+    error::Result,
+    events::{EventKind, GsnEvent},
+};
+use std::convert::TryFrom;
+
+/// Schema applied on startup, see `schema.sql` for column documentation.
+pub const SCHEMA: &str = include_str!("../schema.sql");
+
+const INSERT_EVENT_SQLITE: &str = "INSERT INTO gsn_events \
+    (signature, slot, block_time, log_index, succeeded, kind, consumer, executor, program, amount, fee, nonce, previous_balance, new_balance, raw) \
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15) \
+    ON CONFLICT DO NOTHING";
+
+const INSERT_EVENT_POSTGRES: &str = "INSERT INTO gsn_events \
+    (signature, slot, block_time, log_index, succeeded, kind, consumer, executor, program, amount, fee, nonce, previous_balance, new_balance, raw) \
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15) \
+    ON CONFLICT DO NOTHING";
+
+const UPSERT_CURSOR_SQLITE: &str = "INSERT INTO gsn_cursor (program_id, last_signature, last_slot) \
+    VALUES (?1, ?2, ?3) \
+    ON CONFLICT (program_id) DO UPDATE SET last_signature = excluded.last_signature, last_slot = excluded.last_slot";
+
+const UPSERT_CURSOR_POSTGRES: &str = "INSERT INTO gsn_cursor (program_id, last_signature, last_slot) \
+    VALUES ($1, $2, $3) \
+    ON CONFLICT (program_id) DO UPDATE SET last_signature = excluded.last_signature, last_slot = excluded.last_slot";
+
+const FEE_ROWS_SQLITE: &str = "SELECT f.consumer, s.program, f.fee, f.block_time \
+    FROM gsn_events f \
+    LEFT JOIN gsn_events s ON s.signature = f.signature AND s.kind = 'EXECUTION_START' \
+    WHERE f.kind = 'FEE_DEDUCTION' AND f.succeeded AND f.block_time >= ?1 AND f.block_time < ?2 \
+    ORDER BY f.block_time, f.signature";
+
+const FEE_ROWS_POSTGRES: &str = "SELECT f.consumer, s.program, f.fee, f.block_time \
+    FROM gsn_events f \
+    LEFT JOIN gsn_events s ON s.signature = f.signature AND s.kind = 'EXECUTION_START' \
+    WHERE f.kind = 'FEE_DEDUCTION' AND f.succeeded AND f.block_time >= $1 AND f.block_time < $2 \
+    ORDER BY f.block_time, f.signature";
+
+const UPSERT_CONSUMER_SQLITE: &str = "INSERT INTO gsn_consumers \
+    (consumer, executions, fees_paid, topped_up, first_slot, last_slot) \
+    VALUES (?1, ?2, ?3, ?4, ?5, ?5) \
+    ON CONFLICT (consumer) DO UPDATE SET \
+    executions = gsn_consumers.executions + excluded.executions, \
+    fees_paid = gsn_consumers.fees_paid + excluded.fees_paid, \
+    topped_up = gsn_consumers.topped_up + excluded.topped_up, \
+    first_slot = MIN(gsn_consumers.first_slot, excluded.first_slot), \
+    last_slot = MAX(gsn_consumers.last_slot, excluded.last_slot)";
+
+const UPSERT_CONSUMER_POSTGRES: &str = "INSERT INTO gsn_consumers \
+    (consumer, executions, fees_paid, topped_up, first_slot, last_slot) \
+    VALUES ($1, $2, $3, $4, $5, $5) \
+    ON CONFLICT (consumer) DO UPDATE SET \
+    executions = gsn_consumers.executions + excluded.executions, \
+    fees_paid = gsn_consumers.fees_paid + excluded.fees_paid, \
+    topped_up = gsn_consumers.topped_up + excluded.topped_up, \
+    first_slot = LEAST(gsn_consumers.first_slot, excluded.first_slot), \
+    last_slot = GREATEST(gsn_consumers.last_slot, excluded.last_slot)";
+
+const UPSERT_EXECUTOR_SQLITE: &str = "INSERT INTO gsn_executors \
+    (executor, executions, fees_earned, claimed, first_slot, last_slot) \
+    VALUES (?1, ?2, ?3, ?4, ?5, ?5) \
+    ON CONFLICT (executor) DO UPDATE SET \
+    executions = gsn_executors.executions + excluded.executions, \
+    fees_earned = gsn_executors.fees_earned + excluded.fees_earned, \
+    claimed = gsn_executors.claimed + excluded.claimed, \
+    first_slot = MIN(gsn_executors.first_slot, excluded.first_slot), \
+    last_slot = MAX(gsn_executors.last_slot, excluded.last_slot)";
+
+const UPSERT_EXECUTOR_POSTGRES: &str = "INSERT INTO gsn_executors \
+    (executor, executions, fees_earned, claimed, first_slot, last_slot) \
+    VALUES ($1, $2, $3, $4, $5, $5) \
+    ON CONFLICT (executor) DO UPDATE SET \
+    executions = gsn_executors.executions + excluded.executions, \
+    fees_earned = gsn_executors.fees_earned + excluded.fees_earned, \
+    claimed = gsn_executors.claimed + excluded.claimed, \
+    first_slot = LEAST(gsn_executors.first_slot, excluded.first_slot), \
+    last_slot = GREATEST(gsn_executors.last_slot, excluded.last_slot)";
+
+// Backfilled history may arrive after newer live events; older slots don't
+// overwrite newer balances
+const UPSERT_BALANCE_SQLITE: &str = "INSERT INTO gsn_balances (account, role, mint, balance, slot) \
+    VALUES (?1, ?2, ?3, ?4, ?5) \
+    ON CONFLICT (account, role, mint) DO UPDATE SET balance = excluded.balance, slot = excluded.slot \
+    WHERE excluded.slot >= gsn_balances.slot";
+
+const UPSERT_BALANCE_POSTGRES: &str = "INSERT INTO gsn_balances (account, role, mint, balance, slot) \
+    VALUES ($1, $2, $3, $4, $5) \
+    ON CONFLICT (account, role, mint) DO UPDATE SET balance = excluded.balance, slot = excluded.slot \
+    WHERE excluded.slot >= gsn_balances.slot";
+
+const INSERT_RECEIPT_SQLITE: &str = "INSERT INTO gsn_receipts \
+    (signature, log_index, slot, block_time, consumer, executor, target_program, amount, lane, nonce, fee, tip, fee_mint, charged, executor_fee) \
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15) \
+    ON CONFLICT DO NOTHING";
+
+const INSERT_RECEIPT_POSTGRES: &str = "INSERT INTO gsn_receipts \
+    (signature, log_index, slot, block_time, consumer, executor, target_program, amount, lane, nonce, fee, tip, fee_mint, charged, executor_fee) \
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15) \
+    ON CONFLICT DO NOTHING";
+
+/// `gsn_balances.mint` of lamport balances
+const LAMPORTS: &str = "";
+
+/// A decoded event together with the transaction it came from.
+#[derive(Clone, Debug)]
+pub struct EventRecord {
+    pub signature: String,
+    pub slot: u64,
+    /// Unix timestamp of the block, when the RPC node knows it
+    pub block_time: Option<i64>,
+    /// Position of the event among the GSN events of its transaction
+    pub log_index: u32,
+    /// Whether the transaction that logged the event was committed
+    pub succeeded: bool,
+    pub event: GsnEvent,
+}
+
+/// A committed fee deduction joined with the program it paid for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeRow {
+    pub consumer: String,
+    /// Target program from the matching `EXECUTION_START`, if it was logged
+    pub program: Option<String>,
+    pub fee: i64,
+    pub block_time: i64,
+}
+
+/// Row values in column order, lamport amounts narrowed to BIGINT.
+struct EventRow<'a> {
+    signature: &'a str,
+    slot: i64,
+    block_time: Option<i64>,
+    log_index: i32,
+    succeeded: bool,
+    kind: &'static str,
+    consumer: Option<&'a str>,
+    executor: Option<&'a str>,
+    program: Option<&'a str>,
+    amount: Option<i64>,
+    fee: Option<i64>,
+    nonce: Option<i64>,
+    previous_balance: Option<i64>,
+    new_balance: Option<i64>,
+    raw: &'a str,
+}
+
+impl<'a> From<&'a EventRecord> for EventRow<'a> {
+    fn from(record: &'a EventRecord) -> Self {
+        let event = &record.event;
+        let bigint = |v: Option<u64>| v.and_then(|v| i64::try_from(v).ok());
+        Self {
+            signature: &record.signature,
+            slot: i64::try_from(record.slot).unwrap_or(i64::MAX),
+            block_time: record.block_time,
+            log_index: i32::try_from(record.log_index).unwrap_or(i32::MAX),
+            succeeded: record.succeeded,
+            kind: event.kind.as_str(),
+            consumer: event.consumer(),
+            executor: event.executor(),
+            program: event.field("target_program"),
+            amount: bigint(event.amount()),
+            fee: bigint(event.fee()),
+            nonce: bigint(event.field_u64("nonce")),
+            previous_balance: bigint(event.field_u64("previous_balance")),
+            new_balance: bigint(event.field_u64("new_balance")),
+            raw: &event.raw,
+        }
+    }
+}
+
+/// Totals an event adds to a `gsn_consumers` or `gsn_executors` row.
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    executions: i64,
+    /// `fees_paid` of consumers, `fees_earned` of executors
+    fees: i64,
+    /// `topped_up` of consumers, `claimed` of executors
+    funds: i64,
+}
+
+/// Storage backend, selected from the database argument.
+pub enum Store {
+    Sqlite(rusqlite::Connection),
+    /// Boxed, the client is much larger than a SQLite connection
+    Postgres(Box<postgres::Client>),
+}
+
+impl Store {
+    /// Open a `postgres://` / `postgresql://` URL, or treat anything else as a SQLite path.
+    pub fn open(database: &str) -> Result<Self> {
+        let store = if database.starts_with("postgres://") || database.starts_with("postgresql://")
+        {
+            Store::Postgres(Box::new(postgres::Client::connect(database, postgres::NoTls)?))
+        } else {
+            Store::Sqlite(rusqlite::Connection::open(database)?)
+        };
+        Ok(store)
+    }
+
+    /// Create tables and indexes if they don't exist yet.
+    pub fn migrate(&mut self) -> Result<()> {
+        match self {
+            Store::Sqlite(conn) => conn.execute_batch(SCHEMA)?,
+            Store::Postgres(client) => client.batch_execute(SCHEMA)?,
+        }
+        Ok(())
+    }
+
+    /// Insert an event. Returns `false` if it was already indexed.
+    pub fn insert_event(&mut self, record: &EventRecord) -> Result<bool> {
+        let row = EventRow::from(record);
+        let inserted = match self {
+            Store::Sqlite(conn) => conn.execute(
+                INSERT_EVENT_SQLITE,
+                rusqlite::params![
+                    row.signature,
+                    row.slot,
+                    row.block_time,
+                    row.log_index,
+                    row.succeeded,
+                    row.kind,
+                    row.consumer,
+                    row.executor,
+                    row.program,
+                    row.amount,
+                    row.fee,
+                    row.nonce,
+                    row.previous_balance,
+                    row.new_balance,
+                    row.raw,
+                ],
+            )? as u64,
+            Store::Postgres(client) => client.execute(
+                INSERT_EVENT_POSTGRES,
+                &[
+                    &row.signature,
+                    &row.slot,
+                    &row.block_time,
+                    &row.log_index,
+                    &row.succeeded,
+                    &row.kind,
+                    &row.consumer,
+                    &row.executor,
+                    &row.program,
+                    &row.amount,
+                    &row.fee,
+                    &row.nonce,
+                    &row.previous_balance,
+                    &row.new_balance,
+                    &row.raw,
+                ],
+            )?,
+        };
+        if inserted > 0 && record.succeeded {
+            self.project(record)?;
+        }
+        Ok(inserted > 0)
+    }
+
+    /// Update the consumer, executor, balance and receipt tables with a newly
+    /// indexed, committed event.
+    fn project(&mut self, record: &EventRecord) -> Result<()> {
+        let event = &record.event;
+        let slot = i64::try_from(record.slot).unwrap_or(i64::MAX);
+        let bigint = |v: Option<u64>| v.and_then(|v| i64::try_from(v).ok()).unwrap_or(0);
+        // Token amounts aren't added to the lamport totals
+        let lamports = |mint: &str| event.field(mint).is_none();
+        match event.kind {
+            EventKind::Topup => {
+                if let Some(consumer) = event.consumer() {
+                    if lamports("mint") {
+                        let topped_up = bigint(event.amount());
+                        let totals = Totals {
+                            funds: topped_up,
+                            ..Totals::default()
+                        };
+                        self.upsert_consumer(consumer, totals, slot)?;
+                    }
+                    if let Some(balance) = event.field_u64("new_balance") {
+                        let mint = event.field("mint").unwrap_or(LAMPORTS);
+                        self.set_balance(consumer, "consumer", mint, bigint(Some(balance)), slot)?;
+                    }
+                }
+            }
+            EventKind::ExecutionSuccess => {
+                let paid = if lamports("fee_mint") {
+                    bigint(event.fee()) + bigint(event.field_u64("tip"))
+                } else {
+                    0
+                };
+                if let Some(consumer) = event.consumer() {
+                    let totals = Totals {
+                        executions: 1,
+                        fees: paid,
+                        ..Totals::default()
+                    };
+                    self.upsert_consumer(consumer, totals, slot)?;
+                }
+                if let Some(executor) = event.executor() {
+                    let earned = if lamports("fee_mint") {
+                        bigint(event.field_u64("executor_fee"))
+                    } else {
+                        0
+                    };
+                    let totals = Totals {
+                        executions: 1,
+                        fees: earned,
+                        ..Totals::default()
+                    };
+                    self.upsert_executor(executor, totals, slot)?;
+                }
+                self.insert_receipt(record)?;
+            }
+            EventKind::FeeDeduction => {
+                if let (Some(consumer), Some(balance)) =
+                    (event.consumer(), event.field_u64("new_balance"))
+                {
+                    self.set_balance(consumer, "consumer", LAMPORTS, bigint(Some(balance)), slot)?;
+                }
+            }
+            EventKind::ExecutorCredit => {
+                if let (Some(executor), Some(balance)) =
+                    (event.executor(), event.field_u64("new_balance"))
+                {
+                    self.set_balance(executor, "executor", LAMPORTS, bigint(Some(balance)), slot)?;
+                }
+            }
+            EventKind::ClaimComplete => {
+                if let Some(executor) = event.executor() {
+                    if lamports("mint") {
+                        let totals = Totals {
+                            funds: bigint(event.amount()),
+                            ..Totals::default()
+                        };
+                        self.upsert_executor(executor, totals, slot)?;
+                    }
+                    // Claims pay out the executor's whole balance
+                    let mint = event.field("mint").unwrap_or(LAMPORTS);
+                    self.set_balance(executor, "executor", mint, 0, slot)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn upsert_consumer(&mut self, consumer: &str, totals: Totals, slot: i64) -> Result<()> {
+        let Totals {
+            executions,
+            fees,
+            funds,
+        } = totals;
+        match self {
+            Store::Sqlite(conn) => {
+                conn.execute(
+                    UPSERT_CONSUMER_SQLITE,
+                    rusqlite::params![consumer, executions, fees, funds, slot],
+                )?;
+            }
+            Store::Postgres(client) => {
+                client.execute(
+                    UPSERT_CONSUMER_POSTGRES,
+                    &[&consumer, &executions, &fees, &funds, &slot],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn upsert_executor(&mut self, executor: &str, totals: Totals, slot: i64) -> Result<()> {
+        let Totals {
+            executions,
+            fees,
+            funds,
+        } = totals;
+        match self {
+            Store::Sqlite(conn) => {
+                conn.execute(
+                    UPSERT_EXECUTOR_SQLITE,
+                    rusqlite::params![executor, executions, fees, funds, slot],
+                )?;
+            }
+            Store::Postgres(client) => {
+                client.execute(
+                    UPSERT_EXECUTOR_POSTGRES,
+                    &[&executor, &executions, &fees, &funds, &slot],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_balance(
+        &mut self,
+        account: &str,
+        role: &str,
+        mint: &str,
+        balance: i64,
+        slot: i64,
+    ) -> Result<()> {
+        match self {
+            Store::Sqlite(conn) => {
+                conn.execute(
+                    UPSERT_BALANCE_SQLITE,
+                    rusqlite::params![account, role, mint, balance, slot],
+                )?;
+            }
+            Store::Postgres(client) => {
+                client.execute(
+                    UPSERT_BALANCE_POSTGRES,
+                    &[&account, &role, &mint, &balance, &slot],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_receipt(&mut self, record: &EventRecord) -> Result<()> {
+        let row = EventRow::from(record);
+        let event = &record.event;
+        let consumer = match row.consumer {
+            Some(consumer) => consumer,
+            None => return Ok(()),
+        };
+        let bigint = |key: &str| event.field_u64(key).and_then(|v| i64::try_from(v).ok());
+        let (lane, tip, executor_fee) = (bigint("lane"), bigint("tip"), bigint("executor_fee"));
+        let (fee_mint, charged) = (event.field("fee_mint"), event.field("charged"));
+        match self {
+            Store::Sqlite(conn) => {
+                conn.execute(
+                    INSERT_RECEIPT_SQLITE,
+                    rusqlite::params![
+                        row.signature,
+                        row.log_index,
+                        row.slot,
+                        row.block_time,
+                        consumer,
+                        row.executor,
+                        row.program,
+                        row.amount,
+                        lane,
+                        row.nonce,
+                        row.fee,
+                        tip,
+                        fee_mint,
+                        charged,
+                        executor_fee,
+                    ],
+                )?;
+            }
+            Store::Postgres(client) => {
+                client.execute(
+                    INSERT_RECEIPT_POSTGRES,
+                    &[
+                        &row.signature,
+                        &row.log_index,
+                        &row.slot,
+                        &row.block_time,
+                        &consumer,
+                        &row.executor,
+                        &row.program,
+                        &row.amount,
+                        &lane,
+                        &row.nonce,
+                        &row.fee,
+                        &tip,
+                        &fee_mint,
+                        &charged,
+                        &executor_fee,
+                    ],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Newest signature indexed for `program_id`, if any.
+    pub fn cursor(&mut self, program_id: &str) -> Result<Option<String>> {
+        const QUERY_SQLITE: &str = "SELECT last_signature FROM gsn_cursor WHERE program_id = ?1";
+        const QUERY_POSTGRES: &str = "SELECT last_signature FROM gsn_cursor WHERE program_id = $1";
+        let cursor = match self {
+            Store::Sqlite(conn) => {
+                let mut stmt = conn.prepare(QUERY_SQLITE)?;
+                let mut rows = stmt.query(rusqlite::params![program_id])?;
+                match rows.next()? {
+                    Some(row) => Some(row.get(0)?),
+                    None => None,
+                }
+            }
+            Store::Postgres(client) => client
+                .query_opt(QUERY_POSTGRES, &[&program_id])?
+                .map(|row| row.get(0)),
+        };
+        Ok(cursor)
+    }
+
+    pub fn set_cursor(&mut self, program_id: &str, signature: &str, slot: u64) -> Result<()> {
+        let slot = i64::try_from(slot).unwrap_or(i64::MAX);
+        match self {
+            Store::Sqlite(conn) => {
+                conn.execute(
+                    UPSERT_CURSOR_SQLITE,
+                    rusqlite::params![program_id, signature, slot],
+                )?;
+            }
+            Store::Postgres(client) => {
+                client.execute(UPSERT_CURSOR_POSTGRES, &[&program_id, &signature, &slot])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fees deducted by committed transactions with `from <= block_time < to`,
+    /// oldest first. Events without a known block time are skipped.
+    pub fn fee_rows(&mut self, from: i64, to: i64) -> Result<Vec<FeeRow>> {
+        let rows = match self {
+            Store::Sqlite(conn) => {
+                let mut stmt = conn.prepare(FEE_ROWS_SQLITE)?;
+                let rows = stmt
+                    .query_map(rusqlite::params![from, to], |row| {
+                        Ok(FeeRow {
+                            consumer: row.get(0)?,
+                            program: row.get(1)?,
+                            fee: row.get(2)?,
+                            block_time: row.get(3)?,
+                        })
+                    })?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                rows
+            }
+            Store::Postgres(client) => client
+                .query(FEE_ROWS_POSTGRES, &[&from, &to])?
+                .iter()
+                .map(|row| FeeRow {
+                    consumer: row.get(0),
+                    program: row.get(1),
+                    fee: row.get(2),
+                    block_time: row.get(3),
+                })
+                .collect(),
+        };
+        Ok(rows)
+    }
+}
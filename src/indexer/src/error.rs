@@ -0,0 +1,34 @@
+use solana_client::{client_error::ClientError as RpcError, pubsub_client::PubsubClientError};
+use thiserror::Error;
+
+/// Errors that may be returned by the indexer.
+#[derive(Debug, Error)]
+pub enum IndexerError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Postgres error: {0}")]
+    Postgres(#[from] postgres::Error),
+    /// Boxed like `PubSub`, the RPC errors are much larger than the others
+    #[error("RPC error: {0}")]
+    Rpc(Box<RpcError>),
+    #[error("PubSub error: {0}")]
+    PubSub(Box<PubsubClientError>),
+    #[error("gRPC transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+}
+
+impl From<RpcError> for IndexerError {
+    fn from(error: RpcError) -> Self {
+        Self::Rpc(Box::new(error))
+    }
+}
+
+impl From<PubsubClientError> for IndexerError {
+    fn from(error: PubsubClientError) -> Self {
+        Self::PubSub(Box::new(error))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, IndexerError>;
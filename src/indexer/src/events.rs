@@ -0,0 +1,247 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+use solgsn::event::Event;
+use std::collections::BTreeMap;
+
+/// Prefix the runtime puts in front of every `msg!` line.
+const PROGRAM_LOG_PREFIX: &str = "Program log: ";
+
+/// Prefix the runtime puts in front of every `sol_log_data` line.
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// `schema` of JSON events understood by this parser, see the program's `log` module.
+const JSON_EVENT_SCHEMA: &str = "solgsn.event.v1";
+
+/// Kind of event, taken from the `[TAG]` the program prints or the binary
+/// event it emits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Topup,
+    ExecutionStart,
+    ExecutionSuccess,
+    ExecutionFailed,
+    FeeDeduction,
+    ExecutorCredit,
+    ClaimStart,
+    ClaimSuccess,
+    ClaimFailed,
+    ClaimComplete,
+}
+
+impl EventKind {
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Some(match tag {
+            "TOPUP" => Self::Topup,
+            "EXECUTION_START" => Self::ExecutionStart,
+            "EXECUTION_SUCCESS" => Self::ExecutionSuccess,
+            "EXECUTION_FAILED" => Self::ExecutionFailed,
+            "FEE_DEDUCTION" => Self::FeeDeduction,
+            "EXECUTOR_CREDIT" => Self::ExecutorCredit,
+            "EXECUTOR_CLAIM_START" => Self::ClaimStart,
+            "EXECUTOR_CLAIM_SUCCESS" => Self::ClaimSuccess,
+            "EXECUTOR_CLAIM_FAILED" => Self::ClaimFailed,
+            "EXECUTOR_CLAIM_COMPLETE" => Self::ClaimComplete,
+            _ => return None,
+        })
+    }
+
+    /// Tag as printed by the program, also used as the `kind` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Topup => "TOPUP",
+            Self::ExecutionStart => "EXECUTION_START",
+            Self::ExecutionSuccess => "EXECUTION_SUCCESS",
+            Self::ExecutionFailed => "EXECUTION_FAILED",
+            Self::FeeDeduction => "FEE_DEDUCTION",
+            Self::ExecutorCredit => "EXECUTOR_CREDIT",
+            Self::ClaimStart => "EXECUTOR_CLAIM_START",
+            Self::ClaimSuccess => "EXECUTOR_CLAIM_SUCCESS",
+            Self::ClaimFailed => "EXECUTOR_CLAIM_FAILED",
+            Self::ClaimComplete => "EXECUTOR_CLAIM_COMPLETE",
+        }
+    }
+}
+
+/// A single structured event decoded from a program log line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GsnEvent {
+    pub kind: EventKind,
+    pub fields: BTreeMap<String, String>,
+    /// The log line without the runtime prefix
+    pub raw: String,
+}
+
+impl GsnEvent {
+    /// Parse a line of the form `Program log: [TAG] key=value key=value ...`,
+    /// its JSON equivalent from programs built with `json-logs`, or a binary
+    /// `Program data: <base64>` event.
+    /// Lines that are not GSN events return `None`.
+    pub fn parse(line: &str) -> Option<Self> {
+        if let Some(data) = line.strip_prefix(PROGRAM_DATA_PREFIX) {
+            return Self::parse_data(data);
+        }
+        let body = line.strip_prefix(PROGRAM_LOG_PREFIX).unwrap_or(line);
+        if body.starts_with('{') {
+            return Self::parse_json(body);
+        }
+
+        let rest = body.strip_prefix('[')?;
+        let close = rest.find(']')?;
+        let kind = EventKind::from_tag(&rest[..close])?;
+
+        let mut fields = BTreeMap::new();
+        let mut last_key: Option<String> = None;
+        for token in rest[close + 1..].split_whitespace() {
+            match token.find('=') {
+                Some(eq) => {
+                    let key = token[..eq].to_string();
+                    fields.insert(key.clone(), token[eq + 1..].to_string());
+                    last_key = Some(key);
+                }
+                // Values such as `{:?}`-formatted errors may contain spaces
+                None => {
+                    if let Some(value) = last_key.as_ref().and_then(|k| fields.get_mut(k)) {
+                        value.push(' ');
+                        value.push_str(token);
+                    }
+                }
+            }
+        }
+
+        Some(Self {
+            kind,
+            fields,
+            raw: body.to_string(),
+        })
+    }
+
+    /// Parse a `{"schema":"solgsn.event.v1","event":"TAG",...}` line.
+    fn parse_json(body: &str) -> Option<Self> {
+        let object = match serde_json::from_str::<Value>(body).ok()? {
+            Value::Object(object) => object,
+            _ => return None,
+        };
+        if object.get("schema").and_then(Value::as_str) != Some(JSON_EVENT_SCHEMA) {
+            return None;
+        }
+        let kind = EventKind::from_tag(object.get("event")?.as_str()?)?;
+
+        let fields = object
+            .into_iter()
+            .filter(|(key, _)| key != "schema" && key != "event")
+            .map(|(key, value)| match value {
+                Value::String(value) => (key, value),
+                value => (key, value.to_string()),
+            })
+            .collect();
+
+        Some(Self {
+            kind,
+            fields,
+            raw: body.to_string(),
+        })
+    }
+
+    /// Parse the base64 `data` of a binary event. Its fields are named like
+    /// those of the equivalent text events; governance events aren't indexed.
+    fn parse_data(data: &str) -> Option<Self> {
+        let bytes = STANDARD.decode(data.trim()).ok()?;
+        let mut fields = BTreeMap::new();
+        let mut set = |key: &str, value: String| {
+            fields.insert(key.to_string(), value);
+        };
+        let kind = match Event::decode(&bytes)? {
+            Event::Topup(event) => {
+                set("consumer", event.consumer.to_string());
+                if let Some(mint) = event.mint {
+                    set("mint", mint.to_string());
+                }
+                set("amount", event.amount.to_string());
+                set("previous_balance", event.previous_balance.to_string());
+                set("new_balance", event.new_balance.to_string());
+                EventKind::Topup
+            }
+            Event::Execution(event) => {
+                set("consumer", event.consumer.to_string());
+                set("executor", event.executor.to_string());
+                set("target_program", event.target_program.to_string());
+                set("amount", event.amount.to_string());
+                set("lane", event.lane.to_string());
+                set("nonce", event.nonce.to_string());
+                set("fee", event.fee.to_string());
+                set("tip", event.tip.to_string());
+                if let Some(mint) = event.fee_mint {
+                    set("fee_mint", mint.to_string());
+                }
+                if let Some(charged) = event.charged {
+                    set("charged", charged.to_string());
+                }
+                set("executor_fee", event.executor_fee.to_string());
+                EventKind::ExecutionSuccess
+            }
+            Event::FeeClaim(event) => {
+                set("executor", event.executor.to_string());
+                set("destination", event.destination.to_string());
+                if let Some(mint) = event.mint {
+                    set("mint", mint.to_string());
+                }
+                set("claimed_amount", event.amount.to_string());
+                EventKind::ClaimComplete
+            }
+            Event::Governance(_) => return None,
+            // Credited like the beneficiary's own top-up, attributed to the payer
+            Event::GiftTopup(event) => {
+                set("consumer", event.beneficiary.to_string());
+                set("payer", event.payer.to_string());
+                if let Some(mint) = event.mint {
+                    set("mint", mint.to_string());
+                }
+                set("amount", event.amount.to_string());
+                set("previous_balance", event.previous_balance.to_string());
+                set("new_balance", event.new_balance.to_string());
+                EventKind::Topup
+            }
+        };
+
+        Some(Self {
+            kind,
+            fields,
+            raw: data.to_string(),
+        })
+    }
+
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    pub fn field_u64(&self, key: &str) -> Option<u64> {
+        self.field(key).and_then(|v| v.parse().ok())
+    }
+
+    pub fn consumer(&self) -> Option<&str> {
+        self.field("consumer")
+    }
+
+    pub fn executor(&self) -> Option<&str> {
+        self.field("executor")
+    }
+
+    /// Amount moved by the event; claims log it as `claimed_amount` on completion
+    pub fn amount(&self) -> Option<u64> {
+        self.field_u64("amount")
+            .or_else(|| self.field_u64("claimed_amount"))
+    }
+
+    /// Fee charged; insufficient balance failures log it as `required_fee`
+    pub fn fee(&self) -> Option<u64> {
+        self.field_u64("fee")
+            .or_else(|| self.field_u64("required_fee"))
+    }
+}
+
+/// Decode every GSN event found in a transaction's log messages, in order.
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Vec<GsnEvent> {
+    logs.iter()
+        .filter_map(|line| GsnEvent::parse(line.as_ref()))
+        .collect()
+}
@@ -0,0 +1,183 @@
+use crate::{
+    db::EventRecord,
+    error::Result,
+    events::{EventKind, GsnEvent},
+};
+use std::{net::SocketAddr, pin::Pin};
+use tokio::sync::broadcast;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+use tonic::{transport::Server, Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("solgsn.events.v1");
+}
+
+use proto::{
+    gsn_events_server::{GsnEvents, GsnEventsServer},
+    SubscribeRequest,
+};
+
+/// Number of events buffered per subscriber before it is reported as lagging.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+fn text(event: &GsnEvent, key: &str) -> String {
+    event.field(key).unwrap_or_default().to_string()
+}
+
+fn number(event: &GsnEvent, key: &str) -> u64 {
+    event.field_u64(key).unwrap_or_default()
+}
+
+/// Failure detail of an execution or claim, `reason` for checks, `error` for CPI failures
+fn failure(event: &GsnEvent) -> String {
+    event
+        .field("reason")
+        .or_else(|| event.field("error"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+impl From<EventKind> for proto::EventKind {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::Topup => Self::TopUp,
+            EventKind::ExecutionStart
+            | EventKind::ExecutionSuccess
+            | EventKind::ExecutionFailed => Self::Execution,
+            EventKind::FeeDeduction => Self::FeeDeduction,
+            EventKind::ExecutorCredit => Self::ExecutorCredit,
+            EventKind::ClaimStart
+            | EventKind::ClaimSuccess
+            | EventKind::ClaimFailed
+            | EventKind::ClaimComplete => Self::Claim,
+        }
+    }
+}
+
+impl From<&EventRecord> for proto::GsnEvent {
+    fn from(record: &EventRecord) -> Self {
+        use proto::{gsn_event::Event, ClaimStatus, ExecutionStatus};
+
+        let event = &record.event;
+        let execution = |status: ExecutionStatus| {
+            Event::Execution(proto::Execution {
+                status: status as i32,
+                consumer: text(event, "consumer"),
+                executor: text(event, "executor"),
+                amount: number(event, "amount"),
+                fee: event.fee().unwrap_or_default(),
+                nonce: number(event, "nonce"),
+                error: failure(event),
+            })
+        };
+        let claim = |status: ClaimStatus| {
+            Event::Claim(proto::Claim {
+                status: status as i32,
+                executor: text(event, "executor"),
+                amount: event.amount().unwrap_or_default(),
+                error: failure(event),
+            })
+        };
+
+        let payload = match event.kind {
+            EventKind::Topup => Event::TopUp(proto::TopUp {
+                consumer: text(event, "consumer"),
+                amount: number(event, "amount"),
+                previous_balance: number(event, "previous_balance"),
+                new_balance: number(event, "new_balance"),
+            }),
+            EventKind::ExecutionStart => execution(ExecutionStatus::Started),
+            EventKind::ExecutionSuccess => execution(ExecutionStatus::Succeeded),
+            EventKind::ExecutionFailed => execution(ExecutionStatus::Failed),
+            EventKind::FeeDeduction => Event::FeeDeduction(proto::FeeDeduction {
+                consumer: text(event, "consumer"),
+                fee: number(event, "fee"),
+                previous_balance: number(event, "previous_balance"),
+                new_balance: number(event, "new_balance"),
+            }),
+            EventKind::ExecutorCredit => Event::ExecutorCredit(proto::ExecutorCredit {
+                executor: text(event, "executor"),
+                fee: number(event, "fee"),
+                previous_balance: number(event, "previous_balance"),
+                new_balance: number(event, "new_balance"),
+            }),
+            EventKind::ClaimStart => claim(ClaimStatus::Started),
+            EventKind::ClaimSuccess => claim(ClaimStatus::Succeeded),
+            EventKind::ClaimFailed => claim(ClaimStatus::Failed),
+            EventKind::ClaimComplete => claim(ClaimStatus::Completed),
+        };
+
+        Self {
+            signature: record.signature.clone(),
+            slot: record.slot,
+            log_index: record.log_index,
+            succeeded: record.succeeded,
+            kind: proto::EventKind::from(event.kind) as i32,
+            event: Some(payload),
+        }
+    }
+}
+
+impl SubscribeRequest {
+    /// Whether `record` passes every filter set on the request.
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        let event = &record.event;
+        let listed = |filter: &[String], value: Option<&str>| {
+            filter.is_empty() || value.is_some_and(|v| filter.iter().any(|f| f == v))
+        };
+
+        (record.succeeded || self.include_failed)
+            && listed(&self.consumers, event.consumer())
+            && listed(&self.executors, event.executor())
+            && (self.kinds.is_empty()
+                || self
+                    .kinds
+                    .contains(&(proto::EventKind::from(event.kind) as i32)))
+    }
+}
+
+/// gRPC service fanning out indexed events to subscribers.
+pub struct EventService {
+    sender: broadcast::Sender<EventRecord>,
+}
+
+impl EventService {
+    pub fn new(sender: broadcast::Sender<EventRecord>) -> Self {
+        Self { sender }
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = std::result::Result<proto::GsnEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl GsnEvents for EventService {
+    type SubscribeStream = EventStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> std::result::Result<Response<Self::SubscribeStream>, Status> {
+        let filter = request.into_inner();
+        let stream =
+            BroadcastStream::new(self.sender.subscribe()).filter_map(move |item| match item {
+                Ok(record) if filter.matches(&record) => Some(Ok(proto::GsnEvent::from(&record))),
+                Ok(_) => None,
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(Err(Status::data_loss(
+                    format!("subscriber lagged, {} events dropped", skipped),
+                ))),
+            });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Serve the event stream on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, sender: broadcast::Sender<EventRecord>) -> Result<()> {
+    Server::builder()
+        .add_service(GsnEventsServer::new(EventService::new(sender)))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
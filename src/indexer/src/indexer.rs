@@ -0,0 +1,177 @@
+use crate::{
+    db::{EventRecord, Store},
+    error::{IndexerError, Result},
+    events::parse_logs,
+};
+use solana_client::{
+    pubsub_client::PubsubClient,
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+use tokio::sync::broadcast;
+
+/// Maximum page size accepted by `getSignaturesForAddress`.
+const SIGNATURE_PAGE_LIMIT: usize = 1000;
+
+pub struct Indexer {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    store: Store,
+    commitment: CommitmentConfig,
+    /// Receives every newly indexed event, e.g. for the gRPC stream
+    events: Option<broadcast::Sender<EventRecord>>,
+}
+
+impl Indexer {
+    pub fn new(rpc_url: &str, program_id: Pubkey, store: Store) -> Self {
+        let commitment = CommitmentConfig::confirmed();
+        Self {
+            rpc: RpcClient::new_with_commitment(rpc_url.to_string(), commitment),
+            program_id,
+            store,
+            commitment,
+            events: None,
+        }
+    }
+
+    /// Publish newly indexed events on `sender` in addition to storing them.
+    pub fn with_event_sender(mut self, sender: broadcast::Sender<EventRecord>) -> Self {
+        self.events = Some(sender);
+        self
+    }
+
+    /// Index every transaction newer than the stored cursor using
+    /// `getSignaturesForAddress`, oldest first. Returns the number of new events.
+    pub fn backfill(&mut self) -> Result<usize> {
+        let program_key = self.program_id.to_string();
+        let until = match self.store.cursor(&program_key)? {
+            Some(signature) => Some(parse_signature(&signature)?),
+            None => None,
+        };
+
+        // Signatures come back newest first, page backwards until the cursor
+        let mut pending = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self.rpc.get_signatures_for_address_with_config(
+                &self.program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(SIGNATURE_PAGE_LIMIT),
+                    commitment: Some(self.commitment),
+                },
+            )?;
+            let last = match page.last() {
+                Some(status) => parse_signature(&status.signature)?,
+                None => break,
+            };
+            let full_page = page.len() == SIGNATURE_PAGE_LIMIT;
+            pending.extend(page);
+            if !full_page {
+                break;
+            }
+            before = Some(last);
+        }
+
+        let mut indexed = 0;
+        for status in pending.iter().rev() {
+            let signature = parse_signature(&status.signature)?;
+            let transaction = self.rpc.get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(self.commitment),
+                    max_supported_transaction_version: Some(0),
+                },
+            )?;
+            let logs: Vec<String> = match transaction.transaction.meta {
+                Some(meta) => Option::from(meta.log_messages).unwrap_or_default(),
+                None => Vec::new(),
+            };
+            indexed += self.index_logs(
+                &status.signature,
+                transaction.slot,
+                transaction.block_time,
+                status.err.is_none(),
+                &logs,
+            )?;
+        }
+
+        Ok(indexed)
+    }
+
+    /// Subscribe to program logs over websocket and index them as they arrive.
+    /// Blocks until the subscription is closed by the server.
+    pub fn follow(&mut self, ws_url: &str) -> Result<()> {
+        let (_subscription, receiver) = PubsubClient::logs_subscribe(
+            ws_url,
+            RpcTransactionLogsFilter::Mentions(vec![self.program_id.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(self.commitment),
+            },
+        )?;
+
+        for response in receiver.iter() {
+            let logs = response.value;
+            let slot = response.context.slot;
+            // Notifications don't carry the block time; it may not be known yet
+            let block_time = self.rpc.get_block_time(slot).ok();
+            let indexed = self.index_logs(
+                &logs.signature,
+                slot,
+                block_time,
+                logs.err.is_none(),
+                &logs.logs,
+            )?;
+            if indexed > 0 {
+                println!(
+                    "[INDEXER] signature={} slot={} events={}",
+                    logs.signature, response.context.slot, indexed
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode and store the events of one transaction, then advance the cursor.
+    fn index_logs(
+        &mut self,
+        signature: &str,
+        slot: u64,
+        block_time: Option<i64>,
+        succeeded: bool,
+        logs: &[String],
+    ) -> Result<usize> {
+        let mut indexed = 0;
+        for (log_index, event) in parse_logs(logs).into_iter().enumerate() {
+            let record = EventRecord {
+                signature: signature.to_string(),
+                slot,
+                block_time,
+                log_index: log_index as u32,
+                succeeded,
+                event,
+            };
+            if self.store.insert_event(&record)? {
+                indexed += 1;
+                if let Some(sender) = &self.events {
+                    // Sending only fails when nobody is subscribed
+                    let _ = sender.send(record);
+                }
+            }
+        }
+        self.store
+            .set_cursor(&self.program_id.to_string(), signature, slot)?;
+        Ok(indexed)
+    }
+}
+
+fn parse_signature(signature: &str) -> Result<Signature> {
+    Signature::from_str(signature)
+        .map_err(|_| IndexerError::InvalidSignature(signature.to_string()))
+}
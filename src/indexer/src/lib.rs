@@ -0,0 +1,10 @@
+pub mod db;
+pub mod error;
+pub mod events;
+pub mod grpc;
+pub mod indexer;
+pub mod report;
+
+pub use db::Store;
+pub use error::IndexerError;
+pub use indexer::Indexer;
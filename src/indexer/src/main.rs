@@ -0,0 +1,68 @@
+use clap::Parser;
+use solana_sdk::pubkey::Pubkey;
+use solgsn_indexer::{grpc, Indexer, Store};
+use std::{net::SocketAddr, process::exit, thread};
+use tokio::sync::broadcast;
+
+/// Index SolGSN program events into SQLite or Postgres.
+#[derive(Parser, Debug)]
+#[command(name = "solgsn-indexer", version)]
+struct Args {
+    /// RPC endpoint used for backfill
+    #[arg(long, default_value = "http://localhost:8899")]
+    url: String,
+
+    /// Websocket endpoint used with --follow
+    #[arg(long, default_value = "ws://localhost:8900")]
+    ws_url: String,
+
+    /// SolGSN program id
+    #[arg(long)]
+    program_id: Pubkey,
+
+    /// SQLite file path or postgres:// connection URL
+    #[arg(long, default_value = "solgsn-index.db")]
+    database: String,
+
+    /// Keep running and index new transactions from the logs subscription
+    #[arg(long)]
+    follow: bool,
+
+    /// Serve the typed event stream over gRPC on this address, e.g. 127.0.0.1:50051
+    #[arg(long)]
+    grpc_addr: Option<SocketAddr>,
+}
+
+fn run(args: Args) -> solgsn_indexer::error::Result<()> {
+    let mut store = Store::open(&args.database)?;
+    store.migrate()?;
+
+    let mut indexer = Indexer::new(&args.url, args.program_id, store);
+    if let Some(addr) = args.grpc_addr {
+        let (sender, _) = broadcast::channel(grpc::EVENT_CHANNEL_CAPACITY);
+        indexer = indexer.with_event_sender(sender.clone());
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+            if let Err(error) = runtime.block_on(grpc::serve(addr, sender)) {
+                eprintln!("[INDEXER] grpc error={}", error);
+                exit(1);
+            }
+        });
+        println!("[INDEXER] grpc listening addr={}", addr);
+    }
+
+    let indexed = indexer.backfill()?;
+    println!("[INDEXER] backfill complete events={}", indexed);
+
+    if args.follow {
+        indexer.follow(&args.ws_url)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(error) = run(Args::parse()) {
+        eprintln!("[INDEXER] error={}", error);
+        exit(1);
+    }
+}
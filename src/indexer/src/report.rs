@@ -0,0 +1,145 @@
+use crate::db::FeeRow;
+use serde::Serialize;
+use std::{collections::BTreeMap, str::FromStr};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Dimension fees are summarized by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    /// One line per consumer account
+    Consumer,
+    /// One line per target program (dapp)
+    Program,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "consumer" => Ok(Self::Consumer),
+            "program" => Ok(Self::Program),
+            _ => Err(format!("expected `consumer` or `program`, got `{}`", s)),
+        }
+    }
+}
+
+/// Output format of a report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("expected `csv` or `json`, got `{}`", s)),
+        }
+    }
+}
+
+/// Fees paid by one consumer or for one program over the report range.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReportLine {
+    /// Consumer or program address, `unknown` when the program was not logged
+    pub key: String,
+    pub executions: u64,
+    /// Total fees in lamports
+    pub total_fees: i64,
+    /// Unix timestamps of the first and last fee in the range
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+/// Aggregate fee rows, largest spenders first.
+pub fn summarize(rows: &[FeeRow], group_by: GroupBy) -> Vec<ReportLine> {
+    let mut lines: BTreeMap<String, ReportLine> = BTreeMap::new();
+    for row in rows {
+        let key = match group_by {
+            GroupBy::Consumer => row.consumer.clone(),
+            GroupBy::Program => row.program.clone().unwrap_or_else(|| "unknown".to_string()),
+        };
+        let line = lines.entry(key.clone()).or_insert_with(|| ReportLine {
+            key,
+            executions: 0,
+            total_fees: 0,
+            first_seen: row.block_time,
+            last_seen: row.block_time,
+        });
+        line.executions += 1;
+        line.total_fees = line.total_fees.saturating_add(row.fee);
+        line.first_seen = line.first_seen.min(row.block_time);
+        line.last_seen = line.last_seen.max(row.block_time);
+    }
+
+    let mut lines: Vec<ReportLine> = lines.into_values().collect();
+    // Stable sort keeps ties in key order
+    lines.sort_by_key(|line| std::cmp::Reverse(line.total_fees));
+    lines
+}
+
+/// Render report lines as CSV with a header row.
+pub fn to_csv(lines: &[ReportLine], group_by: GroupBy) -> String {
+    let key_column = match group_by {
+        GroupBy::Consumer => "consumer",
+        GroupBy::Program => "program",
+    };
+    let mut csv = format!(
+        "{},executions,total_fees,first_seen,last_seen\n",
+        key_column
+    );
+    for line in lines {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            line.key, line.executions, line.total_fees, line.first_seen, line.last_seen
+        ));
+    }
+    csv
+}
+
+/// Render report lines as a JSON array.
+pub fn to_json(lines: &[ReportLine]) -> String {
+    serde_json::to_string_pretty(lines).expect("report lines serialize")
+}
+
+/// Parse a range bound given as unix seconds or a `YYYY-MM-DD` date (UTC).
+///
+/// With `end_of_day`, a date resolves to the start of the following day so
+/// that an exclusive upper bound still covers the whole date.
+pub fn parse_time(value: &str, end_of_day: bool) -> Result<i64, String> {
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
+
+    let parts: Vec<&str> = value.split('-').collect();
+    let invalid = || format!("expected unix seconds or YYYY-MM-DD, got `{}`", value);
+    if parts.len() != 3 {
+        return Err(invalid());
+    }
+    let year = parts[0].parse::<i64>().map_err(|_| invalid())?;
+    let month = parts[1].parse::<i64>().map_err(|_| invalid())?;
+    let day = parts[2].parse::<i64>().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    let days = days_from_civil(year, month, day) + if end_of_day { 1 } else { 0 };
+    Ok(days * SECONDS_PER_DAY)
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,488 @@
+// Tests for log decoding, the SQLite store and fee reports of the SolGSN indexer
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_sdk::pubkey::Pubkey;
+use solgsn::event::{Event, ExecutionEvent, GiftTopupEvent};
+use tokio::{runtime::Runtime, sync::broadcast};
+use tokio_stream::StreamExt;
+use tonic::{Code, Request};
+use solgsn_indexer::{
+    db::{EventRecord, FeeRow, Store},
+    events::{parse_logs, EventKind, GsnEvent},
+    grpc::{proto, proto::gsn_events_server::GsnEvents, EventService},
+    report::{self, GroupBy},
+};
+
+fn sample_logs() -> Vec<String> {
+    vec![
+        "Program 11111111111111111111111111111111 invoke [1]".to_string(),
+        "Program log: Instruction: Submit Transaction".to_string(),
+        "Program log: [EXECUTION_START] consumer=Cons1 executor=Exec1 target_program=11111111111111111111111111111111 amount=1000 fee=50000 nonce=0".to_string(),
+        "Program log: [EXECUTION_SUCCESS] consumer=Cons1 executor=Exec1 amount=1000".to_string(),
+        "Program log: [FEE_DEDUCTION] consumer=Cons1 fee=50000 previous_balance=100000 new_balance=50000".to_string(),
+        "Program log: [EXECUTOR_CREDIT] executor=Exec1 fee=50000 previous_balance=0 new_balance=50000".to_string(),
+        "Program 11111111111111111111111111111111 success".to_string(),
+    ]
+}
+
+#[test]
+fn test_parse_topup_event() {
+    let event = GsnEvent::parse(
+        "Program log: [TOPUP] consumer=Cons1 amount=10000000 previous_balance=0 new_balance=10000000",
+    )
+    .expect("topup line should parse");
+
+    assert_eq!(event.kind, EventKind::Topup);
+    assert_eq!(event.consumer(), Some("Cons1"));
+    assert_eq!(event.amount(), Some(10000000));
+    assert_eq!(event.field_u64("previous_balance"), Some(0));
+    assert_eq!(event.field_u64("new_balance"), Some(10000000));
+    assert_eq!(event.executor(), None);
+}
+
+#[test]
+fn test_parse_binary_event() {
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let event = Event::Execution(ExecutionEvent {
+        consumer,
+        executor,
+        target_program: Pubkey::default(),
+        amount: 1_000,
+        lane: 0,
+        nonce: 7,
+        fee: 5_000,
+        tip: 0,
+        fee_mint: None,
+        charged: Some(consumer),
+        executor_fee: 5_000,
+    });
+    let line = format!("Program data: {}", STANDARD.encode(event.encode()));
+    let event = GsnEvent::parse(&line).expect("binary event should parse");
+
+    assert_eq!(event.kind, EventKind::ExecutionSuccess);
+    assert_eq!(event.consumer(), Some(consumer.to_string().as_str()));
+    assert_eq!(event.executor(), Some(executor.to_string().as_str()));
+    assert_eq!(event.fee(), Some(5_000));
+    assert_eq!(event.field_u64("nonce"), Some(7));
+
+    // Data of other programs
+    assert!(GsnEvent::parse("Program data: aGVsbG8=").is_none());
+}
+
+#[test]
+fn test_parse_gift_topup_event() {
+    let (payer, beneficiary) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let event = Event::GiftTopup(GiftTopupEvent {
+        payer,
+        beneficiary,
+        mint: None,
+        amount: 1_000,
+        previous_balance: 0,
+        new_balance: 1_000,
+    });
+    let line = format!("Program data: {}", STANDARD.encode(event.encode()));
+    let event = GsnEvent::parse(&line).expect("binary event should parse");
+
+    // Credited to the beneficiary, attributed to the payer
+    assert_eq!(event.kind, EventKind::Topup);
+    assert_eq!(event.consumer(), Some(beneficiary.to_string().as_str()));
+    assert_eq!(event.field("payer"), Some(payer.to_string().as_str()));
+    assert_eq!(event.amount(), Some(1_000));
+}
+
+#[test]
+fn test_parse_ignores_non_gsn_lines() {
+    assert!(GsnEvent::parse("Program log: Instruction: TopUp").is_none());
+    assert!(GsnEvent::parse("Program log: [UNKNOWN_TAG] a=b").is_none());
+    assert!(GsnEvent::parse("Program 11111111111111111111111111111111 success").is_none());
+
+    let events = parse_logs(&sample_logs());
+    let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::ExecutionStart,
+            EventKind::ExecutionSuccess,
+            EventKind::FeeDeduction,
+            EventKind::ExecutorCredit,
+        ]
+    );
+    assert_eq!(events[0].fee(), Some(50000));
+    assert_eq!(events[0].field_u64("nonce"), Some(0));
+}
+
+#[test]
+fn test_parse_values_with_spaces() {
+    let event = GsnEvent::parse(
+        "Program log: [EXECUTION_FAILED] consumer=Cons1 executor=Exec1 error=Custom(5) extra words",
+    )
+    .unwrap();
+    assert_eq!(event.field("error"), Some("Custom(5) extra words"));
+
+    let event = GsnEvent::parse(
+        "Program log: [EXECUTION_FAILED] reason=insufficient_balance consumer=Cons1 required_fee=50000 available_balance=10",
+    )
+    .unwrap();
+    assert_eq!(event.fee(), Some(50000));
+}
+
+#[test]
+fn test_parse_json_event() {
+    let event = GsnEvent::parse(
+        r#"Program log: {"schema":"solgsn.event.v1","event":"FEE_DEDUCTION","consumer":"Cons1","fee":50000,"previous_balance":100000,"new_balance":50000}"#,
+    )
+    .expect("json line should parse");
+
+    assert_eq!(event.kind, EventKind::FeeDeduction);
+    assert_eq!(event.consumer(), Some("Cons1"));
+    assert_eq!(event.fee(), Some(50000));
+    assert_eq!(event.field_u64("new_balance"), Some(50000));
+    assert_eq!(event.field("schema"), None);
+
+    let kv = GsnEvent::parse(
+        "Program log: [FEE_DEDUCTION] consumer=Cons1 fee=50000 previous_balance=100000 new_balance=50000",
+    )
+    .unwrap();
+    assert_eq!(event.fields, kv.fields);
+
+    assert!(GsnEvent::parse(r#"Program log: {"schema":"other.v1","event":"TOPUP"}"#).is_none());
+    assert!(GsnEvent::parse("Program log: {not json").is_none());
+}
+
+#[test]
+fn test_sqlite_store_deduplicates_and_tracks_cursor() {
+    let mut store = Store::open(":memory:").unwrap();
+    store.migrate().unwrap();
+    // Migrations are idempotent
+    store.migrate().unwrap();
+
+    let events = parse_logs(&sample_logs());
+    for (log_index, event) in events.iter().enumerate() {
+        let record = EventRecord {
+            signature: "sig1".to_string(),
+            slot: 42,
+            block_time: Some(1_700_000_000),
+            log_index: log_index as u32,
+            succeeded: true,
+            event: event.clone(),
+        };
+        assert!(store.insert_event(&record).unwrap());
+        // Re-indexing the same transaction is a no-op
+        assert!(!store.insert_event(&record).unwrap());
+    }
+
+    assert_eq!(store.cursor("program").unwrap(), None);
+    store.set_cursor("program", "sig1", 42).unwrap();
+    assert_eq!(store.cursor("program").unwrap(), Some("sig1".to_string()));
+    store.set_cursor("program", "sig2", 43).unwrap();
+    assert_eq!(store.cursor("program").unwrap(), Some("sig2".to_string()));
+}
+
+#[test]
+fn test_sqlite_store_projects_accounts() {
+    let mut store = Store::open(":memory:").unwrap();
+    store.migrate().unwrap();
+
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let execution = Event::Execution(ExecutionEvent {
+        consumer,
+        executor,
+        target_program: Pubkey::default(),
+        amount: 1_000,
+        lane: 2,
+        nonce: 7,
+        fee: 5_000,
+        tip: 100,
+        fee_mint: None,
+        charged: Some(consumer),
+        executor_fee: 4_600,
+    });
+    let logs = vec![
+        format!(
+            "Program log: [TOPUP] consumer={} amount=100000 previous_balance=0 new_balance=100000",
+            consumer
+        ),
+        format!("Program data: {}", STANDARD.encode(execution.encode())),
+        format!(
+            "Program log: [FEE_DEDUCTION] consumer={} fee=5000 tip=100 previous_balance=100000 new_balance=94900",
+            consumer
+        ),
+        format!(
+            "Program log: [EXECUTOR_CREDIT] executor={} fee=4600 previous_balance=0 new_balance=4600",
+            executor
+        ),
+    ];
+    for (log_index, event) in parse_logs(&logs).into_iter().enumerate() {
+        let record = EventRecord {
+            signature: "sig1".to_string(),
+            slot: 42,
+            block_time: Some(1_700_000_000),
+            log_index: log_index as u32,
+            succeeded: true,
+            event,
+        };
+        assert!(store.insert_event(&record).unwrap());
+        // Re-indexed events aren't counted twice
+        assert!(!store.insert_event(&record).unwrap());
+    }
+    // Neither are events of failed transactions
+    let mut failed = record(&parse_logs(&logs[1..2])[0], false);
+    failed.signature = "sig2".to_string();
+    assert!(store.insert_event(&failed).unwrap());
+
+    let conn = match &store {
+        Store::Sqlite(conn) => conn,
+        Store::Postgres(_) => unreachable!(),
+    };
+    let consumer_row: (i64, i64, i64, i64) = conn
+        .query_row(
+            "SELECT executions, fees_paid, topped_up, last_slot FROM gsn_consumers WHERE consumer = ?1",
+            [consumer.to_string()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(consumer_row, (1, 5_100, 100_000, 42));
+    let executor_row: (i64, i64, i64) = conn
+        .query_row(
+            "SELECT executions, fees_earned, claimed FROM gsn_executors WHERE executor = ?1",
+            [executor.to_string()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(executor_row, (1, 4_600, 0));
+    let receipt: (String, i64, i64, i64, Option<String>) = conn
+        .query_row(
+            "SELECT executor, lane, nonce, tip, charged FROM gsn_receipts WHERE signature = 'sig1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        receipt,
+        (executor.to_string(), 2, 7, 100, Some(consumer.to_string()))
+    );
+
+    let balance = |store: &Store, account: &Pubkey| -> i64 {
+        match store {
+            Store::Sqlite(conn) => conn
+                .query_row(
+                    "SELECT balance FROM gsn_balances WHERE account = ?1 AND mint = ''",
+                    [account.to_string()],
+                    |row| row.get(0),
+                )
+                .unwrap(),
+            Store::Postgres(_) => unreachable!(),
+        }
+    };
+    assert_eq!(balance(&store, &consumer), 94_900);
+    assert_eq!(balance(&store, &executor), 4_600);
+
+    // Claims empty the executor's balance; older events don't overwrite it
+    let claim = format!(
+        "Program log: [EXECUTOR_CLAIM_COMPLETE] executor={} claimed_amount=4600",
+        executor
+    );
+    let mut claimed = record(&parse_logs(&[claim])[0], true);
+    claimed.signature = "sig3".to_string();
+    claimed.slot = 50;
+    assert!(store.insert_event(&claimed).unwrap());
+    let credit = format!(
+        "Program log: [EXECUTOR_CREDIT] executor={} fee=4600 previous_balance=4600 new_balance=9200",
+        executor
+    );
+    let mut stale = record(&parse_logs(&[credit])[0], true);
+    stale.signature = "sig4".to_string();
+    assert!(store.insert_event(&stale).unwrap());
+    assert_eq!(balance(&store, &executor), 0);
+}
+
+fn record(event: &GsnEvent, succeeded: bool) -> EventRecord {
+    EventRecord {
+        signature: "sig1".to_string(),
+        slot: 42,
+        block_time: Some(1_700_000_000),
+        log_index: 0,
+        succeeded,
+        event: event.clone(),
+    }
+}
+
+#[test]
+fn test_grpc_typed_event_conversion() {
+    let events = parse_logs(&sample_logs());
+
+    let start = proto::GsnEvent::from(&record(&events[0], true));
+    assert_eq!(start.kind, proto::EventKind::Execution as i32);
+    match start.event {
+        Some(proto::gsn_event::Event::Execution(execution)) => {
+            assert_eq!(execution.status, proto::ExecutionStatus::Started as i32);
+            assert_eq!(execution.consumer, "Cons1");
+            assert_eq!(execution.executor, "Exec1");
+            assert_eq!(execution.fee, 50000);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    let deduction = proto::GsnEvent::from(&record(&events[2], true));
+    assert_eq!(deduction.kind, proto::EventKind::FeeDeduction as i32);
+    match deduction.event {
+        Some(proto::gsn_event::Event::FeeDeduction(fee)) => {
+            assert_eq!(fee.previous_balance, 100000);
+            assert_eq!(fee.new_balance, 50000);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn test_grpc_subscribe_filters() {
+    let events = parse_logs(&sample_logs());
+    let start = record(&events[0], true);
+    let credit = record(&events[3], true);
+    let failed = record(&events[0], false);
+
+    let all = proto::SubscribeRequest::default();
+    assert!(all.matches(&start));
+    assert!(all.matches(&credit));
+    assert!(!all.matches(&failed), "failed transactions are opt-in");
+
+    let with_failed = proto::SubscribeRequest {
+        include_failed: true,
+        ..Default::default()
+    };
+    assert!(with_failed.matches(&failed));
+
+    let by_consumer = proto::SubscribeRequest {
+        consumers: vec!["Cons1".to_string()],
+        ..Default::default()
+    };
+    assert!(by_consumer.matches(&start));
+    assert!(
+        !by_consumer.matches(&credit),
+        "credit events carry no consumer"
+    );
+
+    let other_executor = proto::SubscribeRequest {
+        executors: vec!["Exec2".to_string()],
+        ..Default::default()
+    };
+    assert!(!other_executor.matches(&start));
+
+    let by_kind = proto::SubscribeRequest {
+        kinds: vec![proto::EventKind::ExecutorCredit as i32],
+        ..Default::default()
+    };
+    assert!(!by_kind.matches(&start));
+    assert!(by_kind.matches(&credit));
+}
+
+#[test]
+fn test_grpc_subscribe_streams_matching_events() {
+    let events = parse_logs(&sample_logs());
+    let (sender, _receiver) = broadcast::channel(4);
+    let service = EventService::new(sender.clone());
+    let request = proto::SubscribeRequest {
+        executors: vec!["Exec1".to_string()],
+        kinds: vec![proto::EventKind::ExecutorCredit as i32],
+        ..Default::default()
+    };
+
+    Runtime::new().unwrap().block_on(async {
+        // Events sent before subscribing aren't replayed
+        let mut missed = record(&events[3], true);
+        missed.signature = "missed".to_string();
+        sender.send(missed).unwrap();
+        let mut stream = service.subscribe(Request::new(request)).await.unwrap().into_inner();
+        for event in &events[..4] {
+            sender.send(record(event, true)).unwrap();
+        }
+        let credit = stream.next().await.unwrap().unwrap();
+        assert_eq!(credit.kind, proto::EventKind::ExecutorCredit as i32);
+        assert_eq!(credit.signature, record(&events[3], true).signature);
+
+        // A subscriber that falls behind the channel is told it lost events
+        for _ in 0..5 {
+            sender.send(record(&events[3], true)).unwrap();
+        }
+        let lagged = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(lagged.code(), Code::DataLoss);
+    });
+}
+
+fn fee_row(consumer: &str, program: Option<&str>, fee: i64, block_time: i64) -> FeeRow {
+    FeeRow {
+        consumer: consumer.to_string(),
+        program: program.map(str::to_string),
+        fee,
+        block_time,
+    }
+}
+
+#[test]
+fn test_sqlite_fee_rows_join_target_program() {
+    let mut store = Store::open(":memory:").unwrap();
+    store.migrate().unwrap();
+
+    for (log_index, event) in parse_logs(&sample_logs()).iter().enumerate() {
+        let record = EventRecord {
+            signature: "sig1".to_string(),
+            slot: 42,
+            block_time: Some(1_700_000_000),
+            log_index: log_index as u32,
+            succeeded: true,
+            event: event.clone(),
+        };
+        store.insert_event(&record).unwrap();
+    }
+
+    assert_eq!(
+        store.fee_rows(0, i64::MAX).unwrap(),
+        vec![fee_row(
+            "Cons1",
+            Some("11111111111111111111111111111111"),
+            50000,
+            1_700_000_000
+        )]
+    );
+    assert!(store.fee_rows(0, 1_700_000_000).unwrap().is_empty());
+}
+
+#[test]
+fn test_report_summarizes_by_consumer_and_program() {
+    let rows = vec![
+        fee_row("Cons1", Some("Prog1"), 5000, 100),
+        fee_row("Cons2", Some("Prog1"), 20000, 200),
+        fee_row("Cons1", Some("Prog2"), 7000, 300),
+        fee_row("Cons1", None, 1000, 50),
+    ];
+
+    let by_consumer = report::summarize(&rows, GroupBy::Consumer);
+    assert_eq!(by_consumer.len(), 2);
+    assert_eq!(by_consumer[0].key, "Cons2");
+    assert_eq!(by_consumer[1].key, "Cons1");
+    assert_eq!(by_consumer[1].executions, 3);
+    assert_eq!(by_consumer[1].total_fees, 13000);
+    assert_eq!(by_consumer[1].first_seen, 50);
+    assert_eq!(by_consumer[1].last_seen, 300);
+
+    let by_program = report::summarize(&rows, GroupBy::Program);
+    let keys: Vec<&str> = by_program.iter().map(|line| line.key.as_str()).collect();
+    assert_eq!(keys, vec!["Prog1", "Prog2", "unknown"]);
+
+    assert_eq!(
+        report::to_csv(&by_program[..1], GroupBy::Program),
+        "program,executions,total_fees,first_seen,last_seen\nProg1,2,25000,100,200\n"
+    );
+    assert!(report::to_json(&by_program).contains("\"total_fees\": 25000"));
+}
+
+#[test]
+fn test_report_parse_time() {
+    assert_eq!(report::parse_time("1700000000", false), Ok(1_700_000_000));
+    assert_eq!(report::parse_time("1970-01-01", false), Ok(0));
+    assert_eq!(report::parse_time("2023-11-14", false), Ok(1_699_920_000));
+    assert_eq!(report::parse_time("2023-11-14", true), Ok(1_700_006_400));
+    assert_eq!(report::parse_time("2024-03-01", false), Ok(1_709_251_200));
+    assert!(report::parse_time("2023-13-01", false).is_err());
+    assert!(report::parse_time("yesterday", false).is_err());
+}
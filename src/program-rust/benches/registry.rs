@@ -0,0 +1,73 @@
+//! Compares the legacy `BTreeMap<String, u64>` registry encoding with the
+//! sorted `Vec<(Pubkey, u64)>` one at realistic registry sizes.
+//!
+//! Run with `cargo bench --bench registry`. Deserializing the state account
+//! dominates the compute units of every instruction, so encoded size and host
+//! decode time track on-chain cost; absolute CU numbers still need a run on a
+//! validator (`solana logs` reports `consumed N of 200000 compute units`).
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+use solgsn::sorted_map::SortedMap;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 4] = [10, 100, 1_000, 5_000];
+const ROUNDS: u32 = 200;
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>10} {:>10} {:>12} {:>12} {:>12} {:>12}",
+        "keys", "bytes_old", "bytes_new", "decode_old", "decode_new", "lookup_old", "lookup_new"
+    );
+
+    for &size in SIZES.iter() {
+        let keys: Vec<Pubkey> = (0..size).map(|_| Pubkey::new_unique()).collect();
+
+        let mut legacy = BTreeMap::new();
+        let mut sorted = SortedMap::new();
+        for (index, key) in keys.iter().enumerate() {
+            legacy.insert(key.to_string(), index as u64);
+            sorted.insert(*key, index as u64);
+        }
+
+        let legacy_data = legacy.try_to_vec().unwrap();
+        let sorted_data = sorted.try_to_vec().unwrap();
+
+        let decode_old = time(|| {
+            BTreeMap::<String, u64>::try_from_slice(&legacy_data).unwrap();
+        });
+        let decode_new = time(|| {
+            SortedMap::<Pubkey, u64>::try_from_slice(&sorted_data).unwrap();
+        });
+
+        // The processor looks keys up by `Pubkey`, which the legacy layout
+        // first has to render as base58
+        let probe = keys[size / 2];
+        let lookup_old = time(|| {
+            assert!(legacy.contains_key(&probe.to_string()));
+        });
+        let lookup_new = time(|| {
+            assert!(sorted.contains_key(&probe));
+        });
+
+        println!(
+            "{:>6} {:>10} {:>10} {:>12?} {:>12?} {:>12?} {:>12?}",
+            size,
+            legacy_data.len(),
+            sorted_data.len(),
+            decode_old,
+            decode_new,
+            lookup_old,
+            lookup_new
+        );
+    }
+}
@@ -9,87 +9,48 @@ Usage: do.sh action
 
 Supported actions:
     build
+    build-sandbox
     clean
     test
     clippy
     fmt
+    dump
 
 EOF
 }
 
-sdkDir=../../node_modules/@solana/web3.js/bpf-sdk
-targetDir="$PWD"/target
-profile=bpfel-unknown-unknown/release
+outDir=../../dist/program
 
 perform_action() {
     set -e
     case "$1" in
     build)
-        "$sdkDir"/rust/build.sh "$PWD"
-
-        so_path="$targetDir/$profile"
-        so_name="solgsn"
-        if [ -f "$so_path/${so_name}.so" ]; then
-            cp "$so_path/${so_name}.so" "$so_path/${so_name}_debug.so"
-            "$sdkDir"/dependencies/llvm-native/bin/llvm-objcopy --strip-all "$so_path/${so_name}.so" "$so_path/$so_name.so"
-        fi
-
-        mkdir -p ../../dist/program
-        cp "$so_path/${so_name}.so" ../../dist/program/solgsn.so
+        # Requires the Solana CLI tools (v1.18), which provide cargo build-sbf
+        cargo build-sbf --sbf-out-dir "$outDir"
+        ;;
+    build-sandbox)
+        # Devnet-only build that lets governance turn on sandbox mode
+        cargo build-sbf --sbf-out-dir "$outDir" --features sandbox
         ;;
     clean)
-        "$sdkDir"/rust/clean.sh "$PWD"
+        cargo clean -p solgsn
         ;;
     test)
         echo "test"
         shift
-        cargo +nightly test $@
+        cargo test $@
         ;;
     clippy)
         echo "clippy"
-        cargo +nightly clippy
+        cargo clippy --all-targets -- -D warnings
         ;;
     fmt)
         echo "formatting"
         cargo fmt
         ;;
     dump)
-        # Dump depends on tools that are not installed by default and must be installed manually
-        # - greadelf
-        # - rustfilt
-        (
-            pwd
-            "$0" build
-
-            so_path="$targetDir/$profile"
-            so_name="solgsn"
-            so="$so_path/${so_name}_debug.so"
-            dump="$so_path/${so_name}-dump"
-
-            if [ -f "$so" ]; then
-                ls \
-                    -la \
-                    "$so" \
-                    >"${dump}-mangled.txt"
-                greadelf \
-                    -aW \
-                    "$so" \
-                    >>"${dump}-mangled.txt"
-                "$sdkDir/dependencies/llvm-native/bin/llvm-objdump" \
-                    -print-imm-hex \
-                    --source \
-                    --disassemble \
-                    "$so" \
-                    >>"${dump}-mangled.txt"
-                sed \
-                    s/://g \
-                    <"${dump}-mangled.txt" |
-                    rustfilt \
-                        >"${dump}.txt"
-            else
-                echo "Warning: No dump created, cannot find: $so"
-            fi
-        )
+        # Writes the disassembly next to the program, needs rustfilt
+        cargo build-sbf --sbf-out-dir "$outDir" --dump
         ;;
     help)
         usage
@@ -0,0 +1,36 @@
+//! Credential checks of the compliance mode.
+//!
+//! A credential is a soulbound token: an SPL Token (or Token-2022) account of
+//! the credential mint configured by governance, owned by the consumer and
+//! holding at least one token. Issuers usually freeze these accounts so they
+//! can't be transferred; frozen accounts are accepted.
+
+use crate::{error::GsnError, token::unpack_token_account};
+use solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+
+pub use crate::token::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+/// Fails with `MissingCredential` unless `data`, owned by `owner_program`, is
+/// a token account of `mint` held by `consumer` with a non-zero balance
+pub fn check_credential(
+    owner_program: &Pubkey,
+    data: &[u8],
+    mint: &Pubkey,
+    consumer: &Pubkey,
+) -> Result<(), GsnError> {
+    match unpack_token_account(owner_program, data) {
+        Some(account) if account.mint == *mint && account.owner == *consumer && account.amount > 0 => {
+            Ok(())
+        }
+        _ => Err(GsnError::MissingCredential),
+    }
+}
+
+/// [`check_credential`] of a credential account passed to an instruction
+pub fn verify_credential(
+    credential: &AccountInfo,
+    mint: &Pubkey,
+    consumer: &Pubkey,
+) -> Result<(), GsnError> {
+    check_credential(credential.owner, &credential.data.borrow(), mint, consumer)
+}
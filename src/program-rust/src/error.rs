@@ -31,6 +31,238 @@ pub enum GsnError {
     /// Unauthorized fee claim: only the executor who executed the transaction can claim fees
     #[error("Unauthorized fee claim: only the executor who executed the transaction can claim")]
     UnauthorizedFeeClaim,
+    /// Metrics account does not match the one registered in the GSN state
+    #[error("Invalid metrics account")]
+    InvalidMetricsAccount,
+    /// Nonce lane id is outside the supported range
+    #[error("Invalid nonce lane")]
+    InvalidNonceLane,
+    /// Config account does not belong to the GSN state account
+    #[error("Invalid config account")]
+    InvalidConfigAccount,
+    /// The state account has no room to register another consumer
+    #[error("Consumer registry full")]
+    ConsumerRegistryFull,
+    /// The state account has no room for another execution receipt
+    #[error("Execution history full")]
+    HistoryFull,
+    /// Batch network fee exceeds the signature fees of the batch transaction
+    #[error("Invalid network fee")]
+    InvalidNetworkFee,
+    /// Escrow account isn't the escrow of the submission or GSN state account
+    #[error("Invalid escrow account")]
+    InvalidEscrowAccount,
+    /// Escrowed fee is still in its dispute window and can't be released yet
+    #[error("Escrow in dispute window")]
+    EscrowInDisputeWindow,
+    /// Escrowed fee is past its dispute window and can no longer be disputed
+    #[error("Escrow dispute window closed")]
+    EscrowDisputeWindowClosed,
+    /// Commitment account doesn't match the revealed submission or its executor
+    #[error("Invalid commitment")]
+    InvalidCommitment,
+    /// The submission was revealed before the reveal delay passed
+    #[error("Reveal too early")]
+    RevealTooEarly,
+    /// The commitment is too old to be revealed
+    #[error("Commitment expired")]
+    CommitmentExpired,
+    /// Compliance mode is on and the consumer didn't present a valid credential
+    #[error("Missing credential")]
+    MissingCredential,
+    /// The fee split ratio is above 10000 basis points or names the fee payer twice
+    #[error("Invalid fee split")]
+    InvalidFeeSplit,
+    /// The program isn't built with sandbox mode, or it is off
+    #[error("Sandbox unavailable")]
+    SandboxUnavailable,
+    /// No Ed25519 instruction of the transaction verifies the executor's quote
+    #[error("Invalid fee quote")]
+    InvalidFeeQuote,
+    /// The quote's expiry slot has passed
+    #[error("Fee quote expired")]
+    FeeQuoteExpired,
+    /// The deployment is shut down, or shutting down for top-ups
+    #[error("Shut down")]
+    ShutDown,
+    /// The deployment isn't shut down or its grace period hasn't passed
+    #[error("Sweep unavailable")]
+    SweepUnavailable,
+    /// The vault isn't the one derived from the GSN state account
+    #[error("Invalid vault account")]
+    InvalidVaultAccount,
+    /// The mint isn't on the config's allowed token list
+    #[error("Token not allowed")]
+    TokenNotAllowed,
+    /// A token account has the wrong mint, owner or token program
+    #[error("Invalid token account")]
+    InvalidTokenAccount,
+    /// No Ed25519 instruction of the transaction verifies the consumer's intent
+    #[error("Invalid intent")]
+    InvalidIntent,
+    /// No Secp256k1 instruction of the transaction verifies a signature by an
+    /// Ethereum address registered to the consumer
+    #[error("Invalid Ethereum signature")]
+    InvalidEthSignature,
+    /// The relayed instruction targets another program than the target
+    /// account, the GSN program itself, or uses the fee payer's signature
+    #[error("Invalid target instruction")]
+    InvalidTargetInstruction,
+    /// The submission's expiry slot has passed
+    #[error("Submission expired")]
+    SubmissionExpired,
+    /// The fee is above the consumer's per-transaction limit, or what remains
+    /// of its limit for the epoch
+    #[error("Limit exceeded")]
+    LimitExceeded,
+    /// The target program isn't on the config's allowed program list
+    #[error("Program not allowed")]
+    ProgramNotAllowed,
+    /// The governance authority paused the deployment
+    #[error("Protocol paused")]
+    ProtocolPaused,
+    /// The multisig has no signers, too many, duplicates, or a threshold
+    /// outside 1 to their number
+    #[error("Invalid multisig")]
+    InvalidMultisig,
+    /// `UpdateFeeParams` may only lower fees; other changes go through
+    /// `ProposeFeeParams`
+    #[error("Fee change timelocked")]
+    FeeChangeTimelocked,
+    /// The proposed fee parameters' activation slot wasn't reached yet
+    #[error("Timelock not elapsed")]
+    TimelockNotElapsed,
+    /// `ApplyFeeParams` without a proposal
+    #[error("No pending fee params")]
+    NoPendingFeeParams,
+    /// Only registered executors may execute while the registry is required
+    #[error("Executor not registered")]
+    ExecutorNotRegistered,
+    /// The executor hasn't bonded the minimum stake, or is unbonding
+    #[error("Executor not bonded")]
+    ExecutorNotBonded,
+    /// The stake account isn't the executor's stake address
+    #[error("Invalid stake account")]
+    InvalidStakeAccount,
+    /// The stake's unbonding period hasn't passed yet
+    #[error("Stake unbonding")]
+    StakeUnbonding,
+    /// The protocol fee is above `MAX_PROTOCOL_FEE_BPS`
+    #[error("Invalid protocol fee")]
+    InvalidProtocolFee,
+    /// The sponsor account isn't a sponsor policy of this deployment
+    #[error("Invalid sponsor account")]
+    InvalidSponsorAccount,
+    /// The sponsor's policy doesn't cover the submission
+    #[error("Sponsor policy violation")]
+    SponsorPolicyViolation,
+    /// The measured compute units are above the fee mode's maximum
+    #[error("Invalid compute units")]
+    InvalidComputeUnits,
+    /// The volume discount tiers aren't ordered, or discount more than 100%
+    #[error("Invalid volume discounts")]
+    InvalidVolumeDiscounts,
+    /// The fee is above the maximum the consumer signed
+    #[error("Fee too high")]
+    FeeTooHigh,
+    /// A tip on a submission whose fee the consumer doesn't pay itself
+    #[error("Invalid tip")]
+    InvalidTip,
+    /// The payload executed already within the retention window
+    #[error("Duplicate payload")]
+    DuplicatePayload,
+    /// A payload retention window above `MAX_PAYLOAD_RETENTION`
+    #[error("Invalid payload retention")]
+    InvalidPayloadRetention,
+    /// The ledger account of a consumer or executor the instruction touched
+    /// wasn't among its accounts
+    #[error("Missing ledger account")]
+    MissingLedgerAccount,
+    /// A ledger account that isn't the PDA of its consumer or executor, or
+    /// isn't writable
+    #[error("Invalid ledger account")]
+    InvalidLedgerAccount,
+    /// The state account was written by a newer program version
+    #[error("Unsupported state version")]
+    UnsupportedStateVersion,
+    /// An account whose discriminator isn't the one of the kind of account
+    /// the instruction expects there
+    #[error("Invalid account type")]
+    InvalidAccountType,
+    /// A program account that isn't owned by this program
+    #[error("Invalid account owner")]
+    InvalidAccountOwner,
+    /// An account the instruction writes to was passed read-only
+    #[error("Account not writable")]
+    AccountNotWritable,
+    /// The account passed as the system program isn't it
+    #[error("Invalid system program")]
+    InvalidSystemProgram,
+    /// A consumer closing with a lamport or token balance left
+    #[error("Balance not empty")]
+    BalanceNotEmpty,
+    /// A withdrawal without a request that cooled down and covers it, while
+    /// governance set a withdrawal cooldown
+    #[error("Withdrawal cooldown")]
+    WithdrawalCooldown,
+    /// An instruction only allowed while the protocol is paused
+    #[error("Protocol not paused")]
+    NotPaused,
+    /// A price feed account other than the one configured for the fee mint,
+    /// not a trading price, or with too wide a confidence interval
+    #[error("Invalid price feed")]
+    InvalidPriceFeed,
+    /// A price feed that wasn't updated within its maximum age
+    #[error("Price feed stale")]
+    StalePriceFeed,
+    /// A token top-up into a mint the vault holds no token account of,
+    /// without the accounts that open it
+    #[error("Vault token account missing")]
+    VaultTokenAccountMissing,
+    /// A fee swap delivering fewer lamports than the claimer's minimum
+    #[error("Swap slippage exceeded")]
+    SwapSlippageExceeded,
+    /// A submission targeting the program itself, or an instruction writing
+    /// the state while a submission's CPI is in flight
+    #[error("Reentrant call")]
+    ReentrantCall,
+    /// A submission's target program account that isn't executable
+    #[error("Target not executable")]
+    TargetNotExecutable,
+    /// A consumer's submission beyond the rate limit of its window
+    #[error("Rate limited")]
+    RateLimited,
+    /// A top-up, submission or fee claim of an address governance
+    /// blacklisted
+    #[error("Blacklisted")]
+    Blacklisted,
+    /// A `DistributeRewards` of an epoch already rewarded
+    #[error("Rewards already distributed")]
+    RewardsAlreadyDistributed,
+    /// A `DistributeRewards` of an epoch without eligible executors
+    #[error("No reward recipients")]
+    NoRewardRecipients,
+    /// The insurance fee is above `MAX_INSURANCE_FEE_BPS`
+    #[error("Invalid insurance fee")]
+    InvalidInsuranceFee,
+    /// A `ReportFailure` of a consumer without a balance, a lane it didn't
+    /// submit on, a nonce other than the lane's next, or reported already
+    #[error("Invalid failure report")]
+    InvalidFailureReport,
+    /// A `Compensate` for a lane and nonce the consumer has no execution
+    /// recorded for, or one past the record retention
+    #[error("Unknown execution")]
+    UnknownExecution,
+    /// A `ReportFailure` beyond the executor's
+    /// `MAX_FAILURE_REPORTS_PER_EXECUTOR` reports of the epoch
+    #[error("Failure report limit reached")]
+    FailureReportLimit,
+    /// A `Compensate` for an execution compensated already
+    #[error("Execution already compensated")]
+    AlreadyCompensated,
+    /// A `RegisterEthAddress` signed over another nonce than the address's next
+    #[error("Stale Ethereum registration")]
+    StaleEthRegistration,
 }
 
 impl From<GsnError> for ProgramError {
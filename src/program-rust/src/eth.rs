@@ -0,0 +1,108 @@
+//! Meta-transactions signed by Ethereum wallets.
+//!
+//! A consumer registers an Ethereum address with `RegisterEthAddress`, proving
+//! it holds the address's key. The wallet then signs intents (see
+//! [`crate::intent`]) with `personal_sign`, and the relayer submits them with
+//! `SubmitEthIntent` after a Secp256k1 program instruction verifying the
+//! signature. The program checks that instruction through the Instructions
+//! sysvar, and pays the transfer out of the consumer's top-up balance, since
+//! no Solana key signs for it.
+
+use solana_program::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, secp256k1_program,
+    sysvar::instructions::load_instruction_at_checked,
+};
+
+/// Prefix of every registration message, so a registration signature can't
+/// be replayed as a signature over anything else
+pub const ETH_REGISTER_DOMAIN: &[u8] = b"solgsn-eth-register";
+
+/// An Ethereum address: the last 20 bytes of the Keccak-256 hash of the public key
+pub type EthAddress = [u8; 20];
+
+/// Length of a Secp256k1 signature followed by its recovery id
+const SIGNATURE_LEN: usize = 64 + 1;
+
+/// Length of the Secp256k1 program's per-signature offsets
+const OFFSETS_LEN: usize = 11;
+
+/// `message` as `personal_sign` (EIP-191) signs it
+pub fn personal_message(message: &[u8]) -> Vec<u8> {
+    let prefix = format!("\x19Ethereum Signed Message:\n{}", message.len());
+    let mut prefixed = Vec::with_capacity(prefix.len() + message.len());
+    prefixed.extend_from_slice(prefix.as_bytes());
+    prefixed.extend_from_slice(message);
+    prefixed
+}
+
+/// Message an Ethereum wallet signs to register with `consumer`: the
+/// domain, GSN state account, consumer and the address's registration
+/// nonce, before the EIP-191 prefix
+pub fn registration_message(gsn_account: &Pubkey, consumer: &Pubkey, nonce: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(ETH_REGISTER_DOMAIN.len() + 32 + 32 + 8);
+    message.extend_from_slice(ETH_REGISTER_DOMAIN);
+    message.extend_from_slice(gsn_account.as_ref());
+    message.extend_from_slice(consumer.as_ref());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+/// Addresses and messages of the signatures a Secp256k1 program instruction
+/// verifies, skipping those whose data lives in other instructions. The
+/// instruction is the transaction's `own_index`th.
+pub fn parse_secp256k1_instruction(data: &[u8], own_index: u8) -> Vec<(EthAddress, &[u8])> {
+    let count = match data.first() {
+        Some(count) => *count as usize,
+        None => return Vec::new(),
+    };
+    (0..count)
+        .filter_map(|signature| {
+            let offsets = data.get(1 + signature * OFFSETS_LEN..1 + (signature + 1) * OFFSETS_LEN)?;
+            let signature_offset = read_u16(offsets, 0)?;
+            let eth_address_offset = read_u16(offsets, 3)?;
+            let message_offset = read_u16(offsets, 6)?;
+            let message_len = read_u16(offsets, 8)?;
+            // Data in other instructions could be something the wallet never signed
+            if offsets[2] != own_index || offsets[5] != own_index || offsets[10] != own_index {
+                return None;
+            }
+
+            data.get(signature_offset..signature_offset + SIGNATURE_LEN)?;
+            let eth_address = data.get(eth_address_offset..eth_address_offset + 20)?;
+            let message = data.get(message_offset..message_offset + message_len)?;
+            let mut address = [0u8; 20];
+            address.copy_from_slice(eth_address);
+            Some((address, message))
+        })
+        .collect()
+}
+
+/// Ethereum addresses whose `personal_sign` signature over `message` a
+/// Secp256k1 program instruction before `current_index` verified. The
+/// runtime fails the transaction if any of those signatures is invalid.
+pub fn eth_signers(
+    instructions_sysvar: &AccountInfo,
+    current_index: u16,
+    message: &[u8],
+) -> Result<Vec<EthAddress>, ProgramError> {
+    let signed = personal_message(message);
+    let mut signers = Vec::new();
+    // The Secp256k1 program takes instruction indexes as u8
+    for index in 0..current_index.min(u8::MAX as u16 + 1) {
+        let instruction = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if instruction.program_id != secp256k1_program::id() {
+            continue;
+        }
+        for (address, message) in parse_secp256k1_instruction(&instruction.data, index as u8) {
+            if message == signed.as_slice() {
+                signers.push(address);
+            }
+        }
+    }
+    Ok(signers)
+}
@@ -0,0 +1,230 @@
+//! Binary events for indexers.
+//!
+//! Top-ups, executions, fee claims and governance changes are emitted with
+//! `sol_log_data` instead of text logs: [`EVENT_PREFIX`] followed by the Borsh
+//! encoding of an [`Event`]. They show up as `Program data: <base64>` log lines
+//! that [`Event::decode`] turns back into events, so indexers don't have to
+//! parse text. Diagnostics such as failures stay text logs, see `log`.
+
+use crate::state::FeeMode;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+/// Marks the data of SolGSN events among the `Program data` of a transaction
+pub const EVENT_PREFIX: [u8; 8] = *b"solgsnev";
+
+/// Funds added to a consumer's balance
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct TopupEvent {
+    pub consumer: Pubkey,
+    /// Mint of a token balance, none for lamports
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub previous_balance: u64,
+    pub new_balance: u64,
+}
+
+/// Funds a payer added to the balance of another consumer, see `Topup`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct GiftTopupEvent {
+    pub payer: Pubkey,
+    pub beneficiary: Pubkey,
+    /// Mint of a token balance, none for lamports
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub previous_balance: u64,
+    pub new_balance: u64,
+}
+
+/// A submission executed
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ExecutionEvent {
+    pub consumer: Pubkey,
+    pub executor: Pubkey,
+    pub target_program: Pubkey,
+    pub amount: u64,
+    pub lane: u64,
+    pub nonce: u64,
+    /// Fee charged, tip excluded
+    pub fee: u64,
+    pub tip: u64,
+    /// Mint the fee was paid in, none for lamports
+    pub fee_mint: Option<Pubkey>,
+    /// Balance the fee was charged to, none when it was waived
+    pub charged: Option<Pubkey>,
+    /// Fee and tip less the protocol's share, earned by the executors
+    pub executor_fee: u64,
+}
+
+/// An executor's earnings paid out
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct FeeClaimEvent {
+    pub executor: Pubkey,
+    /// Account paid, the executor's own or its payout address's
+    pub destination: Pubkey,
+    /// Mint of token earnings, none for lamports
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+}
+
+/// A change governance made to a deployment
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct GovernanceEvent {
+    pub config: Pubkey,
+    /// Signer that approved it, none for steps anyone may take
+    pub authority: Option<Pubkey>,
+    pub action: GovernanceAction,
+}
+
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum GovernanceAction {
+    FeeParamsUpdated {
+        fee_mode: FeeMode,
+    },
+    FeeParamsProposed {
+        fee_mode: FeeMode,
+        activation_slot: u64,
+    },
+    FeeParamsApplied {
+        fee_mode: FeeMode,
+    },
+    TokenAllowed {
+        mint: Pubkey,
+        allowed: bool,
+    },
+    ProgramAllowed {
+        program: Pubkey,
+        allowed: bool,
+    },
+    Paused {
+        paused: bool,
+    },
+    ProtocolFeeSet {
+        protocol_fee_bps: u16,
+    },
+    /// No signers when governance went back to the single authority
+    MultisigSet {
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    },
+    TreasuryWithdrawn {
+        destination: Pubkey,
+        amount: u64,
+        remaining: u64,
+    },
+    WithdrawalCooldownSet {
+        slots: u64,
+    },
+    EmergencyWithdrawalsEnabled,
+    /// No feed when the mint's fees went back to base units
+    PriceFeedSet {
+        mint: Pubkey,
+        feed: Option<Pubkey>,
+    },
+    RateLimitSet {
+        max_submissions: u64,
+        window_slots: u64,
+    },
+    AddressBlacklisted {
+        address: Pubkey,
+        blacklisted: bool,
+    },
+    CircuitBreakerSet {
+        max_failure_bps: u16,
+        min_executions: u64,
+    },
+    /// Emitted with no authority when failures paused the deployment
+    CircuitBreakerTripped {
+        epoch: u64,
+        succeeded: u64,
+        failed: u64,
+    },
+    StatsTrackingSet {
+        enabled: bool,
+    },
+    /// `amount` of the treasury credited to `executors` executors for their
+    /// executions in `epoch`
+    RewardsDistributed {
+        epoch: u64,
+        amount: u64,
+        executors: u64,
+    },
+    InsuranceFeeSet {
+        insurance_fee_bps: u16,
+    },
+    /// `amount` of the insurance pool paid to `consumer` for its execution
+    /// of `lane` and `nonce`
+    ConsumerCompensated {
+        consumer: Pubkey,
+        lane: u64,
+        nonce: u64,
+        amount: u64,
+        remaining: u64,
+    },
+}
+
+/// Variants only ever get appended, so decoders keep reading older events
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum Event {
+    Topup(TopupEvent),
+    Execution(ExecutionEvent),
+    FeeClaim(FeeClaimEvent),
+    Governance(GovernanceEvent),
+    GiftTopup(GiftTopupEvent),
+}
+
+impl Event {
+    /// [`EVENT_PREFIX`] and the Borsh encoding of the event
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = EVENT_PREFIX.to_vec();
+        self.serialize(&mut data).expect("events serialize");
+        data
+    }
+
+    /// The event encoded in `data`, none for data that isn't a SolGSN event
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let mut body = data.strip_prefix(&EVENT_PREFIX[..])?;
+        let event = Self::deserialize(&mut body).ok()?;
+        body.is_empty().then_some(event)
+    }
+
+    /// Log the event as `Program data`
+    pub fn emit(&self) {
+        sol_log_data(&[&self.encode()]);
+    }
+}
+
+impl From<TopupEvent> for Event {
+    fn from(event: TopupEvent) -> Self {
+        Self::Topup(event)
+    }
+}
+
+impl From<ExecutionEvent> for Event {
+    fn from(event: ExecutionEvent) -> Self {
+        Self::Execution(event)
+    }
+}
+
+impl From<FeeClaimEvent> for Event {
+    fn from(event: FeeClaimEvent) -> Self {
+        Self::FeeClaim(event)
+    }
+}
+
+impl From<GovernanceEvent> for Event {
+    fn from(event: GovernanceEvent) -> Self {
+        Self::Governance(event)
+    }
+}
+
+impl From<GiftTopupEvent> for Event {
+    fn from(event: GiftTopupEvent) -> Self {
+        Self::GiftTopup(event)
+    }
+}
+
+/// Emit `event`, e.g. `emit(TopupEvent { .. })`.
+pub fn emit<E: Into<Event>>(event: E) {
+    event.into().emit()
+}
@@ -1,32 +1,482 @@
 /// Instructions supported by the SolGSN.
-use crate::state::FeeMode;
-use solana_program::program_error::ProgramError;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+use crate::state::{
+    ConsumerAccount, ExecutionReceipt, ExecutorAccount, GsnInfo, InstructionFilter, OracleKind, PriceFeed, VolumeDiscountTier,
+    CHANNEL_NONCE_LANE, MAX_SPONSOR_INSTRUCTIONS, MAX_SPONSOR_PROGRAMS, MAX_VOLUME_DISCOUNT_TIERS,
+};
+use crate::token::{self, NATIVE_MINT, TOKEN_PROGRAM_ID};
 use std::mem::size_of;
 
 /// Topup argument structure
-#[repr(C)]
 #[derive(Clone, Debug, PartialEq)]
 pub struct TopupAgrs {
     pub amount: u64,
+    /// Consumer credited instead of the payer, which signs and pays
+    pub beneficiary: Option<Pubkey>,
 }
 
-/// Submit argument structure
+impl TopupAgrs {
+    /// Layout after the instruction tag: `amount: u64`, then optionally
+    /// `beneficiary: [u8; 32]`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const LEN: usize = size_of::<u8>() + size_of::<u64>();
+        let beneficiary = match input.len() {
+            LEN => None,
+            len if len == LEN + 32 => Some(read_pubkey(&input[LEN..])),
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+            beneficiary,
+        })
+    }
+}
+
+/// Withdraw argument structure
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq)]
+pub struct WithdrawArgs {
+    /// Lamports of the consumer's top-up balance to pay back
+    pub amount: u64,
+}
+
+impl WithdrawArgs {
+    /// Layout after the instruction tag: `amount: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+        })
+    }
+}
+
+/// Register Ethereum address argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterEthAddressArgs {
+    /// Registration nonce of the address the wallet signed
+    pub nonce: u64,
+}
+
+impl RegisterEthAddressArgs {
+    /// Layout after the instruction tag: `nonce: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            nonce: read_u64(&input[1..]),
+        })
+    }
+}
+
+/// Submit argument structure for a submission paying its fee in tokens
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitTokenFeeArgs {
+    pub amount: u64,
+    pub nonce: u64,
+    pub lane: u64,
+    /// Mint of the consumer's token balance the fee is charged to
+    pub mint: [u8; 32],
+}
+
+/// Executor quote a submission is bound to, see `SubmitQuoted`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuoteTerms {
+    /// Fee the executor quoted
+    pub fee: u64,
+    /// Last slot the quote is valid in
+    pub expiry_slot: u64,
+}
+
+/// Submit argument structure
+#[derive(Clone, Debug, PartialEq)]
 pub struct SubmitArgs {
     pub amount: u64,
     /// Nonce to prevent replay attacks
     pub nonce: u64,
+    /// Last slot the submission may execute in, none for submissions that never expire
+    pub expiry_slot: Option<u64>,
+    /// Highest fee the consumer agreed to, none to accept the configured fee
+    pub max_fee: Option<u64>,
+    /// Lamports paid to the executor on top of the fee, for priority
+    pub tip: u64,
+    /// Only check the submission and return its fee, without executing it
+    /// or writing any account
+    pub simulate: bool,
+    /// Executor quote charged instead of the configured fee, none to charge
+    /// the configured fee
+    pub quote: Option<QuoteTerms>,
 }
 
-/// Update fee parameters argument structure
+impl SubmitArgs {
+    /// Layout after the instruction tag: `amount: u64`, `nonce: u64`, then
+    /// optionally `expiry_slot: u64`, `max_fee: u64` and `tip: u64`, each only
+    /// with the ones before it. Clients predating them leave them out. Followed
+    /// by a maximum fee, an expiry slot of 0 stands for none; a maximum fee of
+    /// `u64::MAX` doesn't limit the fee. After the tip, a `simulate: u8` of 1
+    /// asks for a dry run. After the flag, a quoted `fee: u64` and its
+    /// `expiry_slot: u64` bind the submission to an executor quote.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const LEN: usize = size_of::<u8>() + 2 * size_of::<u64>();
+        const SIMULATE_OFFSET: usize = LEN + 3 * size_of::<u64>();
+        const QUOTE_OFFSET: usize = SIMULATE_OFFSET + size_of::<u8>();
+        if input.len() < LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let (input, quote) = match input.len() {
+            len if len == QUOTE_OFFSET + 2 * size_of::<u64>() => (
+                &input[..QUOTE_OFFSET],
+                Some(QuoteTerms {
+                    fee: read_u64(&input[QUOTE_OFFSET..]),
+                    expiry_slot: read_u64(&input[QUOTE_OFFSET + 8..]),
+                }),
+            ),
+            _ => (input, None),
+        };
+        let (input, simulate) = match input.len() {
+            len if len == SIMULATE_OFFSET + 1 => match input[SIMULATE_OFFSET] {
+                0 => (&input[..SIMULATE_OFFSET], false),
+                1 => (&input[..SIMULATE_OFFSET], true),
+                _ => return Err(ProgramError::InvalidInstructionData),
+            },
+            _ => (input, false),
+        };
+        let (expiry_slot, max_fee, tip) = unpack_submit_options(&input[LEN..])?;
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+            nonce: read_u64(&input[9..]),
+            expiry_slot,
+            max_fee,
+            tip,
+            simulate,
+            quote,
+        })
+    }
+}
+
+/// The optional `expiry_slot`, `max_fee` and `tip` trailing a submission,
+/// each only with the ones before it
+fn unpack_submit_options(input: &[u8]) -> Result<(Option<u64>, Option<u64>, u64), ProgramError> {
+    let chunks = input.chunks_exact(size_of::<u64>());
+    if !chunks.remainder().is_empty() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let trailing: Vec<u64> = chunks.map(read_u64).collect();
+    Ok(match trailing[..] {
+        [] => (None, None, 0),
+        [expiry_slot] => (Some(expiry_slot), None, 0),
+        [expiry_slot, max_fee] => (Some(expiry_slot).filter(|slot| *slot > 0), Some(max_fee), 0),
+        [expiry_slot, max_fee, tip] => (Some(expiry_slot).filter(|slot| *slot > 0), Some(max_fee), tip),
+        _ => return Err(ProgramError::InvalidInstructionData),
+    })
+}
+
+/// Submit argument structure for a specific nonce lane
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitLaneArgs {
+    pub amount: u64,
+    /// Next nonce of the lane
+    pub nonce: u64,
+    /// Nonce lane, below `MAX_NONCE_LANES`, `UNORDERED_NONCE_LANE` or
+    /// `CHANNEL_NONCE_LANE`. Lane 0 is the legacy nonce
+    pub lane: u64,
+    /// Last slot the submission may execute in, none for submissions that never expire
+    pub expiry_slot: Option<u64>,
+    /// Highest fee the consumer agreed to, none to accept the configured fee
+    pub max_fee: Option<u64>,
+    /// Lamports paid to the executor on top of the fee, for priority
+    pub tip: u64,
+}
+
+impl SubmitLaneArgs {
+    const LEN: usize = size_of::<u8>() + 3 * size_of::<u64>();
+
+    /// Layout after the instruction tag: `amount: u64`, `nonce: u64`,
+    /// `lane: u64`, then the optional `expiry_slot`, `max_fee` and `tip` of
+    /// [`SubmitArgs::unpack`].
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() < Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let (expiry_slot, max_fee, tip) = unpack_submit_options(&input[Self::LEN..])?;
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+            nonce: read_u64(&input[9..]),
+            lane: read_u64(&input[17..]),
+            expiry_slot,
+            max_fee,
+            tip,
+        })
+    }
+
+    /// Like `unpack`, for the submissions taking no expiry slot, maximum fee
+    /// or tip
+    pub fn unpack_lane(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Self::unpack(input)
+    }
+}
+
+/// Most submissions a `SubmitBatch` may carry
+pub const MAX_BATCH_SIZE: usize = 8;
+
+/// Lamports the cluster charges per transaction signature, bounding the
+/// network fee a batch may pass on to its consumers
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// One submission of a batch
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq)]
+pub struct BatchEntry {
+    pub amount: u64,
+    pub nonce: u64,
+    pub lane: u64,
+}
+
+/// Submit batch argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitBatchArgs {
+    /// Network fee the executor paid for the batch transaction, split across the entries
+    pub network_fee: u64,
+    pub entries: Vec<BatchEntry>,
+}
+
+impl SubmitBatchArgs {
+    /// Layout after the instruction tag: `network_fee: u64`, `count: u8`,
+    /// then `count` entries of `amount: u64`, `nonce: u64`, `lane: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const HEADER: usize = size_of::<u8>() + size_of::<u64>() + size_of::<u8>();
+        if input.len() < HEADER {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let network_fee = read_u64(&input[1..]);
+        let count = input[HEADER - 1] as usize;
+        if count == 0 || count > MAX_BATCH_SIZE {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if input.len() < HEADER + count * size_of::<BatchEntry>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let entries = input[HEADER..]
+            .chunks_exact(size_of::<BatchEntry>())
+            .take(count)
+            .map(|entry| BatchEntry {
+                amount: read_u64(entry),
+                nonce: read_u64(&entry[8..]),
+                lane: read_u64(&entry[16..]),
+            })
+            .collect();
+        Ok(Self {
+            network_fee,
+            entries,
+        })
+    }
+
+    /// Network fee charged to each entry. The remainder of an uneven split
+    /// goes to the first entries, so the shares add up to `network_fee`.
+    pub fn network_fee_shares(&self) -> Vec<u64> {
+        let count = self.entries.len() as u64;
+        let share = self.network_fee / count;
+        let remainder = self.network_fee % count;
+        (0..count)
+            .map(|index| share + u64::from(index < remainder))
+            .collect()
+    }
+}
+
+/// Submit argument structure for relaying an arbitrary instruction
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitInstructionArgs {
+    pub nonce: u64,
+    pub lane: u64,
+    /// Instruction of the target program, invoked with the accounts following
+    /// the submission's
+    pub instruction: Instruction,
+}
+
+impl SubmitInstructionArgs {
+    /// Layout after the instruction tag: `nonce: u64`, `lane: u64`,
+    /// `program_id: [u8; 32]`, `count: u8`, then `count` account metas of
+    /// `pubkey: [u8; 32]`, `is_signer: u8`, `is_writable: u8`, then
+    /// `data_len: u16` and the instruction data
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const HEADER: usize = size_of::<u8>() + 2 * size_of::<u64>() + 32 + size_of::<u8>();
+        const META_LEN: usize = 32 + 2;
+        if input.len() < HEADER {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let nonce = read_u64(&input[1..]);
+        let lane = read_u64(&input[9..]);
+        let program_id = read_pubkey(&input[17..]);
+        let count = input[HEADER - 1] as usize;
+        let data_start = HEADER + count * META_LEN + size_of::<u16>();
+        if input.len() < data_start {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts = input[HEADER..]
+            .chunks_exact(META_LEN)
+            .take(count)
+            .map(|meta| AccountMeta {
+                pubkey: read_pubkey(meta),
+                is_signer: meta[32] != 0,
+                is_writable: meta[33] != 0,
+            })
+            .collect();
+        let data_len = u16::from_le_bytes([input[data_start - 2], input[data_start - 1]]) as usize;
+        if input.len() != data_start + data_len {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            nonce,
+            lane,
+            instruction: Instruction {
+                program_id,
+                accounts,
+                data: input[data_start..].to_vec(),
+            },
+        })
+    }
+}
+
+fn read_pubkey(input: &[u8]) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&input[..32]);
+    Pubkey::new_from_array(bytes)
+}
+
+fn read_u64(input: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&input[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Commit argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitArgs {
+    /// `SubmissionCommitment::hash_submission` of the submission to reveal
+    pub hash: [u8; 32],
+}
+
+/// Reveal argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevealArgs {
+    pub amount: u64,
+    pub nonce: u64,
+    pub lane: u64,
+    /// Salt the commitment was hashed with
+    pub salt: [u8; 32],
+}
+
+/// Basis points of a whole fee
+pub const FEE_SPLIT_DENOMINATOR: u64 = 10_000;
+
+/// Submit argument structure for a fee split between two executors
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitSplitArgs {
+    pub amount: u64,
+    pub nonce: u64,
+    pub lane: u64,
+    /// Basis points of the fee credited to the fee payer, the rest goes to the co-executor
+    pub fee_payer_bps: u64,
+}
+
+impl SubmitSplitArgs {
+    /// Fee payer's and co-executor's shares of `fee`. The fee payer's share
+    /// rounds down, so the shares add up to `fee`.
+    pub fn fee_shares(&self, fee: u64) -> (u64, u64) {
+        let fee_payer_share =
+            (fee as u128 * self.fee_payer_bps as u128 / FEE_SPLIT_DENOMINATOR as u128) as u64;
+        (fee_payer_share, fee - fee_payer_share)
+    }
+}
+
+/// Submit argument structure for a submission at an executor's quoted fee
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitQuotedArgs {
+    pub amount: u64,
+    pub nonce: u64,
+    pub lane: u64,
+    /// Fee the executor quoted
+    pub fee: u64,
+    /// Last slot the quote is valid in
+    pub expiry_slot: u64,
+}
+
+/// Update fee parameters argument structure
+#[derive(Clone, Debug, PartialEq)]
 pub struct UpdateFeeParamsArgs {
-    /// Fee mode: 0 = Fixed, 1 = Percent
+    /// Fee mode: 0 = Fixed, 1 = Percent, 2 = ComputeBased
     pub fee_mode_type: u8,
-    /// For Fixed: amount in lamports, For Percent: basis points (e.g., 100 = 1%)
+    /// For Fixed: amount in lamports, For Percent: basis points (e.g., 100 = 1%),
+    /// For ComputeBased: micro-lamports per compute unit
     pub fee_value: u64,
+    /// Further values of the modes taking them, 0 when left out. For Percent:
+    /// the minimum and maximum fee in lamports, 0 for no bound. For
+    /// ComputeBased: the base fee in lamports, then the maximum compute units
+    pub extra: [u64; 2],
+}
+
+impl UpdateFeeParamsArgs {
+    /// Layout after the instruction tag: `fee_mode_type: u8` padded to 8
+    /// bytes, `fee_value: u64`, then optionally `extra: [u64; 2]`. Clients
+    /// predating the extra values leave them out.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const LEN: usize = size_of::<u8>() + 2 * size_of::<u64>();
+        let extra = match input.len() {
+            LEN => [0; 2],
+            len if len == LEN + 2 * size_of::<u64>() => {
+                [read_u64(&input[LEN..]), read_u64(&input[LEN + 8..])]
+            }
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        Ok(Self {
+            fee_mode_type: input[1],
+            fee_value: read_u64(&input[9..]),
+            extra,
+        })
+    }
+}
+
+/// Submit argument structure for a submission whose compute units the
+/// relayer measured
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitMeteredArgs {
+    pub amount: u64,
+    pub nonce: u64,
+    /// Compute units of the submission, as measured by the relayer
+    pub compute_units: u64,
+}
+
+impl SubmitMeteredArgs {
+    /// Layout after the instruction tag: `amount: u64`, `nonce: u64`,
+    /// `compute_units: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + 3 * size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+            nonce: read_u64(&input[9..]),
+            compute_units: read_u64(&input[17..]),
+        })
+    }
 }
 
 /// Add/Remove allowed token argument structure
@@ -37,6 +487,604 @@ pub struct TokenMintArgs {
     pub mint: [u8; 32],
 }
 
+/// Add/Remove allowed program argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramIdArgs {
+    /// Target program address as bytes
+    pub program_id: [u8; 32],
+}
+
+/// Balance query argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryAccountArgs {
+    /// Consumer or executor address as bytes
+    pub account: [u8; 32],
+}
+
+/// Fee quote query argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct GetFeeQuoteArgs {
+    /// Lamports the submission would transfer
+    pub amount: u64,
+    pub consumer: Pubkey,
+}
+
+impl GetFeeQuoteArgs {
+    /// Layout after the instruction tag: `amount: u64`, `consumer: [u8; 32]`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const LEN: usize = size_of::<u8>() + size_of::<u64>() + 32;
+        if input.len() != LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+            consumer: read_pubkey(&input[1 + size_of::<u64>()..]),
+        })
+    }
+}
+
+/// Register/Deregister executor argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutorArgs {
+    /// Executor (fee payer) address as bytes
+    pub executor: [u8; 32],
+}
+
+/// Stake executor / set minimum stake argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StakeArgs {
+    /// Lamports
+    pub amount: u64,
+}
+
+/// Slash executor argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlashArgs {
+    /// Lamports of the stake to take, capped at the stake
+    pub amount: u64,
+    /// Executor address as bytes
+    pub executor: [u8; 32],
+}
+
+/// Set protocol fee argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProtocolFeeArgs {
+    /// Basis points of every lamport fee kept for the treasury
+    pub protocol_fee_bps: u16,
+}
+
+/// Set free tier argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetFreeTierArgs {
+    /// Free submissions of every consumer, 0 turns the free tier off
+    pub free_tx_quota: u64,
+    /// Subsidy account address as bytes, whose balance pays for them
+    pub subsidy: [u8; 32],
+}
+
+/// Set payload retention argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetPayloadRetentionArgs {
+    /// Executed payloads whose hashes are retained, 0 turns the check off
+    pub retention: u16,
+}
+
+impl SetPayloadRetentionArgs {
+    /// Layout after the instruction tag: `retention: u16`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u16>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            retention: u16::from_le_bytes([input[1], input[2]]),
+        })
+    }
+}
+
+/// Set record retention argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetRecordRetentionArgs {
+    /// Executor records kept of every consumer's lane, 0 keeps them all
+    pub retention: u16,
+}
+
+impl SetRecordRetentionArgs {
+    /// Layout after the instruction tag: `retention: u16`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u16>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            retention: u16::from_le_bytes([input[1], input[2]]),
+        })
+    }
+}
+
+/// Set withdrawal cooldown argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetWithdrawalCooldownArgs {
+    /// Slots between requesting a withdrawal and making it, 0 for none
+    pub slots: u64,
+}
+
+impl SetWithdrawalCooldownArgs {
+    /// Layout after the instruction tag: `slots: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            slots: read_u64(&input[1..]),
+        })
+    }
+}
+
+/// Set price feed argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetPriceFeedArgs {
+    pub mint: Pubkey,
+    /// Oracle pricing the mint's fees, none to charge them in base units again
+    pub feed: Option<PriceFeed>,
+}
+
+impl SetPriceFeedArgs {
+    /// Layout after the instruction tag: `mint: [u8; 32]`, `feed: [u8; 32]`,
+    /// `decimals: u8`, `max_age_slots: u64`, `max_confidence_bps: u16`, then
+    /// optionally `kind: u8` (0 Pyth, 1 Switchboard), Pyth when left out. An
+    /// all-zero feed removes the mint's.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const LEN: usize = size_of::<u8>() + 2 * 32 + size_of::<u8>() + size_of::<u64>() + size_of::<u16>();
+        let kind = match input.len() {
+            LEN => OracleKind::Pyth,
+            len if len == LEN + 1 => match input[LEN] {
+                0 => OracleKind::Pyth,
+                1 => OracleKind::Switchboard,
+                _ => return Err(ProgramError::InvalidInstructionData),
+            },
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        let feed = read_pubkey(&input[33..]);
+        Ok(Self {
+            mint: read_pubkey(&input[1..]),
+            feed: (feed != Pubkey::default()).then(|| PriceFeed {
+                feed,
+                decimals: input[65],
+                max_age_slots: read_u64(&input[66..]),
+                max_confidence_bps: u16::from_le_bytes([input[74], input[75]]),
+                kind,
+            }),
+        })
+    }
+}
+
+/// Claim token fees as SOL argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClaimTokenFeesAsSolArgs {
+    /// Fewest lamports the swap of the earnings may deliver
+    pub min_lamports: u64,
+    /// Instruction data of the Jupiter route swapping them
+    pub route: Vec<u8>,
+}
+
+impl ClaimTokenFeesAsSolArgs {
+    /// Layout after the instruction tag: `min_lamports: u64`, then the route
+    /// data to the end
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const LEN: usize = size_of::<u8>() + size_of::<u64>();
+        if input.len() < LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            min_lamports: read_u64(&input[1..]),
+            route: input[LEN..].to_vec(),
+        })
+    }
+}
+
+/// Set rate limit argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetRateLimitArgs {
+    /// Highest submissions of a consumer within a window, 0 for no limit
+    pub max_submissions: u64,
+    /// Slots a window lasts
+    pub window_slots: u64,
+}
+
+impl SetRateLimitArgs {
+    /// Layout after the instruction tag: `max_submissions: u64`,
+    /// `window_slots: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + 2 * size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            max_submissions: read_u64(&input[1..]),
+            window_slots: read_u64(&input[9..]),
+        })
+    }
+}
+
+/// Set circuit breaker argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetCircuitBreakerArgs {
+    /// Basis points of failed executions above which the deployment pauses,
+    /// 0 to turn the breaker off
+    pub max_failure_bps: u16,
+    /// Executions of the epoch before the ratio is judged
+    pub min_executions: u64,
+}
+
+impl SetCircuitBreakerArgs {
+    /// Layout after the instruction tag: `max_failure_bps: u16`,
+    /// `min_executions: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u16>() + size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            max_failure_bps: u16::from_le_bytes([input[1], input[2]]),
+            min_executions: read_u64(&input[3..]),
+        })
+    }
+}
+
+/// Set stats tracking argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetStatsTrackingArgs {
+    pub enabled: bool,
+}
+
+impl SetStatsTrackingArgs {
+    /// Layout after the instruction tag: `enabled: u8`, 0 or 1
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() * 2 || input[1] > 1 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            enabled: input[1] == 1,
+        })
+    }
+}
+
+/// Distribute rewards argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributeRewardsArgs {
+    /// Lamports of the treasury to split
+    pub amount: u64,
+}
+
+impl DistributeRewardsArgs {
+    /// Layout after the instruction tag: `amount: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+        })
+    }
+}
+
+/// Set insurance fee argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct InsuranceFeeArgs {
+    /// Basis points of every lamport fee kept for the insurance pool
+    pub insurance_fee_bps: u16,
+}
+
+impl InsuranceFeeArgs {
+    /// Layout after the instruction tag: `insurance_fee_bps: u16`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<u16>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            insurance_fee_bps: u16::from_le_bytes([input[1], input[2]]),
+        })
+    }
+}
+
+/// Compensate argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompensateArgs {
+    /// Lamports of the insurance pool to pay
+    pub amount: u64,
+    /// Lane and nonce of the consumer's faulty execution
+    pub lane: u64,
+    pub nonce: u64,
+    /// Executor of the channel whose nonce it is, for [`CHANNEL_NONCE_LANE`]
+    pub channel_executor: Option<Pubkey>,
+}
+
+impl CompensateArgs {
+    /// Layout after the instruction tag: `amount: u64`, `lane: u64`,
+    /// `nonce: u64`, then `channel_executor: Pubkey` for the channel lane
+    /// only
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let len = size_of::<u8>() + 3 * size_of::<u64>();
+        if input.len() < len {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let lane = read_u64(&input[9..]);
+        let channel_executor = match (lane, &input[len..]) {
+            (CHANNEL_NONCE_LANE, executor) if executor.len() == 32 => Some(read_pubkey(executor)),
+            (CHANNEL_NONCE_LANE, _) => return Err(ProgramError::InvalidInstructionData),
+            (_, []) => None,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        Ok(Self {
+            amount: read_u64(&input[1..]),
+            lane,
+            nonce: read_u64(&input[17..]),
+            channel_executor,
+        })
+    }
+}
+
+/// Report failure argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportFailureArgs {
+    /// Consumer whose submission failed in the target program
+    pub consumer: Pubkey,
+    /// Lane and nonce of the failed submission
+    pub lane: u64,
+    pub nonce: u64,
+}
+
+impl ReportFailureArgs {
+    /// Layout after the instruction tag: `consumer: Pubkey`, `lane: u64`,
+    /// `nonce: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + 32 + 2 * size_of::<u64>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            consumer: read_pubkey(&input[1..]),
+            lane: read_u64(&input[33..]),
+            nonce: read_u64(&input[41..]),
+        })
+    }
+}
+
+/// Blacklist/Unblacklist address argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlacklistArgs {
+    /// Consumer or executor address
+    pub address: Pubkey,
+}
+
+impl BlacklistArgs {
+    /// Layout after the instruction tag: `address: [u8; 32]`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != size_of::<u8>() + size_of::<Pubkey>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            address: read_pubkey(&input[1..]),
+        })
+    }
+}
+
+/// Set genesis hash argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetGenesisHashArgs {
+    /// Genesis hash of the cluster the deployment runs on
+    pub genesis_hash: [u8; 32],
+}
+
+/// Cancel nonce argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct CancelNonceArgs {
+    /// Nonce to burn
+    pub nonce: u64,
+    /// Nonce lane of `nonce`, lane 0 by default
+    pub lane: u64,
+}
+
+impl CancelNonceArgs {
+    /// Layout after the instruction tag: `nonce: u64`, then optionally
+    /// `lane: u64`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const LEN: usize = size_of::<u8>() + size_of::<u64>();
+        let lane = match input.len() {
+            LEN => 0,
+            len if len == LEN + size_of::<u64>() => read_u64(&input[LEN..]),
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        Ok(Self {
+            nonce: read_u64(&input[1..]),
+            lane,
+        })
+    }
+}
+
+/// Set volume discounts argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetVolumeDiscountsArgs {
+    /// Tiers replacing the current ones, none turns the discounts off
+    pub tiers: Vec<VolumeDiscountTier>,
+}
+
+impl SetVolumeDiscountsArgs {
+    /// Layout after the instruction tag: `count: u8`, then `count` tiers of
+    /// `min_txs: u64` and `discount_bps: u16`, at most `MAX_VOLUME_DISCOUNT_TIERS`
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const HEADER: usize = size_of::<u8>() * 2;
+        const TIER_LEN: usize = size_of::<u64>() + size_of::<u16>();
+        if input.len() < HEADER {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let count = input[1] as usize;
+        if count > MAX_VOLUME_DISCOUNT_TIERS || input.len() != HEADER + count * TIER_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            tiers: input[HEADER..]
+                .chunks_exact(TIER_LEN)
+                .map(|tier| VolumeDiscountTier {
+                    min_txs: read_u64(tier),
+                    discount_bps: u16::from_le_bytes([tier[8], tier[9]]),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Set executor registry argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetExecutorRegistryArgs {
+    /// 0 lets any executor execute, anything else only registered ones
+    pub required: u8,
+}
+
+/// Set credential mint argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetCredentialMintArgs {
+    /// 0 turns compliance mode off, anything else turns it on with `mint`
+    pub enabled: u8,
+    /// Credential mint address as bytes
+    pub mint: [u8; 32],
+}
+
+/// Set sandbox argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetSandboxArgs {
+    /// 0 turns sandbox mode off, anything else turns it on
+    pub enabled: u8,
+    /// 0 waives fees, anything else charges them to `subsidy`
+    pub has_subsidy: u8,
+    /// Subsidy account address as bytes
+    pub subsidy: [u8; 32],
+}
+
+/// Initiate shutdown argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitiateShutdownArgs {
+    /// Recovery address as bytes, receiving the residue swept after the grace period
+    pub recovery: [u8; 32],
+}
+
+/// Set multisig argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetMultisigArgs {
+    /// Signatures required, 0 with no signers returns governance to the authority
+    pub threshold: u8,
+    pub signers: Vec<Pubkey>,
+}
+
+impl SetMultisigArgs {
+    /// Layout after the instruction tag: `threshold: u8`, `count: u8`, then
+    /// `count` signer keys
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const HEADER: usize = size_of::<u8>() * 3;
+        if input.len() < HEADER {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let count = input[2] as usize;
+        if input.len() != HEADER + count * 32 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(Self {
+            threshold: input[1],
+            signers: input[HEADER..].chunks_exact(32).map(read_pubkey).collect(),
+        })
+    }
+}
+
+/// Register sponsor argument structure
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisterSponsorArgs {
+    /// Highest fee of a single sponsored submission, in lamports
+    pub max_fee_per_tx: u64,
+    /// Target programs the sponsor pays every instruction of
+    pub allowed_programs: Vec<Pubkey>,
+    /// Relayed instructions the sponsor pays for, on top of `allowed_programs`
+    pub allowed_instructions: Vec<InstructionFilter>,
+    /// Most submissions the sponsor pays for each user, 0 for no quota
+    pub max_submissions_per_user: u64,
+}
+
+impl RegisterSponsorArgs {
+    /// Layout after the instruction tag: `max_fee_per_tx: u64`, `count: u8`,
+    /// then `count` program ids, at most `MAX_SPONSOR_PROGRAMS`. Optionally
+    /// followed by `count: u8`, `count` filters of `program_id: [u8; 32]` and
+    /// `discriminator: u8`, at most `MAX_SPONSOR_INSTRUCTIONS`, then
+    /// `max_submissions_per_user: u64`.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        const HEADER: usize = size_of::<u8>() + size_of::<u64>() + size_of::<u8>();
+        const FILTER_LEN: usize = 32 + size_of::<u8>();
+        if input.len() < HEADER {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let count = input[HEADER - 1] as usize;
+        let programs_end = HEADER + count * 32;
+        if count > MAX_SPONSOR_PROGRAMS || input.len() < programs_end {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let allowed_programs = input[HEADER..programs_end]
+            .chunks_exact(32)
+            .map(read_pubkey)
+            .collect();
+
+        let (allowed_instructions, max_submissions_per_user) = match &input[programs_end..] {
+            [] => (Vec::new(), 0),
+            [count, rest @ ..] => {
+                let count = *count as usize;
+                if count > MAX_SPONSOR_INSTRUCTIONS
+                    || rest.len() != count * FILTER_LEN + size_of::<u64>()
+                {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                let filters = rest[..count * FILTER_LEN]
+                    .chunks_exact(FILTER_LEN)
+                    .map(|filter| InstructionFilter {
+                        program_id: read_pubkey(filter),
+                        discriminator: filter[32],
+                    })
+                    .collect();
+                (filters, read_u64(&rest[count * FILTER_LEN..]))
+            }
+        };
+        Ok(Self {
+            max_fee_per_tx: read_u64(&input[1..]),
+            allowed_programs,
+            allowed_instructions,
+            max_submissions_per_user,
+        })
+    }
+}
+
+/// Set spending limit argument structure
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetSpendingLimitArgs {
+    /// Highest fee of a single submission, in lamports
+    pub max_fee_per_tx: u64,
+    /// Highest sum of fees within an epoch, in lamports
+    pub max_fees_per_epoch: u64,
+    /// Consumer address as bytes
+    pub consumer: [u8; 32],
+}
+
+impl SetSpendingLimitArgs {
+    /// Both limits at `u64::MAX` cap nothing, and remove the consumer's limit
+    pub fn is_unlimited(&self) -> bool {
+        self.max_fee_per_tx == u64::MAX && self.max_fees_per_epoch == u64::MAX
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq)]
 pub enum GsnInstruction {
@@ -47,6 +1095,157 @@ pub enum GsnInstruction {
     AddAllowedToken(TokenMintArgs),
     RemoveAllowedToken(TokenMintArgs),
     ClaimFees,
+    InitializeMetrics,
+    SubmitTransactionInLane(SubmitLaneArgs),
+    SubmitBatch(SubmitBatchArgs),
+    /// Like `SubmitTransactionInLane`, settling the fee through a `FeeEscrow`
+    SubmitEscrowed(SubmitLaneArgs),
+    ReleaseEscrow,
+    DisputeEscrow,
+    CommitSubmission(CommitArgs),
+    RevealSubmission(RevealArgs),
+    SetCredentialMint(SetCredentialMintArgs),
+    /// Like `SubmitTransactionInLane`, splitting the fee with a co-executor
+    SubmitSplit(SubmitSplitArgs),
+    SetSandbox(SetSandboxArgs),
+    ResetSandbox,
+    /// Like `SubmitTransactionInLane`, charging the fee quoted by the executor
+    SubmitQuoted(SubmitQuotedArgs),
+    InitiateShutdown(InitiateShutdownArgs),
+    /// Pays part of a consumer's top-up balance back to it
+    Withdraw(WithdrawArgs),
+    /// Sweeps the residue of a shut down deployment to its recovery address
+    Sweep,
+    /// Like `Topup`, crediting a token balance out of the payer's token account
+    TopupToken(TopupAgrs),
+    /// Like `SubmitTransactionInLane`, charging the fee to a token balance
+    SubmitTokenFee(SubmitTokenFeeArgs),
+    /// Pays an executor's earnings in one mint to its token account
+    ClaimTokenFees,
+    /// Like `SubmitTransactionInLane`, checking the consumer's signed intent
+    SubmitIntent(SubmitLaneArgs),
+    /// Registers an Ethereum address that signs intents for the consumer
+    RegisterEthAddress(RegisterEthAddressArgs),
+    /// Like `SubmitIntent` for an intent signed by a registered Ethereum
+    /// address, paying the transfer out of the consumer's balance
+    SubmitEthIntent(SubmitLaneArgs),
+    /// Relays an arbitrary instruction of the target program instead of a transfer
+    SubmitInstruction(SubmitInstructionArgs),
+    /// Caps the lamport fees a consumer may be charged
+    SetSpendingLimit(SetSpendingLimitArgs),
+    /// Approves a target program for submissions
+    AddAllowedProgram(ProgramIdArgs),
+    RemoveAllowedProgram(ProgramIdArgs),
+    /// Halts top-ups, submissions, withdrawals and fee claims until `Unpause`
+    Pause,
+    Unpause,
+    /// Hands governance to an M-of-N multisig, or back to the authority
+    SetMultisig(SetMultisigArgs),
+    /// Like `UpdateFeeParams`, but applicable by `ApplyFeeParams` only after
+    /// `FEE_PARAMS_TIMELOCK_SLOTS`
+    ProposeFeeParams(UpdateFeeParamsArgs),
+    ApplyFeeParams,
+    RegisterExecutor(ExecutorArgs),
+    DeregisterExecutor(ExecutorArgs),
+    /// Turns the permissioned phase, where only registered executors execute
+    /// and earn fees, on or off
+    SetExecutorRegistry(SetExecutorRegistryArgs),
+    StakeExecutor(StakeArgs),
+    /// Starts unbonding the caller's stake, or withdraws it once unbonded
+    UnstakeExecutor,
+    SlashExecutor(SlashArgs),
+    SetMinExecutorStake(StakeArgs),
+    SetProtocolFee(ProtocolFeeArgs),
+    /// Pays lamports out of the treasury, signed by the governance authority
+    WithdrawTreasury(WithdrawArgs),
+    /// Creates or replaces the caller's sponsor policy
+    RegisterSponsor(RegisterSponsorArgs),
+    /// Like `SubmitTransaction`, charging the fee to a sponsor's balance
+    SubmitSponsored(SubmitArgs),
+    /// Like `SubmitInstruction`, charging the fee to a sponsor's balance
+    SubmitSponsoredInstruction(SubmitInstructionArgs),
+    /// Charges the first submissions of every consumer to a subsidy's balance
+    SetFreeTier(SetFreeTierArgs),
+    /// Like `SubmitTransaction`, charging a `ComputeBased` fee for the
+    /// measured compute units
+    SubmitMetered(SubmitMeteredArgs),
+    /// Replaces the tiers discounting the fees of consumers by their
+    /// submissions in the epoch
+    SetVolumeDiscounts(SetVolumeDiscountsArgs),
+    /// Registers the address the caller's fees may be claimed to, signed by
+    /// the current one as well when it replaces one
+    SetPayoutAddress,
+    /// Burns a nonce the consumer signed for, so the submission can't be
+    /// relayed
+    CancelNonce(CancelNonceArgs),
+    /// Sets how many executed payloads are retained to reject duplicates
+    SetPayloadRetention(SetPayloadRetentionArgs),
+    /// Sets the genesis hash bound into the domain separator of intents
+    SetGenesisHash(SetGenesisHashArgs),
+    /// Moves the caller's top-up balance and lane 0 nonce into its consumer
+    /// ledger account, creating it
+    OpenConsumerAccount,
+    /// Moves the caller's earnings into its executor ledger account, creating it
+    OpenExecutorAccount,
+    /// Rewrites the state account in the current layout version
+    MigrateState,
+    /// Returns a `query::ConsumerBalance` as return data, changing nothing
+    GetConsumerBalance(QueryAccountArgs),
+    /// Returns a `query::ExecutorBalance` as return data, changing nothing
+    GetExecutorBalance(QueryAccountArgs),
+    /// Returns the `query::FeeEstimate` of a lamport submission as return
+    /// data, changing nothing
+    GetFeeQuote(GetFeeQuoteArgs),
+    /// Sets how many executor records of every consumer's lane `PruneRecords`
+    /// keeps
+    SetRecordRetention(SetRecordRetentionArgs),
+    /// Drops the executor records past the retention, callable by anyone
+    PruneRecords,
+    /// Removes the caller, whose balances are all empty, from the state and
+    /// closes its consumer ledger account, refunding the rent
+    CloseConsumer,
+    /// Sets the slots a consumer waits between `RequestWithdrawal` and
+    /// `Withdraw`
+    SetWithdrawalCooldown(SetWithdrawalCooldownArgs),
+    /// Starts the cooldown of a withdrawal of part of the caller's balance
+    RequestWithdrawal(WithdrawArgs),
+    /// Lets withdrawals skip the pause and the cooldown until `Unpause`,
+    /// only while paused
+    EnableEmergencyWithdrawals,
+    /// Sets or removes the oracle pricing a mint's token fees
+    SetPriceFeed(SetPriceFeedArgs),
+    /// Like `ClaimTokenFees` for wrapped SOL earnings, unwrapping them to a
+    /// lamport destination by closing the executor's token account
+    ClaimWrappedSol,
+    /// Like `ClaimTokenFees`, swapping the earnings to SOL through a Jupiter
+    /// route and unwrapping them like `ClaimWrappedSol`
+    ClaimTokenFeesAsSol(ClaimTokenFeesAsSolArgs),
+    /// Sets how many submissions every consumer may make per window of slots
+    SetRateLimit(SetRateLimitArgs),
+    /// Bars an address from topping up, submitting as consumer or executor,
+    /// and claiming fees
+    BlacklistAddress(BlacklistArgs),
+    /// Lifts an address's blacklisting
+    UnblacklistAddress(BlacklistArgs),
+    /// Sets the failure ratio of an epoch's executions that pauses the
+    /// deployment
+    SetCircuitBreaker(SetCircuitBreakerArgs),
+    /// Turns the counting of executions by epoch on or off
+    SetStatsTracking(SetStatsTrackingArgs),
+    /// Returns the `query::DeploymentStats` of the current and previous
+    /// epochs as return data, changing nothing
+    GetStats,
+    /// Credits part of the treasury to the executors of the last epoch
+    /// counted, weighted by stake and executions, claimable like fees
+    DistributeRewards(DistributeRewardsArgs),
+    /// Sets the cut of every lamport fee kept for the insurance pool
+    SetInsuranceFee(InsuranceFeeArgs),
+    /// Pays a consumer out of the insurance pool for a recorded execution
+    /// governance judged faulty
+    Compensate(CompensateArgs),
+    /// Counts a relay that failed in the target program towards the circuit
+    /// breaker, reported by the registered executor that sent it
+    ReportFailure(ReportFailureArgs),
 }
 
 impl GsnInstruction {
@@ -56,18 +1255,9 @@ impl GsnInstruction {
         }
         Ok(match input[0] {
             0 => Self::Initialize,
-            1 => {
-                let val: &TopupAgrs = unpack(input)?;
-                Self::Topup(val.clone())
-            }
-            2 => {
-                let val: &SubmitArgs = unpack(input)?;
-                Self::SubmitTransaction(val.clone())
-            }
-            3 => {
-                let val: &UpdateFeeParamsArgs = unpack(input)?;
-                Self::UpdateFeeParams(val.clone())
-            }
+            1 => Self::Topup(TopupAgrs::unpack(input)?),
+            2 => Self::SubmitTransaction(SubmitArgs::unpack(input)?),
+            3 => Self::UpdateFeeParams(UpdateFeeParamsArgs::unpack(input)?),
             4 => {
                 let val: &TokenMintArgs = unpack(input)?;
                 Self::AddAllowedToken(val.clone())
@@ -77,6 +1267,160 @@ impl GsnInstruction {
                 Self::RemoveAllowedToken(val.clone())
             }
             6 => Self::ClaimFees,
+            7 => Self::InitializeMetrics,
+            8 => Self::SubmitTransactionInLane(SubmitLaneArgs::unpack(input)?),
+            9 => Self::SubmitBatch(SubmitBatchArgs::unpack(input)?),
+            10 => Self::SubmitEscrowed(SubmitLaneArgs::unpack_lane(input)?),
+            11 => Self::ReleaseEscrow,
+            12 => Self::DisputeEscrow,
+            13 => {
+                let val: &CommitArgs = unpack(input)?;
+                Self::CommitSubmission(val.clone())
+            }
+            14 => {
+                let val: &RevealArgs = unpack(input)?;
+                Self::RevealSubmission(val.clone())
+            }
+            15 => {
+                let val: &SetCredentialMintArgs = unpack(input)?;
+                Self::SetCredentialMint(val.clone())
+            }
+            16 => {
+                let val: &SubmitSplitArgs = unpack(input)?;
+                Self::SubmitSplit(val.clone())
+            }
+            17 => {
+                let val: &SetSandboxArgs = unpack(input)?;
+                Self::SetSandbox(val.clone())
+            }
+            18 => Self::ResetSandbox,
+            19 => {
+                let val: &SubmitQuotedArgs = unpack(input)?;
+                Self::SubmitQuoted(val.clone())
+            }
+            20 => {
+                let val: &InitiateShutdownArgs = unpack(input)?;
+                Self::InitiateShutdown(val.clone())
+            }
+            21 => {
+                let val: &WithdrawArgs = unpack(input)?;
+                Self::Withdraw(val.clone())
+            }
+            22 => Self::Sweep,
+            23 => Self::TopupToken(TopupAgrs::unpack(input)?),
+            24 => {
+                let val: &SubmitTokenFeeArgs = unpack(input)?;
+                Self::SubmitTokenFee(val.clone())
+            }
+            25 => Self::ClaimTokenFees,
+            26 => Self::SubmitIntent(SubmitLaneArgs::unpack_lane(input)?),
+            27 => Self::RegisterEthAddress(RegisterEthAddressArgs::unpack(input)?),
+            28 => Self::SubmitEthIntent(SubmitLaneArgs::unpack_lane(input)?),
+            29 => Self::SubmitInstruction(SubmitInstructionArgs::unpack(input)?),
+            30 => {
+                let val: &SetSpendingLimitArgs = unpack(input)?;
+                Self::SetSpendingLimit(val.clone())
+            }
+            31 => {
+                let val: &ProgramIdArgs = unpack(input)?;
+                Self::AddAllowedProgram(val.clone())
+            }
+            32 => {
+                let val: &ProgramIdArgs = unpack(input)?;
+                Self::RemoveAllowedProgram(val.clone())
+            }
+            33 => Self::Pause,
+            34 => Self::Unpause,
+            35 => Self::SetMultisig(SetMultisigArgs::unpack(input)?),
+            36 => Self::ProposeFeeParams(UpdateFeeParamsArgs::unpack(input)?),
+            37 => Self::ApplyFeeParams,
+            38 => {
+                let val: &ExecutorArgs = unpack(input)?;
+                Self::RegisterExecutor(val.clone())
+            }
+            39 => {
+                let val: &ExecutorArgs = unpack(input)?;
+                Self::DeregisterExecutor(val.clone())
+            }
+            40 => {
+                let val: &SetExecutorRegistryArgs = unpack(input)?;
+                Self::SetExecutorRegistry(val.clone())
+            }
+            41 => {
+                let val: &StakeArgs = unpack(input)?;
+                Self::StakeExecutor(val.clone())
+            }
+            42 => Self::UnstakeExecutor,
+            43 => {
+                let val: &SlashArgs = unpack(input)?;
+                Self::SlashExecutor(val.clone())
+            }
+            44 => {
+                let val: &StakeArgs = unpack(input)?;
+                Self::SetMinExecutorStake(val.clone())
+            }
+            45 => {
+                let val: &ProtocolFeeArgs = unpack(input)?;
+                Self::SetProtocolFee(val.clone())
+            }
+            46 => {
+                let val: &WithdrawArgs = unpack(input)?;
+                Self::WithdrawTreasury(val.clone())
+            }
+            47 => Self::RegisterSponsor(RegisterSponsorArgs::unpack(input)?),
+            48 => {
+                let args = SubmitArgs::unpack(input)?;
+                // Sponsors pay the configured fee, not an executor's quote
+                if args.quote.is_some() {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Self::SubmitSponsored(args)
+            }
+            49 => Self::SubmitSponsoredInstruction(SubmitInstructionArgs::unpack(input)?),
+            50 => {
+                let val: &SetFreeTierArgs = unpack(input)?;
+                Self::SetFreeTier(val.clone())
+            }
+            51 => Self::SubmitMetered(SubmitMeteredArgs::unpack(input)?),
+            52 => Self::SetVolumeDiscounts(SetVolumeDiscountsArgs::unpack(input)?),
+            53 => Self::SetPayoutAddress,
+            54 => Self::CancelNonce(CancelNonceArgs::unpack(input)?),
+            55 => Self::SetPayloadRetention(SetPayloadRetentionArgs::unpack(input)?),
+            56 => {
+                let val: &SetGenesisHashArgs = unpack(input)?;
+                Self::SetGenesisHash(val.clone())
+            }
+            57 => Self::OpenConsumerAccount,
+            58 => Self::OpenExecutorAccount,
+            59 => Self::MigrateState,
+            60 => {
+                let val: &QueryAccountArgs = unpack(input)?;
+                Self::GetConsumerBalance(val.clone())
+            }
+            61 => {
+                let val: &QueryAccountArgs = unpack(input)?;
+                Self::GetExecutorBalance(val.clone())
+            }
+            62 => Self::GetFeeQuote(GetFeeQuoteArgs::unpack(input)?),
+            63 => Self::SetRecordRetention(SetRecordRetentionArgs::unpack(input)?),
+            64 => Self::PruneRecords,
+            65 => Self::CloseConsumer,
+            66 => Self::SetWithdrawalCooldown(SetWithdrawalCooldownArgs::unpack(input)?),
+            67 => Self::RequestWithdrawal(WithdrawArgs::unpack(input)?),
+            68 => Self::EnableEmergencyWithdrawals,
+            69 => Self::SetPriceFeed(SetPriceFeedArgs::unpack(input)?),
+            70 => Self::ClaimWrappedSol,
+            71 => Self::ClaimTokenFeesAsSol(ClaimTokenFeesAsSolArgs::unpack(input)?),
+            72 => Self::SetRateLimit(SetRateLimitArgs::unpack(input)?),
+            73 => Self::BlacklistAddress(BlacklistArgs::unpack(input)?),
+            74 => Self::UnblacklistAddress(BlacklistArgs::unpack(input)?),
+            75 => Self::SetCircuitBreaker(SetCircuitBreakerArgs::unpack(input)?),
+            76 => Self::SetStatsTracking(SetStatsTrackingArgs::unpack(input)?),
+            77 => Self::GetStats,
+            78 => Self::DistributeRewards(DistributeRewardsArgs::unpack(input)?),
+            79 => Self::SetInsuranceFee(InsuranceFeeArgs::unpack(input)?),
+            80 => Self::Compensate(CompensateArgs::unpack(input)?),
+            81 => Self::ReportFailure(ReportFailureArgs::unpack(input)?),
             _ => return Err(ProgramError::InvalidAccountData),
         })
     }
@@ -91,3 +1435,626 @@ pub fn unpack<T>(input: &[u8]) -> Result<&T, ProgramError> {
     let val: &T = unsafe { &*(&input[1] as *const u8 as *const T) };
     Ok(val)
 }
+
+/// `Initialize` of the `gsn` state and `config` accounts, handing governance
+/// to `authority` if one signs
+pub fn initialize(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![AccountMeta::new(*gsn, false), AccountMeta::new(*config, false)];
+    accounts.extend(authority.map(|authority| AccountMeta::new_readonly(*authority, true)));
+    Instruction::new_with_bytes(*program_id, &[0], accounts)
+}
+
+/// `Topup` of `consumer`'s balance by `amount` lamports, paid into the vault
+pub fn topup(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    consumer: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    topup_with_data(program_id, gsn, config, consumer, pack_u64s(1, &[amount]))
+}
+
+/// `Topup` of `beneficiary`'s balance by `amount` lamports, which `payer`
+/// signs and pays. A beneficiary with a ledger account needs it appended.
+pub fn topup_for(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    payer: &Pubkey,
+    beneficiary: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = pack_u64s(1, &[amount]);
+    data.extend_from_slice(beneficiary.as_ref());
+    topup_with_data(program_id, gsn, config, payer, data)
+}
+
+fn topup_with_data(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    payer: &Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    let (vault, _) = GsnInfo::find_vault_address(program_id, gsn);
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// `SubmitTransaction` of a transfer of `args.amount` lamports from `sender`
+/// to `receiver`, relayed by `executor`. Optional accounts, like the metrics
+/// account, are appended by the caller.
+pub fn submit_transaction(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    executor: &Pubkey,
+    args: &SubmitArgs,
+) -> Instruction {
+    let values = [
+        args.amount,
+        args.nonce,
+        args.expiry_slot.unwrap_or(0),
+        args.max_fee.unwrap_or(u64::MAX),
+        args.tip,
+    ];
+    let mut data = pack_u64s(2, &values);
+    if args.simulate || args.quote.is_some() {
+        data.push(args.simulate as u8);
+    }
+    if let Some(quote) = args.quote {
+        data.extend_from_slice(&quote.fee.to_le_bytes());
+        data.extend_from_slice(&quote.expiry_slot.to_le_bytes());
+    }
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(*receiver, false),
+            AccountMeta::new(*executor, true),
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+        ],
+    )
+}
+
+/// Like `submit_transaction`, a `SubmitTransactionInLane` of the transfer in
+/// `args.lane`
+pub fn submit_transaction_in_lane(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    executor: &Pubkey,
+    args: &SubmitLaneArgs,
+) -> Instruction {
+    let values = [
+        args.amount,
+        args.nonce,
+        args.lane,
+        args.expiry_slot.unwrap_or(0),
+        args.max_fee.unwrap_or(u64::MAX),
+        args.tip,
+    ];
+    Instruction::new_with_bytes(
+        *program_id,
+        &pack_u64s(8, &values),
+        vec![
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(*receiver, false),
+            AccountMeta::new(*executor, true),
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+        ],
+    )
+}
+
+/// Account of the `ExecutionReceipt` of `consumer`'s submission with `nonce`
+/// in `lane`, appended to a `submit_transaction` to have the executor write it
+pub fn receipt(program_id: &Pubkey, gsn: &Pubkey, consumer: &Pubkey, lane: u64, nonce: u64) -> AccountMeta {
+    let (receipt, _) = ExecutionReceipt::find_address(program_id, gsn, consumer, lane, nonce);
+    AccountMeta::new(receipt, false)
+}
+
+/// `ClaimFees` of `executor`'s earnings to `destination`, the executor itself
+/// or its payout address
+pub fn claim_fees(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    executor: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    let (vault, _) = GsnInfo::find_vault_address(program_id, gsn);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[6],
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*executor, true),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// `Withdraw` of `amount` lamports of `consumer`'s balance back to it
+pub fn withdraw(program_id: &Pubkey, gsn: &Pubkey, consumer: &Pubkey, amount: u64) -> Instruction {
+    let (vault, _) = GsnInfo::find_vault_address(program_id, gsn);
+    Instruction::new_with_bytes(
+        *program_id,
+        &pack_u64s(21, &[amount]),
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new(*consumer, true),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// `UpdateFeeParams` of `config`, signed by the governance `authority`.
+/// Only fee reductions apply right away, see `propose_fee_params`.
+pub fn update_fee_params(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    args: &UpdateFeeParamsArgs,
+) -> Instruction {
+    governance(program_id, config, authority, pack_fee_params(3, args))
+}
+
+/// `ProposeFeeParams` of `config`, applicable by `ApplyFeeParams` once the
+/// timelock passed
+pub fn propose_fee_params(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    args: &UpdateFeeParamsArgs,
+) -> Instruction {
+    governance(program_id, config, authority, pack_fee_params(36, args))
+}
+
+/// `AddAllowedToken` of `mint` to `config`, signed by the governance `authority`
+pub fn add_allowed_token(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let mut data = vec![4];
+    data.extend_from_slice(mint.as_ref());
+    governance(program_id, config, authority, data)
+}
+
+/// `GetConsumerBalance` of `consumer`, passing its ledger account in case
+/// it opened one
+pub fn get_consumer_balance(program_id: &Pubkey, gsn: &Pubkey, consumer: &Pubkey) -> Instruction {
+    let (ledger, _) = ConsumerAccount::find_address(program_id, gsn, consumer);
+    let mut data = vec![60];
+    data.extend_from_slice(consumer.as_ref());
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*gsn, false),
+            AccountMeta::new_readonly(ledger, false),
+        ],
+    )
+}
+
+/// `GetExecutorBalance` of `executor`, passing its ledger account in case
+/// it opened one
+pub fn get_executor_balance(program_id: &Pubkey, gsn: &Pubkey, executor: &Pubkey) -> Instruction {
+    let (ledger, _) = ExecutorAccount::find_address(program_id, gsn, executor);
+    let mut data = vec![61];
+    data.extend_from_slice(executor.as_ref());
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*gsn, false),
+            AccountMeta::new_readonly(ledger, false),
+        ],
+    )
+}
+
+/// `GetFeeQuote` of a transfer of `amount` lamports relayed for `consumer`
+pub fn get_fee_quote(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    consumer: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (ledger, _) = ConsumerAccount::find_address(program_id, gsn, consumer);
+    let mut data = pack_u64s(62, &[amount]);
+    data.extend_from_slice(consumer.as_ref());
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(ledger, false),
+        ],
+    )
+}
+
+/// `SetRecordRetention` of `config`, signed by the governance `authority`
+pub fn set_record_retention(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    retention: u16,
+) -> Instruction {
+    let mut data = vec![63];
+    data.extend_from_slice(&retention.to_le_bytes());
+    governance(program_id, config, authority, data)
+}
+
+/// `PruneRecords` of the `gsn` state account, which anyone may send
+pub fn prune_records(program_id: &Pubkey, gsn: &Pubkey, config: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[64],
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+        ],
+    )
+}
+
+/// `CloseConsumer` of `consumer`, passing its ledger account in case it
+/// opened one
+pub fn close_consumer(program_id: &Pubkey, gsn: &Pubkey, consumer: &Pubkey) -> Instruction {
+    let (ledger, _) = ConsumerAccount::find_address(program_id, gsn, consumer);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[65],
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new(*consumer, true),
+            AccountMeta::new(ledger, false),
+        ],
+    )
+}
+
+/// `SetWithdrawalCooldown` of the `gsn` state account, signed by the
+/// governance `authority`
+pub fn set_withdrawal_cooldown(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    slots: u64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &pack_u64s(66, &[slots]),
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `RequestWithdrawal` of `amount` lamports of `consumer`'s balance. The
+/// consumer pays for the room a first request takes.
+pub fn request_withdrawal(program_id: &Pubkey, gsn: &Pubkey, consumer: &Pubkey, amount: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &pack_u64s(67, &[amount]),
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new(*consumer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// `EnableEmergencyWithdrawals` of the paused `gsn` state account, signed by
+/// the governance `authority`
+pub fn enable_emergency_withdrawals(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[68],
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `SetPriceFeed` of `config` for `mint`, signed by the governance
+/// `authority`; no `feed` removes the mint's
+pub fn set_price_feed(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    feed: Option<&PriceFeed>,
+) -> Instruction {
+    let mut data = vec![69];
+    data.extend_from_slice(mint.as_ref());
+    match feed {
+        Some(feed) => {
+            data.extend_from_slice(feed.feed.as_ref());
+            data.push(feed.decimals);
+            data.extend_from_slice(&feed.max_age_slots.to_le_bytes());
+            data.extend_from_slice(&feed.max_confidence_bps.to_le_bytes());
+            data.push(feed.kind as u8);
+        }
+        None => data.resize(data.len() + 32 + 1 + 8 + 2, 0),
+    }
+    governance(program_id, config, authority, data)
+}
+
+/// `ClaimWrappedSol` of `executor`'s wrapped SOL earnings through its
+/// associated token account, unwrapped to `destination`, the executor itself
+/// or its payout address
+pub fn claim_wrapped_sol(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    executor: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    let (vault, _) = GsnInfo::find_vault_address(program_id, gsn);
+    Instruction::new_with_bytes(
+        *program_id,
+        &[70],
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*executor, true),
+            AccountMeta::new(token::associated_token_address(executor, &NATIVE_MINT, &TOKEN_PROGRAM_ID), false),
+            AccountMeta::new(token::associated_token_address(&vault, &NATIVE_MINT, &TOKEN_PROGRAM_ID), false),
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new(*destination, false),
+        ],
+    )
+}
+
+/// `SetRateLimit` of the `gsn` state account, signed by the governance
+/// `authority`
+pub fn set_rate_limit(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    max_submissions: u64,
+    window_slots: u64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &pack_u64s(72, &[max_submissions, window_slots]),
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `BlacklistAddress` of `address` in the `gsn` state account, or
+/// `UnblacklistAddress` unless `blacklisted`, signed by the governance
+/// `authority`
+pub fn set_blacklisted(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    address: &Pubkey,
+    blacklisted: bool,
+) -> Instruction {
+    let mut data = vec![if blacklisted { 73 } else { 74 }];
+    data.extend_from_slice(address.as_ref());
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `SetCircuitBreaker` of the `gsn` state account, signed by the governance
+/// `authority`
+pub fn set_circuit_breaker(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    max_failure_bps: u16,
+    min_executions: u64,
+) -> Instruction {
+    let mut data = vec![75];
+    data.extend_from_slice(&max_failure_bps.to_le_bytes());
+    data.extend_from_slice(&min_executions.to_le_bytes());
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `SetStatsTracking` of the `gsn` state account, signed by the governance
+/// `authority`
+pub fn set_stats_tracking(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    enabled: bool,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &[76, enabled as u8],
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `GetStats` of the `gsn` state account
+pub fn get_stats(program_id: &Pubkey, gsn: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(*program_id, &[77], vec![AccountMeta::new_readonly(*gsn, false)])
+}
+
+/// `DistributeRewards` of `amount` lamports of the `gsn` state account's
+/// treasury, signed by the governance `authority`, passing the ledger
+/// accounts of the `executors` to reward in case they opened one
+pub fn distribute_rewards(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    executors: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*gsn, false),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new_readonly(*authority, true),
+    ];
+    for executor in executors {
+        let (ledger, _) = ExecutorAccount::find_address(program_id, gsn, executor);
+        accounts.push(AccountMeta::new(ledger, false));
+    }
+    Instruction::new_with_bytes(*program_id, &pack_u64s(78, &[amount]), accounts)
+}
+
+/// `SetInsuranceFee` of `config`, signed by the governance `authority`
+pub fn set_insurance_fee(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    insurance_fee_bps: u16,
+) -> Instruction {
+    let mut data = vec![79];
+    data.extend_from_slice(&insurance_fee_bps.to_le_bytes());
+    governance(program_id, config, authority, data)
+}
+
+/// `Compensate` paying `consumer` out of the `gsn` state account's insurance
+/// pool, signed by the governance `authority`
+pub fn compensate(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    consumer: &Pubkey,
+    args: &CompensateArgs,
+) -> Instruction {
+    let (vault, _) = GsnInfo::find_vault_address(program_id, gsn);
+    let mut data = pack_u64s(80, &[args.amount, args.lane, args.nonce]);
+    if let Some(executor) = args.channel_executor {
+        data.extend_from_slice(executor.as_ref());
+    }
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*consumer, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// `ReportFailure` of the relay of `nonce` on `consumer`'s `lane`, signed by
+/// the `executor` that sent it
+pub fn report_failure(
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    executor: &Pubkey,
+    consumer: &Pubkey,
+    lane: u64,
+    nonce: u64,
+) -> Instruction {
+    let mut data = vec![81];
+    data.extend_from_slice(consumer.as_ref());
+    data.extend_from_slice(&lane.to_le_bytes());
+    data.extend_from_slice(&nonce.to_le_bytes());
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*gsn, false),
+            AccountMeta::new_readonly(*executor, true),
+        ],
+    )
+}
+
+/// Governance instruction on `config`, signed by `authority`
+fn governance(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    authority: &Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &data,
+        vec![
+            AccountMeta::new(*config, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+fn pack_fee_params(tag: u8, args: &UpdateFeeParamsArgs) -> Vec<u8> {
+    let mut data = vec![tag, args.fee_mode_type, 0, 0, 0, 0, 0, 0, 0];
+    for value in [args.fee_value, args.extra[0], args.extra[1]] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data
+}
+
+fn pack_u64s(tag: u8, values: &[u64]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(size_of::<u8>() + std::mem::size_of_val(values));
+    data.push(tag);
+    for value in values {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data
+}
@@ -0,0 +1,80 @@
+//! Consumer intents.
+//!
+//! A consumer signs an intent off-chain: the transfer it wants relayed, bound
+//! to one GSN deployment, target program and nonce. The relayer submits it
+//! with `SubmitIntent` after an Ed25519 program instruction verifying the
+//! consumer's signature, which the program checks through the Instructions
+//! sysvar. A relayer can then only submit transfers exactly as the consumer
+//! approved them.
+//!
+//! Intents are signed under a domain separator derived from the program id
+//! and the genesis hash of the cluster, so an intent signed for devnet or for
+//! another deployment of the program can't be replayed on mainnet.
+
+use crate::{error::GsnError, quote::has_ed25519_signature};
+use solana_program::{account_info::AccountInfo, hash::hashv, pubkey::Pubkey};
+
+/// Prefix of every intent message, so an intent signature can't be replayed
+/// as a signature over anything else
+pub const INTENT_DOMAIN: &[u8] = b"solgsn-intent";
+
+/// Domain separator of the intents of `program_id` on the cluster with
+/// `genesis_hash`, all zeroes for deployments that didn't set it
+pub fn domain_separator(program_id: &Pubkey, genesis_hash: Option<&[u8; 32]>) -> [u8; 32] {
+    hashv(&[
+        INTENT_DOMAIN,
+        program_id.as_ref(),
+        genesis_hash.unwrap_or(&[0; 32]),
+    ])
+    .to_bytes()
+}
+
+/// A transfer a consumer approves for relaying
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsumerIntent {
+    /// See [`domain_separator`]
+    pub domain_separator: [u8; 32],
+    pub gsn_account: Pubkey,
+    pub consumer: Pubkey,
+    pub target_program: Pubkey,
+    pub receiver: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,
+    pub lane: u64,
+}
+
+impl ConsumerIntent {
+    /// Message the consumer signs: the domain, domain separator, GSN state
+    /// account, consumer, target program, receiver, amount, nonce and lane
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(INTENT_DOMAIN.len() + 32 * 5 + 8 * 3);
+        message.extend_from_slice(INTENT_DOMAIN);
+        message.extend_from_slice(&self.domain_separator);
+        message.extend_from_slice(self.gsn_account.as_ref());
+        message.extend_from_slice(self.consumer.as_ref());
+        message.extend_from_slice(self.target_program.as_ref());
+        message.extend_from_slice(self.receiver.as_ref());
+        message.extend_from_slice(&self.amount.to_le_bytes());
+        message.extend_from_slice(&self.nonce.to_le_bytes());
+        message.extend_from_slice(&self.lane.to_le_bytes());
+        message
+    }
+}
+
+/// Fails with `InvalidIntent` unless an Ed25519 program instruction before
+/// `current_index` verified the consumer's signature over `intent`
+pub fn verify_intent(
+    instructions_sysvar: &AccountInfo,
+    current_index: u16,
+    intent: &ConsumerIntent,
+) -> Result<(), GsnError> {
+    match has_ed25519_signature(
+        instructions_sysvar,
+        current_index,
+        &intent.consumer,
+        &intent.message(),
+    ) {
+        Ok(true) => Ok(()),
+        _ => Err(GsnError::InvalidIntent),
+    }
+}
@@ -1,12 +1,24 @@
+pub mod credential;
 pub mod error;
+pub mod eth;
+pub mod event;
+pub mod intent;
 pub mod instruction;
+pub mod log;
+pub mod oracle;
 pub mod processor;
+pub mod query;
+pub mod quote;
+pub mod sorted_map;
 pub mod state;
+pub mod swap;
+pub mod token;
+pub mod validation;
 
 // Re-export for tests
 pub use processor::Processor;
 pub use state::GsnInfo;
 pub use error::GsnError;
 
-#[cfg(not(feature = "exclude_entrypoint_deprecated"))]
+#[cfg(not(feature = "exclude_entrypoint"))]
 pub mod entrypoint;
@@ -0,0 +1,98 @@
+//! Structured event logging.
+//!
+//! Events are printed as `[TAG] key=value ...` lines by default. Building with
+//! the `json-logs` feature emits them as single-line JSON objects instead,
+//! tagged with [`EVENT_SCHEMA`] so log pipelines can parse them without
+//! custom grok rules.
+
+use solana_program::msg;
+use std::fmt::{Display, Write};
+
+/// Version of the JSON event layout, bumped on incompatible changes.
+pub const EVENT_SCHEMA: &str = "solgsn.event.v1";
+
+/// Emit an event with the given tag and fields, e.g.
+/// `gsn_event!("TOPUP", consumer = key, amount = amount)`.
+#[macro_export]
+macro_rules! gsn_event {
+    ($tag:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::log::emit(
+            $tag,
+            &[$((stringify!($key), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+
+/// Log an event in the format selected at build time.
+pub fn emit(tag: &str, fields: &[(&str, &dyn Display)]) {
+    msg!("{}", format_event(tag, fields));
+}
+
+/// Render an event in the format selected at build time.
+pub fn format_event(tag: &str, fields: &[(&str, &dyn Display)]) -> String {
+    if cfg!(feature = "json-logs") {
+        format_json(tag, fields)
+    } else {
+        format_key_value(tag, fields)
+    }
+}
+
+/// `[TAG] key=value ...`
+pub fn format_key_value(tag: &str, fields: &[(&str, &dyn Display)]) -> String {
+    let mut line = format!("[{}]", tag);
+    for (key, value) in fields {
+        let _ = write!(line, " {}={}", key, value);
+    }
+    line
+}
+
+/// `{"schema":"solgsn.event.v1","event":"TAG","key":value,...}`
+///
+/// Unsigned integers are written as JSON numbers, everything else as strings.
+pub fn format_json(tag: &str, fields: &[(&str, &dyn Display)]) -> String {
+    let mut line = String::from("{");
+    push_json_string(&mut line, "schema");
+    line.push(':');
+    push_json_string(&mut line, EVENT_SCHEMA);
+    line.push(',');
+    push_json_string(&mut line, "event");
+    line.push(':');
+    push_json_string(&mut line, tag);
+    for (key, value) in fields {
+        let value = value.to_string();
+        line.push(',');
+        push_json_string(&mut line, key);
+        line.push(':');
+        if is_json_number(&value) {
+            line.push_str(&value);
+        } else {
+            push_json_string(&mut line, &value);
+        }
+    }
+    line.push('}');
+    line
+}
+
+fn is_json_number(value: &str) -> bool {
+    !value.is_empty()
+        && value.bytes().all(|b| b.is_ascii_digit())
+        && (value == "0" || !value.starts_with('0'))
+}
+
+fn push_json_string(line: &mut String, value: &str) {
+    line.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(line, "\\u{:04x}", c as u32);
+            }
+            c => line.push(c),
+        }
+    }
+    line.push('"');
+}
@@ -0,0 +1,168 @@
+//! Oracle price accounts, read by hand so the program doesn't depend on the
+//! oracle vendors' crates.
+//!
+//! Mints with a [`PriceFeed`] in the config pay token fees at the oracle's
+//! price: the lamport fee is converted into the mint's base units with the
+//! feed's price of the token in SOL. The feed's [`OracleKind`] picks the
+//! adapter decoding its account into an [`OraclePrice`]:
+//! - Pyth: the header and the aggregate price of the v2 price account.
+//! - Switchboard: the latest confirmed round of a v2 aggregator account.
+
+use crate::{
+    error::GsnError,
+    state::{OracleKind, PriceFeed},
+};
+use solana_program::{account_info::AccountInfo, native_token::LAMPORTS_PER_SOL};
+use std::convert::{TryFrom, TryInto};
+
+/// Magic number every Pyth account starts with
+const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+
+/// Version of the account layout read here
+const PYTH_VERSION: u32 = 2;
+
+/// Account type of price accounts
+const PYTH_PRICE_ACCOUNT: u32 = 3;
+
+/// Status of an aggregate price that is currently trading
+const PYTH_TRADING: u32 = 1;
+
+/// Offset of the price's exponent
+const PYTH_EXPO_OFFSET: usize = 20;
+
+/// Offset of the aggregate price: `price: i64`, `conf: u64`, `status: u32`,
+/// `corp_act: u32`, `pub_slot: u64`
+const PYTH_AGGREGATE_OFFSET: usize = 208;
+
+/// Length the Pyth fields read here span
+const PYTH_MIN_LEN: usize = PYTH_AGGREGATE_OFFSET + 32;
+
+/// Anchor discriminator of Switchboard's `AggregatorAccountData`
+const SWITCHBOARD_AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+/// Offset of the aggregator's `latest_confirmed_round`, after the
+/// discriminator, `name`, `metadata`, reserved bytes, `queue_pubkey`, the
+/// batch and result settings, `variance_threshold`, the update timing,
+/// `is_locked` and `crank_pubkey`
+const SWITCHBOARD_ROUND_OFFSET: usize = 341;
+
+/// Offsets in the round of `round_open_slot: u64`, then the `result` and
+/// `std_deviation` decimals (`mantissa: i128`, `scale: u32`)
+const SWITCHBOARD_SLOT_OFFSET: usize = SWITCHBOARD_ROUND_OFFSET + 9;
+const SWITCHBOARD_RESULT_OFFSET: usize = SWITCHBOARD_ROUND_OFFSET + 25;
+const SWITCHBOARD_DEVIATION_OFFSET: usize = SWITCHBOARD_RESULT_OFFSET + 20;
+
+/// Length the Switchboard fields read here span
+const SWITCHBOARD_MIN_LEN: usize = SWITCHBOARD_DEVIATION_OFFSET + 20;
+
+/// Price of a token in SOL, as published by an oracle
+#[derive(Clone, Debug, PartialEq)]
+pub struct OraclePrice {
+    /// SOL per token is `price * 10^expo`
+    pub price: u64,
+    /// Confidence interval, in the units of `price`
+    pub conf: u64,
+    pub expo: i32,
+    /// Slot the price was published in
+    pub publish_slot: u64,
+}
+
+/// Decode `data` as the price account of an oracle of `kind`
+pub fn unpack_price(kind: OracleKind, data: &[u8]) -> Option<OraclePrice> {
+    match kind {
+        OracleKind::Pyth => unpack_pyth_price(data),
+        OracleKind::Switchboard => unpack_switchboard_price(data),
+    }
+}
+
+/// Decode `data` as a Pyth price account whose aggregate price is trading
+/// and positive
+pub fn unpack_pyth_price(data: &[u8]) -> Option<OraclePrice> {
+    if data.len() < PYTH_MIN_LEN
+        || read_u32(data, 0)? != PYTH_MAGIC
+        || read_u32(data, 4)? != PYTH_VERSION
+        || read_u32(data, 8)? != PYTH_PRICE_ACCOUNT
+        || read_u32(data, PYTH_AGGREGATE_OFFSET + 16)? != PYTH_TRADING
+    {
+        return None;
+    }
+    let price = i64::from_le_bytes(data[PYTH_AGGREGATE_OFFSET..PYTH_AGGREGATE_OFFSET + 8].try_into().ok()?);
+    Some(OraclePrice {
+        price: u64::try_from(price).ok().filter(|price| *price > 0)?,
+        conf: read_u64(data, PYTH_AGGREGATE_OFFSET + 8)?,
+        expo: i32::from_le_bytes(data[PYTH_EXPO_OFFSET..PYTH_EXPO_OFFSET + 4].try_into().ok()?),
+        publish_slot: read_u64(data, PYTH_AGGREGATE_OFFSET + 24)?,
+    })
+}
+
+/// Decode `data` as a Switchboard aggregator account whose latest confirmed
+/// result is positive. The standard deviation of the round's responses
+/// stands for the confidence interval.
+pub fn unpack_switchboard_price(data: &[u8]) -> Option<OraclePrice> {
+    if data.len() < SWITCHBOARD_MIN_LEN || data[..8] != SWITCHBOARD_AGGREGATOR_DISCRIMINATOR {
+        return None;
+    }
+    let (mantissa, scale) = read_decimal(data, SWITCHBOARD_RESULT_OFFSET)?;
+    let (deviation, deviation_scale) = read_decimal(data, SWITCHBOARD_DEVIATION_OFFSET)?;
+    // The deviation in the units of the result
+    let conf = match deviation_scale.checked_sub(scale) {
+        Some(extra) => deviation.checked_div(10i128.checked_pow(extra)?)?,
+        None => deviation.checked_mul(10i128.checked_pow(scale - deviation_scale)?)?,
+    };
+    Some(OraclePrice {
+        price: u64::try_from(mantissa).ok().filter(|price| *price > 0)?,
+        conf: u64::try_from(conf.unsigned_abs()).ok()?,
+        expo: -i32::try_from(scale).ok()?,
+        publish_slot: read_u64(data, SWITCHBOARD_SLOT_OFFSET)?,
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+/// Switchboard decimal at `offset`: `mantissa / 10^scale`
+fn read_decimal(data: &[u8], offset: usize) -> Option<(i128, u32)> {
+    let mantissa = i128::from_le_bytes(data.get(offset..offset + 16)?.try_into().ok()?);
+    Some((mantissa, read_u32(data, offset + 16)?))
+}
+
+/// Price of `feed`'s mint read from `account` at `slot`. Fails with
+/// `InvalidPriceFeed` for another account than the configured one, one the
+/// feed's oracle doesn't decode to a positive price, or a confidence
+/// interval wider than the feed allows, and with `StalePriceFeed` for a
+/// price older than its `max_age_slots`.
+pub fn load_price(account: &AccountInfo, feed: &PriceFeed, slot: u64) -> Result<OraclePrice, GsnError> {
+    if *account.key != feed.feed {
+        return Err(GsnError::InvalidPriceFeed);
+    }
+    let price = unpack_price(feed.kind, &account.data.borrow()).ok_or(GsnError::InvalidPriceFeed)?;
+    if slot.saturating_sub(price.publish_slot) > feed.max_age_slots {
+        return Err(GsnError::StalePriceFeed);
+    }
+    if price.conf as u128 * 10_000 > price.price as u128 * feed.max_confidence_bps as u128 {
+        return Err(GsnError::InvalidPriceFeed);
+    }
+    Ok(price)
+}
+
+impl OraclePrice {
+    /// Base units of a mint with `decimals` worth `lamports`, rounded up so
+    /// the executor isn't paid less than the lamport fee. None when the
+    /// amount doesn't fit in a u64.
+    pub fn lamports_to_tokens(&self, lamports: u64, decimals: u8) -> Option<u64> {
+        // tokens = lamports * 10^decimals / (price * 10^expo * LAMPORTS_PER_SOL)
+        let mut numerator = (lamports as u128).checked_mul(10u128.checked_pow(decimals as u32)?)?;
+        let mut denominator = (self.price as u128).checked_mul(LAMPORTS_PER_SOL as u128)?;
+        if self.expo < 0 {
+            numerator = numerator.checked_mul(10u128.checked_pow(self.expo.unsigned_abs())?)?;
+        } else {
+            denominator = denominator.checked_mul(10u128.checked_pow(self.expo as u32)?)?;
+        }
+        numerator.div_ceil(denominator).try_into().ok()
+    }
+}
@@ -1,7 +1,33 @@
 use crate::{
+    credential::verify_credential,
     error::GsnError,
-    instruction::{GsnInstruction, UpdateFeeParamsArgs, TokenMintArgs},
-    state::{FeeMode, GsnInfo},
+    eth::{eth_signers, registration_message},
+    event::{
+        self, ExecutionEvent, FeeClaimEvent, GiftTopupEvent, GovernanceAction, GovernanceEvent, TopupEvent,
+    },
+    gsn_event,
+    oracle::{self, OraclePrice},
+    instruction::{
+        BlacklistArgs, ClaimTokenFeesAsSolArgs, SetCircuitBreakerArgs, ExecutorArgs, GsnInstruction, ProgramIdArgs, RevealArgs, SetCredentialMintArgs, SetExecutorRegistryArgs, SetFreeTierArgs, SetGenesisHashArgs, SetMultisigArgs, SetPayloadRetentionArgs, SetPriceFeedArgs, SetRateLimitArgs, SetRecordRetentionArgs, SetStatsTrackingArgs, DistributeRewardsArgs, InsuranceFeeArgs, CompensateArgs, ReportFailureArgs, SetWithdrawalCooldownArgs, SetSandboxArgs, SetSpendingLimitArgs, SetVolumeDiscountsArgs, SlashArgs, SubmitMeteredArgs, StakeArgs, ProtocolFeeArgs, RegisterSponsorArgs, SubmitArgs,
+        SubmitBatchArgs,
+        SubmitInstructionArgs, SubmitLaneArgs,
+        SubmitQuotedArgs, SubmitSplitArgs, SubmitTokenFeeArgs, TokenMintArgs, UpdateFeeParamsArgs,
+        InitiateShutdownArgs, QuoteTerms, RegisterEthAddressArgs, WithdrawArgs, FEE_SPLIT_DENOMINATOR, LAMPORTS_PER_SIGNATURE,
+    },
+    intent::{domain_separator, verify_intent, ConsumerIntent},
+    query::{self, ConsumerBalance, DeploymentStats, ExecutorBalance, FeeEstimate},
+    quote::{verify_quote, FeeQuote},
+    state::{
+        FeeEscrow, FeeMode, FreeTier, MAX_COMPUTE_UNITS, GsnConfig, GsnInfo, ProtocolMetrics, SandboxConfig, ShutdownState,
+        MultisigConfig, RateLimit, SpendingLimit, SponsorPolicy, MAX_INSURANCE_FEE_BPS, MAX_PROTOCOL_FEE_BPS, SPONSOR_SEED, STATE_VERSION, SubmissionCommitment,
+        COMMITMENT_SEED, ESCROW_SEED, NATIVE_FEE_TOKEN, STAKE_SEED, UNORDERED_NONCE_LANE, CHANNEL_NONCE_LANE,
+        STAKE_UNBONDING_SLOTS, VAULT_SEED, VolumeDiscountTier, is_valid_nonce_lane, MAX_PAYLOAD_RETENTION,
+        ConsumerAccount, ExecutorAccount, MAX_PRICE_FEEDS, CONSUMER_LEDGER, CONSUMER_LEDGER_SEED, EXECUTOR_LEDGER,
+        EXECUTOR_LEDGER_SEED, ExecutionReceipt, RECEIPT_SEED, WithdrawalRequest,
+    },
+    swap,
+    token::{self, is_token_program, load_mint, load_token_account, Mint, TokenAccount},
+    validation,
 };
 
 use num_traits::FromPrimitive;
@@ -9,444 +35,6210 @@ use solana_program::{
     account_info::next_account_info,
     account_info::AccountInfo,
     decode_error::DecodeError,
-    entrypoint_deprecated::ProgramResult,
-    info,
+    entrypoint::ProgramResult,
+    hash::hashv,
+    instruction::Instruction,
     msg,
-    program::invoke,
+    program::{invoke, invoke_signed},
     program_error::{PrintProgramError, ProgramError},
     pubkey::Pubkey,
     system_instruction,
-    // message::Message,
-    // fee_calculator::FeeCalculator,
+    sysvar::{clock::Clock, instructions::load_current_index_checked, rent::Rent, Sysvar},
 };
+use std::cell::Cell;
 
 pub struct Processor {}
 
+/// A consumer's or executor's ledger account, see `load_state`
+enum LedgerAccount {
+    Consumer(ConsumerAccount),
+    Executor(ExecutorAccount),
+}
+
+/// Signer paying the rent of the state account's growth, see `store_state_growing`
+struct StateGrowth<'a, 'b> {
+    payer: &'a AccountInfo<'b>,
+    system_program: &'a AccountInfo<'b>,
+}
+
+/// Accounts and configuration shared by the submissions of an instruction
+struct SubmitContext<'a, 'b> {
+    program_id: Pubkey,
+    /// The GSN state account, written before every CPI and read again after
+    gsn_account: &'a AccountInfo<'b>,
+    /// Accounts the state's ledger accounts are loaded from
+    ledger_accounts: &'a [AccountInfo<'b>],
+    target_program: &'a AccountInfo<'b>,
+    fee_payer: &'a AccountInfo<'b>,
+    config: GsnConfig,
+    metrics: Option<&'a AccountInfo<'b>>,
+    /// The executor pays for the state's growth when the system program is
+    /// among the accounts
+    growth: Option<StateGrowth<'a, 'b>>,
+    /// Run every check and return the fee without executing or writing
+    /// anything, see `SubmitArgs::simulate`
+    simulate: bool,
+}
+
+/// One relayed transfer, or instruction
+struct Submission<'a, 'b> {
+    sender: &'a AccountInfo<'b>,
+    /// Receiver of the transfer, the target program of a relayed instruction
+    receiver: &'a AccountInfo<'b>,
+    amount: u64,
+    nonce: u64,
+    lane: u64,
+    /// Part of a batch's network fee charged on top of the GSN fee
+    network_fee_share: u64,
+    /// Sender's credential, passed when compliance mode is on
+    credential: Option<&'a AccountInfo<'b>>,
+    /// Fee the executor quoted, charged instead of the configured fee
+    quoted_fee: Option<u64>,
+    /// Mint of the token balance the fee is charged to, lamports when `None`
+    fee_mint: Option<Pubkey>,
+    /// Oracle price of `fee_mint` and the mint's decimals, converting the
+    /// lamport fee into its base units
+    fee_price: Option<(OraclePrice, u8)>,
+    /// Instruction invoked instead of transferring `amount` to `receiver`
+    call: Option<TargetCall<'a, 'b>>,
+    /// Policy of the sponsor charged the fee instead of the sender
+    sponsor: Option<&'a SponsorPolicy>,
+    /// Compute units the relayer measured, priced by a `ComputeBased` fee
+    compute_units: Option<u64>,
+    /// Highest fee the consumer signed for
+    max_fee: Option<u64>,
+    /// Lamports the consumer pays the executor on top of the fee
+    tip: u64,
+    funding: Funding<'a, 'b>,
+    settlement: Settlement<'a, 'b>,
+}
+
+/// Instruction of the target program relayed by `SubmitInstruction`
+struct TargetCall<'a, 'b> {
+    instruction: &'a Instruction,
+    /// Accounts the instruction's metas refer to, and the target program
+    accounts: &'a [AccountInfo<'b>],
+}
+
+/// Where the transferred lamports come from
+enum Funding<'a, 'b> {
+    /// The sender's wallet, which signs the transaction
+    Sender,
+    /// The sender's top-up balance, paid out of the vault
+    Balance {
+        program_id: &'a Pubkey,
+        gsn_account: &'a AccountInfo<'b>,
+        vault: &'a AccountInfo<'b>,
+    },
+}
+
+/// Where the fee of a submission goes
+enum Settlement<'a, 'b> {
+    /// Credited to the executor's running balance, claimed with `ClaimFees`
+    Balance,
+    /// Moved from the vault into the submission's `FeeEscrow`
+    Escrow {
+        program_id: &'a Pubkey,
+        gsn_account: &'a AccountInfo<'b>,
+        escrow: &'a AccountInfo<'b>,
+        vault: &'a AccountInfo<'b>,
+        system_program: &'a AccountInfo<'b>,
+    },
+    /// Credited to the executor's and a co-executor's running balances
+    Split {
+        co_executor: &'a AccountInfo<'b>,
+        split: &'a SubmitSplitArgs,
+    },
+    /// Added to the batch's total, credited to the executor once every entry ran
+    Batched { fees: &'a Cell<u64> },
+}
+
 impl Processor {
-    pub fn process(accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = GsnInstruction::deserialize(input)?;
         match instruction {
-            GsnInstruction::Initialize => Self::process_initialize(accounts),
+            GsnInstruction::Initialize => Self::process_initialize(program_id, accounts),
             GsnInstruction::Topup(args) => {
-                info!("Instruction: TopUp");
-                Self::process_topup(args.amount, accounts)
+                msg!("Instruction: TopUp");
+                Self::process_topup(program_id, args.amount, args.beneficiary, accounts)
             }
             GsnInstruction::SubmitTransaction(args) => {
-                info!("Instruction: Submit Transaction");
-                Self::process_submit_tx(args.amount, args.nonce, accounts)
+                msg!("Instruction: Submit Transaction");
+                if args.simulate {
+                    Self::process_simulate_submit(program_id, args, accounts)
+                } else {
+                    Self::process_submit_tx(program_id, args.amount, args.nonce, 0, args.expiry_slot, args.max_fee, args.tip, args.quote, accounts)
+                }
             }
             GsnInstruction::UpdateFeeParams(args) => {
-                info!("Instruction: Update Fee Params");
-                Self::process_update_fee_params(args, accounts)
+                msg!("Instruction: Update Fee Params");
+                Self::process_update_fee_params(program_id, args, accounts)
             }
             GsnInstruction::AddAllowedToken(args) => {
-                info!("Instruction: Add Allowed Token");
-                Self::process_add_allowed_token(args, accounts)
+                msg!("Instruction: Add Allowed Token");
+                Self::process_add_allowed_token(program_id, args, accounts)
             }
             GsnInstruction::RemoveAllowedToken(args) => {
-                info!("Instruction: Remove Allowed Token");
-                Self::process_remove_allowed_token(args, accounts)
+                msg!("Instruction: Remove Allowed Token");
+                Self::process_remove_allowed_token(program_id, args, accounts)
+            }
+            GsnInstruction::ClaimFees => {
+                msg!("Instruction: Claim Fees");
+                Self::process_claim_fees(program_id, accounts)
+            }
+            GsnInstruction::InitializeMetrics => {
+                msg!("Instruction: Initialize Metrics");
+                Self::process_initialize_metrics(program_id, accounts)
+            }
+            GsnInstruction::SubmitTransactionInLane(args) => {
+                msg!("Instruction: Submit Transaction In Lane");
+                Self::process_submit_tx(program_id, args.amount, args.nonce, args.lane, args.expiry_slot, args.max_fee, args.tip, None, accounts)
+            }
+            GsnInstruction::SubmitBatch(args) => {
+                msg!("Instruction: Submit Batch");
+                Self::process_submit_batch(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitEscrowed(args) => {
+                msg!("Instruction: Submit Escrowed");
+                Self::process_submit_escrowed(program_id, args.amount, args.nonce, args.lane, accounts)
+            }
+            GsnInstruction::ReleaseEscrow => {
+                msg!("Instruction: Release Escrow");
+                Self::process_release_escrow(program_id, accounts)
+            }
+            GsnInstruction::DisputeEscrow => {
+                msg!("Instruction: Dispute Escrow");
+                Self::process_dispute_escrow(program_id, accounts)
+            }
+            GsnInstruction::CommitSubmission(args) => {
+                msg!("Instruction: Commit Submission");
+                Self::process_commit_submission(program_id, args.hash, accounts)
+            }
+            GsnInstruction::RevealSubmission(args) => {
+                msg!("Instruction: Reveal Submission");
+                Self::process_reveal_submission(program_id, args, accounts)
+            }
+            GsnInstruction::SetCredentialMint(args) => {
+                msg!("Instruction: Set Credential Mint");
+                Self::process_set_credential_mint(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitSplit(args) => {
+                msg!("Instruction: Submit Split");
+                Self::process_submit_split(program_id, args, accounts)
+            }
+            GsnInstruction::SetSandbox(args) => {
+                msg!("Instruction: Set Sandbox");
+                Self::process_set_sandbox(program_id, args, accounts)
+            }
+            GsnInstruction::ResetSandbox => {
+                msg!("Instruction: Reset Sandbox");
+                Self::process_reset_sandbox(program_id, accounts)
+            }
+            GsnInstruction::SubmitQuoted(args) => {
+                msg!("Instruction: Submit Quoted");
+                Self::process_submit_quoted(program_id, args, accounts)
+            }
+            GsnInstruction::InitiateShutdown(args) => {
+                msg!("Instruction: Initiate Shutdown");
+                Self::process_initiate_shutdown(program_id, args, accounts)
+            }
+            GsnInstruction::Withdraw(args) => {
+                msg!("Instruction: Withdraw");
+                Self::process_withdraw(program_id, args, accounts)
+            }
+            GsnInstruction::Sweep => {
+                msg!("Instruction: Sweep");
+                Self::process_sweep(program_id, accounts)
+            }
+            GsnInstruction::TopupToken(args) => {
+                msg!("Instruction: TopUp Token");
+                Self::process_topup_token(program_id, args.amount, args.beneficiary, accounts)
+            }
+            GsnInstruction::SubmitTokenFee(args) => {
+                msg!("Instruction: Submit Token Fee");
+                Self::process_submit_token_fee(program_id, args, accounts)
+            }
+            GsnInstruction::ClaimTokenFees => {
+                msg!("Instruction: Claim Token Fees");
+                Self::process_claim_token_fees(program_id, accounts)
+            }
+            GsnInstruction::ClaimWrappedSol => {
+                msg!("Instruction: Claim Wrapped SOL");
+                Self::process_claim_wrapped_sol(program_id, accounts)
+            }
+            GsnInstruction::ClaimTokenFeesAsSol(args) => {
+                msg!("Instruction: Claim Token Fees As SOL");
+                Self::process_claim_token_fees_as_sol(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitIntent(args) => {
+                msg!("Instruction: Submit Intent");
+                Self::process_submit_intent(program_id, args, accounts)
+            }
+            GsnInstruction::RegisterEthAddress(args) => {
+                msg!("Instruction: Register Eth Address");
+                Self::process_register_eth_address(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitEthIntent(args) => {
+                msg!("Instruction: Submit Eth Intent");
+                Self::process_submit_eth_intent(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitInstruction(args) => {
+                msg!("Instruction: Submit Instruction");
+                Self::process_submit_instruction(program_id, args, accounts)
+            }
+            GsnInstruction::SetSpendingLimit(args) => {
+                msg!("Instruction: Set Spending Limit");
+                Self::process_set_spending_limit(program_id, args, accounts)
+            }
+            GsnInstruction::AddAllowedProgram(args) => {
+                msg!("Instruction: Add Allowed Program");
+                Self::process_add_allowed_program(program_id, args, accounts)
+            }
+            GsnInstruction::RemoveAllowedProgram(args) => {
+                msg!("Instruction: Remove Allowed Program");
+                Self::process_remove_allowed_program(program_id, args, accounts)
+            }
+            GsnInstruction::Pause => {
+                msg!("Instruction: Pause");
+                Self::process_set_paused(program_id, true, accounts)
+            }
+            GsnInstruction::Unpause => {
+                msg!("Instruction: Unpause");
+                Self::process_set_paused(program_id, false, accounts)
+            }
+            GsnInstruction::SetMultisig(args) => {
+                msg!("Instruction: Set Multisig");
+                Self::process_set_multisig(program_id, args, accounts)
+            }
+            GsnInstruction::ProposeFeeParams(args) => {
+                msg!("Instruction: Propose Fee Params");
+                Self::process_propose_fee_params(program_id, args, accounts)
+            }
+            GsnInstruction::ApplyFeeParams => {
+                msg!("Instruction: Apply Fee Params");
+                Self::process_apply_fee_params(program_id, accounts)
+            }
+            GsnInstruction::RegisterExecutor(args) => {
+                msg!("Instruction: Register Executor");
+                Self::process_set_executor_registered(program_id, args, true, accounts)
+            }
+            GsnInstruction::DeregisterExecutor(args) => {
+                msg!("Instruction: Deregister Executor");
+                Self::process_set_executor_registered(program_id, args, false, accounts)
+            }
+            GsnInstruction::SetExecutorRegistry(args) => {
+                msg!("Instruction: Set Executor Registry");
+                Self::process_set_executor_registry(program_id, args, accounts)
+            }
+            GsnInstruction::StakeExecutor(args) => {
+                msg!("Instruction: Stake Executor");
+                Self::process_stake_executor(program_id, args, accounts)
+            }
+            GsnInstruction::UnstakeExecutor => {
+                msg!("Instruction: Unstake Executor");
+                Self::process_unstake_executor(program_id, accounts)
+            }
+            GsnInstruction::SlashExecutor(args) => {
+                msg!("Instruction: Slash Executor");
+                Self::process_slash_executor(program_id, args, accounts)
+            }
+            GsnInstruction::SetMinExecutorStake(args) => {
+                msg!("Instruction: Set Min Executor Stake");
+                Self::process_set_min_executor_stake(program_id, args, accounts)
+            }
+            GsnInstruction::SetProtocolFee(args) => {
+                msg!("Instruction: Set Protocol Fee");
+                Self::process_set_protocol_fee(program_id, args, accounts)
+            }
+            GsnInstruction::WithdrawTreasury(args) => {
+                msg!("Instruction: Withdraw Treasury");
+                Self::process_withdraw_treasury(program_id, args, accounts)
+            }
+            GsnInstruction::RegisterSponsor(args) => {
+                msg!("Instruction: Register Sponsor");
+                Self::process_register_sponsor(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitSponsored(args) => {
+                msg!("Instruction: Submit Sponsored");
+                Self::process_submit_sponsored(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitSponsoredInstruction(args) => {
+                msg!("Instruction: Submit Sponsored Instruction");
+                Self::submit_instruction(program_id, args, accounts, true)
+            }
+            GsnInstruction::SetFreeTier(args) => {
+                msg!("Instruction: Set Free Tier");
+                Self::process_set_free_tier(program_id, args, accounts)
+            }
+            GsnInstruction::SubmitMetered(args) => {
+                msg!("Instruction: Submit Metered");
+                Self::process_submit_metered(program_id, args, accounts)
+            }
+            GsnInstruction::SetVolumeDiscounts(args) => {
+                msg!("Instruction: Set Volume Discounts");
+                Self::process_set_volume_discounts(program_id, args, accounts)
+            }
+            GsnInstruction::SetPayoutAddress => {
+                msg!("Instruction: Set Payout Address");
+                Self::process_set_payout_address(program_id, accounts)
+            }
+            GsnInstruction::CancelNonce(args) => {
+                msg!("Instruction: Cancel Nonce");
+                Self::process_cancel_nonce(program_id, args.nonce, args.lane, accounts)
+            }
+            GsnInstruction::SetPayloadRetention(args) => {
+                msg!("Instruction: Set Payload Retention");
+                Self::process_set_payload_retention(program_id, args, accounts)
+            }
+            GsnInstruction::SetGenesisHash(args) => {
+                msg!("Instruction: Set Genesis Hash");
+                Self::process_set_genesis_hash(program_id, args, accounts)
+            }
+            GsnInstruction::OpenConsumerAccount => {
+                msg!("Instruction: Open Consumer Account");
+                Self::process_open_consumer_account(program_id, accounts)
+            }
+            GsnInstruction::OpenExecutorAccount => {
+                msg!("Instruction: Open Executor Account");
+                Self::process_open_executor_account(program_id, accounts)
+            }
+            GsnInstruction::MigrateState => {
+                msg!("Instruction: Migrate State");
+                Self::process_migrate_state(program_id, accounts)
+            }
+            GsnInstruction::GetConsumerBalance(args) => {
+                msg!("Instruction: Get Consumer Balance");
+                let consumer = Pubkey::new_from_array(args.account);
+                Self::process_get_consumer_balance(program_id, &consumer, accounts)
+            }
+            GsnInstruction::GetExecutorBalance(args) => {
+                msg!("Instruction: Get Executor Balance");
+                let executor = Pubkey::new_from_array(args.account);
+                Self::process_get_executor_balance(program_id, &executor, accounts)
+            }
+            GsnInstruction::GetFeeQuote(args) => {
+                msg!("Instruction: Get Fee Quote");
+                Self::process_get_fee_quote(program_id, &args.consumer, args.amount, accounts)
+            }
+            GsnInstruction::SetRecordRetention(args) => {
+                msg!("Instruction: Set Record Retention");
+                Self::process_set_record_retention(program_id, args, accounts)
+            }
+            GsnInstruction::PruneRecords => {
+                msg!("Instruction: Prune Records");
+                Self::process_prune_records(program_id, accounts)
+            }
+            GsnInstruction::CloseConsumer => {
+                msg!("Instruction: Close Consumer");
+                Self::process_close_consumer(program_id, accounts)
+            }
+            GsnInstruction::SetWithdrawalCooldown(args) => {
+                msg!("Instruction: Set Withdrawal Cooldown");
+                Self::process_set_withdrawal_cooldown(program_id, args, accounts)
+            }
+            GsnInstruction::RequestWithdrawal(args) => {
+                msg!("Instruction: Request Withdrawal");
+                Self::process_request_withdrawal(program_id, args, accounts)
+            }
+            GsnInstruction::EnableEmergencyWithdrawals => {
+                msg!("Instruction: Enable Emergency Withdrawals");
+                Self::process_enable_emergency_withdrawals(program_id, accounts)
+            }
+            GsnInstruction::SetPriceFeed(args) => {
+                msg!("Instruction: Set Price Feed");
+                Self::process_set_price_feed(program_id, args, accounts)
+            }
+            GsnInstruction::SetRateLimit(args) => {
+                msg!("Instruction: Set Rate Limit");
+                Self::process_set_rate_limit(program_id, args, accounts)
+            }
+            GsnInstruction::BlacklistAddress(args) => {
+                msg!("Instruction: Blacklist Address");
+                Self::process_set_blacklisted(program_id, args, true, accounts)
+            }
+            GsnInstruction::UnblacklistAddress(args) => {
+                msg!("Instruction: Unblacklist Address");
+                Self::process_set_blacklisted(program_id, args, false, accounts)
+            }
+            GsnInstruction::SetCircuitBreaker(args) => {
+                msg!("Instruction: Set Circuit Breaker");
+                Self::process_set_circuit_breaker(program_id, args, accounts)
+            }
+            GsnInstruction::SetStatsTracking(args) => {
+                msg!("Instruction: Set Stats Tracking");
+                Self::process_set_stats_tracking(program_id, args, accounts)
+            }
+            GsnInstruction::GetStats => {
+                msg!("Instruction: Get Stats");
+                Self::process_get_stats(program_id, accounts)
+            }
+            GsnInstruction::DistributeRewards(args) => {
+                msg!("Instruction: Distribute Rewards");
+                Self::process_distribute_rewards(program_id, args, accounts)
+            }
+            GsnInstruction::SetInsuranceFee(args) => {
+                msg!("Instruction: Set Insurance Fee");
+                Self::process_set_insurance_fee(program_id, args, accounts)
+            }
+            GsnInstruction::Compensate(args) => {
+                msg!("Instruction: Compensate");
+                Self::process_compensate(program_id, args, accounts)
+            }
+            GsnInstruction::ReportFailure(args) => {
+                msg!("Instruction: Report Failure");
+                Self::process_report_failure(program_id, args, accounts)
+            }
+        }
+    }
+
+    pub fn process_initialize(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        // Optional: authority account for governance (if provided)
+        let authority_info = next_account_info(account_info_iter).ok();
+
+        validation::check_program_account(program_id, gsn_program_info)?;
+        validation::check_program_account(program_id, config_info)?;
+        // The state starts with its version, 0 until it is initialized; a
+        // config is written whole, so any data means it is in use
+        if gsn_program_info.data.borrow().first().is_some_and(|version| *version != 0)
+            || config_info.data.borrow().iter().any(|byte| *byte != 0)
+        {
+            return Err(GsnError::AlreadyInUse.into());
+        }
+        let rent = Rent::get()?;
+        for account_info in [gsn_program_info, config_info] {
+            if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
+                return Err(ProgramError::AccountNotRentExempt);
+            }
+        }
+
+        let mut gsn = GsnInfo::new();
+        gsn.config = *config_info.key;
+        let mut config = GsnConfig::new(*gsn_program_info.key);
+        
+        // If authority is provided, initialize governance
+        if let Some(auth) = authority_info {
+            if !auth.is_signer {
+                return Err(GsnError::Unauthorized.into());
+            }
+            config.initialize_governance(*auth.key);
+        }
+
+        config.serialize(&mut config_info.data.borrow_mut())?;
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Move `amount` lamports from the payer into the vault and credit them
+    /// to its balance, or to the balance of `beneficiary`
+    pub fn process_topup(
+        program_id: &Pubkey,
+        amount: u64,
+        beneficiary: Option<Pubkey>,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        validation::check_system_program(system_program_info)?;
+        let credited = beneficiary.unwrap_or(*consumer_info.key);
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        // Blacklisted consumers can't fund submissions, their own or others'
+        gsn.check_not_blacklisted(consumer_info.key)?;
+        gsn.check_not_blacklisted(&credited)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if config.shutdown.is_some() {
+            return Err(GsnError::ShutDown.into());
+        }
+        if let Some(mint) = config.credential_mint {
+            let credential_info = next_account_info(account_info_iter)
+                .map_err(|_| GsnError::MissingCredential)?;
+            verify_credential(credential_info, &mint, &credited)?;
+        }
+        // The payer pays for the room the credited entry takes
+        let growth =
+            Self::state_growth(&mut gsn, gsn_program_info, consumer_info, Some(system_program_info));
+        gsn.check_topup_capacity(&credited)?;
+        Self::check_vault(program_id, gsn_program_info, vault_info)?;
+
+        invoke(
+            &system_instruction::transfer(consumer_info.key, vault_info.key, amount),
+            &[
+                consumer_info.clone(),
+                vault_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+
+        let previous_balance = gsn.consumer.get(&credited).copied().unwrap_or(0);
+        let new_balance = previous_balance
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        gsn.consumer_mut().insert(credited, new_balance);
+
+        Self::emit_topup(consumer_info.key, &credited, None, amount, previous_balance, new_balance);
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, growth.as_ref())
+    }
+
+    /// Like `process_topup`, moving `amount` tokens of an allowed mint from
+    /// the payer's token account into the vault's and crediting the balance
+    /// in that mint, which `SubmitTokenFee` charges fees to
+    pub fn process_topup_token(
+        program_id: &Pubkey,
+        amount: u64,
+        beneficiary: Option<Pubkey>,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let consumer_token_info = next_account_info(account_info_iter)?;
+        let vault_token_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let credited = beneficiary.unwrap_or(*consumer_info.key);
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        // Blacklisted consumers can't fund submissions, their own or others'
+        gsn.check_not_blacklisted(consumer_info.key)?;
+        gsn.check_not_blacklisted(&credited)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if config.shutdown.is_some() {
+            return Err(GsnError::ShutDown.into());
+        }
+        if let Some(mint) = config.credential_mint {
+            let credential_info = next_account_info(account_info_iter)
+                .map_err(|_| GsnError::MissingCredential)?;
+            verify_credential(credential_info, &mint, &credited)?;
+        }
+        Self::check_vault(program_id, gsn_program_info, vault_info)?;
+
+        let mint = Self::check_token_account(
+            consumer_token_info,
+            token_program_info,
+            consumer_info.key,
+            None,
+        )?
+        .mint;
+        Self::check_fee_mint(&config, &mint)?;
+        gsn.check_token_topup_capacity(&credited, &mint)?;
+
+        // Token-2022 mints with a transfer fee are only moved by a checked
+        // transfer, which reads the mint
+        let mint_info = next_account_info(account_info_iter).ok();
+        let mint_account = mint_info
+            .map(|mint_info| Self::check_mint(mint_info, token_program_info, &mint))
+            .transpose()?;
+        match mint_info {
+            Some(mint_info) if vault_token_info.data_is_empty() => Self::create_vault_token_account(
+                vault_token_info,
+                vault_info,
+                mint_info,
+                token_program_info,
+                consumer_info,
+                account_info_iter,
+            )?,
+            _ => {
+                Self::check_token_account(
+                    vault_token_info,
+                    token_program_info,
+                    vault_info.key,
+                    Some(&mint),
+                )
+                .map_err(|error| {
+                    if vault_token_info.data_is_empty() {
+                        GsnError::VaultTokenAccountMissing.into()
+                    } else {
+                        error
+                    }
+                })?;
+            }
+        }
+
+        let mut transfer_accounts = vec![
+            consumer_token_info.clone(),
+            vault_token_info.clone(),
+            consumer_info.clone(),
+            token_program_info.clone(),
+        ];
+        transfer_accounts.extend(mint_info.cloned());
+        invoke(
+            &Self::token_transfer(
+                token_program_info,
+                consumer_token_info,
+                vault_token_info,
+                consumer_info,
+                mint_info.zip(mint_account.as_ref()),
+                amount,
+            ),
+            &transfer_accounts,
+        )?;
+        // Only what reaches the vault is credited, the fee stays withheld in
+        // its token account
+        let received = match mint_account.and_then(|mint_account| mint_account.transfer_fees) {
+            Some(fees) => amount - fees.fee(amount, Clock::get()?.epoch),
+            None => amount,
+        };
+
+        let key = (credited, mint);
+        let previous_balance = gsn.token_consumer.get(&key).copied().unwrap_or(0);
+        let new_balance = previous_balance
+            .checked_add(received)
+            .ok_or(ProgramError::InvalidArgument)?;
+        gsn.token_consumer_mut().insert(key, new_balance);
+
+        Self::emit_topup(consumer_info.key, &credited, Some(mint), received, previous_balance, new_balance);
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Emit the top-up of `credited`, as a gift when the payer funded another
+    /// consumer's balance
+    fn emit_topup(
+        payer: &Pubkey,
+        credited: &Pubkey,
+        mint: Option<Pubkey>,
+        amount: u64,
+        previous_balance: u64,
+        new_balance: u64,
+    ) {
+        if payer == credited {
+            event::emit(TopupEvent {
+                consumer: *credited,
+                mint,
+                amount,
+                previous_balance,
+                new_balance,
+            });
+        } else {
+            event::emit(GiftTopupEvent {
+                payer: *payer,
+                beneficiary: *credited,
+                mint,
+                amount,
+                previous_balance,
+                new_balance,
+            });
+        }
+    }
+
+    /// Checks run cheapest first so rejected submissions, whose fees the
+    /// executor pays, fail before the state is deserialized or any CPI is made:
+    /// instruction arguments, then signers and account keys, then state lookups.
+    /// A fee above the consumer's `max_fee` fails with `FeeTooHigh`, so fee
+    /// changes between signing and relaying never overcharge the consumer.
+    /// A `tip` is charged on top of the fee and paid to the executor in full.
+    /// With the submission's `ExecutionReceipt` address among the accounts,
+    /// the executor also writes its receipt.
+    /// With a `quote`, the fee payer's signed quote is verified like
+    /// `SubmitQuoted` verifies it, from the Instructions sysvar following the
+    /// credential, and exactly the quoted fee is charged.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_submit_tx(
+        program_id: &Pubkey,
+        amount: u64,
+        nonce: u64,
+        lane: u64,
+        expiry_slot: Option<u64>,
+        max_fee: Option<u64>,
+        tip: u64,
+        quote: Option<QuoteTerms>,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::submit_tx(program_id, amount, nonce, lane, expiry_slot, None, max_fee, tip, quote, false, accounts)
+    }
+
+    /// `SubmitTransaction` with `simulate` set: every check of
+    /// `process_submit_tx` runs and the fee it would charge is returned as a
+    /// [`FeeEstimate`](query::FeeEstimate) with `set_return_data`, but the
+    /// transfer isn't executed and no account is written. Relayers simulate
+    /// it to preflight a submission.
+    pub fn process_simulate_submit(
+        program_id: &Pubkey,
+        args: SubmitArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::submit_tx(
+            program_id,
+            args.amount,
+            args.nonce,
+            0,
+            args.expiry_slot,
+            None,
+            args.max_fee,
+            args.tip,
+            args.quote,
+            true,
+            accounts,
+        )
+    }
+
+    /// Like `process_submit_tx`, pricing a `ComputeBased` fee by the
+    /// `compute_units` the relayer measured rather than the mode's maximum.
+    /// The consumer signs the measurement along with the transaction.
+    pub fn process_submit_metered(
+        program_id: &Pubkey,
+        args: SubmitMeteredArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::submit_tx(
+            program_id,
+            args.amount,
+            args.nonce,
+            0,
+            None,
+            Some(args.compute_units),
+            None,
+            0,
+            None,
+            false,
+            accounts,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn submit_tx(
+        program_id: &Pubkey,
+        amount: u64,
+        nonce: u64,
+        lane: u64,
+        expiry_slot: Option<u64>,
+        compute_units: Option<u64>,
+        max_fee: Option<u64>,
+        tip: u64,
+        quote: Option<QuoteTerms>,
+        simulate: bool,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        // Only read, so submissions don't contend on it
+        let config_info = next_account_info(account_info_iter)?;
+
+        // The transfer below needs the consumer's signature, fail before paying for the state read
+        if !sender_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        // A delayed or leaked payload must not execute after the consumer stopped expecting it
+        if let Some(expiry_slot) = expiry_slot {
+            let slot = Clock::get()?.slot;
+            if slot > expiry_slot {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "expired",
+                    consumer = sender_info.key.to_string(),
+                    expiry_slot = expiry_slot,
+                    slot = slot,
+                );
+                return Err(GsnError::SubmissionExpired.into());
+            }
+        }
+
+        let (mut gsn, mut context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        context.simulate = simulate;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+        if let Some(quote) = quote {
+            let instructions_info = next_account_info(account_info_iter)?;
+            Self::check_quote(
+                &quote,
+                gsn_program_info,
+                target_program_info,
+                sender_info,
+                reciever_info,
+                fee_payer_info,
+                instructions_info,
+                amount,
+                nonce,
+                lane,
+            )?;
+        }
+
+        let execution = Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount,
+                nonce,
+                lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: quote.map(|quote| quote.fee),
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units,
+                max_fee,
+                tip,
+                funding: Funding::Sender,
+                settlement: Settlement::Balance,
+            },
+        )?;
+        if simulate {
+            return Ok(());
+        }
+        Self::write_receipt(program_id, gsn_program_info, fee_payer_info, accounts, &execution)?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Like `process_submit_tx`, charging the fee to the balance of the sponsor
+    /// whose policy follows the other accounts, if the policy covers it
+    pub fn process_submit_sponsored(
+        program_id: &Pubkey,
+        args: SubmitArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        if !sender_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if let Some(expiry_slot) = args.expiry_slot {
+            let slot = Clock::get()?.slot;
+            if slot > expiry_slot {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "expired",
+                    consumer = sender_info.key.to_string(),
+                    expiry_slot = expiry_slot,
+                    slot = slot,
+                );
+                return Err(GsnError::SubmissionExpired.into());
+            }
+        }
+
+        let (mut gsn, mut context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        context.simulate = args.simulate;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+        let sponsor_info = next_account_info(account_info_iter)?;
+        let sponsor = Self::load_sponsor(program_id, gsn_program_info, sponsor_info)?;
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount: args.amount,
+                nonce: args.nonce,
+                lane: 0,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: Some(&sponsor),
+                compute_units: None,
+                max_fee: args.max_fee,
+                tip: args.tip,
+                funding: Funding::Sender,
+                settlement: Settlement::Balance,
+            },
+        )?;
+        if context.simulate {
+            return Ok(());
+        }
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Like `process_submit_tx`, charging the fee to the sender's balance in
+    /// `args.mint` and crediting it to the executor's earnings in that mint
+    pub fn process_submit_token_fee(
+        program_id: &Pubkey,
+        args: SubmitTokenFeeArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(args.lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        if !sender_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let mint = Pubkey::new_from_array(args.mint);
+        Self::check_fee_mint(&context.config, &mint)?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+        // Mints with an oracle pass its price account last
+        let fee_price = match context.config.price_feeds.get(&mint) {
+            Some(feed) => {
+                let feed_info = next_account_info(account_info_iter).map_err(|_| GsnError::InvalidPriceFeed)?;
+                match oracle::load_price(feed_info, feed, Clock::get()?.slot) {
+                    Ok(price) => Some((price, feed.decimals)),
+                    Err(error) => {
+                        gsn_event!(
+                            "EXECUTION_FAILED",
+                            reason = "price_feed",
+                            consumer = sender_info.key.to_string(),
+                            feed = feed_info.key.to_string(),
+                            error = format!("{:?}", error),
+                        );
+                        return Err(error.into());
+                    }
+                }
+            }
+            None => None,
+        };
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount: args.amount,
+                nonce: args.nonce,
+                lane: args.lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: Some(mint),
+                fee_price,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Balance,
+            },
+        )?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Like `process_submit_tx`, after checking that an Ed25519 instruction of
+    /// the transaction verifies the consumer's signature over the submission,
+    /// so the relayer can't change what the consumer approved
+    pub fn process_submit_intent(
+        program_id: &Pubkey,
+        args: SubmitLaneArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(args.lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        // The transfer below still moves the consumer's lamports
+        if !sender_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+        let instructions_info = next_account_info(account_info_iter)?;
+
+        let intent = ConsumerIntent {
+            domain_separator: domain_separator(program_id, context.config.genesis_hash.as_ref()),
+            gsn_account: *gsn_program_info.key,
+            consumer: *sender_info.key,
+            target_program: *target_program_info.key,
+            receiver: *reciever_info.key,
+            amount: args.amount,
+            nonce: args.nonce,
+            lane: args.lane,
+        };
+        let current_index = load_current_index_checked(instructions_info)?;
+        if let Err(error) = verify_intent(instructions_info, current_index, &intent) {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "invalid_intent",
+                consumer = sender_info.key.to_string(),
+            );
+            return Err(error.into());
+        }
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount: args.amount,
+                nonce: args.nonce,
+                lane: args.lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Balance,
+            },
+        )?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Registers the Ethereum address whose signature a Secp256k1 instruction
+    /// of the transaction verifies over the registration message, so it can
+    /// sign intents for the consumer. A registered address can be moved to
+    /// another consumer by registering it again. The message carries the
+    /// address's registration nonce, so an old registration can't be
+    /// replayed; fails with `StaleEthRegistration` for any other nonce.
+    pub fn process_register_eth_address(
+        program_id: &Pubkey,
+        args: RegisterEthAddressArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+        let instructions_info = next_account_info(account_info_iter)?;
+
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let message = registration_message(gsn_program_info.key, consumer_info.key, args.nonce);
+        let current_index = load_current_index_checked(instructions_info)?;
+        let eth_address = *eth_signers(instructions_info, current_index, &message)?
+            .first()
+            .ok_or(GsnError::InvalidEthSignature)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if gsn.eth_registration_nonce(&eth_address) != args.nonce {
+            return Err(GsnError::StaleEthRegistration.into());
+        }
+        gsn.check_eth_registration_capacity(&eth_address)?;
+        gsn.register_eth_address(eth_address, *consumer_info.key);
+
+        gsn_event!(
+            "ETH_ADDRESS_REGISTERED",
+            consumer = consumer_info.key.to_string(),
+            eth_address = eth_address
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>(),
+        );
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Like `process_submit_intent` for an intent an Ethereum address
+    /// registered to the sender signed with `personal_sign`. The sender
+    /// doesn't sign the transaction, so the transfer comes out of its
+    /// balance in the vault, along with the fee.
+    pub fn process_submit_eth_intent(
+        program_id: &Pubkey,
+        args: SubmitLaneArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(args.lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        if !fee_payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let instructions_info = next_account_info(account_info_iter)?;
+
+        let intent = ConsumerIntent {
+            domain_separator: domain_separator(program_id, context.config.genesis_hash.as_ref()),
+            gsn_account: *gsn_program_info.key,
+            consumer: *sender_info.key,
+            target_program: *target_program_info.key,
+            receiver: *reciever_info.key,
+            amount: args.amount,
+            nonce: args.nonce,
+            lane: args.lane,
+        };
+        let current_index = load_current_index_checked(instructions_info)?;
+        let signers = eth_signers(instructions_info, current_index, &intent.message())?;
+        if !signers
+            .iter()
+            .any(|address| gsn.eth_consumers.get(address) == Some(sender_info.key))
+        {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "invalid_eth_signature",
+                consumer = sender_info.key.to_string(),
+            );
+            return Err(GsnError::InvalidEthSignature.into());
+        }
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount: args.amount,
+                nonce: args.nonce,
+                lane: args.lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Balance {
+                    program_id,
+                    gsn_account: gsn_program_info,
+                    vault: vault_info,
+                },
+                settlement: Settlement::Balance,
+            },
+        )?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Relays an arbitrary instruction of the target program for the sender,
+    /// charging the fee like `process_submit_tx` charges a transfer of 0
+    /// lamports. The instruction is invoked with the accounts following the
+    /// submission's, so it can only use the signatures of the transaction,
+    /// never the program's. It may not use the fee payer's, which the relayer
+    /// gives without reading the instruction.
+    pub fn process_submit_instruction(
+        program_id: &Pubkey,
+        args: SubmitInstructionArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::submit_instruction(program_id, args, accounts, false)
+    }
+
+    /// `process_submit_instruction`, charging the fee to the sponsor whose
+    /// policy follows the credential when `sponsored`
+    fn submit_instruction(
+        program_id: &Pubkey,
+        args: SubmitInstructionArgs,
+        accounts: &[AccountInfo],
+        sponsored: bool,
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(args.lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        if !sender_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let instruction = &args.instruction;
+        if instruction.program_id != *target_program_info.key
+            || instruction.program_id == *program_id
+            || instruction
+                .accounts
+                .iter()
+                .any(|meta| meta.is_signer && meta.pubkey == *fee_payer_info.key)
+        {
+            return Err(GsnError::InvalidTargetInstruction.into());
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+        let sponsor = if sponsored {
+            let sponsor_info = next_account_info(account_info_iter)?;
+            Some(Self::load_sponsor(program_id, gsn_program_info, sponsor_info)?)
+        } else {
+            None
+        };
+        let mut call_accounts: Vec<AccountInfo> = account_info_iter.cloned().collect();
+        call_accounts.push(target_program_info.clone());
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: target_program_info,
+                amount: 0,
+                nonce: args.nonce,
+                lane: args.lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: Some(TargetCall {
+                    instruction,
+                    accounts: &call_accounts,
+                }),
+                sponsor: sponsor.as_ref(),
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Balance,
+            },
+        )?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Executes the batch's submissions in order against a single state read
+    /// and write. Each consumer is charged the GSN fee of its transfer plus
+    /// its share of the batch's network fee, which is credited to the
+    /// executor with the fee. Any failing submission fails the whole batch.
+    pub fn process_submit_batch(
+        program_id: &Pubkey,
+        args: SubmitBatchArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        // Every submission adds a consumer signature to the fee payer's
+        if args.network_fee > LAMPORTS_PER_SIGNATURE * (args.entries.len() as u64 + 1) {
+            return Err(GsnError::InvalidNetworkFee.into());
+        }
+        if args.entries.iter().any(|entry| !is_valid_nonce_lane(entry.lane)) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        // The network fee is reimbursed to this account, it must be the one that paid
+        if !fee_payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+
+        let fees = Cell::new(0);
+        let mut submissions = Vec::with_capacity(args.entries.len());
+        for (entry, network_fee_share) in args.entries.iter().zip(args.network_fee_shares()) {
+            let sender = next_account_info(account_info_iter)?;
+            let receiver = next_account_info(account_info_iter)?;
+            if !sender.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            let credential = Self::next_credential(&context, account_info_iter)?;
+            submissions.push(Submission {
+                sender,
+                receiver,
+                amount: entry.amount,
+                nonce: entry.nonce,
+                lane: entry.lane,
+                network_fee_share,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Batched { fees: &fees },
+            });
+        }
+
+        gsn_event!(
+            "BATCH",
+            executor = fee_payer_info.key.to_string(),
+            entries = submissions.len(),
+            network_fee = args.network_fee,
+        );
+
+        for submission in &submissions {
+            Self::execute_submission(&mut gsn, &context, submission)?;
+        }
+        Self::credit_executor(&mut gsn, fee_payer_info.key, fees.get());
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Executes a submission like `SubmitTransactionInLane`, but moves its fee
+    /// from the GSN state account into a new `FeeEscrow` instead of crediting
+    /// the executor's running balance. The fee payer funds the escrow's rent.
+    pub fn process_submit_escrowed(
+        program_id: &Pubkey,
+        amount: u64,
+        nonce: u64,
+        lane: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        // The fee payer signs the escrow's account creation
+        if !sender_info.is_signer || !fee_payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+
+        let escrow_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let (escrow_key, _) =
+            FeeEscrow::find_address(program_id, gsn_program_info.key, sender_info.key, lane, nonce);
+        if *escrow_info.key != escrow_key {
+            return Err(GsnError::InvalidEscrowAccount.into());
+        }
+        validation::check_system_program(system_program_info)?;
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount,
+                nonce,
+                lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Escrow {
+                    program_id,
+                    gsn_account: gsn_program_info,
+                    escrow: escrow_info,
+                    vault: vault_info,
+                    system_program: system_program_info,
+                },
+            },
+        )?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Executes a submission like `SubmitTransactionInLane` whose fee is split
+    /// between the fee payer and a co-executor, e.g. the party that built and
+    /// simulated it. Both sign to agree on the declared ratio.
+    pub fn process_submit_split(
+        program_id: &Pubkey,
+        args: SubmitSplitArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(args.lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+        if args.fee_payer_bps > FEE_SPLIT_DENOMINATOR {
+            return Err(GsnError::InvalidFeeSplit.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        if !sender_info.is_signer || !fee_payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+
+        let co_executor_info = next_account_info(account_info_iter)?;
+        if !co_executor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if co_executor_info.key == fee_payer_info.key {
+            return Err(GsnError::InvalidFeeSplit.into());
+        }
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount: args.amount,
+                nonce: args.nonce,
+                lane: args.lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Split {
+                    co_executor: co_executor_info,
+                    split: &args,
+                },
+            },
+        )?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Executes a submission like `SubmitTransactionInLane` at the fee its
+    /// executor quoted. An earlier Ed25519 program instruction of the
+    /// transaction must verify the executor's signature over the quote, which
+    /// the consumer agrees to by signing the transaction.
+    pub fn process_submit_quoted(
+        program_id: &Pubkey,
+        args: SubmitQuotedArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(args.lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        if !sender_info.is_signer || !fee_payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+        let instructions_info = next_account_info(account_info_iter)?;
+        Self::check_quote(
+            &QuoteTerms {
+                fee: args.fee,
+                expiry_slot: args.expiry_slot,
+            },
+            gsn_program_info,
+            target_program_info,
+            sender_info,
+            reciever_info,
+            fee_payer_info,
+            instructions_info,
+            args.amount,
+            args.nonce,
+            args.lane,
+        )?;
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount: args.amount,
+                nonce: args.nonce,
+                lane: args.lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: Some(args.fee),
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Balance,
+            },
+        )?;
+
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Fails with `FeeQuoteExpired` past the quote's expiry slot, and with
+    /// `InvalidFeeQuote` unless an Ed25519 instruction of the transaction
+    /// verified the fee payer's signature over the quote of this submission
+    #[allow(clippy::too_many_arguments)]
+    fn check_quote(
+        terms: &QuoteTerms,
+        gsn_program_info: &AccountInfo,
+        target_program_info: &AccountInfo,
+        sender_info: &AccountInfo,
+        reciever_info: &AccountInfo,
+        fee_payer_info: &AccountInfo,
+        instructions_info: &AccountInfo,
+        amount: u64,
+        nonce: u64,
+        lane: u64,
+    ) -> ProgramResult {
+        if Clock::get()?.slot > terms.expiry_slot {
+            return Err(GsnError::FeeQuoteExpired.into());
+        }
+        let quote = FeeQuote {
+            gsn_account: *gsn_program_info.key,
+            executor: *fee_payer_info.key,
+            fee: terms.fee,
+            expiry_slot: terms.expiry_slot,
+            payload_hash: FeeQuote::payload_hash(
+                target_program_info.key,
+                sender_info.key,
+                reciever_info.key,
+                amount,
+                nonce,
+                lane,
+            ),
+        };
+        let current_index = load_current_index_checked(instructions_info)?;
+        verify_quote(instructions_info, current_index, &quote)?;
+
+        gsn_event!(
+            "FEE_QUOTE",
+            executor = fee_payer_info.key.to_string(),
+            consumer = sender_info.key.to_string(),
+            fee = terms.fee,
+            expiry_slot = terms.expiry_slot,
+        );
+        Ok(())
+    }
+
+    /// Create the escrow account of a submission and move its fee into it
+    fn open_escrow<'a, 'b>(
+        program_id: &Pubkey,
+        gsn_account: &'a AccountInfo<'b>,
+        escrow_info: &'a AccountInfo<'b>,
+        vault: &'a AccountInfo<'b>,
+        system_program: &'a AccountInfo<'b>,
+        fee_payer: &'a AccountInfo<'b>,
+        escrow: &FeeEscrow,
+    ) -> ProgramResult {
+        let (_, bump) = FeeEscrow::find_address(
+            program_id,
+            &escrow.gsn_account,
+            &escrow.consumer,
+            escrow.lane,
+            escrow.nonce,
+        );
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                fee_payer.key,
+                escrow_info.key,
+                rent.minimum_balance(FeeEscrow::LEN),
+                FeeEscrow::LEN as u64,
+                program_id,
+            ),
+            &[fee_payer.clone(), escrow_info.clone(), system_program.clone()],
+            &[&[
+                ESCROW_SEED,
+                escrow.gsn_account.as_ref(),
+                escrow.consumer.as_ref(),
+                &escrow.lane.to_le_bytes(),
+                &escrow.nonce.to_le_bytes(),
+                &[bump],
+            ]],
+        )?;
+
+        Self::pay_from_vault(
+            program_id,
+            gsn_account,
+            vault,
+            escrow_info,
+            system_program,
+            escrow.fee,
+        )?;
+        escrow.serialize(&mut escrow_info.data.borrow_mut())?;
+
+        gsn_event!(
+            "ESCROW_OPENED",
+            escrow = escrow_info.key.to_string(),
+            consumer = escrow.consumer.to_string(),
+            executor = escrow.executor.to_string(),
+            lane = escrow.lane,
+            nonce = escrow.nonce,
+            fee = escrow.fee,
+            releasable_at = escrow.releasable_at(),
+        );
+        Ok(())
+    }
+
+    /// Write the `ExecutionReceipt` of a submission if its address is among
+    /// the accounts, creating the account at the executor's expense. A nonce
+    /// reused after a sandbox reset, or an unordered nonce out of its window,
+    /// replaces the receipt of its earlier submission.
+    fn write_receipt<'a, 'b>(
+        program_id: &Pubkey,
+        gsn_account: &'a AccountInfo<'b>,
+        fee_payer: &'a AccountInfo<'b>,
+        accounts: &'a [AccountInfo<'b>],
+        execution: &ExecutionEvent,
+    ) -> ProgramResult {
+        let (receipt_key, bump) = ExecutionReceipt::find_address(
+            program_id,
+            gsn_account.key,
+            &execution.consumer,
+            execution.lane,
+            execution.nonce,
+        );
+        let receipt_info = match accounts.iter().find(|account| *account.key == receipt_key) {
+            Some(receipt_info) => receipt_info,
+            None => return Ok(()),
+        };
+        validation::check_writable(receipt_info)?;
+
+        if receipt_info.lamports() == 0 {
+            let system_program =
+                Self::find_system_program(accounts).ok_or(GsnError::InvalidSystemProgram)?;
+            let rent = Rent::get()?;
+            invoke_signed(
+                &system_instruction::create_account(
+                    fee_payer.key,
+                    receipt_info.key,
+                    rent.minimum_balance(ExecutionReceipt::LEN),
+                    ExecutionReceipt::LEN as u64,
+                    program_id,
+                ),
+                &[fee_payer.clone(), receipt_info.clone(), system_program.clone()],
+                &[&[
+                    RECEIPT_SEED,
+                    gsn_account.key.as_ref(),
+                    execution.consumer.as_ref(),
+                    &execution.lane.to_le_bytes(),
+                    &execution.nonce.to_le_bytes(),
+                    &[bump],
+                ]],
+            )?;
+        } else {
+            validation::check_owner(program_id, receipt_info)?;
+            ExecutionReceipt::deserialize(&receipt_info.data.borrow())?;
+        }
+
+        let receipt = ExecutionReceipt {
+            is_initialized: true,
+            gsn_account: *gsn_account.key,
+            consumer: execution.consumer,
+            executor: execution.executor,
+            target_program: execution.target_program,
+            lane: execution.lane,
+            nonce: execution.nonce,
+            amount: execution.amount,
+            fee: execution.fee,
+            tip: execution.tip,
+            slot: Clock::get()?.slot,
+        };
+        receipt.serialize(&mut receipt_info.data.borrow_mut())?;
+
+        gsn_event!(
+            "RECEIPT",
+            receipt = receipt_info.key.to_string(),
+            consumer = receipt.consumer.to_string(),
+            lane = receipt.lane,
+            nonce = receipt.nonce,
+        );
+        Ok(())
+    }
+
+    /// Pays an escrowed fee, and the escrow's rent, to its executor once the
+    /// dispute window has passed, closing the escrow.
+    pub fn process_release_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let escrow_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(GsnError::UnauthorizedFeeClaim.into());
+        }
+
+        let escrow = Self::load_escrow(program_id, escrow_info)?;
+        if escrow.executor != *executor_info.key {
+            return Err(GsnError::UnauthorizedFeeClaim.into());
+        }
+
+        let clock = Clock::get()?;
+        if clock.slot < escrow.releasable_at() {
+            return Err(GsnError::EscrowInDisputeWindow.into());
+        }
+
+        Self::close_account(escrow_info, executor_info)?;
+
+        gsn_event!(
+            "ESCROW_RELEASED",
+            escrow = escrow_info.key.to_string(),
+            executor = executor_info.key.to_string(),
+            fee = escrow.fee,
+        );
+        Ok(())
+    }
+
+    /// Refunds an escrowed fee to the consumer's top-up balance within the
+    /// dispute window. Only the governance authority may dispute; the
+    /// escrow's rent goes back to the executor that funded it.
+    pub fn process_dispute_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let escrow_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let escrow = Self::load_escrow(program_id, escrow_info)?;
+        if escrow.gsn_account != *gsn_program_info.key || escrow.executor != *executor_info.key {
+            return Err(GsnError::InvalidEscrowAccount.into());
+        }
+
+        let clock = Clock::get()?;
+        if clock.slot >= escrow.releasable_at() {
+            return Err(GsnError::EscrowDisputeWindowClosed.into());
+        }
+
+        gsn.check_topup_capacity(&escrow.consumer)?;
+        let previous_balance = gsn.consumer.get(&escrow.consumer).copied().unwrap_or(0);
+        let new_balance = previous_balance.saturating_add(escrow.fee);
+        gsn.consumer_mut().insert(escrow.consumer, new_balance);
+
+        Self::check_vault(program_id, gsn_program_info, vault_info)?;
+        Self::move_lamports(escrow_info, vault_info, escrow.fee)?;
+        Self::close_account(escrow_info, executor_info)?;
+
+        gsn_event!(
+            "ESCROW_DISPUTED",
+            escrow = escrow_info.key.to_string(),
+            consumer = escrow.consumer.to_string(),
+            executor = executor_info.key.to_string(),
+            fee = escrow.fee,
+            previous_balance = previous_balance,
+            new_balance = new_balance,
+        );
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Create the caller's sponsor policy, or replace it. The sponsor pays the
+    /// policy account's rent and tops its own key up to fund the fees.
+    pub fn process_register_sponsor(
+        program_id: &Pubkey,
+        args: RegisterSponsorArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let sponsor_info = next_account_info(account_info_iter)?;
+        let policy_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !sponsor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        // Only read
+        validation::check_owner(program_id, gsn_program_info)?;
+        validation::check_system_program(system_program_info)?;
+
+        let (policy_key, bump) =
+            SponsorPolicy::find_address(program_id, gsn_program_info.key, sponsor_info.key);
+        if *policy_info.key != policy_key {
+            return Err(GsnError::InvalidSponsorAccount.into());
+        }
+        if policy_info.owner != program_id {
+            let rent = Rent::get()?;
+            invoke_signed(
+                &system_instruction::create_account(
+                    sponsor_info.key,
+                    policy_info.key,
+                    rent.minimum_balance(SponsorPolicy::LEN),
+                    SponsorPolicy::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    sponsor_info.clone(),
+                    policy_info.clone(),
+                    system_program_info.clone(),
+                ],
+                &[&[
+                    SPONSOR_SEED,
+                    gsn_program_info.key.as_ref(),
+                    sponsor_info.key.as_ref(),
+                    &[bump],
+                ]],
+            )?;
+        }
+
+        let policy = SponsorPolicy {
+            is_initialized: true,
+            gsn_account: *gsn_program_info.key,
+            sponsor: *sponsor_info.key,
+            max_fee_per_tx: args.max_fee_per_tx,
+            allowed_programs: args.allowed_programs,
+            allowed_instructions: args.allowed_instructions,
+            max_submissions_per_user: args.max_submissions_per_user,
+        };
+        policy.serialize(&mut policy_info.data.borrow_mut())?;
+
+        gsn_event!(
+            "SPONSOR_REGISTERED",
+            sponsor = sponsor_info.key.to_string(),
+            policy = policy_info.key.to_string(),
+            max_fee_per_tx = policy.max_fee_per_tx,
+            allowed_programs = policy.allowed_programs.len(),
+            allowed_instructions = policy.allowed_instructions.len(),
+            max_submissions_per_user = policy.max_submissions_per_user,
+        );
+        Ok(())
+    }
+
+    /// Decode a sponsor policy, which must be the policy of a sponsor of the
+    /// GSN state account
+    fn load_sponsor(
+        program_id: &Pubkey,
+        gsn_program_info: &AccountInfo,
+        policy_info: &AccountInfo,
+    ) -> Result<SponsorPolicy, ProgramError> {
+        if policy_info.owner != program_id {
+            return Err(GsnError::InvalidSponsorAccount.into());
+        }
+        let policy = SponsorPolicy::deserialize(&policy_info.data.borrow())?;
+        if !policy.is_initialized || policy.gsn_account != *gsn_program_info.key {
+            return Err(GsnError::InvalidSponsorAccount.into());
+        }
+        Ok(policy)
+    }
+
+    /// Decode an escrow account, which must be an open escrow of this program
+    fn load_escrow(program_id: &Pubkey, escrow_info: &AccountInfo) -> Result<FeeEscrow, ProgramError> {
+        if escrow_info.owner != program_id {
+            return Err(GsnError::InvalidEscrowAccount.into());
+        }
+        // Releasing or disputing it closes the escrow
+        validation::check_writable(escrow_info)?;
+        let escrow = FeeEscrow::deserialize(&escrow_info.data.borrow())?;
+        if !escrow.is_initialized {
+            return Err(GsnError::InvalidEscrowAccount.into());
+        }
+        Ok(escrow)
+    }
+
+    /// Move `amount` lamports between two accounts, `from` being owned by the program
+    fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+        let from_lamports = from
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        let to_lamports = to
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        **from.try_borrow_mut_lamports()? = from_lamports;
+        **to.try_borrow_mut_lamports()? = to_lamports;
+        Ok(())
+    }
+
+    /// Send every lamport of a program account to `destination` and wipe its
+    /// data, so the runtime reclaims the account
+    fn close_account(account_info: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+        Self::move_lamports(account_info, destination, account_info.lamports())?;
+        account_info.data.borrow_mut().fill(0);
+        Ok(())
+    }
+
+    /// Check `vault_info` is the vault of the GSN state account and return its bump
+    fn check_vault(
+        program_id: &Pubkey,
+        gsn_program_info: &AccountInfo,
+        vault_info: &AccountInfo,
+    ) -> Result<u8, ProgramError> {
+        let (vault_key, bump) = GsnInfo::find_vault_address(program_id, gsn_program_info.key);
+        if *vault_info.key != vault_key {
+            return Err(GsnError::InvalidVaultAccount.into());
+        }
+        Ok(bump)
+    }
+
+    /// Pay `amount` lamports out of the vault, which the program signs for
+    fn pay_from_vault<'a>(
+        program_id: &Pubkey,
+        gsn_program_info: &AccountInfo<'a>,
+        vault_info: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        let bump = Self::check_vault(program_id, gsn_program_info, vault_info)?;
+        invoke_signed(
+            &system_instruction::transfer(vault_info.key, destination.key, amount),
+            &[
+                vault_info.clone(),
+                destination.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[VAULT_SEED, gsn_program_info.key.as_ref(), &[bump]]],
+        )
+    }
+
+    /// Fees can be paid in any allowed mint other than the native token key
+    fn check_fee_mint(config: &GsnConfig, mint: &Pubkey) -> ProgramResult {
+        if *mint == NATIVE_FEE_TOKEN || !config.is_token_allowed(mint) {
+            return Err(GsnError::TokenNotAllowed.into());
+        }
+        Ok(())
+    }
+
+    /// Decode a token account of `token_program` held by `owner`, of `mint` if given
+    fn check_token_account(
+        account_info: &AccountInfo,
+        token_program_info: &AccountInfo,
+        owner: &Pubkey,
+        mint: Option<&Pubkey>,
+    ) -> Result<TokenAccount, ProgramError> {
+        if !is_token_program(token_program_info.key) || account_info.owner != token_program_info.key {
+            return Err(GsnError::InvalidTokenAccount.into());
+        }
+        match load_token_account(account_info) {
+            Some(account)
+                if account.owner == *owner && (mint.is_none() || mint == Some(&account.mint)) =>
+            {
+                Ok(account)
+            }
+            _ => Err(GsnError::InvalidTokenAccount.into()),
+        }
+    }
+
+    /// Open the vault's associated token account of a mint it holds none of,
+    /// for a token top-up. The system and associated token account programs
+    /// follow the mint, then optionally the signer paying the rent, such as
+    /// the executor relaying the top-up, instead of the consumer.
+    fn create_vault_token_account<'a>(
+        vault_token_info: &AccountInfo<'a>,
+        vault_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        consumer_info: &AccountInfo<'a>,
+        account_info_iter: &mut std::slice::Iter<AccountInfo<'a>>,
+    ) -> ProgramResult {
+        let system_program_info = next_account_info(account_info_iter)
+            .map_err(|_| GsnError::VaultTokenAccountMissing)?;
+        let associated_token_program_info = next_account_info(account_info_iter)
+            .map_err(|_| GsnError::VaultTokenAccountMissing)?;
+        let payer_info = next_account_info(account_info_iter).unwrap_or(consumer_info);
+        validation::check_system_program(system_program_info)?;
+        if *associated_token_program_info.key != token::ASSOCIATED_TOKEN_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *vault_token_info.key
+            != token::associated_token_address(vault_info.key, mint_info.key, token_program_info.key)
+        {
+            return Err(GsnError::InvalidTokenAccount.into());
+        }
+        invoke(
+            &token::create_associated_token_account(
+                payer_info.key,
+                vault_info.key,
+                mint_info.key,
+                token_program_info.key,
+            ),
+            &[
+                payer_info.clone(),
+                vault_token_info.clone(),
+                vault_info.clone(),
+                mint_info.clone(),
+                system_program_info.clone(),
+                token_program_info.clone(),
+                associated_token_program_info.clone(),
+            ],
+        )
+    }
+
+    /// Decode the mint `mint`, which must belong to `token_program`
+    fn check_mint(
+        account_info: &AccountInfo,
+        token_program_info: &AccountInfo,
+        mint: &Pubkey,
+    ) -> Result<Mint, ProgramError> {
+        if account_info.key != mint || account_info.owner != token_program_info.key {
+            return Err(GsnError::InvalidTokenAccount.into());
+        }
+        load_mint(account_info).ok_or_else(|| GsnError::InvalidTokenAccount.into())
+    }
+
+    /// Transfer of `amount` tokens from `source` to `destination`, checked
+    /// when the mint's account was passed
+    fn token_transfer(
+        token_program_info: &AccountInfo,
+        source_info: &AccountInfo,
+        destination_info: &AccountInfo,
+        authority_info: &AccountInfo,
+        mint: Option<(&AccountInfo, &Mint)>,
+        amount: u64,
+    ) -> Instruction {
+        match mint {
+            Some((mint_info, mint)) => token::transfer_checked(
+                token_program_info.key,
+                source_info.key,
+                mint_info.key,
+                destination_info.key,
+                authority_info.key,
+                amount,
+                mint.decimals,
+            ),
+            None => token::transfer(
+                token_program_info.key,
+                source_info.key,
+                destination_info.key,
+                authority_info.key,
+                amount,
+            ),
+        }
+    }
+
+    /// Records an executor's commitment to a submission it will reveal after
+    /// `REVEAL_DELAY_SLOTS`. Only the hash is published, so the submission
+    /// can't be copied or front-run before it lands. The executor funds the
+    /// commitment account, whose rent the reveal refunds.
+    pub fn process_commit_submission(
+        program_id: &Pubkey,
+        hash: [u8; 32],
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let executor_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let commitment_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        // Only read
+        validation::check_owner(program_id, gsn_program_info)?;
+        validation::check_system_program(system_program_info)?;
+
+        let (commitment_key, bump) =
+            SubmissionCommitment::find_address(program_id, gsn_program_info.key, &hash);
+        if *commitment_info.key != commitment_key {
+            return Err(GsnError::InvalidCommitment.into());
+        }
+
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                executor_info.key,
+                commitment_info.key,
+                rent.minimum_balance(SubmissionCommitment::LEN),
+                SubmissionCommitment::LEN as u64,
+                program_id,
+            ),
+            &[
+                executor_info.clone(),
+                commitment_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[COMMITMENT_SEED, gsn_program_info.key.as_ref(), &hash, &[bump]]],
+        )?;
+
+        let clock = Clock::get()?;
+        let commitment = SubmissionCommitment {
+            is_initialized: true,
+            gsn_account: *gsn_program_info.key,
+            executor: *executor_info.key,
+            hash,
+            slot: clock.slot,
+        };
+        commitment.serialize(&mut commitment_info.data.borrow_mut())?;
+
+        gsn_event!(
+            "COMMIT",
+            commitment = commitment_info.key.to_string(),
+            executor = executor_info.key.to_string(),
+            revealable_at = commitment.revealable_at(),
+            expires_at = commitment.expires_at(),
+        );
+        Ok(())
+    }
+
+    /// Executes a committed submission like `SubmitTransactionInLane` once the
+    /// reveal delay has passed. The revealed arguments and accounts must hash
+    /// to the commitment, which only its executor may reveal; the commitment
+    /// is closed and its rent returned to the executor.
+    pub fn process_reveal_submission(
+        program_id: &Pubkey,
+        args: RevealArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !is_valid_nonce_lane(args.lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let target_program_info = next_account_info(account_info_iter)?;
+        let sender_info = next_account_info(account_info_iter)?;
+        let reciever_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        if !sender_info.is_signer || !fee_payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (mut gsn, context) = Self::load_submit_state(
+            program_id,
+            target_program_info,
+            fee_payer_info,
+            gsn_program_info,
+            config_info,
+            account_info_iter,
+        )?;
+        let credential = Self::next_credential(&context, account_info_iter)?;
+
+        let commitment_info = next_account_info(account_info_iter)?;
+        let hash = SubmissionCommitment::hash_submission(
+            target_program_info.key,
+            sender_info.key,
+            reciever_info.key,
+            args.amount,
+            args.nonce,
+            args.lane,
+            &args.salt,
+        );
+        let (commitment_key, _) =
+            SubmissionCommitment::find_address(program_id, gsn_program_info.key, &hash);
+        if *commitment_info.key != commitment_key || commitment_info.owner != program_id {
+            return Err(GsnError::InvalidCommitment.into());
+        }
+        let commitment = SubmissionCommitment::deserialize(&commitment_info.data.borrow())?;
+        if !commitment.is_initialized || commitment.executor != *fee_payer_info.key {
+            return Err(GsnError::InvalidCommitment.into());
+        }
+
+        let clock = Clock::get()?;
+        if clock.slot < commitment.revealable_at() {
+            return Err(GsnError::RevealTooEarly.into());
+        }
+        if clock.slot >= commitment.expires_at() {
+            return Err(GsnError::CommitmentExpired.into());
+        }
+
+        gsn_event!(
+            "REVEAL",
+            commitment = commitment_info.key.to_string(),
+            executor = fee_payer_info.key.to_string(),
+            committed_slot = commitment.slot,
+        );
+
+        Self::execute_submission(
+            &mut gsn,
+            &context,
+            &Submission {
+                sender: sender_info,
+                receiver: reciever_info,
+                amount: args.amount,
+                nonce: args.nonce,
+                lane: args.lane,
+                network_fee_share: 0,
+                credential,
+                quoted_fee: None,
+                fee_mint: None,
+                fee_price: None,
+                call: None,
+                sponsor: None,
+                compute_units: None,
+                max_fee: None,
+                tip: 0,
+                funding: Funding::Sender,
+                settlement: Settlement::Balance,
+            },
+        )?;
+
+        Self::close_account(commitment_info, fee_payer_info)?;
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, context.growth.as_ref())
+    }
+
+    /// Decode the state, loading the balances and nonces of the ledger
+    /// accounts among `accounts` into it. Every instruction that reads the
+    /// state writes it back, so its account must be writable.
+    fn load_state(
+        program_id: &Pubkey,
+        gsn_program_info: &AccountInfo,
+        accounts: &[AccountInfo],
+    ) -> Result<GsnInfo, ProgramError> {
+        validation::check_program_account(program_id, gsn_program_info)?;
+        let gsn = Self::read_state(program_id, gsn_program_info, accounts)?;
+        // Nothing writes the state from within a submission's CPI
+        gsn.check_not_in_flight()?;
+        Ok(gsn)
+    }
+
+    /// Like `load_state` for instructions that don't write the state, which
+    /// may be passed read-only
+    fn read_state(
+        program_id: &Pubkey,
+        gsn_program_info: &AccountInfo,
+        accounts: &[AccountInfo],
+    ) -> Result<GsnInfo, ProgramError> {
+        validation::check_owner(program_id, gsn_program_info)?;
+        let mut gsn = GsnInfo::deserialize(&gsn_program_info.data.borrow())?;
+        if gsn.ledger_accounts.is_empty() {
+            return Ok(gsn);
+        }
+        let mut loaded: Vec<&Pubkey> = Vec::new();
+        for account_info in accounts {
+            if loaded.contains(&account_info.key) {
+                continue;
+            }
+            match Self::ledger_account(gsn_program_info, account_info) {
+                Some(LedgerAccount::Consumer(account)) => gsn.load_consumer_account(&account),
+                Some(LedgerAccount::Executor(account)) => gsn.load_executor_account(&account),
+                None => continue,
+            }
+            loaded.push(account_info.key);
+        }
+        Ok(gsn)
+    }
+
+    /// Decode the config, which must be this program's
+    fn load_config(program_id: &Pubkey, config_info: &AccountInfo) -> Result<GsnConfig, ProgramError> {
+        validation::check_owner(program_id, config_info)?;
+        GsnConfig::deserialize(&config_info.data.borrow())
+    }
+
+    /// Move the balances and nonces loaded by `load_state` back into their
+    /// ledger accounts, then write the state
+    fn store_state(
+        gsn: &mut GsnInfo,
+        gsn_program_info: &AccountInfo,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        Self::store_state_growing(gsn, gsn_program_info, accounts, None)
+    }
+
+    /// Like `store_state`, first growing the state account if the state no
+    /// longer fits, with `growth` paying the rent
+    fn store_state_growing<'a>(
+        gsn: &mut GsnInfo,
+        gsn_program_info: &AccountInfo<'a>,
+        accounts: &[AccountInfo],
+        growth: Option<&StateGrowth<'_, 'a>>,
+    ) -> ProgramResult {
+        if !gsn.ledger_accounts.is_empty() {
+            let mut stored: Vec<&Pubkey> = Vec::new();
+            for account_info in accounts {
+                if stored.contains(&account_info.key) {
+                    continue;
+                }
+                let ledger = match Self::ledger_account(gsn_program_info, account_info) {
+                    Some(ledger) => ledger,
+                    None => continue,
+                };
+                if !account_info.is_writable {
+                    return Err(GsnError::InvalidLedgerAccount.into());
+                }
+                match ledger {
+                    LedgerAccount::Consumer(mut account) => {
+                        gsn.store_consumer_account(&mut account);
+                        account.serialize(&mut account_info.data.borrow_mut())?;
+                    }
+                    LedgerAccount::Executor(mut account) => {
+                        gsn.store_executor_account(&mut account);
+                        account.serialize(&mut account_info.data.borrow_mut())?;
+                    }
+                }
+                stored.push(account_info.key);
+            }
+            gsn.check_ledgers_stored()?;
+        }
+        if let Some(growth) = growth {
+            Self::grow_state(gsn, gsn_program_info, growth)?;
+        }
+        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+    }
+
+    /// The system program, if it is among `accounts`
+    fn find_system_program<'a, 'b>(accounts: &'a [AccountInfo<'b>]) -> Option<&'a AccountInfo<'b>> {
+        accounts
+            .iter()
+            .find(|account| solana_program::system_program::check_id(account.key))
+    }
+
+    /// Let the state grow past its account for the rest of the instruction,
+    /// if `payer` can pay for it: a writable signer, with the system program
+    /// among the accounts. Capacity checks then allow for the growth.
+    fn state_growth<'a, 'b>(
+        gsn: &mut GsnInfo,
+        gsn_program_info: &AccountInfo,
+        payer: &'a AccountInfo<'b>,
+        system_program: Option<&'a AccountInfo<'b>>,
+    ) -> Option<StateGrowth<'a, 'b>> {
+        if !payer.is_signer || !payer.is_writable {
+            return None;
+        }
+        let system_program = system_program?;
+        gsn.allow_growth(GsnInfo::max_grown_len(gsn_program_info.data_len()));
+        Some(StateGrowth {
+            payer,
+            system_program,
+        })
+    }
+
+    /// Realloc the state account to fit `gsn`, topping its lamports up to
+    /// rent exemption from the payer
+    fn grow_state<'a>(
+        gsn: &GsnInfo,
+        gsn_program_info: &AccountInfo<'a>,
+        growth: &StateGrowth<'_, 'a>,
+    ) -> ProgramResult {
+        let old_len = gsn_program_info.data_len();
+        let new_len = match gsn.grown_len(old_len) {
+            Some(new_len) => new_len,
+            None => return Ok(()),
+        };
+        gsn_program_info.realloc(new_len, true)?;
+
+        let rent = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(gsn_program_info.lamports());
+        if rent > 0 {
+            invoke(
+                &system_instruction::transfer(growth.payer.key, gsn_program_info.key, rent),
+                &[
+                    growth.payer.clone(),
+                    gsn_program_info.clone(),
+                    growth.system_program.clone(),
+                ],
+            )?;
+        }
+        gsn_event!(
+            "STATE_GROWN",
+            old_len = old_len,
+            new_len = new_len,
+            payer = growth.payer.key.to_string(),
+            rent = rent,
+        );
+        Ok(())
+    }
+
+    /// The ledger account of a consumer or executor of the state that
+    /// `account_info` holds, if it is one
+    fn ledger_account(
+        gsn_program_info: &AccountInfo,
+        account_info: &AccountInfo,
+    ) -> Option<LedgerAccount> {
+        if account_info.owner != gsn_program_info.owner {
+            return None;
+        }
+        let data = account_info.data.borrow();
+        let (ledger, address, gsn_account) = match data.len() {
+            ConsumerAccount::LEN => {
+                let account = ConsumerAccount::deserialize(&data).ok()?;
+                let address = account.address(gsn_program_info.owner);
+                let gsn_account = Some(account.gsn_account).filter(|_| account.is_initialized);
+                (LedgerAccount::Consumer(account), address, gsn_account)
+            }
+            ExecutorAccount::LEN => {
+                let account = ExecutorAccount::deserialize(&data).ok()?;
+                let address = account.address(gsn_program_info.owner);
+                let gsn_account = Some(account.gsn_account).filter(|_| account.is_initialized);
+                (LedgerAccount::Executor(account), address, gsn_account)
+            }
+            _ => return None,
+        };
+        if gsn_account != Some(*gsn_program_info.key) || address != Some(*account_info.key) {
+            return None;
+        }
+        Some(ledger)
+    }
+
+    /// Create the ledger account of `owner_info` at `ledger_info`, funded by
+    /// the owner
+    fn create_ledger_account<'a>(
+        program_id: &Pubkey,
+        owner_info: &AccountInfo<'a>,
+        ledger_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        signer_seeds: &[&[u8]],
+        len: usize,
+    ) -> ProgramResult {
+        let rent = Rent::get()?;
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_info.key,
+                ledger_info.key,
+                rent.minimum_balance(len),
+                len as u64,
+                program_id,
+            ),
+            &[
+                owner_info.clone(),
+                ledger_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[signer_seeds],
+        )
+    }
+
+    /// Move the caller's top-up balance and lane 0 nonce out of the state into
+    /// its consumer ledger account, which the caller funds. Accounts: GSN
+    /// state (w), consumer (signer, w), consumer account (w), system program.
+    pub fn process_open_consumer_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+        let ledger_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        validation::check_system_program(system_program_info)?;
+
+        // No ledger account is loaded, since the state is written back whole:
+        // only the caller's entries of the state itself move
+        let mut gsn = Self::load_state(program_id, gsn_program_info, &[])?;
+        let (ledger_key, bump) =
+            ConsumerAccount::find_address(program_id, gsn_program_info.key, consumer_info.key);
+        if *ledger_info.key != ledger_key {
+            return Err(GsnError::InvalidLedgerAccount.into());
+        }
+        if ledger_info.owner == program_id {
+            return Err(GsnError::AlreadyInUse.into());
+        }
+        gsn.check_ledger_capacity(consumer_info.key)?;
+        Self::create_ledger_account(
+            program_id,
+            consumer_info,
+            ledger_info,
+            system_program_info,
+            &[
+                CONSUMER_LEDGER_SEED,
+                gsn_program_info.key.as_ref(),
+                consumer_info.key.as_ref(),
+                &[bump],
+            ],
+            ConsumerAccount::LEN,
+        )?;
+
+        let mut account = ConsumerAccount {
+            is_initialized: true,
+            gsn_account: *gsn_program_info.key,
+            consumer: *consumer_info.key,
+            balance: 0,
+            nonce: 0,
+            bump,
+        };
+        gsn.open_consumer_account(&mut account);
+        account.serialize(&mut ledger_info.data.borrow_mut())?;
+
+        gsn_event!(
+            "LEDGER_ACCOUNT_OPENED",
+            consumer = consumer_info.key.to_string(),
+            account = ledger_info.key.to_string(),
+            balance = account.balance,
+            nonce = account.nonce,
+        );
+        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+    }
+
+    /// Move the caller's earnings out of the state into its executor ledger
+    /// account, which the caller funds. Accounts: GSN state (w), executor
+    /// (signer, w), executor account (w), system program.
+    pub fn process_open_executor_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let ledger_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        validation::check_system_program(system_program_info)?;
+
+        // No ledger account is loaded, since the state is written back whole:
+        // only the caller's entries of the state itself move
+        let mut gsn = Self::load_state(program_id, gsn_program_info, &[])?;
+        let (ledger_key, bump) =
+            ExecutorAccount::find_address(program_id, gsn_program_info.key, executor_info.key);
+        if *ledger_info.key != ledger_key {
+            return Err(GsnError::InvalidLedgerAccount.into());
+        }
+        if ledger_info.owner == program_id {
+            return Err(GsnError::AlreadyInUse.into());
+        }
+        gsn.check_ledger_capacity(executor_info.key)?;
+        Self::create_ledger_account(
+            program_id,
+            executor_info,
+            ledger_info,
+            system_program_info,
+            &[
+                EXECUTOR_LEDGER_SEED,
+                gsn_program_info.key.as_ref(),
+                executor_info.key.as_ref(),
+                &[bump],
+            ],
+            ExecutorAccount::LEN,
+        )?;
+
+        let mut account = ExecutorAccount {
+            is_initialized: true,
+            gsn_account: *gsn_program_info.key,
+            executor: *executor_info.key,
+            earned: 0,
+            bump,
+        };
+        gsn.open_executor_account(&mut account);
+        account.serialize(&mut ledger_info.data.borrow_mut())?;
+
+        gsn_event!(
+            "LEDGER_ACCOUNT_OPENED",
+            executor = executor_info.key.to_string(),
+            account = ledger_info.key.to_string(),
+            earned = account.earned,
+        );
+        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+    }
+
+    /// Remove the caller, whose balances are all empty, from the state and
+    /// close its consumer ledger account, refunding the rent to it. Its
+    /// nonces stay. Accounts: GSN state (w), consumer (signer, w), consumer
+    /// account (w) if it opened one.
+    pub fn process_close_consumer(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        let ledger = if gsn.ledger_flags(consumer_info.key) & CONSUMER_LEDGER != 0 {
+            let (ledger_key, _) =
+                ConsumerAccount::find_address(program_id, gsn_program_info.key, consumer_info.key);
+            let ledger_info = accounts
+                .iter()
+                .find(|account| *account.key == ledger_key)
+                .ok_or(GsnError::MissingLedgerAccount)?;
+            match Self::ledger_account(gsn_program_info, ledger_info) {
+                Some(LedgerAccount::Consumer(account)) => Some((ledger_info, account)),
+                _ => return Err(GsnError::InvalidLedgerAccount.into()),
+            }
+        } else {
+            None
+        };
+        gsn.close_consumer(consumer_info.key, ledger.as_ref().map(|(_, account)| account))?;
+
+        let mut refund = 0;
+        if let Some((ledger_info, _)) = ledger {
+            validation::check_writable(ledger_info)?;
+            validation::check_writable(consumer_info)?;
+            refund = ledger_info.lamports();
+            Self::close_account(ledger_info, consumer_info)?;
+        }
+
+        gsn_event!(
+            "CONSUMER_CLOSED",
+            consumer = consumer_info.key.to_string(),
+            next_nonce = gsn.get_next_lane_nonce(consumer_info.key, 0),
+            refund = refund,
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Decode the state and config of a submission and pick up the metrics
+    /// account that follows the config once governance registered one.
+    fn load_submit_state<'a, 'b>(
+        program_id: &Pubkey,
+        target_program: &'a AccountInfo<'b>,
+        fee_payer: &'a AccountInfo<'b>,
+        gsn_program_info: &'a AccountInfo<'b>,
+        config_info: &'a AccountInfo<'b>,
+        account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+    ) -> Result<(GsnInfo, SubmitContext<'a, 'b>), ProgramError> {
+        // A target program reentering the program could spend the
+        // consumer's balance twice
+        if target_program.key == program_id {
+            return Err(GsnError::ReentrantCall.into());
+        }
+        let ledger_accounts = account_info_iter.as_slice();
+        let mut gsn = Self::load_state(program_id, gsn_program_info, ledger_accounts)?;
+        gsn.check_not_paused()?;
+        let system_program = Some(target_program)
+            .filter(|program| solana_program::system_program::check_id(program.key))
+            .or_else(|| Self::find_system_program(account_info_iter.as_slice()));
+        let growth = Self::state_growth(&mut gsn, gsn_program_info, fee_payer, system_program);
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if let Some(shutdown) = &config.shutdown {
+            if shutdown.is_effective(Clock::get()?.unix_timestamp) {
+                return Err(GsnError::ShutDown.into());
+            }
+        }
+        validation::check_executable(target_program)?;
+        // Sponsors only pay for the programs they approved
+        if !config.is_program_allowed(target_program.key) {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "program_not_allowed",
+                target_program = target_program.key.to_string(),
+            );
+            return Err(GsnError::ProgramNotAllowed.into());
+        }
+        // Permissioned phase: fees are only credited to registered executors
+        if config.registered_executors_only && !gsn.is_executor_registered(fee_payer.key) {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "executor_not_registered",
+                executor = fee_payer.key.to_string(),
+            );
+            return Err(GsnError::ExecutorNotRegistered.into());
+        }
+        if config.min_executor_stake > 0
+            && !gsn.is_executor_bonded(fee_payer.key, config.min_executor_stake)
+        {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "executor_not_bonded",
+                executor = fee_payer.key.to_string(),
+            );
+            return Err(GsnError::ExecutorNotBonded.into());
+        }
+
+        // Validate the metrics account before executing rather than after the CPI
+        let metrics = match config.metrics {
+            Some(metrics_key) => {
+                let metrics_info = next_account_info(account_info_iter)?;
+                if *metrics_info.key != metrics_key {
+                    return Err(GsnError::InvalidMetricsAccount.into());
+                }
+                validation::check_program_account(program_id, metrics_info)?;
+                Some(metrics_info)
+            }
+            None => None,
+        };
+
+        Ok((
+            gsn,
+            SubmitContext {
+                program_id: *program_id,
+                gsn_account: gsn_program_info,
+                ledger_accounts,
+                target_program,
+                fee_payer,
+                config,
+                metrics,
+                growth,
+                simulate: false,
+            },
+        ))
+    }
+
+    /// Credential account of the next submission's sender, which follows its
+    /// other accounts while compliance mode is on
+    fn next_credential<'a, 'b>(
+        context: &SubmitContext<'a, 'b>,
+        account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+    ) -> Result<Option<&'a AccountInfo<'b>>, ProgramError> {
+        match context.config.credential_mint {
+            Some(_) => Ok(Some(
+                next_account_info(account_info_iter).map_err(|_| GsnError::MissingCredential)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Hash of what a submission executes: its accounts, amount, nonce and
+    /// lane, the relayed instruction, and the executor of a channel nonce
+    fn payload_hash(context: &SubmitContext, submission: &Submission) -> [u8; 32] {
+        let channel: &[u8] = if submission.lane == CHANNEL_NONCE_LANE {
+            context.fee_payer.key.as_ref()
+        } else {
+            &[]
+        };
+        let (call_program, call_data): (&[u8], &[u8]) = match &submission.call {
+            Some(call) => (call.instruction.program_id.as_ref(), &call.instruction.data),
+            None => (&[], &[]),
+        };
+        hashv(&[
+            context.target_program.key.as_ref(),
+            submission.sender.key.as_ref(),
+            submission.receiver.key.as_ref(),
+            &submission.amount.to_le_bytes(),
+            &submission.nonce.to_le_bytes(),
+            &submission.lane.to_le_bytes(),
+            channel,
+            call_program,
+            call_data,
+        ])
+        .to_bytes()
+    }
+
+    /// Check, execute and account for one relayed transfer, returning the
+    /// execution it emitted
+    fn execute_submission<'a, 'b>(
+        gsn: &mut GsnInfo,
+        context: &SubmitContext<'a, 'b>,
+        submission: &Submission<'a, 'b>,
+    ) -> Result<ExecutionEvent, ProgramError> {
+        let sender_info = submission.sender;
+        let reciever_info = submission.receiver;
+        let fee_payer_info = context.fee_payer;
+        let target_program_info = context.target_program;
+        let (amount, nonce, lane) = (submission.amount, submission.nonce, submission.lane);
+
+        let sender_key = sender_info.key.to_string();
+
+        // Blacklisted consumers and executors are never served
+        for key in [sender_info.key, fee_payer_info.key] {
+            if let Err(error) = gsn.check_not_blacklisted(key) {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "blacklisted",
+                    consumer = sender_key,
+                    address = key.to_string(),
+                );
+                return Err(error.into());
+            }
+        }
+
+        // Compliance mode: only consumers holding the configured credential are served
+        if let Some(mint) = context.config.credential_mint {
+            let credential = submission.credential.ok_or(GsnError::MissingCredential)?;
+            if let Err(error) = verify_credential(credential, &mint, sender_info.key) {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "missing_credential",
+                    consumer = sender_key,
+                );
+                return Err(error.into());
+            }
+        }
+
+        // A sponsor pays for its users; sandbox mode charges the fee to the
+        // subsidy's balance, or waives it without one
+        let charged = match (submission.sponsor, &context.config.sandbox) {
+            (Some(sponsor), _) => Some(sponsor.sponsor),
+            (None, Some(sandbox)) => sandbox.subsidy,
+            (None, None) => Some(*sender_info.key),
+        };
+
+        // Check if consumer exists; a single lookup serves the balance check below
+        let fee_mint = submission.fee_mint.as_ref();
+        let current_balance = match Self::fee_balance(gsn, sender_info.key, fee_mint) {
+            Some(balance) => balance,
+            // Sandbox, sponsored and free tier consumers don't need a top-up
+            None if context.config.sandbox.is_some()
+                || submission.sponsor.is_some()
+                || context.config.free_tier.is_some() =>
+            {
+                0
+            }
+            None => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        // SECURITY CHECK 1: Verify nonce to prevent replay attacks
+        // Each lane has its own counter, so lanes don't order against each other.
+        // Unordered nonces only have to be unused within the consumer's window,
+        // and channel nonces count per executor.
+        if lane == UNORDERED_NONCE_LANE {
+            if gsn.is_unordered_nonce_used(sender_info.key, nonce) {
+                return Err(GsnError::ReplayAttack.into());
+            }
+        } else {
+            let expected_nonce = if lane == CHANNEL_NONCE_LANE {
+                gsn.get_next_channel_nonce(sender_info.key, fee_payer_info.key)
+            } else {
+                gsn.get_next_lane_nonce(sender_info.key, lane)
+            };
+            if nonce < expected_nonce {
+                return Err(GsnError::ReplayAttack.into());
+            }
+            if nonce != expected_nonce {
+                return Err(GsnError::InvalidNonce.into());
+            }
+        }
+
+        // Identical payloads within the retention window are rejected even when
+        // the nonce checks out, e.g. after a sandbox reset cleared the nonces
+        let payload_hash = if gsn.recent_payloads.retention() > 0 {
+            let hash = Self::payload_hash(context, submission);
+            if gsn.is_recent_payload(&hash) {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "duplicate_payload",
+                    consumer = sender_key,
+                    nonce = nonce,
+                );
+                return Err(GsnError::DuplicatePayload.into());
+            }
+            Some(hash)
+        } else {
+            None
+        };
+
+        // Governance caps how often a consumer submits, so a single consumer
+        // can't drain its sponsor or keep relayers busy
+        let rate_slot = if gsn.rate_limit.max_submissions > 0 {
+            let slot = Clock::get()?.slot;
+            if let Err(error) = gsn.check_rate_limit(sender_info.key, slot) {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "rate_limited",
+                    consumer = sender_key,
+                    max_submissions = gsn.rate_limit.max_submissions,
+                    window_slots = gsn.rate_limit.window_slots,
+                );
+                return Err(error.into());
+            }
+            Some(slot)
+        } else {
+            None
+        };
+
+        // Volume discounts count the submissions of consumers paying lamport
+        // fees themselves
+        let volume_epoch = if !context.config.volume_discounts.is_empty()
+            && charged == Some(*sender_info.key)
+            && fee_mint.is_none()
+        {
+            Some(Clock::get()?.epoch)
+        } else {
+            None
+        };
+        let stats_epoch = if gsn.stats.enabled {
+            Some(Clock::get()?.epoch)
+        } else {
+            None
+        };
+
+        // Calculate fee using governance configuration, or the executor's quote,
+        // plus the batch network fee share, in the mint's base units for a
+        // mint with an oracle
+        let fee_price = submission
+            .fee_price
+            .as_ref()
+            .map(|(price, decimals)| (price, *decimals));
+        let fee = match (charged, submission.quoted_fee) {
+            (None, _) => 0,
+            (Some(_), Some(quoted_fee)) => query::submission_fee(
+                &context.config,
+                quoted_fee,
+                None,
+                submission.network_fee_share,
+                fee_price,
+            )?,
+            (Some(_), None) => {
+                let fee = match submission.compute_units {
+                    Some(compute_units) => match context.config.calculate_metered_fee(amount, compute_units) {
+                        Ok(fee) => fee,
+                        Err(error) => {
+                            gsn_event!(
+                                "EXECUTION_FAILED",
+                                reason = "invalid_compute_units",
+                                consumer = sender_key,
+                                compute_units = compute_units,
+                            );
+                            return Err(error.into());
+                        }
+                    },
+                    None => context.config.calculate_fee(amount),
+                };
+                let epoch_txs = volume_epoch.map(|epoch| gsn.epoch_txs(sender_info.key, epoch));
+                query::submission_fee(
+                    &context.config,
+                    fee,
+                    epoch_txs,
+                    submission.network_fee_share,
+                    fee_price,
+                )?
+            }
+        };
+        // The consumer signed for at most `max_fee`, whatever governance set since
+        if let Some(max_fee) = submission.max_fee {
+            if fee > max_fee {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "fee_too_high",
+                    consumer = sender_key,
+                    fee = fee,
+                    max_fee = max_fee,
+                );
+                return Err(GsnError::FeeTooHigh.into());
+            }
+        }
+
+        // A consumer's first lamport submissions are charged to the free
+        // tier's subsidy while its balance covers them, unless it tips
+        let free_tier = match &context.config.free_tier {
+            Some(tier)
+                if charged == Some(*sender_info.key)
+                    && fee_mint.is_none()
+                    && submission.tip == 0
+                    && gsn.free_txs_used(sender_info.key) < tier.free_tx_quota
+                    && Self::fee_balance(gsn, &tier.subsidy, None).unwrap_or(0) >= fee =>
+            {
+                Some(tier.subsidy)
+            }
+            _ => None,
+        };
+        let charged = free_tier.or(charged);
+
+        // Tips come out of the consumer's own balance, never a sponsor's or subsidy's
+        let tip = submission.tip;
+        if tip > 0 && (charged != Some(*sender_info.key) || fee_mint.is_some()) {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "invalid_tip",
+                consumer = sender_key,
+                tip = tip,
+            );
+            return Err(GsnError::InvalidTip.into());
+        }
+        let fee = fee.saturating_add(tip);
+        let charged_balance = match charged {
+            Some(key) if key != *sender_info.key => {
+                Self::fee_balance(gsn, &key, fee_mint).unwrap_or(0)
+            }
+            _ => current_balance,
+        };
+
+        // The sponsor's policy is checked before its balance is charged
+        if let Some(sponsor) = submission.sponsor {
+            let discriminator = submission
+                .call
+                .as_ref()
+                .and_then(|call| call.instruction.data.first().copied());
+            if !sponsor.covers(target_program_info.key, discriminator) || fee > sponsor.max_fee_per_tx {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "sponsor_policy",
+                    consumer = sender_key,
+                    sponsor = sponsor.sponsor.to_string(),
+                    target_program = target_program_info.key.to_string(),
+                    fee = fee,
+                );
+                return Err(GsnError::SponsorPolicyViolation.into());
+            }
+            let used = gsn.sponsored_submissions(&sponsor.sponsor, sender_info.key);
+            if !sponsor.has_quota_left(used) {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "sponsor_quota",
+                    consumer = sender_key,
+                    sponsor = sponsor.sponsor.to_string(),
+                    used = used,
+                );
+                return Err(GsnError::SponsorPolicyViolation.into());
+            }
+        }
+
+        // SECURITY CHECK 2: Verify top-up balance covers expected fee BEFORE execution
+        if charged_balance < fee {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "insufficient_balance",
+                consumer = sender_key,
+                required_fee = fee,
+                available_balance = charged_balance,
+            );
+            return Err(GsnError::InsufficientBalance.into());
+        }
+        // A transfer out of the sender's balance must leave the sender's own fee covered
+        if let Funding::Balance { .. } = submission.funding {
+            let own_fee = if charged == Some(*sender_info.key) { fee } else { 0 };
+            if current_balance < amount.saturating_add(own_fee) {
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    reason = "insufficient_balance",
+                    consumer = sender_key,
+                    required_fee = own_fee,
+                    available_balance = current_balance,
+                );
+                return Err(GsnError::InsufficientBalance.into());
+            }
+        }
+
+        // Governance caps what a compromised consumer key can spend on fees
+        if fee_mint.is_none() && fee > 0 {
+            if let Some(limit) = gsn.spending_limits.get(sender_info.key) {
+                let mut limit = *limit;
+                let epoch = Clock::get()?.epoch;
+                if let Err(error) = limit.charge(epoch, fee) {
+                    gsn_event!(
+                        "EXECUTION_FAILED",
+                        reason = "limit_exceeded",
+                        consumer = sender_key,
+                        fee = fee,
+                        remaining_limit = limit.remaining(epoch),
+                    );
+                    return Err(error.into());
+                }
+                gsn.spending_limits_mut().insert(*sender_info.key, limit);
+            }
+        }
+
+        // Fail before the CPI rather than when writing the state back
+        let executors = match &submission.settlement {
+            Settlement::Split { co_executor, .. } => vec![fee_payer_info.key, co_executor.key],
+            _ => vec![fee_payer_info.key],
+        };
+        let capacity = match fee_mint {
+            Some(mint) => gsn.check_token_submit_capacity(sender_info.key, lane, fee_payer_info.key, mint),
+            None => gsn.check_counted_submit_capacity(
+                sender_info.key,
+                lane,
+                &executors,
+                submission
+                    .sponsor
+                    .filter(|sponsor| sponsor.max_submissions_per_user > 0)
+                    .map(|sponsor| &sponsor.sponsor),
+                free_tier.is_some(),
+                volume_epoch.is_some(),
+            ),
+        };
+        // The fee is held in the state's escrow while the CPI runs
+        let escrow_mint = *fee_mint.unwrap_or(&NATIVE_FEE_TOKEN);
+        let escrowed = charged.filter(|_| fee > 0);
+        let capacity = capacity
+            .and_then(|_| match escrowed {
+                Some(key) => gsn.check_fee_escrow_capacity(&key, &escrow_mint),
+                None => Ok(()),
+            })
+            .and_then(|_| gsn.check_rate_window_capacity(sender_info.key))
+            .and_then(|_| match stats_epoch {
+                Some(epoch) => gsn.check_stats_capacity(epoch, sender_info.key, fee_payer_info.key),
+                None => Ok(()),
+            });
+        if let Err(error) = capacity {
+            gsn_event!(
+                "EXECUTION_FAILED",
+                reason = "history_full",
+                consumer = sender_key,
+                remaining_capacity = gsn.remaining_capacity().unwrap_or(0),
+            );
+            return Err(error.into());
+        }
+
+        // A simulation stops with every check passed, returning the fee
+        // before the escrow, the CPI or any write
+        if context.simulate {
+            let executor_fee = match fee_mint {
+                Some(_) => fee,
+                None => context.config.protocol_fee_shares(fee - tip).1 + tip,
+            };
+            query::set_answer(&FeeEstimate {
+                fee: fee - tip,
+                charged,
+                executor_fee,
+            });
+            return Ok(ExecutionEvent {
+                consumer: *sender_info.key,
+                executor: *fee_payer_info.key,
+                target_program: *target_program_info.key,
+                amount,
+                lane,
+                nonce,
+                fee: fee - tip,
+                tip,
+                fee_mint: fee_mint.copied(),
+                charged,
+                executor_fee,
+            });
+        }
+
+        if let Some(slot) = rate_slot {
+            gsn.record_rate_limited_submission(sender_info.key, slot);
+        }
+
+        let executor_key = fee_payer_info.key.to_string();
+        let target_program_key = target_program_info.key.to_string();
+
+        gsn_event!(
+            "EXECUTION_START",
+            consumer = sender_key,
+            executor = executor_key,
+            target_program = target_program_key,
+            amount = amount,
+            fee = fee,
+            nonce = nonce,
+            lane = lane,
+        );
+
+        // Deduct fee from consumer balance into the escrow, and write the state
+        // before the CPI: the target program only sees the fee gone from the
+        // balance, and no state read before the CPI is written back after it
+        let val = charged_balance - fee;
+        match charged {
+            Some(key) if fee_mint.is_some() => {
+                let key = (key, *fee_mint.unwrap());
+                if gsn.token_consumer.contains_key(&key) {
+                    gsn.token_consumer_mut().insert(key, val);
+                }
+            }
+            Some(key) if key == *sender_info.key => {
+                gsn.consumer_mut().insert(key, val);
+            }
+            // A subsidy without a balance was charged nothing, don't register it
+            Some(key) if gsn.consumer.contains_key(&key) => {
+                gsn.consumer_mut().insert(key, val);
+            }
+            _ => {}
+        }
+        if let Some(key) = escrowed {
+            gsn.escrow_fee(&key, &escrow_mint, fee);
+        }
+        gsn.set_in_flight(true);
+        Self::store_state_growing(gsn, context.gsn_account, context.ledger_accounts, context.growth.as_ref())?;
+
+        // Execute the transaction
+        let transfer = match (&submission.call, &submission.funding) {
+            (Some(call), _) => invoke(call.instruction, call.accounts),
+            (None, Funding::Sender) => invoke(
+                &system_instruction::transfer(sender_info.key, reciever_info.key, amount),
+                &[
+                    sender_info.clone(),
+                    reciever_info.clone(),
+                    target_program_info.clone(),
+                ],
+            ),
+            (
+                None,
+                Funding::Balance {
+                    program_id,
+                    gsn_account,
+                    vault,
+                },
+            ) => Self::pay_from_vault(
+                program_id,
+                gsn_account,
+                vault,
+                reciever_info,
+                target_program_info,
+                amount,
+            ),
+        };
+        gsn.reload(Self::read_state(&context.program_id, context.gsn_account, context.ledger_accounts)?);
+        gsn.set_in_flight(false);
+
+        match transfer {
+            Ok(_) => {
+                // SECURITY CHECK 3: Record transaction-executor mapping before updating balances.
+                // A channel's nonces are reused by every executor, the channel names its executor.
+                if lane != CHANNEL_NONCE_LANE {
+                    gsn.record_lane_transaction_executor(sender_info.key, lane, nonce, fee_payer_info.key);
+                }
+
+                // Increment nonce to prevent replay
+                match lane {
+                    UNORDERED_NONCE_LANE => gsn.use_unordered_nonce(sender_info.key, nonce),
+                    CHANNEL_NONCE_LANE => {
+                        gsn.increment_channel_nonce(sender_info.key, fee_payer_info.key);
+                    }
+                    _ => {
+                        gsn.increment_lane_nonce(sender_info.key, lane);
+                    }
+                }
+                if let Some(hash) = payload_hash {
+                    gsn.record_payload(hash);
+                }
+
+                if let Some(subsidy) = free_tier {
+                    gsn.record_free_tx(sender_info.key);
+                    gsn_event!(
+                        "FREE_TX",
+                        consumer = sender_key,
+                        subsidy = subsidy.to_string(),
+                        fee = fee,
+                        used = gsn.free_txs_used(sender_info.key),
+                    );
+                }
+
+                if let Some(epoch) = volume_epoch {
+                    gsn.record_epoch_tx(sender_info.key, epoch);
+                }
+
+                // Only a quota needs the user's sponsored submissions counted
+                if let Some(sponsor) = submission.sponsor {
+                    if sponsor.max_submissions_per_user > 0 {
+                        gsn.record_sponsored_submission(&sponsor.sponsor, sender_info.key);
+                    }
+                }
+
+                // Settle the escrowed fee
+                if let Some(key) = escrowed {
+                    gsn.release_escrowed_fee(&key, &escrow_mint, fee)?;
+                }
+
+                if let Funding::Balance { .. } = submission.funding {
+                    let balance = gsn.consumer.get(sender_info.key).copied().unwrap_or(0);
+                    gsn.consumer_mut().insert(*sender_info.key, balance - amount);
+                    gsn_event!(
+                        "BALANCE_TRANSFER",
+                        consumer = sender_key,
+                        amount = amount,
+                        new_balance = balance - amount,
+                    );
+                }
+
+                gsn_event!(
+                    "FEE_DEDUCTION",
+                    consumer = charged.map_or(sender_key.clone(), |key| key.to_string()),
+                    fee = fee,
+                    tip = tip,
+                    previous_balance = charged_balance,
+                    new_balance = val,
+                );
+
+                // The treasury's cut of lamport fees stays in the vault, tips
+                // go to the executor in full
+                let executor_fee = match fee_mint {
+                    Some(_) => fee,
+                    None => Self::take_protocol_fee(gsn, &context.config, fee - tip) + tip,
+                };
+
+                match &submission.settlement {
+                    Settlement::Balance => match fee_mint {
+                        Some(mint) => Self::credit_token_executor(gsn, fee_payer_info.key, mint, fee),
+                        None => Self::credit_executor(gsn, fee_payer_info.key, executor_fee),
+                    },
+                    Settlement::Split { co_executor, split } => {
+                        let (fee_payer_share, co_executor_share) = split.fee_shares(executor_fee);
+                        gsn_event!(
+                            "FEE_SPLIT",
+                            executor = executor_key,
+                            co_executor = co_executor.key.to_string(),
+                            fee = executor_fee,
+                            executor_share = fee_payer_share,
+                            co_executor_share = co_executor_share,
+                        );
+                        Self::credit_executor(gsn, fee_payer_info.key, fee_payer_share);
+                        Self::credit_executor(gsn, co_executor.key, co_executor_share);
+                    }
+                    Settlement::Batched { fees } => fees.set(fees.get() + executor_fee),
+                    Settlement::Escrow {
+                        program_id,
+                        gsn_account,
+                        escrow,
+                        vault,
+                        system_program,
+                    } => {
+                        let clock = Clock::get()?;
+                        Self::open_escrow(
+                            program_id,
+                            gsn_account,
+                            escrow,
+                            vault,
+                            system_program,
+                            fee_payer_info,
+                            &FeeEscrow {
+                                is_initialized: true,
+                                gsn_account: *gsn_account.key,
+                                consumer: *sender_info.key,
+                                executor: *fee_payer_info.key,
+                                lane,
+                                nonce,
+                                fee: executor_fee,
+                                slot: clock.slot,
+                            },
+                        )?;
+                    }
+                }
+
+                if let Some(metrics_info) = context.metrics {
+                    let clock = Clock::get()?;
+
+                    let mut metrics = ProtocolMetrics::deserialize(&metrics_info.data.borrow())?;
+                    metrics.record_execution(
+                        clock.epoch,
+                        target_program_info.key,
+                        fee_payer_info.key,
+                        fee_mint.unwrap_or(&NATIVE_FEE_TOKEN),
+                        fee,
+                    );
+                    if let Settlement::Split { co_executor, split } = &submission.settlement {
+                        let (_, co_executor_share) = split.fee_shares(executor_fee);
+                        metrics.transfer_executor_fee(
+                            fee_payer_info.key,
+                            co_executor.key,
+                            co_executor_share,
+                        );
+                    }
+                    metrics.serialize(&mut metrics_info.data.borrow_mut())?;
+                }
+
+                // Token fees count the execution, not the fee
+                if let Some(epoch) = stats_epoch {
+                    let (fee, executor_fee) = match fee_mint {
+                        Some(_) => (0, 0),
+                        None => (fee, executor_fee),
+                    };
+                    gsn.stats_mut()
+                        .record(epoch, sender_info.key, fee_payer_info.key, fee, executor_fee);
+                }
+
+                Self::record_execution_outcome(gsn, true)?;
+
+                let execution = ExecutionEvent {
+                    consumer: *sender_info.key,
+                    executor: *fee_payer_info.key,
+                    target_program: *target_program_info.key,
+                    amount,
+                    lane,
+                    nonce,
+                    fee: fee - tip,
+                    tip,
+                    fee_mint: fee_mint.copied(),
+                    charged,
+                    executor_fee,
+                };
+                event::emit(execution.clone());
+                Ok(execution)
+            }
+            Err(error) => {
+                // Failing reverts every write of the instruction, the escrowed
+                // fee included, so the consumer is charged nothing
+                gsn_event!(
+                    "EXECUTION_FAILED",
+                    consumer = sender_key,
+                    executor = executor_key,
+                    error = format!("{:?}", error),
+                );
+                // Nor can it count towards the circuit breaker: the executor
+                // reports it with `ReportFailure`
+                Err(error)
+            }
+        }
+    }
+
+    /// Count an execution's outcome towards the circuit breaker, emitting a
+    /// governance event when it pauses the deployment
+    fn record_execution_outcome(gsn: &mut GsnInfo, succeeded: bool) -> ProgramResult {
+        if !gsn.circuit_breaker.is_enabled() {
+            return Ok(());
+        }
+        let epoch = Clock::get()?.epoch;
+        if gsn.record_execution_outcome(epoch, succeeded) {
+            let breaker = gsn.circuit_breaker;
+            gsn_event!(
+                "CIRCUIT_BREAKER",
+                epoch = epoch,
+                succeeded = breaker.succeeded,
+                failed = breaker.failed,
+            );
+            event::emit(GovernanceEvent {
+                config: gsn.config,
+                authority: None,
+                action: GovernanceAction::CircuitBreakerTripped {
+                    epoch,
+                    succeeded: breaker.succeeded,
+                    failed: breaker.failed,
+                },
+            });
+        }
+        Ok(())
+    }
+
+    /// Balance `key` pays fees in `mint` from, lamports when `None`
+    fn fee_balance(gsn: &GsnInfo, key: &Pubkey, mint: Option<&Pubkey>) -> Option<u64> {
+        match mint {
+            Some(mint) => gsn.token_consumer.get(&(*key, *mint)).copied(),
+            None => gsn.consumer.get(key).copied(),
+        }
+    }
+
+    /// Add `fee` tokens of `mint` to an executor's earnings
+    fn credit_token_executor(gsn: &mut GsnInfo, executor: &Pubkey, mint: &Pubkey, fee: u64) {
+        let key = (*executor, *mint);
+        let previous_balance = gsn.token_executor.get(&key).copied().unwrap_or(0);
+        let new_balance = previous_balance.saturating_add(fee);
+        gsn.token_executor_mut().insert(key, new_balance);
+
+        gsn_event!(
+            "TOKEN_EXECUTOR_CREDIT",
+            executor = executor.to_string(),
+            mint = mint.to_string(),
+            fee = fee,
+            previous_balance = previous_balance,
+            new_balance = new_balance,
+        );
+    }
+
+    /// Credit the treasury with its `protocol_fee_bps` of a lamport `fee`,
+    /// and the insurance pool with its `insurance_fee_bps`, and return the
+    /// executors' share
+    fn take_protocol_fee(gsn: &mut GsnInfo, config: &GsnConfig, fee: u64) -> u64 {
+        let (treasury_share, executor_share) = config.protocol_fee_shares(fee);
+        if treasury_share > 0 {
+            gsn.credit_treasury(treasury_share);
+            gsn_event!(
+                "PROTOCOL_FEE",
+                fee = fee,
+                treasury_share = treasury_share,
+                treasury = gsn.treasury,
+            );
+        }
+        let insurance_share = config.insurance_fee_share(fee);
+        if insurance_share > 0 {
+            gsn.credit_insurance_pool(insurance_share);
+            gsn_event!(
+                "INSURANCE_FEE",
+                fee = fee,
+                insurance_share = insurance_share,
+                insurance_pool = gsn.insurance_pool,
+            );
+        }
+        executor_share
+    }
+
+    /// Add `fee` to an executor's running balance
+    fn credit_executor(gsn: &mut GsnInfo, executor: &Pubkey, fee: u64) {
+        let executor_previous_balance = gsn.executor.get(executor).copied().unwrap_or(0);
+        let executor_new_balance = executor_previous_balance.saturating_add(fee);
+        gsn.executor_mut().insert(*executor, executor_new_balance);
+
+        gsn_event!(
+            "EXECUTOR_CREDIT",
+            executor = executor.to_string(),
+            fee = fee,
+            previous_balance = executor_previous_balance,
+            new_balance = executor_new_balance,
+        );
+    }
+
+    pub fn process_update_fee_params(
+        program_id: &Pubkey,
+        args: UpdateFeeParamsArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        // Only fee reductions skip the timelock
+        let fee_mode = Self::fee_mode(&args)?;
+        let current = config
+            .governance
+            .as_ref()
+            .ok_or(GsnError::GovernanceNotInitialized)?;
+        if !fee_mode.is_reduction_of(&current.fee_mode) {
+            return Err(GsnError::FeeChangeTimelocked.into());
+        }
+
+        config.update_fee_params(fee_mode.clone());
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::FeeParamsUpdated { fee_mode },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Emit the `GovernanceEvent` of `action`, approved by `authority_info`
+    fn emit_governance(
+        config_info: &AccountInfo,
+        authority_info: Option<&AccountInfo>,
+        action: GovernanceAction,
+    ) {
+        event::emit(GovernanceEvent {
+            config: *config_info.key,
+            authority: authority_info.map(|info| *info.key),
+            action,
+        });
+    }
+
+    /// Record a fee change that `ApplyFeeParams` can apply once
+    /// `FEE_PARAMS_TIMELOCK_SLOTS` passed, giving consumers time to react
+    pub fn process_propose_fee_params(
+        program_id: &Pubkey,
+        args: UpdateFeeParamsArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let fee_mode = Self::fee_mode(&args)?;
+        let activation_slot = config.propose_fee_params(fee_mode.clone(), Clock::get()?.slot);
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::FeeParamsProposed {
+                fee_mode,
+                activation_slot,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Apply the proposed fee change after its timelock. Governance already
+    /// approved it, so anyone may apply it.
+    pub fn process_apply_fee_params(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        let fee_mode = config.apply_fee_params(Clock::get()?.slot)?;
+        Self::emit_governance(config_info, None, GovernanceAction::FeeParamsApplied { fee_mode });
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    fn fee_mode(args: &UpdateFeeParamsArgs) -> Result<FeeMode, ProgramError> {
+        match args.fee_mode_type {
+            0 => Ok(FeeMode::Fixed(args.fee_value)),
+            1 if args.fee_value <= 10000 => {
+                // A bound of 0 is no bound; a floor above the cap could never apply
+                let bound = |value: u64| Some(value).filter(|value| *value > 0);
+                let (min_fee, max_fee) = (bound(args.extra[0]), bound(args.extra[1]));
+                if min_fee.unwrap_or(0) > max_fee.unwrap_or(u64::MAX) {
+                    return Err(GsnError::InvalidFeeMode.into());
+                }
+                Ok(FeeMode::Percent {
+                    basis_points: args.fee_value as u16,
+                    min_fee,
+                    max_fee,
+                })
+            }
+            2 if args.extra[1] > 0 && args.extra[1] <= MAX_COMPUTE_UNITS => Ok(FeeMode::ComputeBased {
+                price_per_cu: args.fee_value,
+                base: args.extra[0],
+                max_compute_units: args.extra[1],
+            }),
+            _ => Err(GsnError::InvalidFeeMode.into()),
+        }
+    }
+
+    pub fn process_add_allowed_token(
+        program_id: &Pubkey,
+        args: TokenMintArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mint_pubkey = Pubkey::new_from_array(args.mint);
+        config.add_allowed_token(mint_pubkey);
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::TokenAllowed {
+                mint: mint_pubkey,
+                allowed: true,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    pub fn process_remove_allowed_token(
+        program_id: &Pubkey,
+        args: TokenMintArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mint_pubkey = Pubkey::new_from_array(args.mint);
+        config.remove_allowed_token(&mint_pubkey);
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::TokenAllowed {
+                mint: mint_pubkey,
+                allowed: false,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    pub fn process_add_allowed_program(
+        program_id: &Pubkey,
+        args: ProgramIdArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let program = Pubkey::new_from_array(args.program_id);
+        config.add_allowed_program(program);
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::ProgramAllowed {
+                program,
+                allowed: true,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    pub fn process_remove_allowed_program(
+        program_id: &Pubkey,
+        args: ProgramIdArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let program = Pubkey::new_from_array(args.program_id);
+        config.remove_allowed_program(&program);
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::ProgramAllowed {
+                program,
+                allowed: false,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Turn compliance mode on with the given credential mint, or off
+    pub fn process_set_credential_mint(
+        program_id: &Pubkey,
+        args: SetCredentialMintArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.credential_mint = match args.enabled {
+            0 => None,
+            _ => Some(Pubkey::new_from_array(args.mint)),
+        };
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Cap the lamport fees a consumer may be charged per submission and per
+    /// epoch, or lift its cap. Changing a cap keeps what the consumer already
+    /// spent this epoch.
+    pub fn process_set_spending_limit(
+        program_id: &Pubkey,
+        args: SetSpendingLimitArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let consumer = Pubkey::new_from_array(args.consumer);
+        if args.is_unlimited() {
+            gsn.spending_limits_mut().remove(&consumer);
+        } else {
+            gsn.check_spending_limit_capacity(&consumer)?;
+            let limit = match gsn.spending_limits.get(&consumer) {
+                Some(limit) => SpendingLimit {
+                    max_fee_per_tx: args.max_fee_per_tx,
+                    max_fees_per_epoch: args.max_fees_per_epoch,
+                    ..*limit
+                },
+                None => SpendingLimit::new(args.max_fee_per_tx, args.max_fees_per_epoch),
+            };
+            gsn.spending_limits_mut().insert(consumer, limit);
+        }
+
+        gsn_event!(
+            "SPENDING_LIMIT",
+            consumer = consumer.to_string(),
+            max_fee_per_tx = args.max_fee_per_tx,
+            max_fees_per_epoch = args.max_fees_per_epoch,
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Hand governance to a multisig, replace its signers, or return it to
+    /// the authority. The current authority or multisig approves the change.
+    pub fn process_set_multisig(
+        program_id: &Pubkey,
+        args: SetMultisigArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.multisig = match (args.threshold, args.signers.is_empty()) {
+            (0, true) => None,
+            _ => {
+                let multisig = MultisigConfig {
+                    signers: args.signers,
+                    threshold: args.threshold,
+                };
+                if !multisig.is_valid() {
+                    return Err(GsnError::InvalidMultisig.into());
+                }
+                Some(multisig)
+            }
+        };
+        let signers = config
+            .multisig
+            .as_ref()
+            .map_or_else(Vec::new, |multisig| multisig.signers.clone());
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::MultisigSet {
+                signers,
+                threshold: args.threshold,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Halt or resume top-ups, submissions, withdrawals and fee claims. Unlike
+    /// a shutdown, a pause can be lifted.
+    pub fn process_set_paused(
+        program_id: &Pubkey,
+        paused: bool,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        gsn.set_paused(paused);
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::Paused { paused },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Add an executor to the registry, or remove it
+    pub fn process_set_executor_registered(
+        program_id: &Pubkey,
+        args: ExecutorArgs,
+        registered: bool,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let executor = Pubkey::new_from_array(args.executor);
+        if registered {
+            gsn.check_executor_registration_capacity(&executor)?;
+            gsn.register_executor(executor);
+        } else {
+            gsn.deregister_executor(&executor);
+        }
+        gsn_event!(
+            "EXECUTOR_REGISTRY",
+            executor = executor.to_string(),
+            registered = registered,
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Blacklist a consumer or executor, or lift its blacklisting. Accounts:
+    /// GSN state (w), config, authority (signer).
+    pub fn process_set_blacklisted(
+        program_id: &Pubkey,
+        args: BlacklistArgs,
+        blacklisted: bool,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        if blacklisted {
+            gsn.check_blacklist_capacity(&args.address)?;
+            gsn.blacklist_address(args.address);
+        } else {
+            gsn.unblacklist_address(&args.address);
+        }
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::AddressBlacklisted {
+                address: args.address,
+                blacklisted,
+            },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Bond `amount` more lamports to the executor's stake. Staking again
+    /// while unbonding cancels the unbonding.
+    pub fn process_stake_executor(
+        program_id: &Pubkey,
+        args: StakeArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        validation::check_system_program(system_program_info)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        gsn.check_stake_capacity(executor_info.key)?;
+        Self::check_stake_account(program_id, gsn_program_info, executor_info, stake_info)?;
+
+        invoke(
+            &system_instruction::transfer(executor_info.key, stake_info.key, args.amount),
+            &[
+                executor_info.clone(),
+                stake_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+
+        let mut stake = gsn
+            .executor_stakes
+            .get(executor_info.key)
+            .copied()
+            .unwrap_or_default();
+        stake.amount = stake
+            .amount
+            .checked_add(args.amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        stake.unbonding_at = 0;
+        gsn.executor_stakes_mut().insert(*executor_info.key, stake);
+
+        gsn_event!(
+            "STAKE",
+            executor = executor_info.key.to_string(),
+            amount = args.amount,
+            stake = stake.amount,
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Start unbonding the executor's stake, which stops counting as bonded at
+    /// once; after `STAKE_UNBONDING_SLOTS`, pay it back to the executor
+    pub fn process_unstake_executor(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        validation::check_system_program(system_program_info)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        let bump = Self::check_stake_account(program_id, gsn_program_info, executor_info, stake_info)?;
+        let mut stake = *gsn
+            .executor_stakes
+            .get(executor_info.key)
+            .ok_or(GsnError::ExecutorNotBonded)?;
+
+        let slot = Clock::get()?.slot;
+        if stake.unbonding_at == 0 {
+            stake.unbonding_at = slot.saturating_add(STAKE_UNBONDING_SLOTS);
+            gsn.executor_stakes_mut().insert(*executor_info.key, stake);
+            gsn_event!(
+                "UNSTAKE_REQUESTED",
+                executor = executor_info.key.to_string(),
+                stake = stake.amount,
+                unbonding_at = stake.unbonding_at,
+            );
+            return Self::store_state(&mut gsn, gsn_program_info, accounts);
+        }
+        if slot < stake.unbonding_at {
+            return Err(GsnError::StakeUnbonding.into());
+        }
+
+        invoke_signed(
+            &system_instruction::transfer(stake_info.key, executor_info.key, stake.amount),
+            &[
+                stake_info.clone(),
+                executor_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[
+                STAKE_SEED,
+                gsn_program_info.key.as_ref(),
+                executor_info.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+        gsn.executor_stakes_mut().remove(executor_info.key);
+
+        gsn_event!(
+            "UNSTAKED",
+            executor = executor_info.key.to_string(),
+            amount = stake.amount,
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Take up to `amount` of an executor's stake, bonded or unbonding, for
+    /// provable misbehavior, paying it to `recipient`
+    pub fn process_slash_executor(
+        program_id: &Pubkey,
+        args: SlashArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let recipient_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+        validation::check_system_program(system_program_info)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let executor = Pubkey::new_from_array(args.executor);
+        let (stake_key, bump) =
+            GsnInfo::find_stake_address(program_id, gsn_program_info.key, &executor);
+        if *stake_info.key != stake_key {
+            return Err(GsnError::InvalidStakeAccount.into());
+        }
+        let mut stake = *gsn
+            .executor_stakes
+            .get(&executor)
+            .ok_or(GsnError::ExecutorNotBonded)?;
+        let slashed = args.amount.min(stake.amount);
+
+        invoke_signed(
+            &system_instruction::transfer(stake_info.key, recipient_info.key, slashed),
+            &[
+                stake_info.clone(),
+                recipient_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[
+                STAKE_SEED,
+                gsn_program_info.key.as_ref(),
+                executor.as_ref(),
+                &[bump],
+            ]],
+        )?;
+        stake.amount -= slashed;
+        if stake.amount == 0 {
+            gsn.executor_stakes_mut().remove(&executor);
+        } else {
+            gsn.executor_stakes_mut().insert(executor, stake);
+        }
+
+        gsn_event!(
+            "SLASH",
+            executor = executor.to_string(),
+            amount = slashed,
+            stake = stake.amount,
+            recipient = recipient_info.key.to_string(),
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Set the stake executors must bond to execute, 0 to stop requiring one
+    pub fn process_set_min_executor_stake(
+        program_id: &Pubkey,
+        args: StakeArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.min_executor_stake = args.amount;
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Set the treasury's cut of every lamport fee
+    pub fn process_set_protocol_fee(
+        program_id: &Pubkey,
+        args: ProtocolFeeArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if args.protocol_fee_bps > MAX_PROTOCOL_FEE_BPS {
+            return Err(GsnError::InvalidProtocolFee.into());
+        }
+
+        config.protocol_fee_bps = args.protocol_fee_bps;
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::ProtocolFeeSet {
+                protocol_fee_bps: args.protocol_fee_bps,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Keep `args.insurance_fee_bps` of every lamport fee for the insurance
+    /// pool. Fails with `InvalidInsuranceFee` above `MAX_INSURANCE_FEE_BPS`.
+    /// Accounts: config (w), authority (signer).
+    pub fn process_set_insurance_fee(
+        program_id: &Pubkey,
+        args: InsuranceFeeArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if args.insurance_fee_bps > MAX_INSURANCE_FEE_BPS {
+            return Err(GsnError::InvalidInsuranceFee.into());
+        }
+
+        config.insurance_fee_bps = args.insurance_fee_bps;
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::InsuranceFeeSet {
+                insurance_fee_bps: args.insurance_fee_bps,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Pay `args.amount` of the insurance pool out of the vault to a consumer
+    /// whose execution of `args.lane` and `args.nonce` governance judged
+    /// faulty. Allowed while paused, since that is when incidents are settled.
+    /// Fails with `UnknownExecution` unless the execution is recorded in
+    /// `transaction_executor`, or is a nonce the channel with
+    /// `args.channel_executor` went past, within the `record_retention`
+    /// latest of its lane; with `AlreadyCompensated` when it was paid for
+    /// already, and with `InsufficientFunds` beyond the pool. Accounts: GSN state (w), config, authority (signer), consumer (w),
+    /// vault (w), system program.
+    pub fn process_compensate(
+        program_id: &Pubkey,
+        args: CompensateArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+        validation::check_system_program(system_program_info)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        // Only executions the program still keeps a record of are
+        // compensated, and only once
+        let executor = gsn
+            .compensable_executor(
+                consumer_info.key,
+                args.lane,
+                args.nonce,
+                args.channel_executor.as_ref(),
+                config.record_retention as usize,
+            )
+            .ok_or(GsnError::UnknownExecution)?;
+        if gsn.is_compensated(consumer_info.key, args.lane, args.nonce, &executor) {
+            return Err(GsnError::AlreadyCompensated.into());
+        }
+        gsn.check_compensation_capacity()?;
+        let remaining = gsn
+            .debit_insurance_pool(args.amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        Self::pay_from_vault(
+            program_id,
+            gsn_program_info,
+            vault_info,
+            consumer_info,
+            system_program_info,
+            args.amount,
+        )?;
+        gsn.record_compensation(consumer_info.key, args.lane, args.nonce, &executor, args.amount);
+
+        gsn_event!(
+            "COMPENSATION",
+            consumer = consumer_info.key.to_string(),
+            lane = args.lane,
+            nonce = args.nonce,
+            amount = args.amount,
+            insurance_pool = remaining,
+        );
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::ConsumerCompensated {
+                consumer: *consumer_info.key,
+                lane: args.lane,
+                nonce: args.nonce,
+                amount: args.amount,
+                remaining,
+            },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Turn the free tier on, charging the first `free_tx_quota` submissions
+    /// of every consumer to the subsidy's balance, or off with a quota of 0
+    pub fn process_set_free_tier(
+        program_id: &Pubkey,
+        args: SetFreeTierArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.free_tier = match args.free_tx_quota {
+            0 => None,
+            free_tx_quota => Some(FreeTier {
+                free_tx_quota,
+                subsidy: Pubkey::new_from_array(args.subsidy),
+            }),
+        };
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Replace the volume discount tiers, failing with `InvalidVolumeDiscounts`
+    /// unless they are ordered. Consumers' submissions are only counted while
+    /// tiers are set.
+    pub fn process_set_volume_discounts(
+        program_id: &Pubkey,
+        args: SetVolumeDiscountsArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if !VolumeDiscountTier::are_valid(&args.tiers) {
+            return Err(GsnError::InvalidVolumeDiscounts.into());
+        }
+
+        config.volume_discounts = args.tiers;
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Retain the hashes of the last `retention` executed payloads, rejecting
+    /// duplicates of them. Accounts: GSN state (w), config, authority (signer).
+    pub fn process_set_payload_retention(
+        program_id: &Pubkey,
+        args: SetPayloadRetentionArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if args.retention > MAX_PAYLOAD_RETENTION {
+            return Err(GsnError::InvalidPayloadRetention.into());
+        }
+
+        gsn.check_payload_retention_capacity(args.retention as usize)?;
+        gsn.set_payload_retention(args.retention as usize);
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Set how many executor records of every consumer's lane `PruneRecords`
+    /// keeps, 0 to keep them all. Accounts: config (w), authority (signer).
+    pub fn process_set_record_retention(
+        program_id: &Pubkey,
+        args: SetRecordRetentionArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.record_retention = args.retention;
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Drop the executor records of every consumer's lane but the
+    /// `record_retention` latest, making room for new entries in the state
+    /// account. Anyone may call it; without a retention set it changes
+    /// nothing. Accounts: GSN state (w), config.
+    pub fn process_prune_records(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if config.record_retention == 0 {
+            return Ok(());
+        }
+
+        let pruned = gsn.prune_transaction_executors(config.record_retention as usize);
+        gsn_event!(
+            "RECORDS_PRUNED",
+            pruned = pruned,
+            remaining = gsn.transaction_executor.len(),
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Rewrite the state account in the layout of [`STATE_VERSION`], and tag a
+    /// config account written before accounts had discriminators. Accounts:
+    /// GSN state (w), config (w), authority (signer).
+    pub fn process_migrate_state(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_program_account(program_id, config_info)?;
+        // The state is rewritten whole, so no ledger account is loaded into it
+        let mut gsn = Self::load_state(program_id, gsn_program_info, &[])?;
+        if !gsn.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let (config, tagged) = match GsnConfig::deserialize(&config_info.data.borrow()) {
+            Ok(config) => (config, true),
+            Err(_) => (GsnConfig::deserialize_untagged(&config_info.data.borrow())?, false),
+        };
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        if !tagged {
+            config.serialize(&mut config_info.data.borrow_mut())?;
+        }
+        let version = gsn.migrate();
+        gsn_event!(
+            "STATE_MIGRATED",
+            from_version = version,
+            to_version = STATE_VERSION,
+            config_tagged = !tagged,
+        );
+        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+    }
+
+    /// Bind intents to the cluster with `genesis_hash`, so intents signed for
+    /// another cluster stop verifying. Accounts: config (w), authority (signer).
+    pub fn process_set_genesis_hash(
+        program_id: &Pubkey,
+        args: SetGenesisHashArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.genesis_hash = Some(args.genesis_hash);
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Pay `amount` of the treasury out of the vault to `destination`
+    pub fn process_withdraw_treasury(
+        program_id: &Pubkey,
+        args: WithdrawArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+        validation::check_system_program(system_program_info)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let remaining = gsn
+            .debit_treasury(args.amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        Self::pay_from_vault(
+            program_id,
+            gsn_program_info,
+            vault_info,
+            destination_info,
+            system_program_info,
+            args.amount,
+        )?;
+
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::TreasuryWithdrawn {
+                destination: *destination_info.key,
+                amount: args.amount,
+                remaining,
+            },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Check `stake_info` is the stake account of the executor and return its bump
+    fn check_stake_account(
+        program_id: &Pubkey,
+        gsn_program_info: &AccountInfo,
+        executor_info: &AccountInfo,
+        stake_info: &AccountInfo,
+    ) -> Result<u8, ProgramError> {
+        let (stake_key, bump) =
+            GsnInfo::find_stake_address(program_id, gsn_program_info.key, executor_info.key);
+        if *stake_info.key != stake_key {
+            return Err(GsnError::InvalidStakeAccount.into());
+        }
+        validation::check_writable(stake_info)?;
+        Ok(bump)
+    }
+
+    /// Require executors to be registered, or let any executor execute again
+    pub fn process_set_executor_registry(
+        program_id: &Pubkey,
+        args: SetExecutorRegistryArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.registered_executors_only = args.required != 0;
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Turn sandbox mode on, charging fees to `subsidy` or waiving them, or
+    /// off. Only builds with the `sandbox` feature, meant for devnet, accept it.
+    pub fn process_set_sandbox(
+        program_id: &Pubkey,
+        args: SetSandboxArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if !cfg!(feature = "sandbox") {
+            return Err(GsnError::SandboxUnavailable.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        config.sandbox = match args.enabled {
+            0 => None,
+            _ => Some(SandboxConfig {
+                subsidy: match args.has_subsidy {
+                    0 => None,
+                    _ => Some(Pubkey::new_from_array(args.subsidy)),
+                },
+                reset_epoch: Clock::get()?.epoch,
+            }),
+        };
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Reset the registries of a sandbox once per epoch, keeping the
+    /// subsidy's balance and the executors' earnings. Anyone may crank it;
+    /// a second reset in the same epoch does nothing.
+    pub fn process_reset_sandbox(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        let sandbox = match config.sandbox.as_mut() {
+            Some(sandbox) => sandbox,
+            None => return Err(GsnError::SandboxUnavailable.into()),
+        };
+
+        let epoch = Clock::get()?.epoch;
+        if epoch <= sandbox.reset_epoch {
+            return Ok(());
+        }
+
+        gsn_event!(
+            "SANDBOX_RESET",
+            epoch = epoch,
+            previous_epoch = sandbox.reset_epoch,
+            consumers = gsn.consumer.len(),
+        );
+
+        gsn.reset_registries(sandbox.subsidy.as_ref());
+        sandbox.reset_epoch = epoch;
+        Self::store_state(&mut gsn, gsn_program_info, accounts)?;
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Start the terminal shutdown of a deployment, paying its residue to
+    /// `recovery` in the end. Top-ups stop at once and submissions after
+    /// `SHUTDOWN_TIMELOCK_SECS`, leaving consumers time to withdraw and
+    /// executors to claim; there is no way back.
+    pub fn process_initiate_shutdown(
+        program_id: &Pubkey,
+        args: InitiateShutdownArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if config.shutdown.is_some() {
+            return Err(GsnError::ShutDown.into());
+        }
+
+        let shutdown = ShutdownState::new(
+            Pubkey::new_from_array(args.recovery),
+            Clock::get()?.unix_timestamp,
+        );
+        gsn_event!(
+            "SHUTDOWN_INITIATED",
+            recovery = shutdown.recovery.to_string(),
+            effective_at = shutdown.effective_at,
+            sweep_at = shutdown.sweep_at,
+        );
+
+        config.shutdown = Some(shutdown);
+        config.serialize(&mut config_info.data.borrow_mut())
+    }
+
+    /// Pay `amount` lamports of a consumer's unspent top-up balance back to
+    /// it. Withdrawals are open whether or not the deployment is shutting down.
+    /// With a withdrawal cooldown set, `amount` comes out of a request that
+    /// cooled down, unless emergency withdrawals are enabled.
+    pub fn process_withdraw(
+        program_id: &Pubkey,
+        args: WithdrawArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if args.amount == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        validation::check_system_program(system_program_info)?;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        let emergency = gsn.is_emergency_withdrawal();
+        if !emergency {
+            gsn.check_not_paused()?;
+        }
+        let previous_balance = gsn.consumer.get(consumer_info.key).copied().unwrap_or(0);
+        let new_balance = previous_balance
+            .checked_sub(args.amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        if gsn.withdrawal_policy.cooldown_slots > 0 && !emergency {
+            gsn.take_withdrawal_request(consumer_info.key, args.amount, Clock::get()?.slot)?;
+        }
+
+        Self::pay_from_vault(
+            program_id,
+            gsn_program_info,
+            vault_info,
+            consumer_info,
+            system_program_info,
+            args.amount,
+        )?;
+        // An emptied balance leaves the registry, its nonces stay
+        if new_balance == 0 {
+            gsn.consumer_mut().remove(consumer_info.key);
+        } else {
+            gsn.consumer_mut().insert(*consumer_info.key, new_balance);
+        }
+
+        gsn_event!(
+            "WITHDRAW",
+            consumer = consumer_info.key.to_string(),
+            amount = args.amount,
+            previous_balance = previous_balance,
+            new_balance = new_balance,
+        );
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Start the cooldown of a withdrawal of `amount` lamports of the caller's
+    /// balance, replacing its previous request. Accounts: GSN state (w),
+    /// consumer (signer, w), then the system program to grow the state.
+    pub fn process_request_withdrawal(
+        program_id: &Pubkey,
+        args: WithdrawArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        if args.amount == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let consumer_info = next_account_info(account_info_iter)?;
+
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if args.amount > gsn.consumer.get(consumer_info.key).copied().unwrap_or(0) {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        let growth = Self::state_growth(
+            &mut gsn,
+            gsn_program_info,
+            consumer_info,
+            Self::find_system_program(accounts),
+        );
+        gsn.check_withdrawal_request_capacity(consumer_info.key)?;
+
+        let request = WithdrawalRequest {
+            amount: args.amount,
+            unlock_slot: Clock::get()?
+                .slot
+                .saturating_add(gsn.withdrawal_policy.cooldown_slots),
+        };
+        gsn.withdrawal_requests_mut().insert(*consumer_info.key, request);
+
+        gsn_event!(
+            "WITHDRAWAL_REQUESTED",
+            consumer = consumer_info.key.to_string(),
+            amount = request.amount,
+            unlock_slot = request.unlock_slot,
+        );
+        Self::store_state_growing(&mut gsn, gsn_program_info, accounts, growth.as_ref())
+    }
+
+    /// Set the slots consumers wait between requesting a withdrawal and
+    /// making it; 0 lets them withdraw without a request. Accounts: GSN state
+    /// (w), config, authority (signer).
+    pub fn process_set_withdrawal_cooldown(
+        program_id: &Pubkey,
+        args: SetWithdrawalCooldownArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        gsn.withdrawal_policy_mut().cooldown_slots = args.slots;
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::WithdrawalCooldownSet { slots: args.slots },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Pause the deployment once more than `args.max_failure_bps` of the
+    /// epoch's executions failed, judged from `args.min_executions`
+    /// executions on; 0 basis points turns the breaker off. The epoch's
+    /// counts are kept. Fails with `InvalidInstructionData` above 10,000
+    /// basis points. Accounts: GSN state (w), config, authority (signer).
+    pub fn process_set_circuit_breaker(
+        program_id: &Pubkey,
+        args: SetCircuitBreakerArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if args.max_failure_bps > 10_000 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let breaker = gsn.circuit_breaker_mut();
+        breaker.max_failure_bps = args.max_failure_bps;
+        breaker.min_executions = args.min_executions;
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::CircuitBreakerSet {
+                max_failure_bps: args.max_failure_bps,
+                min_executions: args.min_executions,
+            },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Count executions by epoch from the current one on, or stop counting
+    /// and drop the counters. Accounts: GSN state (w), config, authority
+    /// (signer).
+    pub fn process_set_stats_tracking(
+        program_id: &Pubkey,
+        args: SetStatsTrackingArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        gsn.set_stats_tracking(args.enabled, Clock::get()?.epoch);
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::StatsTrackingSet {
+                enabled: args.enabled,
+            },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Credit up to `args.amount` lamports of the treasury to the executors
+    /// of the last epoch the stats counted, before the current one, by
+    /// `GsnInfo::reward_shares`; the rounding stays in the treasury. Fails
+    /// with `RewardsAlreadyDistributed` for an epoch rewarded already,
+    /// `NoRewardRecipients` without an eligible executor, and
+    /// `InsufficientFunds` beyond the treasury. Accounts: GSN state (w),
+    /// config, authority (signer), then the ledger accounts of the rewarded
+    /// executors that opened one.
+    pub fn process_distribute_rewards(
+        program_id: &Pubkey,
+        args: DistributeRewardsArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        // Only an epoch that is over is rewarded
+        let (_, epoch_stats) = gsn.stats.as_of(Clock::get()?.epoch);
+        if gsn.rewarded_epoch.is_some_and(|epoch| epoch >= epoch_stats.epoch) {
+            return Err(GsnError::RewardsAlreadyDistributed.into());
+        }
+        if args.amount > gsn.treasury {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        let shares = gsn.reward_shares(&epoch_stats, config.min_executor_stake, args.amount);
+        if shares.is_empty() {
+            return Err(GsnError::NoRewardRecipients.into());
+        }
+        let executors: Vec<&Pubkey> = shares.iter().map(|(executor, _)| executor).collect();
+        for executor in &executors {
+            if gsn.ledger_flags(executor) & EXECUTOR_LEDGER != 0 && !gsn.executor.contains_key(executor) {
+                return Err(GsnError::MissingLedgerAccount.into());
+            }
+        }
+        gsn.check_reward_capacity(&executors)?;
+
+        let distributed: u64 = shares.iter().map(|(_, share)| share).sum();
+        gsn.debit_treasury(distributed)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        for (executor, share) in &shares {
+            gsn_event!(
+                "EXECUTOR_REWARD",
+                executor = executor.to_string(),
+                epoch = epoch_stats.epoch,
+                reward = *share,
+            );
+            Self::credit_executor(&mut gsn, executor, *share);
+        }
+        gsn.set_rewarded_epoch(epoch_stats.epoch);
+
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::RewardsDistributed {
+                epoch: epoch_stats.epoch,
+                amount: distributed,
+                executors: shares.len() as u64,
+            },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Count the relay of `args.nonce` on `args.consumer`'s `args.lane`,
+    /// which failed in the target program, towards the circuit breaker. A
+    /// failed submission reverts along with everything it wrote, so the
+    /// executor that sent it reports it in a transaction of its own. Only
+    /// registered executors report, only a nonce the lane of a consumer with
+    /// a balance still expects, once per epoch and at most
+    /// `MAX_FAILURE_REPORTS_PER_EXECUTOR` times per executor and epoch; see
+    /// `GsnInfo::check_failure_report`. Does nothing while the breaker is
+    /// off. Accounts: GSN state (w), executor (signer).
+    pub fn process_report_failure(
+        program_id: &Pubkey,
+        args: ReportFailureArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if !gsn.circuit_breaker.is_enabled() {
+            return Ok(());
+        }
+        gsn.check_not_blacklisted(executor_info.key)?;
+        if !gsn.is_executor_registered(executor_info.key) {
+            return Err(GsnError::Unauthorized.into());
+        }
+        let epoch = Clock::get()?.epoch;
+        gsn.check_failure_report(epoch, &args.consumer, executor_info.key, args.lane, args.nonce)?;
+
+        gsn_event!(
+            "FAILURE_REPORT",
+            consumer = args.consumer.to_string(),
+            executor = executor_info.key.to_string(),
+            lane = args.lane,
+            nonce = args.nonce,
+        );
+        Self::record_execution_outcome(&mut gsn, false)?;
+        gsn.record_failure_report(&args.consumer, executor_info.key, args.lane, args.nonce);
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Limit every consumer to `args.max_submissions` submissions per window
+    /// of `args.window_slots` slots; 0 submissions lifts the limit and drops
+    /// the windows. Fails with `InvalidInstructionData` for a limit without
+    /// a window. Accounts: GSN state (w), config, authority (signer).
+    pub fn process_set_rate_limit(
+        program_id: &Pubkey,
+        args: SetRateLimitArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if args.max_submissions > 0 && args.window_slots == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        gsn.set_rate_limit(RateLimit {
+            max_submissions: args.max_submissions,
+            window_slots: args.window_slots,
+        });
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::RateLimitSet {
+                max_submissions: args.max_submissions,
+                window_slots: args.window_slots,
+            },
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Let consumers withdraw despite the pause and without waiting out the
+    /// cooldown, until `Unpause`. Only a paused deployment accepts it.
+    /// Accounts: GSN state (w), config, authority (signer).
+    pub fn process_enable_emergency_withdrawals(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+        if !gsn.paused {
+            return Err(GsnError::NotPaused.into());
+        }
+
+        gsn.withdrawal_policy_mut().emergency = true;
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::EmergencyWithdrawalsEnabled,
+        );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Price the token fees of `args.mint` with a Pyth price feed, or charge
+    /// them in base units again without one. Fails with `InvalidPriceFeed`
+    /// for a confidence threshold above 100% or more than `MAX_PRICE_FEEDS`
+    /// mints. Accounts: config (w), authority (signer).
+    pub fn process_set_price_feed(
+        program_id: &Pubkey,
+        args: SetPriceFeedArgs,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        if !authority_info.is_signer {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        validation::check_writable(config_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
+        if !config.is_approved_by(&signer_keys(accounts)) {
+            return Err(GsnError::Unauthorized.into());
+        }
+
+        let feed = args.feed.as_ref().map(|feed| feed.feed);
+        match args.feed {
+            Some(feed) => {
+                if feed.max_confidence_bps > 10_000
+                    || (!config.price_feeds.contains_key(&args.mint)
+                        && config.price_feeds.len() >= MAX_PRICE_FEEDS)
+                {
+                    return Err(GsnError::InvalidPriceFeed.into());
+                }
+                config.price_feeds.insert(args.mint, feed);
             }
-            GsnInstruction::ClaimFees => {
-                info!("Instruction: Claim Fees");
-                Self::process_claim_fees(accounts)
+            None => {
+                config.price_feeds.remove(&args.mint);
             }
         }
+        Self::emit_governance(
+            config_info,
+            Some(authority_info),
+            GovernanceAction::PriceFeedSet {
+                mint: args.mint,
+                feed,
+            },
+        );
+        config.serialize(&mut config_info.data.borrow_mut())
     }
 
-    pub fn process_initialize(accounts: &[AccountInfo]) -> ProgramResult {
+    /// Empty the vault into the recovery address once the grace period of a
+    /// shutdown has passed, and clear the lamport balances and earnings that
+    /// were left unclaimed. Token balances are left to the vault's token
+    /// accounts, which it doesn't move. Anyone may crank it.
+    pub fn process_sweep(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let gsn_program_info = next_account_info(account_info_iter)?;
-        // Optional: authority account for governance (if provided)
-        let authority_info = next_account_info(account_info_iter).ok();
+        let config_info = next_account_info(account_info_iter)?;
+        let recovery_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
 
-        let mut gsn = GsnInfo::new();
-        
-        // If authority is provided, initialize governance
-        if let Some(auth) = authority_info {
-            if !auth.is_signer {
+        validation::check_system_program(system_program_info)?;
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
+        }
+        let config = Self::load_config(program_id, config_info)?;
+        let shutdown = match &config.shutdown {
+            Some(shutdown) if shutdown.is_sweepable(Clock::get()?.unix_timestamp) => shutdown,
+            _ => return Err(GsnError::SweepUnavailable.into()),
+        };
+        if *recovery_info.key != shutdown.recovery {
+            return Err(GsnError::InvalidState.into());
+        }
+
+        let residue = vault_info.lamports();
+        Self::pay_from_vault(
+            program_id,
+            gsn_program_info,
+            vault_info,
+            recovery_info,
+            system_program_info,
+            residue,
+        )?;
+
+        gsn_event!(
+            "SHUTDOWN_SWEEP",
+            recovery = recovery_info.key.to_string(),
+            amount = residue,
+            consumers = gsn.consumer.len(),
+            executors = gsn.executor.len(),
+        );
+
+        gsn.clear_balances();
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
+
+    /// Register the address an executor's fees may be claimed to, so a hot
+    /// executor key can sweep them to a cold wallet. Replacing a registered
+    /// address takes its signature too, so a leaked executor key can't
+    /// redirect the fees.
+    pub fn process_set_payout_address(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let payout_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        if let Some(current) = gsn.payout_address(executor_info.key) {
+            let current_info = next_account_info(account_info_iter)?;
+            if current_info.key != current || !current_info.is_signer {
+                gsn_event!(
+                    "PAYOUT_ADDRESS_FAILED",
+                    executor = executor_info.key.to_string(),
+                    reason = "current_payout_not_signer",
+                );
                 return Err(GsnError::Unauthorized.into());
             }
-            gsn.initialize_governance(*auth.key);
         }
+        gsn.check_payout_capacity(executor_info.key)?;
+        gsn.set_payout_address(*executor_info.key, *payout_info.key);
 
-        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+        gsn_event!(
+            "PAYOUT_ADDRESS_SET",
+            executor = executor_info.key.to_string(),
+            payout = payout_info.key.to_string(),
+        );
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
     }
 
-    pub fn process_topup(amount: u64, accounts: &[AccountInfo]) -> ProgramResult {
+    /// Burn a nonce of the consumer's lane. Accounts: GSN state (w), consumer
+    /// (signer), then the executor whose channel it is for `CHANNEL_NONCE_LANE`.
+    pub fn process_cancel_nonce(
+        program_id: &Pubkey,
+        nonce: u64,
+        lane: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let gsn_program_info = next_account_info(account_info_iter)?;
         let consumer_info = next_account_info(account_info_iter)?;
 
-        let mut gsn = GsnInfo::deserialize(gsn_program_info.data.borrow().as_ref())?;
-
-        // TODO: deduct amount
-
-        let previous_balance = gsn.consumer.get(&consumer_info.key.to_string()).copied().unwrap_or(0);
-        let new_balance;
+        if !consumer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if !is_valid_nonce_lane(lane) {
+            return Err(GsnError::InvalidNonceLane.into());
+        }
 
-        if gsn.consumer.contains_key(&consumer_info.key.to_string()) {
-            match gsn.consumer.get(&consumer_info.key.to_string()) {
-                Some(current_topup) => {
-                    let val = current_topup + amount;
-                    gsn.consumer
-                        .entry(consumer_info.key.to_string())
-                        .or_insert(val);
-                    new_balance = val;
-                }
-                None => {
-                    println!("has no value");
-                    gsn.add_consumer(consumer_info.key.to_string(), amount);
-                    new_balance = amount;
-                }
-            }
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        let consumer_key = consumer_info.key.to_string();
+        let result = if lane == CHANNEL_NONCE_LANE {
+            let executor_info = next_account_info(account_info_iter)?;
+            gsn.check_nonce_capacity(consumer_info.key, lane, executor_info.key)?;
+            gsn.cancel_channel_nonce(consumer_info.key, executor_info.key, nonce)
         } else {
-            gsn.add_consumer(consumer_info.key.to_string(), amount);
-            new_balance = amount;
+            gsn.check_nonce_capacity(consumer_info.key, lane, consumer_info.key)?;
+            gsn.cancel_lane_nonce(consumer_info.key, lane, nonce)
+        };
+        if let Err(error) = result {
+            gsn_event!(
+                "NONCE_CANCEL_FAILED",
+                consumer = consumer_key,
+                lane = lane,
+                nonce = nonce,
+                reason = if error == GsnError::ReplayAttack { "nonce_used" } else { "invalid_nonce" },
+            );
+            return Err(error.into());
         }
 
-        msg!(
-            "[TOPUP] consumer={} amount={} previous_balance={} new_balance={}",
-            consumer_info.key.to_string(),
-            amount,
-            previous_balance,
-            new_balance
+        gsn_event!(
+            "NONCE_CANCELLED",
+            consumer = consumer_key,
+            lane = lane,
+            nonce = nonce,
         );
 
-        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
     }
 
-    pub fn process_submit_tx(amount: u64, nonce: u64, accounts: &[AccountInfo]) -> ProgramResult {
+    pub fn process_claim_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let target_program_info = next_account_info(account_info_iter)?;
-        let sender_info = next_account_info(account_info_iter)?;
-        let reciever_info = next_account_info(account_info_iter)?;
-        let fee_payer_info = next_account_info(account_info_iter)?;
         let gsn_program_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
 
-        let mut gsn = GsnInfo::deserialize(&gsn_program_info.data.borrow())?;
-
-        let sender_key = sender_info.key.to_string();
-
-        // Check if consumer exists
-        if !gsn.consumer.contains_key(&sender_key) {
-            return Err(ProgramError::InvalidInstructionData);
+        // SECURITY CHECK: Only the executor can claim their own fees
+        if !executor_info.is_signer {
+            gsn_event!(
+                "EXECUTOR_CLAIM_FAILED",
+                executor = executor_info.key.to_string(),
+                reason = "not_signer",
+            );
+            return Err(GsnError::UnauthorizedFeeClaim.into());
         }
+        validation::check_system_program(system_program_info)?;
 
-        // SECURITY CHECK 1: Verify nonce to prevent replay attacks
-        let expected_nonce = gsn.get_next_nonce(&sender_key);
-        if nonce != expected_nonce {
-            return Err(GsnError::InvalidNonce.into());
-        }
+        let executor_key = executor_info.key.to_string();
 
-        // Additional replay protection: check if nonce was already used
-        if gsn.is_nonce_used(&sender_key, nonce) {
-            return Err(GsnError::ReplayAttack.into());
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        if let Err(error) = gsn.check_not_blacklisted(executor_info.key) {
+            gsn_event!(
+                "EXECUTOR_CLAIM_FAILED",
+                executor = executor_key,
+                reason = "blacklisted",
+            );
+            return Err(error.into());
         }
 
-        // Calculate fee using governance configuration
-        let fee = gsn.calculate_fee(amount);
+        // Verify the executor is claiming fees to its own account or payout address
+        if !gsn.is_claim_destination(executor_info.key, destination_info.key) {
+            gsn_event!(
+                "EXECUTOR_CLAIM_FAILED",
+                executor = executor_key,
+                destination = destination_info.key.to_string(),
+                reason = "destination_mismatch",
+            );
+            return Err(GsnError::UnauthorizedFeeClaim.into());
+        }
 
-        // SECURITY CHECK 2: Verify top-up balance covers expected fee BEFORE execution
-        let current_balance = gsn.consumer.get(&sender_key)
+        // Get the executor's earned fees
+        let earned_fees = gsn.executor
+            .get(executor_info.key)
             .copied()
-            .ok_or(GsnError::InsufficientBalance)?;
-        
-        if current_balance < fee {
-            msg!(
-                "[EXECUTION_FAILED] reason=insufficient_balance consumer={} required_fee={} available_balance={}",
-                sender_key,
-                fee,
-                current_balance
+            .unwrap_or(0);
+
+        if earned_fees == 0 {
+            gsn_event!(
+                "EXECUTOR_CLAIM_FAILED",
+                executor = executor_key,
+                reason = "insufficient_funds",
+                earned_fees = 0,
             );
-            return Err(GsnError::InsufficientBalance.into());
+            return Err(ProgramError::InsufficientFunds);
         }
 
-        msg!(
-            "[EXECUTION_START] consumer={} executor={} amount={} fee={} nonce={}",
-            sender_key,
-            fee_payer_info.key.to_string(),
-            amount,
-            fee,
-            nonce
+        gsn_event!(
+            "EXECUTOR_CLAIM_START",
+            executor = executor_key,
+            amount = earned_fees,
         );
 
-        // Execute the transaction
-        let inst = system_instruction::transfer(&sender_info.key, &reciever_info.key, amount);
-
-        match invoke(
-            &inst,
-            &[
-                sender_info.clone(),
-                reciever_info.clone(),
-                target_program_info.clone(),
-            ],
+        // Transfer fees to executor out of the vault backing the balances
+        match Self::pay_from_vault(
+            program_id,
+            gsn_program_info,
+            vault_info,
+            destination_info,
+            system_program_info,
+            earned_fees,
         ) {
-            Ok(_) => {
-                msg!(
-                    "[EXECUTION_SUCCESS] consumer={} executor={} amount={}",
-                    sender_key,
-                    fee_payer_info.key.to_string(),
-                    amount
+            Ok(_) => {}
+            Err(error) => {
+                gsn_event!(
+                    "EXECUTOR_CLAIM_FAILED",
+                    executor = executor_key,
+                    amount = earned_fees,
+                    error = format!("{:?}", error),
                 );
+                return Err(error);
+            }
+        }
 
-                // SECURITY CHECK 3: Record transaction-executor mapping before updating balances
-                gsn.record_transaction_executor(&sender_key, nonce, &fee_payer_info.key.to_string());
-                
-                // Increment nonce to prevent replay
-                gsn.increment_nonce(&sender_key);
-
-                // Update executor balance
-                let executor_previous_balance = gsn.executor.get(&fee_payer_info.key.to_string()).copied().unwrap_or(0);
-                let executor_new_balance;
-                if gsn.executor.contains_key(&fee_payer_info.key.to_string()) {
-                    match gsn.executor.get(&fee_payer_info.key.to_string()) {
-                        Some(earned_amount) => {
-                            let val = earned_amount + fee;
-                            gsn.executor
-                                .entry(fee_payer_info.key.to_string())
-                                .or_insert(val);
-                            executor_new_balance = val;
-                        }
-                        None => {
-                            println!("has no value");
-                            gsn.add_executor(fee_payer_info.key.to_string(), fee);
-                            executor_new_balance = fee;
-                        }
-                    }
-                } else {
-                    gsn.add_executor(fee_payer_info.key.to_string(), fee);
-                    executor_new_balance = fee;
-                }
+        // Reset executor's earned balance
+        gsn.executor_mut().insert(*executor_info.key, 0);
 
-                // Deduct fee from consumer balance
-                let val = current_balance - fee;
-                gsn.consumer
-                    .entry(sender_key.clone())
-                    .or_insert(val);
+        event::emit(FeeClaimEvent {
+            executor: *executor_info.key,
+            destination: *destination_info.key,
+            mint: None,
+            amount: earned_fees,
+        });
 
-                msg!(
-                    "[FEE_DEDUCTION] consumer={} fee={} previous_balance={} new_balance={}",
-                    sender_key,
-                    fee,
-                    current_balance,
-                    val
-                );
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
+    }
 
-                msg!(
-                    "[EXECUTOR_CREDIT] executor={} fee={} previous_balance={} new_balance={}",
-                    fee_payer_info.key.to_string(),
-                    fee,
-                    executor_previous_balance,
-                    executor_new_balance
-                );
-            }
-            Err(error) => {
-                msg!(
-                    "[EXECUTION_FAILED] consumer={} executor={} error={:?}",
-                    sender_key,
-                    fee_payer_info.key.to_string(),
-                    error
-                );
-                return Err(error);
+    /// Pays an executor's earnings in one mint, read from the vault's token
+    /// account, to a token account of that mint held by the executor or its
+    /// payout address
+    pub fn process_claim_token_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let executor_token_info = next_account_info(account_info_iter)?;
+        let vault_token_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        if !executor_info.is_signer {
+            return Err(GsnError::UnauthorizedFeeClaim.into());
+        }
+
+        let bump = Self::check_vault(program_id, gsn_program_info, vault_info)?;
+        let mint = Self::check_token_account(
+            vault_token_info,
+            token_program_info,
+            vault_info.key,
+            None,
+        )?
+        .mint;
+
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        gsn.check_not_blacklisted(executor_info.key)?;
+        // Earnings are only paid to the executor's or its payout address's token account
+        if let Err(error) = Self::check_token_account(
+            executor_token_info,
+            token_program_info,
+            executor_info.key,
+            Some(&mint),
+        ) {
+            match gsn.payout_address(executor_info.key) {
+                Some(payout) => {
+                    Self::check_token_account(
+                        executor_token_info,
+                        token_program_info,
+                        payout,
+                        Some(&mint),
+                    )?;
+                }
+                None => return Err(error),
             }
         }
+        let key = (*executor_info.key, mint);
+        let earned_fees = gsn.token_executor.get(&key).copied().unwrap_or(0);
+        if earned_fees == 0 {
+            return Err(ProgramError::InsufficientFunds);
+        }
 
-        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+        // The executor bears the transfer fee of Token-2022 mints that have one
+        let mint_info = next_account_info(account_info_iter).ok();
+        let mint_account = mint_info
+            .map(|mint_info| Self::check_mint(mint_info, token_program_info, &mint))
+            .transpose()?;
+
+        let mut transfer_accounts = vec![
+            vault_token_info.clone(),
+            executor_token_info.clone(),
+            vault_info.clone(),
+            token_program_info.clone(),
+        ];
+        transfer_accounts.extend(mint_info.cloned());
+        invoke_signed(
+            &Self::token_transfer(
+                token_program_info,
+                vault_token_info,
+                executor_token_info,
+                vault_info,
+                mint_info.zip(mint_account.as_ref()),
+                earned_fees,
+            ),
+            &transfer_accounts,
+            &[&[VAULT_SEED, gsn_program_info.key.as_ref(), &[bump]]],
+        )?;
+        gsn.token_executor_mut().remove(&key);
+
+        event::emit(FeeClaimEvent {
+            executor: *executor_info.key,
+            destination: *executor_token_info.key,
+            mint: Some(mint),
+            amount: earned_fees,
+        });
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
     }
 
-    pub fn process_update_fee_params(
-        args: UpdateFeeParamsArgs,
-        accounts: &[AccountInfo],
-    ) -> ProgramResult {
+    /// Pays an executor's wrapped SOL earnings into its own wrapped SOL
+    /// token account, then closes that account so the lamports land in
+    /// `destination`, the executor or its payout address, unwrapped
+    pub fn process_claim_wrapped_sol(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let gsn_program_info = next_account_info(account_info_iter)?;
-        let authority_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let executor_token_info = next_account_info(account_info_iter)?;
+        let vault_token_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
 
-        if !authority_info.is_signer {
-            return Err(GsnError::Unauthorized.into());
+        if !executor_info.is_signer {
+            return Err(GsnError::UnauthorizedFeeClaim.into());
         }
 
-        let mut gsn = GsnInfo::deserialize(&gsn_program_info.data.borrow())?;
+        let bump = Self::check_vault(program_id, gsn_program_info, vault_info)?;
+        Self::check_token_account(
+            vault_token_info,
+            token_program_info,
+            vault_info.key,
+            Some(&token::NATIVE_MINT),
+        )?;
+        // The executor closes the account, so it must hold it
+        Self::check_token_account(
+            executor_token_info,
+            token_program_info,
+            executor_info.key,
+            Some(&token::NATIVE_MINT),
+        )?;
 
-        if !gsn.is_authority(authority_info.key) {
-            return Err(GsnError::Unauthorized.into());
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        gsn.check_not_blacklisted(executor_info.key)?;
+        if !gsn.is_claim_destination(executor_info.key, destination_info.key) {
+            return Err(GsnError::UnauthorizedFeeClaim.into());
+        }
+        let key = (*executor_info.key, token::NATIVE_MINT);
+        let earned_fees = gsn.token_executor.get(&key).copied().unwrap_or(0);
+        if earned_fees == 0 {
+            return Err(ProgramError::InsufficientFunds);
         }
 
-        let fee_mode = match args.fee_mode_type {
-            0 => FeeMode::Fixed(args.fee_value),
-            1 => {
-                if args.fee_value > 10000 {
-                    return Err(GsnError::InvalidFeeMode.into());
-                }
-                FeeMode::Percent(args.fee_value as u16)
-            }
-            _ => return Err(GsnError::InvalidFeeMode.into()),
-        };
+        invoke_signed(
+            &token::transfer(
+                token_program_info.key,
+                vault_token_info.key,
+                executor_token_info.key,
+                vault_info.key,
+                earned_fees,
+            ),
+            &[
+                vault_token_info.clone(),
+                executor_token_info.clone(),
+                vault_info.clone(),
+                token_program_info.clone(),
+            ],
+            &[&[VAULT_SEED, gsn_program_info.key.as_ref(), &[bump]]],
+        )?;
+        invoke(
+            &token::close_account(
+                token_program_info.key,
+                executor_token_info.key,
+                destination_info.key,
+                executor_info.key,
+            ),
+            &[
+                executor_token_info.clone(),
+                destination_info.clone(),
+                executor_info.clone(),
+                token_program_info.clone(),
+            ],
+        )?;
+        gsn.token_executor_mut().remove(&key);
 
-        gsn.update_fee_params(fee_mode);
-        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+        event::emit(FeeClaimEvent {
+            executor: *executor_info.key,
+            destination: *destination_info.key,
+            mint: Some(token::NATIVE_MINT),
+            amount: earned_fees,
+        });
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
     }
 
-    pub fn process_add_allowed_token(
-        args: TokenMintArgs,
+    /// Pays an executor's earnings in one mint into its own token account of
+    /// the mint, swaps them to SOL through the Jupiter route it passed, with
+    /// the executor as the swap's authority, and unwraps the SOL to the
+    /// executor or its payout address. The vault never signs the route; the
+    /// claim fails unless the route delivered `min_lamports` into the
+    /// executor's wrapped SOL account.
+    pub fn process_claim_token_fees_as_sol(
+        program_id: &Pubkey,
+        args: ClaimTokenFeesAsSolArgs,
         accounts: &[AccountInfo],
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let gsn_program_info = next_account_info(account_info_iter)?;
-        let authority_info = next_account_info(account_info_iter)?;
+        let executor_info = next_account_info(account_info_iter)?;
+        let executor_token_info = next_account_info(account_info_iter)?;
+        let vault_token_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let executor_wsol_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let jupiter_program_info = next_account_info(account_info_iter)?;
+        let route_infos = account_info_iter.as_slice();
 
-        if !authority_info.is_signer {
-            return Err(GsnError::Unauthorized.into());
+        if !executor_info.is_signer {
+            return Err(GsnError::UnauthorizedFeeClaim.into());
+        }
+        if *jupiter_program_info.key != swap::JUPITER_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
         }
 
-        let mut gsn = GsnInfo::deserialize(&gsn_program_info.data.borrow())?;
+        let bump = Self::check_vault(program_id, gsn_program_info, vault_info)?;
+        let mint = Self::check_token_account(
+            vault_token_info,
+            token_program_info,
+            vault_info.key,
+            None,
+        )?
+        .mint;
+        // The executor authorizes the swap and closes the wrapped SOL account,
+        // so it must hold both
+        Self::check_token_account(
+            executor_token_info,
+            token_program_info,
+            executor_info.key,
+            Some(&mint),
+        )?;
+        let wsol_token_program_info = accounts
+            .iter()
+            .find(|account| *account.key == token::TOKEN_PROGRAM_ID)
+            .ok_or(ProgramError::IncorrectProgramId)?;
+        let wsol_before = Self::check_token_account(
+            executor_wsol_info,
+            wsol_token_program_info,
+            executor_info.key,
+            Some(&token::NATIVE_MINT),
+        )?
+        .amount;
 
-        if !gsn.is_authority(authority_info.key) {
-            return Err(GsnError::Unauthorized.into());
+        let mut gsn = Self::load_state(program_id, gsn_program_info, accounts)?;
+        gsn.check_not_paused()?;
+        gsn.check_not_blacklisted(executor_info.key)?;
+        if !gsn.is_claim_destination(executor_info.key, destination_info.key) {
+            return Err(GsnError::UnauthorizedFeeClaim.into());
+        }
+        let key = (*executor_info.key, mint);
+        let earned_fees = gsn.token_executor.get(&key).copied().unwrap_or(0);
+        if earned_fees == 0 {
+            return Err(ProgramError::InsufficientFunds);
         }
 
-        let mint_pubkey = Pubkey::new_from_array(args.mint);
-        gsn.add_allowed_token(mint_pubkey.to_string());
-        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+        invoke_signed(
+            &token::transfer(
+                token_program_info.key,
+                vault_token_info.key,
+                executor_token_info.key,
+                vault_info.key,
+                earned_fees,
+            ),
+            &[
+                vault_token_info.clone(),
+                executor_token_info.clone(),
+                vault_info.clone(),
+                token_program_info.clone(),
+            ],
+            &[&[VAULT_SEED, gsn_program_info.key.as_ref(), &[bump]]],
+        )?;
+        let mut route_accounts = route_infos.to_vec();
+        route_accounts.push(jupiter_program_info.clone());
+        invoke(&swap::route(route_infos, &args.route), &route_accounts)?;
+
+        let received = load_token_account(executor_wsol_info)
+            .map(|account| account.amount.saturating_sub(wsol_before))
+            .unwrap_or(0);
+        if received < args.min_lamports {
+            gsn_event!(
+                "EXECUTOR_CLAIM_FAILED",
+                executor = executor_info.key.to_string(),
+                reason = "slippage",
+                received = received,
+                min_lamports = args.min_lamports,
+            );
+            return Err(GsnError::SwapSlippageExceeded.into());
+        }
+        invoke(
+            &token::close_account(
+                wsol_token_program_info.key,
+                executor_wsol_info.key,
+                destination_info.key,
+                executor_info.key,
+            ),
+            &[
+                executor_wsol_info.clone(),
+                destination_info.clone(),
+                executor_info.clone(),
+                wsol_token_program_info.clone(),
+            ],
+        )?;
+        gsn.token_executor_mut().remove(&key);
+
+        gsn_event!(
+            "TOKEN_FEES_SWAPPED",
+            executor = executor_info.key.to_string(),
+            mint = mint.to_string(),
+            amount = earned_fees,
+            lamports = received,
+        );
+        event::emit(FeeClaimEvent {
+            executor: *executor_info.key,
+            destination: *destination_info.key,
+            mint: Some(mint),
+            amount: earned_fees,
+        });
+
+        Self::store_state(&mut gsn, gsn_program_info, accounts)
     }
 
-    pub fn process_remove_allowed_token(
-        args: TokenMintArgs,
+    pub fn process_initialize_metrics(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let metrics_info = next_account_info(account_info_iter)?;
         let authority_info = next_account_info(account_info_iter)?;
 
         if !authority_info.is_signer {
             return Err(GsnError::Unauthorized.into());
         }
 
-        let mut gsn = GsnInfo::deserialize(&gsn_program_info.data.borrow())?;
+        validation::check_writable(config_info)?;
+        validation::check_program_account(program_id, metrics_info)?;
+        let mut config = Self::load_config(program_id, config_info)?;
 
-        if !gsn.is_authority(authority_info.key) {
+        if !config.is_approved_by(&signer_keys(accounts)) {
             return Err(GsnError::Unauthorized.into());
         }
 
-        let mint_pubkey = Pubkey::new_from_array(args.mint);
-        gsn.remove_allowed_token(&mint_pubkey.to_string());
-        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+        // A freshly created account deserializes to an uninitialized default
+        if let Ok(existing) = ProtocolMetrics::deserialize(&metrics_info.data.borrow()) {
+            if existing.is_initialized {
+                return Err(GsnError::AlreadyInUse.into());
+            }
+        }
+
+        let clock = Clock::get()?;
+        let metrics = ProtocolMetrics::new(config.gsn_account, clock.epoch);
+        metrics.serialize(&mut metrics_info.data.borrow_mut())?;
+
+        config.metrics = Some(*metrics_info.key);
+        config.serialize(&mut config_info.data.borrow_mut())
     }
 
-    pub fn process_claim_fees(accounts: &[AccountInfo]) -> ProgramResult {
+    /// Answer a `ConsumerBalance` for `consumer`. Fails with
+    /// `MissingLedgerAccount` if it opened a ledger account that isn't passed.
+    pub fn process_get_consumer_balance(
+        program_id: &Pubkey,
+        consumer: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let gsn_program_info = next_account_info(account_info_iter)?;
-        let executor_info = next_account_info(account_info_iter)?;
-        let destination_info = next_account_info(account_info_iter)?;
-        let system_program_info = next_account_info(account_info_iter)?;
-
-        // SECURITY CHECK: Only the executor can claim their own fees
-        if !executor_info.is_signer {
-            msg!(
-                "[EXECUTOR_CLAIM_FAILED] executor={} reason=not_signer",
-                executor_info.key.to_string()
-            );
-            return Err(GsnError::UnauthorizedFeeClaim.into());
-        }
-
-        let executor_key = executor_info.key.to_string();
 
-        // Verify the executor is claiming fees to their own account
-        if executor_info.key != destination_info.key {
-            msg!(
-                "[EXECUTOR_CLAIM_FAILED] executor={} destination={} reason=destination_mismatch",
-                executor_key,
-                destination_info.key.to_string()
-            );
-            return Err(GsnError::UnauthorizedFeeClaim.into());
+        let gsn = Self::read_state(program_id, gsn_program_info, accounts)?;
+        if gsn.ledger_flags(consumer) & CONSUMER_LEDGER != 0 && !gsn.consumer.contains_key(consumer) {
+            return Err(GsnError::MissingLedgerAccount.into());
         }
+        query::set_answer(&ConsumerBalance {
+            balance: gsn.consumer.get(consumer).copied(),
+            next_nonce: gsn.get_next_lane_nonce(consumer, 0),
+            free_txs_used: gsn.free_txs_used(consumer),
+            token_balances: Self::token_balances(gsn.consumer_balances(consumer)),
+        });
+        Ok(())
+    }
 
-        let mut gsn = GsnInfo::deserialize(&gsn_program_info.data.borrow())?;
-
-        // Get the executor's earned fees
-        let earned_fees = gsn.executor
-            .get(&executor_key)
-            .copied()
-            .unwrap_or(0);
+    /// Answer an `ExecutorBalance` for `executor`, like `GetConsumerBalance`
+    pub fn process_get_executor_balance(
+        program_id: &Pubkey,
+        executor: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
 
-        if earned_fees == 0 {
-            msg!(
-                "[EXECUTOR_CLAIM_FAILED] executor={} reason=insufficient_funds earned_fees=0",
-                executor_key
-            );
-            return Err(ProgramError::InsufficientFunds);
+        let gsn = Self::read_state(program_id, gsn_program_info, accounts)?;
+        if gsn.ledger_flags(executor) & EXECUTOR_LEDGER != 0 && !gsn.executor.contains_key(executor) {
+            return Err(GsnError::MissingLedgerAccount.into());
         }
+        query::set_answer(&ExecutorBalance {
+            earned: gsn.executor.get(executor).copied().unwrap_or(0),
+            payout_address: gsn.payout_address(executor).copied(),
+            token_earned: Self::token_balances(gsn.executor_balances(executor)),
+        });
+        Ok(())
+    }
 
-        msg!(
-            "[EXECUTOR_CLAIM_START] executor={} amount={}",
-            executor_key,
-            earned_fees
-        );
+    /// Per-mint `balances` without the lamport one
+    fn token_balances(balances: Vec<(Pubkey, u64)>) -> Vec<(Pubkey, u64)> {
+        balances
+            .into_iter()
+            .filter(|(mint, _)| *mint != NATIVE_FEE_TOKEN)
+            .collect()
+    }
 
-        // Transfer fees to executor
-        // Note: This assumes gsn_program_info is a program-owned account that holds SOL
-        // The account must be writable and owned by the system program or this program
-        let transfer_instruction = system_instruction::transfer(
-            gsn_program_info.key,
-            destination_info.key,
-            earned_fees,
-        );
+    /// Answer the `query::estimate_fee` of a lamport submission of `amount`
+    /// for `consumer`
+    pub fn process_get_fee_quote(
+        program_id: &Pubkey,
+        consumer: &Pubkey,
+        amount: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
 
-        match invoke(
-            &transfer_instruction,
-            &[
-                gsn_program_info.clone(),
-                destination_info.clone(),
-                system_program_info.clone(),
-            ],
-        ) {
-            Ok(_) => {
-                msg!(
-                    "[EXECUTOR_CLAIM_SUCCESS] executor={} amount={}",
-                    executor_key,
-                    earned_fees
-                );
-            }
-            Err(error) => {
-                msg!(
-                    "[EXECUTOR_CLAIM_FAILED] executor={} amount={} error={:?}",
-                    executor_key,
-                    earned_fees,
-                    error
-                );
-                return Err(error);
-            }
+        let gsn = Self::read_state(program_id, gsn_program_info, accounts)?;
+        if *config_info.key != gsn.config {
+            return Err(GsnError::InvalidConfigAccount.into());
         }
+        let config = Self::load_config(program_id, config_info)?;
 
-        // Reset executor's earned balance
-        gsn.executor.insert(executor_key.clone(), 0);
+        let epoch = if config.volume_discounts.is_empty() {
+            0
+        } else {
+            Clock::get()?.epoch
+        };
+        let estimate = query::estimate_fee(&gsn, &config, consumer, amount, None, None, epoch)?;
+        query::set_answer(&estimate);
+        Ok(())
+    }
 
-        msg!(
-            "[EXECUTOR_CLAIM_COMPLETE] executor={} claimed_amount={} remaining_balance=0",
-            executor_key,
-            earned_fees
-        );
+    /// Answer the execution counters of the current epoch and of the last
+    /// earlier one counted
+    pub fn process_get_stats(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let gsn_program_info = next_account_info(account_info_iter)?;
 
-        gsn.serialize(&mut gsn_program_info.data.borrow_mut())
+        let gsn = Self::read_state(program_id, gsn_program_info, accounts)?;
+        let (current, previous) = gsn.stats.as_of(Clock::get()?.epoch);
+        query::set_answer(&DeploymentStats {
+            enabled: gsn.stats.enabled,
+            current: current.into(),
+            previous: previous.into(),
+        });
+        Ok(())
     }
 }
 
+/// Keys of the accounts that signed an instruction, for governance approval
+fn signer_keys<'a>(accounts: &'a [AccountInfo]) -> Vec<&'a Pubkey> {
+    accounts
+        .iter()
+        .filter(|account| account.is_signer)
+        .map(|account| account.key)
+        .collect()
+}
+
 impl PrintProgramError for GsnError {
     fn print<E>(&self)
     where
         E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
     {
         match self {
-            GsnError::AlreadyInUse => info!("Error: GSN account already in use"),
-            GsnError::InvalidState => info!("Error: GSN state is not valid"),
-            GsnError::Unauthorized => info!("Error: Unauthorized - not the governance authority"),
-            GsnError::GovernanceNotInitialized => info!("Error: Governance not initialized"),
-            GsnError::InvalidFeeMode => info!("Error: Invalid fee mode"),
-            GsnError::InsufficientBalance => info!("Error: Insufficient balance in top-up account"),
-            GsnError::ReplayAttack => info!("Error: Replay attack detected"),
-            GsnError::InvalidNonce => info!("Error: Invalid nonce"),
-            GsnError::UnauthorizedFeeClaim => info!("Error: Unauthorized fee claim"),
+            GsnError::AlreadyInUse => msg!("Error: GSN account already in use"),
+            GsnError::InvalidState => msg!("Error: GSN state is not valid"),
+            GsnError::Unauthorized => msg!("Error: Unauthorized - not the governance authority"),
+            GsnError::GovernanceNotInitialized => msg!("Error: Governance not initialized"),
+            GsnError::InvalidFeeMode => msg!("Error: Invalid fee mode"),
+            GsnError::InsufficientBalance => msg!("Error: Insufficient balance in top-up account"),
+            GsnError::ReplayAttack => msg!("Error: Replay attack detected"),
+            GsnError::InvalidNonce => msg!("Error: Invalid nonce"),
+            GsnError::UnauthorizedFeeClaim => msg!("Error: Unauthorized fee claim"),
+            GsnError::InvalidMetricsAccount => msg!("Error: Invalid metrics account"),
+            GsnError::InvalidNonceLane => msg!("Error: Invalid nonce lane"),
+            GsnError::InvalidConfigAccount => msg!("Error: Invalid config account"),
+            GsnError::ConsumerRegistryFull => msg!("Error: Consumer registry full"),
+            GsnError::HistoryFull => msg!("Error: Execution history full"),
+            GsnError::InvalidNetworkFee => msg!("Error: Invalid network fee"),
+            GsnError::InvalidEscrowAccount => msg!("Error: Invalid escrow account"),
+            GsnError::EscrowInDisputeWindow => msg!("Error: Escrow in dispute window"),
+            GsnError::EscrowDisputeWindowClosed => msg!("Error: Escrow dispute window closed"),
+            GsnError::InvalidCommitment => msg!("Error: Invalid commitment"),
+            GsnError::RevealTooEarly => msg!("Error: Reveal too early"),
+            GsnError::CommitmentExpired => msg!("Error: Commitment expired"),
+            GsnError::MissingCredential => msg!("Error: Missing credential"),
+            GsnError::InvalidFeeSplit => msg!("Error: Invalid fee split"),
+            GsnError::SandboxUnavailable => msg!("Error: Sandbox unavailable"),
+            GsnError::InvalidFeeQuote => msg!("Error: Invalid fee quote"),
+            GsnError::FeeQuoteExpired => msg!("Error: Fee quote expired"),
+            GsnError::ShutDown => msg!("Error: Shut down"),
+            GsnError::SweepUnavailable => msg!("Error: Sweep unavailable"),
+            GsnError::InvalidVaultAccount => msg!("Error: Invalid vault account"),
+            GsnError::TokenNotAllowed => msg!("Error: Token not allowed"),
+            GsnError::InvalidTokenAccount => msg!("Error: Invalid token account"),
+            GsnError::InvalidIntent => msg!("Error: Invalid intent"),
+            GsnError::InvalidEthSignature => msg!("Error: Invalid Ethereum signature"),
+            GsnError::InvalidTargetInstruction => msg!("Error: Invalid target instruction"),
+            GsnError::SubmissionExpired => msg!("Error: Submission expired"),
+            GsnError::LimitExceeded => msg!("Error: Limit exceeded"),
+            GsnError::ProgramNotAllowed => msg!("Error: Program not allowed"),
+            GsnError::ProtocolPaused => msg!("Error: Protocol paused"),
+            GsnError::InvalidMultisig => msg!("Error: Invalid multisig"),
+            GsnError::FeeChangeTimelocked => {
+                msg!("Error: Fee increases must go through ProposeFeeParams")
+            }
+            GsnError::TimelockNotElapsed => msg!("Error: Fee change timelock not elapsed"),
+            GsnError::NoPendingFeeParams => msg!("Error: No fee change proposed"),
+            GsnError::ExecutorNotRegistered => msg!("Error: Executor not registered"),
+            GsnError::ExecutorNotBonded => msg!("Error: Executor not bonded"),
+            GsnError::InvalidStakeAccount => msg!("Error: Invalid stake account"),
+            GsnError::StakeUnbonding => msg!("Error: Stake still unbonding"),
+            GsnError::InvalidProtocolFee => msg!("Error: Protocol fee above the maximum"),
+            GsnError::InvalidSponsorAccount => msg!("Error: Invalid sponsor account"),
+            GsnError::SponsorPolicyViolation => msg!("Error: Sponsor policy doesn't cover the submission"),
+            GsnError::InvalidComputeUnits => msg!("Error: Compute units above the fee mode's maximum"),
+            GsnError::InvalidVolumeDiscounts => msg!("Error: Invalid volume discount tiers"),
+            GsnError::FeeTooHigh => msg!("Error: Fee above the consumer's maximum"),
+            GsnError::InvalidTip => msg!("Error: Tip on a fee the consumer doesn't pay"),
+            GsnError::DuplicatePayload => msg!("Error: Payload executed recently"),
+            GsnError::InvalidPayloadRetention => msg!("Error: Invalid payload retention"),
+            GsnError::MissingLedgerAccount => msg!("Error: Missing ledger account"),
+            GsnError::InvalidLedgerAccount => msg!("Error: Invalid ledger account"),
+            GsnError::UnsupportedStateVersion => msg!("Error: Unsupported state version"),
+            GsnError::InvalidAccountType => msg!("Error: Invalid account type"),
+            GsnError::InvalidAccountOwner => msg!("Error: Invalid account owner"),
+            GsnError::AccountNotWritable => msg!("Error: Account not writable"),
+            GsnError::InvalidSystemProgram => msg!("Error: Invalid system program"),
+            GsnError::BalanceNotEmpty => msg!("Error: Balance not empty"),
+            GsnError::WithdrawalCooldown => msg!("Error: Withdrawal cooldown"),
+            GsnError::NotPaused => msg!("Error: Protocol not paused"),
+            GsnError::InvalidPriceFeed => msg!("Error: Invalid price feed"),
+            GsnError::StalePriceFeed => msg!("Error: Price feed stale"),
+            GsnError::VaultTokenAccountMissing => msg!("Error: Vault token account missing"),
+            GsnError::SwapSlippageExceeded => msg!("Error: Swap slippage exceeded"),
+            GsnError::ReentrantCall => msg!("Error: Reentrant call"),
+            GsnError::TargetNotExecutable => msg!("Error: Target not executable"),
+            GsnError::RateLimited => msg!("Error: Rate limited"),
+            GsnError::Blacklisted => msg!("Error: Blacklisted"),
+            GsnError::RewardsAlreadyDistributed => msg!("Error: Rewards already distributed"),
+            GsnError::NoRewardRecipients => msg!("Error: No reward recipients"),
+            GsnError::InvalidInsuranceFee => msg!("Error: Invalid insurance fee"),
+            GsnError::InvalidFailureReport => msg!("Error: Invalid failure report"),
+            GsnError::UnknownExecution => msg!("Error: Unknown execution"),
+            GsnError::FailureReportLimit => msg!("Error: Failure report limit reached"),
+            GsnError::AlreadyCompensated => msg!("Error: Execution already compensated"),
+            GsnError::StaleEthRegistration => msg!("Error: Stale Ethereum registration"),
         }
     }
 }
@@ -0,0 +1,194 @@
+//! Answers of the read-side instructions.
+//!
+//! `GetConsumerBalance`, `GetExecutorBalance` and `GetFeeQuote` change no
+//! account: they write the Borsh encoding of their answer with
+//! `set_return_data`. Clients simulate them and decode the transaction's
+//! return data with `try_from_slice`, instead of deserializing the state
+//! account and reimplementing the fee rules themselves. Off-chain, the same
+//! rules are available through [`estimate_fee`], which prices fees with the
+//! [`submission_fee`] submissions are charged.
+//! A `SubmitTransaction` with `simulate` set answers a [`FeeEstimate`] the
+//! same way, and `GetStats` a [`DeploymentStats`].
+
+use crate::{
+    error::GsnError,
+    oracle::OraclePrice,
+    state::{EpochStats, ExecutorVolume, GsnConfig, GsnInfo, NATIVE_FEE_TOKEN},
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program::set_return_data, pubkey::Pubkey};
+
+/// Answer of `GetConsumerBalance`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ConsumerBalance {
+    /// Lamport top-up balance, none if the consumer has none
+    pub balance: Option<u64>,
+    /// Next nonce of lane 0
+    pub next_nonce: u64,
+    /// Submissions the free tier paid for so far
+    pub free_txs_used: u64,
+    /// Token top-up balances, by mint
+    pub token_balances: Vec<(Pubkey, u64)>,
+}
+
+/// Answer of `GetExecutorBalance`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ExecutorBalance {
+    /// Lamport fees earned and not claimed yet
+    pub earned: u64,
+    /// Address the fees may be claimed to besides the executor's own
+    pub payout_address: Option<Pubkey>,
+    /// Token fees earned and not claimed yet, by mint
+    pub token_earned: Vec<(Pubkey, u64)>,
+}
+
+/// Answer of `GetFeeQuote`: what a `SubmitTransaction` for the consumer
+/// would be charged at the current slot, before tips
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct FeeEstimate {
+    pub fee: u64,
+    /// Balance the fee would be charged to, none when it's waived
+    pub charged: Option<Pubkey>,
+    /// Fee less the protocol's share, earned by the executor
+    pub executor_fee: u64,
+}
+
+/// Counters of an epoch's executions
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct EpochSummary {
+    pub epoch: u64,
+    pub executions: u64,
+    /// Lamport fees charged, tips included
+    pub total_fees: u64,
+    /// Consumers served at least once
+    pub unique_consumers: u64,
+    /// Executions and lamport fees of every executor, by executor
+    pub executor_volumes: Vec<(Pubkey, ExecutorVolume)>,
+}
+
+impl From<EpochStats> for EpochSummary {
+    fn from(stats: EpochStats) -> Self {
+        Self {
+            epoch: stats.epoch,
+            executions: stats.executions,
+            total_fees: stats.total_fees,
+            unique_consumers: stats.consumers.len() as u64,
+            executor_volumes: stats
+                .executor_volumes
+                .iter()
+                .map(|(executor, volume)| (*executor, *volume))
+                .collect(),
+        }
+    }
+}
+
+/// Answer of `GetStats`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct DeploymentStats {
+    /// Whether governance turned the counting on, see `SetStatsTracking`
+    pub enabled: bool,
+    /// The current epoch so far
+    pub current: EpochSummary,
+    /// The last earlier epoch that was counted
+    pub previous: EpochSummary,
+}
+
+/// Fee charged for a submission whose governance or quoted fee is `fee`:
+/// discounted by the volume tier of the consumer's `epoch_txs`, when it pays
+/// lamport fees itself, plus the batch's `network_fee_share`, converted into
+/// the base units of the fee mint at `fee_price`, its oracle price and
+/// decimals, when it has a price feed. Fails with `InvalidPriceFeed` when the
+/// converted fee doesn't fit in a u64.
+pub fn submission_fee(
+    config: &GsnConfig,
+    fee: u64,
+    epoch_txs: Option<u64>,
+    network_fee_share: u64,
+    fee_price: Option<(&OraclePrice, u8)>,
+) -> Result<u64, GsnError> {
+    let fee = match epoch_txs {
+        Some(epoch_txs) => config.apply_volume_discount(fee, epoch_txs),
+        None => fee,
+    } + network_fee_share;
+    match fee_price {
+        Some((price, decimals)) => price
+            .lamports_to_tokens(fee, decimals)
+            .ok_or(GsnError::InvalidPriceFeed),
+        None => Ok(fee),
+    }
+}
+
+/// Fee of a submission of `amount` relayed for `consumer` in `epoch`, paid
+/// in `mint` (`SubmitTokenFee`) or lamports (`SubmitTransaction`) when none.
+/// Mints with a price feed are charged at `price`, read off the feed.
+///
+/// These are the rules submissions are charged by: sandbox mode, the
+/// configured fee mode, volume discounts, oracle prices and the free tier.
+/// Lamport fees leave the protocol's share to the treasury; token fees are
+/// earned whole. Fails with `TokenNotAllowed` for a mint that can't pay
+/// fees, and with `InvalidPriceFeed` for a mint with a price feed but no
+/// `price`.
+pub fn estimate_fee(
+    gsn: &GsnInfo,
+    config: &GsnConfig,
+    consumer: &Pubkey,
+    amount: u64,
+    mint: Option<&Pubkey>,
+    price: Option<&OraclePrice>,
+    epoch: u64,
+) -> Result<FeeEstimate, GsnError> {
+    if let Some(mint) = mint {
+        if *mint == NATIVE_FEE_TOKEN || !config.is_token_allowed(mint) {
+            return Err(GsnError::TokenNotAllowed);
+        }
+    }
+    let fee_price = match mint.and_then(|mint| config.price_feeds.get(mint)) {
+        Some(feed) => Some((price.ok_or(GsnError::InvalidPriceFeed)?, feed.decimals)),
+        None => None,
+    };
+
+    // Sandbox mode charges its subsidy, or waives the fee without one
+    let charged = match &config.sandbox {
+        Some(sandbox) => sandbox.subsidy,
+        None => Some(*consumer),
+    };
+    let fee = match charged {
+        None => 0,
+        Some(key) => {
+            // Volume discounts count the submissions of consumers paying
+            // lamport fees themselves
+            let epoch_txs = if key == *consumer && mint.is_none() && !config.volume_discounts.is_empty() {
+                Some(gsn.epoch_txs(consumer, epoch))
+            } else {
+                None
+            };
+            submission_fee(config, config.calculate_fee(amount), epoch_txs, 0, fee_price)?
+        }
+    };
+    let free_tier = match &config.free_tier {
+        Some(tier)
+            if charged == Some(*consumer)
+                && mint.is_none()
+                && gsn.free_txs_used(consumer) < tier.free_tx_quota
+                && gsn.consumer.get(&tier.subsidy).copied().unwrap_or(0) >= fee =>
+        {
+            Some(tier.subsidy)
+        }
+        _ => None,
+    };
+
+    let executor_fee = match mint {
+        Some(_) => fee,
+        None => config.protocol_fee_shares(fee).1,
+    };
+    Ok(FeeEstimate {
+        fee,
+        charged: free_tier.or(charged),
+        executor_fee,
+    })
+}
+
+/// Write `answer` as the instruction's return data
+pub fn set_answer<T: BorshSerialize>(answer: &T) {
+    set_return_data(&answer.try_to_vec().expect("answers serialize"));
+}
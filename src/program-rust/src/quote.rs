@@ -0,0 +1,145 @@
+//! Executor fee quotes.
+//!
+//! An executor signs a quote off-chain: the fee it will charge for one
+//! submission, identified by its payload hash, until an expiry slot. The
+//! consumer signs a `SubmitQuoted` that carries the quote, and the transaction
+//! verifies the executor's signature with an Ed25519 program instruction. The
+//! program checks that instruction through the Instructions sysvar and charges
+//! the quoted fee instead of the configured one.
+
+use crate::error::GsnError;
+use solana_program::{
+    account_info::AccountInfo, ed25519_program, hash::hashv, program_error::ProgramError,
+    pubkey::Pubkey, sysvar::instructions::load_instruction_at_checked,
+};
+
+/// Prefix of every quote message, so a quote signature can't be replayed as
+/// a signature over anything else
+pub const QUOTE_DOMAIN: &[u8] = b"solgsn-fee-quote";
+
+/// Length of an Ed25519 signature
+const SIGNATURE_LEN: usize = 64;
+
+/// Length of the Ed25519 program's per-signature offsets
+const OFFSETS_LEN: usize = 14;
+
+/// `instruction_index` of offsets pointing into the Ed25519 instruction itself
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// A fee an executor agrees to charge for one submission
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeQuote {
+    pub gsn_account: Pubkey,
+    pub executor: Pubkey,
+    pub fee: u64,
+    /// Last slot the quote may be executed in
+    pub expiry_slot: u64,
+    /// See [`FeeQuote::payload_hash`]
+    pub payload_hash: [u8; 32],
+}
+
+impl FeeQuote {
+    /// Hash of the submission a quote is for: its accounts and arguments
+    pub fn payload_hash(
+        target_program: &Pubkey,
+        consumer: &Pubkey,
+        receiver: &Pubkey,
+        amount: u64,
+        nonce: u64,
+        lane: u64,
+    ) -> [u8; 32] {
+        hashv(&[
+            target_program.as_ref(),
+            consumer.as_ref(),
+            receiver.as_ref(),
+            &amount.to_le_bytes(),
+            &nonce.to_le_bytes(),
+            &lane.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+
+    /// Message the executor signs: the domain, GSN state account, payload
+    /// hash, fee and expiry slot
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(QUOTE_DOMAIN.len() + 32 + 32 + 8 + 8);
+        message.extend_from_slice(QUOTE_DOMAIN);
+        message.extend_from_slice(self.gsn_account.as_ref());
+        message.extend_from_slice(&self.payload_hash);
+        message.extend_from_slice(&self.fee.to_le_bytes());
+        message.extend_from_slice(&self.expiry_slot.to_le_bytes());
+        message
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn slice(data: &[u8], offset: u16, len: usize) -> Option<&[u8]> {
+    data.get(offset as usize..(offset as usize).checked_add(len)?)
+}
+
+/// Public key and message of an Ed25519 program instruction verifying a
+/// single signature whose data all lives in the instruction itself
+pub fn parse_ed25519_instruction(data: &[u8]) -> Option<(Pubkey, &[u8])> {
+    if data.len() < 2 + OFFSETS_LEN || data[0] != 1 {
+        return None;
+    }
+    let field = |index: usize| read_u16(data, 2 + 2 * index);
+    let signature_offset = field(0)?;
+    let public_key_offset = field(2)?;
+    let message_offset = field(4)?;
+    let message_len = field(5)?;
+    // Offsets into other instructions could point at data the consumer never saw
+    if field(1)? != CURRENT_INSTRUCTION
+        || field(3)? != CURRENT_INSTRUCTION
+        || field(6)? != CURRENT_INSTRUCTION
+    {
+        return None;
+    }
+
+    slice(data, signature_offset, SIGNATURE_LEN)?;
+    let public_key = slice(data, public_key_offset, 32)?;
+    let message = slice(data, message_offset, message_len as usize)?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(public_key);
+    Some((Pubkey::new_from_array(key), message))
+}
+
+/// Whether an Ed25519 program instruction before `current_index` verified a
+/// signature of `signer` over `message`. The runtime fails the transaction if
+/// that signature is invalid.
+pub fn has_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    current_index: u16,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<bool, ProgramError> {
+    for index in 0..current_index {
+        let instruction = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if instruction.program_id != ed25519_program::id() {
+            continue;
+        }
+        if let Some((key, signed)) = parse_ed25519_instruction(&instruction.data) {
+            if key == *signer && signed == message {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Fails with `InvalidFeeQuote` unless an Ed25519 program instruction before
+/// `current_index` verified `quote`'s executor signature over its message
+pub fn verify_quote(
+    instructions_sysvar: &AccountInfo,
+    current_index: u16,
+    quote: &FeeQuote,
+) -> Result<(), GsnError> {
+    match has_ed25519_signature(instructions_sysvar, current_index, &quote.executor, &quote.message()) {
+        Ok(true) => Ok(()),
+        _ => Err(GsnError::InvalidFeeQuote),
+    }
+}
@@ -0,0 +1,145 @@
+//! Map stored as a key-sorted vector.
+//!
+//! Lookups and inserts use binary search. Under Borsh it encodes exactly like
+//! a `Vec<(K, V)>`: a `u32` length followed by the entries, with no per-key
+//! string length or base58 text, and decoding is a single pass that only
+//! checks the ordering instead of rebuilding a tree.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortedMap<K, V> {
+    entries: Vec<(K, V)>,
+}
+
+impl<K, V> Default for SortedMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<K: Ord, V> SortedMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn search(&self, key: &K) -> std::result::Result<usize, usize> {
+        self.entries.binary_search_by(|(k, _)| k.cmp(key))
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match self.search(key) {
+            Ok(index) => Some(&self.entries[index].1),
+            Err(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.search(key) {
+            Ok(index) => Some(&mut self.entries[index].1),
+            Err(_) => None,
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.search(key).is_ok()
+    }
+
+    /// Insert or replace the value of `key`, returning the previous value
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.search(&key) {
+            Ok(index) => Some(std::mem::replace(&mut self.entries[index].1, value)),
+            Err(index) => {
+                self.entries.insert(index, (key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        match self.search(key) {
+            Ok(index) => Some(self.entries.remove(index).1),
+            Err(_) => None,
+        }
+    }
+
+    /// Entry for in-place access, mirroring `BTreeMap::entry`
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let index = self.search(&key);
+        Entry {
+            map: self,
+            key,
+            index,
+        }
+    }
+
+    /// Keep only the entries `f` returns true for, visiting them in
+    /// ascending key order
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+        self.entries.retain(|(k, v)| f(k, v));
+    }
+
+    /// Entries in ascending key order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+/// A possibly vacant slot of a [`SortedMap`]
+pub struct Entry<'a, K, V> {
+    map: &'a mut SortedMap<K, V>,
+    key: K,
+    index: std::result::Result<usize, usize>,
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    /// Insert `default` if the key is vacant, then return its value
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        let index = match self.index {
+            Ok(index) => index,
+            Err(index) => {
+                self.map.entries.insert(index, (self.key, default));
+                index
+            }
+        };
+        &mut self.map.entries[index].1
+    }
+}
+
+impl<K: BorshSerialize, V: BorshSerialize> BorshSerialize for SortedMap<K, V> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.entries.serialize(writer)
+    }
+}
+
+impl<K: BorshDeserialize + Ord, V: BorshDeserialize> BorshDeserialize for SortedMap<K, V> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let entries: Vec<(K, V)> = BorshDeserialize::deserialize_reader(reader)?;
+        // Binary search relies on strictly ascending keys
+        for pair in entries.windows(2) {
+            if pair[0].0 >= pair[1].0 {
+                return Err(Error::new(ErrorKind::InvalidData, "SortedMap keys out of order"));
+            }
+        }
+        Ok(Self { entries })
+    }
+}
@@ -1,17 +1,327 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
+    entrypoint::MAX_PERMITTED_DATA_INCREASE,
+    hash::hashv,
     program_error::ProgramError,
     pubkey::Pubkey,
+    system_instruction::MAX_PERMITTED_DATA_LENGTH,
 };
-use std::collections::BTreeMap;
+use crate::{error::GsnError, sorted_map::SortedMap};
+
+/// Mint key standing for native SOL (the all-zero key): fees paid in lamports
+/// in metrics, and lamport balances in [`GsnInfo::consumer_balances`]
+pub const NATIVE_FEE_TOKEN: Pubkey = Pubkey::new_from_array([0; 32]);
+
+/// Number of independent nonce lanes each consumer may use
+pub const MAX_NONCE_LANES: u64 = 16;
+
+/// Lane whose nonces may be used in any order within the consumer's
+/// [`NonceWindow`], instead of counting up
+pub const UNORDERED_NONCE_LANE: u64 = u64::MAX;
+
+/// Lane whose nonces count up per (consumer, executor) channel, so executors
+/// relaying for the same consumer don't fight over one counter
+pub const CHANNEL_NONCE_LANE: u64 = u64::MAX - 1;
+
+/// Encoded length of the `version` and `config` key
+pub const HEADER_LEN: usize = 1 + 32;
+
+/// Bytes the state account grows by at a time, so that not every new entry
+/// reallocs it
+pub const STATE_GROWTH_STEP: usize = 1024;
+
+/// Layout version of the state accounts this program writes. Accounts written
+/// before the state was versioned hold their `is_initialized` flag in the
+/// version byte, so initialized ones read as version 1.
+pub const STATE_VERSION: u8 = 1;
+
+/// Whether submissions may use `lane`: one of the `MAX_NONCE_LANES` counted
+/// lanes, [`UNORDERED_NONCE_LANE`] or [`CHANNEL_NONCE_LANE`]
+pub fn is_valid_nonce_lane(lane: u64) -> bool {
+    lane < MAX_NONCE_LANES || lane == UNORDERED_NONCE_LANE || lane == CHANNEL_NONCE_LANE
+}
+
+/// Encoded length of a `consumer`, `executor` or `consumer_nonces` entry
+pub const BALANCE_ENTRY_LEN: usize = 32 + 8;
+
+/// Encoded length of a `transaction_executor` entry (receipt)
+pub const RECEIPT_ENTRY_LEN: usize = 32 + 8 + 8 + 32;
+
+/// Encoded length of a `lane_nonces` entry
+pub const LANE_NONCE_ENTRY_LEN: usize = 32 + 8 + 8;
+
+/// Encoded length of a `token_consumer` or `token_executor` entry
+pub const TOKEN_BALANCE_ENTRY_LEN: usize = 32 + 32 + 8;
+
+/// Encoded length of an `eth_consumers` entry
+pub const ETH_CONSUMER_ENTRY_LEN: usize = 20 + 32;
+
+/// Encoded length of a `spending_limits` entry
+pub const SPENDING_LIMIT_ENTRY_LEN: usize = 32 + 8 * 4;
+
+/// Encoded length of the `paused` flag
+pub const PAUSED_LEN: usize = 1;
+
+/// Encoded length of a `registered_executors` entry
+pub const REGISTERED_EXECUTOR_ENTRY_LEN: usize = 32 + 1;
+
+/// Encoded length of an `executor_stakes` entry
+pub const EXECUTOR_STAKE_ENTRY_LEN: usize = 32 + 8 + 8;
+
+/// Encoded length of the `treasury` balance
+pub const TREASURY_LEN: usize = 8;
+
+/// Encoded length of a `sponsor_usage` entry
+pub const SPONSOR_USAGE_ENTRY_LEN: usize = 32 + 32 + 8;
+
+/// Encoded length of an `epoch_volumes` entry
+pub const EPOCH_VOLUME_ENTRY_LEN: usize = 32 + 8 + 8;
+
+/// Encoded length of a `payout_addresses` entry
+pub const PAYOUT_ADDRESS_ENTRY_LEN: usize = 32 + 32;
+
+/// Encoded length of a `nonce_windows` entry
+pub const NONCE_WINDOW_ENTRY_LEN: usize = 32 + 8 + 32;
+
+/// Encoded length of a `channel_nonces` entry
+pub const CHANNEL_NONCE_ENTRY_LEN: usize = 32 + 32 + 8;
+
+/// Encoded length of a hash retained in `recent_payloads`
+pub const PAYLOAD_HASH_LEN: usize = 32;
+
+/// Most payload hashes `recent_payloads` may retain
+pub const MAX_PAYLOAD_RETENTION: u16 = 256;
+
+/// Encoded length of a `ledger_accounts` entry
+pub const LEDGER_ACCOUNT_ENTRY_LEN: usize = 32 + 1;
+
+/// Encoded length of the `withdrawal_policy`
+pub const WITHDRAWAL_POLICY_LEN: usize = 8 + 1;
+
+/// Encoded length of a `withdrawal_requests` entry
+pub const WITHDRAWAL_REQUEST_ENTRY_LEN: usize = 32 + 8 + 8;
+
+/// Encoded length of an `escrowed_fees` entry
+pub const ESCROWED_FEE_ENTRY_LEN: usize = 32 + 32 + 8;
+
+/// Encoded length of the `in_flight` flag
+pub const IN_FLIGHT_LEN: usize = 1;
+
+/// Encoded length of the `rate_limit`
+pub const RATE_LIMIT_LEN: usize = 8 + 8;
+
+/// Encoded length of a `rate_windows` entry
+pub const RATE_WINDOW_ENTRY_LEN: usize = 32 + 8 + 8;
+
+/// Encoded length of a `blacklist` entry
+pub const BLACKLIST_ENTRY_LEN: usize = 32 + 1;
+
+/// Encoded length of the `circuit_breaker`
+pub const CIRCUIT_BREAKER_LEN: usize = 2 + 8 * 4;
+
+/// Encoded length of an unset `rewarded_epoch`, 8 more once set
+pub const REWARDED_EPOCH_LEN: usize = 1;
+
+/// Encoded length of the `insurance_pool` balance
+pub const INSURANCE_POOL_LEN: usize = 8;
+
+/// Encoded length of a `failure_reports` entry
+pub const FAILURE_REPORT_ENTRY_LEN: usize = 32 + 8 + 8 + 32;
+
+/// Encoded length of a `compensations` entry
+pub const COMPENSATION_ENTRY_LEN: usize = 32 + 8 + 8 + 32 + 8;
+
+/// Encoded length of an `eth_registration_nonces` entry
+pub const ETH_REGISTRATION_NONCE_ENTRY_LEN: usize = 20 + 8;
+
+/// Failed relays an executor may report in an epoch, so a single executor
+/// can't fill the state or trip the circuit breaker on its own
+pub const MAX_FAILURE_REPORTS_PER_EXECUTOR: usize = 8;
+
+/// Encoded length of an [`EpochStats`] without entries
+pub const EPOCH_STATS_LEN: usize = 8 * 3 + 4 + 4;
+
+/// Encoded length of an [`EpochStats::consumers`] entry
+pub const STATS_CONSUMER_ENTRY_LEN: usize = 32 + 8;
+
+/// Encoded length of an [`EpochStats::executor_volumes`] entry
+pub const EXECUTOR_VOLUME_ENTRY_LEN: usize = 32 + 8 + 8;
+
+/// `ledger_accounts` flag of a key whose top-up balance and lane 0 nonce live
+/// in its [`ConsumerAccount`]
+pub const CONSUMER_LEDGER: u8 = 1;
+
+/// `ledger_accounts` flag of a key whose earnings live in its [`ExecutorAccount`]
+pub const EXECUTOR_LEDGER: u8 = 2;
+
+/// Highest `protocol_fee_bps`, so executors always keep half of every fee
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 5_000;
+
+/// Highest `insurance_fee_bps`, a small slice next to the protocol fee
+pub const MAX_INSURANCE_FEE_BPS: u16 = 1_000;
+
+/// Most tiers of `GsnConfig::volume_discounts`
+pub const MAX_VOLUME_DISCOUNT_TIERS: usize = 8;
+
+/// Most mints of `GsnConfig::price_feeds`
+pub const MAX_PRICE_FEEDS: usize = 4;
 
 /// Fee calculation mode
-#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+///
+/// Encoded like a derived enum, except that a `Percent` with bounds is tagged
+/// 3 so unbounded percentages keep the layout predating the bounds.
+#[derive(Clone, Debug, PartialEq)]
 pub enum FeeMode {
     /// Fixed fee amount in lamports
     Fixed(u64),
-    /// Percentage fee (basis points, e.g., 100 = 1%)
-    Percent(u16),
+    /// Percentage fee (basis points, e.g., 100 = 1%), raised to `min_fee` and
+    /// lowered to `max_fee` lamports when set
+    Percent {
+        basis_points: u16,
+        min_fee: Option<u64>,
+        max_fee: Option<u64>,
+    },
+    /// `base` lamports plus `price_per_cu` micro-lamports per compute unit
+    /// the relayer measured, at most `max_compute_units`. Submissions without
+    /// a measurement pay for `max_compute_units`.
+    ComputeBased {
+        price_per_cu: u64,
+        base: u64,
+        max_compute_units: u64,
+    },
+}
+
+impl FeeMode {
+    /// Percentage fee without bounds
+    pub fn percent(basis_points: u16) -> Self {
+        FeeMode::Percent {
+            basis_points,
+            min_fee: None,
+            max_fee: None,
+        }
+    }
+
+    /// Whether switching from `current` to this mode can only lower fees,
+    /// which takes effect without the timelock
+    pub fn is_reduction_of(&self, current: &FeeMode) -> bool {
+        match (self, current) {
+            (FeeMode::Fixed(new), FeeMode::Fixed(old)) => new <= old,
+            (
+                FeeMode::Percent {
+                    basis_points,
+                    min_fee,
+                    max_fee,
+                },
+                FeeMode::Percent {
+                    basis_points: old_basis_points,
+                    min_fee: old_min_fee,
+                    max_fee: old_max_fee,
+                },
+            ) => {
+                basis_points <= old_basis_points
+                    && min_fee.unwrap_or(0) <= old_min_fee.unwrap_or(0)
+                    && max_fee.unwrap_or(u64::MAX) <= old_max_fee.unwrap_or(u64::MAX)
+            }
+            (
+                FeeMode::ComputeBased {
+                    price_per_cu,
+                    base,
+                    max_compute_units,
+                },
+                FeeMode::ComputeBased {
+                    price_per_cu: old_price,
+                    base: old_base,
+                    max_compute_units: old_max,
+                },
+            ) => price_per_cu <= old_price && base <= old_base && max_compute_units <= old_max,
+            _ => false,
+        }
+    }
+}
+
+impl BorshSerialize for FeeMode {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            FeeMode::Fixed(amount) => {
+                0u8.serialize(writer)?;
+                amount.serialize(writer)
+            }
+            FeeMode::Percent {
+                basis_points,
+                min_fee: None,
+                max_fee: None,
+            } => {
+                1u8.serialize(writer)?;
+                basis_points.serialize(writer)
+            }
+            FeeMode::ComputeBased {
+                price_per_cu,
+                base,
+                max_compute_units,
+            } => {
+                2u8.serialize(writer)?;
+                price_per_cu.serialize(writer)?;
+                base.serialize(writer)?;
+                max_compute_units.serialize(writer)
+            }
+            FeeMode::Percent {
+                basis_points,
+                min_fee,
+                max_fee,
+            } => {
+                3u8.serialize(writer)?;
+                basis_points.serialize(writer)?;
+                min_fee.serialize(writer)?;
+                max_fee.serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for FeeMode {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            0 => Ok(FeeMode::Fixed(u64::deserialize_reader(reader)?)),
+            1 => Ok(FeeMode::percent(u16::deserialize_reader(reader)?)),
+            2 => Ok(FeeMode::ComputeBased {
+                price_per_cu: u64::deserialize_reader(reader)?,
+                base: u64::deserialize_reader(reader)?,
+                max_compute_units: u64::deserialize_reader(reader)?,
+            }),
+            3 => Ok(FeeMode::Percent {
+                basis_points: u16::deserialize_reader(reader)?,
+                min_fee: BorshDeserialize::deserialize_reader(reader)?,
+                max_fee: BorshDeserialize::deserialize_reader(reader)?,
+            }),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Unknown fee mode",
+            )),
+        }
+    }
+}
+
+/// Compute units a transaction may use, bounding `FeeMode::ComputeBased`
+pub const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+
+/// Micro-lamports per lamport, the unit of compute unit prices
+pub const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// `base` plus `compute_units` at `price_per_cu` micro-lamports, rounded down
+fn compute_fee(price_per_cu: u64, base: u64, compute_units: u64) -> u64 {
+    let price = price_per_cu as u128 * compute_units as u128 / MICRO_LAMPORTS_PER_LAMPORT as u128;
+    base.saturating_add(price.min(u64::MAX as u128) as u64)
+}
+
+/// Slots between `ProposeFeeParams` and the earliest `ApplyFeeParams`,
+/// about a day at 400ms slots
+pub const FEE_PARAMS_TIMELOCK_SLOTS: u64 = 216_000;
+
+/// Fee mode proposed by governance, applicable from `activation_slot`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct PendingFeeParams {
+    pub fee_mode: FeeMode,
+    pub activation_slot: u64,
 }
 
 /// Governance configuration
@@ -22,58 +332,244 @@ pub struct GovernanceConfig {
     /// Fee calculation mode
     pub fee_mode: FeeMode,
     /// Set of allowed token mint addresses (empty means all tokens allowed)
-    pub allowed_tokens: BTreeMap<String, bool>,
+    pub allowed_tokens: SortedMap<Pubkey, bool>,
 }
 
-#[derive(Default, BorshSerialize, BorshDeserialize)]
-pub struct GsnInfo {
-    pub is_initialized: bool,
-    pub consumer: BTreeMap<String, u64>,
-    pub executor: BTreeMap<String, u64>,
-    pub governance: Option<GovernanceConfig>,
-    /// Nonce tracking per consumer to prevent replay attacks
-    pub consumer_nonces: BTreeMap<String, u64>,
-    /// Track which executor executed which transaction (by nonce)
-    /// Key: format!("{}:{}", consumer_address, nonce), Value: executor_address
-    pub transaction_executor: BTreeMap<String, String>,
+/// Most signers a governance multisig may have
+pub const MAX_MULTISIG_SIGNERS: usize = 11;
+
+/// M-of-N governance: `threshold` of `signers` must sign governance
+/// instructions instead of the single authority, see `SetMultisig`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct MultisigConfig {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
 }
 
-impl GsnInfo {
-    pub fn serialize(&self, mut data: &mut [u8]) -> Result<(), ProgramError> {
-        BorshSerialize::serialize(self, &mut data).map_err(|_| ProgramError::AccountDataTooSmall)
+impl MultisigConfig {
+    /// Distinct signers, between 1 and `MAX_MULTISIG_SIGNERS`, and a threshold
+    /// between 1 and their number
+    pub fn is_valid(&self) -> bool {
+        let distinct = self
+            .signers
+            .iter()
+            .enumerate()
+            .all(|(index, signer)| !self.signers[..index].contains(signer));
+        !self.signers.is_empty()
+            && self.signers.len() <= MAX_MULTISIG_SIGNERS
+            && distinct
+            && self.threshold >= 1
+            && self.threshold as usize <= self.signers.len()
     }
 
-    pub fn deserialize(mut data: &[u8]) -> Result<Self, ProgramError> {
-        BorshDeserialize::deserialize(&mut data).map_err(|_| ProgramError::InvalidAccountData)
+    /// Number of the multisig's signers among `signed`
+    pub fn approvals(&self, signed: &[&Pubkey]) -> usize {
+        self.signers
+            .iter()
+            .filter(|signer| signed.contains(signer))
+            .count()
     }
+}
 
-    pub fn add_consumer(&mut self, address: String, amount: u64) -> bool {
-        self.consumer.insert(address, amount);
-        true
+/// Developer sandbox of a devnet deployment, see `SetSandbox`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct SandboxConfig {
+    /// Consumer balance fees are charged to instead of the consumer's; fees
+    /// are waived without one
+    pub subsidy: Option<Pubkey>,
+    /// Epoch the registries were last reset in by `ResetSandbox`
+    pub reset_epoch: u64,
+}
+
+/// Onboarding subsidy of new consumers, see `SetFreeTier`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct FreeTier {
+    /// Submissions of every consumer charged to `subsidy` instead
+    pub free_tx_quota: u64,
+    /// Consumer balance paying for free submissions while it covers them
+    pub subsidy: Pubkey,
+}
+
+/// Fee discount of consumers that submitted `min_txs` times in the epoch, see
+/// `SetVolumeDiscounts`
+#[derive(Clone, Copy, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct VolumeDiscountTier {
+    /// Earlier submissions of the consumer in the epoch
+    pub min_txs: u64,
+    /// Basis points taken off the fee
+    pub discount_bps: u16,
+}
+
+impl VolumeDiscountTier {
+    /// At most `MAX_VOLUME_DISCOUNT_TIERS` tiers in increasing order of
+    /// `min_txs`, whose discounts don't decrease and stay within 100%
+    pub fn are_valid(tiers: &[VolumeDiscountTier]) -> bool {
+        tiers.len() <= MAX_VOLUME_DISCOUNT_TIERS
+            && tiers.iter().all(|tier| tier.discount_bps <= 10_000)
+            && tiers.windows(2).all(|pair| {
+                pair[0].min_txs < pair[1].min_txs && pair[0].discount_bps <= pair[1].discount_bps
+            })
     }
+}
 
-    pub fn add_executor(&mut self, address: String, amount: u64) -> bool {
-        self.executor.insert(address, amount);
-        true
+/// Oracle program a [`PriceFeed`] account belongs to
+#[derive(Clone, Copy, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum OracleKind {
+    /// Pyth price account
+    Pyth,
+    /// Switchboard aggregator account
+    Switchboard,
+}
+
+/// Oracle pricing the token fees of a mint, see `SetPriceFeed` and
+/// [`crate::oracle`]
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct PriceFeed {
+    /// Price account of the mint in SOL
+    pub feed: Pubkey,
+    /// Decimals of the mint
+    pub decimals: u8,
+    /// Slots the published price may trail the current slot by
+    pub max_age_slots: u64,
+    /// Widest confidence interval accepted, in basis points of the price
+    pub max_confidence_bps: u16,
+    pub kind: OracleKind,
+}
+
+/// Seconds between the authority initiating a shutdown and it taking effect
+pub const SHUTDOWN_TIMELOCK_SECS: i64 = 2 * 24 * 60 * 60;
+
+/// Seconds after a shutdown takes effect until the residue may be swept
+pub const SHUTDOWN_GRACE_SECS: i64 = 180 * 24 * 60 * 60;
+
+/// Terminal shutdown of a deployment, see `InitiateShutdown`
+#[derive(Clone, Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ShutdownState {
+    /// Receives the residue swept after the grace period
+    pub recovery: Pubkey,
+    /// Unix timestamp from which only withdrawals and fee claims are accepted
+    pub effective_at: i64,
+    /// Unix timestamp from which anyone may sweep the residue to `recovery`
+    pub sweep_at: i64,
+}
+
+impl ShutdownState {
+    /// Shutdown initiated at `now`, the timelock and grace period counted from it
+    pub fn new(recovery: Pubkey, now: i64) -> Self {
+        let effective_at = now.saturating_add(SHUTDOWN_TIMELOCK_SECS);
+        Self {
+            recovery,
+            effective_at,
+            sweep_at: effective_at.saturating_add(SHUTDOWN_GRACE_SECS),
+        }
     }
 
-    pub fn new() -> Self {
+    pub fn is_effective(&self, now: i64) -> bool {
+        now >= self.effective_at
+    }
+
+    pub fn is_sweepable(&self, now: i64) -> bool {
+        now >= self.sweep_at
+    }
+}
+
+/// Configuration of a GSN deployment. It changes only through governance
+/// instructions, so submissions pass its account read-only and the runtime can
+/// schedule them in parallel with each other's config reads.
+#[derive(Default, BorshSerialize, BorshDeserialize)]
+pub struct GsnConfig {
+    pub is_initialized: bool,
+    /// GSN state account this configuration belongs to
+    pub gsn_account: Pubkey,
+    pub governance: Option<GovernanceConfig>,
+    /// Metrics account updated on every execution, if configured
+    pub metrics: Option<Pubkey>,
+    /// Compliance mode: mint of the credential (SBT) consumers must hold to
+    /// top up or submit, see [`crate::credential`]
+    pub credential_mint: Option<Pubkey>,
+    /// Sandbox mode, which only builds with the `sandbox` feature can turn on
+    pub sandbox: Option<SandboxConfig>,
+    /// Set once the authority initiated the shutdown, which can't be undone
+    pub shutdown: Option<ShutdownState>,
+    /// Target programs submissions may invoke (empty means all programs allowed)
+    pub allowed_programs: SortedMap<Pubkey, bool>,
+    /// Signers replacing the governance authority once set
+    pub multisig: Option<MultisigConfig>,
+    /// Fee change waiting out the timelock, see `ProposeFeeParams`
+    pub pending_fee_params: Option<PendingFeeParams>,
+    /// Permissioned phase: only registered executors may execute and earn fees
+    pub registered_executors_only: bool,
+    /// Lamports executors must bond to execute and earn fees, 0 when bonding
+    /// isn't required
+    pub min_executor_stake: u64,
+    /// Basis points of every lamport fee kept for the treasury
+    pub protocol_fee_bps: u16,
+    /// Free submissions of every consumer, paid by a subsidy
+    pub free_tier: Option<FreeTier>,
+    /// Discounts of consumers' lamport fees by their submissions in the
+    /// epoch, in increasing order of `min_txs`
+    pub volume_discounts: Vec<VolumeDiscountTier>,
+    /// Genesis hash of the cluster the deployment runs on, bound into the
+    /// domain separator of intents, see [`crate::intent::domain_separator`]
+    pub genesis_hash: Option<[u8; 32]>,
+    /// Executor records `PruneRecords` keeps of every consumer's lane, the
+    /// latest nonces; 0 keeps them all
+    pub record_retention: u16,
+    /// Oracles the token fees of these mints are priced by, instead of
+    /// charging the lamport fee in base units
+    pub price_feeds: SortedMap<Pubkey, PriceFeed>,
+    /// Basis points of every lamport fee kept for the insurance pool, which
+    /// `Compensate` pays consumers from
+    pub insurance_fee_bps: u16,
+}
+
+impl GsnConfig {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNCONFG";
+
+    pub fn new(gsn_account: Pubkey) -> Self {
         Self {
             is_initialized: true,
-            consumer: BTreeMap::new(),
-            executor: BTreeMap::new(),
+            gsn_account,
             governance: None,
-            consumer_nonces: BTreeMap::new(),
-            transaction_executor: BTreeMap::new(),
+            metrics: None,
+            credential_mint: None,
+            sandbox: None,
+            shutdown: None,
+            allowed_programs: SortedMap::new(),
+            multisig: None,
+            pending_fee_params: None,
+            registered_executors_only: false,
+            min_executor_stake: 0,
+            protocol_fee_bps: 0,
+            free_tier: None,
+            volume_discounts: Vec::new(),
+            genesis_hash: None,
+            record_retention: 0,
+            price_feeds: SortedMap::new(),
+            insurance_fee_bps: 0,
         }
     }
 
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+
+    /// Decode a config account written before accounts had discriminators,
+    /// which `MigrateState` tags
+    pub fn deserialize_untagged(mut data: &[u8]) -> Result<Self, ProgramError> {
+        BorshDeserialize::deserialize(&mut data).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
     /// Initialize governance with default authority
     pub fn initialize_governance(&mut self, authority: Pubkey) {
         self.governance = Some(GovernanceConfig {
             authority,
             fee_mode: FeeMode::Fixed(50000), // Default 50,000 lamports
-            allowed_tokens: BTreeMap::new(), // Empty means all tokens allowed
+            allowed_tokens: SortedMap::new(), // Empty means all tokens allowed
         });
     }
 
@@ -82,17 +578,68 @@ impl GsnInfo {
         match &self.governance {
             Some(gov) => match &gov.fee_mode {
                 FeeMode::Fixed(amount) => *amount,
-                FeeMode::Percent(basis_points) => {
+                FeeMode::Percent {
+                    basis_points,
+                    min_fee,
+                    max_fee,
+                } => {
                     // Calculate percentage: (amount * basis_points) / 10000
-                    (transaction_amount as u128 * *basis_points as u128 / 10000) as u64
+                    let fee = (transaction_amount as u128 * *basis_points as u128 / 10000) as u64;
+                    fee.max(min_fee.unwrap_or(0))
+                        .min(max_fee.unwrap_or(u64::MAX))
                 }
+                FeeMode::ComputeBased {
+                    price_per_cu,
+                    base,
+                    max_compute_units,
+                } => compute_fee(*price_per_cu, *base, *max_compute_units),
             },
             None => 50000, // Default fallback
         }
     }
 
+    /// [`GsnConfig::calculate_fee`] of a submission the relayer measured
+    /// `compute_units` for. Only `ComputeBased` fees depend on it, and fail
+    /// with `InvalidComputeUnits` above the mode's bound.
+    pub fn calculate_metered_fee(
+        &self,
+        transaction_amount: u64,
+        compute_units: u64,
+    ) -> Result<u64, GsnError> {
+        match self.governance.as_ref().map(|gov| &gov.fee_mode) {
+            Some(FeeMode::ComputeBased {
+                price_per_cu,
+                base,
+                max_compute_units,
+            }) => {
+                if compute_units > *max_compute_units {
+                    return Err(GsnError::InvalidComputeUnits);
+                }
+                Ok(compute_fee(*price_per_cu, *base, compute_units))
+            }
+            _ => Ok(self.calculate_fee(transaction_amount)),
+        }
+    }
+
+    /// Discount of a consumer that submitted `epoch_txs` times in the epoch,
+    /// in basis points: that of the highest tier it reached
+    pub fn volume_discount_bps(&self, epoch_txs: u64) -> u16 {
+        self.volume_discounts
+            .iter()
+            .rev()
+            .find(|tier| epoch_txs >= tier.min_txs)
+            .map_or(0, |tier| tier.discount_bps)
+    }
+
+    /// [`GsnConfig::calculate_fee`] (or the metered fee) of a consumer that
+    /// submitted `epoch_txs` times in the epoch, after its volume discount
+    pub fn apply_volume_discount(&self, fee: u64, epoch_txs: u64) -> u64 {
+        let discount_bps = self.volume_discount_bps(epoch_txs) as u128;
+        (fee as u128 * (10_000 - discount_bps) / 10_000) as u64
+    }
+
     /// Check if a token is allowed for fee payment
-    pub fn is_token_allowed(&self, token_mint: &str) -> bool {
+    pub fn is_token_allowed(&self, token_mint: &Pubkey) -> bool {
         match &self.governance {
             Some(gov) => {
                 // If allowed_tokens is empty, all tokens are allowed
@@ -107,19 +654,34 @@ impl GsnInfo {
     }
 
     /// Add an allowed token
-    pub fn add_allowed_token(&mut self, token_mint: String) {
+    pub fn add_allowed_token(&mut self, token_mint: Pubkey) {
         if let Some(gov) = &mut self.governance {
             gov.allowed_tokens.insert(token_mint, true);
         }
     }
 
     /// Remove an allowed token
-    pub fn remove_allowed_token(&mut self, token_mint: &str) {
+    pub fn remove_allowed_token(&mut self, token_mint: &Pubkey) {
         if let Some(gov) = &mut self.governance {
             gov.allowed_tokens.remove(token_mint);
         }
     }
 
+    /// Check if submissions may invoke a target program
+    pub fn is_program_allowed(&self, program_id: &Pubkey) -> bool {
+        self.allowed_programs.is_empty() || self.allowed_programs.contains_key(program_id)
+    }
+
+    /// Add an allowed target program
+    pub fn add_allowed_program(&mut self, program_id: Pubkey) {
+        self.allowed_programs.insert(program_id, true);
+    }
+
+    /// Remove an allowed target program
+    pub fn remove_allowed_program(&mut self, program_id: &Pubkey) {
+        self.allowed_programs.remove(program_id);
+    }
+
     /// Update fee parameters
     pub fn update_fee_params(&mut self, fee_mode: FeeMode) {
         if let Some(gov) = &mut self.governance {
@@ -127,6 +689,45 @@ impl GsnInfo {
         }
     }
 
+    /// Treasury's and executors' shares of a lamport `fee`. The treasury's
+    /// and the insurance pool's shares round down, so with
+    /// [`GsnConfig::insurance_fee_share`] the shares add up to `fee`.
+    pub fn protocol_fee_shares(&self, fee: u64) -> (u64, u64) {
+        let treasury_share = (fee as u128 * self.protocol_fee_bps as u128 / 10_000) as u64;
+        (treasury_share, fee - treasury_share - self.insurance_fee_share(fee))
+    }
+
+    /// Insurance pool's share of a lamport `fee`
+    pub fn insurance_fee_share(&self, fee: u64) -> u64 {
+        (fee as u128 * self.insurance_fee_bps as u128 / 10_000) as u64
+    }
+
+    /// Record `fee_mode` to apply once the timelock elapsed, replacing any
+    /// earlier proposal. Returns the activation slot.
+    pub fn propose_fee_params(&mut self, fee_mode: FeeMode, current_slot: u64) -> u64 {
+        let activation_slot = current_slot.saturating_add(FEE_PARAMS_TIMELOCK_SLOTS);
+        self.pending_fee_params = Some(PendingFeeParams {
+            fee_mode,
+            activation_slot,
+        });
+        activation_slot
+    }
+
+    /// Apply the proposed fee mode if its activation slot was reached
+    pub fn apply_fee_params(&mut self, current_slot: u64) -> Result<FeeMode, GsnError> {
+        let pending = self
+            .pending_fee_params
+            .as_ref()
+            .ok_or(GsnError::NoPendingFeeParams)?;
+        if current_slot < pending.activation_slot {
+            return Err(GsnError::TimelockNotElapsed);
+        }
+        let fee_mode = pending.fee_mode.clone();
+        self.pending_fee_params = None;
+        self.update_fee_params(fee_mode.clone());
+        Ok(fee_mode)
+    }
+
     /// Check if an address is the governance authority
     pub fn is_authority(&self, address: &Pubkey) -> bool {
         match &self.governance {
@@ -135,35 +736,2801 @@ impl GsnInfo {
         }
     }
 
-    /// Get the next nonce for a consumer
-    pub fn get_next_nonce(&self, consumer: &str) -> u64 {
-        self.consumer_nonces.get(consumer).copied().unwrap_or(0)
+    /// Check if the keys that signed an instruction may govern the
+    /// deployment: the multisig's threshold of its signers when one is set,
+    /// otherwise the authority
+    pub fn is_approved_by(&self, signed: &[&Pubkey]) -> bool {
+        match &self.multisig {
+            Some(multisig) => multisig.approvals(signed) >= multisig.threshold as usize,
+            None => signed.iter().any(|key| self.is_authority(key)),
+        }
     }
+}
 
-    /// Increment and return the nonce for a consumer
-    pub fn increment_nonce(&mut self, consumer: &str) -> u64 {
-        let current_nonce = self.get_next_nonce(consumer);
-        let next_nonce = current_nonce + 1;
-        self.consumer_nonces.insert(consumer.to_string(), next_nonce);
-        next_nonce
+/// Sections of the GSN state account, in layout order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Section {
+    /// `version` and `config`
+    Header,
+    Consumer,
+    Executor,
+    ConsumerNonces,
+    TransactionExecutor,
+    LaneNonces,
+    TokenConsumer,
+    TokenExecutor,
+    EthConsumers,
+    SpendingLimits,
+    Paused,
+    RegisteredExecutors,
+    ExecutorStakes,
+    Treasury,
+    SponsorUsage,
+    FreeTxs,
+    EpochVolumes,
+    PayoutAddresses,
+    NonceWindows,
+    ChannelNonces,
+    RecentPayloads,
+    LedgerAccounts,
+    WithdrawalPolicy,
+    WithdrawalRequests,
+    EscrowedFees,
+    InFlight,
+    RateLimit,
+    RateWindows,
+    Blacklist,
+    CircuitBreaker,
+    Stats,
+    RewardedEpoch,
+    InsurancePool,
+    FailureReports,
+    Compensations,
+    EthRegistrationNonces,
+}
+
+impl Section {
+    pub const ALL: [Section; 36] = [
+        Section::Header,
+        Section::Consumer,
+        Section::Executor,
+        Section::ConsumerNonces,
+        Section::TransactionExecutor,
+        Section::LaneNonces,
+        Section::TokenConsumer,
+        Section::TokenExecutor,
+        Section::EthConsumers,
+        Section::SpendingLimits,
+        Section::Paused,
+        Section::RegisteredExecutors,
+        Section::ExecutorStakes,
+        Section::Treasury,
+        Section::SponsorUsage,
+        Section::FreeTxs,
+        Section::EpochVolumes,
+        Section::PayoutAddresses,
+        Section::NonceWindows,
+        Section::ChannelNonces,
+        Section::RecentPayloads,
+        Section::LedgerAccounts,
+        Section::WithdrawalPolicy,
+        Section::WithdrawalRequests,
+        Section::EscrowedFees,
+        Section::InFlight,
+        Section::RateLimit,
+        Section::RateWindows,
+        Section::Blacklist,
+        Section::CircuitBreaker,
+        Section::Stats,
+        Section::RewardedEpoch,
+        Section::InsurancePool,
+        Section::FailureReports,
+        Section::Compensations,
+        Section::EthRegistrationNonces,
+    ];
+}
+
+/// First seed of every executor stake address
+pub const STAKE_SEED: &[u8] = b"stake";
+
+/// Slots between asking to unstake and withdrawing the stake, during which
+/// governance can still slash it; about two days at 400ms slots
+pub const STAKE_UNBONDING_SLOTS: u64 = 432_000;
+
+/// Lamports an executor bonded with `StakeExecutor`, held in its stake
+/// account: a system account without data that only the program can sign for
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ExecutorStake {
+    pub amount: u64,
+    /// Slot the stake can be withdrawn from, 0 while it is bonded
+    pub unbonding_at: u64,
+}
+
+impl ExecutorStake {
+    /// Whether the stake counts towards a `min_stake` bond; an unbonding
+    /// stake no longer does
+    pub fn is_bonded(&self, min_stake: u64) -> bool {
+        self.unbonding_at == 0 && self.amount >= min_stake
     }
+}
 
-    /// Check if a nonce has been used (replay protection)
-    /// Returns true if the nonce is less than the next expected nonce
-    pub fn is_nonce_used(&self, consumer: &str, nonce: u64) -> bool {
-        let next_nonce = self.get_next_nonce(consumer);
-        nonce < next_nonce
+/// Delay between a consumer requesting a withdrawal and making it, set by
+/// governance with `SetWithdrawalCooldown`
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalPolicy {
+    /// Slots a `RequestWithdrawal` waits before `Withdraw` pays it, 0 for
+    /// withdrawals without a request
+    pub cooldown_slots: u64,
+    /// Emergency mode: while paused, withdrawals skip the pause and the
+    /// cooldown. `EnableEmergencyWithdrawals` sets it, `Unpause` clears it.
+    pub emergency: bool,
+}
+
+/// Withdrawal a consumer requested with `RequestWithdrawal`
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalRequest {
+    /// Lamports `Withdraw` may pay out of the request
+    pub amount: u64,
+    /// Slot the request can be withdrawn from
+    pub unlock_slot: u64,
+}
+
+/// Submissions a consumer may make within a window of slots, set by
+/// governance with `SetRateLimit`
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct RateLimit {
+    /// Highest submissions of a consumer within a window, 0 for no limit
+    pub max_submissions: u64,
+    /// Slots a window lasts
+    pub window_slots: u64,
+}
+
+/// Submissions a consumer made in its current rate limit window
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct RateWindow {
+    /// Slot of the window's first submission
+    pub start_slot: u64,
+    pub submissions: u64,
+}
+
+/// Failure ratio of an epoch's executions that pauses the deployment, set by
+/// governance with `SetCircuitBreaker`, and the executions counted so far
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct CircuitBreaker {
+    /// Basis points of failed executions above which the breaker trips, 0
+    /// when it is off
+    pub max_failure_bps: u16,
+    /// Executions of the epoch before the ratio is judged
+    pub min_executions: u64,
+    /// Epoch the counts are of
+    pub epoch: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+impl CircuitBreaker {
+    pub fn is_enabled(&self) -> bool {
+        self.max_failure_bps > 0
     }
 
-    /// Record which executor executed a transaction
-    pub fn record_transaction_executor(&mut self, consumer: &str, nonce: u64, executor: &str) {
-        let key = format!("{}:{}", consumer, nonce);
-        self.transaction_executor.insert(key, executor.to_string());
+    /// Count an execution in `epoch`, restarting the counts in a new epoch.
+    /// Returns whether the failure ratio now trips the breaker.
+    pub fn record(&mut self, epoch: u64, succeeded: bool) -> bool {
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.succeeded = 0;
+            self.failed = 0;
+        }
+        if succeeded {
+            self.succeeded = self.succeeded.saturating_add(1);
+        } else {
+            self.failed = self.failed.saturating_add(1);
+        }
+        let executions = self.succeeded.saturating_add(self.failed);
+        executions >= self.min_executions
+            && self.failed as u128 * 10_000 > self.max_failure_bps as u128 * executions as u128
+    }
+}
+
+/// Failed relay of a consumer lane's nonce, and the executor that reported it
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct FailureReport {
+    pub nonce: u64,
+    pub executor: Pubkey,
+}
+
+/// Executions an executor relayed in an epoch, and the lamport fees it
+/// earned with them
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ExecutorVolume {
+    pub executions: u64,
+    pub fees: u64,
+}
+
+/// Counters of the executions in an epoch, see [`Stats`]
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct EpochStats {
+    pub epoch: u64,
+    pub executions: u64,
+    /// Lamport fees charged, tips included. Token fees aren't summed.
+    pub total_fees: u64,
+    /// Executions of every consumer served
+    pub consumers: SortedMap<Pubkey, u64>,
+    /// Executions and lamport fees of every executor that relayed
+    pub executor_volumes: SortedMap<Pubkey, ExecutorVolume>,
+}
+
+impl EpochStats {
+    pub fn new(epoch: u64) -> Self {
+        Self {
+            epoch,
+            ..Self::default()
+        }
     }
 
-    /// Get the executor that executed a specific transaction
-    pub fn get_transaction_executor(&self, consumer: &str, nonce: u64) -> Option<&String> {
-        let key = format!("{}:{}", consumer, nonce);
-        self.transaction_executor.get(&key)
+    pub fn serialized_len(&self) -> usize {
+        EPOCH_STATS_LEN
+            + self.consumers.len() * STATS_CONSUMER_ENTRY_LEN
+            + self.executor_volumes.len() * EXECUTOR_VOLUME_ENTRY_LEN
+    }
+}
+
+/// Executions counted by epoch while governance turned it on with
+/// `SetStatsTracking`, answered by `GetStats`
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct Stats {
+    pub enabled: bool,
+    pub current: EpochStats,
+    /// The last epoch before `current` that was counted
+    pub previous: EpochStats,
+}
+
+impl Stats {
+    pub fn serialized_len(&self) -> usize {
+        1 + self.current.serialized_len() + self.previous.serialized_len()
+    }
+
+    /// The counters of `epoch` and of the epoch before it that was counted,
+    /// as they are once `epoch` starts
+    pub fn as_of(&self, epoch: u64) -> (EpochStats, EpochStats) {
+        if epoch == self.current.epoch {
+            (self.current.clone(), self.previous.clone())
+        } else {
+            (EpochStats::new(epoch), self.current.clone())
+        }
+    }
+
+    /// Bytes counting an execution of `consumer` relayed by `executor` in
+    /// `epoch` may add. Moving `current` to `previous` in a new epoch frees
+    /// at least the empty counters it replaces.
+    pub fn growth(&self, epoch: u64, consumer: &Pubkey, executor: &Pubkey) -> usize {
+        let counted = epoch == self.current.epoch;
+        let mut growth = 0;
+        if !counted || !self.current.consumers.contains_key(consumer) {
+            growth += STATS_CONSUMER_ENTRY_LEN;
+        }
+        if !counted || !self.current.executor_volumes.contains_key(executor) {
+            growth += EXECUTOR_VOLUME_ENTRY_LEN;
+        }
+        growth
+    }
+
+    /// Count an execution of `consumer` relayed by `executor` in `epoch`,
+    /// charged `fee` lamports of which the executor earned `executor_fee`,
+    /// moving on from the counters of an earlier epoch
+    pub fn record(&mut self, epoch: u64, consumer: &Pubkey, executor: &Pubkey, fee: u64, executor_fee: u64) {
+        if epoch != self.current.epoch {
+            self.previous = std::mem::replace(&mut self.current, EpochStats::new(epoch));
+        }
+        let current = &mut self.current;
+        current.executions = current.executions.saturating_add(1);
+        current.total_fees = current.total_fees.saturating_add(fee);
+        add_to_entry(&mut current.consumers, consumer, 1);
+        let volume = current.executor_volumes.entry(*executor).or_insert(ExecutorVolume::default());
+        volume.executions = volume.executions.saturating_add(1);
+        volume.fees = volume.fees.saturating_add(executor_fee);
+    }
+}
+
+/// Lamport fees a consumer may be charged, set by the governance authority
+/// with `SetSpendingLimit`
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct SpendingLimit {
+    /// Highest fee of a single submission
+    pub max_fee_per_tx: u64,
+    /// Highest sum of fees within an epoch
+    pub max_fees_per_epoch: u64,
+    /// Epoch `spent` was charged in
+    pub epoch: u64,
+    /// Fees charged in `epoch`
+    pub spent: u64,
+}
+
+impl SpendingLimit {
+    pub fn new(max_fee_per_tx: u64, max_fees_per_epoch: u64) -> Self {
+        Self {
+            max_fee_per_tx,
+            max_fees_per_epoch,
+            ..Self::default()
+        }
+    }
+
+    /// Fees the consumer may still be charged in `epoch`
+    pub fn remaining(&self, epoch: u64) -> u64 {
+        let spent = if epoch == self.epoch { self.spent } else { 0 };
+        self.max_fees_per_epoch.saturating_sub(spent)
+    }
+
+    /// Record a `fee` charged in `epoch`, failing with `LimitExceeded` when it
+    /// is above the per-transaction limit or what remains of the epoch's
+    pub fn charge(&mut self, epoch: u64, fee: u64) -> Result<(), GsnError> {
+        if fee > self.max_fee_per_tx || fee > self.remaining(epoch) {
+            return Err(GsnError::LimitExceeded);
+        }
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.spent = 0;
+        }
+        self.spent += fee;
+        Ok(())
+    }
+}
+
+/// Submissions of a consumer in an epoch, see `GsnConfig::volume_discounts`
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct EpochVolume {
+    pub epoch: u64,
+    /// Submissions in `epoch`
+    pub txs: u64,
+}
+
+impl EpochVolume {
+    /// Submissions in `epoch`, 0 once it is over
+    pub fn txs_in(&self, epoch: u64) -> u64 {
+        if epoch == self.epoch {
+            self.txs
+        } else {
+            0
+        }
+    }
+}
+
+/// Unordered nonces of a consumer, see [`UNORDERED_NONCE_LANE`]. Nonces
+/// below `base` are used; bit `i` of `bits` marks `base + i` used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct NonceWindow {
+    pub base: u64,
+    pub bits: [u64; 4],
+}
+
+impl NonceWindow {
+    /// Nonces the window tracks
+    pub const SIZE: u64 = 256;
+
+    pub fn is_used(&self, nonce: u64) -> bool {
+        if nonce < self.base {
+            return true;
+        }
+        let offset = nonce - self.base;
+        offset < Self::SIZE && self.bits[(offset / 64) as usize] & (1 << (offset % 64)) != 0
+    }
+
+    /// Mark `nonce` used. A nonce past the window slides it forward so the
+    /// nonce is its highest, and the nonces left below it can't be used.
+    pub fn mark_used(&mut self, nonce: u64) {
+        if nonce < self.base {
+            return;
+        }
+        if nonce - self.base >= Self::SIZE {
+            self.slide(nonce - (Self::SIZE - 1));
+        }
+        let offset = nonce - self.base;
+        self.bits[(offset / 64) as usize] |= 1 << (offset % 64);
     }
+
+    fn slide(&mut self, base: u64) {
+        let shift = base - self.base;
+        let mut bits = [0; 4];
+        if shift < Self::SIZE {
+            let (words, offset) = ((shift / 64) as usize, shift % 64);
+            for (i, word) in bits.iter_mut().enumerate() {
+                let low = self.bits.get(i + words).copied().unwrap_or(0);
+                let high = self.bits.get(i + words + 1).copied().unwrap_or(0);
+                *word = if offset == 0 {
+                    low
+                } else {
+                    low >> offset | high << (64 - offset)
+                };
+            }
+        }
+        self.base = base;
+        self.bits = bits;
+    }
+}
+
+/// Hashes of the last executed payloads, a ring overwriting the oldest. Its
+/// length is the retention window set with `SetPayloadRetention`, 0 when
+/// duplicate payloads aren't checked.
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct RecentPayloads {
+    /// Index of `hashes` the next payload is written to
+    pub cursor: u16,
+    /// Payload hashes, all-zero in slots not written yet
+    pub hashes: Vec<[u8; 32]>,
+}
+
+impl RecentPayloads {
+    pub fn retention(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn contains(&self, hash: &[u8; 32]) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    /// Retain `hash`, dropping the oldest one once the ring is full
+    pub fn record(&mut self, hash: [u8; 32]) {
+        if self.hashes.is_empty() {
+            return;
+        }
+        let cursor = self.cursor as usize % self.hashes.len();
+        self.hashes[cursor] = hash;
+        self.cursor = ((cursor + 1) % self.hashes.len()) as u16;
+    }
+
+    /// Change the retention window, keeping the most recent hashes that fit
+    pub fn resize(&mut self, retention: usize) {
+        let cursor = (self.cursor as usize).min(self.hashes.len());
+        let (newer, older) = self.hashes.split_at(cursor);
+        let mut hashes: Vec<[u8; 32]> = older
+            .iter()
+            .chain(newer)
+            .filter(|hash| **hash != [0; 32])
+            .copied()
+            .collect();
+        hashes.drain(..hashes.len().saturating_sub(retention));
+        self.cursor = if retention == 0 {
+            0
+        } else {
+            (hashes.len() % retention) as u16
+        };
+        hashes.resize(retention, [0; 32]);
+        self.hashes = hashes;
+    }
+}
+
+/// Set of sections mutated since the state was decoded
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DirtySections(u64);
+
+impl DirtySections {
+    pub fn mark(&mut self, section: Section) {
+        self.0 |= 1 << section as u64;
+    }
+
+    pub fn contains(&self, section: Section) -> bool {
+        self.0 & (1 << section as u64) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Seed of the vault holding the lamports that back a deployment's balances
+pub const VAULT_SEED: &[u8] = b"vault";
+
+/// Balances and nonces, written by every top-up, submission and claim
+///
+/// Mutate it through its methods (or `consumer_mut` / `executor_mut`) so the
+/// touched sections are marked dirty: a state decoded with [`GsnInfo::deserialize`]
+/// only rewrites the byte ranges of its dirty sections on `serialize`.
+#[derive(Default, BorshSerialize, BorshDeserialize)]
+pub struct GsnInfo {
+    /// Layout version, 0 for an uninitialized account, see [`STATE_VERSION`]
+    pub version: u8,
+    /// Configuration account of this deployment, see [`GsnConfig`]
+    pub config: Pubkey,
+    pub consumer: SortedMap<Pubkey, u64>,
+    pub executor: SortedMap<Pubkey, u64>,
+    /// Nonce tracking per consumer to prevent replay attacks
+    pub consumer_nonces: SortedMap<Pubkey, u64>,
+    /// Track which executor executed which transaction
+    /// Key: (consumer, lane, nonce), Value: executor
+    pub transaction_executor: SortedMap<(Pubkey, u64, u64), Pubkey>,
+    /// Next nonce of every lane other than 0, which lives in `consumer_nonces`
+    /// Key: (consumer, lane)
+    pub lane_nonces: SortedMap<(Pubkey, u64), u64>,
+    /// SPL token balances paying fees in tokens, see `TopupToken`
+    /// Key: (consumer, mint)
+    pub token_consumer: SortedMap<(Pubkey, Pubkey), u64>,
+    /// Fees earned in SPL tokens, claimed with `ClaimTokenFees`
+    /// Key: (executor, mint)
+    pub token_executor: SortedMap<(Pubkey, Pubkey), u64>,
+    /// Consumers Ethereum addresses sign for, see `RegisterEthAddress`
+    /// Key: Ethereum address
+    pub eth_consumers: SortedMap<[u8; 20], Pubkey>,
+    /// Fee limits of the consumers the governance authority capped
+    pub spending_limits: SortedMap<Pubkey, SpendingLimit>,
+    /// Circuit breaker set by `Pause`, halting everything that moves funds
+    pub paused: bool,
+    /// Executors the governance authority registered, see `RegisterExecutor`
+    pub registered_executors: SortedMap<Pubkey, bool>,
+    /// Lamports executors bonded, see `StakeExecutor`
+    pub executor_stakes: SortedMap<Pubkey, ExecutorStake>,
+    /// Lamports of the protocol's cut of fees, withdrawn with `WithdrawTreasury`
+    pub treasury: u64,
+    /// Sponsored submissions of every user of a sponsor with a quota
+    /// Key: (sponsor, consumer)
+    pub sponsor_usage: SortedMap<(Pubkey, Pubkey), u64>,
+    /// Free submissions every consumer used, see `SetFreeTier`
+    pub free_txs: SortedMap<Pubkey, u64>,
+    /// Submissions of every consumer in the epoch, counted while governance
+    /// set volume discounts
+    pub epoch_volumes: SortedMap<Pubkey, EpochVolume>,
+    /// Addresses executors' fees may be claimed to besides their own, see
+    /// `SetPayoutAddress`
+    pub payout_addresses: SortedMap<Pubkey, Pubkey>,
+    /// Unordered nonces of every consumer that used [`UNORDERED_NONCE_LANE`]
+    pub nonce_windows: SortedMap<Pubkey, NonceWindow>,
+    /// Next nonce of every channel used through [`CHANNEL_NONCE_LANE`]
+    /// Key: (consumer, executor)
+    pub channel_nonces: SortedMap<(Pubkey, Pubkey), u64>,
+    /// Hashes of the last executed payloads, rejected if submitted again
+    pub recent_payloads: RecentPayloads,
+    /// Consumers and executors whose balances moved to ledger accounts, see
+    /// `OpenConsumerAccount` and `OpenExecutorAccount`
+    /// Value: [`CONSUMER_LEDGER`] and [`EXECUTOR_LEDGER`] flags
+    pub ledger_accounts: SortedMap<Pubkey, u8>,
+    /// Cooldown and emergency mode of consumer withdrawals
+    pub withdrawal_policy: WithdrawalPolicy,
+    /// Pending withdrawal of every consumer that requested one
+    pub withdrawal_requests: SortedMap<Pubkey, WithdrawalRequest>,
+    /// Fees taken from their charged balance while their submission's CPI
+    /// runs, settled or refunded once it returns; empty between instructions
+    /// Key: (charged key, mint), lamports under [`NATIVE_FEE_TOKEN`]
+    pub escrowed_fees: SortedMap<(Pubkey, Pubkey), u64>,
+    /// Set while a submission's CPI runs, so the target program can't
+    /// reenter the program and spend the state again
+    pub in_flight: bool,
+    /// Submissions every consumer may make per window of slots
+    pub rate_limit: RateLimit,
+    /// Current window of every consumer that submitted under a rate limit
+    pub rate_windows: SortedMap<Pubkey, RateWindow>,
+    /// Consumers and executors governance barred from topping up, submitting
+    /// and claiming fees, see `BlacklistAddress`
+    pub blacklist: SortedMap<Pubkey, bool>,
+    /// Failure threshold pausing the deployment, and the epoch's executions
+    pub circuit_breaker: CircuitBreaker,
+    /// Executions counted by epoch, see `SetStatsTracking`
+    pub stats: Stats,
+    /// Last epoch whose executors `DistributeRewards` rewarded
+    pub rewarded_epoch: Option<u64>,
+    /// Lamports of the insurance cut of fees, paid out with `Compensate`
+    pub insurance_pool: u64,
+    /// Every consumer lane whose failed relay an executor reported in the
+    /// circuit breaker's epoch, see `ReportFailure`
+    pub failure_reports: SortedMap<(Pubkey, u64), FailureReport>,
+    /// Lamports `Compensate` paid for every execution, so none is paid twice
+    /// Key: (consumer, lane, nonce, executor)
+    pub compensations: SortedMap<(Pubkey, u64, u64, Pubkey), u64>,
+    /// Next registration nonce of every Ethereum address, so a registration
+    /// signature can't be replayed, see `RegisterEthAddress`
+    /// Key: Ethereum address
+    pub eth_registration_nonces: SortedMap<[u8; 20], u64>,
+    #[borsh_skip]
+    dirty: DirtySections,
+    /// End offset of every section in the account data this state was decoded from
+    #[borsh_skip]
+    section_ends: Option<[usize; 36]>,
+    /// Length of the account data this state was decoded from
+    #[borsh_skip]
+    capacity: Option<usize>,
+    /// Bytes of the entries loaded from ledger accounts, which are moved back
+    /// out before the state is written
+    #[borsh_skip]
+    loaded_ledger_len: usize,
+}
+
+impl GsnInfo {
+    /// Address and bump of the vault of a GSN state account: a system account
+    /// without data that only the program can sign for
+    pub fn find_vault_address(program_id: &Pubkey, gsn_account: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[VAULT_SEED, gsn_account.as_ref()], program_id)
+    }
+
+    /// Address and bump of the account holding `executor`'s stake
+    pub fn find_stake_address(
+        program_id: &Pubkey,
+        gsn_account: &Pubkey,
+        executor: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[STAKE_SEED, gsn_account.as_ref(), executor.as_ref()],
+            program_id,
+        )
+    }
+
+    /// Write the state to `data`. A state decoded from `data` only rewrites its
+    /// dirty sections, plus the clean sections after them that moved because a
+    /// dirty one changed length. Writes are staged before the first byte is
+    /// written, so a failure leaves the account untouched.
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        let ends = match self.section_ends {
+            Some(ends) => ends,
+            None => {
+                let mut data = data;
+                return BorshSerialize::serialize(self, &mut data)
+                    .map_err(|_| ProgramError::AccountDataTooSmall);
+            }
+        };
+
+        let mut writes: Vec<(usize, Vec<u8>)> = Vec::new();
+        let mut offset = 0;
+        for (index, section) in Section::ALL.iter().enumerate() {
+            let old_start = if index == 0 { 0 } else { ends[index - 1] };
+            let old_end = ends[index];
+            if self.dirty.contains(*section) {
+                let mut bytes = Vec::new();
+                self.serialize_section(*section, &mut bytes)
+                    .map_err(|_| ProgramError::AccountDataTooSmall)?;
+                let len = bytes.len();
+                writes.push((offset, bytes));
+                offset += len;
+            } else if offset != old_start {
+                // Shifted by an earlier section that changed length
+                writes.push((offset, data[old_start..old_end].to_vec()));
+                offset += old_end - old_start;
+            } else {
+                offset = old_end;
+            }
+        }
+
+        if offset > data.len() {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        for (start, bytes) in writes {
+            data[start..start + bytes.len()].copy_from_slice(&bytes);
+        }
+        Ok(())
+    }
+
+    /// Decode the state, remembering where each section ends in `data`. Fails
+    /// with `UnsupportedStateVersion` for accounts of a newer layout.
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        match data.first().copied().unwrap_or(0) {
+            0..=STATE_VERSION => Self::deserialize_v1(data),
+            _ => Err(GsnError::UnsupportedStateVersion.into()),
+        }
+    }
+
+    fn deserialize_v1(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut buf = data;
+        let mut ends = [0; 36];
+        let mut end = |buf: &[u8], section: Section| ends[section as usize] = data.len() - buf.len();
+
+        let version = decode(&mut buf)?;
+        let config = decode(&mut buf)?;
+        end(buf, Section::Header);
+        let consumer = decode(&mut buf)?;
+        end(buf, Section::Consumer);
+        let executor = decode(&mut buf)?;
+        end(buf, Section::Executor);
+        let consumer_nonces = decode(&mut buf)?;
+        end(buf, Section::ConsumerNonces);
+        let transaction_executor = decode(&mut buf)?;
+        end(buf, Section::TransactionExecutor);
+        let lane_nonces = decode(&mut buf)?;
+        end(buf, Section::LaneNonces);
+        // Accounts written before the token sections existed end here
+        let token_consumer = decode_trailing(&mut buf)?;
+        end(buf, Section::TokenConsumer);
+        let token_executor = decode_trailing(&mut buf)?;
+        end(buf, Section::TokenExecutor);
+        let eth_consumers = decode_trailing(&mut buf)?;
+        end(buf, Section::EthConsumers);
+        let spending_limits = decode_trailing(&mut buf)?;
+        end(buf, Section::SpendingLimits);
+        let paused = decode_trailing(&mut buf)?;
+        end(buf, Section::Paused);
+        let registered_executors = decode_trailing(&mut buf)?;
+        end(buf, Section::RegisteredExecutors);
+        let executor_stakes = decode_trailing(&mut buf)?;
+        end(buf, Section::ExecutorStakes);
+        let treasury = decode_trailing(&mut buf)?;
+        end(buf, Section::Treasury);
+        let sponsor_usage = decode_trailing(&mut buf)?;
+        end(buf, Section::SponsorUsage);
+        let free_txs = decode_trailing(&mut buf)?;
+        end(buf, Section::FreeTxs);
+        let epoch_volumes = decode_trailing(&mut buf)?;
+        end(buf, Section::EpochVolumes);
+        let payout_addresses = decode_trailing(&mut buf)?;
+        end(buf, Section::PayoutAddresses);
+        let nonce_windows = decode_trailing(&mut buf)?;
+        end(buf, Section::NonceWindows);
+        let channel_nonces = decode_trailing(&mut buf)?;
+        end(buf, Section::ChannelNonces);
+        let recent_payloads = decode_trailing(&mut buf)?;
+        end(buf, Section::RecentPayloads);
+        let ledger_accounts = decode_trailing(&mut buf)?;
+        end(buf, Section::LedgerAccounts);
+        let withdrawal_policy = decode_trailing(&mut buf)?;
+        end(buf, Section::WithdrawalPolicy);
+        let withdrawal_requests = decode_trailing(&mut buf)?;
+        end(buf, Section::WithdrawalRequests);
+        let escrowed_fees = decode_trailing(&mut buf)?;
+        end(buf, Section::EscrowedFees);
+        let in_flight = decode_trailing(&mut buf)?;
+        end(buf, Section::InFlight);
+        let rate_limit = decode_trailing(&mut buf)?;
+        end(buf, Section::RateLimit);
+        let rate_windows = decode_trailing(&mut buf)?;
+        end(buf, Section::RateWindows);
+        let blacklist = decode_trailing(&mut buf)?;
+        end(buf, Section::Blacklist);
+        let circuit_breaker = decode_trailing(&mut buf)?;
+        end(buf, Section::CircuitBreaker);
+        let stats = decode_trailing(&mut buf)?;
+        end(buf, Section::Stats);
+        let rewarded_epoch = decode_trailing(&mut buf)?;
+        end(buf, Section::RewardedEpoch);
+        let insurance_pool = decode_trailing(&mut buf)?;
+        end(buf, Section::InsurancePool);
+        let failure_reports = decode_trailing(&mut buf)?;
+        end(buf, Section::FailureReports);
+        let compensations = decode_trailing(&mut buf)?;
+        end(buf, Section::Compensations);
+        let eth_registration_nonces = decode_trailing(&mut buf)?;
+        end(buf, Section::EthRegistrationNonces);
+
+        Ok(Self {
+            version,
+            config,
+            consumer,
+            executor,
+            consumer_nonces,
+            transaction_executor,
+            lane_nonces,
+            token_consumer,
+            token_executor,
+            eth_consumers,
+            spending_limits,
+            paused,
+            registered_executors,
+            executor_stakes,
+            treasury,
+            sponsor_usage,
+            free_txs,
+            epoch_volumes,
+            payout_addresses,
+            nonce_windows,
+            channel_nonces,
+            recent_payloads,
+            ledger_accounts,
+            withdrawal_policy,
+            withdrawal_requests,
+            escrowed_fees,
+            in_flight,
+            rate_limit,
+            rate_windows,
+            blacklist,
+            circuit_breaker,
+            stats,
+            rewarded_epoch,
+            insurance_pool,
+            failure_reports,
+            compensations,
+            eth_registration_nonces,
+            dirty: DirtySections::default(),
+            section_ends: Some(ends),
+            capacity: Some(data.len()),
+            loaded_ledger_len: 0,
+        })
+    }
+
+    fn serialize_section<W: std::io::Write>(
+        &self,
+        section: Section,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        match section {
+            Section::Header => {
+                self.version.serialize(writer)?;
+                self.config.serialize(writer)
+            }
+            Section::Consumer => self.consumer.serialize(writer),
+            Section::Executor => self.executor.serialize(writer),
+            Section::ConsumerNonces => self.consumer_nonces.serialize(writer),
+            Section::TransactionExecutor => self.transaction_executor.serialize(writer),
+            Section::LaneNonces => self.lane_nonces.serialize(writer),
+            Section::TokenConsumer => self.token_consumer.serialize(writer),
+            Section::TokenExecutor => self.token_executor.serialize(writer),
+            Section::EthConsumers => self.eth_consumers.serialize(writer),
+            Section::SpendingLimits => self.spending_limits.serialize(writer),
+            Section::Paused => self.paused.serialize(writer),
+            Section::RegisteredExecutors => self.registered_executors.serialize(writer),
+            Section::ExecutorStakes => self.executor_stakes.serialize(writer),
+            Section::Treasury => self.treasury.serialize(writer),
+            Section::SponsorUsage => self.sponsor_usage.serialize(writer),
+            Section::FreeTxs => self.free_txs.serialize(writer),
+            Section::EpochVolumes => self.epoch_volumes.serialize(writer),
+            Section::PayoutAddresses => self.payout_addresses.serialize(writer),
+            Section::NonceWindows => self.nonce_windows.serialize(writer),
+            Section::ChannelNonces => self.channel_nonces.serialize(writer),
+            Section::RecentPayloads => self.recent_payloads.serialize(writer),
+            Section::LedgerAccounts => self.ledger_accounts.serialize(writer),
+            Section::WithdrawalPolicy => self.withdrawal_policy.serialize(writer),
+            Section::WithdrawalRequests => self.withdrawal_requests.serialize(writer),
+            Section::EscrowedFees => self.escrowed_fees.serialize(writer),
+            Section::InFlight => self.in_flight.serialize(writer),
+            Section::RateLimit => self.rate_limit.serialize(writer),
+            Section::RateWindows => self.rate_windows.serialize(writer),
+            Section::Blacklist => self.blacklist.serialize(writer),
+            Section::CircuitBreaker => self.circuit_breaker.serialize(writer),
+            Section::Stats => self.stats.serialize(writer),
+            Section::RewardedEpoch => self.rewarded_epoch.serialize(writer),
+            Section::InsurancePool => self.insurance_pool.serialize(writer),
+            Section::FailureReports => self.failure_reports.serialize(writer),
+            Section::Compensations => self.compensations.serialize(writer),
+            Section::EthRegistrationNonces => self.eth_registration_nonces.serialize(writer),
+        }
+    }
+
+    /// Encoded length of the state, from the entry count of each section
+    pub fn serialized_size(&self) -> usize {
+        HEADER_LEN
+            + map_len(self.consumer.len(), BALANCE_ENTRY_LEN)
+            + map_len(self.executor.len(), BALANCE_ENTRY_LEN)
+            + map_len(self.consumer_nonces.len(), BALANCE_ENTRY_LEN)
+            + map_len(self.transaction_executor.len(), RECEIPT_ENTRY_LEN)
+            + map_len(self.lane_nonces.len(), LANE_NONCE_ENTRY_LEN)
+            + map_len(self.token_consumer.len(), TOKEN_BALANCE_ENTRY_LEN)
+            + map_len(self.token_executor.len(), TOKEN_BALANCE_ENTRY_LEN)
+            + map_len(self.eth_consumers.len(), ETH_CONSUMER_ENTRY_LEN)
+            + map_len(self.spending_limits.len(), SPENDING_LIMIT_ENTRY_LEN)
+            + PAUSED_LEN
+            + map_len(self.registered_executors.len(), REGISTERED_EXECUTOR_ENTRY_LEN)
+            + map_len(self.executor_stakes.len(), EXECUTOR_STAKE_ENTRY_LEN)
+            + TREASURY_LEN
+            + map_len(self.sponsor_usage.len(), SPONSOR_USAGE_ENTRY_LEN)
+            + map_len(self.free_txs.len(), BALANCE_ENTRY_LEN)
+            + map_len(self.epoch_volumes.len(), EPOCH_VOLUME_ENTRY_LEN)
+            + map_len(self.payout_addresses.len(), PAYOUT_ADDRESS_ENTRY_LEN)
+            + map_len(self.nonce_windows.len(), NONCE_WINDOW_ENTRY_LEN)
+            + map_len(self.channel_nonces.len(), CHANNEL_NONCE_ENTRY_LEN)
+            + 2
+            + map_len(self.recent_payloads.retention(), PAYLOAD_HASH_LEN)
+            + map_len(self.ledger_accounts.len(), LEDGER_ACCOUNT_ENTRY_LEN)
+            + WITHDRAWAL_POLICY_LEN
+            + map_len(self.withdrawal_requests.len(), WITHDRAWAL_REQUEST_ENTRY_LEN)
+            + map_len(self.escrowed_fees.len(), ESCROWED_FEE_ENTRY_LEN)
+            + IN_FLIGHT_LEN
+            + RATE_LIMIT_LEN
+            + map_len(self.rate_windows.len(), RATE_WINDOW_ENTRY_LEN)
+            + map_len(self.blacklist.len(), BLACKLIST_ENTRY_LEN)
+            + CIRCUIT_BREAKER_LEN
+            + self.stats.serialized_len()
+            + REWARDED_EPOCH_LEN
+            + self.rewarded_epoch.map_or(0, |_| 8)
+            + INSURANCE_POOL_LEN
+            + map_len(self.failure_reports.len(), FAILURE_REPORT_ENTRY_LEN)
+            + map_len(self.compensations.len(), COMPENSATION_ENTRY_LEN)
+            + map_len(self.eth_registration_nonces.len(), ETH_REGISTRATION_NONCE_ENTRY_LEN)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.version != 0
+    }
+
+    /// Bring the state to [`STATE_VERSION`], returning the version it had.
+    /// Every section is rewritten, in the current layout.
+    pub fn migrate(&mut self) -> u8 {
+        let version = self.version;
+        self.version = STATE_VERSION;
+        for section in Section::ALL.iter() {
+            self.dirty.mark(*section);
+        }
+        version
+    }
+
+    /// Unused bytes of the account the state was decoded from, `None` for a
+    /// state that wasn't decoded from an account
+    pub fn remaining_capacity(&self) -> Option<usize> {
+        let size = self.serialized_size() - self.loaded_ledger_len;
+        self.capacity.map(|capacity| capacity.saturating_sub(size))
+    }
+
+    /// Let capacity checks count on the account growing to `len` bytes
+    pub fn allow_growth(&mut self, len: usize) {
+        self.capacity = self.capacity.map(|capacity| capacity.max(len));
+    }
+
+    /// Largest length an instruction can realloc an account of `len` bytes to
+    pub fn max_grown_len(len: usize) -> usize {
+        (len + MAX_PERMITTED_DATA_INCREASE).min(MAX_PERMITTED_DATA_LENGTH as usize)
+    }
+
+    /// Length to realloc an account of `len` bytes to so the state fits, in
+    /// steps of [`STATE_GROWTH_STEP`], `None` if it fits already
+    pub fn grown_len(&self, len: usize) -> Option<usize> {
+        let size = self.serialized_size();
+        if size <= len {
+            return None;
+        }
+        let steps = (size - len).div_ceil(STATE_GROWTH_STEP);
+        Some((len + steps * STATE_GROWTH_STEP).min(Self::max_grown_len(len)))
+    }
+
+    fn ensure_capacity(&self, growth: usize, error: GsnError) -> Result<(), GsnError> {
+        match self.remaining_capacity() {
+            Some(remaining) if remaining < growth => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails with `ConsumerRegistryFull` when a top-up would register a new
+    /// consumer the account has no room for
+    pub fn check_topup_capacity(&self, consumer: &Pubkey) -> Result<(), GsnError> {
+        if self.consumer.contains_key(consumer) {
+            return Ok(());
+        }
+        self.ensure_capacity(BALANCE_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Fails with `ConsumerRegistryFull` when a token top-up would register a
+    /// new token balance the account has no room for
+    pub fn check_token_topup_capacity(&self, consumer: &Pubkey, mint: &Pubkey) -> Result<(), GsnError> {
+        if self.token_consumer.contains_key(&(*consumer, *mint)) {
+            return Ok(());
+        }
+        self.ensure_capacity(TOKEN_BALANCE_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Fails with `ConsumerRegistryFull` when registering `eth_address` would
+    /// add entries the account has no room for
+    pub fn check_eth_registration_capacity(&self, eth_address: &[u8; 20]) -> Result<(), GsnError> {
+        let mut added = 0;
+        if !self.eth_consumers.contains_key(eth_address) {
+            added += ETH_CONSUMER_ENTRY_LEN;
+        }
+        if !self.eth_registration_nonces.contains_key(eth_address) {
+            added += ETH_REGISTRATION_NONCE_ENTRY_LEN;
+        }
+        if added == 0 {
+            return Ok(());
+        }
+        self.ensure_capacity(added, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Fails with `ConsumerRegistryFull` when limiting `consumer` would add an
+    /// entry the account has no room for
+    pub fn check_spending_limit_capacity(&self, consumer: &Pubkey) -> Result<(), GsnError> {
+        if self.spending_limits.contains_key(consumer) {
+            return Ok(());
+        }
+        self.ensure_capacity(SPENDING_LIMIT_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Fails with `ConsumerRegistryFull` when registering `executor` would
+    /// add an entry the account has no room for
+    pub fn check_executor_registration_capacity(&self, executor: &Pubkey) -> Result<(), GsnError> {
+        if self.registered_executors.contains_key(executor) {
+            return Ok(());
+        }
+        self.ensure_capacity(REGISTERED_EXECUTOR_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Fails with `ConsumerRegistryFull` when blacklisting `address` would add
+    /// an entry the account has no room for
+    pub fn check_blacklist_capacity(&self, address: &Pubkey) -> Result<(), GsnError> {
+        if self.blacklist.contains_key(address) {
+            return Ok(());
+        }
+        self.ensure_capacity(BLACKLIST_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Fails with `ConsumerRegistryFull` when `executor`'s first stake would
+    /// add an entry the account has no room for
+    pub fn check_stake_capacity(&self, executor: &Pubkey) -> Result<(), GsnError> {
+        if self.executor_stakes.contains_key(executor) {
+            return Ok(());
+        }
+        self.ensure_capacity(EXECUTOR_STAKE_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Fails with `HistoryFull` when the account has no room to retain
+    /// `retention` payload hashes
+    pub fn check_payload_retention_capacity(&self, retention: usize) -> Result<(), GsnError> {
+        let growth = retention.saturating_sub(self.recent_payloads.retention()) * PAYLOAD_HASH_LEN;
+        self.ensure_capacity(growth, GsnError::HistoryFull)
+    }
+
+    /// Fails with `ConsumerRegistryFull` when recording that `key` opened a
+    /// ledger account would need more room than moving its entries out frees
+    pub fn check_ledger_capacity(&self, key: &Pubkey) -> Result<(), GsnError> {
+        if self.ledger_accounts.contains_key(key) {
+            return Ok(());
+        }
+        let freed = [&self.consumer, &self.consumer_nonces, &self.executor]
+            .iter()
+            .filter(|map| map.contains_key(key))
+            .count()
+            * BALANCE_ENTRY_LEN;
+        self.ensure_capacity(
+            LEDGER_ACCOUNT_ENTRY_LEN.saturating_sub(freed),
+            GsnError::ConsumerRegistryFull,
+        )
+    }
+
+    /// Fails with `ConsumerRegistryFull` when `executor`'s first payout address
+    /// would add an entry the account has no room for
+    pub fn check_payout_capacity(&self, executor: &Pubkey) -> Result<(), GsnError> {
+        if self.payout_addresses.contains_key(executor) {
+            return Ok(());
+        }
+        self.ensure_capacity(PAYOUT_ADDRESS_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Growth of the nonce entry using a nonce of `consumer`'s lane may
+    /// create; `executor` names the channel of `CHANNEL_NONCE_LANE`
+    fn nonce_growth(&self, consumer: &Pubkey, lane: u64, executor: &Pubkey) -> usize {
+        match lane {
+            0 if !self.consumer_nonces.contains_key(consumer) => BALANCE_ENTRY_LEN,
+            UNORDERED_NONCE_LANE if !self.nonce_windows.contains_key(consumer) => {
+                NONCE_WINDOW_ENTRY_LEN
+            }
+            CHANNEL_NONCE_LANE if !self.channel_nonces.contains_key(&(*consumer, *executor)) => {
+                CHANNEL_NONCE_ENTRY_LEN
+            }
+            0 | UNORDERED_NONCE_LANE | CHANNEL_NONCE_LANE => 0,
+            _ if !self.lane_nonces.contains_key(&(*consumer, lane)) => LANE_NONCE_ENTRY_LEN,
+            _ => 0,
+        }
+    }
+
+    /// Growth of the receipt of a submission by `executor` and the nonce
+    /// entry it may create. Channel submissions have no receipt, the channel
+    /// already names the executor.
+    fn receipt_growth(&self, consumer: &Pubkey, lane: u64, executor: &Pubkey) -> usize {
+        let receipt = if lane == CHANNEL_NONCE_LANE { 0 } else { RECEIPT_ENTRY_LEN };
+        receipt + self.nonce_growth(consumer, lane, executor)
+    }
+
+    /// Fails with `HistoryFull` when cancelling a nonce of `consumer`'s lane
+    /// would add a nonce entry the account has no room for
+    pub fn check_nonce_capacity(
+        &self,
+        consumer: &Pubkey,
+        lane: u64,
+        executor: &Pubkey,
+    ) -> Result<(), GsnError> {
+        self.ensure_capacity(self.nonce_growth(consumer, lane, executor), GsnError::HistoryFull)
+    }
+
+    /// [`GsnInfo::check_submit_capacity`] of a submission whose fee is paid
+    /// in `mint` and credited to `executor`'s earnings in that mint
+    pub fn check_token_submit_capacity(
+        &self,
+        consumer: &Pubkey,
+        lane: u64,
+        executor: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<(), GsnError> {
+        let mut growth = self.receipt_growth(consumer, lane, executor);
+        if !self.token_executor.contains_key(&(*executor, *mint)) {
+            growth += TOKEN_BALANCE_ENTRY_LEN;
+        }
+        self.ensure_capacity(growth, GsnError::HistoryFull)
+    }
+
+    /// Fails with `HistoryFull` when the receipt of a submission, plus the
+    /// nonce and executor entries it may create, doesn't fit in the account.
+    /// `executors` are the keys credited with the fee, the fee payer first.
+    pub fn check_submit_capacity(
+        &self,
+        consumer: &Pubkey,
+        lane: u64,
+        executors: &[&Pubkey],
+    ) -> Result<(), GsnError> {
+        self.ensure_capacity(self.submit_growth(consumer, lane, executors), GsnError::HistoryFull)
+    }
+
+    /// [`GsnInfo::check_submit_capacity`] of a submission that is counted
+    /// against the quota of `sponsor`, against the free tier when `free_tx`,
+    /// or towards the consumer's volume discount when `volume`
+    pub fn check_counted_submit_capacity(
+        &self,
+        consumer: &Pubkey,
+        lane: u64,
+        executors: &[&Pubkey],
+        sponsor: Option<&Pubkey>,
+        free_tx: bool,
+        volume: bool,
+    ) -> Result<(), GsnError> {
+        let mut growth = self.submit_growth(consumer, lane, executors);
+        if let Some(sponsor) = sponsor {
+            if !self.sponsor_usage.contains_key(&(*sponsor, *consumer)) {
+                growth += SPONSOR_USAGE_ENTRY_LEN;
+            }
+        }
+        if free_tx && !self.free_txs.contains_key(consumer) {
+            growth += BALANCE_ENTRY_LEN;
+        }
+        if volume && !self.epoch_volumes.contains_key(consumer) {
+            growth += EPOCH_VOLUME_ENTRY_LEN;
+        }
+        self.ensure_capacity(growth, GsnError::HistoryFull)
+    }
+
+    fn submit_growth(&self, consumer: &Pubkey, lane: u64, executors: &[&Pubkey]) -> usize {
+        let mut growth = match executors.first() {
+            Some(executor) => self.receipt_growth(consumer, lane, executor),
+            None => self.receipt_growth(consumer, lane, &Pubkey::default()),
+        };
+        for executor in executors {
+            if !self.executor.contains_key(executor) {
+                growth += BALANCE_ENTRY_LEN;
+            }
+        }
+        growth
+    }
+
+    /// Sections mutated since the state was decoded
+    pub fn dirty(&self) -> DirtySections {
+        self.dirty
+    }
+
+    /// Record a mutation made through a public field
+    pub fn mark_dirty(&mut self, section: Section) {
+        self.dirty.mark(section);
+    }
+
+    /// Consumer balances, marked dirty
+    pub fn consumer_mut(&mut self) -> &mut SortedMap<Pubkey, u64> {
+        self.dirty.mark(Section::Consumer);
+        &mut self.consumer
+    }
+
+    /// Executor earnings, marked dirty
+    pub fn executor_mut(&mut self) -> &mut SortedMap<Pubkey, u64> {
+        self.dirty.mark(Section::Executor);
+        &mut self.executor
+    }
+
+    /// Token balances, marked dirty
+    pub fn token_consumer_mut(&mut self) -> &mut SortedMap<(Pubkey, Pubkey), u64> {
+        self.dirty.mark(Section::TokenConsumer);
+        &mut self.token_consumer
+    }
+
+    /// Token earnings, marked dirty
+    pub fn token_executor_mut(&mut self) -> &mut SortedMap<(Pubkey, Pubkey), u64> {
+        self.dirty.mark(Section::TokenExecutor);
+        &mut self.token_executor
+    }
+
+    /// Ethereum address registry, marked dirty
+    pub fn eth_consumers_mut(&mut self) -> &mut SortedMap<[u8; 20], Pubkey> {
+        self.dirty.mark(Section::EthConsumers);
+        &mut self.eth_consumers
+    }
+
+    /// Nonce the next registration of `eth_address` must sign, zero before
+    /// its first
+    pub fn eth_registration_nonce(&self, eth_address: &[u8; 20]) -> u64 {
+        self.eth_registration_nonces
+            .get(eth_address)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Registers `eth_address` to `consumer`, using up its registration nonce
+    pub fn register_eth_address(&mut self, eth_address: [u8; 20], consumer: Pubkey) {
+        let next = self.eth_registration_nonce(&eth_address).saturating_add(1);
+        self.dirty.mark(Section::EthRegistrationNonces);
+        self.eth_registration_nonces.insert(eth_address, next);
+        self.eth_consumers_mut().insert(eth_address, consumer);
+    }
+
+    /// Fails with `ProtocolPaused` while the deployment is paused
+    pub fn check_not_paused(&self) -> Result<(), GsnError> {
+        if self.paused {
+            return Err(GsnError::ProtocolPaused);
+        }
+        Ok(())
+    }
+
+    /// Fails with `Blacklisted` when governance blacklisted `address`
+    pub fn check_not_blacklisted(&self, address: &Pubkey) -> Result<(), GsnError> {
+        if self.blacklist.contains_key(address) {
+            return Err(GsnError::Blacklisted);
+        }
+        Ok(())
+    }
+
+    pub fn blacklist_address(&mut self, address: Pubkey) {
+        self.dirty.mark(Section::Blacklist);
+        self.blacklist.insert(address, true);
+    }
+
+    pub fn unblacklist_address(&mut self, address: &Pubkey) {
+        self.dirty.mark(Section::Blacklist);
+        self.blacklist.remove(address);
+    }
+
+    /// Fails with `ReentrantCall` while a submission's CPI is in flight
+    pub fn check_not_in_flight(&self) -> Result<(), GsnError> {
+        if self.in_flight {
+            return Err(GsnError::ReentrantCall);
+        }
+        Ok(())
+    }
+
+    /// Mark a submission's CPI as running, or returned
+    pub fn set_in_flight(&mut self, in_flight: bool) {
+        self.dirty.mark(Section::InFlight);
+        self.in_flight = in_flight;
+    }
+
+    /// Pause or resume the deployment; resuming ends the emergency mode of
+    /// withdrawals
+    pub fn set_paused(&mut self, paused: bool) {
+        self.dirty.mark(Section::Paused);
+        self.paused = paused;
+        if !paused && self.withdrawal_policy.emergency {
+            self.withdrawal_policy_mut().emergency = false;
+        }
+    }
+
+    /// Withdrawal cooldown and emergency mode, marked dirty
+    pub fn withdrawal_policy_mut(&mut self) -> &mut WithdrawalPolicy {
+        self.dirty.mark(Section::WithdrawalPolicy);
+        &mut self.withdrawal_policy
+    }
+
+    /// Whether withdrawals skip the pause and the cooldown
+    pub fn is_emergency_withdrawal(&self) -> bool {
+        self.paused && self.withdrawal_policy.emergency
+    }
+
+    /// Pending withdrawals, marked dirty
+    pub fn withdrawal_requests_mut(&mut self) -> &mut SortedMap<Pubkey, WithdrawalRequest> {
+        self.dirty.mark(Section::WithdrawalRequests);
+        &mut self.withdrawal_requests
+    }
+
+    /// Fails with `ConsumerRegistryFull` when `consumer`'s first withdrawal
+    /// request would add an entry the account has no room for
+    pub fn check_withdrawal_request_capacity(&self, consumer: &Pubkey) -> Result<(), GsnError> {
+        if self.withdrawal_requests.contains_key(consumer) {
+            return Ok(());
+        }
+        self.ensure_capacity(WITHDRAWAL_REQUEST_ENTRY_LEN, GsnError::ConsumerRegistryFull)
+    }
+
+    /// Take `amount` out of `consumer`'s withdrawal request, which must have
+    /// cooled down by `slot`; fails with `WithdrawalCooldown` otherwise
+    pub fn take_withdrawal_request(
+        &mut self,
+        consumer: &Pubkey,
+        amount: u64,
+        slot: u64,
+    ) -> Result<(), GsnError> {
+        let request = match self.withdrawal_requests.get(consumer) {
+            Some(request) if slot >= request.unlock_slot && amount <= request.amount => *request,
+            _ => return Err(GsnError::WithdrawalCooldown),
+        };
+        if request.amount == amount {
+            self.withdrawal_requests_mut().remove(consumer);
+        } else {
+            self.withdrawal_requests_mut().insert(
+                *consumer,
+                WithdrawalRequest {
+                    amount: request.amount - amount,
+                    ..request
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Rate limit of consumers' submissions, marked dirty
+    pub fn rate_limit_mut(&mut self) -> &mut RateLimit {
+        self.dirty.mark(Section::RateLimit);
+        &mut self.rate_limit
+    }
+
+    /// Set the rate limit; lifting it drops every window
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) {
+        *self.rate_limit_mut() = rate_limit;
+        if rate_limit.max_submissions == 0 && !self.rate_windows.is_empty() {
+            *self.rate_windows_mut() = SortedMap::new();
+        }
+    }
+
+    /// Consumers' rate limit windows, marked dirty
+    pub fn rate_windows_mut(&mut self) -> &mut SortedMap<Pubkey, RateWindow> {
+        self.dirty.mark(Section::RateWindows);
+        &mut self.rate_windows
+    }
+
+    /// `consumer`'s window as of `slot`: a new one once its current window
+    /// lasted `window_slots`
+    fn rate_window(&self, consumer: &Pubkey, slot: u64) -> RateWindow {
+        match self.rate_windows.get(consumer) {
+            Some(window)
+                if slot < window.start_slot.saturating_add(self.rate_limit.window_slots) =>
+            {
+                *window
+            }
+            _ => RateWindow {
+                start_slot: slot,
+                submissions: 0,
+            },
+        }
+    }
+
+    /// Fails with `RateLimited` when `consumer` already made the submissions
+    /// the rate limit allows in its window as of `slot`
+    pub fn check_rate_limit(&self, consumer: &Pubkey, slot: u64) -> Result<(), GsnError> {
+        let max_submissions = self.rate_limit.max_submissions;
+        if max_submissions > 0 && self.rate_window(consumer, slot).submissions >= max_submissions {
+            return Err(GsnError::RateLimited);
+        }
+        Ok(())
+    }
+
+    /// Fails with `HistoryFull` when counting `consumer`'s submission against
+    /// the rate limit would add an entry the account has no room for
+    pub fn check_rate_window_capacity(&self, consumer: &Pubkey) -> Result<(), GsnError> {
+        if self.rate_limit.max_submissions == 0 || self.rate_windows.contains_key(consumer) {
+            return Ok(());
+        }
+        self.ensure_capacity(RATE_WINDOW_ENTRY_LEN, GsnError::HistoryFull)
+    }
+
+    /// Count a submission of `consumer` at `slot` in its window, while a
+    /// rate limit is set
+    pub fn record_rate_limited_submission(&mut self, consumer: &Pubkey, slot: u64) {
+        if self.rate_limit.max_submissions == 0 {
+            return;
+        }
+        let mut window = self.rate_window(consumer, slot);
+        window.submissions += 1;
+        self.rate_windows_mut().insert(*consumer, window);
+    }
+
+    /// Circuit breaker threshold and counts, marked dirty
+    pub fn circuit_breaker_mut(&mut self) -> &mut CircuitBreaker {
+        self.dirty.mark(Section::CircuitBreaker);
+        &mut self.circuit_breaker
+    }
+
+    /// Count an execution in `epoch` while the circuit breaker is on, pausing
+    /// the deployment when it trips. Returns whether it tripped.
+    pub fn record_execution_outcome(&mut self, epoch: u64, succeeded: bool) -> bool {
+        if !self.circuit_breaker.is_enabled() {
+            return false;
+        }
+        // Reports are only kept for the epoch whose counts they went into
+        if epoch != self.circuit_breaker.epoch && !self.failure_reports.is_empty() {
+            self.dirty.mark(Section::FailureReports);
+            self.failure_reports = SortedMap::new();
+        }
+        let tripped = self.circuit_breaker_mut().record(epoch, succeeded) && !self.paused;
+        if tripped {
+            self.set_paused(true);
+        }
+        tripped
+    }
+
+    /// Fails with `InvalidFailureReport` unless `consumer` holds a balance,
+    /// `lane` is lane 0, a counted lane it submitted on before or its channel
+    /// with `executor`, `nonce` is the lane's next nonce and it wasn't
+    /// reported failed in `epoch` yet. A failed relay doesn't advance the
+    /// nonce, so every relay is reported at most once. Fails with
+    /// `FailureReportLimit` once `executor` reported
+    /// `MAX_FAILURE_REPORTS_PER_EXECUTOR` failures in `epoch`.
+    pub fn check_failure_report(
+        &self,
+        epoch: u64,
+        consumer: &Pubkey,
+        executor: &Pubkey,
+        lane: u64,
+        nonce: u64,
+    ) -> Result<(), GsnError> {
+        let known = !self.consumer_balances(consumer).is_empty()
+            || self.ledger_flags(consumer) & CONSUMER_LEDGER != 0;
+        let expected = match lane {
+            _ if !known => None,
+            0 => Some(self.get_next_nonce(consumer)),
+            CHANNEL_NONCE_LANE => self.channel_nonces.get(&(*consumer, *executor)).copied(),
+            lane if lane < MAX_NONCE_LANES => self.lane_nonces.get(&(*consumer, lane)).copied(),
+            _ => None,
+        };
+        let current = epoch == self.circuit_breaker.epoch;
+        let reported = current
+            && self
+                .failure_reports
+                .get(&(*consumer, lane))
+                .is_some_and(|report| report.nonce == nonce);
+        if expected != Some(nonce) || reported {
+            return Err(GsnError::InvalidFailureReport);
+        }
+        let reports = self
+            .failure_reports
+            .values()
+            .filter(|report| current && report.executor == *executor)
+            .count();
+        if reports >= MAX_FAILURE_REPORTS_PER_EXECUTOR {
+            return Err(GsnError::FailureReportLimit);
+        }
+        self.check_failure_report_capacity(consumer, lane)
+    }
+
+    /// Fails with `HistoryFull` when the first report of `consumer`'s `lane`
+    /// would add an entry the account has no room for
+    pub fn check_failure_report_capacity(&self, consumer: &Pubkey, lane: u64) -> Result<(), GsnError> {
+        if self.failure_reports.contains_key(&(*consumer, lane)) {
+            return Ok(());
+        }
+        self.ensure_capacity(FAILURE_REPORT_ENTRY_LEN, GsnError::HistoryFull)
+    }
+
+    /// Remember the failed relay of `nonce` on `consumer`'s `lane` that
+    /// `executor` reported
+    pub fn record_failure_report(&mut self, consumer: &Pubkey, executor: &Pubkey, lane: u64, nonce: u64) {
+        self.dirty.mark(Section::FailureReports);
+        self.failure_reports
+            .insert((*consumer, lane), FailureReport { nonce, executor: *executor });
+    }
+
+    /// Execution counters, marked dirty
+    pub fn stats_mut(&mut self) -> &mut Stats {
+        self.dirty.mark(Section::Stats);
+        &mut self.stats
+    }
+
+    /// Turn the counting of executions on from `epoch`, or off, dropping the
+    /// counters
+    pub fn set_stats_tracking(&mut self, enabled: bool, epoch: u64) {
+        if enabled == self.stats.enabled {
+            return;
+        }
+        *self.stats_mut() = match enabled {
+            true => Stats {
+                enabled,
+                current: EpochStats::new(epoch),
+                previous: EpochStats::default(),
+            },
+            false => Stats::default(),
+        };
+    }
+
+    /// Fails with `HistoryFull` when counting an execution of `consumer`
+    /// relayed by `executor` in `epoch` would add entries the account has no
+    /// room for
+    pub fn check_stats_capacity(&self, epoch: u64, consumer: &Pubkey, executor: &Pubkey) -> Result<(), GsnError> {
+        if !self.stats.enabled {
+            return Ok(());
+        }
+        self.ensure_capacity(self.stats.growth(epoch, consumer, executor), GsnError::HistoryFull)
+    }
+
+    /// Shares of a reward of `amount` lamports for the executors of `stats`'s
+    /// epoch: registered, not blacklisted, with a stake bonded for at least
+    /// `min_stake`, weighted by their stake times their executions. Shares
+    /// round down; executors whose share rounds to 0 are left out.
+    pub fn reward_shares(&self, stats: &EpochStats, min_stake: u64, amount: u64) -> Vec<(Pubkey, u64)> {
+        let weights: Vec<(Pubkey, u128)> = stats
+            .executor_volumes
+            .iter()
+            .filter(|(executor, _)| {
+                self.registered_executors.contains_key(executor) && !self.blacklist.contains_key(executor)
+            })
+            .filter_map(|(executor, volume)| {
+                let stake = self.executor_stakes.get(executor)?;
+                if !stake.is_bonded(min_stake) {
+                    return None;
+                }
+                Some((*executor, stake.amount as u128 * volume.executions as u128))
+            })
+            .collect();
+        let total: u128 = weights.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return Vec::new();
+        }
+        weights
+            .into_iter()
+            .map(|(executor, weight)| (executor, (amount as u128 * weight / total) as u64))
+            .filter(|(_, share)| *share > 0)
+            .collect()
+    }
+
+    /// Fails with `HistoryFull` when crediting rewards to `executors` would
+    /// add balances the account has no room for
+    pub fn check_reward_capacity(&self, executors: &[&Pubkey]) -> Result<(), GsnError> {
+        let mut growth = executors
+            .iter()
+            .filter(|executor| !self.executor.contains_key(executor))
+            .count()
+            * BALANCE_ENTRY_LEN;
+        if self.rewarded_epoch.is_none() {
+            growth += 8;
+        }
+        self.ensure_capacity(growth, GsnError::HistoryFull)
+    }
+
+    pub fn set_rewarded_epoch(&mut self, epoch: u64) {
+        self.dirty.mark(Section::RewardedEpoch);
+        self.rewarded_epoch = Some(epoch);
+    }
+
+    /// Escrowed fees, marked dirty
+    pub fn escrowed_fees_mut(&mut self) -> &mut SortedMap<(Pubkey, Pubkey), u64> {
+        self.dirty.mark(Section::EscrowedFees);
+        &mut self.escrowed_fees
+    }
+
+    /// Fails with `HistoryFull` when escrowing a fee charged to `charged` in
+    /// `mint` would add an entry the account has no room for
+    pub fn check_fee_escrow_capacity(&self, charged: &Pubkey, mint: &Pubkey) -> Result<(), GsnError> {
+        if self.escrowed_fees.contains_key(&(*charged, *mint)) {
+            return Ok(());
+        }
+        self.ensure_capacity(ESCROWED_FEE_ENTRY_LEN, GsnError::HistoryFull)
+    }
+
+    /// Hold `fee`, already taken from `charged`'s balance in `mint`, until
+    /// its submission settles
+    pub fn escrow_fee(&mut self, charged: &Pubkey, mint: &Pubkey, fee: u64) {
+        let key = (*charged, *mint);
+        let held = self.escrowed_fees.get(&key).copied().unwrap_or(0);
+        self.escrowed_fees_mut().insert(key, held.saturating_add(fee));
+    }
+
+    /// Take `fee` back out of the escrow of `charged` in `mint`; fails with
+    /// `InsufficientBalance` when less is held
+    pub fn release_escrowed_fee(&mut self, charged: &Pubkey, mint: &Pubkey, fee: u64) -> Result<(), GsnError> {
+        let key = (*charged, *mint);
+        match self.escrowed_fees.get(&key).copied() {
+            Some(held) if held == fee => {
+                self.escrowed_fees_mut().remove(&key);
+            }
+            Some(held) if held > fee => {
+                self.escrowed_fees_mut().insert(key, held - fee);
+            }
+            _ => return Err(GsnError::InsufficientBalance),
+        }
+        Ok(())
+    }
+
+    /// Replace the state with `state`, decoded again from the account after
+    /// a CPI, keeping the growth the instruction may still count on
+    pub fn reload(&mut self, state: GsnInfo) {
+        let capacity = self.capacity.max(state.capacity);
+        *self = state;
+        self.capacity = capacity;
+    }
+
+    pub fn is_executor_registered(&self, executor: &Pubkey) -> bool {
+        self.registered_executors.contains_key(executor)
+    }
+
+    pub fn register_executor(&mut self, executor: Pubkey) {
+        self.dirty.mark(Section::RegisteredExecutors);
+        self.registered_executors.insert(executor, true);
+    }
+
+    pub fn deregister_executor(&mut self, executor: &Pubkey) {
+        self.dirty.mark(Section::RegisteredExecutors);
+        self.registered_executors.remove(executor);
+    }
+
+    /// Whether `executor` bonded at least `min_stake` and isn't unbonding
+    pub fn is_executor_bonded(&self, executor: &Pubkey, min_stake: u64) -> bool {
+        self.executor_stakes
+            .get(executor)
+            .is_some_and(|stake| stake.is_bonded(min_stake))
+    }
+
+    /// Executor stakes, marked dirty
+    pub fn executor_stakes_mut(&mut self) -> &mut SortedMap<Pubkey, ExecutorStake> {
+        self.dirty.mark(Section::ExecutorStakes);
+        &mut self.executor_stakes
+    }
+
+    pub fn credit_treasury(&mut self, amount: u64) {
+        self.dirty.mark(Section::Treasury);
+        self.treasury = self.treasury.saturating_add(amount);
+    }
+
+    /// Take `amount` out of the treasury, `None` when it holds less
+    pub fn debit_treasury(&mut self, amount: u64) -> Option<u64> {
+        let remaining = self.treasury.checked_sub(amount)?;
+        self.dirty.mark(Section::Treasury);
+        self.treasury = remaining;
+        Some(remaining)
+    }
+
+    pub fn credit_insurance_pool(&mut self, amount: u64) {
+        self.dirty.mark(Section::InsurancePool);
+        self.insurance_pool = self.insurance_pool.saturating_add(amount);
+    }
+
+    /// Take `amount` out of the insurance pool, `None` when it holds less
+    pub fn debit_insurance_pool(&mut self, amount: u64) -> Option<u64> {
+        let remaining = self.insurance_pool.checked_sub(amount)?;
+        self.dirty.mark(Section::InsurancePool);
+        self.insurance_pool = remaining;
+        Some(remaining)
+    }
+
+    /// Executor of `consumer`'s execution of `nonce` on `lane`, while it is
+    /// one of the `retention` latest of the lane (any with a retention of 0),
+    /// whose records `PruneRecords` keeps. Channel executions aren't
+    /// recorded: `channel_executor`'s channel executed `nonce` once its next
+    /// nonce is past it.
+    pub fn compensable_executor(
+        &self,
+        consumer: &Pubkey,
+        lane: u64,
+        nonce: u64,
+        channel_executor: Option<&Pubkey>,
+        retention: usize,
+    ) -> Option<Pubkey> {
+        let (executor, newer) = match (lane, channel_executor) {
+            (CHANNEL_NONCE_LANE, Some(executor)) => {
+                let next = self.get_next_channel_nonce(consumer, executor);
+                (*executor, next.checked_sub(nonce)?.checked_sub(1)?)
+            }
+            (CHANNEL_NONCE_LANE, None) | (_, Some(_)) => return None,
+            _ => {
+                let executor = *self.get_lane_transaction_executor(consumer, lane, nonce)?;
+                let newer = self
+                    .transaction_executor
+                    .keys()
+                    .filter(|(key, key_lane, key_nonce)| {
+                        key == consumer && *key_lane == lane && *key_nonce > nonce
+                    })
+                    .count();
+                (executor, newer as u64)
+            }
+        };
+        (retention == 0 || newer < retention as u64).then_some(executor)
+    }
+
+    /// Whether `Compensate` paid for `consumer`'s execution of `nonce` on
+    /// `lane` by `executor`
+    pub fn is_compensated(&self, consumer: &Pubkey, lane: u64, nonce: u64, executor: &Pubkey) -> bool {
+        self.compensations.contains_key(&(*consumer, lane, nonce, *executor))
+    }
+
+    /// Fails with `HistoryFull` when the account has no room for another
+    /// compensation
+    pub fn check_compensation_capacity(&self) -> Result<(), GsnError> {
+        self.ensure_capacity(COMPENSATION_ENTRY_LEN, GsnError::HistoryFull)
+    }
+
+    /// Remember that `amount` lamports compensated `consumer`'s execution of
+    /// `nonce` on `lane` by `executor`
+    pub fn record_compensation(&mut self, consumer: &Pubkey, lane: u64, nonce: u64, executor: &Pubkey, amount: u64) {
+        self.dirty.mark(Section::Compensations);
+        self.compensations
+            .insert((*consumer, lane, nonce, *executor), amount);
+    }
+
+    /// Submissions `sponsor` paid for `consumer`, counted once it set a quota
+    pub fn sponsored_submissions(&self, sponsor: &Pubkey, consumer: &Pubkey) -> u64 {
+        self.sponsor_usage
+            .get(&(*sponsor, *consumer))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn record_sponsored_submission(&mut self, sponsor: &Pubkey, consumer: &Pubkey) {
+        let used = self.sponsored_submissions(sponsor, consumer);
+        self.dirty.mark(Section::SponsorUsage);
+        self.sponsor_usage.insert((*sponsor, *consumer), used + 1);
+    }
+
+    /// Free submissions `consumer` used
+    pub fn free_txs_used(&self, consumer: &Pubkey) -> u64 {
+        self.free_txs.get(consumer).copied().unwrap_or(0)
+    }
+
+    pub fn record_free_tx(&mut self, consumer: &Pubkey) {
+        let used = self.free_txs_used(consumer);
+        self.dirty.mark(Section::FreeTxs);
+        self.free_txs.insert(*consumer, used + 1);
+    }
+
+    pub fn payout_address(&self, executor: &Pubkey) -> Option<&Pubkey> {
+        self.payout_addresses.get(executor)
+    }
+
+    pub fn set_payout_address(&mut self, executor: Pubkey, payout: Pubkey) {
+        self.dirty.mark(Section::PayoutAddresses);
+        self.payout_addresses.insert(executor, payout);
+    }
+
+    /// Whether `executor`'s fees may be claimed to `destination`: its own
+    /// address or its payout address
+    pub fn is_claim_destination(&self, executor: &Pubkey, destination: &Pubkey) -> bool {
+        executor == destination || self.payout_address(executor) == Some(destination)
+    }
+
+    /// Submissions of `consumer` in `epoch`
+    pub fn epoch_txs(&self, consumer: &Pubkey, epoch: u64) -> u64 {
+        self.epoch_volumes
+            .get(consumer)
+            .map_or(0, |volume| volume.txs_in(epoch))
+    }
+
+    pub fn record_epoch_tx(&mut self, consumer: &Pubkey, epoch: u64) {
+        let txs = self.epoch_txs(consumer, epoch);
+        self.dirty.mark(Section::EpochVolumes);
+        self.epoch_volumes
+            .insert(*consumer, EpochVolume { epoch, txs: txs + 1 });
+    }
+
+    /// Consumer fee limits, marked dirty
+    pub fn spending_limits_mut(&mut self) -> &mut SortedMap<Pubkey, SpendingLimit> {
+        self.dirty.mark(Section::SpendingLimits);
+        &mut self.spending_limits
+    }
+
+    /// Clear every lamport consumer balance and executor earning, the
+    /// treasury and the insurance pool, once the lamports backing them have
+    /// been swept after a shutdown. Token balances stay: the vault's token
+    /// accounts aren't swept and still back them.
+    pub fn clear_balances(&mut self) {
+        *self.consumer_mut() = SortedMap::new();
+        *self.executor_mut() = SortedMap::new();
+        self.treasury = 0;
+        self.insurance_pool = 0;
+        self.dirty.mark(Section::Treasury);
+        self.dirty.mark(Section::InsurancePool);
+    }
+
+    /// Clear the consumer balances but `keep`'s, the nonces and the receipts.
+    /// Executor earnings are kept so unclaimed fees stay claimable.
+    pub fn reset_registries(&mut self, keep: Option<&Pubkey>) {
+        let kept = keep.and_then(|key| self.consumer.get(key).map(|balance| (*key, *balance)));
+        self.consumer = SortedMap::new();
+        if let Some((key, balance)) = kept {
+            self.consumer.insert(key, balance);
+        }
+        self.consumer_nonces = SortedMap::new();
+        self.transaction_executor = SortedMap::new();
+        self.lane_nonces = SortedMap::new();
+        self.nonce_windows = SortedMap::new();
+        self.channel_nonces = SortedMap::new();
+        for section in [
+            Section::Consumer,
+            Section::ConsumerNonces,
+            Section::TransactionExecutor,
+            Section::LaneNonces,
+            Section::NonceWindows,
+            Section::ChannelNonces,
+        ] {
+            self.dirty.mark(section);
+        }
+    }
+
+    pub fn add_consumer(&mut self, address: Pubkey, amount: u64) -> bool {
+        self.consumer_mut().insert(address, amount);
+        true
+    }
+
+    pub fn add_executor(&mut self, address: Pubkey, amount: u64) -> bool {
+        self.executor_mut().insert(address, amount);
+        true
+    }
+
+    /// `consumer`'s top-up balance in `mint`, lamports for `NATIVE_FEE_TOKEN`
+    pub fn consumer_balance(&self, consumer: &Pubkey, mint: &Pubkey) -> u64 {
+        Self::mint_balance(&self.consumer, &self.token_consumer, consumer, mint)
+    }
+
+    /// `executor`'s unclaimed earnings in `mint`, lamports for `NATIVE_FEE_TOKEN`
+    pub fn executor_balance(&self, executor: &Pubkey, mint: &Pubkey) -> u64 {
+        Self::mint_balance(&self.executor, &self.token_executor, executor, mint)
+    }
+
+    /// Every balance `consumer` holds, by mint: lamports under
+    /// `NATIVE_FEE_TOKEN` first if it has a lamport balance, then its token
+    /// balances in mint order
+    pub fn consumer_balances(&self, consumer: &Pubkey) -> Vec<(Pubkey, u64)> {
+        Self::mint_balances(&self.consumer, &self.token_consumer, consumer)
+    }
+
+    /// Every balance `executor` earned, by mint, like `consumer_balances`
+    pub fn executor_balances(&self, executor: &Pubkey) -> Vec<(Pubkey, u64)> {
+        Self::mint_balances(&self.executor, &self.token_executor, executor)
+    }
+
+    fn mint_balance(
+        lamports: &SortedMap<Pubkey, u64>,
+        tokens: &SortedMap<(Pubkey, Pubkey), u64>,
+        key: &Pubkey,
+        mint: &Pubkey,
+    ) -> u64 {
+        let balance = match *mint == NATIVE_FEE_TOKEN {
+            true => lamports.get(key),
+            false => tokens.get(&(*key, *mint)),
+        };
+        balance.copied().unwrap_or(0)
+    }
+
+    fn mint_balances(
+        lamports: &SortedMap<Pubkey, u64>,
+        tokens: &SortedMap<(Pubkey, Pubkey), u64>,
+        key: &Pubkey,
+    ) -> Vec<(Pubkey, u64)> {
+        let native = lamports.get(key).map(|balance| (NATIVE_FEE_TOKEN, *balance));
+        let tokens = tokens
+            .iter()
+            .filter(|((owner, _), _)| owner == key)
+            .map(|((_, mint), balance)| (*mint, *balance));
+        native.into_iter().chain(tokens).collect()
+    }
+
+    pub fn new() -> Self {
+        Self {
+            version: STATE_VERSION,
+            config: Pubkey::default(),
+            consumer: SortedMap::new(),
+            executor: SortedMap::new(),
+            consumer_nonces: SortedMap::new(),
+            transaction_executor: SortedMap::new(),
+            lane_nonces: SortedMap::new(),
+            token_consumer: SortedMap::new(),
+            token_executor: SortedMap::new(),
+            eth_consumers: SortedMap::new(),
+            spending_limits: SortedMap::new(),
+            paused: false,
+            registered_executors: SortedMap::new(),
+            executor_stakes: SortedMap::new(),
+            treasury: 0,
+            sponsor_usage: SortedMap::new(),
+            free_txs: SortedMap::new(),
+            epoch_volumes: SortedMap::new(),
+            payout_addresses: SortedMap::new(),
+            nonce_windows: SortedMap::new(),
+            channel_nonces: SortedMap::new(),
+            recent_payloads: RecentPayloads::default(),
+            ledger_accounts: SortedMap::new(),
+            withdrawal_policy: WithdrawalPolicy::default(),
+            withdrawal_requests: SortedMap::new(),
+            escrowed_fees: SortedMap::new(),
+            in_flight: false,
+            rate_limit: RateLimit::default(),
+            rate_windows: SortedMap::new(),
+            blacklist: SortedMap::new(),
+            circuit_breaker: CircuitBreaker::default(),
+            stats: Stats::default(),
+            rewarded_epoch: None,
+            insurance_pool: 0,
+            failure_reports: SortedMap::new(),
+            compensations: SortedMap::new(),
+            eth_registration_nonces: SortedMap::new(),
+            dirty: DirtySections::default(),
+            section_ends: None,
+            capacity: None,
+            loaded_ledger_len: 0,
+        }
+    }
+
+    /// Get the next nonce for a consumer
+    pub fn get_next_nonce(&self, consumer: &Pubkey) -> u64 {
+        self.consumer_nonces.get(consumer).copied().unwrap_or(0)
+    }
+
+    /// Increment and return the nonce for a consumer
+    pub fn increment_nonce(&mut self, consumer: &Pubkey) -> u64 {
+        let current_nonce = self.get_next_nonce(consumer);
+        let next_nonce = current_nonce + 1;
+        self.dirty.mark(Section::ConsumerNonces);
+        self.consumer_nonces.insert(*consumer, next_nonce);
+        next_nonce
+    }
+
+    /// Check if a nonce has been used (replay protection)
+    /// Returns true if the nonce is less than the next expected nonce
+    pub fn is_nonce_used(&self, consumer: &Pubkey, nonce: u64) -> bool {
+        let next_nonce = self.get_next_nonce(consumer);
+        nonce < next_nonce
+    }
+
+    /// Record which executor executed a transaction
+    pub fn record_transaction_executor(&mut self, consumer: &Pubkey, nonce: u64, executor: &Pubkey) {
+        self.record_lane_transaction_executor(consumer, 0, nonce, executor);
+    }
+
+    /// Get the executor that executed a specific transaction
+    pub fn get_transaction_executor(&self, consumer: &Pubkey, nonce: u64) -> Option<&Pubkey> {
+        self.get_lane_transaction_executor(consumer, 0, nonce)
+    }
+
+    /// Get the next nonce of a consumer's lane
+    pub fn get_next_lane_nonce(&self, consumer: &Pubkey, lane: u64) -> u64 {
+        if lane == 0 {
+            return self.get_next_nonce(consumer);
+        }
+        self.lane_nonces.get(&(*consumer, lane)).copied().unwrap_or(0)
+    }
+
+    /// Increment and return the nonce of a consumer's lane
+    pub fn increment_lane_nonce(&mut self, consumer: &Pubkey, lane: u64) -> u64 {
+        if lane == 0 {
+            return self.increment_nonce(consumer);
+        }
+        let next_nonce = self.get_next_lane_nonce(consumer, lane) + 1;
+        self.dirty.mark(Section::LaneNonces);
+        self.lane_nonces.insert((*consumer, lane), next_nonce);
+        next_nonce
+    }
+
+    /// Check if a nonce has been used in a consumer's lane
+    pub fn is_lane_nonce_used(&self, consumer: &Pubkey, lane: u64, nonce: u64) -> bool {
+        if lane == UNORDERED_NONCE_LANE {
+            return self.is_unordered_nonce_used(consumer, nonce);
+        }
+        nonce < self.get_next_lane_nonce(consumer, lane)
+    }
+
+    /// Get the next nonce of the channel between a consumer and an executor
+    pub fn get_next_channel_nonce(&self, consumer: &Pubkey, executor: &Pubkey) -> u64 {
+        self.channel_nonces
+            .get(&(*consumer, *executor))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Increment and return the nonce of the channel between a consumer and
+    /// an executor
+    pub fn increment_channel_nonce(&mut self, consumer: &Pubkey, executor: &Pubkey) -> u64 {
+        let next_nonce = self.get_next_channel_nonce(consumer, executor) + 1;
+        self.dirty.mark(Section::ChannelNonces);
+        self.channel_nonces.insert((*consumer, *executor), next_nonce);
+        next_nonce
+    }
+
+    /// Check if a nonce has been used in the channel between a consumer and
+    /// an executor
+    pub fn is_channel_nonce_used(&self, consumer: &Pubkey, executor: &Pubkey, nonce: u64) -> bool {
+        nonce < self.get_next_channel_nonce(consumer, executor)
+    }
+
+    /// Burn `nonce` of a consumer's lane so no submission can use it. In a
+    /// counted lane the nonces before it are burnt too, as the lane moves
+    /// past it. Fails with `ReplayAttack` when it is used already.
+    pub fn cancel_lane_nonce(&mut self, consumer: &Pubkey, lane: u64, nonce: u64) -> Result<(), GsnError> {
+        if self.is_lane_nonce_used(consumer, lane, nonce) {
+            return Err(GsnError::ReplayAttack);
+        }
+        if lane == UNORDERED_NONCE_LANE {
+            self.use_unordered_nonce(consumer, nonce);
+            return Ok(());
+        }
+        let next_nonce = nonce.checked_add(1).ok_or(GsnError::InvalidNonce)?;
+        if lane == 0 {
+            self.dirty.mark(Section::ConsumerNonces);
+            self.consumer_nonces.insert(*consumer, next_nonce);
+        } else {
+            self.dirty.mark(Section::LaneNonces);
+            self.lane_nonces.insert((*consumer, lane), next_nonce);
+        }
+        Ok(())
+    }
+
+    /// [`GsnInfo::cancel_lane_nonce`] of the channel between a consumer and
+    /// an executor
+    pub fn cancel_channel_nonce(
+        &mut self,
+        consumer: &Pubkey,
+        executor: &Pubkey,
+        nonce: u64,
+    ) -> Result<(), GsnError> {
+        if self.is_channel_nonce_used(consumer, executor, nonce) {
+            return Err(GsnError::ReplayAttack);
+        }
+        let next_nonce = nonce.checked_add(1).ok_or(GsnError::InvalidNonce)?;
+        self.dirty.mark(Section::ChannelNonces);
+        self.channel_nonces.insert((*consumer, *executor), next_nonce);
+        Ok(())
+    }
+
+    /// [`CONSUMER_LEDGER`] and [`EXECUTOR_LEDGER`] flags of `key`
+    pub fn ledger_flags(&self, key: &Pubkey) -> u8 {
+        self.ledger_accounts.get(key).copied().unwrap_or(0)
+    }
+
+    fn add_ledger_flag(&mut self, key: &Pubkey, flag: u8) {
+        let flags = self.ledger_flags(key) | flag;
+        self.dirty.mark(Section::LedgerAccounts);
+        self.ledger_accounts.insert(*key, flags);
+    }
+
+    /// Move the consumer's balance and lane 0 nonce out of the state into
+    /// its newly created `account`
+    pub fn open_consumer_account(&mut self, account: &mut ConsumerAccount) {
+        self.add_ledger_flag(&account.consumer, CONSUMER_LEDGER);
+        account.balance = self.consumer_mut().remove(&account.consumer).unwrap_or(0);
+        self.dirty.mark(Section::ConsumerNonces);
+        account.nonce = self.consumer_nonces.remove(&account.consumer).unwrap_or(0);
+    }
+
+    /// Move the executor's earnings out of the state into its newly created
+    /// `account`
+    pub fn open_executor_account(&mut self, account: &mut ExecutorAccount) {
+        self.add_ledger_flag(&account.executor, EXECUTOR_LEDGER);
+        account.earned = self.executor_mut().remove(&account.executor).unwrap_or(0);
+    }
+
+    /// Load the balance and nonce of `account` for the instruction, see
+    /// [`GsnInfo::store_consumer_account`]
+    pub fn load_consumer_account(&mut self, account: &ConsumerAccount) {
+        self.consumer_mut()
+            .insert(account.consumer, account.balance);
+        self.dirty.mark(Section::ConsumerNonces);
+        self.consumer_nonces.insert(account.consumer, account.nonce);
+        self.loaded_ledger_len += 2 * BALANCE_ENTRY_LEN;
+    }
+
+    /// Move the balance and nonce loaded from `account` back into it
+    pub fn store_consumer_account(&mut self, account: &mut ConsumerAccount) {
+        account.balance = self.consumer_mut().remove(&account.consumer).unwrap_or(0);
+        self.dirty.mark(Section::ConsumerNonces);
+        account.nonce = self.consumer_nonces.remove(&account.consumer).unwrap_or(0);
+        self.loaded_ledger_len = self.loaded_ledger_len.saturating_sub(2 * BALANCE_ENTRY_LEN);
+    }
+
+    /// Load the earnings of `account` for the instruction, see
+    /// [`GsnInfo::store_executor_account`]
+    pub fn load_executor_account(&mut self, account: &ExecutorAccount) {
+        self.executor_mut().insert(account.executor, account.earned);
+        self.loaded_ledger_len += BALANCE_ENTRY_LEN;
+    }
+
+    /// Move the earnings loaded from `account` back into it
+    pub fn store_executor_account(&mut self, account: &mut ExecutorAccount) {
+        account.earned = self.executor_mut().remove(&account.executor).unwrap_or(0);
+        self.loaded_ledger_len = self.loaded_ledger_len.saturating_sub(BALANCE_ENTRY_LEN);
+    }
+
+    /// Remove `consumer`, whose lamport and token balances must all be empty,
+    /// from the state. Its nonces stay so intents can't be replayed: with its
+    /// ledger `account` loaded, the lane 0 nonce moves back into the state and
+    /// the account is no longer tracked.
+    pub fn close_consumer(
+        &mut self,
+        consumer: &Pubkey,
+        account: Option<&ConsumerAccount>,
+    ) -> Result<(), GsnError> {
+        let has_tokens = self
+            .token_consumer
+            .iter()
+            .any(|((key, _), balance)| key == consumer && *balance > 0);
+        if self.consumer.get(consumer).copied().unwrap_or(0) > 0 || has_tokens {
+            return Err(GsnError::BalanceNotEmpty);
+        }
+        if let Some(account) = account {
+            let flags = self.ledger_flags(consumer) & !CONSUMER_LEDGER;
+            let freed = if flags == 0 { LEDGER_ACCOUNT_ENTRY_LEN } else { 0 };
+            if account.nonce > 0 {
+                self.ensure_capacity(
+                    BALANCE_ENTRY_LEN.saturating_sub(freed),
+                    GsnError::ConsumerRegistryFull,
+                )?;
+            } else {
+                self.dirty.mark(Section::ConsumerNonces);
+                self.consumer_nonces.remove(consumer);
+            }
+            self.dirty.mark(Section::LedgerAccounts);
+            if flags == 0 {
+                self.ledger_accounts.remove(consumer);
+            } else {
+                self.ledger_accounts.insert(*consumer, flags);
+            }
+            self.loaded_ledger_len = self.loaded_ledger_len.saturating_sub(2 * BALANCE_ENTRY_LEN);
+        }
+        if self.consumer.contains_key(consumer) {
+            self.consumer_mut().remove(consumer);
+        }
+        if self.token_consumer.keys().any(|(key, _)| key == consumer) {
+            self.token_consumer_mut().retain(|(key, _), _| key != consumer);
+        }
+        if self.withdrawal_requests.contains_key(consumer) {
+            self.withdrawal_requests_mut().remove(consumer);
+        }
+        if self.rate_windows.contains_key(consumer) {
+            self.rate_windows_mut().remove(consumer);
+        }
+        Ok(())
+    }
+
+    /// Fails with `MissingLedgerAccount` when the state holds a balance or
+    /// nonce of a consumer or executor with a ledger account, which only
+    /// happens when an instruction touched it without taking the account
+    pub fn check_ledgers_stored(&self) -> Result<(), GsnError> {
+        for (key, flags) in self.ledger_accounts.iter() {
+            if flags & CONSUMER_LEDGER != 0
+                && (self.consumer.contains_key(key) || self.consumer_nonces.contains_key(key))
+            {
+                return Err(GsnError::MissingLedgerAccount);
+            }
+            if flags & EXECUTOR_LEDGER != 0 && self.executor.contains_key(key) {
+                return Err(GsnError::MissingLedgerAccount);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a payload with `hash` executed within the retention window
+    pub fn is_recent_payload(&self, hash: &[u8; 32]) -> bool {
+        self.recent_payloads.contains(hash)
+    }
+
+    pub fn record_payload(&mut self, hash: [u8; 32]) {
+        if self.recent_payloads.retention() > 0 {
+            self.dirty.mark(Section::RecentPayloads);
+            self.recent_payloads.record(hash);
+        }
+    }
+
+    pub fn set_payload_retention(&mut self, retention: usize) {
+        self.dirty.mark(Section::RecentPayloads);
+        self.recent_payloads.resize(retention);
+    }
+
+    /// Window of a consumer's unordered nonces
+    pub fn nonce_window(&self, consumer: &Pubkey) -> NonceWindow {
+        self.nonce_windows.get(consumer).copied().unwrap_or_default()
+    }
+
+    /// Check if an unordered nonce has been used, or fell below the window
+    pub fn is_unordered_nonce_used(&self, consumer: &Pubkey, nonce: u64) -> bool {
+        self.nonce_window(consumer).is_used(nonce)
+    }
+
+    /// Mark an unordered nonce of a consumer used
+    pub fn use_unordered_nonce(&mut self, consumer: &Pubkey, nonce: u64) {
+        let mut window = self.nonce_window(consumer);
+        window.mark_used(nonce);
+        self.dirty.mark(Section::NonceWindows);
+        self.nonce_windows.insert(*consumer, window);
+    }
+
+    /// Record which executor executed a transaction of a lane
+    pub fn record_lane_transaction_executor(
+        &mut self,
+        consumer: &Pubkey,
+        lane: u64,
+        nonce: u64,
+        executor: &Pubkey,
+    ) {
+        self.dirty.mark(Section::TransactionExecutor);
+        self.transaction_executor
+            .insert((*consumer, lane, nonce), *executor);
+    }
+
+    /// Drop the executor records of every consumer's lane but the
+    /// `retention` with the highest nonces, returning how many were dropped
+    pub fn prune_transaction_executors(&mut self, retention: usize) -> usize {
+        // Records of a lane are adjacent and ordered by nonce, count the newer
+        // ones of the same lane from the end
+        let keys: Vec<&(Pubkey, u64, u64)> = self.transaction_executor.keys().collect();
+        let mut keep = vec![true; keys.len()];
+        let mut newer = 0;
+        for index in (0..keys.len()).rev() {
+            let (consumer, lane, _) = keys[index];
+            newer = match keys.get(index + 1) {
+                Some((next_consumer, next_lane, _))
+                    if next_consumer == consumer && next_lane == lane =>
+                {
+                    newer + 1
+                }
+                _ => 0,
+            };
+            keep[index] = newer < retention;
+        }
+
+        let pruned = keep.iter().filter(|keep| !**keep).count();
+        if pruned > 0 {
+            self.dirty.mark(Section::TransactionExecutor);
+            let mut keep = keep.into_iter();
+            self.transaction_executor.retain(|_, _| keep.next().unwrap_or(true));
+        }
+        pruned
+    }
+
+    /// Get the executor that executed a specific transaction of a lane
+    pub fn get_lane_transaction_executor(
+        &self,
+        consumer: &Pubkey,
+        lane: u64,
+        nonce: u64,
+    ) -> Option<&Pubkey> {
+        self.transaction_executor.get(&(*consumer, lane, nonce))
+    }
+}
+
+/// Aggregates for a single epoch
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct EpochMetrics {
+    pub epoch: u64,
+    /// Number of successful executions
+    pub executions: u64,
+    /// Sum of all fees charged
+    pub total_fees: u64,
+    /// Fees charged per fee token
+    pub fees_by_token: SortedMap<Pubkey, u64>,
+    /// Successful executions per target program
+    pub executions_by_program: SortedMap<Pubkey, u64>,
+    /// Fees earned per executor
+    pub executor_fees: SortedMap<Pubkey, u64>,
+}
+
+impl EpochMetrics {
+    pub fn new(epoch: u64) -> Self {
+        Self {
+            epoch,
+            ..Self::default()
+        }
+    }
+
+    /// Executors ordered by fees earned this epoch, highest first
+    pub fn top_executors(&self, limit: usize) -> Vec<(&Pubkey, u64)> {
+        let mut executors: Vec<(&Pubkey, u64)> = self
+            .executor_fees
+            .iter()
+            .map(|(executor, fees)| (executor, *fees))
+            .collect();
+        executors.sort_by_key(|(_, fees)| std::cmp::Reverse(*fees));
+        executors.truncate(limit);
+        executors
+    }
+}
+
+/// Protocol health metrics kept in a dedicated account, rolled over every epoch
+#[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct ProtocolMetrics {
+    pub is_initialized: bool,
+    /// GSN state account these metrics belong to
+    pub gsn_account: Pubkey,
+    pub current: EpochMetrics,
+    /// The last epoch before `current` that had activity
+    pub previous: Option<EpochMetrics>,
+}
+
+impl ProtocolMetrics {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNMETRC";
+
+    pub fn new(gsn_account: Pubkey, epoch: u64) -> Self {
+        Self {
+            is_initialized: true,
+            gsn_account,
+            current: EpochMetrics::new(epoch),
+            previous: None,
+        }
+    }
+
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+
+    /// Account a successful execution in `epoch`, rolling over if the epoch changed
+    pub fn record_execution(
+        &mut self,
+        epoch: u64,
+        target_program: &Pubkey,
+        executor: &Pubkey,
+        fee_token: &Pubkey,
+        fee: u64,
+    ) {
+        if epoch != self.current.epoch {
+            let finished = std::mem::replace(&mut self.current, EpochMetrics::new(epoch));
+            self.previous = Some(finished);
+        }
+
+        let current = &mut self.current;
+        current.executions = current.executions.saturating_add(1);
+        current.total_fees = current.total_fees.saturating_add(fee);
+        add_to_entry(&mut current.fees_by_token, fee_token, fee);
+        add_to_entry(&mut current.executions_by_program, target_program, 1);
+        add_to_entry(&mut current.executor_fees, executor, fee);
+    }
+
+    /// Move `amount` of the fees `record_execution` credited to `from` this
+    /// epoch to `to`, for a fee split between executors
+    pub fn transfer_executor_fee(&mut self, from: &Pubkey, to: &Pubkey, amount: u64) {
+        let fees = &mut self.current.executor_fees;
+        if let Some(credited) = fees.get(from).copied() {
+            fees.insert(*from, credited.saturating_sub(amount));
+        }
+        add_to_entry(fees, to, amount);
+    }
+}
+
+/// Slots an escrowed fee can be disputed for before its executor may release it
+pub const ESCROW_DISPUTE_WINDOW_SLOTS: u64 = 150;
+
+/// First seed of every fee escrow address
+pub const ESCROW_SEED: &[u8] = b"escrow";
+
+/// Fee of a single submission settled through its own account instead of the
+/// executor's running balance
+///
+/// The account is a PDA of the GSN state account and the submission's
+/// (consumer, lane, nonce), so each submission has exactly one. It holds the
+/// fee on top of its rent, and is closed when the fee is released to the
+/// executor or refunded to the consumer.
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct FeeEscrow {
+    pub is_initialized: bool,
+    /// GSN state account the fee was taken from
+    pub gsn_account: Pubkey,
+    pub consumer: Pubkey,
+    pub executor: Pubkey,
+    pub lane: u64,
+    pub nonce: u64,
+    pub fee: u64,
+    /// Slot the submission executed in
+    pub slot: u64,
+}
+
+impl FeeEscrow {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNESCRW";
+
+    /// Encoded length, the size of an escrow account
+    pub const LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 8 + 8 + 8 + 8;
+
+    /// Address and bump of the escrow of a submission
+    pub fn find_address(
+        program_id: &Pubkey,
+        gsn_account: &Pubkey,
+        consumer: &Pubkey,
+        lane: u64,
+        nonce: u64,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                ESCROW_SEED,
+                gsn_account.as_ref(),
+                consumer.as_ref(),
+                &lane.to_le_bytes(),
+                &nonce.to_le_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// First slot the executor may release the fee in; disputes are accepted before it
+    pub fn releasable_at(&self) -> u64 {
+        self.slot.saturating_add(ESCROW_DISPUTE_WINDOW_SLOTS)
+    }
+
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+}
+
+/// First seed of every sponsor policy address
+pub const SPONSOR_SEED: &[u8] = b"sponsor";
+
+/// Most target programs a sponsor policy may list
+pub const MAX_SPONSOR_PROGRAMS: usize = 16;
+
+/// Most target instructions a sponsor policy may list
+pub const MAX_SPONSOR_INSTRUCTIONS: usize = 8;
+
+/// Instructions of a target program a sponsor pays for: those whose data
+/// starts with `discriminator`
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct InstructionFilter {
+    pub program_id: Pubkey,
+    pub discriminator: u8,
+}
+
+/// What a sponsor pays for with `SubmitSponsored`
+///
+/// The account is a PDA of the GSN state account and the sponsor, created by
+/// `RegisterSponsor`. Fees are charged to the sponsor's own top-up balance,
+/// so a dApp tops up its sponsor key like any consumer.
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct SponsorPolicy {
+    pub is_initialized: bool,
+    /// GSN state account the sponsor's balance is in
+    pub gsn_account: Pubkey,
+    pub sponsor: Pubkey,
+    /// Highest fee of a single sponsored submission
+    pub max_fee_per_tx: u64,
+    /// Target programs the sponsor pays every instruction of
+    pub allowed_programs: Vec<Pubkey>,
+    /// Relayed instructions the sponsor pays for, on top of `allowed_programs`.
+    /// Without either, the sponsor pays for everything.
+    pub allowed_instructions: Vec<InstructionFilter>,
+    /// Most submissions the sponsor pays for each user, 0 for no quota
+    pub max_submissions_per_user: u64,
+}
+
+impl SponsorPolicy {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNSPONS";
+
+    /// Size of a sponsor account, leaving room for the policy to grow
+    pub const LEN: usize = 1024;
+
+    /// Address and bump of the policy of a sponsor
+    pub fn find_address(
+        program_id: &Pubkey,
+        gsn_account: &Pubkey,
+        sponsor: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[SPONSOR_SEED, gsn_account.as_ref(), sponsor.as_ref()],
+            program_id,
+        )
+    }
+
+    /// Whether the sponsor pays for invoking `program_id` with instruction
+    /// data starting with `discriminator`; a plain transfer has none
+    pub fn covers(&self, program_id: &Pubkey, discriminator: Option<u8>) -> bool {
+        if self.allowed_programs.is_empty() && self.allowed_instructions.is_empty() {
+            return true;
+        }
+        self.allowed_programs.contains(program_id)
+            || discriminator.is_some_and(|discriminator| {
+                self.allowed_instructions.contains(&InstructionFilter {
+                    program_id: *program_id,
+                    discriminator,
+                })
+            })
+    }
+
+    /// Whether `used` sponsored submissions leave the user within the quota
+    pub fn has_quota_left(&self, used: u64) -> bool {
+        self.max_submissions_per_user == 0 || used < self.max_submissions_per_user
+    }
+
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+}
+
+/// First seed of every consumer ledger address
+pub const CONSUMER_LEDGER_SEED: &[u8] = b"consumer";
+
+/// First seed of every executor ledger address
+pub const EXECUTOR_LEDGER_SEED: &[u8] = b"executor";
+
+/// Top-up balance and lane 0 nonce of a consumer, moved out of the state
+/// account by `OpenConsumerAccount` so they stop taking room in it
+///
+/// The account is a PDA of the GSN state account and the consumer.
+/// Instructions touching the consumer take it among their accounts, anywhere
+/// after the ones they list, and fail with `MissingLedgerAccount` without it.
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ConsumerAccount {
+    pub is_initialized: bool,
+    pub gsn_account: Pubkey,
+    pub consumer: Pubkey,
+    /// Top-up balance in lamports
+    pub balance: u64,
+    /// Next nonce of lane 0
+    pub nonce: u64,
+    pub bump: u8,
+}
+
+impl ConsumerAccount {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNCONSM";
+
+    /// Encoded length, the size of a consumer account
+    pub const LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 8 + 8 + 1;
+
+    /// Address and bump of the ledger account of `consumer`
+    pub fn find_address(
+        program_id: &Pubkey,
+        gsn_account: &Pubkey,
+        consumer: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                CONSUMER_LEDGER_SEED,
+                gsn_account.as_ref(),
+                consumer.as_ref(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Address the account must have, derived from its bump
+    pub fn address(&self, program_id: &Pubkey) -> Option<Pubkey> {
+        Pubkey::create_program_address(
+            &[
+                CONSUMER_LEDGER_SEED,
+                self.gsn_account.as_ref(),
+                self.consumer.as_ref(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .ok()
+    }
+
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+}
+
+/// Lamport earnings of an executor, moved out of the state account by
+/// `OpenExecutorAccount`
+///
+/// The account is a PDA of the GSN state account and the executor, taken by
+/// instructions touching the executor like a [`ConsumerAccount`].
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ExecutorAccount {
+    pub is_initialized: bool,
+    pub gsn_account: Pubkey,
+    pub executor: Pubkey,
+    /// Fees earned and not claimed yet, in lamports
+    pub earned: u64,
+    pub bump: u8,
+}
+
+impl ExecutorAccount {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNEXECU";
+
+    /// Encoded length, the size of an executor account
+    pub const LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 8 + 1;
+
+    /// Address and bump of the ledger account of `executor`
+    pub fn find_address(
+        program_id: &Pubkey,
+        gsn_account: &Pubkey,
+        executor: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                EXECUTOR_LEDGER_SEED,
+                gsn_account.as_ref(),
+                executor.as_ref(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Address the account must have, derived from its bump
+    pub fn address(&self, program_id: &Pubkey) -> Option<Pubkey> {
+        Pubkey::create_program_address(
+            &[
+                EXECUTOR_LEDGER_SEED,
+                self.gsn_account.as_ref(),
+                self.executor.as_ref(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .ok()
+    }
+
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+}
+
+/// Slots that must pass between committing to a submission and revealing it
+pub const REVEAL_DELAY_SLOTS: u64 = 2;
+
+/// Slots after its commit past which a submission can no longer be revealed
+pub const COMMITMENT_EXPIRY_SLOTS: u64 = 150;
+
+/// First seed of every commitment address
+pub const COMMITMENT_SEED: &[u8] = b"commit";
+
+/// Executor's commitment to a submission it will reveal and execute later
+///
+/// The account is a PDA of the GSN state account and the commitment hash, so
+/// the submission's consumer, amount and receiver stay hidden until the reveal.
+/// It is closed by the reveal, refunding its rent to the executor.
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct SubmissionCommitment {
+    pub is_initialized: bool,
+    pub gsn_account: Pubkey,
+    /// Only this executor may reveal the submission
+    pub executor: Pubkey,
+    /// See [`SubmissionCommitment::hash_submission`]
+    pub hash: [u8; 32],
+    /// Slot the commitment was made in
+    pub slot: u64,
+}
+
+impl SubmissionCommitment {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNCOMMT";
+
+    /// Encoded length, the size of a commitment account
+    pub const LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 8;
+
+    /// Hash an executor commits to: the submission's accounts and arguments,
+    /// and a salt that keeps it from being guessed from the consumer's nonce
+    pub fn hash_submission(
+        target_program: &Pubkey,
+        consumer: &Pubkey,
+        receiver: &Pubkey,
+        amount: u64,
+        nonce: u64,
+        lane: u64,
+        salt: &[u8; 32],
+    ) -> [u8; 32] {
+        hashv(&[
+            target_program.as_ref(),
+            consumer.as_ref(),
+            receiver.as_ref(),
+            &amount.to_le_bytes(),
+            &nonce.to_le_bytes(),
+            &lane.to_le_bytes(),
+            salt,
+        ])
+        .to_bytes()
+    }
+
+    /// Address and bump of the commitment account of `hash`
+    pub fn find_address(program_id: &Pubkey, gsn_account: &Pubkey, hash: &[u8; 32]) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[COMMITMENT_SEED, gsn_account.as_ref(), hash], program_id)
+    }
+
+    /// First slot the submission may be revealed in
+    pub fn revealable_at(&self) -> u64 {
+        self.slot.saturating_add(REVEAL_DELAY_SLOTS)
+    }
+
+    /// First slot the commitment can no longer be revealed in
+    pub fn expires_at(&self) -> u64 {
+        self.slot.saturating_add(COMMITMENT_EXPIRY_SLOTS)
+    }
+
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+}
+
+/// First seed of every receipt address
+pub const RECEIPT_SEED: &[u8] = b"receipt";
+
+/// Proof that a submission executed, kept after its logs are gone
+///
+/// The account is a PDA of the GSN state account and the submission's
+/// (consumer, lane, nonce), written by `SubmitTransaction` and
+/// `SubmitTransactionInLane` when it's among their accounts. The executor
+/// funds its rent.
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct ExecutionReceipt {
+    pub is_initialized: bool,
+    pub gsn_account: Pubkey,
+    pub consumer: Pubkey,
+    pub executor: Pubkey,
+    pub target_program: Pubkey,
+    pub lane: u64,
+    pub nonce: u64,
+    pub amount: u64,
+    /// Fee charged, tip excluded
+    pub fee: u64,
+    pub tip: u64,
+    /// Slot the submission executed in
+    pub slot: u64,
+}
+
+impl ExecutionReceipt {
+    pub const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN] = *b"GSNRECPT";
+
+    /// Encoded length, the size of a receipt account
+    pub const LEN: usize = DISCRIMINATOR_LEN + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8;
+
+    /// Address and bump of the receipt of a submission
+    pub fn find_address(
+        program_id: &Pubkey,
+        gsn_account: &Pubkey,
+        consumer: &Pubkey,
+        lane: u64,
+        nonce: u64,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                RECEIPT_SEED,
+                gsn_account.as_ref(),
+                consumer.as_ref(),
+                &lane.to_le_bytes(),
+                &nonce.to_le_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    pub fn serialize(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_account(&Self::DISCRIMINATOR, self, data)
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_account(&Self::DISCRIMINATOR, data)
+    }
+}
+
+/// Length of the discriminator the data of every program-owned account but
+/// the state account starts with, so that one kind of account can't be passed
+/// where another is expected. The state account starts with its version.
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+fn serialize_account<T: BorshSerialize>(
+    discriminator: &[u8; DISCRIMINATOR_LEN],
+    account: &T,
+    data: &mut [u8],
+) -> Result<(), ProgramError> {
+    if data.len() < DISCRIMINATOR_LEN {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    let (tag, mut body) = data.split_at_mut(DISCRIMINATOR_LEN);
+    BorshSerialize::serialize(account, &mut body).map_err(|_| ProgramError::AccountDataTooSmall)?;
+    tag.copy_from_slice(discriminator);
+    Ok(())
+}
+
+/// Decode an account of the kind `discriminator` tags, failing with
+/// `InvalidAccountType` for any other
+fn deserialize_account<T: BorshDeserialize>(
+    discriminator: &[u8; DISCRIMINATOR_LEN],
+    data: &[u8],
+) -> Result<T, ProgramError> {
+    if data.len() < DISCRIMINATOR_LEN || data[..DISCRIMINATOR_LEN] != discriminator[..] {
+        return Err(GsnError::InvalidAccountType.into());
+    }
+    decode(&mut &data[DISCRIMINATOR_LEN..])
+}
+
+fn decode<T: BorshDeserialize>(buf: &mut &[u8]) -> Result<T, ProgramError> {
+    T::deserialize(buf).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Decode a section appended after the account layout was first deployed:
+/// older accounts end before it, or are zero-filled where it goes
+fn decode_trailing<T: BorshDeserialize + Default>(buf: &mut &[u8]) -> Result<T, ProgramError> {
+    if buf.is_empty() {
+        return Ok(T::default());
+    }
+    decode(buf)
+}
+
+/// Encoded length of a `SortedMap`: a `u32` length, then the entries
+fn map_len(entries: usize, entry_len: usize) -> usize {
+    4 + entries * entry_len
+}
+
+fn add_to_entry(map: &mut SortedMap<Pubkey, u64>, key: &Pubkey, amount: u64) {
+    let entry = map.entry(*key).or_insert(0);
+    *entry = entry.saturating_add(amount);
 }
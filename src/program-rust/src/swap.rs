@@ -0,0 +1,32 @@
+//! Jupiter swaps settling token fees in SOL.
+//!
+//! The route is computed off-chain by the claimer, whose instruction data and
+//! accounts the program forwards to the Jupiter aggregator as they are. The
+//! program doesn't trust the route: it only checks what the swap delivered.
+
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+};
+
+/// Jupiter aggregator v6
+pub const JUPITER_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+
+/// Jupiter instruction with the claimer's `data`, on `accounts` passed with
+/// the signer and writable flags they were given
+pub fn route(accounts: &[AccountInfo], data: &[u8]) -> Instruction {
+    Instruction::new_with_bytes(
+        JUPITER_PROGRAM_ID,
+        data,
+        accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+    )
+}
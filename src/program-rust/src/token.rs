@@ -0,0 +1,279 @@
+//! SPL Token accounts and transfers, read and encoded by hand so the program
+//! doesn't depend on the token program crates.
+//!
+//! Only the base account and mint layouts, Token-2022's transfer fee
+//! extension and the `Transfer`, `TransferChecked` and `CloseAccount`
+//! instructions are used;
+//! the base layouts and instructions are the same in SPL Token and Token-2022.
+
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    system_program,
+};
+use std::convert::TryInto;
+
+/// SPL Token program
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// SPL Token-2022 program
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PT3PkDeYJaoWHf");
+
+/// Associated token account program
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Mint of wrapped SOL, whose token accounts hold their balance as lamports
+pub const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+
+/// Length of the base token account layout, shared by both token programs
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Offset of the `state` byte in a token account: 0 uninitialized, 1 initialized, 2 frozen
+const STATE_OFFSET: usize = 108;
+
+/// Length of the base mint layout, shared by both token programs
+pub const MINT_LEN: usize = 82;
+
+/// Offsets of the mint's `decimals` and `is_initialized` bytes
+const DECIMALS_OFFSET: usize = 44;
+const MINT_INITIALIZED_OFFSET: usize = 45;
+
+/// Token-2022 pads mints with extensions to a token account's length, then
+/// writes the account type and the extensions as type-length-value entries
+const ACCOUNT_TYPE_OFFSET: usize = TOKEN_ACCOUNT_LEN;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const EXTENSIONS_OFFSET: usize = ACCOUNT_TYPE_OFFSET + 1;
+
+/// Extension type of `TransferFeeConfig`, whose value holds two authorities
+/// and the withheld amount before the older and newer fees
+const TRANSFER_FEE_CONFIG: u16 = 1;
+const TRANSFER_FEES_OFFSET: usize = 72;
+const TRANSFER_FEE_LEN: usize = 18;
+
+/// Tag of the associated token account program's `CreateIdempotent`
+const CREATE_IDEMPOTENT: u8 = 1;
+
+/// Tags of the token program's `Transfer`, `CloseAccount` and
+/// `TransferChecked` instructions
+const TRANSFER: u8 = 3;
+const CLOSE_ACCOUNT: u8 = 9;
+const TRANSFER_CHECKED: u8 = 12;
+
+/// Fields of a token account the program reads
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub is_frozen: bool,
+}
+
+/// Fields of a mint the program reads
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mint {
+    pub decimals: u8,
+    /// Fees of a Token-2022 mint with the transfer fee extension
+    pub transfer_fees: Option<TransferFees>,
+}
+
+/// Token-2022 transfer fee, taking `basis_points` of the amount up to
+/// `maximum_fee` from epoch `epoch`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+/// The fee in force and the one replacing it at its epoch
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferFees {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFee {
+    /// Fee withheld from a transfer of `amount`, rounded up like the token
+    /// program does
+    pub fn fee(&self, amount: u64) -> u64 {
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(10_000);
+        fee.min(self.maximum_fee as u128).min(amount as u128) as u64
+    }
+}
+
+impl TransferFees {
+    /// Fee withheld from a transfer of `amount` in `epoch`
+    pub fn fee(&self, amount: u64, epoch: u64) -> u64 {
+        if epoch >= self.newer.epoch {
+            self.newer.fee(amount)
+        } else {
+            self.older.fee(amount)
+        }
+    }
+}
+
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == TOKEN_PROGRAM_ID || *program_id == TOKEN_2022_PROGRAM_ID
+}
+
+/// Decode `data`, owned by `owner_program`, as an initialized token account
+pub fn unpack_token_account(owner_program: &Pubkey, data: &[u8]) -> Option<TokenAccount> {
+    // Token-2022 accounts with extensions are longer, the base layout comes first
+    if !is_token_program(owner_program) || data.len() < TOKEN_ACCOUNT_LEN || data[STATE_OFFSET] == 0 {
+        return None;
+    }
+    let mut amount = [0u8; 8];
+    amount.copy_from_slice(&data[64..72]);
+    Some(TokenAccount {
+        mint: Pubkey::new_from_array(data[..32].try_into().ok()?),
+        owner: Pubkey::new_from_array(data[32..64].try_into().ok()?),
+        amount: u64::from_le_bytes(amount),
+        is_frozen: data[STATE_OFFSET] == 2,
+    })
+}
+
+/// [`unpack_token_account`] of an account passed to an instruction
+pub fn load_token_account(account: &AccountInfo) -> Option<TokenAccount> {
+    unpack_token_account(account.owner, &account.data.borrow())
+}
+
+/// Decode `data`, owned by `owner_program`, as an initialized mint
+pub fn unpack_mint(owner_program: &Pubkey, data: &[u8]) -> Option<Mint> {
+    if !is_token_program(owner_program) || data.len() < MINT_LEN || data[MINT_INITIALIZED_OFFSET] == 0 {
+        return None;
+    }
+    let mut mint = Mint {
+        decimals: data[DECIMALS_OFFSET],
+        transfer_fees: None,
+    };
+    if *owner_program != TOKEN_2022_PROGRAM_ID || data.len() <= ACCOUNT_TYPE_OFFSET {
+        return Some(mint);
+    }
+    if data[ACCOUNT_TYPE_OFFSET] != ACCOUNT_TYPE_MINT {
+        return None;
+    }
+    let mut offset = EXTENSIONS_OFFSET;
+    while let Some(header) = data.get(offset..offset + 4) {
+        let extension = u16::from_le_bytes([header[0], header[1]]);
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let value = data.get(offset + 4..offset + 4 + len)?;
+        if extension == TRANSFER_FEE_CONFIG {
+            mint.transfer_fees = Some(TransferFees {
+                older: unpack_transfer_fee(value, TRANSFER_FEES_OFFSET)?,
+                newer: unpack_transfer_fee(value, TRANSFER_FEES_OFFSET + TRANSFER_FEE_LEN)?,
+            });
+        }
+        offset += 4 + len;
+    }
+    Some(mint)
+}
+
+fn unpack_transfer_fee(value: &[u8], offset: usize) -> Option<TransferFee> {
+    let fee = value.get(offset..offset + TRANSFER_FEE_LEN)?;
+    Some(TransferFee {
+        epoch: u64::from_le_bytes(fee[..8].try_into().ok()?),
+        maximum_fee: u64::from_le_bytes(fee[8..16].try_into().ok()?),
+        basis_points: u16::from_le_bytes([fee[16], fee[17]]),
+    })
+}
+
+/// [`unpack_mint`] of an account passed to an instruction
+pub fn load_mint(account: &AccountInfo) -> Option<Mint> {
+    unpack_mint(account.owner, &account.data.borrow())
+}
+
+/// `Transfer` of `amount` tokens from `source` to `destination`, signed by
+/// the source's `authority`
+pub fn transfer(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![TRANSFER];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction::new_with_bytes(
+        *token_program,
+        &data,
+        vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `TransferChecked` of `amount` tokens of `mint` with `decimals` from
+/// `source` to `destination`, signed by the source's `authority`. Token-2022
+/// requires it for mints with a transfer fee.
+pub fn transfer_checked(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![TRANSFER_CHECKED];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction::new_with_bytes(
+        *token_program,
+        &data,
+        vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// `CloseAccount` of `account`, paying its lamports to `destination`, signed
+/// by its `owner`. Closing a wrapped SOL account unwraps its balance.
+pub fn close_account(token_program: &Pubkey, account: &Pubkey, destination: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        *token_program,
+        &[CLOSE_ACCOUNT],
+        vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+    )
+}
+
+/// Associated token account of `owner` for `mint` of `token_program`
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// `CreateIdempotent` of the associated token account of `owner` for `mint`
+/// of `token_program`, `payer` paying its rent
+pub fn create_associated_token_account(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[CREATE_IDEMPOTENT],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_token_address(owner, mint, token_program), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+    )
+}
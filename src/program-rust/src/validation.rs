@@ -0,0 +1,49 @@
+//! Checks of the accounts passed to an instruction, run by every handler
+//! before it reads or writes them.
+//!
+//! Accounts the program decodes must be owned by it, or anyone could pass an
+//! account holding forged data. Accounts it writes must be writable, the
+//! system program it invokes must be the real one, and a submission's target
+//! program must be a program.
+
+use crate::error::GsnError;
+use solana_program::{account_info::AccountInfo, pubkey::Pubkey, system_program};
+
+/// Fails with `InvalidAccountOwner` unless `account` is owned by `program_id`
+pub fn check_owner(program_id: &Pubkey, account: &AccountInfo) -> Result<(), GsnError> {
+    if account.owner != program_id {
+        return Err(GsnError::InvalidAccountOwner);
+    }
+    Ok(())
+}
+
+/// Fails with `AccountNotWritable` unless `account` was passed writable
+pub fn check_writable(account: &AccountInfo) -> Result<(), GsnError> {
+    if !account.is_writable {
+        return Err(GsnError::AccountNotWritable);
+    }
+    Ok(())
+}
+
+/// [`check_owner`] and [`check_writable`] of a program account the
+/// instruction changes
+pub fn check_program_account(program_id: &Pubkey, account: &AccountInfo) -> Result<(), GsnError> {
+    check_owner(program_id, account)?;
+    check_writable(account)
+}
+
+/// Fails with `InvalidSystemProgram` unless `account` is the system program
+pub fn check_system_program(account: &AccountInfo) -> Result<(), GsnError> {
+    if !system_program::check_id(account.key) {
+        return Err(GsnError::InvalidSystemProgram);
+    }
+    Ok(())
+}
+
+/// Fails with `TargetNotExecutable` unless `account` is an executable program
+pub fn check_executable(account: &AccountInfo) -> Result<(), GsnError> {
+    if !account.executable {
+        return Err(GsnError::TargetNotExecutable);
+    }
+    Ok(())
+}
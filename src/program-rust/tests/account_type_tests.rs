@@ -0,0 +1,71 @@
+// Tests for the discriminators of program-owned accounts
+
+use borsh::BorshSerialize;
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    state::{
+        ConsumerAccount, ExecutorAccount, GsnConfig, GsnInfo, SponsorPolicy, DISCRIMINATOR_LEN,
+    },
+    Processor,
+};
+
+fn invalid_type<T>(result: Result<T, ProgramError>) -> bool {
+    matches!(result, Err(error) if error == GsnError::InvalidAccountType.into())
+}
+
+#[test]
+fn test_accounts_only_decode_as_their_type() {
+    let mut config_data = vec![0u8; 1024];
+    GsnConfig::new(Pubkey::new_unique())
+        .serialize(&mut config_data)
+        .unwrap();
+    assert_eq!(config_data[..DISCRIMINATOR_LEN], GsnConfig::DISCRIMINATOR);
+    assert!(GsnConfig::deserialize(&config_data).is_ok());
+    assert!(invalid_type(ConsumerAccount::deserialize(&config_data)));
+    assert!(invalid_type(SponsorPolicy::deserialize(&config_data)));
+    // The state account starts with its version instead
+    assert!(GsnInfo::deserialize(&config_data).is_err());
+
+    let mut consumer_data = vec![0u8; ConsumerAccount::LEN];
+    ConsumerAccount::default().serialize(&mut consumer_data).unwrap();
+    assert!(invalid_type(ExecutorAccount::deserialize(&consumer_data)));
+    assert!(invalid_type(GsnConfig::deserialize(&consumer_data)));
+
+    // Fresh accounts have no type yet
+    assert!(invalid_type(GsnConfig::deserialize(&[0u8; 1024])));
+    assert!(invalid_type(ConsumerAccount::deserialize(&[])));
+}
+
+#[test]
+fn test_migrate_state_tags_legacy_config() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let mut gsn = GsnInfo::new();
+    gsn.config = config.gsn_account;
+
+    let mut data = [vec![0u8; 1024], vec![0u8; 1024], vec![]];
+    gsn.serialize(&mut data[0]).unwrap();
+    BorshSerialize::serialize(&config, &mut &mut data[1][..]).unwrap();
+    assert!(invalid_type(GsnConfig::deserialize(&data[1])));
+
+    let owner = Pubkey::new_unique();
+    let keys = [Pubkey::new_unique(), config.gsn_account, authority];
+    let mut lamports = [1; 3];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == 2, true, lamports, data, &owner, false, 0)
+        })
+        .collect();
+    Processor::process_migrate_state(&owner, &accounts).unwrap();
+    drop(accounts);
+
+    let migrated = GsnConfig::deserialize(&data[1]).unwrap();
+    assert_eq!(migrated.gsn_account, config.gsn_account);
+    assert!(migrated.is_approved_by(&[&authority]));
+}
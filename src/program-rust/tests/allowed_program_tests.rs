@@ -0,0 +1,156 @@
+// Tests for the target program allowlist
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, ProgramIdArgs},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_allowed_program_instructions_decode() {
+    let mut data = vec![31];
+    data.extend_from_slice(&[6; 32]);
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::AddAllowedProgram(ProgramIdArgs {
+            program_id: [6; 32]
+        })
+    );
+    data[0] = 32;
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::RemoveAllowedProgram(ProgramIdArgs {
+            program_id: [6; 32]
+        })
+    );
+}
+
+#[test]
+fn test_empty_allowlist_allows_every_program() {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    let (approved, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    assert!(config.is_program_allowed(&other));
+
+    config.add_allowed_program(approved);
+    assert!(config.is_program_allowed(&approved));
+    assert!(!config.is_program_allowed(&other));
+
+    config.remove_allowed_program(&approved);
+    assert!(config.is_program_allowed(&other));
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    /// Allowing the target program, or only another one
+    fn new(allow_target: bool) -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 200_000);
+        let mut config = GsnConfig::new(keys[4]);
+        config.add_allowed_program(match allow_target {
+            true => keys[0],
+            false => Pubkey::new_unique(),
+        });
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, 0, 0, None, None, 0, None, &accounts)
+    }
+}
+
+#[test]
+fn test_submission_requires_an_allowed_target() {
+    let mut setup = Setup::new(false);
+    assert_eq!(setup.submit(), Err(GsnError::ProgramNotAllowed.into()));
+
+    let mut setup = Setup::new(true);
+    setup.submit().unwrap();
+    let gsn = GsnInfo::deserialize(&setup.data[4]).unwrap();
+    assert_eq!(gsn.get_next_nonce(&setup.keys[1]), 1);
+}
+
+#[test]
+fn test_only_the_authority_changes_the_allowlist() {
+    let (config_key, authority, program_id) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+    let owner = Pubkey::new_unique();
+    let args = ProgramIdArgs {
+        program_id: program_id.to_bytes(),
+    };
+
+    let (mut config_lamports, mut authority_lamports) = (1, 1);
+    let mut authority_data = vec![];
+    let mut add = |signer: &Pubkey, data: &mut Vec<u8>| {
+        let accounts = [
+            AccountInfo::new(
+                &config_key,
+                false,
+                true,
+                &mut config_lamports,
+                data,
+                &owner,
+                false,
+                0,
+            ),
+            AccountInfo::new(
+                signer,
+                true,
+                false,
+                &mut authority_lamports,
+                &mut authority_data,
+                &owner,
+                false,
+                0,
+            ),
+        ];
+        Processor::process_add_allowed_program(&owner, args.clone(), &accounts)
+    };
+
+    assert_eq!(
+        add(&Pubkey::new_unique(), &mut data),
+        Err(GsnError::Unauthorized.into())
+    );
+    add(&authority, &mut data).unwrap();
+    let config = GsnConfig::deserialize(&data).unwrap();
+    assert!(config.is_program_allowed(&program_id));
+    assert!(!config.is_program_allowed(&Pubkey::new_unique()));
+}
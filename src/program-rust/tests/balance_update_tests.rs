@@ -0,0 +1,114 @@
+// Tests for top-ups and fees updating existing balances
+
+mod common;
+
+use common::Submit;
+use solana_program::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, system_program,
+};
+use solgsn::{
+    state::{FeeMode, GsnConfig, GsnInfo},
+    Processor,
+};
+
+const FEE: u64 = 50_000;
+
+/// State and config of a deployment charging `FEE` per submission
+fn deployment(gsn_key: &Pubkey, config_key: &Pubkey) -> (Vec<u8>, Vec<u8>) {
+    let mut gsn = GsnInfo::new();
+    gsn.config = *config_key;
+    let mut config = GsnConfig::new(*gsn_key);
+    config.initialize_governance(Pubkey::new_unique());
+    config.update_fee_params(FeeMode::Fixed(FEE));
+    let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+    gsn.serialize(&mut gsn_data).unwrap();
+    config.serialize(&mut config_data).unwrap();
+    (gsn_data, config_data)
+}
+
+/// `TopUp` accounts: GSN state, consumer, config, vault, system program
+struct Topup {
+    program_id: Pubkey,
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Topup {
+    fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let (gsn_key, config_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (vault_key, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+        let (gsn_data, config_data) = deployment(&gsn_key, &config_key);
+        Self {
+            program_id,
+            keys: vec![
+                gsn_key,
+                Pubkey::new_unique(),
+                config_key,
+                vault_key,
+                system_program::id(),
+            ],
+            lamports: vec![1, 10_000_000, 1, 0, 1],
+            data: vec![gsn_data, vec![], config_data, vec![], vec![]],
+        }
+    }
+
+    fn topup(&mut self, amount: u64) -> Result<(), ProgramError> {
+        let program_id = self.program_id;
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 1, true, lamports, data, &program_id, false, 0)
+            })
+            .collect();
+        Processor::process_topup(&program_id, amount, None, &accounts)
+    }
+
+    fn balance(&self) -> Option<u64> {
+        let gsn = GsnInfo::deserialize(&self.data[0]).unwrap();
+        gsn.consumer.get(&self.keys[1]).copied()
+    }
+}
+
+#[test]
+fn test_sequential_topups_add_up() {
+    let mut setup = Topup::new();
+    setup.topup(1_000).unwrap();
+    assert_eq!(setup.balance(), Some(1_000));
+    setup.topup(2_500).unwrap();
+    setup.topup(500).unwrap();
+    assert_eq!(setup.balance(), Some(4_000));
+}
+
+/// A deployment charging `FEE` per submission where the sender has `balance`
+fn submitter(balance: u64) -> Submit {
+    Submit::new(|gsn, config, keys| {
+        gsn.add_consumer(keys[1], balance);
+        config.update_fee_params(FeeMode::Fixed(FEE));
+    })
+}
+
+#[test]
+fn test_fees_are_deducted_from_existing_balances() {
+    let mut setup = submitter(1_000_000);
+    for nonce in 0..3 {
+        setup.submit(nonce).unwrap();
+    }
+    let gsn = setup.state();
+    assert_eq!(gsn.consumer.get(&setup.keys[1]), Some(&(1_000_000 - 3 * FEE)));
+    assert_eq!(gsn.executor.get(&setup.keys[3]), Some(&(3 * FEE)));
+}
+
+#[test]
+fn test_deducted_balance_runs_out() {
+    let mut setup = submitter(FEE + FEE / 2);
+    setup.submit(0).unwrap();
+    // Half a fee left, the second submission is refused
+    assert!(setup.submit(1).is_err());
+    assert_eq!(setup.state().consumer.get(&setup.keys[1]), Some(&(FEE / 2)));
+}
@@ -0,0 +1,156 @@
+// Tests for SubmitBatch decoding, network fee splitting and execution
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{BatchEntry, GsnInstruction, SubmitBatchArgs, MAX_BATCH_SIZE},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+fn encode(network_fee: u64, entries: &[(u64, u64, u64)]) -> Vec<u8> {
+    let mut data = vec![9];
+    data.extend_from_slice(&network_fee.to_le_bytes());
+    data.push(entries.len() as u8);
+    for (amount, nonce, lane) in entries {
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&nonce.to_le_bytes());
+        data.extend_from_slice(&lane.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_batch_decodes_entries_in_order() {
+    let data = encode(15_000, &[(1_000, 0, 0), (2_000, 4, 3)]);
+
+    match GsnInstruction::deserialize(&data).unwrap() {
+        GsnInstruction::SubmitBatch(args) => {
+            assert_eq!(args.network_fee, 15_000);
+            assert_eq!(
+                args.entries,
+                vec![
+                    BatchEntry {
+                        amount: 1_000,
+                        nonce: 0,
+                        lane: 0
+                    },
+                    BatchEntry {
+                        amount: 2_000,
+                        nonce: 4,
+                        lane: 3
+                    },
+                ]
+            );
+        }
+        other => panic!("expected SubmitBatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_batch_rejects_bad_sizes() {
+    assert_eq!(
+        SubmitBatchArgs::unpack(&encode(0, &[])),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    let too_many = vec![(1, 0, 0); MAX_BATCH_SIZE + 1];
+    assert_eq!(
+        SubmitBatchArgs::unpack(&encode(0, &too_many)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    let mut truncated = encode(0, &[(1, 0, 0), (2, 0, 0)]);
+    truncated.pop();
+    assert_eq!(
+        SubmitBatchArgs::unpack(&truncated),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_network_fee_shares_add_up() {
+    let args = SubmitBatchArgs::unpack(&encode(20_000, &[(1, 0, 0); 3])).unwrap();
+    assert_eq!(args.network_fee_shares(), vec![6_667, 6_667, 6_666]);
+    assert_eq!(args.network_fee_shares().iter().sum::<u64>(), 20_000);
+
+    let even = SubmitBatchArgs::unpack(&encode(15_000, &[(1, 0, 0); 3])).unwrap();
+    assert_eq!(even.network_fee_shares(), vec![5_000; 3]);
+}
+
+/// Accounts of a two-entry `SubmitBatch`: target program, fee payer, GSN
+/// state, config, then each consumer and its receiver
+struct Setup {
+    program_id: Pubkey,
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[3];
+        gsn.add_consumer(keys[4], 200_000);
+        gsn.add_consumer(keys[6], 200_000);
+        let mut data = vec![vec![]; 8];
+        data[2] = vec![0u8; 1024];
+        data[3] = vec![0u8; 1024];
+        gsn.serialize(&mut data[2]).unwrap();
+        GsnConfig::new(keys[2]).serialize(&mut data[3]).unwrap();
+        Self {
+            program_id,
+            keys,
+            lamports: vec![
+                1, 1_000_000, 1_000_000, 1_000_000, 1_000_000, 0, 1_000_000, 0,
+            ],
+            data,
+        }
+    }
+
+    fn submit(
+        &mut self,
+        network_fee: u64,
+        entries: &[(u64, u64, u64)],
+    ) -> Result<(), ProgramError> {
+        let program_id = self.program_id;
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 4 || index == 6;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        let args = SubmitBatchArgs::unpack(&encode(network_fee, entries))?;
+        Processor::process_submit_batch(&program_id, args, &accounts)
+    }
+}
+
+#[test]
+fn test_batch_credits_executor_every_entry_fee() {
+    let mut setup = Setup::new();
+    setup
+        .submit(10_000, &[(1_000, 0, 0), (2_000, 0, 1)])
+        .unwrap();
+
+    let gsn = GsnInfo::deserialize(&setup.data[2]).unwrap();
+    assert_eq!(gsn.get_next_lane_nonce(&setup.keys[4], 0), 1);
+    assert_eq!(gsn.get_next_lane_nonce(&setup.keys[6], 1), 1);
+    // Each entry pays the configured fee plus half the network fee
+    assert_eq!(gsn.executor.get(&setup.keys[1]), Some(&110_000));
+}
+
+#[test]
+fn test_batch_checks_each_entry_nonce() {
+    let mut setup = Setup::new();
+    assert_eq!(
+        setup.submit(10_000, &[(1_000, 0, 0), (2_000, 1, 0)]),
+        Err(GsnError::InvalidNonce.into())
+    );
+}
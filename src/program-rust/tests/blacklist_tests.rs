@@ -0,0 +1,194 @@
+// Tests for the blacklist of consumers and executors
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, BlacklistArgs, GsnInstruction},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_blacklist_instructions_decode() {
+    let keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let args = BlacklistArgs { address: keys[4] };
+    let blacklist = instruction::set_blacklisted(&keys[0], &keys[1], &keys[2], &keys[3], &keys[4], true);
+    assert_eq!(
+        GsnInstruction::deserialize(&blacklist.data).unwrap(),
+        GsnInstruction::BlacklistAddress(args.clone())
+    );
+    let unblacklist = instruction::set_blacklisted(&keys[0], &keys[1], &keys[2], &keys[3], &keys[4], false);
+    assert_eq!(
+        GsnInstruction::deserialize(&unblacklist.data).unwrap(),
+        GsnInstruction::UnblacklistAddress(args)
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&blacklist.data[..32]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // Accounts written before the blacklist existed have none
+    let mut gsn = GsnInfo::new();
+    gsn.blacklist_address(keys[4]);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let legacy_len = gsn.serialized_size() - 157;
+    assert!(GsnInfo::deserialize(&data[..legacy_len]).unwrap().blacklist.is_empty());
+    assert_eq!(
+        GsnInfo::deserialize(&data).unwrap().check_not_blacklisted(&keys[4]),
+        Err(GsnError::Blacklisted)
+    );
+}
+
+fn accounts<'a>(
+    keys: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    signers: &[usize],
+    owner: &'a Pubkey,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = signers.contains(&index);
+            AccountInfo::new(key, is_signer, true, lamports, data, owner, index == 0, 0)
+        })
+        .collect()
+}
+
+/// GSN state and config accounts governed by `authority`, with a consumer
+/// and an executor holding balances
+struct Deployment {
+    program_id: Pubkey,
+    authority: Pubkey,
+    gsn_key: Pubkey,
+    config_key: Pubkey,
+    consumer: Pubkey,
+    executor: Pubkey,
+    gsn: Vec<u8>,
+    config: Vec<u8>,
+}
+
+impl Deployment {
+    fn new() -> Self {
+        let (gsn_key, config_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let authority = Pubkey::new_unique();
+        let mut gsn = GsnInfo::new();
+        gsn.config = config_key;
+        gsn.add_consumer(consumer, 1_000_000);
+        gsn.executor_mut().insert(executor, 50_000);
+        let mut config = GsnConfig::new(gsn_key);
+        config.initialize_governance(authority);
+        let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+        gsn.serialize(&mut gsn_data).unwrap();
+        config.serialize(&mut config_data).unwrap();
+        Self {
+            program_id: Pubkey::new_unique(),
+            authority,
+            gsn_key,
+            config_key,
+            consumer,
+            executor,
+            gsn: gsn_data,
+            config: config_data,
+        }
+    }
+
+    fn set_blacklisted(&mut self, signer: Pubkey, address: Pubkey, blacklisted: bool) -> Result<(), ProgramError> {
+        let keys = [self.gsn_key, self.config_key, signer];
+        let mut data = [self.gsn.clone(), self.config.clone(), vec![]];
+        let mut lamports = [1, 1, 1];
+        let accounts = accounts(&keys, &mut lamports, &mut data, &[2], &self.program_id);
+        Processor::process_set_blacklisted(&self.program_id, BlacklistArgs { address }, blacklisted, &accounts)?;
+        self.gsn = data[0].clone();
+        Ok(())
+    }
+
+    /// `SubmitTransaction` of the consumer, relayed by `executor`
+    fn submit(&self, executor: Pubkey) -> Result<(), ProgramError> {
+        // target, sender, receiver, fee payer, state, config
+        let keys = [
+            Pubkey::new_unique(),
+            self.consumer,
+            Pubkey::new_unique(),
+            executor,
+            self.gsn_key,
+            self.config_key,
+        ];
+        let mut data = [vec![], vec![], vec![], vec![], self.gsn.clone(), self.config.clone()];
+        let mut lamports = [1, 1_000_000, 0, 1_000_000, 1, 1];
+        let submit = accounts(&keys, &mut lamports, &mut data, &[1, 3], &self.program_id);
+        Processor::process_submit_tx(&self.program_id, 1_000, 0, 0, None, None, 0, None, &submit)
+    }
+
+    /// `Topup` by the consumer; blacklisting fails it before the vault is
+    /// checked
+    fn topup(&self) -> Result<(), ProgramError> {
+        // state, consumer, config, vault, system program
+        let keys = [
+            self.gsn_key,
+            self.consumer,
+            self.config_key,
+            Pubkey::new_unique(),
+            Pubkey::default(),
+        ];
+        let mut data = [self.gsn.clone(), vec![], self.config.clone(), vec![], vec![]];
+        let mut lamports = [1, 1_000_000, 1, 0, 1];
+        let topup = accounts(&keys, &mut lamports, &mut data, &[1], &self.program_id);
+        Processor::process_topup(&self.program_id, 1_000, None, &topup)
+    }
+
+    /// `ClaimFees` of the executor
+    fn claim(&self) -> Result<(), ProgramError> {
+        // state, executor, destination, vault, system program
+        let keys = [
+            self.gsn_key,
+            self.executor,
+            self.executor,
+            Pubkey::new_unique(),
+            Pubkey::default(),
+        ];
+        let mut data = [self.gsn.clone(), vec![], vec![], vec![], vec![]];
+        let mut lamports = [1, 0, 0, 50_000, 1];
+        let claim = accounts(&keys, &mut lamports, &mut data, &[1, 2], &self.program_id);
+        Processor::process_claim_fees(&self.program_id, &claim)
+    }
+}
+
+#[test]
+fn test_only_the_authority_blacklists() {
+    let mut deployment = Deployment::new();
+    let consumer = deployment.consumer;
+    assert_eq!(
+        deployment.set_blacklisted(Pubkey::new_unique(), consumer, true),
+        Err(GsnError::Unauthorized.into())
+    );
+    deployment.set_blacklisted(deployment.authority, consumer, true).unwrap();
+    let gsn = GsnInfo::deserialize(&deployment.gsn).unwrap();
+    assert_eq!(gsn.check_not_blacklisted(&consumer), Err(GsnError::Blacklisted));
+
+    deployment.set_blacklisted(deployment.authority, consumer, false).unwrap();
+    assert!(GsnInfo::deserialize(&deployment.gsn).unwrap().blacklist.is_empty());
+}
+
+#[test]
+fn test_blacklisted_addresses_are_refused() {
+    let mut deployment = Deployment::new();
+    let (authority, consumer, executor) = (deployment.authority, deployment.consumer, deployment.executor);
+    let blacklisted: Result<(), ProgramError> = Err(GsnError::Blacklisted.into());
+
+    deployment.set_blacklisted(authority, consumer, true).unwrap();
+    assert_eq!(deployment.topup(), blacklisted);
+    assert_eq!(deployment.submit(executor), blacklisted);
+    deployment.set_blacklisted(authority, consumer, false).unwrap();
+    deployment.submit(executor).unwrap();
+
+    // Executors can neither relay nor claim
+    deployment.set_blacklisted(authority, executor, true).unwrap();
+    assert_eq!(deployment.submit(executor), blacklisted);
+    assert_eq!(deployment.claim(), blacklisted);
+    deployment.submit(Pubkey::new_unique()).unwrap();
+}
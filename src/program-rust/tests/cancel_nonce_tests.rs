@@ -0,0 +1,132 @@
+// Tests for consumers cancelling nonces they signed for
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{CancelNonceArgs, GsnInstruction},
+    state::{GsnInfo, CHANNEL_NONCE_LANE, UNORDERED_NONCE_LANE},
+    Processor,
+};
+
+#[test]
+fn test_cancel_nonce_decodes() {
+    let mut data = vec![54];
+    data.extend_from_slice(&7u64.to_le_bytes());
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::CancelNonce(CancelNonceArgs { nonce: 7, lane: 0 })
+    );
+    data.extend_from_slice(&UNORDERED_NONCE_LANE.to_le_bytes());
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::CancelNonce(CancelNonceArgs {
+            nonce: 7,
+            lane: UNORDERED_NONCE_LANE,
+        })
+    );
+    for len in [data.len() - 1, data.len() + 8] {
+        data.resize(len, 0);
+        assert!(GsnInstruction::deserialize(&data).is_err());
+    }
+}
+
+/// `CancelNonce` accounts: state, consumer, then the channel's executor
+fn cancel(
+    gsn_data: &mut Vec<u8>,
+    consumer: &Pubkey,
+    signs: bool,
+    nonce: u64,
+    lane: u64,
+    executor: Option<&Pubkey>,
+) -> ProgramResult {
+    let owner = Pubkey::new_unique();
+    let mut keys = vec![Pubkey::new_unique(), *consumer];
+    keys.extend(executor);
+    let mut data = vec![vec![]; keys.len()];
+    data[0] = gsn_data.clone();
+    let mut lamports = vec![1; keys.len()];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(
+                key,
+                index == 1 && signs,
+                true,
+                lamports,
+                data,
+                &owner,
+                false,
+                0,
+            )
+        })
+        .collect();
+    let result = Processor::process_cancel_nonce(&owner, nonce, lane, &accounts);
+    drop(accounts);
+    *gsn_data = data[0].clone();
+    result
+}
+
+fn gsn_data() -> Vec<u8> {
+    let mut data = vec![0u8; 1024];
+    GsnInfo::new().serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_cancel_burns_counted_nonces() {
+    let consumer = Pubkey::new_unique();
+    let mut data = gsn_data();
+    assert!(cancel(&mut data, &consumer, false, 0, 0, None).is_err());
+
+    // Cancelling a pending nonce burns the ones before it too
+    cancel(&mut data, &consumer, true, 2, 0, None).unwrap();
+    cancel(&mut data, &consumer, true, 0, 5, None).unwrap();
+    let gsn = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(gsn.get_next_nonce(&consumer), 3);
+    assert!(gsn.is_nonce_used(&consumer, 1));
+    assert_eq!(gsn.get_next_lane_nonce(&consumer, 5), 1);
+
+    assert_eq!(
+        cancel(&mut data, &consumer, true, 2, 0, None),
+        Err(GsnError::ReplayAttack.into())
+    );
+    assert_eq!(
+        cancel(&mut data, &consumer, true, 0, 16, None),
+        Err(GsnError::InvalidNonceLane.into())
+    );
+}
+
+#[test]
+fn test_cancel_unordered_and_channel_nonces() {
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = gsn_data();
+
+    // Only the cancelled unordered nonce is burnt
+    cancel(&mut data, &consumer, true, 9, UNORDERED_NONCE_LANE, None).unwrap();
+    let gsn = GsnInfo::deserialize(&data).unwrap();
+    assert!(gsn.is_unordered_nonce_used(&consumer, 9));
+    assert!(!gsn.is_unordered_nonce_used(&consumer, 8));
+    assert_eq!(
+        cancel(&mut data, &consumer, true, 9, UNORDERED_NONCE_LANE, None),
+        Err(GsnError::ReplayAttack.into())
+    );
+
+    cancel(
+        &mut data,
+        &consumer,
+        true,
+        1,
+        CHANNEL_NONCE_LANE,
+        Some(&executor),
+    )
+    .unwrap();
+    let gsn = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(gsn.get_next_channel_nonce(&consumer, &executor), 2);
+    assert_eq!(
+        gsn.get_next_channel_nonce(&consumer, &Pubkey::new_unique()),
+        0
+    );
+}
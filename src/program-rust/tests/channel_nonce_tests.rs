@@ -0,0 +1,117 @@
+// Tests for per-(consumer, executor) channel nonces
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    state::{GsnConfig, GsnInfo, CHANNEL_NONCE_LANE},
+    Processor,
+};
+
+#[test]
+fn test_channel_nonces_count_per_executor() {
+    let consumer = Pubkey::new_unique();
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    assert_eq!(gsn.increment_channel_nonce(&consumer, &first), 1);
+    assert_eq!(gsn.increment_channel_nonce(&consumer, &first), 2);
+
+    assert_eq!(gsn.get_next_channel_nonce(&consumer, &first), 2);
+    assert_eq!(gsn.get_next_channel_nonce(&consumer, &second), 0);
+    assert!(gsn.is_channel_nonce_used(&consumer, &first, 1));
+    assert!(!gsn.is_channel_nonce_used(&consumer, &second, 1));
+    // Channels don't touch the consumer's lanes
+    assert_eq!(gsn.get_next_nonce(&consumer), 0);
+}
+
+/// Accounts of a `SubmitTransactionInLane`: target program, sender, receiver,
+/// fee payer, GSN state and config, then a second executor
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let keys: Vec<Pubkey> = (0..7).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 1_000_000);
+        let config = GsnConfig::new(keys[4]);
+        let mut data = vec![vec![]; 7];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    /// Submit with the executor at `executor`, 3 or 6, paying the fee
+    fn submit(&mut self, executor: usize, nonce: u64) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let mut accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3 || index == 6;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        if executor == 6 {
+            accounts.swap(3, 6);
+        }
+        accounts.truncate(6);
+        Processor::process_submit_tx(&owner, 1_000, nonce, CHANNEL_NONCE_LANE, None, None, 0, None, &accounts)
+    }
+
+    fn gsn(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.data[4]).unwrap()
+    }
+}
+
+#[test]
+fn test_executors_relay_concurrently_in_their_channels() {
+    let mut setup = Setup::new();
+    setup.submit(3, 0).unwrap();
+    // The second executor's channel starts at 0 too
+    setup.submit(6, 0).unwrap();
+    setup.submit(6, 1).unwrap();
+    assert_eq!(setup.submit(3, 0), Err(GsnError::ReplayAttack.into()));
+    assert_eq!(setup.submit(3, 2), Err(GsnError::InvalidNonce.into()));
+
+    let gsn = setup.gsn();
+    let consumer = &setup.keys[1];
+    assert_eq!(gsn.get_next_channel_nonce(consumer, &setup.keys[3]), 1);
+    assert_eq!(gsn.get_next_channel_nonce(consumer, &setup.keys[6]), 2);
+    assert!(gsn.transaction_executor.is_empty());
+}
+
+#[test]
+fn test_channel_nonces_round_trip() {
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before channel nonces existed have none
+    let legacy_len = gsn.serialized_size() - 176;
+    let mut decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.get_next_channel_nonce(&consumer, &executor), 0);
+
+    decoded.increment_channel_nonce(&consumer, &executor);
+    decoded.serialize(&mut data).unwrap();
+    assert_eq!(
+        GsnInfo::deserialize(&data)
+            .unwrap()
+            .get_next_channel_nonce(&consumer, &executor),
+        1
+    );
+}
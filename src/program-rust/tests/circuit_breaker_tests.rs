@@ -0,0 +1,287 @@
+// Tests for the circuit breaker pausing the deployment on failed executions
+
+mod common;
+
+use common::Submit;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, ReportFailureArgs, SetCircuitBreakerArgs},
+    state::{
+        CircuitBreaker, GsnInfo, CHANNEL_NONCE_LANE, MAX_FAILURE_REPORTS_PER_EXECUTOR,
+        MAX_NONCE_LANES,
+    },
+    Processor,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the target program fails
+static FAIL: AtomicBool = AtomicBool::new(false);
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            epoch: 7,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        _instruction: &Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if FAIL.load(Ordering::SeqCst) {
+            return Err(ProgramError::Custom(1));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_set_circuit_breaker_decode() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let ix = instruction::set_circuit_breaker(&keys[0], &keys[1], &keys[2], &keys[3], 2_500, 40);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data).unwrap(),
+        GsnInstruction::SetCircuitBreaker(SetCircuitBreakerArgs {
+            max_failure_bps: 2_500,
+            min_executions: 40,
+        })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data[..10]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // Accounts written before the breaker existed have it off
+    let mut gsn = GsnInfo::new();
+    gsn.circuit_breaker_mut().max_failure_bps = 2_500;
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let legacy_len = gsn.serialized_size() - 120;
+    let decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.circuit_breaker, CircuitBreaker::default());
+    assert!(GsnInfo::deserialize(&data).unwrap().circuit_breaker.is_enabled());
+}
+
+#[test]
+fn test_report_failure_decode() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let ix = instruction::report_failure(&keys[0], &keys[1], &keys[2], &keys[3], 2, 9);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data).unwrap(),
+        GsnInstruction::ReportFailure(ReportFailureArgs {
+            consumer: keys[3],
+            lane: 2,
+            nonce: 9,
+        })
+    );
+    assert!(ix.accounts[1].is_signer);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data[..48]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_counts_restart_every_epoch() {
+    let mut breaker = CircuitBreaker {
+        max_failure_bps: 5_000,
+        min_executions: 2,
+        ..CircuitBreaker::default()
+    };
+    assert!(!breaker.record(1, false));
+    // Half failed isn't above the threshold
+    assert!(!breaker.record(1, true));
+    assert!(breaker.record(1, false));
+    assert!(!breaker.record(2, false));
+    assert_eq!((breaker.epoch, breaker.succeeded, breaker.failed), (2, 0, 1));
+}
+
+impl Submit {
+    /// Submit `nonce` to a target program failing when `fail` is set
+    fn relay(&mut self, nonce: u64, fail: bool) -> ProgramResult {
+        FAIL.store(fail, Ordering::SeqCst);
+        self.submit(nonce)
+    }
+
+    /// `ReportFailure` of the sender's `lane` and `nonce`, signed by `executor`
+    fn report_failure(&mut self, executor: Pubkey, lane: u64, nonce: u64) -> ProgramResult {
+        self.report_consumer_failure(executor, self.keys[1], lane, nonce)
+    }
+
+    /// `ReportFailure` of `consumer`'s `lane` and `nonce`, signed by `executor`
+    fn report_consumer_failure(
+        &mut self,
+        executor: Pubkey,
+        consumer: Pubkey,
+        lane: u64,
+        nonce: u64,
+    ) -> ProgramResult {
+        let program_id = self.program_id;
+        let (mut gsn_lamports, mut executor_lamports) = (1, 0);
+        let mut executor_data = vec![];
+        let accounts = [
+            AccountInfo::new(
+                &self.keys[4],
+                false,
+                true,
+                &mut gsn_lamports,
+                &mut self.data[4],
+                &program_id,
+                false,
+                0,
+            ),
+            AccountInfo::new(
+                &executor,
+                true,
+                false,
+                &mut executor_lamports,
+                &mut executor_data,
+                &program_id,
+                false,
+                0,
+            ),
+        ];
+        let args = ReportFailureArgs {
+            consumer,
+            lane,
+            nonce,
+        };
+        Processor::process_report_failure(&program_id, args, &accounts)
+    }
+
+    /// `SetCircuitBreaker` signed by `signer`
+    fn set_circuit_breaker(&mut self, signer: Pubkey, max_failure_bps: u16, min_executions: u64) -> ProgramResult {
+        let args = SetCircuitBreakerArgs {
+            max_failure_bps,
+            min_executions,
+        };
+        self.govern(signer, |program_id, accounts| {
+            Processor::process_set_circuit_breaker(program_id, args, accounts)
+        })
+    }
+}
+
+#[test]
+fn test_set_circuit_breaker_requires_governance() {
+    set_syscall_stubs(Box::new(Stubs));
+    let mut setup = Submit::new(|_, _, _| {});
+    assert_eq!(
+        setup.set_circuit_breaker(Pubkey::new_unique(), 5_000, 4),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        setup.set_circuit_breaker(setup.authority, 10_001, 4),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    setup.set_circuit_breaker(setup.authority, 5_000, 4).unwrap();
+    let breaker = setup.state().circuit_breaker;
+    assert_eq!((breaker.max_failure_bps, breaker.min_executions), (5_000, 4));
+}
+
+#[test]
+fn test_reported_failures_above_the_threshold_pause_the_deployment() {
+    set_syscall_stubs(Box::new(Stubs));
+    // The fee payer is a registered executor, the sender submitted on lanes
+    // 1 and 2 before
+    let mut setup = Submit::new(|gsn, _, keys| {
+        gsn.register_executor(keys[3]);
+        gsn.increment_lane_nonce(&keys[1], 1);
+        gsn.increment_lane_nonce(&keys[1], 2);
+    });
+    let executor = setup.keys[3];
+    // Reports count nothing while the breaker is off
+    setup.report_failure(executor, 0, 0).unwrap();
+    assert_eq!(setup.state().circuit_breaker, CircuitBreaker::default());
+    setup.set_circuit_breaker(setup.authority, 5_000, 4).unwrap();
+
+    setup.relay(0, false).unwrap();
+    // The failed relay reverts, it counts once reported
+    assert_eq!(setup.relay(1, true), Err(ProgramError::Custom(1)));
+    assert_eq!(setup.state().circuit_breaker.failed, 0);
+    assert_eq!(
+        setup.report_failure(Pubkey::new_unique(), 0, 1),
+        Err(GsnError::Unauthorized.into())
+    );
+    setup.report_failure(executor, 0, 1).unwrap();
+    // Every relay is reported once, by the nonce the lane still expects
+    assert_eq!(
+        setup.report_failure(executor, 0, 1),
+        Err(GsnError::InvalidFailureReport.into())
+    );
+    assert_eq!(
+        setup.report_failure(executor, 0, 0),
+        Err(GsnError::InvalidFailureReport.into())
+    );
+    // Only lanes the consumer submitted on are reported
+    assert_eq!(
+        setup.report_failure(executor, 3, 0),
+        Err(GsnError::InvalidFailureReport.into())
+    );
+    setup.report_failure(executor, 1, 1).unwrap();
+    // Too few executions to judge yet
+    let breaker = setup.state().circuit_breaker;
+    assert_eq!((breaker.epoch, breaker.succeeded, breaker.failed), (7, 1, 2));
+    assert!(!setup.state().paused);
+
+    setup.report_failure(executor, 2, 1).unwrap();
+    assert!(setup.state().paused);
+    assert_eq!(setup.relay(1, false), Err(GsnError::ProtocolPaused.into()));
+    // The failed submission charged nothing
+    assert_eq!(setup.state().consumer.get(&setup.keys[1]), Some(&950_000));
+}
+
+#[test]
+fn test_failure_reports_need_a_consumer_and_are_capped_per_executor() {
+    set_syscall_stubs(Box::new(Stubs));
+    let (executor, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut setup = Submit::new(|gsn, _, keys| {
+        gsn.register_executor(executor);
+        gsn.register_executor(other);
+        for lane in 1..MAX_NONCE_LANES {
+            gsn.increment_lane_nonce(&keys[1], lane);
+        }
+    });
+    setup.set_circuit_breaker(setup.authority, 10_000, 1_000).unwrap();
+    let consumer = setup.keys[1];
+
+    // A key the program never saw has nothing to relay
+    assert_eq!(
+        setup.report_consumer_failure(executor, Pubkey::new_unique(), 0, 0),
+        Err(GsnError::InvalidFailureReport.into())
+    );
+    // Nor a channel the executor never used
+    assert_eq!(
+        setup.report_consumer_failure(executor, consumer, CHANNEL_NONCE_LANE, 0),
+        Err(GsnError::InvalidFailureReport.into())
+    );
+    for lane in 0..MAX_FAILURE_REPORTS_PER_EXECUTOR as u64 {
+        let nonce = if lane == 0 { 0 } else { 1 };
+        setup.report_consumer_failure(executor, consumer, lane, nonce).unwrap();
+    }
+    let lane = MAX_FAILURE_REPORTS_PER_EXECUTOR as u64;
+    assert_eq!(
+        setup.report_consumer_failure(executor, consumer, lane, 1),
+        Err(GsnError::FailureReportLimit.into())
+    );
+    // Other executors still report
+    setup.report_consumer_failure(other, consumer, lane, 1).unwrap();
+    let state = setup.state();
+    assert_eq!(state.circuit_breaker.failed, MAX_FAILURE_REPORTS_PER_EXECUTOR as u64 + 1);
+    assert_eq!(state.failure_reports.get(&(consumer, lane)).unwrap().executor, other);
+}
@@ -0,0 +1,129 @@
+// Tests for closing consumers whose balances are empty
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction},
+    state::{ConsumerAccount, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_close_consumer_decode() {
+    let (program_id, gsn, consumer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let close = instruction::close_consumer(&program_id, &gsn, &consumer);
+    assert_eq!(
+        GsnInstruction::deserialize(&close.data).unwrap(),
+        GsnInstruction::CloseConsumer
+    );
+    let (ledger, _) = ConsumerAccount::find_address(&program_id, &gsn, &consumer);
+    assert_eq!(close.accounts[2].pubkey, ledger);
+    assert!(close.accounts[1].is_signer);
+}
+
+#[test]
+fn test_close_consumer_keeps_nonces() {
+    let (consumer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, 10);
+    gsn.token_consumer.insert((consumer, mint), 0);
+    gsn.consumer_nonces.insert(consumer, 4);
+    assert_eq!(gsn.close_consumer(&consumer, None), Err(GsnError::BalanceNotEmpty));
+
+    gsn.add_consumer(consumer, 0);
+    gsn.token_consumer.insert((consumer, mint), 5);
+    assert_eq!(gsn.close_consumer(&consumer, None), Err(GsnError::BalanceNotEmpty));
+
+    gsn.token_consumer.insert((consumer, mint), 0);
+    assert_eq!(gsn.close_consumer(&consumer, None), Ok(()));
+    assert!(!gsn.consumer.contains_key(&consumer));
+    assert!(gsn.token_consumer.is_empty());
+    assert_eq!(gsn.get_next_lane_nonce(&consumer, 0), 4);
+}
+
+/// A state holding `consumer`, whose balance and nonce moved into its
+/// ledger account
+struct Deployment {
+    program_id: Pubkey,
+    gsn_key: Pubkey,
+    consumer: Pubkey,
+    ledger_key: Pubkey,
+    gsn: Vec<u8>,
+    ledger: Vec<u8>,
+}
+
+impl Deployment {
+    fn new(balance: u64, nonce: u64) -> Self {
+        let (program_id, gsn_key, consumer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (ledger_key, bump) = ConsumerAccount::find_address(&program_id, &gsn_key, &consumer);
+        let mut gsn = GsnInfo::new();
+        gsn.add_consumer(consumer, balance);
+        gsn.consumer_nonces.insert(consumer, nonce);
+        let mut account = ConsumerAccount {
+            is_initialized: true,
+            gsn_account: gsn_key,
+            consumer,
+            balance: 0,
+            nonce: 0,
+            bump,
+        };
+        gsn.open_consumer_account(&mut account);
+
+        let (mut gsn_data, mut ledger_data) = (vec![0u8; 1024], vec![0u8; ConsumerAccount::LEN]);
+        gsn.serialize(&mut gsn_data).unwrap();
+        account.serialize(&mut ledger_data).unwrap();
+        Self {
+            program_id,
+            gsn_key,
+            consumer,
+            ledger_key,
+            gsn: gsn_data,
+            ledger: ledger_data,
+        }
+    }
+
+    /// `CloseConsumer`, with or without the ledger account, returning the
+    /// lamports the consumer ends up with
+    fn close(&mut self, with_ledger: bool) -> (ProgramResult, u64) {
+        let mut lamports = [1_000_000, 0, 2_000];
+        let [gsn_lamports, consumer_lamports, ledger_lamports] = &mut lamports;
+        let mut consumer_data = vec![];
+        let mut accounts = vec![
+            AccountInfo::new(&self.gsn_key, false, true, gsn_lamports, &mut self.gsn, &self.program_id, false, 0),
+            AccountInfo::new(&self.consumer, true, true, consumer_lamports, &mut consumer_data, &self.program_id, false, 0),
+        ];
+        if with_ledger {
+            accounts.push(AccountInfo::new(
+                &self.ledger_key,
+                false,
+                true,
+                ledger_lamports,
+                &mut self.ledger,
+                &self.program_id,
+                false,
+                0,
+            ));
+        }
+        let result = Processor::process_close_consumer(&self.program_id, &accounts);
+        drop(accounts);
+        (result, lamports[1])
+    }
+}
+
+#[test]
+fn test_close_consumer_closes_its_ledger_account() {
+    let mut deployment = Deployment::new(0, 7);
+    assert_eq!(deployment.close(false).0, Err(GsnError::MissingLedgerAccount.into()));
+
+    assert_eq!(deployment.close(true), (Ok(()), 2_000));
+    assert!(deployment.ledger.iter().all(|byte| *byte == 0));
+    let gsn = GsnInfo::deserialize(&deployment.gsn).unwrap();
+    assert!(gsn.ledger_accounts.is_empty());
+    assert!(!gsn.consumer.contains_key(&deployment.consumer));
+    // The lane 0 nonce is back in the state
+    assert_eq!(gsn.get_next_lane_nonce(&deployment.consumer, 0), 7);
+
+    // A balance left in the ledger account keeps it open
+    let mut deployment = Deployment::new(500, 7);
+    assert_eq!(deployment.close(true).0, Err(GsnError::BalanceNotEmpty.into()));
+}
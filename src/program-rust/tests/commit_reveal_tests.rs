@@ -0,0 +1,125 @@
+// Tests for commit-reveal submissions
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::GsnInstruction,
+    state::{SubmissionCommitment, COMMITMENT_EXPIRY_SLOTS, REVEAL_DELAY_SLOTS},
+    Processor,
+};
+
+fn commitment(slot: u64) -> SubmissionCommitment {
+    SubmissionCommitment {
+        is_initialized: true,
+        gsn_account: Pubkey::new_unique(),
+        executor: Pubkey::new_unique(),
+        hash: [7; 32],
+        slot,
+    }
+}
+
+#[test]
+fn test_commitment_fits_its_account() {
+    let commitment = commitment(42);
+    let mut data = vec![0; SubmissionCommitment::LEN];
+    commitment.serialize(&mut data).unwrap();
+    assert_eq!(
+        SubmissionCommitment::deserialize(&data).unwrap(),
+        commitment
+    );
+
+    let mut short = vec![0; SubmissionCommitment::LEN - 1];
+    assert_eq!(
+        commitment.serialize(&mut short),
+        Err(ProgramError::AccountDataTooSmall)
+    );
+}
+
+#[test]
+fn test_commitment_reveal_window() {
+    let commitment = commitment(1_000);
+    assert_eq!(commitment.revealable_at(), 1_000 + REVEAL_DELAY_SLOTS);
+    assert_eq!(commitment.expires_at(), 1_000 + COMMITMENT_EXPIRY_SLOTS);
+    assert!(commitment.revealable_at() < commitment.expires_at());
+}
+
+#[test]
+fn test_commit_instruction_decodes() {
+    let mut data = vec![13];
+    data.extend_from_slice(&[9; 32]);
+    match GsnInstruction::deserialize(&data).unwrap() {
+        GsnInstruction::CommitSubmission(args) => assert_eq!(args.hash, [9; 32]),
+        other => panic!("expected CommitSubmission, got {:?}", other),
+    }
+
+    // The hash is required
+    assert_eq!(
+        GsnInstruction::deserialize(&data[..32]),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+/// Executor, GSN state, commitment and System Program accounts of a commit
+fn commit_accounts<'a>(
+    keys: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    executor_signs: bool,
+    gsn_owner: &'a Pubkey,
+    system_owner: &'a Pubkey,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(
+                key,
+                index == 0 && executor_signs,
+                true,
+                lamports,
+                data,
+                if index == 1 { gsn_owner } else { system_owner },
+                false,
+                0,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_commit_requires_executor_signature_and_gsn_account() {
+    let program_id = Pubkey::new_unique();
+    let system_owner = Pubkey::default();
+    let mut keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    keys[3] = system_owner;
+    let mut lamports = vec![1_000_000; 4];
+    let mut data = vec![vec![]; 4];
+
+    let accounts = commit_accounts(
+        &keys,
+        &mut lamports,
+        &mut data,
+        false,
+        &program_id,
+        &system_owner,
+    );
+    assert_eq!(
+        Processor::process_commit_submission(&program_id, [1; 32], &accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    drop(accounts);
+
+    let accounts = commit_accounts(
+        &keys,
+        &mut lamports,
+        &mut data,
+        true,
+        &system_owner,
+        &system_owner,
+    );
+    assert_eq!(
+        Processor::process_commit_submission(&program_id, [1; 32], &accounts),
+        Err(GsnError::InvalidAccountOwner.into())
+    );
+}
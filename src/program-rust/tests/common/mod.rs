@@ -0,0 +1,102 @@
+// Fixture shared by the tests submitting with `SubmitTransaction`
+
+// Each test crate uses its own part of the fixture
+#![allow(dead_code)]
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solgsn::{
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+/// `SubmitTransaction` accounts: target program, sender, receiver, fee
+/// payer, GSN state, config
+pub struct Submit {
+    pub program_id: Pubkey,
+    pub keys: Vec<Pubkey>,
+    /// Governance authority of the config
+    pub authority: Pubkey,
+    pub lamports: Vec<u64>,
+    pub data: Vec<Vec<u8>>,
+}
+
+impl Submit {
+    /// A deployment where the sender has a 1,000,000 lamport balance, after
+    /// `setup` changed its state and config, given the keys
+    pub fn new(setup: impl FnOnce(&mut GsnInfo, &mut GsnConfig, &[Pubkey])) -> Self {
+        let program_id = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let authority = Pubkey::new_unique();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 1_000_000);
+        let mut config = GsnConfig::new(keys[4]);
+        config.initialize_governance(authority);
+        setup(&mut gsn, &mut config, &keys);
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 2048];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            program_id,
+            keys,
+            authority,
+            lamports: vec![1, 10_000_000, 0, 1_000_000, 1, 1],
+            data,
+        }
+    }
+
+    pub fn state(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.data[4]).unwrap()
+    }
+
+    /// Submit 1,000 lamports with `nonce`, reverting the accounts' data when
+    /// it fails like the runtime does
+    pub fn submit(&mut self, nonce: u64) -> ProgramResult {
+        let program_id = self.program_id;
+        let committed = self.data.clone();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        let result = Processor::process_submit_tx(&program_id, 1_000, nonce, 0, None, None, 0, None, &accounts);
+        drop(accounts);
+        if result.is_err() {
+            self.data = committed;
+        }
+        result
+    }
+
+    /// Governance instruction signed by `signer`, processed by `process` with
+    /// the GSN state, the config and the signer
+    pub fn govern(
+        &mut self,
+        signer: Pubkey,
+        process: impl FnOnce(&Pubkey, &[AccountInfo]) -> ProgramResult,
+    ) -> ProgramResult {
+        let program_id = self.program_id;
+        let keys = [self.keys[4], self.keys[5], signer];
+        let mut lamports = [1, 1, 0];
+        let (gsn, config) = self.data.split_at_mut(5);
+        let mut signer_data = vec![];
+        let data = [&mut gsn[4], &mut config[0], &mut signer_data];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data)
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 2, index == 0, lamports, data, &program_id, false, 0)
+            })
+            .collect();
+        process(&program_id, &accounts)
+    }
+}
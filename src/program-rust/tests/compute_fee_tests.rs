@@ -0,0 +1,176 @@
+// Tests for fees priced by compute units
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SubmitMeteredArgs, UpdateFeeParamsArgs},
+    state::{FeeMode, GsnConfig, GsnInfo},
+    Processor,
+};
+
+const COMPUTE_BASED: FeeMode = FeeMode::ComputeBased {
+    price_per_cu: 1_000_000,
+    base: 5_000,
+    max_compute_units: 200_000,
+};
+
+fn config(fee_mode: FeeMode) -> GsnConfig {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(Pubkey::new_unique());
+    config.update_fee_params(fee_mode);
+    config
+}
+
+#[test]
+fn test_compute_based_fee() {
+    let config = config(COMPUTE_BASED);
+    // Unmetered submissions pay for the maximum
+    assert_eq!(config.calculate_fee(1_000), 205_000);
+    assert_eq!(config.calculate_metered_fee(1_000, 30_000), Ok(35_000));
+    assert_eq!(
+        config.calculate_metered_fee(1_000, 200_001),
+        Err(GsnError::InvalidComputeUnits)
+    );
+
+    // Micro-lamport prices round down
+    let config = self::config(FeeMode::ComputeBased {
+        price_per_cu: 1,
+        base: 0,
+        max_compute_units: 200_000,
+    });
+    assert_eq!(config.calculate_metered_fee(1_000, 199_999), Ok(0));
+
+    // Other modes don't depend on the measurement
+    let config = self::config(FeeMode::Fixed(7_000));
+    assert_eq!(config.calculate_metered_fee(1_000, u64::MAX), Ok(7_000));
+}
+
+#[test]
+fn test_compute_based_reductions() {
+    let lower = FeeMode::ComputeBased {
+        price_per_cu: 500_000,
+        base: 5_000,
+        max_compute_units: 100_000,
+    };
+    assert!(lower.is_reduction_of(&COMPUTE_BASED));
+    assert!(!COMPUTE_BASED.is_reduction_of(&lower));
+    assert!(!COMPUTE_BASED.is_reduction_of(&FeeMode::Fixed(u64::MAX)));
+}
+
+#[test]
+fn test_fee_params_decode_extra_values() {
+    let mut data = vec![3, 2, 0, 0, 0, 0, 0, 0, 0];
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::UpdateFeeParams(UpdateFeeParamsArgs {
+            fee_mode_type: 2,
+            fee_value: 1_000_000,
+            extra: [0; 2],
+        })
+    );
+
+    data.extend_from_slice(&5_000u64.to_le_bytes());
+    data.extend_from_slice(&200_000u64.to_le_bytes());
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::UpdateFeeParams(UpdateFeeParamsArgs {
+            fee_mode_type: 2,
+            fee_value: 1_000_000,
+            extra: [5_000, 200_000],
+        })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&data[..data.len() - 1]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_submit_metered_decodes() {
+    let mut data = vec![51];
+    for value in [1_000u64, 4, 30_000] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::SubmitMetered(SubmitMeteredArgs {
+            amount: 1_000,
+            nonce: 4,
+            compute_units: 30_000,
+        })
+    );
+}
+
+/// Accounts of a `SubmitMetered`: target program, sender, receiver, fee
+/// payer, GSN state and config, with the compute based fee above
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 200_000);
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        let mut config = config(COMPUTE_BASED);
+        config.gsn_account = keys[4];
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self, compute_units: Option<u64>) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        match compute_units {
+            Some(compute_units) => Processor::process_submit_metered(
+                &owner,
+                SubmitMeteredArgs {
+                    amount: 1_000,
+                    nonce: 0,
+                    compute_units,
+                },
+                &accounts,
+            ),
+            None => Processor::process_submit_tx(&owner, 1_000, 0, 0, None, None, 0, None, &accounts),
+        }
+    }
+}
+
+#[test]
+fn test_metered_submission_pays_for_its_compute_units() {
+    let mut setup = Setup::new();
+    assert_eq!(
+        setup.submit(None),
+        Err(GsnError::InsufficientBalance.into())
+    );
+    assert_eq!(
+        setup.submit(Some(200_001)),
+        Err(GsnError::InvalidComputeUnits.into())
+    );
+
+    setup.submit(Some(30_000)).unwrap();
+    let gsn = GsnInfo::deserialize(&setup.data[4]).unwrap();
+    assert_eq!(gsn.executor.get(&setup.keys[3]), Some(&35_000));
+}
@@ -0,0 +1,166 @@
+// Tests for credential-gated compliance mode
+
+use solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+use solgsn::{
+    credential::{check_credential, TOKEN_PROGRAM_ID},
+    error::GsnError,
+    instruction::GsnInstruction,
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+/// Base token account layout of `amount` tokens of `mint` held by `owner`
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    data[..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data[108] = 1;
+    data
+}
+
+#[test]
+fn test_credential_must_be_held_by_consumer() {
+    let mint = Pubkey::new_unique();
+    let consumer = Pubkey::new_unique();
+    let data = token_account(&mint, &consumer, 1);
+    assert_eq!(
+        check_credential(&TOKEN_PROGRAM_ID, &data, &mint, &consumer),
+        Ok(())
+    );
+
+    // Another consumer's credential, another mint, or an emptied account
+    let other = Pubkey::new_unique();
+    for (data, mint, consumer) in [
+        (token_account(&mint, &other, 1), mint, consumer),
+        (token_account(&other, &consumer, 1), mint, consumer),
+        (token_account(&mint, &consumer, 0), mint, consumer),
+    ] {
+        assert_eq!(
+            check_credential(&TOKEN_PROGRAM_ID, &data, &mint, &consumer),
+            Err(GsnError::MissingCredential)
+        );
+    }
+}
+
+#[test]
+fn test_credential_must_be_an_initialized_token_account() {
+    let mint = Pubkey::new_unique();
+    let consumer = Pubkey::new_unique();
+    let data = token_account(&mint, &consumer, 1);
+
+    // Same bytes in an account the token programs don't own
+    assert_eq!(
+        check_credential(&Pubkey::new_unique(), &data, &mint, &consumer),
+        Err(GsnError::MissingCredential)
+    );
+    assert_eq!(
+        check_credential(&TOKEN_PROGRAM_ID, &data[..164], &mint, &consumer),
+        Err(GsnError::MissingCredential)
+    );
+
+    let mut uninitialized = data.clone();
+    uninitialized[108] = 0;
+    assert_eq!(
+        check_credential(&TOKEN_PROGRAM_ID, &uninitialized, &mint, &consumer),
+        Err(GsnError::MissingCredential)
+    );
+
+    // Issuers freeze soulbound credentials, and Token-2022 extensions follow the base layout
+    let mut frozen = data.clone();
+    frozen[108] = 2;
+    frozen.resize(200, 0);
+    assert_eq!(
+        check_credential(&TOKEN_PROGRAM_ID, &frozen, &mint, &consumer),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_set_credential_mint_decodes() {
+    let mut data = vec![15, 1];
+    data.extend_from_slice(&[4; 32]);
+    match GsnInstruction::deserialize(&data).unwrap() {
+        GsnInstruction::SetCredentialMint(args) => {
+            assert_eq!(args.enabled, 1);
+            assert_eq!(args.mint, [4; 32]);
+        }
+        other => panic!("expected SetCredentialMint, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_config_without_credential_mint_decodes_as_off() {
+    let config = GsnConfig::new(Pubkey::new_unique());
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+    assert_eq!(GsnConfig::deserialize(&data).unwrap().credential_mint, None);
+}
+
+#[test]
+fn test_topup_requires_credential_in_compliance_mode() {
+    let mint = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let (gsn_key, consumer, config_key) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let keys = [
+        gsn_key,
+        consumer,
+        config_key,
+        vault,
+        Pubkey::default(),
+        Pubkey::new_unique(),
+    ];
+
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    let mut gsn_data = vec![0u8; 1024];
+    gsn.serialize(&mut gsn_data).unwrap();
+
+    let mut config = GsnConfig::new(gsn_key);
+    config.credential_mint = Some(mint);
+    let mut config_data = vec![0u8; 1024];
+    config.serialize(&mut config_data).unwrap();
+
+    let mut data = [
+        gsn_data,
+        vec![],
+        config_data,
+        vec![],
+        vec![],
+        token_account(&mint, &consumer, 1),
+    ];
+    let mut lamports = [1_000_000; 6];
+    let system = Pubkey::default();
+    let owners = [
+        program_id,
+        system,
+        program_id,
+        system,
+        system,
+        TOKEN_PROGRAM_ID,
+    ];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .zip(owners.iter())
+        .enumerate()
+        .map(|(index, (((key, lamports), data), owner))| {
+            AccountInfo::new(key, index == 1, true, lamports, data, owner, false, 0)
+        })
+        .collect();
+
+    assert_eq!(
+        Processor::process_topup(&program_id, 500, None, &accounts[..5]),
+        Err(GsnError::MissingCredential.into())
+    );
+    Processor::process_topup(&program_id, 500, None, &accounts).unwrap();
+
+    let gsn = GsnInfo::deserialize(&accounts[0].data.borrow()).unwrap();
+    assert_eq!(gsn.consumer.get(&consumer), Some(&500));
+}
@@ -0,0 +1,118 @@
+// Tests for per-submission fee escrows
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::GsnInstruction,
+    state::{FeeEscrow, ESCROW_DISPUTE_WINDOW_SLOTS},
+    Processor,
+};
+
+fn escrow(executor: Pubkey) -> FeeEscrow {
+    FeeEscrow {
+        is_initialized: true,
+        gsn_account: Pubkey::new_unique(),
+        consumer: Pubkey::new_unique(),
+        executor,
+        lane: 2,
+        nonce: 7,
+        fee: 50_000,
+        slot: 1_000,
+    }
+}
+
+#[test]
+fn test_escrow_fits_its_account() {
+    let escrow = escrow(Pubkey::new_unique());
+    let mut data = vec![0; FeeEscrow::LEN];
+    escrow.serialize(&mut data).unwrap();
+    assert_eq!(FeeEscrow::deserialize(&data).unwrap(), escrow);
+
+    let mut short = vec![0; FeeEscrow::LEN - 1];
+    assert_eq!(
+        escrow.serialize(&mut short),
+        Err(ProgramError::AccountDataTooSmall)
+    );
+}
+
+#[test]
+fn test_escrow_releasable_after_dispute_window() {
+    let escrow = escrow(Pubkey::new_unique());
+    assert_eq!(escrow.releasable_at(), 1_000 + ESCROW_DISPUTE_WINDOW_SLOTS);
+
+    let late = FeeEscrow {
+        slot: u64::MAX,
+        ..escrow
+    };
+    assert_eq!(late.releasable_at(), u64::MAX);
+}
+
+#[test]
+fn test_escrow_instructions_decode() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[11]).unwrap(),
+        GsnInstruction::ReleaseEscrow
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&[12]).unwrap(),
+        GsnInstruction::DisputeEscrow
+    );
+}
+
+#[test]
+fn test_release_rejects_foreign_accounts_and_executors() {
+    let program_id = Pubkey::new_unique();
+    let executor_key = Pubkey::new_unique();
+    let escrow_key = Pubkey::new_unique();
+    let system_owner = Pubkey::default();
+
+    let mut data = vec![0; FeeEscrow::LEN];
+    escrow(executor_key).serialize(&mut data).unwrap();
+    let (mut escrow_lamports, mut executor_lamports) = (1_000_000, 0);
+    let mut executor_data = vec![];
+
+    // Not owned by the program
+    let escrow_info = AccountInfo::new(
+        &escrow_key,
+        false,
+        true,
+        &mut escrow_lamports,
+        &mut data,
+        &system_owner,
+        false,
+        0,
+    );
+    let executor_info = AccountInfo::new(
+        &executor_key,
+        true,
+        true,
+        &mut executor_lamports,
+        &mut executor_data,
+        &system_owner,
+        false,
+        0,
+    );
+    assert_eq!(
+        Processor::process_release_escrow(&program_id, &[escrow_info, executor_info.clone()]),
+        Err(GsnError::InvalidEscrowAccount.into())
+    );
+
+    // Signed by another executor
+    let mut data = vec![0; FeeEscrow::LEN];
+    escrow(Pubkey::new_unique()).serialize(&mut data).unwrap();
+    let mut escrow_lamports = 1_000_000;
+    let escrow_info = AccountInfo::new(
+        &escrow_key,
+        false,
+        true,
+        &mut escrow_lamports,
+        &mut data,
+        &program_id,
+        false,
+        0,
+    );
+    assert_eq!(
+        Processor::process_release_escrow(&program_id, &[escrow_info, executor_info]),
+        Err(GsnError::UnauthorizedFeeClaim.into())
+    );
+}
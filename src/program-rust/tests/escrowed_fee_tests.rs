@@ -0,0 +1,101 @@
+// Tests for fees held in the state's escrow while the target program runs
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    instruction::SubmitInstructionArgs,
+    state::{GsnConfig, GsnInfo, NATIVE_FEE_TOKEN},
+    Processor,
+};
+use std::sync::Mutex;
+
+/// Consumer balance, escrowed fee and in-flight flag the target program saw
+/// in the state
+static SEEN: Mutex<Option<(u64, u64, bool)>> = Mutex::new(None);
+
+/// The target program reads the GSN state it is passed, then tops up the
+/// consumer by 1,000 lamports behind the program's back
+struct TargetStubs;
+
+impl SyscallStubs for TargetStubs {
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let consumer = instruction.accounts[0].pubkey;
+        let state = account_infos
+            .iter()
+            .find(|account| *account.key == instruction.accounts[1].pubkey)
+            .unwrap();
+        let mut gsn = GsnInfo::deserialize(&state.data.borrow())?;
+        let balance = gsn.consumer.get(&consumer).copied().unwrap_or(0);
+        let escrowed = gsn
+            .escrowed_fees
+            .get(&(consumer, NATIVE_FEE_TOKEN))
+            .copied()
+            .unwrap_or(0);
+        *SEEN.lock().unwrap() = Some((balance, escrowed, gsn.in_flight));
+        gsn.consumer_mut().insert(consumer, balance + 1_000);
+        gsn.serialize(&mut state.data.borrow_mut())
+    }
+}
+
+#[test]
+fn test_fee_is_escrowed_during_cpi_and_settled_after() {
+    set_syscall_stubs(Box::new(TargetStubs));
+    let program_id = Pubkey::new_unique();
+    // target program, sender, fee payer, GSN state, config
+    let keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[4];
+    gsn.add_consumer(keys[1], 200_000);
+    let mut data = [vec![], vec![], vec![], vec![0u8; 1024], vec![0u8; 1024]];
+    gsn.serialize(&mut data[3]).unwrap();
+    GsnConfig::new(keys[3]).serialize(&mut data[4]).unwrap();
+    let mut lamports = [1, 1_000_000, 1_000_000, 1_000_000, 1_000_000];
+
+    let mut accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = index == 1 || index == 2;
+            AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+        })
+        .collect();
+    // The relayed instruction reads the GSN state
+    accounts.push(accounts[3].clone());
+    let instruction = Instruction {
+        program_id: keys[0],
+        accounts: vec![
+            AccountMeta::new(keys[1], true),
+            AccountMeta::new_readonly(keys[3], false),
+        ],
+        data: vec![],
+    };
+    let args = SubmitInstructionArgs {
+        nonce: 0,
+        lane: 0,
+        instruction,
+    };
+    Processor::process_submit_instruction(&program_id, args, &accounts).unwrap();
+
+    // The fee had already left the balance for the escrow
+    assert_eq!(*SEEN.lock().unwrap(), Some((150_000, 50_000, true)));
+    let gsn = GsnInfo::deserialize(&accounts[3].data.borrow()).unwrap();
+    assert!(gsn.escrowed_fees.is_empty());
+    assert!(!gsn.in_flight);
+    assert_eq!(gsn.executor.get(&keys[2]), Some(&50_000));
+    // What the target program wrote wasn't overwritten with what was read
+    // before the CPI
+    assert_eq!(gsn.consumer.get(&keys[1]), Some(&151_000));
+    assert_eq!(gsn.get_next_lane_nonce(&keys[1], 0), 1);
+}
@@ -0,0 +1,177 @@
+// Tests for meta-transactions signed by Ethereum wallets
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    eth::{
+        parse_secp256k1_instruction, personal_message, registration_message, ETH_REGISTER_DOMAIN,
+    },
+    instruction::{GsnInstruction, RegisterEthAddressArgs, SubmitLaneArgs},
+    state::GsnInfo,
+    Processor,
+};
+
+/// Secp256k1 program instruction data as `Secp256k1Program.createInstructionWithEthAddress`
+/// lays it out: offsets, Ethereum address, signature and recovery id, then the message
+fn secp256k1_data(eth_address: &[u8; 20], message: &[u8], instruction_index: u8) -> Vec<u8> {
+    let (eth_address_offset, signature_offset, message_offset) = (12u16, 32u16, 97u16);
+    let mut data = vec![1];
+    data.extend_from_slice(&signature_offset.to_le_bytes());
+    data.push(instruction_index);
+    data.extend_from_slice(&eth_address_offset.to_le_bytes());
+    data.push(instruction_index);
+    data.extend_from_slice(&message_offset.to_le_bytes());
+    data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+    data.push(instruction_index);
+    data.extend_from_slice(eth_address);
+    data.extend_from_slice(&[9; 65]);
+    data.extend_from_slice(message);
+    data
+}
+
+#[test]
+fn test_personal_message_prefix() {
+    assert_eq!(
+        personal_message(b"hello"),
+        b"\x19Ethereum Signed Message:\n5hello".to_vec()
+    );
+}
+
+#[test]
+fn test_registration_message_layout() {
+    let gsn_account = Pubkey::new_unique();
+    let consumer = Pubkey::new_unique();
+    let message = registration_message(&gsn_account, &consumer, 3);
+    assert!(message.starts_with(ETH_REGISTER_DOMAIN));
+    let rest = &message[ETH_REGISTER_DOMAIN.len()..];
+    assert_eq!(&rest[..32], gsn_account.as_ref());
+    assert_eq!(&rest[32..64], consumer.as_ref());
+    assert_eq!(&rest[64..], &3u64.to_le_bytes());
+    assert_ne!(registration_message(&gsn_account, &consumer, 4), message);
+}
+
+#[test]
+fn test_register_eth_address_unpacks_its_nonce() {
+    let mut data = vec![27];
+    data.extend_from_slice(&5u64.to_le_bytes());
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::RegisterEthAddress(RegisterEthAddressArgs { nonce: 5 })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&[27]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_parse_self_contained_signature() {
+    let eth_address = [7u8; 20];
+    let message = personal_message(b"intent");
+    let data = secp256k1_data(&eth_address, &message, 2);
+    assert_eq!(
+        parse_secp256k1_instruction(&data, 2),
+        vec![(eth_address, message.as_slice())]
+    );
+}
+
+#[test]
+fn test_parse_rejects_foreign_or_malformed_data() {
+    let eth_address = [7u8; 20];
+    let message = personal_message(b"intent");
+
+    // Offsets into another instruction of the transaction
+    let data = secp256k1_data(&eth_address, &message, 0);
+    assert!(parse_secp256k1_instruction(&data, 1).is_empty());
+    // Truncated message
+    let data = secp256k1_data(&eth_address, &message, 1);
+    assert!(parse_secp256k1_instruction(&data[..data.len() - 1], 1).is_empty());
+    // More signatures than offsets
+    let mut two = data.clone();
+    two[0] = 2;
+    assert_eq!(parse_secp256k1_instruction(&two, 1).len(), 1);
+    assert!(parse_secp256k1_instruction(&[], 1).is_empty());
+}
+
+#[test]
+fn test_eth_registry_round_trips() {
+    let mut gsn = GsnInfo::new();
+    let consumer = Pubkey::new_unique();
+    gsn.add_consumer(consumer, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the registry existed decode it as empty (its
+    // empty section, the spending limits' and the paused flag follow the
+    // token sections)
+    let legacy_len = gsn.serialized_size() - 221;
+    assert!(GsnInfo::deserialize(&data[..legacy_len])
+        .unwrap()
+        .eth_consumers
+        .is_empty());
+
+    // and so do the registration nonces, the last section
+    let legacy_len = gsn.serialized_size() - 4;
+    assert!(GsnInfo::deserialize(&data[..legacy_len])
+        .unwrap()
+        .eth_registration_nonces
+        .is_empty());
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(decoded.eth_registration_nonce(&[7; 20]), 0);
+    decoded.check_eth_registration_capacity(&[7; 20]).unwrap();
+    decoded.register_eth_address([7; 20], consumer);
+    decoded.serialize(&mut data).unwrap();
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(decoded.eth_consumers.get(&[7; 20]), Some(&consumer));
+    assert_eq!(decoded.eth_registration_nonce(&[7; 20]), 1);
+    assert_eq!(decoded.consumer.get(&consumer), Some(&1_000));
+
+    // Moving the address uses up the next nonce
+    let other = Pubkey::new_unique();
+    decoded.register_eth_address([7; 20], other);
+    assert_eq!(decoded.eth_consumers.get(&[7; 20]), Some(&other));
+    assert_eq!(decoded.eth_registration_nonce(&[7; 20]), 2);
+}
+
+#[test]
+fn test_eth_instructions_check_signers_first() {
+    let program_id = Pubkey::new_unique();
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let mut lamports = [0u64; 6];
+    let mut data = vec![vec![]; 6];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| {
+            AccountInfo::new(key, false, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+
+    assert_eq!(
+        Processor::process_register_eth_address(
+            &program_id,
+            RegisterEthAddressArgs { nonce: 0 },
+            &accounts
+        ),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    let args = |lane| SubmitLaneArgs {
+        amount: 1,
+        nonce: 0,
+        lane,
+        expiry_slot: None,
+        max_fee: None,
+        tip: 0,
+    };
+    assert_eq!(
+        Processor::process_submit_eth_intent(&program_id, args(16), &accounts),
+        Err(GsnError::InvalidNonceLane.into())
+    );
+    // The relayer signs in place of the sender
+    assert_eq!(
+        Processor::process_submit_eth_intent(&program_id, args(0), &accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
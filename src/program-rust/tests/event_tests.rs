@@ -0,0 +1,112 @@
+// Tests for the binary events emitted with `sol_log_data`
+
+use solana_program::{
+    account_info::AccountInfo,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    system_program,
+};
+use solgsn::{
+    event::{
+        Event, FeeClaimEvent, GovernanceAction, GovernanceEvent, TopupEvent, EVENT_PREFIX,
+    },
+    state::{FeeMode, GsnConfig, GsnInfo},
+    Processor,
+};
+use std::sync::Mutex;
+
+/// Data logged with `sol_log_data`
+static LOGGED: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+struct CaptureStubs;
+
+impl SyscallStubs for CaptureStubs {
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        LOGGED.lock().unwrap().push(data.concat());
+    }
+}
+
+#[test]
+fn test_events_round_trip() {
+    let events: Vec<Event> = vec![
+        FeeClaimEvent {
+            executor: Pubkey::new_unique(),
+            destination: Pubkey::new_unique(),
+            mint: Some(Pubkey::new_unique()),
+            amount: 42,
+        }
+        .into(),
+        GovernanceEvent {
+            config: Pubkey::new_unique(),
+            authority: None,
+            action: GovernanceAction::FeeParamsProposed {
+                fee_mode: FeeMode::percent(50),
+                activation_slot: 1_000,
+            },
+        }
+        .into(),
+    ];
+    for event in events {
+        let data = event.encode();
+        assert_eq!(data[..8], EVENT_PREFIX);
+        assert_eq!(Event::decode(&data), Some(event));
+
+        // Other programs' data and truncated or padded events aren't events
+        assert_eq!(Event::decode(&data[8..]), None);
+        assert_eq!(Event::decode(&data[..data.len() - 1]), None);
+        let mut padded = data.clone();
+        padded.push(0);
+        assert_eq!(Event::decode(&padded), None);
+    }
+}
+
+#[test]
+fn test_topup_emits_event() {
+    set_syscall_stubs(Box::new(CaptureStubs));
+    let program_id = Pubkey::new_unique();
+    let (gsn_key, config_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+    gsn.serialize(&mut gsn_data).unwrap();
+    GsnConfig::new(gsn_key).serialize(&mut config_data).unwrap();
+
+    // `TopUp` accounts: GSN state, consumer, config, vault, system program
+    let (vault_key, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let keys = [
+        gsn_key,
+        Pubkey::new_unique(),
+        config_key,
+        vault_key,
+        system_program::id(),
+    ];
+    let mut lamports = [1, 10_000_000, 1, 0, 1];
+    let mut data = [gsn_data, vec![], config_data, vec![], vec![]];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == 1, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    Processor::process_topup(&program_id, 1_000, None, &accounts).unwrap();
+
+    let events: Vec<Event> = LOGGED
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|data| Event::decode(data))
+        .collect();
+    assert_eq!(
+        events,
+        vec![Event::Topup(TopupEvent {
+            consumer: keys[1],
+            mint: None,
+            amount: 1_000,
+            previous_balance: 0,
+            new_balance: 1_000,
+        })]
+    );
+}
@@ -0,0 +1,206 @@
+// Tests for the executor registry
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{ExecutorArgs, GsnInstruction, SetExecutorRegistryArgs},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_executor_registry_instructions_decode() {
+    let mut data = vec![38];
+    data.extend_from_slice(&[9; 32]);
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::RegisterExecutor(ExecutorArgs { executor: [9; 32] })
+    );
+    data[0] = 39;
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::DeregisterExecutor(ExecutorArgs { executor: [9; 32] })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&[40, 1]).unwrap(),
+        GsnInstruction::SetExecutorRegistry(SetExecutorRegistryArgs { required: 1 })
+    );
+}
+
+#[test]
+fn test_registered_executors_round_trip() {
+    let executor = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(Pubkey::new_unique(), 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the registry existed have none registered
+    let legacy_len = gsn.serialized_size() - 212;
+    assert!(GsnInfo::deserialize(&data[..legacy_len])
+        .unwrap()
+        .registered_executors
+        .is_empty());
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.register_executor(executor);
+    decoded.serialize(&mut data).unwrap();
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    assert!(decoded.is_executor_registered(&executor));
+    assert_eq!(decoded.consumer, gsn.consumer);
+
+    decoded.deregister_executor(&executor);
+    decoded.serialize(&mut data).unwrap();
+    assert!(!GsnInfo::deserialize(&data)
+        .unwrap()
+        .is_executor_registered(&executor));
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    /// With the registry required, and the fee payer registered or not
+    fn new(register_fee_payer: bool) -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 200_000);
+        if register_fee_payer {
+            gsn.register_executor(keys[3]);
+        }
+        let mut config = GsnConfig::new(keys[4]);
+        config.registered_executors_only = true;
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, 0, 0, None, None, 0, None, &accounts)
+    }
+}
+
+#[test]
+fn test_only_registered_executors_execute_when_required() {
+    let mut setup = Setup::new(false);
+    assert_eq!(setup.submit(), Err(GsnError::ExecutorNotRegistered.into()));
+
+    let mut setup = Setup::new(true);
+    setup.submit().unwrap();
+    let gsn = GsnInfo::deserialize(&setup.data[4]).unwrap();
+    assert_eq!(gsn.get_next_nonce(&setup.keys[1]), 1);
+}
+
+fn register(
+    gsn_data: &mut [u8],
+    config_key: &Pubkey,
+    config_data: &mut [u8],
+    signer: &Pubkey,
+    executor: &Pubkey,
+) -> ProgramResult {
+    let (gsn_key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut gsn_lamports, mut config_lamports, mut signer_lamports) = (1, 1, 1);
+    let mut signer_data = vec![];
+    let accounts = vec![
+        AccountInfo::new(
+            &gsn_key,
+            false,
+            true,
+            &mut gsn_lamports,
+            gsn_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            config_key,
+            false,
+            false,
+            &mut config_lamports,
+            config_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            signer,
+            true,
+            false,
+            &mut signer_lamports,
+            &mut signer_data,
+            &owner,
+            false,
+            0,
+        ),
+    ];
+    let args = ExecutorArgs {
+        executor: executor.to_bytes(),
+    };
+    Processor::process_set_executor_registered(&owner, args, true, &accounts)
+}
+
+#[test]
+fn test_only_the_authority_registers_executors() {
+    let (config_key, authority, executor) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+    gsn.serialize(&mut gsn_data).unwrap();
+    config.serialize(&mut config_data).unwrap();
+
+    assert_eq!(
+        register(
+            &mut gsn_data,
+            &config_key,
+            &mut config_data,
+            &executor,
+            &executor
+        ),
+        Err(GsnError::Unauthorized.into())
+    );
+    register(
+        &mut gsn_data,
+        &config_key,
+        &mut config_data,
+        &authority,
+        &executor,
+    )
+    .unwrap();
+    assert!(GsnInfo::deserialize(&gsn_data)
+        .unwrap()
+        .is_executor_registered(&executor));
+}
@@ -0,0 +1,63 @@
+// Tests for the optional expiry slot of SubmitTransaction
+
+use solana_program::program_error::ProgramError;
+use solgsn::instruction::{GsnInstruction, SubmitArgs};
+
+fn encode(amount: u64, nonce: u64, expiry_slot: Option<u64>) -> Vec<u8> {
+    let mut data = vec![2];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&nonce.to_le_bytes());
+    if let Some(expiry_slot) = expiry_slot {
+        data.extend_from_slice(&expiry_slot.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_submit_without_expiry_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&encode(1_000, 3, None)).unwrap(),
+        GsnInstruction::SubmitTransaction(SubmitArgs {
+            amount: 1_000,
+            nonce: 3,
+            expiry_slot: None,
+            max_fee: None,
+            tip: 0,
+            simulate: false,
+            quote: None,
+        })
+    );
+}
+
+#[test]
+fn test_submit_with_expiry_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&encode(1_000, 3, Some(250))).unwrap(),
+        GsnInstruction::SubmitTransaction(SubmitArgs {
+            amount: 1_000,
+            nonce: 3,
+            expiry_slot: Some(250),
+            max_fee: None,
+            tip: 0,
+            simulate: false,
+            quote: None,
+        })
+    );
+}
+
+#[test]
+fn test_submit_rejects_partial_expiry() {
+    let data = encode(1_000, 3, Some(250));
+    for len in [data.len() - 1, data.len() - 7, 16] {
+        assert_eq!(
+            SubmitArgs::unpack(&data[..len]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+    let mut trailing = data.clone();
+    trailing.push(0);
+    assert_eq!(
+        SubmitArgs::unpack(&trailing),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
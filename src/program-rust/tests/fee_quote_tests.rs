@@ -0,0 +1,276 @@
+// Tests for signed executor fee quotes
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    ed25519_program,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    system_program,
+    sysvar::{
+        self,
+        instructions::{construct_instructions_data, BorrowedInstruction},
+    },
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, QuoteTerms, SubmitArgs, SubmitQuotedArgs},
+    quote::{parse_ed25519_instruction, FeeQuote, QUOTE_DOMAIN},
+    state::{FeeMode, GsnConfig, GsnInfo},
+    Processor,
+};
+
+/// Slot of the stubbed clock
+const SLOT: u64 = 500;
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        _instruction: &Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        Ok(())
+    }
+}
+
+fn quote() -> FeeQuote {
+    FeeQuote {
+        gsn_account: Pubkey::new_unique(),
+        executor: Pubkey::new_unique(),
+        fee: 12_345,
+        expiry_slot: 1_000,
+        payload_hash: [3; 32],
+    }
+}
+
+/// Ed25519 program instruction data as `Ed25519Program.createInstructionWithPublicKey`
+/// lays it out: offsets, public key, signature, then the message
+fn ed25519_data(public_key: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+    let (public_key_offset, signature_offset, message_offset) = (16u16, 48u16, 112u16);
+    let mut data = vec![1, 0];
+    for field in [
+        signature_offset,
+        instruction_index,
+        public_key_offset,
+        instruction_index,
+        message_offset,
+        message.len() as u16,
+        instruction_index,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(public_key.as_ref());
+    data.extend_from_slice(&[9; 64]);
+    data.extend_from_slice(message);
+    data
+}
+
+#[test]
+fn test_quote_message_layout() {
+    let quote = quote();
+    let message = quote.message();
+    assert_eq!(message.len(), QUOTE_DOMAIN.len() + 32 + 32 + 8 + 8);
+    assert!(message.starts_with(QUOTE_DOMAIN));
+    let rest = &message[QUOTE_DOMAIN.len()..];
+    assert_eq!(&rest[..32], quote.gsn_account.as_ref());
+    assert_eq!(&rest[32..64], &[3; 32]);
+    assert_eq!(&rest[64..72], &12_345u64.to_le_bytes());
+    assert_eq!(&rest[72..], &1_000u64.to_le_bytes());
+}
+
+#[test]
+fn test_parse_self_contained_signature() {
+    let quote = quote();
+    let message = quote.message();
+    let data = ed25519_data(&quote.executor, &message, u16::MAX);
+    assert_eq!(
+        parse_ed25519_instruction(&data),
+        Some((quote.executor, message.as_slice()))
+    );
+}
+
+#[test]
+fn test_parse_rejects_foreign_or_malformed_data() {
+    let quote = quote();
+    let message = quote.message();
+
+    // Offsets into another instruction of the transaction
+    assert_eq!(
+        parse_ed25519_instruction(&ed25519_data(&quote.executor, &message, 0)),
+        None
+    );
+    // Truncated message
+    let data = ed25519_data(&quote.executor, &message, u16::MAX);
+    assert_eq!(parse_ed25519_instruction(&data[..data.len() - 1]), None);
+    // More than one signature
+    let mut two = data.clone();
+    two[0] = 2;
+    assert_eq!(parse_ed25519_instruction(&two), None);
+    assert_eq!(parse_ed25519_instruction(&[]), None);
+}
+
+#[test]
+fn test_quoted_lane_is_validated_first() {
+    let args = SubmitQuotedArgs {
+        amount: 1,
+        nonce: 0,
+        lane: 16,
+        fee: 5_000,
+        expiry_slot: 100,
+    };
+    assert_eq!(
+        Processor::process_submit_quoted(&Pubkey::new_unique(), args, &[]),
+        Err(GsnError::InvalidNonceLane.into())
+    );
+}
+
+fn submit_args(quote: Option<QuoteTerms>) -> SubmitArgs {
+    SubmitArgs {
+        amount: 1_000,
+        nonce: 0,
+        expiry_slot: None,
+        max_fee: Some(30_000),
+        tip: 0,
+        simulate: false,
+        quote,
+    }
+}
+
+#[test]
+fn test_submit_transaction_quote_decode() {
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let terms = QuoteTerms {
+        fee: 20_000,
+        expiry_slot: 600,
+    };
+    let submit = |args: &SubmitArgs| {
+        instruction::submit_transaction(&keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5], args)
+    };
+    let quoted = submit(&submit_args(Some(terms)));
+    assert_eq!(quoted.data.len(), 58);
+    assert_eq!(
+        GsnInstruction::deserialize(&quoted.data).unwrap(),
+        GsnInstruction::SubmitTransaction(submit_args(Some(terms)))
+    );
+    // Unquoted submissions keep their layout
+    assert_eq!(submit(&submit_args(None)).data, quoted.data[..41]);
+
+    // Sponsored submissions are charged the configured fee only
+    let mut sponsored = quoted.data.clone();
+    sponsored[0] = 48;
+    assert_eq!(
+        GsnInstruction::deserialize(&sponsored),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_submit_transaction_charges_the_verified_quote() {
+    set_syscall_stubs(Box::new(Stubs));
+    let program_id = Pubkey::new_unique();
+    // target program, sender, receiver, fee payer, GSN state, config, Instructions sysvar
+    let mut keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    keys[0] = system_program::id();
+    keys.push(sysvar::instructions::id());
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[5];
+    gsn.add_consumer(keys[1], 100_000);
+    let mut config = GsnConfig::new(keys[4]);
+    config.initialize_governance(Pubkey::new_unique());
+    config.update_fee_params(FeeMode::Fixed(50_000));
+    let mut state = vec![0u8; 1024];
+    gsn.serialize(&mut state).unwrap();
+    let mut config_data = vec![0u8; 1024];
+    config.serialize(&mut config_data).unwrap();
+
+    let quote = |fee: u64, expiry_slot: u64| FeeQuote {
+        gsn_account: keys[4],
+        executor: keys[3],
+        fee,
+        expiry_slot,
+        payload_hash: FeeQuote::payload_hash(&keys[0], &keys[1], &keys[2], 1_000, 0, 0),
+    };
+    // Instructions of a transaction verifying the executor's signature over
+    // `signed`, then submitting
+    let sysvar_data = |signed: &FeeQuote| {
+        let verify = ed25519_data(&signed.executor, &signed.message(), u16::MAX);
+        let mut data = construct_instructions_data(&[
+            BorrowedInstruction {
+                program_id: &ed25519_program::id(),
+                accounts: vec![],
+                data: &verify,
+            },
+            BorrowedInstruction {
+                program_id: &program_id,
+                accounts: vec![],
+                data: &[2],
+            },
+        ]);
+        let len = data.len();
+        data[len - 2..].copy_from_slice(&1u16.to_le_bytes());
+        data
+    };
+    let submit = |terms: QuoteTerms, signed: &FeeQuote| {
+        let mut data = [
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            state.clone(),
+            config_data.clone(),
+            sysvar_data(signed),
+        ];
+        let mut lamports = [1, 1_000_000, 0, 1_000_000, 1, 1, 1];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        let result = Processor::process_submit_tx(&program_id, 1_000, 0, 0, None, Some(30_000), 0, Some(terms), &accounts);
+        drop(accounts);
+        result.map(|_| GsnInfo::deserialize(&data[4]).unwrap())
+    };
+    let terms = |fee, expiry_slot| QuoteTerms { fee, expiry_slot };
+
+    // The quote is charged instead of the configured fee, which is above
+    // the consumer's maximum
+    let gsn = submit(terms(20_000, 600), &quote(20_000, 600)).unwrap();
+    assert_eq!(gsn.consumer.get(&keys[1]), Some(&(100_000 - 20_000)));
+    assert_eq!(gsn.executor.get(&keys[3]), Some(&20_000));
+
+    // A quote nobody signed, or signed for another fee, isn't charged
+    assert_eq!(
+        submit(terms(10_000, 600), &quote(20_000, 600)).err(),
+        Some(GsnError::InvalidFeeQuote.into())
+    );
+    let mut foreign = quote(10_000, 600);
+    foreign.executor = Pubkey::new_unique();
+    assert_eq!(
+        submit(terms(10_000, 600), &foreign).err(),
+        Some(GsnError::InvalidFeeQuote.into())
+    );
+    assert_eq!(
+        submit(terms(20_000, SLOT - 1), &quote(20_000, SLOT - 1)).err(),
+        Some(GsnError::FeeQuoteExpired.into())
+    );
+}
@@ -0,0 +1,101 @@
+// Tests for fees split between two executors
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{SubmitSplitArgs, FEE_SPLIT_DENOMINATOR},
+    state::{GsnConfig, GsnInfo, ProtocolMetrics},
+    Processor,
+};
+
+fn split(fee_payer_bps: u64) -> SubmitSplitArgs {
+    SubmitSplitArgs {
+        amount: 1_000,
+        nonce: 0,
+        lane: 0,
+        fee_payer_bps,
+    }
+}
+
+#[test]
+fn test_fee_shares_add_up_to_fee() {
+    assert_eq!(split(7_000).fee_shares(50_000), (35_000, 15_000));
+    // The fee payer's share rounds down
+    assert_eq!(split(3_333).fee_shares(10), (3, 7));
+    assert_eq!(split(0).fee_shares(50_000), (0, 50_000));
+    assert_eq!(
+        split(FEE_SPLIT_DENOMINATOR).fee_shares(u64::MAX),
+        (u64::MAX, 0)
+    );
+}
+
+#[test]
+fn test_split_ratio_is_validated_first() {
+    assert_eq!(
+        Processor::process_submit_split(&Pubkey::new_unique(), split(FEE_SPLIT_DENOMINATOR + 1), &[]),
+        Err(GsnError::InvalidFeeSplit.into())
+    );
+}
+
+/// Target, sender, receiver, fee payer, GSN state, config and co-executor
+/// accounts of a split submission, signed by the sender, fee payer and,
+/// if `co_executor_signs`, the co-executor
+fn split_accounts<'a>(
+    keys: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    co_executor_signs: bool,
+    owner: &'a Pubkey,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = index == 1 || index == 3 || (index == 6 && co_executor_signs);
+            AccountInfo::new(key, is_signer, true, lamports, data, owner, index == 0, 0)
+        })
+        .collect()
+}
+
+#[test]
+fn test_co_executor_must_sign_and_differ_from_fee_payer() {
+    let program_id = Pubkey::new_unique();
+    let mut keys: Vec<Pubkey> = (0..7).map(|_| Pubkey::new_unique()).collect();
+
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[5];
+    let mut data = vec![vec![]; 7];
+    data[4] = vec![0u8; 1024];
+    gsn.serialize(&mut data[4]).unwrap();
+    data[5] = vec![0u8; 1024];
+    GsnConfig::new(keys[4]).serialize(&mut data[5]).unwrap();
+    let mut lamports = vec![1_000_000; 7];
+
+    let accounts = split_accounts(&keys, &mut lamports, &mut data, false, &program_id);
+    assert_eq!(
+        Processor::process_submit_split(&program_id, split(5_000), &accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    drop(accounts);
+
+    // The fee payer can't take both shares
+    keys[6] = keys[3];
+    let accounts = split_accounts(&keys, &mut lamports, &mut data, true, &program_id);
+    assert_eq!(
+        Processor::process_submit_split(&program_id, split(5_000), &accounts),
+        Err(GsnError::InvalidFeeSplit.into())
+    );
+}
+
+#[test]
+fn test_metrics_follow_the_split() {
+    let (executor, co_executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut metrics = ProtocolMetrics::new(Pubkey::new_unique(), 1);
+    metrics.record_execution(1, &Pubkey::new_unique(), &executor, &Pubkey::default(), 100);
+    metrics.transfer_executor_fee(&executor, &co_executor, 40);
+
+    assert_eq!(metrics.current.total_fees, 100);
+    assert_eq!(metrics.current.executor_fees.get(&executor), Some(&60));
+    assert_eq!(metrics.current.executor_fees.get(&co_executor), Some(&40));
+}
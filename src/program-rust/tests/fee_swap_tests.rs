@@ -0,0 +1,171 @@
+// Tests for token fees claimed as SOL through a Jupiter route
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{ClaimTokenFeesAsSolArgs, GsnInstruction},
+    swap::JUPITER_PROGRAM_ID,
+    token::{unpack_token_account, NATIVE_MINT, TOKEN_PROGRAM_ID},
+    GsnInfo, Processor,
+};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Once,
+};
+
+/// Lamports the stubbed route delivers
+static SWAP_OUTPUT: AtomicU64 = AtomicU64::new(0);
+
+/// Token program instructions do nothing, a Jupiter route credits
+/// `SWAP_OUTPUT` to the wrapped SOL accounts it is passed
+struct SwapStubs;
+
+impl SyscallStubs for SwapStubs {
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if instruction.program_id != JUPITER_PROGRAM_ID {
+            return Ok(());
+        }
+        for account in account_infos {
+            let wsol = unpack_token_account(account.owner, &account.data.borrow())
+                .filter(|token| token.mint == NATIVE_MINT);
+            if let Some(wsol) = wsol {
+                let amount = wsol.amount + SWAP_OUTPUT.load(Ordering::SeqCst);
+                account.data.borrow_mut()[64..72].copy_from_slice(&amount.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+static STUBS: Once = Once::new();
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    data[..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data[108] = 1;
+    data
+}
+
+#[test]
+fn test_claim_token_fees_as_sol_decode() {
+    let mut data = vec![71];
+    data.extend_from_slice(&5_000u64.to_le_bytes());
+    data.extend_from_slice(&[1, 2, 3]);
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::ClaimTokenFeesAsSol(ClaimTokenFeesAsSolArgs {
+            min_lamports: 5_000,
+            route: vec![1, 2, 3],
+        })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&data[..8]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_claim_token_fees_as_sol_enforces_minimum_output() {
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(SwapStubs));
+    });
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let executor = Pubkey::new_unique();
+    // GSN state, executor, its token and wrapped SOL accounts, vault token
+    // account, vault, token program, destination, Jupiter
+    let keys = [
+        gsn_key,
+        executor,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        vault,
+        TOKEN_PROGRAM_ID,
+        Pubkey::new_unique(),
+        executor,
+        JUPITER_PROGRAM_ID,
+    ];
+    let owners = [
+        program_id,
+        Pubkey::default(),
+        TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        Pubkey::default(),
+        Pubkey::default(),
+        TOKEN_PROGRAM_ID,
+        Pubkey::default(),
+        Pubkey::default(),
+    ];
+    let mut gsn = GsnInfo::new();
+    gsn.token_executor.insert((executor, mint), 10_000);
+    let mut data = [
+        vec![0u8; 1024],
+        vec![],
+        token_account(&mint, &executor, 0),
+        token_account(&mint, &vault, 10_000),
+        vec![],
+        vec![],
+        token_account(&NATIVE_MINT, &executor, 0),
+        vec![],
+        vec![],
+    ];
+    gsn.serialize(&mut data[0]).unwrap();
+    let mut lamports = [1_000_000, 1_000_000, 1, 1, 0, 1, 1, 1_000_000, 1];
+
+    let mut claim = |keys: &[Pubkey], output: u64, min_lamports: u64| {
+        SWAP_OUTPUT.store(output, Ordering::SeqCst);
+        let mut accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(owners.iter())
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, (((key, owner), lamports), data))| {
+                AccountInfo::new(key, index == 1, true, lamports, data, owner, false, 0)
+            })
+            .collect();
+        // The route swaps out of the executor's token account into its
+        // wrapped SOL account
+        accounts.extend_from_slice(&[accounts[1].clone(), accounts[2].clone(), accounts[6].clone()]);
+        let args = ClaimTokenFeesAsSolArgs {
+            min_lamports,
+            route: vec![0xe5, 0x17],
+        };
+        Processor::process_claim_token_fees_as_sol(&program_id, args, &accounts)
+    };
+
+    // Only through Jupiter
+    let mut other_keys = keys;
+    other_keys[8] = Pubkey::new_unique();
+    assert_eq!(claim(&other_keys, 20_000, 0), Err(ProgramError::IncorrectProgramId));
+    // Only to the executor or its payout address
+    other_keys = keys;
+    other_keys[7] = Pubkey::new_unique();
+    assert_eq!(claim(&other_keys, 20_000, 0), Err(GsnError::UnauthorizedFeeClaim.into()));
+
+    assert_eq!(
+        claim(&keys, 19_999, 20_000),
+        Err(GsnError::SwapSlippageExceeded.into())
+    );
+    claim(&keys, 20_000, 20_000).unwrap();
+    assert_eq!(claim(&keys, 20_000, 0), Err(ProgramError::InsufficientFunds));
+
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.token_executor.get(&(executor, mint)), None);
+}
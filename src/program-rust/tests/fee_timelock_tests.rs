@@ -0,0 +1,142 @@
+// Tests for the fee parameter timelock
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, UpdateFeeParamsArgs},
+    state::{FeeMode, GsnConfig, FEE_PARAMS_TIMELOCK_SLOTS},
+    Processor,
+};
+
+#[test]
+fn test_apply_fee_params_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[37]).unwrap(),
+        GsnInstruction::ApplyFeeParams
+    );
+}
+
+#[test]
+fn test_only_same_mode_reductions_skip_the_timelock() {
+    assert!(FeeMode::Fixed(10).is_reduction_of(&FeeMode::Fixed(10)));
+    assert!(FeeMode::Fixed(5).is_reduction_of(&FeeMode::Fixed(10)));
+    assert!(!FeeMode::Fixed(11).is_reduction_of(&FeeMode::Fixed(10)));
+    assert!(FeeMode::percent(50).is_reduction_of(&FeeMode::percent(100)));
+    assert!(!FeeMode::percent(101).is_reduction_of(&FeeMode::percent(100)));
+    assert!(!FeeMode::Fixed(0).is_reduction_of(&FeeMode::percent(100)));
+    assert!(!FeeMode::percent(0).is_reduction_of(&FeeMode::Fixed(10)));
+}
+
+#[test]
+fn test_proposed_fee_params_wait_for_the_timelock() {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(Pubkey::new_unique());
+    assert_eq!(
+        config.apply_fee_params(0),
+        Err(GsnError::NoPendingFeeParams)
+    );
+
+    let activation_slot = config.propose_fee_params(FeeMode::Fixed(90_000), 100);
+    assert_eq!(activation_slot, 100 + FEE_PARAMS_TIMELOCK_SLOTS);
+    assert_eq!(
+        config.apply_fee_params(activation_slot - 1),
+        Err(GsnError::TimelockNotElapsed)
+    );
+    assert_eq!(config.calculate_fee(0), 50_000);
+
+    assert_eq!(
+        config.apply_fee_params(activation_slot),
+        Ok(FeeMode::Fixed(90_000))
+    );
+    assert_eq!(config.calculate_fee(0), 90_000);
+    assert_eq!(config.pending_fee_params, None);
+}
+
+#[test]
+fn test_a_new_proposal_restarts_the_timelock() {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(Pubkey::new_unique());
+    config.propose_fee_params(FeeMode::Fixed(90_000), 100);
+    let activation_slot = config.propose_fee_params(FeeMode::percent(200), 200);
+
+    assert_eq!(
+        config.apply_fee_params(100 + FEE_PARAMS_TIMELOCK_SLOTS),
+        Err(GsnError::TimelockNotElapsed)
+    );
+    assert_eq!(
+        config.apply_fee_params(activation_slot),
+        Ok(FeeMode::percent(200))
+    );
+}
+
+fn update(
+    config_data: &mut [u8],
+    authority: &Pubkey,
+    fee_mode_type: u8,
+    fee_value: u64,
+) -> ProgramResult {
+    let config_key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let (mut config_lamports, mut authority_lamports) = (1, 1);
+    let mut authority_data = vec![];
+    let accounts = vec![
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            config_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            authority,
+            true,
+            false,
+            &mut authority_lamports,
+            &mut authority_data,
+            &owner,
+            false,
+            0,
+        ),
+    ];
+    let args = UpdateFeeParamsArgs {
+        fee_mode_type,
+        fee_value,
+        extra: [0; 2],
+    };
+    Processor::process_update_fee_params(&owner, args, &accounts)
+}
+
+#[test]
+fn test_update_fee_params_only_lowers_fees() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+
+    update(&mut data, &authority, 0, 40_000).unwrap();
+    assert_eq!(
+        GsnConfig::deserialize(&data).unwrap().calculate_fee(0),
+        40_000
+    );
+
+    assert_eq!(
+        update(&mut data, &authority, 0, 40_001),
+        Err(GsnError::FeeChangeTimelocked.into())
+    );
+    assert_eq!(
+        update(&mut data, &authority, 1, 1),
+        Err(GsnError::FeeChangeTimelocked.into())
+    );
+    assert_eq!(
+        update(&mut data, &authority, 1, 10_001),
+        Err(GsnError::InvalidFeeMode.into())
+    );
+    assert_eq!(
+        GsnConfig::deserialize(&data).unwrap().calculate_fee(0),
+        40_000
+    );
+}
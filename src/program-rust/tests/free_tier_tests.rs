@@ -0,0 +1,178 @@
+// Tests for the free tier of new consumers
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::SetFreeTierArgs,
+    state::{FreeTier, GsnConfig, GsnInfo},
+    Processor,
+};
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config, with a free tier of two submissions
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+    subsidy: Pubkey,
+}
+
+impl Setup {
+    fn new(subsidy_balance: u64, sender_balance: Option<u64>) -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let subsidy = Pubkey::new_unique();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(subsidy, subsidy_balance);
+        if let Some(balance) = sender_balance {
+            gsn.add_consumer(keys[1], balance);
+        }
+        let mut config = GsnConfig::new(keys[4]);
+        config.initialize_governance(Pubkey::new_unique());
+        config.free_tier = Some(FreeTier {
+            free_tx_quota: 2,
+            subsidy,
+        });
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+            subsidy,
+        }
+    }
+
+    fn submit(&mut self, nonce: u64) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, nonce, 0, None, None, 0, None, &accounts)
+    }
+
+    fn gsn(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.data[4]).unwrap()
+    }
+}
+
+#[test]
+fn test_free_tier_round_trips() {
+    let consumer = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the free tier existed count nothing
+    let legacy_len = gsn.serialized_size() - 192;
+    let mut decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.free_txs_used(&consumer), 0);
+
+    decoded.record_free_tx(&consumer);
+    decoded.serialize(&mut data).unwrap();
+    assert_eq!(
+        GsnInfo::deserialize(&data)
+            .unwrap()
+            .free_txs_used(&consumer),
+        1
+    );
+}
+
+#[test]
+fn test_new_consumer_submits_for_free_until_the_quota() {
+    let mut setup = Setup::new(200_000, None);
+    setup.submit(0).unwrap();
+    setup.submit(1).unwrap();
+
+    let gsn = setup.gsn();
+    assert_eq!(gsn.consumer.get(&setup.subsidy), Some(&100_000));
+    assert_eq!(gsn.free_txs_used(&setup.keys[1]), 2);
+
+    // Past the quota the consumer needs a balance of its own
+    assert_eq!(setup.submit(2), Err(GsnError::InsufficientBalance.into()));
+}
+
+#[test]
+fn test_consumer_pays_past_the_quota_or_when_the_subsidy_runs_dry() {
+    let mut setup = Setup::new(60_000, Some(200_000));
+    setup.submit(0).unwrap();
+    setup.submit(1).unwrap();
+
+    let gsn = setup.gsn();
+    assert_eq!(gsn.consumer.get(&setup.subsidy), Some(&10_000));
+    assert_eq!(gsn.free_txs_used(&setup.keys[1]), 1);
+    assert_eq!(gsn.get_next_nonce(&setup.keys[1]), 2);
+}
+
+fn set_free_tier(signer: &Pubkey, config_data: &mut [u8], args: SetFreeTierArgs) -> ProgramResult {
+    let (config_key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut config_lamports, mut signer_lamports) = (1, 1);
+    let mut signer_data = vec![];
+    let accounts = vec![
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            config_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            signer,
+            true,
+            false,
+            &mut signer_lamports,
+            &mut signer_data,
+            &owner,
+            false,
+            0,
+        ),
+    ];
+    Processor::process_set_free_tier(&owner, args, &accounts)
+}
+
+#[test]
+fn test_authority_sets_the_free_tier() {
+    let authority = Pubkey::new_unique();
+    let subsidy = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+    let args = |free_tx_quota| SetFreeTierArgs {
+        free_tx_quota,
+        subsidy: subsidy.to_bytes(),
+    };
+
+    assert_eq!(
+        set_free_tier(&Pubkey::new_unique(), &mut data, args(3)),
+        Err(GsnError::Unauthorized.into())
+    );
+    set_free_tier(&authority, &mut data, args(3)).unwrap();
+    assert_eq!(
+        GsnConfig::deserialize(&data).unwrap().free_tier,
+        Some(FreeTier {
+            free_tx_quota: 3,
+            subsidy
+        })
+    );
+    set_free_tier(&authority, &mut data, args(0)).unwrap();
+    assert_eq!(GsnConfig::deserialize(&data).unwrap().free_tier, None);
+}
@@ -0,0 +1,116 @@
+// Tests for top-ups paid on behalf of another consumer
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::SUCCESS,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solgsn::{
+    event::{Event, GiftTopupEvent},
+    instruction::{self, GsnInstruction, TopupAgrs},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+use std::sync::Mutex;
+
+/// Data logged with `sol_log_data`
+static LOGGED: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+struct CaptureStubs;
+
+impl SyscallStubs for CaptureStubs {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        LOGGED.lock().unwrap().push(data.concat());
+    }
+}
+
+#[test]
+fn test_topup_beneficiary_decode() {
+    let (program_id, gsn, config) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let (payer, beneficiary) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let own = instruction::topup(&program_id, &gsn, &config, &payer, 500);
+    assert_eq!(
+        GsnInstruction::deserialize(&own.data).unwrap(),
+        GsnInstruction::Topup(TopupAgrs {
+            amount: 500,
+            beneficiary: None,
+        })
+    );
+    let gift = instruction::topup_for(&program_id, &gsn, &config, &payer, &beneficiary, 500);
+    assert_eq!(
+        GsnInstruction::deserialize(&gift.data).unwrap(),
+        GsnInstruction::Topup(TopupAgrs {
+            amount: 500,
+            beneficiary: Some(beneficiary),
+        })
+    );
+    assert_eq!(gift.accounts, own.accounts);
+
+    // A truncated beneficiary
+    assert_eq!(
+        GsnInstruction::deserialize(&gift.data[..gift.data.len() - 1]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_gift_topup_credits_the_beneficiary() {
+    set_syscall_stubs(Box::new(CaptureStubs));
+    let program_id = Pubkey::new_unique();
+    let (gsn_key, config_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (payer, beneficiary) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    gsn.add_consumer(beneficiary, 200);
+    let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+    gsn.serialize(&mut gsn_data).unwrap();
+    GsnConfig::new(gsn_key).serialize(&mut config_data).unwrap();
+
+    let (vault_key, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let keys = [gsn_key, payer, config_key, vault_key, system_program::id()];
+    let mut lamports = [1, 10_000_000, 1, 0, 1];
+    let mut data = [gsn_data, vec![], config_data, vec![], vec![]];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == 1, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    Processor::process_topup(&program_id, 1_000, Some(beneficiary), &accounts).unwrap();
+    drop(accounts);
+
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.consumer.get(&beneficiary), Some(&1_200));
+    assert!(!gsn.consumer.contains_key(&payer));
+
+    let events: Vec<Event> = LOGGED
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|data| Event::decode(data))
+        .collect();
+    assert_eq!(
+        events,
+        vec![Event::GiftTopup(GiftTopupEvent {
+            payer,
+            beneficiary,
+            mint: None,
+            amount: 1_000,
+            previous_balance: 200,
+            new_balance: 1_200,
+        })]
+    );
+}
@@ -0,0 +1,73 @@
+// Tests for Initialize refusing accounts in use or not rent exempt
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::{ProgramResult, SUCCESS},
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use solgsn::{
+    error::GsnError,
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+/// Serves the default rent to `Rent::get`
+struct RentStubs;
+
+impl SyscallStubs for RentStubs {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+}
+
+/// `Initialize` of state and config accounts holding `data` and `lamports`
+fn initialize(data: &mut [Vec<u8>; 2], lamports: u64) -> ProgramResult {
+    set_syscall_stubs(Box::new(RentStubs));
+    let program_id = Pubkey::new_unique();
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut lamports = [lamports; 2];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| {
+            AccountInfo::new(key, false, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    Processor::process_initialize(&program_id, &accounts)
+}
+
+#[test]
+fn test_initialize_once() {
+    let exempt = Rent::default().minimum_balance(1024);
+    let mut data = [vec![0u8; 1024], vec![0u8; 1024]];
+    initialize(&mut data, exempt).unwrap();
+    assert!(GsnInfo::deserialize(&data[0]).unwrap().is_initialized());
+    assert!(GsnConfig::deserialize(&data[1]).is_ok());
+
+    // Neither account may be initialized again
+    assert_eq!(
+        initialize(&mut data, exempt),
+        Err(GsnError::AlreadyInUse.into())
+    );
+    data[0] = vec![0u8; 1024];
+    assert_eq!(
+        initialize(&mut data, exempt),
+        Err(GsnError::AlreadyInUse.into())
+    );
+}
+
+#[test]
+fn test_initialize_requires_rent_exemption() {
+    let mut data = [vec![0u8; 1024], vec![0u8; 1024]];
+    let exempt = Rent::default().minimum_balance(1024);
+    assert_eq!(
+        initialize(&mut data, exempt - 1),
+        Err(ProgramError::AccountNotRentExempt)
+    );
+    assert!(!GsnInfo::deserialize(&data[0]).unwrap().is_initialized());
+}
@@ -0,0 +1,170 @@
+// Tests for the instruction builders of Rust clients
+
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey, system_program};
+use solgsn::{
+    instruction::{self, GsnInstruction, SubmitArgs, SubmitLaneArgs, UpdateFeeParamsArgs},
+    state::GsnInfo,
+};
+
+#[test]
+fn test_initialize_metas() {
+    let (program_id, gsn, config) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let ix = instruction::initialize(&program_id, &gsn, &config, None);
+    assert_eq!(ix.program_id, program_id);
+    assert_eq!(GsnInstruction::deserialize(&ix.data).unwrap(), GsnInstruction::Initialize);
+    assert_eq!(
+        ix.accounts,
+        vec![AccountMeta::new(gsn, false), AccountMeta::new(config, false)]
+    );
+
+    let authority = Pubkey::new_unique();
+    let ix = instruction::initialize(&program_id, &gsn, &config, Some(&authority));
+    assert_eq!(ix.accounts[2], AccountMeta::new_readonly(authority, true));
+}
+
+#[test]
+fn test_topup_and_withdraw_use_the_vault() {
+    let (program_id, gsn, config) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let consumer = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn);
+
+    let ix = instruction::topup(&program_id, &gsn, &config, &consumer, 1_000);
+    assert_eq!(ix.data[0], 1);
+    assert_eq!(ix.data[1..], 1_000u64.to_le_bytes());
+    assert_eq!(
+        ix.accounts,
+        vec![
+            AccountMeta::new(gsn, false),
+            AccountMeta::new(consumer, true),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ]
+    );
+
+    let ix = instruction::withdraw(&program_id, &gsn, &consumer, 400);
+    assert_eq!(ix.data[0], 21);
+    assert_eq!(ix.data[1..], 400u64.to_le_bytes());
+    assert_eq!(ix.accounts[2], AccountMeta::new(vault, false));
+}
+
+#[test]
+fn test_submit_transaction_round_trips() {
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let (program_id, gsn, config, sender, receiver, executor) =
+        (&keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5]);
+    for (expiry_slot, max_fee) in [(None, None), (Some(90), Some(5_000)), (None, Some(5_000))] {
+        let args = SubmitArgs {
+            amount: 1_000,
+            nonce: 3,
+            expiry_slot,
+            max_fee,
+            tip: 7,
+            simulate: false,
+            quote: None,
+        };
+        let ix = instruction::submit_transaction(
+            program_id, gsn, config, sender, receiver, executor, &args,
+        );
+        let decoded = match GsnInstruction::deserialize(&ix.data).unwrap() {
+            GsnInstruction::SubmitTransaction(decoded) => decoded,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(decoded.amount, args.amount);
+        assert_eq!(decoded.nonce, args.nonce);
+        assert_eq!(decoded.expiry_slot, args.expiry_slot);
+        // No maximum is encoded as one that doesn't limit the fee
+        assert_eq!(decoded.max_fee, Some(args.max_fee.unwrap_or(u64::MAX)));
+        assert_eq!(decoded.tip, args.tip);
+
+        let signers: Vec<Pubkey> = ix
+            .accounts
+            .iter()
+            .filter(|meta| meta.is_signer)
+            .map(|meta| meta.pubkey)
+            .collect();
+        assert_eq!(signers, vec![*sender, *executor]);
+        assert_eq!(ix.accounts[5], AccountMeta::new_readonly(*config, false));
+    }
+}
+
+#[test]
+fn test_submit_transaction_in_lane_round_trips() {
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let args = SubmitLaneArgs {
+        amount: 1_000,
+        nonce: 3,
+        lane: 5,
+        expiry_slot: Some(90),
+        max_fee: Some(5_000),
+        tip: 7,
+    };
+    let ix = instruction::submit_transaction_in_lane(
+        &keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5], &args,
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data).unwrap(),
+        GsnInstruction::SubmitTransactionInLane(args)
+    );
+    assert_eq!(ix.accounts[1], AccountMeta::new(keys[3], true));
+    assert_eq!(ix.accounts[3], AccountMeta::new(keys[5], true));
+}
+
+#[test]
+fn test_claim_fees_metas() {
+    let (program_id, gsn, executor) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let destination = Pubkey::new_unique();
+    let ix = instruction::claim_fees(&program_id, &gsn, &executor, &destination);
+    assert_eq!(GsnInstruction::deserialize(&ix.data).unwrap(), GsnInstruction::ClaimFees);
+    assert_eq!(ix.accounts[1], AccountMeta::new_readonly(executor, true));
+    assert_eq!(ix.accounts[2], AccountMeta::new(destination, false));
+    assert_eq!(ix.accounts[3].pubkey, GsnInfo::find_vault_address(&program_id, &gsn).0);
+}
+
+#[test]
+fn test_governance_builders() {
+    let (program_id, config, authority) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let args = UpdateFeeParamsArgs {
+        fee_mode_type: 1,
+        fee_value: 250,
+        extra: [1_000, 90_000],
+    };
+    let metas = vec![
+        AccountMeta::new(config, false),
+        AccountMeta::new_readonly(authority, true),
+    ];
+
+    let ix = instruction::update_fee_params(&program_id, &config, &authority, &args);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data).unwrap(),
+        GsnInstruction::UpdateFeeParams(args.clone())
+    );
+    assert_eq!(ix.accounts, metas);
+    let ix = instruction::propose_fee_params(&program_id, &config, &authority, &args);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data).unwrap(),
+        GsnInstruction::ProposeFeeParams(args)
+    );
+
+    let mint = Pubkey::new_unique();
+    let ix = instruction::add_allowed_token(&program_id, &config, &authority, &mint);
+    assert_eq!(ix.data[0], 4);
+    assert_eq!(ix.data[1..], mint.to_bytes());
+    assert_eq!(ix.accounts, metas);
+}
@@ -0,0 +1,291 @@
+// Tests for the insurance pool and Compensate
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey, system_program,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, CompensateArgs, GsnInstruction, InsuranceFeeArgs},
+    state::{GsnConfig, GsnInfo, CHANNEL_NONCE_LANE, MAX_INSURANCE_FEE_BPS},
+    Processor,
+};
+
+#[test]
+fn test_insurance_instructions_decode() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let set_fee = instruction::set_insurance_fee(&keys[0], &keys[1], &keys[2], 200);
+    assert_eq!(
+        GsnInstruction::deserialize(&set_fee.data).unwrap(),
+        GsnInstruction::SetInsuranceFee(InsuranceFeeArgs {
+            insurance_fee_bps: 200
+        })
+    );
+    let args = CompensateArgs {
+        amount: 5_000,
+        lane: 1,
+        nonce: 42,
+        channel_executor: None,
+    };
+    let compensate = instruction::compensate(&keys[0], &keys[1], &keys[2], &keys[3], &keys[3], &args);
+    assert_eq!(
+        GsnInstruction::deserialize(&compensate.data).unwrap(),
+        GsnInstruction::Compensate(args)
+    );
+    assert_eq!(compensate.accounts.len(), 6);
+    assert_eq!(
+        GsnInstruction::deserialize(&compensate.data[..17]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // Only the channel lane names the channel's executor
+    let args = CompensateArgs {
+        amount: 5_000,
+        lane: CHANNEL_NONCE_LANE,
+        nonce: 3,
+        channel_executor: Some(keys[0]),
+    };
+    let compensate = instruction::compensate(&keys[0], &keys[1], &keys[2], &keys[3], &keys[3], &args);
+    assert_eq!(compensate.data.len(), 57);
+    assert_eq!(
+        GsnInstruction::deserialize(&compensate.data).unwrap(),
+        GsnInstruction::Compensate(args)
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&compensate.data[..25]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    let mut data = compensate.data.clone();
+    data[9..17].copy_from_slice(&1u64.to_le_bytes());
+    assert_eq!(
+        GsnInstruction::deserialize(&data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_insurance_and_treasury_shares_add_up() {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.protocol_fee_bps = 1_000;
+    config.insurance_fee_bps = 200;
+    assert_eq!(config.insurance_fee_share(50_000), 1_000);
+    assert_eq!(config.protocol_fee_shares(50_000), (5_000, 44_000));
+    assert_eq!(config.protocol_fee_shares(9), (0, 9));
+}
+
+#[test]
+fn test_insurance_pool_round_trips() {
+    let mut gsn = GsnInfo::new();
+    gsn.credit_insurance_pool(7_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the pool existed hold nothing
+    let legacy_len = gsn.serialized_size() - 20;
+    assert_eq!(GsnInfo::deserialize(&data[..legacy_len]).unwrap().insurance_pool, 0);
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(decoded.insurance_pool, 7_000);
+    assert_eq!(decoded.debit_insurance_pool(7_001), None);
+    assert_eq!(decoded.debit_insurance_pool(2_000), Some(5_000));
+}
+
+#[test]
+fn test_sweep_clears_the_pool_and_treasury_but_not_token_balances() {
+    let (consumer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, 1_000);
+    gsn.credit_insurance_pool(7_000);
+    gsn.credit_treasury(3_000);
+    gsn.token_consumer.insert((consumer, mint), 500);
+    gsn.token_executor.insert((Pubkey::new_unique(), mint), 200);
+    gsn.clear_balances();
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    let gsn = GsnInfo::deserialize(&data).unwrap();
+    assert!(gsn.consumer.is_empty());
+    // The vault's token accounts aren't swept, they still back these
+    assert_eq!(gsn.token_consumer.get(&(consumer, mint)), Some(&500));
+    assert_eq!(gsn.token_executor.len(), 1);
+    assert_eq!(gsn.treasury, 0);
+    assert_eq!(gsn.insurance_pool, 0);
+}
+
+#[test]
+fn test_submissions_credit_the_insurance_cut() {
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[5];
+    gsn.add_consumer(keys[1], 200_000);
+    let mut config = GsnConfig::new(keys[4]);
+    config.initialize_governance(Pubkey::new_unique());
+    config.protocol_fee_bps = 1_000;
+    config.insurance_fee_bps = 200;
+    let mut lamports = [1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000];
+    let mut data = vec![vec![]; 6];
+    data[4] = vec![0u8; 1024];
+    data[5] = vec![0u8; 1024];
+    gsn.serialize(&mut data[4]).unwrap();
+    config.serialize(&mut data[5]).unwrap();
+
+    let owner = Pubkey::new_unique();
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = index == 1 || index == 3;
+            AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+        })
+        .collect();
+    Processor::process_submit_tx(&owner, 1_000, 0, 0, None, None, 0, None, &accounts).unwrap();
+    drop(accounts);
+
+    let gsn = GsnInfo::deserialize(&data[4]).unwrap();
+    assert_eq!(gsn.insurance_pool, 1_000);
+    assert_eq!(gsn.treasury, 5_000);
+    assert_eq!(gsn.executor.get(&keys[3]), Some(&44_000));
+}
+
+fn set_insurance_fee(signer: &Pubkey, config_data: &mut [u8], bps: u16) -> ProgramResult {
+    let (config_key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut config_lamports, mut signer_lamports) = (1, 1);
+    let mut signer_data = vec![];
+    let accounts = vec![
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            config_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            signer,
+            true,
+            false,
+            &mut signer_lamports,
+            &mut signer_data,
+            &owner,
+            false,
+            0,
+        ),
+    ];
+    Processor::process_set_insurance_fee(
+        &owner,
+        InsuranceFeeArgs {
+            insurance_fee_bps: bps,
+        },
+        &accounts,
+    )
+}
+
+#[test]
+fn test_insurance_fee_is_capped() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+
+    assert_eq!(
+        set_insurance_fee(&Pubkey::new_unique(), &mut data, 100),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        set_insurance_fee(&authority, &mut data, MAX_INSURANCE_FEE_BPS + 1),
+        Err(GsnError::InvalidInsuranceFee.into())
+    );
+    set_insurance_fee(&authority, &mut data, MAX_INSURANCE_FEE_BPS).unwrap();
+    assert_eq!(
+        GsnConfig::deserialize(&data).unwrap().insurance_fee_bps,
+        MAX_INSURANCE_FEE_BPS
+    );
+}
+
+#[test]
+fn test_authority_compensates_from_the_pool() {
+    let program_id = Pubkey::new_unique();
+    let (gsn_key, config_key, authority) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (vault_key, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    for nonce in 6..9 {
+        gsn.record_lane_transaction_executor(&consumer, 0, nonce, &executor);
+    }
+    for _ in 0..3 {
+        gsn.increment_channel_nonce(&consumer, &executor);
+    }
+    gsn.credit_insurance_pool(10_000);
+    gsn.credit_treasury(50_000);
+    gsn.set_paused(true);
+    let mut config = GsnConfig::new(gsn_key);
+    config.initialize_governance(authority);
+    config.record_retention = 2;
+    let mut data = vec![vec![]; 6];
+    data[0] = vec![0u8; 1024];
+    data[1] = vec![0u8; 1024];
+    gsn.serialize(&mut data[0]).unwrap();
+    config.serialize(&mut data[1]).unwrap();
+
+    let mut compensate = |signer: Pubkey, amount: u64, lane: u64, nonce: u64| {
+        let keys = [
+            gsn_key,
+            config_key,
+            signer,
+            consumer,
+            vault_key,
+            system_program::id(),
+        ];
+        let mut lamports = [1; 6];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 2, true, lamports, data, &program_id, false, 0)
+            })
+            .collect();
+        let args = CompensateArgs {
+            amount,
+            lane,
+            nonce,
+            channel_executor: (lane == CHANNEL_NONCE_LANE).then_some(executor),
+        };
+        Processor::process_compensate(&program_id, args, &accounts)
+    };
+    assert_eq!(
+        compensate(Pubkey::new_unique(), 1_000, 0, 7),
+        Err(GsnError::Unauthorized.into())
+    );
+    // Only recorded executions are compensated
+    assert_eq!(compensate(authority, 1_000, 0, 9), Err(GsnError::UnknownExecution.into()));
+    // Nor ones past the retention, which pruning drops
+    assert_eq!(compensate(authority, 1_000, 0, 6), Err(GsnError::UnknownExecution.into()));
+    // The treasury doesn't back the pool
+    assert_eq!(compensate(authority, 10_001, 0, 7), Err(ProgramError::InsufficientFunds));
+    // Incidents are settled while paused
+    compensate(authority, 4_000, 0, 7).unwrap();
+    // Once
+    assert_eq!(compensate(authority, 1_000, 0, 7), Err(GsnError::AlreadyCompensated.into()));
+    // Channel executions are the nonces the channel went past
+    assert_eq!(
+        compensate(authority, 1_000, CHANNEL_NONCE_LANE, 3),
+        Err(GsnError::UnknownExecution.into())
+    );
+    compensate(authority, 1_000, CHANNEL_NONCE_LANE, 2).unwrap();
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.insurance_pool, 5_000);
+    assert_eq!(gsn.treasury, 50_000);
+    assert_eq!(gsn.compensations.get(&(consumer, 0, 7, executor)), Some(&4_000));
+}
@@ -0,0 +1,180 @@
+// Tests for consumer intents verified with Ed25519 signatures
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SetGenesisHashArgs, SubmitLaneArgs},
+    intent::{domain_separator, ConsumerIntent, INTENT_DOMAIN},
+    state::GsnConfig,
+    Processor,
+};
+
+fn intent() -> ConsumerIntent {
+    ConsumerIntent {
+        domain_separator: domain_separator(&Pubkey::new_unique(), Some(&[1; 32])),
+        gsn_account: Pubkey::new_unique(),
+        consumer: Pubkey::new_unique(),
+        target_program: Pubkey::new_unique(),
+        receiver: Pubkey::new_unique(),
+        amount: 1_000,
+        nonce: 7,
+        lane: 2,
+    }
+}
+
+#[test]
+fn test_intent_message_layout() {
+    let intent = intent();
+    let message = intent.message();
+    assert_eq!(message.len(), INTENT_DOMAIN.len() + 32 * 5 + 8 * 3);
+    assert!(message.starts_with(INTENT_DOMAIN));
+    let rest = &message[INTENT_DOMAIN.len()..];
+    assert_eq!(&rest[..32], &intent.domain_separator);
+    let rest = &rest[32..];
+    assert_eq!(&rest[..32], intent.gsn_account.as_ref());
+    let rest = &rest[32..];
+    assert_eq!(&rest[..32], intent.consumer.as_ref());
+    assert_eq!(&rest[32..64], intent.target_program.as_ref());
+    assert_eq!(&rest[64..96], intent.receiver.as_ref());
+    assert_eq!(&rest[96..104], &1_000u64.to_le_bytes());
+    assert_eq!(&rest[104..112], &7u64.to_le_bytes());
+    assert_eq!(&rest[112..], &2u64.to_le_bytes());
+}
+
+#[test]
+fn test_intent_message_binds_every_field() {
+    let intent = intent();
+    let message = intent.message();
+    for changed in [
+        ConsumerIntent {
+            domain_separator: [0; 32],
+            ..intent.clone()
+        },
+        ConsumerIntent {
+            gsn_account: Pubkey::new_unique(),
+            ..intent.clone()
+        },
+        ConsumerIntent {
+            consumer: Pubkey::new_unique(),
+            ..intent.clone()
+        },
+        ConsumerIntent {
+            target_program: Pubkey::new_unique(),
+            ..intent.clone()
+        },
+        ConsumerIntent {
+            receiver: Pubkey::new_unique(),
+            ..intent.clone()
+        },
+        ConsumerIntent {
+            amount: 1_001,
+            ..intent.clone()
+        },
+        ConsumerIntent {
+            nonce: 8,
+            ..intent.clone()
+        },
+        ConsumerIntent {
+            lane: 3,
+            ..intent.clone()
+        },
+    ] {
+        assert_ne!(changed.message(), message);
+    }
+}
+
+#[test]
+fn test_submit_intent_checks_lane_and_signature_first() {
+    let args = |lane| SubmitLaneArgs {
+        amount: 1,
+        nonce: 0,
+        lane,
+        expiry_slot: None,
+        max_fee: None,
+        tip: 0,
+    };
+    assert_eq!(
+        Processor::process_submit_intent(&Pubkey::new_unique(), args(16), &[]),
+        Err(GsnError::InvalidNonceLane.into())
+    );
+
+    let program_id = Pubkey::new_unique();
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let mut lamports = [0u64; 6];
+    let mut data = vec![vec![]; 6];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| {
+            AccountInfo::new(key, false, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    assert_eq!(
+        Processor::process_submit_intent(&program_id, args(0), &accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_domain_separator_binds_program_and_cluster() {
+    let program_id = Pubkey::new_unique();
+    let (devnet, mainnet) = ([1; 32], [2; 32]);
+    let separator = domain_separator(&program_id, Some(&mainnet));
+    assert_ne!(domain_separator(&program_id, Some(&devnet)), separator);
+    assert_ne!(domain_separator(&Pubkey::new_unique(), Some(&mainnet)), separator);
+    assert_ne!(domain_separator(&program_id, None), separator);
+    assert_eq!(
+        domain_separator(&program_id, None),
+        domain_separator(&program_id, Some(&[0; 32]))
+    );
+}
+
+#[test]
+fn test_set_genesis_hash() {
+    let mut data = vec![56];
+    data.extend_from_slice(&[3; 32]);
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::SetGenesisHash(SetGenesisHashArgs { genesis_hash: [3; 32] })
+    );
+
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let owner = Pubkey::new_unique();
+    let set = |signer: &Pubkey, config_data: &mut Vec<u8>| {
+        let keys = [Pubkey::new_unique(), *signer];
+        let mut lamports = [1; 2];
+        let mut data = [config_data.clone(), vec![]];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 1, true, lamports, data, &owner, false, 0)
+            })
+            .collect();
+        let result = Processor::process_set_genesis_hash(
+            &owner,
+            SetGenesisHashArgs { genesis_hash: [3; 32] },
+            &accounts,
+        );
+        drop(accounts);
+        *config_data = data[0].clone();
+        result
+    };
+    let mut config_data = vec![0u8; 1024];
+    config.serialize(&mut config_data).unwrap();
+
+    assert_eq!(
+        set(&Pubkey::new_unique(), &mut config_data),
+        Err(GsnError::Unauthorized.into())
+    );
+    set(&authority, &mut config_data).unwrap();
+    assert_eq!(
+        GsnConfig::deserialize(&config_data).unwrap().genesis_hash,
+        Some([3; 32])
+    );
+}
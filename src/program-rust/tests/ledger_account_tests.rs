@@ -0,0 +1,186 @@
+// Tests for consumer and executor ledger accounts
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::GsnInstruction,
+    state::{
+        ConsumerAccount, ExecutorAccount, FeeMode, GsnConfig, GsnInfo, CONSUMER_LEDGER,
+        EXECUTOR_LEDGER,
+    },
+    Processor,
+};
+
+#[test]
+fn test_open_ledger_instructions_decode() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[57]).unwrap(),
+        GsnInstruction::OpenConsumerAccount
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&[58]).unwrap(),
+        GsnInstruction::OpenExecutorAccount
+    );
+}
+
+#[test]
+fn test_opening_moves_entries_out_of_the_state() {
+    let key = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(key, 5_000);
+    gsn.increment_nonce(&key);
+    gsn.add_executor(key, 700);
+    let size = gsn.serialized_size();
+
+    let mut consumer = ConsumerAccount {
+        consumer: key,
+        ..ConsumerAccount::default()
+    };
+    gsn.open_consumer_account(&mut consumer);
+    let mut executor = ExecutorAccount {
+        executor: key,
+        ..ExecutorAccount::default()
+    };
+    gsn.open_executor_account(&mut executor);
+
+    assert_eq!(
+        (consumer.balance, consumer.nonce, executor.earned),
+        (5_000, 1, 700)
+    );
+    assert_eq!(gsn.ledger_flags(&key), CONSUMER_LEDGER | EXECUTOR_LEDGER);
+    assert!(gsn.consumer.is_empty() && gsn.consumer_nonces.is_empty() && gsn.executor.is_empty());
+    // Three 40-byte entries give way to a 33-byte one
+    assert_eq!(gsn.serialized_size(), size - 3 * 40 + 33);
+
+    // Touching them without the accounts is caught before the state is written
+    gsn.add_consumer(key, 1);
+    assert_eq!(
+        gsn.check_ledgers_stored(),
+        Err(GsnError::MissingLedgerAccount)
+    );
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config, then the sender's ledger account
+struct Setup {
+    program_id: Pubkey,
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let (ledger_key, bump) = ConsumerAccount::find_address(&program_id, &keys[4], &keys[1]);
+        keys.push(ledger_key);
+
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 1_000_000);
+        let mut account = ConsumerAccount {
+            is_initialized: true,
+            gsn_account: keys[4],
+            consumer: keys[1],
+            bump,
+            ..ConsumerAccount::default()
+        };
+        gsn.open_consumer_account(&mut account);
+
+        let mut data = vec![vec![]; 7];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        data[6] = vec![0u8; ConsumerAccount::LEN];
+        gsn.serialize(&mut data[4]).unwrap();
+        let mut config = GsnConfig::new(keys[4]);
+        config.initialize_governance(Pubkey::new_unique());
+        config.update_fee_params(FeeMode::Fixed(50_000));
+        config.serialize(&mut data[5]).unwrap();
+        account.serialize(&mut data[6]).unwrap();
+        Self {
+            program_id,
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self, nonce: u64, with_ledger: bool) -> Result<(), ProgramError> {
+        let program_id = self.program_id;
+        let mut accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        if !with_ledger {
+            accounts.pop();
+        }
+        Processor::process_submit_tx(&program_id, 1_000, nonce, 0, None, None, 0, None, &accounts)
+    }
+
+    fn ledger(&self) -> ConsumerAccount {
+        ConsumerAccount::deserialize(&self.data[6]).unwrap()
+    }
+}
+
+#[test]
+fn test_submissions_use_the_ledger_account() {
+    let mut setup = Setup::new();
+    setup.submit(0, true).unwrap();
+    setup.submit(1, true).unwrap();
+
+    assert_eq!(setup.ledger().nonce, 2);
+    let gsn = GsnInfo::deserialize(&setup.data[4]).unwrap();
+    assert!(gsn.consumer.is_empty() && gsn.consumer_nonces.is_empty());
+    // The executor has no ledger account, its earnings stay in the state
+    assert!(gsn.executor.contains_key(&setup.keys[3]));
+}
+
+#[test]
+fn test_submissions_without_the_ledger_account_fail() {
+    let mut setup = Setup::new();
+    setup.submit(0, true).unwrap();
+    // Without its account the consumer reads as never topped up
+    assert_eq!(
+        setup.submit(1, false),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(setup.ledger().nonce, 1);
+}
+
+#[test]
+fn test_ledger_accounts_of_other_states_are_ignored() {
+    let mut setup = Setup::new();
+    let mut ledger = setup.ledger();
+    ledger.gsn_account = Pubkey::new_unique();
+    ledger.serialize(&mut setup.data[6]).unwrap();
+    assert!(setup.submit(0, true).is_err());
+}
+
+#[test]
+fn test_ledger_accounts_round_trip() {
+    let key = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before ledger accounts existed have none
+    let legacy_len = gsn.serialized_size() - 166;
+    let mut decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.ledger_flags(&key), 0);
+
+    decoded.open_executor_account(&mut ExecutorAccount {
+        executor: key,
+        ..ExecutorAccount::default()
+    });
+    decoded.serialize(&mut data).unwrap();
+    gsn = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(gsn.ledger_flags(&key), EXECUTOR_LEDGER);
+}
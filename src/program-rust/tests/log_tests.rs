@@ -0,0 +1,38 @@
+// Tests for the key=value and JSON event formats
+
+use solgsn::log::{format_json, format_key_value, EVENT_SCHEMA};
+
+#[test]
+fn test_key_value_format_matches_legacy_logs() {
+    let consumer = "Cons1".to_string();
+    let amount = 1000u64;
+    assert_eq!(
+        format_key_value("TOPUP", &[("consumer", &consumer), ("amount", &amount)]),
+        "[TOPUP] consumer=Cons1 amount=1000"
+    );
+}
+
+#[test]
+fn test_json_format_is_versioned_single_line() {
+    let consumer = "Cons1".to_string();
+    let amount = 1000u64;
+    let line = format_json("TOPUP", &[("consumer", &consumer), ("amount", &amount)]);
+    assert_eq!(
+        line,
+        format!(
+            "{{\"schema\":\"{}\",\"event\":\"TOPUP\",\"consumer\":\"Cons1\",\"amount\":1000}}",
+            EVENT_SCHEMA
+        )
+    );
+}
+
+#[test]
+fn test_json_format_escapes_strings() {
+    let error = "Custom(\"bad\")\nline".to_string();
+    let nonce = "007".to_string();
+    let line = format_json("EXECUTION_FAILED", &[("error", &error), ("nonce", &nonce)]);
+    assert!(line.contains("\"error\":\"Custom(\\\"bad\\\")\\nline\""));
+    // Leading zeros are not valid JSON numbers
+    assert!(line.contains("\"nonce\":\"007\""));
+    assert!(!line.contains('\n'));
+}
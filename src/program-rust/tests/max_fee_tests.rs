@@ -0,0 +1,192 @@
+// Tests for the maximum fee consumers sign with SubmitTransaction and
+// SubmitTransactionInLane
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SubmitArgs, SubmitLaneArgs},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+fn encode(values: &[u64]) -> Vec<u8> {
+    encode_tagged(2, values)
+}
+
+fn encode_tagged(tag: u8, values: &[u64]) -> Vec<u8> {
+    let mut data = vec![tag];
+    for value in values {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_submit_with_max_fee_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&encode(&[1_000, 3, 250, 60_000])).unwrap(),
+        GsnInstruction::SubmitTransaction(SubmitArgs {
+            amount: 1_000,
+            nonce: 3,
+            expiry_slot: Some(250),
+            max_fee: Some(60_000),
+            tip: 0,
+            simulate: false,
+            quote: None,
+        })
+    );
+    // An expiry slot of 0 leaves the expiry out
+    assert_eq!(
+        GsnInstruction::deserialize(&encode(&[1_000, 3, 0, 60_000])).unwrap(),
+        GsnInstruction::SubmitTransaction(SubmitArgs {
+            amount: 1_000,
+            nonce: 3,
+            expiry_slot: None,
+            max_fee: Some(60_000),
+            tip: 0,
+            simulate: false,
+            quote: None,
+        })
+    );
+    let data = encode(&[1_000, 3, 250, 60_000]);
+    assert_eq!(
+        SubmitArgs::unpack(&data[..data.len() - 1]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_lane_submissions_decode_the_same_options() {
+    let args = |expiry_slot, max_fee, tip| SubmitLaneArgs {
+        amount: 1_000,
+        nonce: 3,
+        lane: 2,
+        expiry_slot,
+        max_fee,
+        tip,
+    };
+    // Clients predating the options leave them out
+    assert_eq!(
+        GsnInstruction::deserialize(&encode_tagged(8, &[1_000, 3, 2])).unwrap(),
+        GsnInstruction::SubmitTransactionInLane(args(None, None, 0))
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&encode_tagged(8, &[1_000, 3, 2, 0, 60_000, 7])).unwrap(),
+        GsnInstruction::SubmitTransactionInLane(args(None, Some(60_000), 7))
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&encode_tagged(8, &[1_000, 3, 2, 250, 60_000, 7, 1])),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    // The other lane submissions take none of them
+    for tag in [10, 26, 28] {
+        assert!(GsnInstruction::deserialize(&encode_tagged(tag, &[1_000, 3, 2])).is_ok());
+        assert_eq!(
+            GsnInstruction::deserialize(&encode_tagged(tag, &[1_000, 3, 2, 250])),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config, with the default fee of 50,000 lamports
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 200_000);
+        let mut config = GsnConfig::new(keys[4]);
+        config.initialize_governance(Pubkey::new_unique());
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self, max_fee: Option<u64>) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, 0, 0, None, max_fee, 0, None, &accounts)
+    }
+
+    fn next_nonce(&self) -> u64 {
+        GsnInfo::deserialize(&self.data[4])
+            .unwrap()
+            .get_next_nonce(&self.keys[1])
+    }
+}
+
+#[test]
+fn test_fee_above_the_max_fee_is_rejected() {
+    let mut setup = Setup::new();
+    assert_eq!(setup.submit(Some(49_999)), Err(GsnError::FeeTooHigh.into()));
+    assert_eq!(setup.next_nonce(), 0);
+
+    setup.submit(Some(50_000)).unwrap();
+    assert_eq!(setup.next_nonce(), 1);
+}
+
+#[test]
+fn test_submission_without_max_fee_accepts_the_fee() {
+    let mut setup = Setup::new();
+    setup.submit(None).unwrap();
+    assert_eq!(setup.next_nonce(), 1);
+}
+
+#[test]
+fn test_lane_submission_above_the_max_fee_is_rejected() {
+    let mut setup = Setup::new();
+    let program_id = Pubkey::new_unique();
+    let mut submit = |max_fee| {
+        let args = SubmitLaneArgs {
+            amount: 1_000,
+            nonce: 0,
+            lane: 2,
+            expiry_slot: None,
+            max_fee: Some(max_fee),
+            tip: 0,
+        };
+        let (keys, data) = (&setup.keys, &mut setup.data);
+        let ix = instruction::submit_transaction_in_lane(
+            &program_id, &keys[4], &keys[5], &keys[1], &keys[2], &keys[3], &args,
+        );
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(setup.lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        Processor::process(&program_id, &accounts, &ix.data)
+    };
+    assert_eq!(submit(49_999), Err(GsnError::FeeTooHigh.into()));
+    submit(50_000).unwrap();
+    let gsn = GsnInfo::deserialize(&setup.data[4]).unwrap();
+    assert_eq!(gsn.get_next_lane_nonce(&setup.keys[1], 2), 1);
+}
@@ -0,0 +1,54 @@
+// Tests for the per-epoch protocol metrics account
+
+use solana_program::pubkey::Pubkey;
+use solgsn::state::{ProtocolMetrics, NATIVE_FEE_TOKEN};
+
+#[test]
+fn test_metrics_accumulate_within_epoch() {
+    let gsn_account = Pubkey::new_unique();
+    let mut metrics = ProtocolMetrics::new(gsn_account, 10);
+    assert!(metrics.is_initialized);
+    assert_eq!(metrics.gsn_account, gsn_account);
+
+    let system_program = Pubkey::default();
+    let sol = NATIVE_FEE_TOKEN;
+    let executor1 = Pubkey::new_unique();
+    let executor2 = Pubkey::new_unique();
+
+    metrics.record_execution(10, &system_program, &executor1, &sol, 50000);
+    metrics.record_execution(10, &system_program, &executor2, &sol, 50000);
+    metrics.record_execution(10, &system_program, &executor2, &sol, 50000);
+
+    let current = &metrics.current;
+    assert_eq!(current.epoch, 10);
+    assert_eq!(current.executions, 3);
+    assert_eq!(current.total_fees, 150000);
+    assert_eq!(current.fees_by_token.get(&sol), Some(&150000));
+    assert_eq!(current.executions_by_program.get(&system_program), Some(&3));
+    assert!(metrics.previous.is_none());
+
+    // Executors are ranked by fees earned
+    let top = current.top_executors(1);
+    assert_eq!(top, vec![(&executor2, 100000)]);
+    assert_eq!(current.top_executors(10).len(), 2);
+}
+
+#[test]
+fn test_metrics_roll_over_on_new_epoch() {
+    let mut metrics = ProtocolMetrics::new(Pubkey::new_unique(), 10);
+    let program = Pubkey::new_unique();
+    let executor = Pubkey::new_unique();
+
+    metrics.record_execution(10, &program, &executor, &NATIVE_FEE_TOKEN, 50000);
+    metrics.record_execution(11, &program, &executor, &NATIVE_FEE_TOKEN, 20000);
+
+    let previous = metrics.previous.as_ref().expect("epoch 10 should be kept");
+    assert_eq!(previous.epoch, 10);
+    assert_eq!(previous.executions, 1);
+    assert_eq!(previous.total_fees, 50000);
+
+    assert_eq!(metrics.current.epoch, 11);
+    assert_eq!(metrics.current.executions, 1);
+    assert_eq!(metrics.current.total_fees, 20000);
+    assert_eq!(metrics.current.executor_fees.get(&executor), Some(&20000));
+}
@@ -0,0 +1,189 @@
+// Tests for M-of-N multisig governance
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SetMultisigArgs, UpdateFeeParamsArgs},
+    state::{GsnConfig, MultisigConfig, MAX_MULTISIG_SIGNERS},
+    Processor,
+};
+
+/// Owner of every account the tests pass
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([9; 32]);
+
+fn encode(threshold: u8, signers: &[Pubkey]) -> Vec<u8> {
+    let mut data = vec![35, threshold, signers.len() as u8];
+    for signer in signers {
+        data.extend_from_slice(signer.as_ref());
+    }
+    data
+}
+
+#[test]
+fn test_set_multisig_decodes() {
+    let signers = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    let data = encode(2, &signers);
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::SetMultisig(SetMultisigArgs {
+            threshold: 2,
+            signers
+        })
+    );
+    assert_eq!(
+        SetMultisigArgs::unpack(&data[..data.len() - 1]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_multisig_validity() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let multisig = |signers: Vec<Pubkey>, threshold| MultisigConfig { signers, threshold };
+    assert!(multisig(vec![a, b], 2).is_valid());
+    assert!(multisig(vec![a, b], 1).is_valid());
+    assert!(!multisig(vec![a, b], 0).is_valid());
+    assert!(!multisig(vec![a, b], 3).is_valid());
+    assert!(!multisig(vec![a, a], 1).is_valid());
+    assert!(!multisig(vec![], 0).is_valid());
+    let too_many = (0..=MAX_MULTISIG_SIGNERS)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    assert!(!multisig(too_many, 1).is_valid());
+}
+
+#[test]
+fn test_multisig_replaces_the_authority() {
+    let authority = Pubkey::new_unique();
+    let signers: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    assert!(config.is_approved_by(&[&authority]));
+
+    config.multisig = Some(MultisigConfig {
+        signers: signers.clone(),
+        threshold: 2,
+    });
+    assert!(!config.is_approved_by(&[&authority]));
+    assert!(!config.is_approved_by(&[&signers[0], &signers[0]]));
+    assert!(config.is_approved_by(&[&signers[0], &signers[2]]));
+    assert!(config.is_approved_by(&[&authority, &signers[1], &signers[2]]));
+}
+
+/// Governance accounts: the config, then each key, signing if it is in `signed`
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new(authority: &Pubkey, keys: &[Pubkey]) -> Self {
+        let mut config = GsnConfig::new(Pubkey::new_unique());
+        config.initialize_governance(*authority);
+        let mut data = vec![vec![0u8; 1024]];
+        config.serialize(&mut data[0]).unwrap();
+        data.extend(keys.iter().map(|_| vec![]));
+        let mut all_keys = vec![Pubkey::new_unique()];
+        all_keys.extend_from_slice(keys);
+        Self {
+            lamports: vec![1; all_keys.len()],
+            keys: all_keys,
+            data,
+        }
+    }
+
+    fn accounts(&mut self, signed: &[Pubkey]) -> Vec<AccountInfo<'_>> {
+        self.keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .map(|((key, lamports), data)| {
+                let is_signer = signed.contains(key);
+                AccountInfo::new(key, is_signer, true, lamports, data, &PROGRAM_ID, false, 0)
+            })
+            .collect()
+    }
+
+    fn config(&self) -> GsnConfig {
+        GsnConfig::deserialize(&self.data[0]).unwrap()
+    }
+}
+
+fn fixed_fee(fee: u64) -> UpdateFeeParamsArgs {
+    UpdateFeeParamsArgs {
+        fee_mode_type: 0,
+        fee_value: fee,
+        extra: [0; 2],
+    }
+}
+
+#[test]
+fn test_fee_changes_need_the_threshold() {
+    let authority = Pubkey::new_unique();
+    let signers: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let mut setup = Setup::new(&authority, &[authority, signers[1], signers[2]]);
+
+    let args = SetMultisigArgs {
+        threshold: 2,
+        signers: signers.clone(),
+    };
+    Processor::process_set_multisig(&PROGRAM_ID, args, &setup.accounts(&[authority])).unwrap();
+    assert_eq!(setup.config().multisig.unwrap().threshold, 2);
+
+    // The authority alone, or one of the signers, no longer governs
+    assert_eq!(
+        Processor::process_update_fee_params(
+            &PROGRAM_ID,
+            fixed_fee(1),
+            &setup.accounts(&[authority])
+        ),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        Processor::process_update_fee_params(
+            &PROGRAM_ID,
+            fixed_fee(1),
+            &setup.accounts(&[authority, signers[1]])
+        ),
+        Err(GsnError::Unauthorized.into())
+    );
+
+    setup.keys[1] = signers[0];
+    let approvers = [signers[0], signers[2]];
+    Processor::process_update_fee_params(&PROGRAM_ID, fixed_fee(1), &setup.accounts(&approvers))
+        .unwrap();
+    assert_eq!(setup.config().calculate_fee(1_000), 1);
+}
+
+#[test]
+fn test_multisig_hands_governance_back() {
+    let authority = Pubkey::new_unique();
+    let signers: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+    let mut setup = Setup::new(&authority, &signers);
+
+    let invalid = SetMultisigArgs {
+        threshold: 3,
+        signers: signers.clone(),
+    };
+    setup.keys[1] = authority;
+    assert_eq!(
+        Processor::process_set_multisig(&PROGRAM_ID, invalid, &setup.accounts(&[authority])),
+        Err(GsnError::InvalidMultisig.into())
+    );
+
+    let args = SetMultisigArgs {
+        threshold: 2,
+        signers: signers.clone(),
+    };
+    Processor::process_set_multisig(&PROGRAM_ID, args, &setup.accounts(&[authority])).unwrap();
+
+    setup.keys[1] = signers[0];
+    let clear = SetMultisigArgs {
+        threshold: 0,
+        signers: vec![],
+    };
+    Processor::process_set_multisig(&PROGRAM_ID, clear, &setup.accounts(&signers)).unwrap();
+    assert_eq!(setup.config().multisig, None);
+    assert!(setup.config().is_approved_by(&[&authority]));
+}
@@ -0,0 +1,168 @@
+// Tests for the pause circuit breaker
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, WithdrawArgs},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_pause_instructions_decode() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[33]).unwrap(),
+        GsnInstruction::Pause
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&[34]).unwrap(),
+        GsnInstruction::Unpause
+    );
+}
+
+#[test]
+fn test_paused_flag_round_trips() {
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(Pubkey::new_unique(), 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the flag existed aren't paused
+    let legacy_len = gsn.serialized_size() - 213;
+    assert!(!GsnInfo::deserialize(&data[..legacy_len]).unwrap().paused);
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.set_paused(true);
+    decoded.serialize(&mut data).unwrap();
+    let decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(decoded.check_not_paused(), Err(GsnError::ProtocolPaused));
+    assert_eq!(decoded.consumer, gsn.consumer);
+}
+
+fn accounts<'a>(
+    keys: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    signers: &[usize],
+    owner: &'a Pubkey,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = signers.contains(&index);
+            AccountInfo::new(key, is_signer, true, lamports, data, owner, false, 0)
+        })
+        .collect()
+}
+
+/// GSN state and config accounts governed by `authority`, paused or not
+fn state(authority: &Pubkey, paused: bool) -> (Pubkey, Pubkey, Vec<u8>, Vec<u8>) {
+    let (gsn_key, config_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    gsn.set_paused(paused);
+    let mut config = GsnConfig::new(gsn_key);
+    config.initialize_governance(*authority);
+    let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+    gsn.serialize(&mut gsn_data).unwrap();
+    config.serialize(&mut config_data).unwrap();
+    (gsn_key, config_key, gsn_data, config_data)
+}
+
+#[test]
+fn test_only_the_authority_pauses() {
+    let owner = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let (gsn_key, config_key, gsn_data, config_data) = state(&authority, false);
+    let mut data = [gsn_data, config_data, vec![]];
+    let mut lamports = [1, 1, 1];
+
+    let intruder = [gsn_key, config_key, Pubkey::new_unique()];
+    assert_eq!(
+        Processor::process_set_paused(
+            &owner,
+            true,
+            &accounts(&intruder, &mut lamports, &mut data, &[2], &owner)
+        ),
+        Err(GsnError::Unauthorized.into())
+    );
+
+    let keys = [gsn_key, config_key, authority];
+    Processor::process_set_paused(
+        &owner,
+        true,
+        &accounts(&keys, &mut lamports, &mut data, &[2], &owner),
+    )
+    .unwrap();
+    assert!(GsnInfo::deserialize(&data[0]).unwrap().paused);
+
+    Processor::process_set_paused(
+        &owner,
+        false,
+        &accounts(&keys, &mut lamports, &mut data, &[2], &owner),
+    )
+    .unwrap();
+    assert!(!GsnInfo::deserialize(&data[0]).unwrap().paused);
+}
+
+#[test]
+fn test_paused_deployment_halts_funds_movement() {
+    let owner = Pubkey::new_unique();
+    let (gsn_key, config_key, gsn_data, config_data) = state(&Pubkey::new_unique(), true);
+    let paused: Result<(), ProgramError> = Err(GsnError::ProtocolPaused.into());
+
+    // SubmitTransaction: target, sender, receiver, fee payer, state, config
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        gsn_key,
+        config_key,
+    ];
+    let mut data = [
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        gsn_data.clone(),
+        config_data,
+    ];
+    let mut lamports = [1, 1_000_000, 0, 1_000_000, 1, 1];
+    let submit = accounts(&keys, &mut lamports, &mut data, &[1, 3], &owner);
+    assert_eq!(
+        Processor::process_submit_tx(&owner, 1_000, 0, 0, None, None, 0, None, &submit),
+        paused
+    );
+
+    // Withdraw: state, consumer, vault, system program
+    let keys = [
+        gsn_key,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::default(),
+    ];
+    let mut data = [gsn_data.clone(), vec![], vec![], vec![]];
+    let mut lamports = [1, 0, 1_000, 1];
+    let withdraw = accounts(&keys, &mut lamports, &mut data, &[1], &owner);
+    assert_eq!(
+        Processor::process_withdraw(&owner, WithdrawArgs { amount: 1_000 }, &withdraw),
+        paused
+    );
+
+    // ClaimFees: state, executor, destination, vault, system program
+    let executor = Pubkey::new_unique();
+    let keys = [
+        gsn_key,
+        executor,
+        executor,
+        Pubkey::new_unique(),
+        Pubkey::default(),
+    ];
+    let mut data = [gsn_data, vec![], vec![], vec![], vec![]];
+    let mut lamports = [1, 0, 0, 1_000, 1];
+    let claim = accounts(&keys, &mut lamports, &mut data, &[1, 2], &owner);
+    assert_eq!(Processor::process_claim_fees(&owner, &claim), paused);
+}
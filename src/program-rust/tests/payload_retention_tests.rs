@@ -0,0 +1,208 @@
+// Tests for rejecting duplicates of recently executed payloads
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SetPayloadRetentionArgs},
+    sorted_map::SortedMap,
+    state::{FeeMode, GovernanceConfig, GsnConfig, GsnInfo, RecentPayloads},
+    Processor,
+};
+
+#[test]
+fn test_set_payload_retention_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[55, 64, 0]).unwrap(),
+        GsnInstruction::SetPayloadRetention(SetPayloadRetentionArgs { retention: 64 })
+    );
+    assert!(GsnInstruction::deserialize(&[55, 64]).is_err());
+}
+
+#[test]
+fn test_recent_payloads_ring() {
+    let mut payloads = RecentPayloads::default();
+    // Nothing is retained without a window
+    payloads.record([1; 32]);
+    assert!(!payloads.contains(&[1; 32]));
+
+    payloads.resize(2);
+    for byte in 1..=3 {
+        payloads.record([byte; 32]);
+    }
+    assert!(!payloads.contains(&[1; 32]));
+    assert!(payloads.contains(&[2; 32]) && payloads.contains(&[3; 32]));
+
+    // Shrinking keeps the most recent hashes, growing keeps them all
+    payloads.resize(1);
+    assert_eq!(payloads.hashes, vec![[3; 32]]);
+    payloads.resize(3);
+    payloads.record([4; 32]);
+    payloads.record([5; 32]);
+    assert_eq!(payloads.retention(), 3);
+    assert!([3, 4, 5].iter().all(|byte| payloads.contains(&[*byte; 32])));
+    payloads.record([6; 32]);
+    assert!(!payloads.contains(&[3; 32]));
+}
+
+/// `SetPayloadRetention` accounts: GSN state, config, authority
+fn set_retention(
+    gsn_data: &mut Vec<u8>,
+    config: &GsnConfig,
+    authority: &Pubkey,
+    retention: u16,
+) -> Result<(), ProgramError> {
+    let owner = Pubkey::new_unique();
+    let keys = [Pubkey::new_unique(), config.gsn_account, *authority];
+    let mut config_data = vec![0u8; 1024];
+    config.serialize(&mut config_data).unwrap();
+    let mut data = [gsn_data.clone(), config_data, vec![]];
+    let mut lamports = [1; 3];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == 2, true, lamports, data, &owner, false, 0)
+        })
+        .collect();
+    let result = Processor::process_set_payload_retention(
+        &owner,
+        SetPayloadRetentionArgs { retention },
+        &accounts,
+    );
+    drop(accounts);
+    *gsn_data = data[0].clone();
+    result
+}
+
+#[test]
+fn test_only_governance_sets_retention() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.governance = Some(GovernanceConfig {
+        authority,
+        fee_mode: FeeMode::Fixed(5_000),
+        allowed_tokens: SortedMap::new(),
+    });
+    let mut gsn = GsnInfo::new();
+    gsn.config = config.gsn_account;
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    assert_eq!(
+        set_retention(&mut data, &config, &Pubkey::new_unique(), 8),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        set_retention(&mut data, &config, &authority, 257),
+        Err(GsnError::InvalidPayloadRetention.into())
+    );
+    // The account has no room for 256 hashes
+    assert_eq!(
+        set_retention(&mut data, &config, &authority, 256),
+        Err(GsnError::HistoryFull.into())
+    );
+    set_retention(&mut data, &config, &authority, 8).unwrap();
+    assert_eq!(
+        GsnInfo::deserialize(&data).unwrap().recent_payloads.retention(),
+        8
+    );
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new(retention: usize) -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 1_000_000);
+        gsn.set_payload_retention(retention);
+        let config = GsnConfig::new(keys[4]);
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self, amount: u64, nonce: u64) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, amount, nonce, 0, None, None, 0, None, &accounts)
+    }
+
+    /// Rewind the consumer's nonces, as a buggy relayer's stale state would
+    fn reset_nonces(&mut self) {
+        let mut gsn = GsnInfo::deserialize(&self.data[4]).unwrap();
+        let mut fresh = GsnInfo::new();
+        fresh.config = gsn.config;
+        fresh.consumer = std::mem::take(&mut gsn.consumer);
+        fresh.executor = std::mem::take(&mut gsn.executor);
+        fresh.recent_payloads = gsn.recent_payloads;
+        self.data[4] = vec![0u8; 1024];
+        fresh.serialize(&mut self.data[4]).unwrap();
+    }
+}
+
+#[test]
+fn test_duplicate_payload_rejected() {
+    let mut setup = Setup::new(4);
+    setup.submit(1_000, 0).unwrap();
+    setup.reset_nonces();
+    assert_eq!(
+        setup.submit(1_000, 0),
+        Err(GsnError::DuplicatePayload.into())
+    );
+    // The same transfer of another amount is a different payload
+    setup.submit(2_000, 0).unwrap();
+}
+
+#[test]
+fn test_duplicates_pass_without_retention() {
+    let mut setup = Setup::new(0);
+    setup.submit(1_000, 0).unwrap();
+    setup.reset_nonces();
+    setup.submit(1_000, 0).unwrap();
+}
+
+#[test]
+fn test_recent_payloads_round_trip() {
+    let mut gsn = GsnInfo::new();
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before payload hashes were retained keep none
+    let legacy_len = gsn.serialized_size() - 172;
+    let mut decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.recent_payloads.retention(), 0);
+
+    decoded.set_payload_retention(2);
+    decoded.record_payload([7; 32]);
+    decoded.serialize(&mut data).unwrap();
+    gsn = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(gsn.recent_payloads.retention(), 2);
+    assert!(gsn.is_recent_payload(&[7; 32]));
+}
@@ -0,0 +1,161 @@
+// Tests for executor payout addresses
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solgsn::{error::GsnError, instruction::GsnInstruction, state::GsnInfo, Processor};
+
+fn accounts<'a>(
+    keys: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    signers: &[usize],
+    owner: &'a Pubkey,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = signers.contains(&index);
+            AccountInfo::new(key, is_signer, true, lamports, data, owner, false, 0)
+        })
+        .collect()
+}
+
+/// `SetPayoutAddress` accounts: state, executor, payout, then the current
+/// payout address when one is passed
+fn set_payout(
+    gsn_data: &mut Vec<u8>,
+    executor: &Pubkey,
+    payout: &Pubkey,
+    current: Option<(&Pubkey, bool)>,
+) -> ProgramResult {
+    let owner = Pubkey::new_unique();
+    let mut keys = vec![Pubkey::new_unique(), *executor, *payout];
+    let mut signers = vec![1];
+    if let Some((current, signs)) = current {
+        keys.push(*current);
+        if signs {
+            signers.push(3);
+        }
+    }
+    let mut data = vec![vec![]; keys.len()];
+    data[0] = gsn_data.clone();
+    let mut lamports = vec![1; keys.len()];
+    let result = Processor::process_set_payout_address(&owner, &accounts(
+        &keys,
+        &mut lamports,
+        &mut data,
+        &signers,
+        &owner,
+    ));
+    *gsn_data = data[0].clone();
+    result
+}
+
+#[test]
+fn test_set_payout_address_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[53]).unwrap(),
+        GsnInstruction::SetPayoutAddress
+    );
+}
+
+#[test]
+fn test_replacing_a_payout_address_takes_its_signature() {
+    let (executor, cold, other) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut data = vec![0u8; 1024];
+    GsnInfo::new().serialize(&mut data).unwrap();
+
+    set_payout(&mut data, &executor, &cold, None).unwrap();
+    let gsn = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(gsn.payout_address(&executor), Some(&cold));
+    assert!(gsn.is_claim_destination(&executor, &cold));
+    assert!(gsn.is_claim_destination(&executor, &executor));
+    assert!(!gsn.is_claim_destination(&executor, &other));
+
+    // A leaked executor key can't redirect the fees
+    assert!(set_payout(&mut data, &executor, &other, None).is_err());
+    assert_eq!(
+        set_payout(&mut data, &executor, &other, Some((&cold, false))),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        set_payout(&mut data, &executor, &other, Some((&other, true))),
+        Err(GsnError::Unauthorized.into())
+    );
+
+    set_payout(&mut data, &executor, &other, Some((&cold, true))).unwrap();
+    assert_eq!(
+        GsnInfo::deserialize(&data)
+            .unwrap()
+            .payout_address(&executor),
+        Some(&other)
+    );
+}
+
+#[test]
+fn test_fees_are_claimed_to_the_payout_address() {
+    let program_id = Pubkey::new_unique();
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let (executor, cold) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.add_executor(executor, 100_000);
+    gsn.set_payout_address(executor, cold);
+    let mut gsn_data = vec![0u8; 1024];
+    gsn.serialize(&mut gsn_data).unwrap();
+
+    // ClaimFees: state, executor, destination, vault, system program
+    let claim = |gsn_data: &mut Vec<u8>, destination: Pubkey| {
+        let keys = [gsn_key, executor, destination, vault, Pubkey::default()];
+        let mut data = [gsn_data.clone(), vec![], vec![], vec![], vec![]];
+        let mut lamports = [1, 0, 0, 100_000, 1];
+        let result = Processor::process_claim_fees(
+            &program_id,
+            &accounts(&keys, &mut lamports, &mut data, &[1], &program_id),
+        );
+        *gsn_data = data[0].clone();
+        result
+    };
+
+    assert_eq!(
+        claim(&mut gsn_data, Pubkey::new_unique()),
+        Err(GsnError::UnauthorizedFeeClaim.into())
+    );
+    claim(&mut gsn_data, cold).unwrap();
+    assert_eq!(
+        GsnInfo::deserialize(&gsn_data)
+            .unwrap()
+            .executor
+            .get(&executor),
+        Some(&0)
+    );
+}
+
+#[test]
+fn test_payout_addresses_round_trip() {
+    let executor = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_executor(executor, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before payout addresses existed have none
+    let legacy_len = gsn.serialized_size() - 184;
+    let mut decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.payout_address(&executor), None);
+
+    let cold = Pubkey::new_unique();
+    decoded.set_payout_address(executor, cold);
+    decoded.serialize(&mut data).unwrap();
+    assert_eq!(
+        GsnInfo::deserialize(&data)
+            .unwrap()
+            .payout_address(&executor),
+        Some(&cold)
+    );
+}
@@ -0,0 +1,140 @@
+// Tests for minimum and maximum bounds on percentage fees
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::UpdateFeeParamsArgs,
+    state::{FeeMode, GsnConfig},
+    Processor,
+};
+
+const BOUNDED: FeeMode = FeeMode::Percent {
+    basis_points: 100,
+    min_fee: Some(5_000),
+    max_fee: Some(100_000),
+};
+
+fn config(fee_mode: FeeMode) -> GsnConfig {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(Pubkey::new_unique());
+    config.update_fee_params(fee_mode);
+    config
+}
+
+#[test]
+fn test_percent_fee_bounds() {
+    let config = config(BOUNDED);
+    assert_eq!(config.calculate_fee(1_000), 5_000);
+    assert_eq!(config.calculate_fee(1_000_000), 10_000);
+    assert_eq!(config.calculate_fee(1_000_000_000), 100_000);
+
+    // Either bound may be left out
+    let config = self::config(FeeMode::Percent {
+        basis_points: 100,
+        min_fee: None,
+        max_fee: Some(100_000),
+    });
+    assert_eq!(config.calculate_fee(1_000), 10);
+    let config = self::config(FeeMode::Percent {
+        basis_points: 100,
+        min_fee: Some(5_000),
+        max_fee: None,
+    });
+    assert_eq!(config.calculate_fee(1_000_000_000), 10_000_000);
+}
+
+#[test]
+fn test_unbounded_percent_keeps_its_layout() {
+    let legacy = [1u8, 100, 0];
+    assert_eq!(
+        FeeMode::try_from_slice(&legacy).unwrap(),
+        FeeMode::percent(100)
+    );
+    assert_eq!(FeeMode::percent(100).try_to_vec().unwrap(), legacy);
+
+    let encoded = BOUNDED.try_to_vec().unwrap();
+    assert_eq!(encoded[0], 3);
+    assert_eq!(FeeMode::try_from_slice(&encoded).unwrap(), BOUNDED);
+    assert!(FeeMode::try_from_slice(&[4]).is_err());
+}
+
+#[test]
+fn test_bounded_percent_reductions() {
+    let lower = FeeMode::Percent {
+        basis_points: 50,
+        min_fee: None,
+        max_fee: Some(50_000),
+    };
+    assert!(lower.is_reduction_of(&BOUNDED));
+    assert!(!BOUNDED.is_reduction_of(&lower));
+    // Dropping the cap raises large fees
+    assert!(!FeeMode::percent(50).is_reduction_of(&BOUNDED));
+    // Adding a floor raises small fees
+    assert!(!BOUNDED.is_reduction_of(&FeeMode::percent(100)));
+}
+
+fn update(
+    config_data: &mut [u8],
+    authority: &Pubkey,
+    fee_value: u64,
+    extra: [u64; 2],
+) -> ProgramResult {
+    let config_key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let (mut config_lamports, mut authority_lamports) = (1, 1);
+    let mut authority_data = vec![];
+    let accounts = vec![
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            config_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            authority,
+            true,
+            false,
+            &mut authority_lamports,
+            &mut authority_data,
+            &owner,
+            false,
+            0,
+        ),
+    ];
+    let args = UpdateFeeParamsArgs {
+        fee_mode_type: 1,
+        fee_value,
+        extra,
+    };
+    Processor::process_update_fee_params(&owner, args, &accounts)
+}
+
+#[test]
+fn test_update_fee_params_validates_bounds() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    config.update_fee_params(BOUNDED);
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+
+    assert_eq!(
+        update(&mut data, &authority, 50, [60_000, 50_000]),
+        Err(GsnError::InvalidFeeMode.into())
+    );
+    // 0 leaves the cap out, raising large fees
+    assert_eq!(
+        update(&mut data, &authority, 50, [1_000, 0]),
+        Err(GsnError::FeeChangeTimelocked.into())
+    );
+
+    update(&mut data, &authority, 50, [1_000, 50_000]).unwrap();
+    let config = GsnConfig::deserialize(&data).unwrap();
+    assert_eq!(config.calculate_fee(1_000), 1_000);
+    assert_eq!(config.calculate_fee(1_000_000_000), 50_000);
+}
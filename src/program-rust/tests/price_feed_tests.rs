@@ -0,0 +1,274 @@
+// Tests for token fees priced by oracle price feeds
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::SUCCESS,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SetPriceFeedArgs, SubmitTokenFeeArgs},
+    oracle::{load_price, unpack_price, OraclePrice},
+    sorted_map::SortedMap,
+    state::{FeeMode, GovernanceConfig, GsnConfig, GsnInfo, OracleKind, PriceFeed},
+    Processor,
+};
+
+/// Slot of the stubbed clock
+const SLOT: u64 = 1_000;
+
+struct ClockStubs;
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+/// Pyth v2 price account publishing `price * 10^expo` at `publish_slot`
+fn price_account(price: i64, conf: u64, expo: i32, publish_slot: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 3312];
+    data[..4].copy_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    data[4..8].copy_from_slice(&2u32.to_le_bytes());
+    data[8..12].copy_from_slice(&3u32.to_le_bytes());
+    data[20..24].copy_from_slice(&expo.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[216..224].copy_from_slice(&conf.to_le_bytes());
+    // Trading
+    data[224..228].copy_from_slice(&1u32.to_le_bytes());
+    data[232..240].copy_from_slice(&publish_slot.to_le_bytes());
+    data
+}
+
+/// A feed quoting a 6 decimals token at 0.005 SOL
+fn usdc_feed(feed: Pubkey) -> PriceFeed {
+    PriceFeed {
+        feed,
+        decimals: 6,
+        max_age_slots: 25,
+        max_confidence_bps: 100,
+        kind: OracleKind::Pyth,
+    }
+}
+
+/// Switchboard v2 aggregator account whose latest confirmed round opened at
+/// `round_open_slot` with `mantissa / 10^scale`
+fn aggregator_account(mantissa: i128, std_deviation: i128, scale: u32, round_open_slot: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 3851];
+    data[..8].copy_from_slice(&[217, 230, 65, 101, 201, 162, 27, 125]);
+    data[350..358].copy_from_slice(&round_open_slot.to_le_bytes());
+    data[366..382].copy_from_slice(&mantissa.to_le_bytes());
+    data[382..386].copy_from_slice(&scale.to_le_bytes());
+    data[386..402].copy_from_slice(&std_deviation.to_le_bytes());
+    data[402..406].copy_from_slice(&scale.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_set_price_feed_decode() {
+    let (program_id, config, authority, mint) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let feed = usdc_feed(Pubkey::new_unique());
+    let set = instruction::set_price_feed(&program_id, &config, &authority, &mint, Some(&feed));
+    assert_eq!(
+        GsnInstruction::deserialize(&set.data).unwrap(),
+        GsnInstruction::SetPriceFeed(SetPriceFeedArgs {
+            mint,
+            feed: Some(feed.clone()),
+        })
+    );
+    let remove = instruction::set_price_feed(&program_id, &config, &authority, &mint, None);
+    assert_eq!(
+        GsnInstruction::deserialize(&remove.data).unwrap(),
+        GsnInstruction::SetPriceFeed(SetPriceFeedArgs { mint, feed: None })
+    );
+    // Instructions predating oracle kinds leave the kind out
+    assert_eq!(
+        GsnInstruction::deserialize(&set.data[..set.data.len() - 1]).unwrap(),
+        GsnInstruction::SetPriceFeed(SetPriceFeedArgs {
+            mint,
+            feed: Some(feed.clone()),
+        })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&set.data[..set.data.len() - 2]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    let switchboard = PriceFeed {
+        kind: OracleKind::Switchboard,
+        ..feed
+    };
+    let mut set = instruction::set_price_feed(&program_id, &config, &authority, &mint, Some(&switchboard));
+    assert_eq!(
+        GsnInstruction::deserialize(&set.data).unwrap(),
+        GsnInstruction::SetPriceFeed(SetPriceFeedArgs {
+            mint,
+            feed: Some(switchboard),
+        })
+    );
+    *set.data.last_mut().unwrap() = 2;
+    assert_eq!(
+        GsnInstruction::deserialize(&set.data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_price_feed_checks() {
+    let key = Pubkey::new_unique();
+    let feed = usdc_feed(key);
+    let price = unpack_price(OracleKind::Pyth, &price_account(500_000, 400, -8, 990)).unwrap();
+    assert_eq!(
+        price,
+        OraclePrice {
+            price: 500_000,
+            conf: 400,
+            expo: -8,
+            publish_slot: 990,
+        }
+    );
+    // 50,000 lamports at 0.005 SOL per token are 0.01 tokens
+    assert_eq!(price.lamports_to_tokens(50_000, 6), Some(10_000));
+    // Rounded up
+    assert_eq!(price.lamports_to_tokens(50_001, 6), Some(10_001));
+    assert_eq!(price.lamports_to_tokens(u64::MAX, 18), None);
+
+    let load = |key: &Pubkey, mut data: Vec<u8>| {
+        let (mut lamports, owner) = (1, Pubkey::new_unique());
+        let account = AccountInfo::new(key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        load_price(&account, &feed, SLOT)
+    };
+    assert_eq!(load(&key, price_account(500_000, 400, -8, 990)), Ok(price));
+    assert_eq!(
+        load(&Pubkey::new_unique(), price_account(500_000, 400, -8, 990)),
+        Err(GsnError::InvalidPriceFeed)
+    );
+    assert_eq!(
+        load(&key, price_account(500_000, 400, -8, 974)),
+        Err(GsnError::StalePriceFeed)
+    );
+    // A confidence interval over 1% of the price
+    assert_eq!(
+        load(&key, price_account(500_000, 5_001, -8, 990)),
+        Err(GsnError::InvalidPriceFeed)
+    );
+    assert_eq!(load(&key, price_account(-1, 0, -8, 990)), Err(GsnError::InvalidPriceFeed));
+    let mut halted = price_account(500_000, 400, -8, 990);
+    halted[224] = 2;
+    assert_eq!(load(&key, halted), Err(GsnError::InvalidPriceFeed));
+    // An account of the other oracle
+    assert_eq!(
+        load(&key, aggregator_account(5_000_000, 0, 9, 990)),
+        Err(GsnError::InvalidPriceFeed)
+    );
+}
+
+#[test]
+fn test_switchboard_price_feed() {
+    let key = Pubkey::new_unique();
+    let feed = PriceFeed {
+        kind: OracleKind::Switchboard,
+        ..usdc_feed(key)
+    };
+    let price = unpack_price(OracleKind::Switchboard, &aggregator_account(5_000_000, 20_000, 9, 995)).unwrap();
+    assert_eq!(
+        price,
+        OraclePrice {
+            price: 5_000_000,
+            conf: 20_000,
+            expo: -9,
+            publish_slot: 995,
+        }
+    );
+    // The same 0.005 SOL per token as the Pyth feed
+    assert_eq!(price.lamports_to_tokens(50_000, 6), Some(10_000));
+
+    let load = |mut data: Vec<u8>| {
+        let (mut lamports, owner) = (1, Pubkey::new_unique());
+        let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        load_price(&account, &feed, SLOT)
+    };
+    assert_eq!(load(aggregator_account(5_000_000, 20_000, 9, 995)), Ok(price));
+    assert_eq!(
+        load(aggregator_account(5_000_000, 20_000, 9, 900)),
+        Err(GsnError::StalePriceFeed)
+    );
+    assert_eq!(
+        load(aggregator_account(5_000_000, 60_000, 9, 995)),
+        Err(GsnError::InvalidPriceFeed)
+    );
+    assert_eq!(load(aggregator_account(0, 0, 9, 995)), Err(GsnError::InvalidPriceFeed));
+    assert_eq!(load(price_account(500_000, 400, -8, 990)), Err(GsnError::InvalidPriceFeed));
+}
+
+#[test]
+fn test_submit_token_fee_at_oracle_price() {
+    set_syscall_stubs(Box::new(ClockStubs));
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    // target, sender, receiver, fee payer, GSN state, config, price feed
+    let keys: Vec<Pubkey> = (0..7).map(|_| Pubkey::new_unique()).collect();
+    let mut config = GsnConfig::new(keys[4]);
+    config.governance = Some(GovernanceConfig {
+        authority: Pubkey::new_unique(),
+        fee_mode: FeeMode::Fixed(50_000),
+        allowed_tokens: SortedMap::new(),
+    });
+    config.price_feeds.insert(mint, usdc_feed(keys[6]));
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[5];
+    gsn.token_consumer.insert((keys[1], mint), 25_000);
+    let mut data = [
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![0u8; 2048],
+        vec![0u8; 1024],
+        price_account(500_000, 400, -8, 990),
+    ];
+    gsn.serialize(&mut data[4]).unwrap();
+    config.serialize(&mut data[5]).unwrap();
+    let mut lamports = [1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000, 1];
+
+    let mut submit = |nonce, with_feed: bool| {
+        let len = if with_feed { 7 } else { 6 };
+        let accounts: Vec<AccountInfo> = keys[..len]
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 1, index < 6, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        let args = SubmitTokenFeeArgs {
+            amount: 1_000,
+            nonce,
+            lane: 0,
+            mint: mint.to_bytes(),
+        };
+        Processor::process_submit_token_fee(&program_id, args, &accounts)
+    };
+    assert_eq!(submit(0, false), Err(GsnError::InvalidPriceFeed.into()));
+    submit(0, true).unwrap();
+    submit(1, true).unwrap();
+
+    // Each 50,000 lamport fee cost 10,000 base units
+    let gsn = GsnInfo::deserialize(&data[4]).unwrap();
+    assert_eq!(gsn.token_consumer.get(&(keys[1], mint)), Some(&5_000));
+    assert_eq!(gsn.token_executor.get(&(keys[3], mint)), Some(&20_000));
+}
@@ -0,0 +1,104 @@
+// Tests for pruning the executor records of past submissions
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SetRecordRetentionArgs},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_record_instructions_decode() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[63, 16, 0]).unwrap(),
+        GsnInstruction::SetRecordRetention(SetRecordRetentionArgs { retention: 16 })
+    );
+    assert!(GsnInstruction::deserialize(&[63, 16]).is_err());
+
+    let (program_id, gsn, config) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let prune = instruction::prune_records(&program_id, &gsn, &config);
+    assert_eq!(
+        GsnInstruction::deserialize(&prune.data).unwrap(),
+        GsnInstruction::PruneRecords
+    );
+    // Permissionless: nobody signs
+    assert!(prune.accounts.iter().all(|account| !account.is_signer));
+}
+
+#[test]
+fn test_prune_keeps_the_latest_records_of_every_lane() {
+    let (first, second, executor) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    for nonce in 0..5 {
+        gsn.record_lane_transaction_executor(&first, 0, nonce, &executor);
+    }
+    for nonce in [3, 40, 7] {
+        gsn.record_lane_transaction_executor(&first, 2, nonce, &executor);
+    }
+    gsn.record_lane_transaction_executor(&second, 0, 0, &executor);
+
+    assert_eq!(gsn.prune_transaction_executors(2), 4);
+    let kept: Vec<_> = gsn.transaction_executor.keys().copied().collect();
+    assert_eq!(
+        kept,
+        [(first, 0, 3), (first, 0, 4), (first, 2, 7), (first, 2, 40), (second, 0, 0)]
+    );
+
+    // Nothing is left to prune
+    assert_eq!(gsn.prune_transaction_executors(2), 0);
+}
+
+/// `PruneRecords` of a state account holding `gsn_data`, with `config` at
+/// `config_key`
+fn prune(gsn_data: &mut Vec<u8>, config: &GsnConfig, config_key: Pubkey) -> Result<(), ProgramError> {
+    let program_id = Pubkey::new_unique();
+    let keys = [Pubkey::new_unique(), config_key];
+    let mut config_data = vec![0u8; 1024];
+    config.serialize(&mut config_data).unwrap();
+    let mut data = [gsn_data.clone(), config_data];
+    let mut lamports = [1; 2];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, false, index == 0, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    let result = Processor::process_prune_records(&program_id, &accounts);
+    drop(accounts);
+    *gsn_data = data[0].clone();
+    result
+}
+
+#[test]
+fn test_prune_records_frees_room() {
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.config = Pubkey::new_unique();
+    for nonce in 0..10 {
+        gsn.record_lane_transaction_executor(&consumer, 0, nonce, &executor);
+    }
+    let mut gsn_data = vec![0u8; 2048];
+    gsn.serialize(&mut gsn_data).unwrap();
+    let remaining = |data: &[u8]| GsnInfo::deserialize(data).unwrap().remaining_capacity().unwrap();
+    let before = remaining(&gsn_data);
+
+    // Without a retention, everything is kept
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    assert_eq!(prune(&mut gsn_data, &config, gsn.config), Ok(()));
+    assert_eq!(GsnInfo::deserialize(&gsn_data).unwrap().transaction_executor.len(), 10);
+
+    config.record_retention = 3;
+    assert_eq!(
+        prune(&mut gsn_data, &config, Pubkey::new_unique()),
+        Err(GsnError::InvalidConfigAccount.into())
+    );
+    assert_eq!(prune(&mut gsn_data, &config, gsn.config), Ok(()));
+    let pruned = GsnInfo::deserialize(&gsn_data).unwrap();
+    assert_eq!(pruned.transaction_executor.len(), 3);
+    assert_eq!(pruned.get_lane_transaction_executor(&consumer, 0, 9), Some(&executor));
+    assert!(remaining(&gsn_data) > before);
+}
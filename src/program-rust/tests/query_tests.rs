@@ -0,0 +1,208 @@
+// Tests for the read-side query instructions answering with return data
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GetFeeQuoteArgs, GsnInstruction, QueryAccountArgs},
+    query::{ConsumerBalance, ExecutorBalance, FeeEstimate},
+    state::{ConsumerAccount, FreeTier, GsnConfig, GsnInfo, SandboxConfig},
+    Processor,
+};
+use std::sync::Mutex;
+
+/// Return data set by the last instruction
+static RETURN_DATA: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Serializes the tests, which share `RETURN_DATA`
+static SERIAL: Mutex<()> = Mutex::new(());
+
+struct ReturnDataStubs;
+
+impl SyscallStubs for ReturnDataStubs {
+    fn sol_set_return_data(&self, data: &[u8]) {
+        *RETURN_DATA.lock().unwrap() = data.to_vec();
+    }
+}
+
+/// Run `process` over the GSN state and config accounts and decode its answer
+fn query<T: BorshDeserialize>(
+    gsn: &GsnInfo,
+    config: &GsnConfig,
+    process: impl Fn(&Pubkey, &[AccountInfo]) -> Result<(), ProgramError>,
+) -> Result<T, ProgramError> {
+    let _serial = SERIAL.lock().unwrap_or_else(|error| error.into_inner());
+    set_syscall_stubs(Box::new(ReturnDataStubs));
+    RETURN_DATA.lock().unwrap().clear();
+
+    let program_id = Pubkey::new_unique();
+    let (gsn_key, config_key) = (Pubkey::new_unique(), gsn.config);
+    let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+    gsn.serialize(&mut gsn_data).unwrap();
+    config.serialize(&mut config_data).unwrap();
+    let (mut gsn_lamports, mut config_lamports) = (1, 1);
+    let accounts = [
+        AccountInfo::new(&gsn_key, false, false, &mut gsn_lamports, &mut gsn_data, &program_id, false, 0),
+        AccountInfo::new(&config_key, false, false, &mut config_lamports, &mut config_data, &program_id, false, 0),
+    ];
+    process(&program_id, &accounts)?;
+    let data = RETURN_DATA.lock().unwrap().clone();
+    Ok(T::try_from_slice(&data).unwrap())
+}
+
+fn state() -> (GsnInfo, GsnConfig) {
+    let mut gsn = GsnInfo::new();
+    gsn.config = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(Pubkey::new_unique());
+    (gsn, config)
+}
+
+#[test]
+fn test_query_instructions_decode() {
+    let (program_id, gsn, config, key) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let args = QueryAccountArgs {
+        account: key.to_bytes(),
+    };
+    let balance = instruction::get_consumer_balance(&program_id, &gsn, &key);
+    assert_eq!(
+        GsnInstruction::deserialize(&balance.data).unwrap(),
+        GsnInstruction::GetConsumerBalance(args.clone())
+    );
+    assert!(balance.accounts.iter().all(|account| !account.is_writable));
+    let earnings = instruction::get_executor_balance(&program_id, &gsn, &key);
+    assert_eq!(
+        GsnInstruction::deserialize(&earnings.data).unwrap(),
+        GsnInstruction::GetExecutorBalance(args)
+    );
+    let quote = instruction::get_fee_quote(&program_id, &gsn, &config, &key, 1_000);
+    assert_eq!(
+        GsnInstruction::deserialize(&quote.data).unwrap(),
+        GsnInstruction::GetFeeQuote(GetFeeQuoteArgs {
+            amount: 1_000,
+            consumer: key,
+        })
+    );
+}
+
+#[test]
+fn test_get_consumer_balance() {
+    let (mut gsn, config) = state();
+    let (consumer, unknown) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let usdc = Pubkey::new_unique();
+    gsn.add_consumer(consumer, 5_000);
+    gsn.token_consumer.insert((consumer, usdc), 80);
+    gsn.increment_nonce(&consumer);
+
+    let get = |key: Pubkey| {
+        move |program_id: &Pubkey, accounts: &[AccountInfo]| {
+            Processor::process_get_consumer_balance(program_id, &key, accounts)
+        }
+    };
+    assert_eq!(
+        query(&gsn, &config, get(consumer)),
+        Ok(ConsumerBalance {
+            balance: Some(5_000),
+            next_nonce: 1,
+            free_txs_used: 0,
+            token_balances: vec![(usdc, 80)],
+        })
+    );
+    assert_eq!(
+        query(&gsn, &config, get(unknown)),
+        Ok(ConsumerBalance {
+            balance: None,
+            next_nonce: 0,
+            free_txs_used: 0,
+            token_balances: vec![],
+        })
+    );
+
+    // The balance of a consumer with a ledger account lives in it
+    gsn.open_consumer_account(&mut ConsumerAccount {
+        consumer,
+        ..ConsumerAccount::default()
+    });
+    assert_eq!(
+        query::<ConsumerBalance>(&gsn, &config, get(consumer)),
+        Err(GsnError::MissingLedgerAccount.into())
+    );
+}
+
+#[test]
+fn test_get_executor_balance() {
+    let (mut gsn, config) = state();
+    let (executor, payout) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let usdc = Pubkey::new_unique();
+    gsn.add_executor(executor, 700);
+    gsn.token_executor.insert((executor, usdc), 30);
+    gsn.set_payout_address(executor, payout);
+
+    let answer = query(&gsn, &config, |program_id, accounts| {
+        Processor::process_get_executor_balance(program_id, &executor, accounts)
+    });
+    assert_eq!(
+        answer,
+        Ok(ExecutorBalance {
+            earned: 700,
+            payout_address: Some(payout),
+            token_earned: vec![(usdc, 30)],
+        })
+    );
+}
+
+#[test]
+fn test_get_fee_quote() {
+    let (mut gsn, mut config) = state();
+    let consumer = Pubkey::new_unique();
+    config.protocol_fee_bps = 1_000;
+    let get = |program_id: &Pubkey, accounts: &[AccountInfo]| {
+        Processor::process_get_fee_quote(program_id, &consumer, 1_000, accounts)
+    };
+
+    let fee = config.calculate_fee(1_000);
+    assert_eq!(
+        query(&gsn, &config, get),
+        Ok(FeeEstimate {
+            fee,
+            charged: Some(consumer),
+            executor_fee: fee - fee / 10,
+        })
+    );
+
+    // The free tier pays while its subsidy covers the fee
+    let subsidy = Pubkey::new_unique();
+    config.free_tier = Some(FreeTier {
+        free_tx_quota: 1,
+        subsidy,
+    });
+    let answer: FeeEstimate = query(&gsn, &config, get).unwrap();
+    assert_eq!(answer.charged, Some(consumer));
+    gsn.add_consumer(subsidy, fee);
+    let answer: FeeEstimate = query(&gsn, &config, get).unwrap();
+    assert_eq!(answer.charged, Some(subsidy));
+
+    // Sandbox mode without a subsidy waives it
+    config.sandbox = Some(SandboxConfig {
+        subsidy: None,
+        reset_epoch: 0,
+    });
+    assert_eq!(
+        query(&gsn, &config, get),
+        Ok(FeeEstimate {
+            fee: 0,
+            charged: None,
+            executor_fee: 0,
+        })
+    );
+}
@@ -0,0 +1,140 @@
+// Tests for the rate limit of consumers' submissions
+
+mod common;
+
+use common::Submit;
+use solana_program::{
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SetRateLimitArgs},
+    state::{GsnInfo, RateLimit, RateWindow},
+    Processor,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Slot of the stubbed clock
+static SLOT: AtomicU64 = AtomicU64::new(0);
+
+struct ClockStubs;
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT.load(Ordering::SeqCst),
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+#[test]
+fn test_set_rate_limit_decode() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let ix = instruction::set_rate_limit(&keys[0], &keys[1], &keys[2], &keys[3], 5, 150);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data).unwrap(),
+        GsnInstruction::SetRateLimit(SetRateLimitArgs {
+            max_submissions: 5,
+            window_slots: 150,
+        })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data[..16]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // Accounts written before rate limits existed have none
+    let mut gsn = GsnInfo::new();
+    gsn.set_rate_limit(RateLimit {
+        max_submissions: 5,
+        window_slots: 150,
+    });
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let legacy_len = gsn.serialized_size() - 144;
+    let decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.rate_limit, RateLimit::default());
+    assert_eq!(GsnInfo::deserialize(&data).unwrap().rate_limit.max_submissions, 5);
+}
+
+impl Submit {
+    /// `SetRateLimit` signed by `signer`
+    fn set_rate_limit(&mut self, signer: Pubkey, max_submissions: u64, window_slots: u64) -> ProgramResult {
+        let args = SetRateLimitArgs {
+            max_submissions,
+            window_slots,
+        };
+        self.govern(signer, |program_id, accounts| {
+            Processor::process_set_rate_limit(program_id, args, accounts)
+        })
+    }
+}
+
+#[test]
+fn test_set_rate_limit_requires_governance() {
+    set_syscall_stubs(Box::new(ClockStubs));
+    let mut setup = Submit::new(|_, _, _| {});
+    assert_eq!(
+        setup.set_rate_limit(Pubkey::new_unique(), 2, 10),
+        Err(GsnError::Unauthorized.into())
+    );
+    // A limit needs a window to count in
+    assert_eq!(
+        setup.set_rate_limit(setup.authority, 2, 0),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    setup.set_rate_limit(setup.authority, 2, 10).unwrap();
+    assert_eq!(
+        setup.state().rate_limit,
+        RateLimit {
+            max_submissions: 2,
+            window_slots: 10,
+        }
+    );
+}
+
+#[test]
+fn test_submissions_beyond_the_window_limit_fail() {
+    set_syscall_stubs(Box::new(ClockStubs));
+    let mut setup = Submit::new(|_, _, _| {});
+    // Without a limit, nothing is counted
+    SLOT.store(100, Ordering::SeqCst);
+    setup.submit(0).unwrap();
+    assert!(setup.state().rate_windows.is_empty());
+
+    setup.set_rate_limit(setup.authority, 2, 10).unwrap();
+    setup.submit(1).unwrap();
+    SLOT.store(109, Ordering::SeqCst);
+    setup.submit(2).unwrap();
+    assert_eq!(setup.submit(3), Err(GsnError::RateLimited.into()));
+    assert_eq!(
+        setup.state().rate_windows.get(&setup.keys[1]),
+        Some(&RateWindow {
+            start_slot: 100,
+            submissions: 2,
+        })
+    );
+
+    // The next window starts with the next submission
+    SLOT.store(110, Ordering::SeqCst);
+    setup.submit(3).unwrap();
+    assert_eq!(
+        setup.state().rate_windows.get(&setup.keys[1]),
+        Some(&RateWindow {
+            start_slot: 110,
+            submissions: 1,
+        })
+    );
+
+    // Lifting the limit drops the windows
+    setup.set_rate_limit(setup.authority, 0, 0).unwrap();
+    assert!(setup.state().rate_windows.is_empty());
+    setup.submit(4).unwrap();
+}
@@ -0,0 +1,194 @@
+// Tests for the execution receipts written by submissions
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solgsn::{
+    error::GsnError,
+    instruction,
+    state::{ExecutionReceipt, FeeEscrow, FeeMode, GsnConfig, GsnInfo},
+    Processor,
+};
+
+/// Slot of the stubbed clock
+const SLOT: u64 = 42;
+
+/// Serves the default rent and a clock at `SLOT`
+struct SysvarStubs;
+
+impl SyscallStubs for SysvarStubs {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+struct Deployment {
+    program_id: Pubkey,
+    gsn_key: Pubkey,
+    consumer: Pubkey,
+    executor: Pubkey,
+    gsn: Vec<u8>,
+    config: Vec<u8>,
+}
+
+impl Deployment {
+    fn new() -> Self {
+        let consumer = Pubkey::new_unique();
+        let mut gsn = GsnInfo::new();
+        gsn.config = Pubkey::new_unique();
+        gsn.add_consumer(consumer, 1_000_000);
+        let mut config = GsnConfig::new(Pubkey::new_unique());
+        config.initialize_governance(Pubkey::new_unique());
+        config.governance.as_mut().unwrap().fee_mode = FeeMode::Fixed(5_000);
+
+        let (mut gsn_data, mut config_data) = (vec![0u8; 2048], vec![0u8; 1024]);
+        gsn.serialize(&mut gsn_data).unwrap();
+        config.serialize(&mut config_data).unwrap();
+        Self {
+            program_id: Pubkey::new_unique(),
+            gsn_key: Pubkey::new_unique(),
+            consumer,
+            executor: Pubkey::new_unique(),
+            gsn: gsn_data,
+            config: config_data,
+        }
+    }
+
+    fn receipt_key(&self, nonce: u64) -> Pubkey {
+        instruction::receipt(&self.program_id, &self.gsn_key, &self.consumer, 0, nonce).pubkey
+    }
+
+    /// `SubmitTransaction` with nonce 0, passing the receipt's key, lamports,
+    /// data, owner and writability after the other accounts
+    fn submit(&mut self, receipt: Option<(&Pubkey, u64, &mut Vec<u8>, &Pubkey, bool)>) -> ProgramResult {
+        set_syscall_stubs(Box::new(SysvarStubs));
+        let config_key = GsnInfo::deserialize(&self.gsn).unwrap().config;
+        let system_program = system_program::id();
+        let receiver = Pubkey::new_unique();
+        let mut lamports = [1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000];
+        let mut empty = [vec![], vec![], vec![]];
+        let [target_data, sender_data, receiver_data] = &mut empty;
+        let [target_lamports, sender_lamports, receiver_lamports, executor_lamports, gsn_lamports, config_lamports] =
+            &mut lamports;
+        let mut executor_data = vec![];
+        let mut accounts = vec![
+            AccountInfo::new(&system_program, false, false, target_lamports, target_data, &system_program, true, 0),
+            AccountInfo::new(&self.consumer, true, true, sender_lamports, sender_data, &system_program, false, 0),
+            AccountInfo::new(&receiver, false, true, receiver_lamports, receiver_data, &system_program, false, 0),
+            AccountInfo::new(&self.executor, true, true, executor_lamports, &mut executor_data, &system_program, false, 0),
+            AccountInfo::new(&self.gsn_key, false, true, gsn_lamports, &mut self.gsn, &self.program_id, false, 0),
+            AccountInfo::new(&config_key, false, false, config_lamports, &mut self.config, &self.program_id, false, 0),
+        ];
+        let mut receipt_lamports = receipt.as_ref().map_or(0, |receipt| receipt.1);
+        if let Some((key, _, data, owner, is_writable)) = receipt {
+            accounts.push(AccountInfo::new(key, false, is_writable, &mut receipt_lamports, data, owner, false, 0));
+        }
+        Processor::process_submit_tx(&self.program_id, 1_000, 0, 0, None, None, 0, None, &accounts)
+    }
+}
+
+#[test]
+fn test_receipt_fits_its_account() {
+    let receipt = ExecutionReceipt {
+        is_initialized: true,
+        gsn_account: Pubkey::new_unique(),
+        consumer: Pubkey::new_unique(),
+        executor: Pubkey::new_unique(),
+        target_program: Pubkey::new_unique(),
+        lane: 3,
+        nonce: 9,
+        amount: 1_000,
+        fee: 5_000,
+        tip: 100,
+        slot: SLOT,
+    };
+    let mut data = vec![0; ExecutionReceipt::LEN];
+    receipt.serialize(&mut data).unwrap();
+    assert_eq!(ExecutionReceipt::deserialize(&data).unwrap(), receipt);
+
+    let mut short = vec![0; ExecutionReceipt::LEN - 1];
+    assert_eq!(
+        receipt.serialize(&mut short),
+        Err(ProgramError::AccountDataTooSmall)
+    );
+}
+
+#[test]
+fn test_submission_writes_its_receipt() {
+    let mut deployment = Deployment::new();
+    let (program_id, receipt_key) = (deployment.program_id, deployment.receipt_key(0));
+
+    // The account is created empty by the system program
+    let mut data = vec![0; ExecutionReceipt::LEN];
+    deployment
+        .submit(Some((&receipt_key, 0, &mut data, &program_id, true)))
+        .unwrap();
+    assert_eq!(
+        ExecutionReceipt::deserialize(&data).unwrap(),
+        ExecutionReceipt {
+            is_initialized: true,
+            gsn_account: deployment.gsn_key,
+            consumer: deployment.consumer,
+            executor: deployment.executor,
+            target_program: system_program::id(),
+            lane: 0,
+            nonce: 0,
+            amount: 1_000,
+            fee: 5_000,
+            tip: 0,
+            slot: SLOT,
+        }
+    );
+
+    // Without its account, the submission writes no receipt
+    let mut deployment = Deployment::new();
+    assert_eq!(deployment.submit(None), Ok(()));
+}
+
+#[test]
+fn test_receipt_account_checks() {
+    // The runtime reverts what a failed submission wrote, so each case
+    // starts from the initial state, nonce 0 unused
+    let mut deployment = Deployment::new();
+    let (program_id, receipt_key) = (deployment.program_id, deployment.receipt_key(0));
+    let initial = deployment.gsn.clone();
+
+    // Read-only
+    let mut data = vec![0; ExecutionReceipt::LEN];
+    assert_eq!(
+        deployment.submit(Some((&receipt_key, 0, &mut data, &program_id, false))),
+        Err(GsnError::AccountNotWritable.into())
+    );
+
+    // An existing account of another program, or of another kind
+    let other_program = Pubkey::new_unique();
+    deployment.gsn = initial.clone();
+    assert_eq!(
+        deployment.submit(Some((&receipt_key, 1, &mut data, &other_program, true))),
+        Err(GsnError::InvalidAccountOwner.into())
+    );
+    let mut escrow = vec![0; FeeEscrow::LEN];
+    FeeEscrow::default().serialize(&mut escrow).unwrap();
+    deployment.gsn = initial;
+    assert_eq!(
+        deployment.submit(Some((&receipt_key, 1, &mut escrow, &program_id, true))),
+        Err(GsnError::InvalidAccountType.into())
+    );
+}
@@ -0,0 +1,113 @@
+// Tests for the guard against target programs reentering the program
+
+mod common;
+
+use common::Submit;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey, system_program,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::WithdrawArgs,
+    state::GsnInfo,
+    Processor,
+};
+
+/// Result of `process` over accounts with `keys` and `data`, owned by the
+/// program, of which only the `signer`th signs
+fn run(
+    program_id: &Pubkey,
+    keys: &[Pubkey],
+    mut data: Vec<Vec<u8>>,
+    signer: usize,
+    process: impl Fn(&Pubkey, &[AccountInfo]) -> ProgramResult,
+) -> ProgramResult {
+    let mut lamports = vec![1_000_000; keys.len()];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == signer, true, lamports, data, program_id, false, 0)
+        })
+        .collect();
+    process(program_id, &accounts)
+}
+
+/// A deployment whose state records an instruction in flight when `in_flight`
+fn deployment(in_flight: bool) -> Submit {
+    Submit::new(|gsn, _, _| gsn.set_in_flight(in_flight))
+}
+
+#[test]
+fn test_submission_targeting_the_program_is_rejected() {
+    let mut setup = deployment(false);
+    setup.keys[0] = setup.program_id;
+    assert_eq!(setup.submit(0), Err(GsnError::ReentrantCall.into()));
+
+    setup.keys[0] = Pubkey::new_unique();
+    setup.submit(0).unwrap();
+}
+
+#[test]
+fn test_state_writes_fail_while_in_flight() {
+    let mut setup = deployment(true);
+    assert_eq!(setup.submit(0), Err(GsnError::ReentrantCall.into()));
+
+    // Withdraw: state, consumer, vault, system program
+    let program_id = setup.program_id;
+    let keys = [
+        setup.keys[4],
+        setup.keys[1],
+        GsnInfo::find_vault_address(&program_id, &setup.keys[4]).0,
+        Pubkey::default(),
+    ];
+    let mut data = [setup.data[4].clone(), vec![], vec![], vec![]];
+    let mut lamports = [1, 0, 1_000_000, 1];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == 1, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    assert_eq!(
+        Processor::process_withdraw(&program_id, WithdrawArgs { amount: 1_000 }, &accounts),
+        Err(GsnError::ReentrantCall.into())
+    );
+}
+
+#[test]
+fn test_ledger_accounts_and_migrations_fail_while_in_flight() {
+    let setup = deployment(true);
+    let program_id = setup.program_id;
+
+    // OpenConsumerAccount and OpenExecutorAccount: state, owner, ledger
+    // account, system program
+    let keys = [
+        setup.keys[4],
+        setup.keys[1],
+        Pubkey::new_unique(),
+        system_program::id(),
+    ];
+    let data = vec![setup.data[4].clone(), vec![], vec![], vec![]];
+    assert_eq!(
+        run(&program_id, &keys, data.clone(), 1, Processor::process_open_consumer_account),
+        Err(GsnError::ReentrantCall.into())
+    );
+    assert_eq!(
+        run(&program_id, &keys, data, 1, Processor::process_open_executor_account),
+        Err(GsnError::ReentrantCall.into())
+    );
+
+    // MigrateState: state, config, authority
+    let keys = [setup.keys[4], setup.keys[5], setup.authority];
+    let data = vec![setup.data[4].clone(), setup.data[5].clone(), vec![]];
+    assert_eq!(
+        run(&program_id, &keys, data, 2, Processor::process_migrate_state),
+        Err(GsnError::ReentrantCall.into())
+    );
+}
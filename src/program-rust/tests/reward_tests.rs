@@ -0,0 +1,210 @@
+// Tests for the distribution of the treasury to staked executors
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, DistributeRewardsArgs, GsnInstruction},
+    state::{EpochStats, ExecutorStake, ExecutorVolume, GsnConfig, GsnInfo},
+    Processor,
+};
+
+struct ClockStubs;
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            epoch: 5,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+#[test]
+fn test_distribute_rewards_decode() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let ix = instruction::distribute_rewards(&keys[0], &keys[1], &keys[2], &keys[3], 900, &[keys[3]]);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data).unwrap(),
+        GsnInstruction::DistributeRewards(DistributeRewardsArgs { amount: 900 })
+    );
+    assert_eq!(ix.accounts.len(), 4);
+    assert_eq!(
+        GsnInstruction::deserialize(&ix.data[..8]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    // Accounts written before rewards existed rewarded no epoch
+    let mut gsn = GsnInfo::new();
+    gsn.set_rewarded_epoch(4);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let legacy_len = gsn.serialized_size() - 29;
+    assert_eq!(GsnInfo::deserialize(&data[..legacy_len]).unwrap().rewarded_epoch, None);
+    assert_eq!(GsnInfo::deserialize(&data).unwrap().rewarded_epoch, Some(4));
+}
+
+fn volume(executions: u64) -> ExecutorVolume {
+    ExecutorVolume { executions, fees: 0 }
+}
+
+/// Executors of epoch 4: `rewarded` are registered with a bonded stake of
+/// 100 and 200 lamports, `others` are unregistered, unbonding and
+/// blacklisted
+fn epoch_4(gsn: &mut GsnInfo, rewarded: [Pubkey; 2], others: [Pubkey; 3]) {
+    let mut stats = EpochStats::new(4);
+    for (executor, stake) in rewarded.iter().zip([100, 200]) {
+        gsn.register_executor(*executor);
+        gsn.executor_stakes_mut().insert(
+            *executor,
+            ExecutorStake {
+                amount: stake,
+                unbonding_at: 0,
+            },
+        );
+        stats.executor_volumes.insert(*executor, volume(3));
+    }
+    for (index, executor) in others.iter().enumerate() {
+        if index != 0 {
+            gsn.register_executor(*executor);
+        }
+        let unbonding_at = if index == 1 { 10 } else { 0 };
+        gsn.executor_stakes_mut().insert(
+            *executor,
+            ExecutorStake {
+                amount: 1_000,
+                unbonding_at,
+            },
+        );
+        stats.executor_volumes.insert(*executor, volume(5));
+    }
+    gsn.blacklist_address(others[2]);
+    let gsn_stats = gsn.stats_mut();
+    gsn_stats.enabled = true;
+    gsn_stats.current = stats;
+}
+
+#[test]
+fn test_shares_weigh_stake_and_executions() {
+    let rewarded = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let others = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut gsn = GsnInfo::new();
+    epoch_4(&mut gsn, rewarded, others);
+
+    let mut shares = gsn.reward_shares(&gsn.stats.current, 0, 900);
+    shares.sort_by_key(|(_, share)| *share);
+    assert_eq!(shares, vec![(rewarded[0], 300), (rewarded[1], 600)]);
+
+    // Executions count as much as stake
+    gsn.stats_mut().current.executor_volumes.insert(rewarded[0], volume(6));
+    let shares = gsn.reward_shares(&gsn.stats.current, 0, 900);
+    assert!(shares.iter().all(|(_, share)| *share == 450));
+    // Stakes below the minimum aren't bonded
+    assert_eq!(gsn.reward_shares(&gsn.stats.current, 150, 900), vec![(rewarded[1], 900)]);
+}
+
+/// GSN state and config accounts governed by `authority`
+struct Deployment {
+    program_id: Pubkey,
+    authority: Pubkey,
+    keys: [Pubkey; 2],
+    data: [Vec<u8>; 2],
+}
+
+impl Deployment {
+    fn new(gsn: impl FnOnce(&mut GsnInfo)) -> Self {
+        set_syscall_stubs(Box::new(ClockStubs));
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let authority = Pubkey::new_unique();
+        let mut state = GsnInfo::new();
+        state.config = keys[1];
+        state.credit_treasury(1_000);
+        gsn(&mut state);
+        let mut config = GsnConfig::new(keys[0]);
+        config.initialize_governance(authority);
+        let mut data = [vec![0u8; 2048], vec![0u8; 1024]];
+        state.serialize(&mut data[0]).unwrap();
+        config.serialize(&mut data[1]).unwrap();
+        Self {
+            program_id: Pubkey::new_unique(),
+            authority,
+            keys,
+            data,
+        }
+    }
+
+    fn state(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.data[0]).unwrap()
+    }
+
+    /// `DistributeRewards` signed by `signer`
+    fn distribute(&mut self, signer: Pubkey, amount: u64) -> ProgramResult {
+        let program_id = self.program_id;
+        let keys = [self.keys[0], self.keys[1], signer];
+        let mut lamports = [1, 1, 0];
+        let (gsn, config) = self.data.split_at_mut(1);
+        let mut signer_data = vec![];
+        let data = [&mut gsn[0], &mut config[0], &mut signer_data];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data)
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 2, index == 0, lamports, data, &program_id, false, 0)
+            })
+            .collect();
+        Processor::process_distribute_rewards(&program_id, DistributeRewardsArgs { amount }, &accounts)
+    }
+}
+
+#[test]
+fn test_rewards_are_credited_once_per_epoch() {
+    let rewarded = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let others = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut deployment = Deployment::new(|gsn| {
+        epoch_4(gsn, rewarded, others);
+        gsn.executor_mut().insert(rewarded[1], 50);
+    });
+    assert_eq!(
+        deployment.distribute(Pubkey::new_unique(), 900),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        deployment.distribute(deployment.authority, 1_001),
+        Err(ProgramError::InsufficientFunds)
+    );
+
+    // The epoch is over by the clock, its rewards are claimable like fees
+    deployment.distribute(deployment.authority, 901).unwrap();
+    let gsn = deployment.state();
+    assert_eq!(gsn.executor.get(&rewarded[0]), Some(&300));
+    assert_eq!(gsn.executor.get(&rewarded[1]), Some(&650));
+    assert!(others.iter().all(|executor| !gsn.executor.contains_key(executor)));
+    // The rounding stays in the treasury
+    assert_eq!(gsn.treasury, 100);
+    assert_eq!(gsn.rewarded_epoch, Some(4));
+
+    assert_eq!(
+        deployment.distribute(deployment.authority, 50),
+        Err(GsnError::RewardsAlreadyDistributed.into())
+    );
+}
+
+#[test]
+fn test_epoch_without_eligible_executors_is_not_rewarded() {
+    let mut deployment = Deployment::new(|gsn| gsn.set_stats_tracking(true, 4));
+    assert_eq!(
+        deployment.distribute(deployment.authority, 900),
+        Err(GsnError::NoRewardRecipients.into())
+    );
+    assert_eq!(deployment.state().treasury, 1_000);
+}
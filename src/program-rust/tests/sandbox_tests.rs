@@ -0,0 +1,84 @@
+// Tests for the devnet developer sandbox
+
+use solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_reset_keeps_subsidy_and_earnings() {
+    let subsidy = Pubkey::new_unique();
+    let consumer = Pubkey::new_unique();
+    let executor = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(subsidy, 1_000_000);
+    gsn.add_consumer(consumer, 500);
+    gsn.add_executor(executor, 50_000);
+    gsn.increment_lane_nonce(&consumer, 0);
+    gsn.increment_lane_nonce(&consumer, 3);
+    gsn.record_lane_transaction_executor(&consumer, 3, 0, &executor);
+
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.reset_registries(Some(&subsidy));
+    decoded.serialize(&mut data).unwrap();
+
+    let reset = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(reset.consumer.len(), 1);
+    assert_eq!(reset.consumer.get(&subsidy), Some(&1_000_000));
+    assert_eq!(reset.executor.get(&executor), Some(&50_000));
+    assert_eq!(reset.get_next_lane_nonce(&consumer, 0), 0);
+    assert_eq!(reset.get_next_lane_nonce(&consumer, 3), 0);
+    assert_eq!(reset.get_lane_transaction_executor(&consumer, 3, 0), None);
+}
+
+#[test]
+fn test_config_without_sandbox_decodes_as_off() {
+    let mut data = vec![0u8; 1024];
+    GsnConfig::new(Pubkey::new_unique())
+        .serialize(&mut data)
+        .unwrap();
+    assert_eq!(GsnConfig::deserialize(&data).unwrap().sandbox, None);
+}
+
+#[cfg(not(feature = "sandbox"))]
+#[test]
+fn test_set_sandbox_needs_sandbox_build() {
+    let args = solgsn::instruction::SetSandboxArgs {
+        enabled: 1,
+        has_subsidy: 0,
+        subsidy: [0; 32],
+    };
+    assert_eq!(
+        Processor::process_set_sandbox(&Pubkey::new_unique(), args, &[]),
+        Err(GsnError::SandboxUnavailable.into())
+    );
+}
+
+#[test]
+fn test_reset_needs_sandbox_mode() {
+    let program_id = Pubkey::new_unique();
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[1];
+    let mut data = [vec![0u8; 1024], vec![0u8; 1024]];
+    gsn.serialize(&mut data[0]).unwrap();
+    GsnConfig::new(keys[0]).serialize(&mut data[1]).unwrap();
+    let mut lamports = [1_000_000; 2];
+
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| {
+            AccountInfo::new(key, false, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    assert_eq!(
+        Processor::process_reset_sandbox(&program_id, &accounts),
+        Err(GsnError::SandboxUnavailable.into())
+    );
+}
@@ -1,19 +1,16 @@
 // Security tests for SolGSN program
 // These tests verify replay attack prevention, balance checks, and fee claim authorization
 
-use solgsn::{
-    error::GsnError,
-    state::{GsnInfo, FeeMode},
-};
+use solgsn::state::{GsnConfig, GsnInfo, FeeMode};
 use solana_program::pubkey::Pubkey;
 
 #[test]
 fn test_replay_attack_prevention() {
     // Test that the same nonce cannot be used twice (replay attack)
     
-    let sender_key = Pubkey::new_unique().to_string();
+    let sender_key = Pubkey::new_unique();
     let mut gsn = GsnInfo::new();
-    gsn.add_consumer(sender_key.clone(), 1000000); // 1 SOL top-up
+    gsn.add_consumer(sender_key, 1000000); // 1 SOL top-up
     
     // Test nonce validation logic
     assert_eq!(gsn.get_next_nonce(&sender_key), 0);
@@ -51,16 +48,17 @@ fn test_replay_attack_prevention() {
 fn test_insufficient_balance_check() {
     // Test that transactions fail if top-up balance doesn't cover the fee
     
-    let sender_key = Pubkey::new_unique().to_string();
+    let sender_key = Pubkey::new_unique();
     let mut gsn = GsnInfo::new();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
     
     // Set fee to 100,000 lamports
-    gsn.update_fee_params(FeeMode::Fixed(100000));
+    config.update_fee_params(FeeMode::Fixed(100000));
     
     // Add consumer with insufficient balance (only 50,000, but fee is 100,000)
-    gsn.add_consumer(sender_key.clone(), 50000);
+    gsn.add_consumer(sender_key, 50000);
     
-    let fee = gsn.calculate_fee(1000000);
+    let fee = config.calculate_fee(1000000);
     assert_eq!(fee, 100000);
     
     let balance = gsn.consumer.get(&sender_key).copied().unwrap_or(0);
@@ -76,10 +74,11 @@ fn test_underfunded_topup_account() {
     // Test various scenarios with underfunded accounts
     
     let mut gsn = GsnInfo::new();
-    gsn.update_fee_params(FeeMode::Fixed(100000));
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.update_fee_params(FeeMode::Fixed(100000));
     
-    let sender_key = Pubkey::new_unique().to_string();
-    let fee = gsn.calculate_fee(1000000);
+    let sender_key = Pubkey::new_unique();
+    let fee = config.calculate_fee(1000000);
     
     // Test 1: No balance at all
     assert!(!gsn.consumer.contains_key(&sender_key));
@@ -88,18 +87,18 @@ fn test_underfunded_topup_account() {
     assert!(balance < fee, "No balance should fail balance check");
     
     // Test 2: Balance exactly equal to fee (should pass, but edge case)
-    gsn.add_consumer(sender_key.clone(), 100000);
+    gsn.add_consumer(sender_key, 100000);
     let balance = gsn.consumer.get(&sender_key).copied().unwrap_or(0);
     assert_eq!(balance, fee);
     assert!(balance >= fee, "Balance equal to fee should pass check");
     
     // Test 3: Balance less than fee
-    gsn.consumer.insert(sender_key.clone(), 50000);
+    gsn.consumer.insert(sender_key, 50000);
     let balance = gsn.consumer.get(&sender_key).copied().unwrap_or(0);
     assert!(balance < fee, "Balance less than fee should fail check");
     
     // Test 4: Balance slightly less than fee
-    gsn.consumer.insert(sender_key.clone(), 99999);
+    gsn.consumer.insert(sender_key, 99999);
     let balance = gsn.consumer.get(&sender_key).copied().unwrap_or(0);
     assert!(balance < fee, "Balance 1 lamport less than fee should fail");
 }
@@ -110,14 +109,14 @@ fn test_unauthorized_fee_claim() {
     
     let mut gsn = GsnInfo::new();
     
-    let consumer_key = Pubkey::new_unique().to_string();
-    let executor1_key = Pubkey::new_unique().to_string();
-    let executor2_key = Pubkey::new_unique().to_string();
+    let consumer_key = Pubkey::new_unique();
+    let executor1_key = Pubkey::new_unique();
+    let executor2_key = Pubkey::new_unique();
     
     // Simulate executor1 executing a transaction
     let nonce = 0;
     gsn.record_transaction_executor(&consumer_key, nonce, &executor1_key);
-    gsn.add_executor(executor1_key.clone(), 100000);
+    gsn.add_executor(executor1_key, 100000);
     
     // Verify executor1 is recorded as the executor
     let recorded_executor = gsn.get_transaction_executor(&consumer_key, nonce);
@@ -142,14 +141,14 @@ fn test_malicious_fee_claim_attempt() {
     
     let mut gsn = GsnInfo::new();
     
-    let consumer_key = Pubkey::new_unique().to_string();
-    let legitimate_executor = Pubkey::new_unique().to_string();
-    let malicious_executor = Pubkey::new_unique().to_string();
+    let consumer_key = Pubkey::new_unique();
+    let legitimate_executor = Pubkey::new_unique();
+    let malicious_executor = Pubkey::new_unique();
     
     // Legitimate executor executes transaction
     let nonce = 0;
     gsn.record_transaction_executor(&consumer_key, nonce, &legitimate_executor);
-    gsn.add_executor(legitimate_executor.clone(), 100000);
+    gsn.add_executor(legitimate_executor, 100000);
     
     // Malicious executor tries to claim
     let malicious_balance = gsn.executor.get(&malicious_executor).copied().unwrap_or(0);
@@ -172,7 +171,7 @@ fn test_nonce_sequence_enforcement() {
     // Test that nonces must be sequential (cannot skip or reuse)
     
     let mut gsn = GsnInfo::new();
-    let consumer_key = Pubkey::new_unique().to_string();
+    let consumer_key = Pubkey::new_unique();
     
     // Initial state: nonce should be 0
     assert_eq!(gsn.get_next_nonce(&consumer_key), 0);
@@ -197,19 +196,54 @@ fn test_nonce_sequence_enforcement() {
     assert_ne!(expected_nonce, provided_nonce, "Should fail with InvalidNonce");
 }
 
+#[test]
+fn test_nonce_lanes_are_independent() {
+    // Transactions in different lanes can land in any order relative to each other
+    
+    let mut gsn = GsnInfo::new();
+    let consumer_key = Pubkey::new_unique();
+    let executor_key = Pubkey::new_unique();
+    
+    // Lane 0 is the legacy nonce
+    gsn.increment_lane_nonce(&consumer_key, 0);
+    assert_eq!(gsn.get_next_nonce(&consumer_key), 1);
+    assert_eq!(gsn.get_next_lane_nonce(&consumer_key, 0), 1);
+    
+    // Other lanes start at 0 and advance on their own
+    assert_eq!(gsn.get_next_lane_nonce(&consumer_key, 3), 0);
+    gsn.increment_lane_nonce(&consumer_key, 3);
+    gsn.increment_lane_nonce(&consumer_key, 3);
+    assert_eq!(gsn.get_next_lane_nonce(&consumer_key, 3), 2);
+    assert_eq!(gsn.get_next_lane_nonce(&consumer_key, 1), 0);
+    assert_eq!(gsn.get_next_nonce(&consumer_key), 1);
+    
+    // Replay protection is per lane
+    assert!(gsn.is_lane_nonce_used(&consumer_key, 3, 1));
+    assert!(!gsn.is_lane_nonce_used(&consumer_key, 1, 1));
+    
+    // The same nonce in two lanes records two distinct executions
+    gsn.record_lane_transaction_executor(&consumer_key, 0, 0, &executor_key);
+    gsn.record_lane_transaction_executor(&consumer_key, 3, 0, &executor_key);
+    assert_eq!(gsn.get_transaction_executor(&consumer_key, 0), Some(&executor_key));
+    assert_eq!(gsn.get_lane_transaction_executor(&consumer_key, 3, 0), Some(&executor_key));
+    assert_eq!(gsn.get_lane_transaction_executor(&consumer_key, 3, 1), None);
+    assert_eq!(gsn.transaction_executor.len(), 2);
+}
+
 #[test]
 fn test_balance_check_before_execution() {
     // Test that balance is checked BEFORE executing the transaction
     // This is critical: we must verify balance covers fee before invoking the user's transaction
     
     let mut gsn = GsnInfo::new();
-    gsn.update_fee_params(FeeMode::Fixed(100000));
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.update_fee_params(FeeMode::Fixed(100000));
     
-    let sender_key = Pubkey::new_unique().to_string();
-    let fee = gsn.calculate_fee(1000000);
+    let sender_key = Pubkey::new_unique();
+    let fee = config.calculate_fee(1000000);
     
     // Add balance that exactly covers the fee
-    gsn.add_consumer(sender_key.clone(), 100000);
+    gsn.add_consumer(sender_key, 100000);
     
     let balance = gsn.consumer.get(&sender_key).copied().unwrap_or(0);
     
@@ -217,7 +251,7 @@ fn test_balance_check_before_execution() {
     assert!(balance >= fee, "Balance should cover fee - check happens before execution");
     
     // Now reduce balance below fee
-    gsn.consumer.insert(sender_key.clone(), 50000);
+    gsn.consumer.insert(sender_key, 50000);
     let balance = gsn.consumer.get(&sender_key).copied().unwrap_or(0);
     
     // Balance check should fail BEFORE any transaction execution
@@ -225,3 +259,26 @@ fn test_balance_check_before_execution() {
     // This would cause process_submit_tx to return InsufficientBalance error
     // and the user's transaction would never be invoked
 }
+
+#[test]
+fn test_only_config_authority_controls_fees() {
+    // Governance lives in the config account, apart from balances and nonces
+
+    let gsn_key = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(gsn_key);
+    config.initialize_governance(authority);
+
+    assert!(config.is_authority(&authority));
+    assert!(!config.is_authority(&Pubkey::new_unique()));
+
+    config.update_fee_params(FeeMode::percent(100));
+    assert_eq!(config.calculate_fee(1_000_000), 10_000);
+
+    let mut data = vec![0u8; 256];
+    config.serialize(&mut data).unwrap();
+    let decoded = GsnConfig::deserialize(&data).unwrap();
+    assert_eq!(decoded.gsn_account, gsn_key);
+    assert!(decoded.is_authority(&authority));
+    assert_eq!(decoded.calculate_fee(1_000_000), 10_000);
+}
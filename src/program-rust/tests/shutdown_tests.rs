@@ -0,0 +1,60 @@
+// Tests for the terminal shutdown
+
+use solana_program::{account_info::AccountInfo, pubkey::Pubkey, system_program};
+use solgsn::{
+    error::GsnError,
+    state::{GsnConfig, GsnInfo, ShutdownState, SHUTDOWN_GRACE_SECS, SHUTDOWN_TIMELOCK_SECS},
+    Processor,
+};
+
+#[test]
+fn test_shutdown_timeline() {
+    let shutdown = ShutdownState::new(Pubkey::new_unique(), 1_000);
+    assert_eq!(shutdown.effective_at, 1_000 + SHUTDOWN_TIMELOCK_SECS);
+    assert_eq!(
+        shutdown.sweep_at,
+        shutdown.effective_at + SHUTDOWN_GRACE_SECS
+    );
+    assert!(!shutdown.is_effective(shutdown.effective_at - 1));
+    assert!(shutdown.is_effective(shutdown.effective_at));
+    assert!(!shutdown.is_sweepable(shutdown.sweep_at - 1));
+    assert!(shutdown.is_sweepable(shutdown.sweep_at));
+}
+
+#[test]
+fn test_config_without_shutdown_decodes_as_running() {
+    let mut data = vec![0u8; 1024];
+    GsnConfig::new(Pubkey::new_unique())
+        .serialize(&mut data)
+        .unwrap();
+    assert_eq!(GsnConfig::deserialize(&data).unwrap().shutdown, None);
+}
+
+#[test]
+fn test_topup_rejected_once_shutdown_initiated() {
+    let program_id = Pubkey::new_unique();
+    let mut keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    keys[4] = system_program::id();
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[2];
+    let mut config = GsnConfig::new(keys[0]);
+    config.shutdown = Some(ShutdownState::new(Pubkey::new_unique(), 0));
+    let mut data = [vec![0u8; 1024], vec![], vec![0u8; 1024], vec![], vec![]];
+    gsn.serialize(&mut data[0]).unwrap();
+    config.serialize(&mut data[2]).unwrap();
+    let mut lamports = [1_000_000, 1_000_000, 1_000_000, 0, 1];
+
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == 1, true, lamports, data, &program_id, false, 0)
+        })
+        .collect();
+    assert_eq!(
+        Processor::process_topup(&program_id, 1_000, None, &accounts),
+        Err(GsnError::ShutDown.into())
+    );
+}
@@ -0,0 +1,143 @@
+// Tests for dry runs of SubmitTransaction
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SubmitArgs},
+    query::FeeEstimate,
+    state::{FeeMode, GsnConfig, GsnInfo},
+    Processor,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+/// Return data set by the last instruction
+static RETURN_DATA: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// CPIs made so far
+static INVOKES: AtomicUsize = AtomicUsize::new(0);
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_set_return_data(&self, data: &[u8]) {
+        *RETURN_DATA.lock().unwrap() = data.to_vec();
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        _instruction: &Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        INVOKES.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn args(nonce: u64, max_fee: Option<u64>, simulate: bool) -> SubmitArgs {
+    SubmitArgs {
+        amount: 1_000,
+        nonce,
+        expiry_slot: None,
+        max_fee,
+        tip: 2_000,
+        simulate,
+        quote: None,
+    }
+}
+
+#[test]
+fn test_simulate_flag_decode() {
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let submit = |args: &SubmitArgs| {
+        instruction::submit_transaction(&keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5], args)
+    };
+    let dry_run = submit(&args(3, Some(60_000), true));
+    assert_eq!(dry_run.data.len(), 42);
+    assert_eq!(
+        GsnInstruction::deserialize(&dry_run.data).unwrap(),
+        GsnInstruction::SubmitTransaction(args(3, Some(60_000), true))
+    );
+    // Left out unless set
+    let submission = submit(&args(3, Some(60_000), false));
+    assert_eq!(submission.data, dry_run.data[..41]);
+    assert_eq!(
+        GsnInstruction::deserialize(&submission.data).unwrap(),
+        GsnInstruction::SubmitTransaction(args(3, Some(60_000), false))
+    );
+
+    let mut invalid = dry_run.data;
+    invalid[41] = 2;
+    assert_eq!(
+        GsnInstruction::deserialize(&invalid),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_dry_run_checks_and_returns_the_fee_without_executing() {
+    set_syscall_stubs(Box::new(Stubs));
+    let program_id = Pubkey::new_unique();
+    // target program, sender, receiver, fee payer, GSN state, config
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[5];
+    gsn.add_consumer(keys[1], 200_000);
+    let mut config = GsnConfig::new(keys[4]);
+    config.initialize_governance(Pubkey::new_unique());
+    config.update_fee_params(FeeMode::Fixed(50_000));
+    config.protocol_fee_bps = 1_000;
+    let mut data = [vec![], vec![], vec![], vec![], vec![0u8; 1024], vec![0u8; 1024]];
+    gsn.serialize(&mut data[4]).unwrap();
+    config.serialize(&mut data[5]).unwrap();
+    let state = data[4].clone();
+    let mut lamports = [1, 1_000_000, 0, 1_000_000, 1, 1];
+
+    let mut submit = |args: SubmitArgs| {
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        RETURN_DATA.lock().unwrap().clear();
+        let result = Processor::process_simulate_submit(&program_id, args, &accounts);
+        let written = accounts[4].data.borrow().to_vec();
+        (result, written)
+    };
+
+    let (result, written) = submit(args(0, None, true));
+    result.unwrap();
+    assert_eq!(
+        FeeEstimate::try_from_slice(&RETURN_DATA.lock().unwrap()).unwrap(),
+        FeeEstimate {
+            fee: 50_000,
+            charged: Some(keys[1]),
+            // 10% to the treasury, the tip in full
+            executor_fee: 47_000,
+        }
+    );
+    assert_eq!(INVOKES.load(Ordering::SeqCst), 0);
+    assert_eq!(written, state);
+
+    // The checks of a submission fail the dry run too
+    let (result, _) = submit(args(1, None, true));
+    assert_eq!(result, Err(GsnError::InvalidNonce.into()));
+    let (result, _) = submit(args(0, Some(49_999), true));
+    assert_eq!(result, Err(GsnError::FeeTooHigh.into()));
+}
@@ -0,0 +1,98 @@
+// Tests for the sorted-vector map backing the GSN state
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+use solgsn::{sorted_map::SortedMap, state::GsnInfo};
+use std::collections::BTreeMap;
+
+#[test]
+fn test_sorted_map_insert_get_remove() {
+    let mut map = SortedMap::new();
+    assert!(map.is_empty());
+
+    assert_eq!(map.insert(30u64, "c"), None);
+    assert_eq!(map.insert(10, "a"), None);
+    assert_eq!(map.insert(20, "b"), None);
+    assert_eq!(map.insert(20, "B"), Some("b"));
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get(&20), Some(&"B"));
+    assert_eq!(map.get(&25), None);
+    assert!(map.contains_key(&10));
+    assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![10, 20, 30]);
+
+    *map.get_mut(&10).unwrap() = "A";
+    assert_eq!(map.remove(&10), Some("A"));
+    assert_eq!(map.remove(&10), None);
+    assert_eq!(map.keys().copied().collect::<Vec<_>>(), vec![20, 30]);
+}
+
+#[test]
+fn test_sorted_map_entry_or_insert() {
+    let mut map = SortedMap::new();
+    *map.entry(5u64).or_insert(0) += 7;
+    *map.entry(5u64).or_insert(0) += 1;
+    assert_eq!(map.get(&5), Some(&8));
+
+    // Like BTreeMap, or_insert leaves an existing value untouched
+    map.entry(5u64).or_insert(100);
+    assert_eq!(map.get(&5), Some(&8));
+}
+
+#[test]
+fn test_sorted_map_borsh_roundtrip_and_ordering() {
+    let mut map = SortedMap::new();
+    for key in &[9u64, 3, 7, 1] {
+        map.insert(*key, key * 10);
+    }
+
+    let data = map.try_to_vec().unwrap();
+    // Encodes as Vec<(u64, u64)>
+    assert_eq!(data.len(), 4 + 4 * 16);
+    assert_eq!(SortedMap::<u64, u64>::try_from_slice(&data).unwrap(), map);
+
+    let unsorted = vec![(3u64, 0u64), (1, 0)].try_to_vec().unwrap();
+    assert!(SortedMap::<u64, u64>::try_from_slice(&unsorted).is_err());
+    let duplicate = vec![(3u64, 0u64), (3, 1)].try_to_vec().unwrap();
+    assert!(SortedMap::<u64, u64>::try_from_slice(&duplicate).is_err());
+}
+
+#[test]
+fn test_pubkey_keys_encode_smaller_than_base58_strings() {
+    let keys: Vec<Pubkey> = (0..100).map(|_| Pubkey::new_unique()).collect();
+
+    let mut sorted = SortedMap::new();
+    let mut strings = BTreeMap::new();
+    for key in &keys {
+        sorted.insert(*key, 1_000_000u64);
+        strings.insert(key.to_string(), 1_000_000u64);
+    }
+
+    let sorted_len = sorted.try_to_vec().unwrap().len();
+    let strings_len = strings.try_to_vec().unwrap().len();
+    assert_eq!(sorted_len, 4 + 100 * (32 + 8));
+    assert!(sorted_len < strings_len);
+}
+
+#[test]
+fn test_gsn_info_roundtrip() {
+    let mut gsn = GsnInfo::new();
+    let consumer = Pubkey::new_unique();
+    let executor = Pubkey::new_unique();
+    gsn.add_consumer(consumer, 500);
+    gsn.add_executor(executor, 50);
+    gsn.increment_lane_nonce(&consumer, 2);
+    gsn.record_lane_transaction_executor(&consumer, 2, 0, &executor);
+
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let decoded = GsnInfo::deserialize(&data).unwrap();
+
+    assert_eq!(decoded.consumer.get(&consumer), Some(&500));
+    assert_eq!(decoded.executor.get(&executor), Some(&50));
+    assert_eq!(decoded.get_next_lane_nonce(&consumer, 2), 1);
+    assert_eq!(
+        decoded.get_lane_transaction_executor(&consumer, 2, 0),
+        Some(&executor)
+    );
+}
@@ -0,0 +1,167 @@
+// Tests for per-consumer spending limits
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::SetSpendingLimitArgs,
+    state::{GsnConfig, GsnInfo, SpendingLimit},
+    Processor,
+};
+
+#[test]
+fn test_limit_caps_each_fee_and_the_epoch_total() {
+    let mut limit = SpendingLimit::new(5_000, 12_000);
+    limit.charge(3, 5_000).unwrap();
+    assert_eq!(limit.charge(3, 5_001), Err(GsnError::LimitExceeded));
+    limit.charge(3, 5_000).unwrap();
+    assert_eq!(limit.remaining(3), 2_000);
+    assert_eq!(limit.charge(3, 2_001), Err(GsnError::LimitExceeded));
+    limit.charge(3, 2_000).unwrap();
+    assert_eq!(limit.charge(3, 1), Err(GsnError::LimitExceeded));
+
+    // A new epoch starts from nothing spent
+    assert_eq!(limit.remaining(4), 12_000);
+    limit.charge(4, 5_000).unwrap();
+    assert_eq!(limit.spent, 5_000);
+    assert_eq!(limit.epoch, 4);
+}
+
+#[test]
+fn test_spending_limits_round_trip() {
+    let mut gsn = GsnInfo::new();
+    let consumer = Pubkey::new_unique();
+    gsn.add_consumer(consumer, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the limits existed decode them as empty (the
+    // paused flag follows them)
+    let legacy_len = gsn.serialized_size() - 217;
+    assert!(GsnInfo::deserialize(&data[..legacy_len])
+        .unwrap()
+        .spending_limits
+        .is_empty());
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.check_spending_limit_capacity(&consumer).unwrap();
+    decoded
+        .spending_limits_mut()
+        .insert(consumer, SpendingLimit::new(5_000, 50_000));
+    decoded.serialize(&mut data).unwrap();
+    let decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(
+        decoded.spending_limits.get(&consumer),
+        Some(&SpendingLimit::new(5_000, 50_000))
+    );
+    assert_eq!(decoded.consumer.get(&consumer), Some(&1_000));
+}
+
+/// Accounts of a `SetSpendingLimit`: GSN state, config and authority
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let keys: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[1];
+        let mut config = GsnConfig::new(keys[0]);
+        config.initialize_governance(keys[2]);
+        let mut data = vec![vec![0u8; 1024], vec![0u8; 1024], vec![]];
+        gsn.serialize(&mut data[0]).unwrap();
+        config.serialize(&mut data[1]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1; 3],
+            data,
+        }
+    }
+
+    fn set(
+        &mut self,
+        authority_signs: bool,
+        args: SetSpendingLimitArgs,
+    ) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 2 && authority_signs;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, false, 0)
+            })
+            .collect();
+        Processor::process_set_spending_limit(&owner, args, &accounts)
+    }
+
+    fn limit(&self, consumer: &Pubkey) -> Option<SpendingLimit> {
+        let gsn = GsnInfo::deserialize(&self.data[0]).unwrap();
+        gsn.spending_limits.get(consumer).copied()
+    }
+}
+
+fn args(consumer: &Pubkey, max_fee_per_tx: u64, max_fees_per_epoch: u64) -> SetSpendingLimitArgs {
+    SetSpendingLimitArgs {
+        max_fee_per_tx,
+        max_fees_per_epoch,
+        consumer: consumer.to_bytes(),
+    }
+}
+
+#[test]
+fn test_authority_sets_and_lifts_limits() {
+    let mut setup = Setup::new();
+    let consumer = Pubkey::new_unique();
+    setup.set(true, args(&consumer, 5_000, 50_000)).unwrap();
+    assert_eq!(
+        setup.limit(&consumer),
+        Some(SpendingLimit::new(5_000, 50_000))
+    );
+
+    setup
+        .set(true, args(&consumer, u64::MAX, u64::MAX))
+        .unwrap();
+    assert_eq!(setup.limit(&consumer), None);
+}
+
+#[test]
+fn test_changing_a_limit_keeps_the_epoch_spend() {
+    let mut setup = Setup::new();
+    let consumer = Pubkey::new_unique();
+    let mut gsn = GsnInfo::deserialize(&setup.data[0]).unwrap();
+    let mut spent = SpendingLimit::new(5_000, 50_000);
+    spent.charge(7, 5_000).unwrap();
+    gsn.spending_limits_mut().insert(consumer, spent);
+    gsn.serialize(&mut setup.data[0]).unwrap();
+
+    setup.set(true, args(&consumer, 2_000, 20_000)).unwrap();
+    let limit = setup.limit(&consumer).unwrap();
+    assert_eq!(
+        (limit.max_fee_per_tx, limit.max_fees_per_epoch),
+        (2_000, 20_000)
+    );
+    assert_eq!((limit.epoch, limit.spent), (7, 5_000));
+}
+
+#[test]
+fn test_only_the_authority_sets_limits() {
+    let mut setup = Setup::new();
+    let consumer = Pubkey::new_unique();
+    assert_eq!(
+        setup.set(false, args(&consumer, 5_000, 50_000)),
+        Err(GsnError::Unauthorized.into())
+    );
+
+    setup.keys[2] = Pubkey::new_unique();
+    assert_eq!(
+        setup.set(true, args(&consumer, 5_000, 50_000)),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(setup.limit(&consumer), None);
+}
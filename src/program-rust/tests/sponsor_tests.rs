@@ -0,0 +1,346 @@
+// Tests for sponsored submissions
+
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, RegisterSponsorArgs, SubmitArgs},
+    state::{
+        GsnConfig, GsnInfo, InstructionFilter, SponsorPolicy, MAX_SPONSOR_INSTRUCTIONS,
+        MAX_SPONSOR_PROGRAMS,
+    },
+    Processor,
+};
+
+fn encode(max_fee_per_tx: u64, programs: &[Pubkey]) -> Vec<u8> {
+    let mut data = vec![47];
+    data.extend_from_slice(&max_fee_per_tx.to_le_bytes());
+    data.push(programs.len() as u8);
+    for program in programs {
+        data.extend_from_slice(program.as_ref());
+    }
+    data
+}
+
+#[test]
+fn test_register_sponsor_decodes() {
+    let programs = vec![Pubkey::new_unique()];
+    assert_eq!(
+        GsnInstruction::deserialize(&encode(70_000, &programs)).unwrap(),
+        GsnInstruction::RegisterSponsor(RegisterSponsorArgs {
+            max_fee_per_tx: 70_000,
+            allowed_programs: programs,
+            allowed_instructions: vec![],
+            max_submissions_per_user: 0,
+        })
+    );
+
+    let too_many: Vec<Pubkey> = (0..=MAX_SPONSOR_PROGRAMS)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    assert_eq!(
+        GsnInstruction::deserialize(&encode(70_000, &too_many)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    // A policy with the most programs and instructions fits in the account
+    let policy = SponsorPolicy {
+        allowed_programs: too_many[1..].to_vec(),
+        allowed_instructions: vec![InstructionFilter::default(); MAX_SPONSOR_INSTRUCTIONS],
+        ..SponsorPolicy::default()
+    };
+    policy.serialize(&mut [0u8; SponsorPolicy::LEN]).unwrap();
+}
+
+/// Accounts of a `SubmitSponsored`: target program, sender, receiver, fee
+/// payer, GSN state, config and sponsor policy. Only the sponsor has a balance.
+struct Setup {
+    program_id: Pubkey,
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+    sponsor: Pubkey,
+}
+
+impl Setup {
+    fn new(policy: impl FnOnce(&mut SponsorPolicy)) -> Self {
+        let program_id = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..7).map(|_| Pubkey::new_unique()).collect();
+        let sponsor = Pubkey::new_unique();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(sponsor, 200_000);
+        let config = GsnConfig::new(keys[4]);
+        let mut sponsor_policy = SponsorPolicy {
+            is_initialized: true,
+            gsn_account: keys[4],
+            sponsor,
+            max_fee_per_tx: 50_000,
+            allowed_programs: vec![keys[0]],
+            allowed_instructions: vec![],
+            max_submissions_per_user: 0,
+        };
+        policy(&mut sponsor_policy);
+        let mut data = vec![vec![]; 7];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        data[6] = vec![0u8; SponsorPolicy::LEN];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        sponsor_policy.serialize(&mut data[6]).unwrap();
+        Self {
+            program_id,
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000, 1_000_000],
+            data,
+            sponsor,
+        }
+    }
+
+    fn submit(&mut self) -> Result<(), ProgramError> {
+        self.submit_nonce(0)
+    }
+
+    fn submit_nonce(&mut self, nonce: u64) -> Result<(), ProgramError> {
+        let program_id = self.program_id;
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        let args = SubmitArgs {
+            amount: 1_000,
+            nonce,
+            expiry_slot: None,
+            max_fee: None,
+            tip: 0,
+            simulate: false,
+            quote: None,
+        };
+        Processor::process_submit_sponsored(&program_id, args, &accounts)
+    }
+
+    fn gsn(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.data[4]).unwrap()
+    }
+}
+
+#[test]
+fn test_sponsor_pays_the_fee() {
+    let mut setup = Setup::new(|_| {});
+    setup.submit().unwrap();
+
+    let gsn = setup.gsn();
+    assert_eq!(gsn.consumer.get(&setup.sponsor), Some(&150_000));
+    assert_eq!(gsn.consumer.get(&setup.keys[1]), None);
+    assert_eq!(gsn.executor.get(&setup.keys[3]), Some(&50_000));
+    assert_eq!(gsn.get_next_nonce(&setup.keys[1]), 1);
+}
+
+#[test]
+fn test_sponsor_policy_is_enforced() {
+    let mut setup = Setup::new(|policy| policy.max_fee_per_tx = 49_999);
+    assert_eq!(setup.submit(), Err(GsnError::SponsorPolicyViolation.into()));
+
+    let mut setup = Setup::new(|policy| policy.allowed_programs = vec![Pubkey::new_unique()]);
+    assert_eq!(setup.submit(), Err(GsnError::SponsorPolicyViolation.into()));
+
+    let mut setup = Setup::new(|policy| policy.allowed_programs = vec![]);
+    setup.submit().unwrap();
+}
+
+#[test]
+fn test_sponsor_of_another_deployment_is_rejected() {
+    let mut setup = Setup::new(|policy| policy.gsn_account = Pubkey::new_unique());
+    assert_eq!(setup.submit(), Err(GsnError::InvalidSponsorAccount.into()));
+}
+
+fn encode_filters(filters: &[InstructionFilter], max_submissions_per_user: u64) -> Vec<u8> {
+    let mut data = vec![filters.len() as u8];
+    for filter in filters {
+        data.extend_from_slice(filter.program_id.as_ref());
+        data.push(filter.discriminator);
+    }
+    data.extend_from_slice(&max_submissions_per_user.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_register_sponsor_decodes_filters_and_quota() {
+    let filters = vec![InstructionFilter {
+        program_id: Pubkey::new_unique(),
+        discriminator: 3,
+    }];
+    let mut data = encode(70_000, &[]);
+    data.extend(encode_filters(&filters, 5));
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::RegisterSponsor(RegisterSponsorArgs {
+            max_fee_per_tx: 70_000,
+            allowed_programs: vec![],
+            allowed_instructions: filters,
+            max_submissions_per_user: 5,
+        })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&data[..data.len() - 1]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+
+    let mut data = encode(70_000, &[]);
+    data.extend(encode_filters(
+        &[InstructionFilter::default(); MAX_SPONSOR_INSTRUCTIONS + 1],
+        0,
+    ));
+    assert_eq!(
+        GsnInstruction::deserialize(&data),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_sponsor_covers_listed_instructions() {
+    let program = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let mut policy = SponsorPolicy::default();
+    assert!(policy.covers(&other, None));
+
+    policy.allowed_instructions = vec![InstructionFilter {
+        program_id: program,
+        discriminator: 7,
+    }];
+    assert!(policy.covers(&program, Some(7)));
+    assert!(!policy.covers(&program, Some(8)));
+    assert!(!policy.covers(&program, None));
+    assert!(!policy.covers(&other, Some(7)));
+
+    policy.allowed_programs = vec![other];
+    assert!(policy.covers(&other, Some(8)));
+    assert!(policy.covers(&other, None));
+}
+
+#[test]
+fn test_sponsor_quota_is_enforced_per_user() {
+    let mut setup = Setup::new(|policy| policy.max_submissions_per_user = 1);
+    setup.submit_nonce(0).unwrap();
+    assert_eq!(
+        setup
+            .gsn()
+            .sponsored_submissions(&setup.sponsor, &setup.keys[1]),
+        1
+    );
+
+    assert_eq!(
+        setup.submit_nonce(1),
+        Err(GsnError::SponsorPolicyViolation.into())
+    );
+    let gsn = setup.gsn();
+    assert_eq!(gsn.consumer.get(&setup.sponsor), Some(&150_000));
+    assert_eq!(gsn.get_next_nonce(&setup.keys[1]), 1);
+}
+
+#[test]
+fn test_sponsor_without_quota_counts_nothing() {
+    let mut setup = Setup::new(|_| {});
+    setup.submit().unwrap();
+    assert!(setup.gsn().sponsor_usage.is_empty());
+}
+
+/// `SubmitSponsoredInstruction` data relaying `instruction`
+fn encode_sponsored_instruction(nonce: u64, instruction: &Instruction) -> Vec<u8> {
+    let mut data = vec![49];
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(instruction.program_id.as_ref());
+    data.push(instruction.accounts.len() as u8);
+    for meta in &instruction.accounts {
+        data.extend_from_slice(meta.pubkey.as_ref());
+        data.push(meta.is_signer as u8);
+        data.push(meta.is_writable as u8);
+    }
+    data.extend_from_slice(&(instruction.data.len() as u16).to_le_bytes());
+    data.extend_from_slice(&instruction.data);
+    data
+}
+
+/// Relays an instruction of the target program whose data starts with
+/// `discriminator` for a sender without a balance, the sponsor paying for
+/// instructions starting with 7. Accounts: target program, sender, fee payer,
+/// GSN state, config, sponsor policy, then the relayed instruction's account.
+fn submit_sponsored_instruction(discriminator: u8) -> (Result<(), ProgramError>, GsnInfo) {
+    let program_id = Pubkey::new_unique();
+    let keys: Vec<Pubkey> = (0..7).map(|_| Pubkey::new_unique()).collect();
+    let sponsor = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[4];
+    gsn.add_consumer(sponsor, 200_000);
+    let policy = SponsorPolicy {
+        is_initialized: true,
+        gsn_account: keys[3],
+        sponsor,
+        max_fee_per_tx: 50_000,
+        allowed_programs: vec![],
+        allowed_instructions: vec![InstructionFilter {
+            program_id: keys[0],
+            discriminator: 7,
+        }],
+        max_submissions_per_user: 0,
+    };
+    let mut data = vec![vec![]; 7];
+    data[3] = vec![0u8; 1024];
+    data[4] = vec![0u8; 1024];
+    data[5] = vec![0u8; SponsorPolicy::LEN];
+    gsn.serialize(&mut data[3]).unwrap();
+    GsnConfig::new(keys[3]).serialize(&mut data[4]).unwrap();
+    policy.serialize(&mut data[5]).unwrap();
+    let mut lamports = [1, 1_000_000, 1_000_000, 1_000_000, 1_000_000, 1_000_000, 0];
+
+    let instruction = Instruction {
+        program_id: keys[0],
+        accounts: vec![AccountMeta::new(keys[6], false)],
+        data: vec![discriminator, 1],
+    };
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = index == 1 || index == 2;
+            AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+        })
+        .collect();
+    let result = Processor::process(
+        &program_id,
+        &accounts,
+        &encode_sponsored_instruction(0, &instruction),
+    );
+    drop(accounts);
+    (result, GsnInfo::deserialize(&data[3]).unwrap())
+}
+
+#[test]
+fn test_sponsored_instruction_is_filtered_by_discriminator() {
+    let (result, gsn) = submit_sponsored_instruction(7);
+    result.unwrap();
+    assert_eq!(
+        gsn.consumer.values().copied().collect::<Vec<_>>(),
+        vec![150_000]
+    );
+
+    let (result, gsn) = submit_sponsored_instruction(8);
+    assert_eq!(result, Err(GsnError::SponsorPolicyViolation.into()));
+    assert_eq!(
+        gsn.consumer.values().copied().collect::<Vec<_>>(),
+        vec![200_000]
+    );
+}
@@ -0,0 +1,321 @@
+// Tests for executor staking and slashing
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey, system_program,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SlashArgs, StakeArgs},
+    state::{ExecutorStake, GsnConfig, GsnInfo},
+    Processor,
+};
+
+#[test]
+fn test_unstake_executor_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[42]).unwrap(),
+        GsnInstruction::UnstakeExecutor
+    );
+}
+
+#[test]
+fn test_unbonding_stakes_are_not_bonded() {
+    let stake = ExecutorStake {
+        amount: 1_000,
+        unbonding_at: 0,
+    };
+    assert!(stake.is_bonded(1_000));
+    assert!(!stake.is_bonded(1_001));
+    let unbonding = ExecutorStake {
+        unbonding_at: 5,
+        ..stake
+    };
+    assert!(!unbonding.is_bonded(0));
+}
+
+#[test]
+fn test_executor_stakes_round_trip() {
+    let executor = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(Pubkey::new_unique(), 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before stakes existed have none
+    let legacy_len = gsn.serialized_size() - 208;
+    assert!(GsnInfo::deserialize(&data[..legacy_len])
+        .unwrap()
+        .executor_stakes
+        .is_empty());
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.executor_stakes_mut().insert(
+        executor,
+        ExecutorStake {
+            amount: 5_000,
+            unbonding_at: 0,
+        },
+    );
+    decoded.serialize(&mut data).unwrap();
+    let decoded = GsnInfo::deserialize(&data).unwrap();
+    assert!(decoded.is_executor_bonded(&executor, 5_000));
+    assert!(!decoded.is_executor_bonded(&Pubkey::new_unique(), 0));
+    assert_eq!(decoded.consumer, gsn.consumer);
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config, with a minimum stake of 1 SOL
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new(fee_payer_stake: Option<ExecutorStake>) -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 200_000);
+        if let Some(stake) = fee_payer_stake {
+            gsn.executor_stakes_mut().insert(keys[3], stake);
+        }
+        let mut config = GsnConfig::new(keys[4]);
+        config.min_executor_stake = 1_000_000_000;
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, 0, 0, None, None, 0, None, &accounts)
+    }
+}
+
+#[test]
+fn test_only_bonded_executors_execute() {
+    let bonded = ExecutorStake {
+        amount: 1_000_000_000,
+        unbonding_at: 0,
+    };
+    let mut setup = Setup::new(None);
+    assert_eq!(setup.submit(), Err(GsnError::ExecutorNotBonded.into()));
+
+    let mut setup = Setup::new(Some(ExecutorStake {
+        amount: 999_999_999,
+        ..bonded
+    }));
+    assert_eq!(setup.submit(), Err(GsnError::ExecutorNotBonded.into()));
+
+    let mut setup = Setup::new(Some(ExecutorStake {
+        unbonding_at: 10,
+        ..bonded
+    }));
+    assert_eq!(setup.submit(), Err(GsnError::ExecutorNotBonded.into()));
+
+    let mut setup = Setup::new(Some(bonded));
+    setup.submit().unwrap();
+    let gsn = GsnInfo::deserialize(&setup.data[4]).unwrap();
+    assert_eq!(gsn.get_next_nonce(&setup.keys[1]), 1);
+}
+
+#[test]
+fn test_staking_again_cancels_unbonding() {
+    let program_id = Pubkey::new_unique();
+    let (gsn_key, executor, system_program) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        system_program::id(),
+    );
+    let (stake_key, _) = GsnInfo::find_stake_address(&program_id, &gsn_key, &executor);
+    let mut gsn = GsnInfo::new();
+    gsn.executor_stakes_mut().insert(
+        executor,
+        ExecutorStake {
+            amount: 1_000,
+            unbonding_at: 10,
+        },
+    );
+    let mut gsn_data = vec![0u8; 1024];
+    gsn.serialize(&mut gsn_data).unwrap();
+
+    let mut lamports = [1, 1_000_000, 1_000, 1];
+    let mut data = [vec![], vec![], vec![]];
+    let [executor_data, stake_data, system_data] = &mut data;
+    let [gsn_lamports, executor_lamports, stake_lamports, system_lamports] = &mut lamports;
+    let accounts = vec![
+        AccountInfo::new(
+            &gsn_key,
+            false,
+            true,
+            gsn_lamports,
+            &mut gsn_data,
+            &program_id,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &executor,
+            true,
+            true,
+            executor_lamports,
+            executor_data,
+            &system_program,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &stake_key,
+            false,
+            true,
+            stake_lamports,
+            stake_data,
+            &system_program,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            &system_program,
+            false,
+            false,
+            system_lamports,
+            system_data,
+            &system_program,
+            false,
+            0,
+        ),
+    ];
+    Processor::process_stake_executor(&program_id, StakeArgs { amount: 500 }, &accounts).unwrap();
+    drop(accounts);
+
+    let gsn = GsnInfo::deserialize(&gsn_data).unwrap();
+    assert_eq!(
+        gsn.executor_stakes.get(&executor),
+        Some(&ExecutorStake {
+            amount: 1_500,
+            unbonding_at: 0,
+        })
+    );
+}
+
+struct SlashSetup {
+    program_id: Pubkey,
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+    authority: Pubkey,
+    executor: Pubkey,
+}
+
+impl SlashSetup {
+    /// Accounts: GSN state, config, signer, stake, recipient, system program
+    fn new(stake: u64) -> Self {
+        let program_id = Pubkey::new_unique();
+        let (gsn_key, config_key, authority, executor) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (stake_key, _) = GsnInfo::find_stake_address(&program_id, &gsn_key, &executor);
+        let mut gsn = GsnInfo::new();
+        gsn.config = config_key;
+        gsn.executor_stakes_mut().insert(
+            executor,
+            ExecutorStake {
+                amount: stake,
+                unbonding_at: 0,
+            },
+        );
+        let mut config = GsnConfig::new(gsn_key);
+        config.initialize_governance(authority);
+        let mut data = vec![vec![]; 6];
+        data[0] = vec![0u8; 1024];
+        data[1] = vec![0u8; 1024];
+        gsn.serialize(&mut data[0]).unwrap();
+        config.serialize(&mut data[1]).unwrap();
+        Self {
+            program_id,
+            keys: vec![
+                gsn_key,
+                config_key,
+                authority,
+                stake_key,
+                Pubkey::new_unique(),
+                system_program::id(),
+            ],
+            lamports: vec![1, 1, 1, stake, 0, 1],
+            data,
+            authority,
+            executor,
+        }
+    }
+
+    fn slash(&mut self, signer: Pubkey, amount: u64) -> ProgramResult {
+        self.keys[2] = signer;
+        let owner = self.program_id;
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 2, true, lamports, data, &owner, false, 0)
+            })
+            .collect();
+        let args = SlashArgs {
+            amount,
+            executor: self.executor.to_bytes(),
+        };
+        Processor::process_slash_executor(&self.program_id, args, &accounts)
+    }
+
+    fn stake(&self) -> Option<u64> {
+        let gsn = GsnInfo::deserialize(&self.data[0]).unwrap();
+        gsn.executor_stakes
+            .get(&self.executor)
+            .map(|stake| stake.amount)
+    }
+}
+
+#[test]
+fn test_governance_slashes_stakes() {
+    let mut setup = SlashSetup::new(10_000);
+    assert_eq!(
+        setup.slash(Pubkey::new_unique(), 1_000),
+        Err(GsnError::Unauthorized.into())
+    );
+
+    let authority = setup.authority;
+    setup.slash(authority, 4_000).unwrap();
+    assert_eq!(setup.stake(), Some(6_000));
+
+    // Slashing more than the stake takes all of it
+    setup.slash(authority, 50_000).unwrap();
+    assert_eq!(setup.stake(), None);
+    assert_eq!(
+        setup.slash(authority, 1),
+        Err(GsnError::ExecutorNotBonded.into())
+    );
+}
@@ -0,0 +1,211 @@
+// Tests for section-level rewrites, capacity accounting and growth of the GSN state account
+
+use borsh::BorshSerialize;
+use solana_program::{
+    entrypoint::MAX_PERMITTED_DATA_INCREASE, program_error::ProgramError, pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    state::{GsnInfo, Section, BALANCE_ENTRY_LEN, HEADER_LEN, RECEIPT_ENTRY_LEN, STATE_GROWTH_STEP},
+};
+
+fn populated_state() -> (GsnInfo, Vec<Pubkey>) {
+    let mut gsn = GsnInfo::new();
+    let consumers: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    for consumer in &consumers {
+        gsn.add_consumer(*consumer, 1_000_000);
+        gsn.increment_lane_nonce(consumer, 3);
+    }
+    gsn.add_executor(Pubkey::new_unique(), 50_000);
+    (gsn, consumers)
+}
+
+fn account_data(gsn: &GsnInfo) -> Vec<u8> {
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_clean_state_writes_nothing() {
+    let (gsn, _) = populated_state();
+    let data = account_data(&gsn);
+
+    let decoded = GsnInfo::deserialize(&data).unwrap();
+    assert!(decoded.dirty().is_empty());
+
+    // Garbage in the account would be overwritten by a full re-encode
+    let mut written = data.clone();
+    written[0] = 7;
+    decoded.serialize(&mut written).unwrap();
+    assert_eq!(written[0], 7);
+}
+
+#[test]
+fn test_in_place_update_only_rewrites_its_section() {
+    let (gsn, consumers) = populated_state();
+    let data = account_data(&gsn);
+    let consumer_start = 1 + 32;
+    let consumer_end = consumer_start + 4 + 3 * 40;
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.consumer_mut().insert(consumers[1], 950_000);
+    assert!(decoded.dirty().contains(Section::Consumer));
+    assert!(!decoded.dirty().contains(Section::Executor));
+
+    let mut written = data.clone();
+    decoded.serialize(&mut written).unwrap();
+
+    assert_eq!(written[..consumer_start], data[..consumer_start]);
+    assert_eq!(written[consumer_end..], data[consumer_end..]);
+    let mut expected = Vec::new();
+    BorshSerialize::serialize(&decoded, &mut expected).unwrap();
+    assert_eq!(&written[..expected.len()], &expected[..]);
+    let reread = GsnInfo::deserialize(&written).unwrap();
+    assert_eq!(reread.consumer.get(&consumers[1]), Some(&950_000));
+}
+
+#[test]
+fn test_growing_section_shifts_later_sections() {
+    let (gsn, consumers) = populated_state();
+    let data = account_data(&gsn);
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    let executor = Pubkey::new_unique();
+    decoded.record_lane_transaction_executor(&consumers[0], 3, 1, &executor);
+
+    let mut written = data;
+    decoded.serialize(&mut written).unwrap();
+
+    let mut expected = Vec::new();
+    BorshSerialize::serialize(&decoded, &mut expected).unwrap();
+    assert_eq!(&written[..expected.len()], &expected[..]);
+
+    let reread = GsnInfo::deserialize(&written).unwrap();
+    assert_eq!(
+        reread.get_lane_transaction_executor(&consumers[0], 3, 1),
+        Some(&executor)
+    );
+    for consumer in &consumers {
+        assert_eq!(reread.get_next_lane_nonce(consumer, 3), 1);
+    }
+}
+
+#[test]
+fn test_overflow_leaves_account_untouched() {
+    let (gsn, _) = populated_state();
+    let mut full = Vec::new();
+    BorshSerialize::serialize(&gsn, &mut full).unwrap();
+    let mut data = full.clone();
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.add_consumer(Pubkey::new_unique(), 1);
+
+    assert_eq!(
+        decoded.serialize(&mut data),
+        Err(ProgramError::AccountDataTooSmall)
+    );
+    assert_eq!(data, full);
+}
+
+/// Account data holding `gsn` with `spare` unused bytes
+fn account_with_spare(gsn: &GsnInfo, spare: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    BorshSerialize::serialize(gsn, &mut data).unwrap();
+    data.resize(data.len() + spare, 0);
+    data
+}
+
+#[test]
+fn test_serialized_size_matches_encoding() {
+    let (mut gsn, consumers) = populated_state();
+    gsn.record_lane_transaction_executor(&consumers[0], 3, 0, &Pubkey::new_unique());
+    gsn.increment_nonce(&consumers[1]);
+
+    let mut encoded = Vec::new();
+    BorshSerialize::serialize(&gsn, &mut encoded).unwrap();
+    assert_eq!(gsn.serialized_size(), encoded.len());
+    assert_eq!(gsn.remaining_capacity(), None);
+
+    let decoded = GsnInfo::deserialize(&account_with_spare(&gsn, 100)).unwrap();
+    assert_eq!(decoded.remaining_capacity(), Some(100));
+}
+
+#[test]
+fn test_new_consumer_needs_registry_space() {
+    let (gsn, consumers) = populated_state();
+
+    let full = GsnInfo::deserialize(&account_with_spare(&gsn, BALANCE_ENTRY_LEN - 1)).unwrap();
+    assert_eq!(
+        full.check_topup_capacity(&Pubkey::new_unique()),
+        Err(GsnError::ConsumerRegistryFull)
+    );
+    // Topping up a registered consumer doesn't grow the registry
+    assert_eq!(full.check_topup_capacity(&consumers[0]), Ok(()));
+
+    let mut data = account_with_spare(&gsn, BALANCE_ENTRY_LEN);
+    let mut fits = GsnInfo::deserialize(&data).unwrap();
+    let consumer = Pubkey::new_unique();
+    assert_eq!(fits.check_topup_capacity(&consumer), Ok(()));
+    fits.add_consumer(consumer, 1);
+    fits.serialize(&mut data).unwrap();
+    assert_eq!(
+        GsnInfo::deserialize(&data).unwrap().remaining_capacity(),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_submission_needs_history_space() {
+    let (gsn, consumers) = populated_state();
+    let executor = *gsn.executor.keys().next().unwrap();
+
+    // Lane 3 nonces and the executor already exist: only the receipt is new
+    let data = account_with_spare(&gsn, RECEIPT_ENTRY_LEN);
+    let decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(
+        decoded.check_submit_capacity(&consumers[0], 3, &[&executor]),
+        Ok(())
+    );
+
+    // A first submission in lane 0 also creates a nonce entry
+    assert_eq!(
+        decoded.check_submit_capacity(&consumers[0], 0, &[&executor]),
+        Err(GsnError::HistoryFull)
+    );
+    // and a new executor an earnings entry
+    assert_eq!(
+        decoded.check_submit_capacity(&consumers[0], 3, &[&Pubkey::new_unique()]),
+        Err(GsnError::HistoryFull)
+    );
+}
+
+#[test]
+fn test_growth_allows_for_realloc() {
+    let (gsn, _) = populated_state();
+    let data = account_with_spare(&gsn, 0);
+    let mut full = GsnInfo::deserialize(&data).unwrap();
+    let consumer = Pubkey::new_unique();
+    assert_eq!(
+        full.check_topup_capacity(&consumer),
+        Err(GsnError::ConsumerRegistryFull)
+    );
+
+    full.allow_growth(GsnInfo::max_grown_len(data.len()));
+    assert_eq!(full.check_topup_capacity(&consumer), Ok(()));
+    assert_eq!(full.grown_len(data.len()), None);
+    // A new consumer reallocs by a whole step
+    full.add_consumer(consumer, 1);
+    assert_eq!(full.grown_len(data.len()), Some(data.len() + STATE_GROWTH_STEP));
+}
+
+#[test]
+fn test_growth_is_capped_per_instruction() {
+    let mut gsn = GsnInfo::new();
+    for _ in 0..300 {
+        gsn.add_consumer(Pubkey::new_unique(), 1);
+    }
+    let len = HEADER_LEN;
+    assert_eq!(gsn.grown_len(len), Some(GsnInfo::max_grown_len(len)));
+    assert_eq!(GsnInfo::max_grown_len(len), len + MAX_PERMITTED_DATA_INCREASE);
+}
@@ -0,0 +1,103 @@
+// Tests for state account versioning and MigrateState
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::GsnInstruction,
+    state::{GsnConfig, GsnInfo, Section, STATE_VERSION},
+    Processor,
+};
+
+#[test]
+fn test_migrate_state_decodes() {
+    assert_eq!(
+        GsnInstruction::deserialize(&[59]).unwrap(),
+        GsnInstruction::MigrateState
+    );
+}
+
+#[test]
+fn test_versioned_deserialization() {
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(Pubkey::new_unique(), 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Initialized accounts from before versioning hold `true`, version 1
+    assert_eq!(data[0], 1);
+    assert_eq!(GsnInfo::deserialize(&data).unwrap().version, STATE_VERSION);
+    assert!(!GsnInfo::deserialize(&[0u8; 1024]).unwrap().is_initialized());
+
+    // A layout this program doesn't know is never misread
+    data[0] = STATE_VERSION + 1;
+    assert!(matches!(
+        GsnInfo::deserialize(&data),
+        Err(error) if error == GsnError::UnsupportedStateVersion.into()
+    ));
+}
+
+/// `MigrateState` accounts: GSN state, config, authority
+fn migrate(
+    gsn_data: &mut Vec<u8>,
+    config: &GsnConfig,
+    authority: &Pubkey,
+) -> Result<(), ProgramError> {
+    let owner = Pubkey::new_unique();
+    let keys = [Pubkey::new_unique(), config.gsn_account, *authority];
+    let mut config_data = vec![0u8; 1024];
+    config.serialize(&mut config_data).unwrap();
+    let mut data = [gsn_data.clone(), config_data, vec![]];
+    let mut lamports = [1; 3];
+    let accounts: Vec<AccountInfo> = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            AccountInfo::new(key, index == 2, true, lamports, data, &owner, false, 0)
+        })
+        .collect();
+    let result = Processor::process_migrate_state(&owner, &accounts);
+    drop(accounts);
+    *gsn_data = data[0].clone();
+    result
+}
+
+#[test]
+fn test_migrate_state() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let consumer = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.config = config.gsn_account;
+    gsn.add_consumer(consumer, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    assert_eq!(
+        migrate(&mut data, &config, &Pubkey::new_unique()),
+        Err(GsnError::Unauthorized.into())
+    );
+    let mut uninitialized = vec![0u8; 1024];
+    assert_eq!(
+        migrate(&mut uninitialized, &config, &authority),
+        Err(ProgramError::UninitializedAccount)
+    );
+
+    migrate(&mut data, &config, &authority).unwrap();
+    let migrated = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(migrated.version, STATE_VERSION);
+    assert_eq!(migrated.consumer.get(&consumer), Some(&1_000));
+}
+
+#[test]
+fn test_migration_rewrites_every_section() {
+    let mut data = vec![0u8; 1024];
+    GsnInfo::new().serialize(&mut data).unwrap();
+    let mut gsn = GsnInfo::deserialize(&data).unwrap();
+    assert!(gsn.dirty().is_empty());
+
+    assert_eq!(gsn.migrate(), STATE_VERSION);
+    assert!(Section::ALL.iter().all(|section| gsn.dirty().contains(*section)));
+}
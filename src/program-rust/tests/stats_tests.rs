@@ -0,0 +1,195 @@
+// Tests for the execution counters by epoch and GetStats
+
+mod common;
+
+use borsh::BorshDeserialize;
+use common::Submit;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SetStatsTrackingArgs},
+    query::{DeploymentStats, EpochSummary},
+    state::{EpochStats, ExecutorVolume, GsnInfo, Stats},
+    Processor,
+};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+/// Epoch of the stubbed clock
+static EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Return data set by the last instruction
+static RETURN_DATA: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            epoch: EPOCH.load(Ordering::SeqCst),
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        *RETURN_DATA.lock().unwrap() = data.to_vec();
+    }
+}
+
+#[test]
+fn test_stats_instructions_decode() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let enable = instruction::set_stats_tracking(&keys[0], &keys[1], &keys[2], &keys[3], true);
+    assert_eq!(
+        GsnInstruction::deserialize(&enable.data).unwrap(),
+        GsnInstruction::SetStatsTracking(SetStatsTrackingArgs { enabled: true })
+    );
+    assert_eq!(
+        GsnInstruction::deserialize(&[76, 2]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    let get_stats = instruction::get_stats(&keys[0], &keys[1]);
+    assert_eq!(GsnInstruction::deserialize(&get_stats.data).unwrap(), GsnInstruction::GetStats);
+
+    // Accounts written before the counters existed don't count
+    let mut gsn = GsnInfo::new();
+    gsn.set_stats_tracking(true, 3);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let legacy_len = gsn.serialized_size() - 86;
+    assert_eq!(GsnInfo::deserialize(&data[..legacy_len]).unwrap().stats, Stats::default());
+    assert!(GsnInfo::deserialize(&data).unwrap().stats.enabled);
+}
+
+#[test]
+fn test_counters_move_on_every_epoch() {
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut stats = Stats {
+        enabled: true,
+        current: EpochStats::new(3),
+        previous: EpochStats::default(),
+    };
+    assert_eq!(stats.growth(3, &consumer, &executor), 40 + 48);
+    stats.record(3, &consumer, &executor, 1_000, 900);
+    assert_eq!(stats.growth(3, &consumer, &executor), 0);
+    // A new epoch starts from empty counters
+    assert_eq!(stats.growth(4, &consumer, &executor), 40 + 48);
+
+    let (current, previous) = stats.as_of(5);
+    assert_eq!((current, previous), (EpochStats::new(5), stats.current.clone()));
+    stats.record(5, &consumer, &executor, 2_000, 1_800);
+    assert_eq!(stats.previous.executions, 1);
+    assert_eq!(
+        stats.current.executor_volumes.get(&executor),
+        Some(&ExecutorVolume {
+            executions: 1,
+            fees: 1_800,
+        })
+    );
+}
+
+impl Submit {
+    /// `SetStatsTracking` signed by `signer`
+    fn set_stats_tracking(&mut self, signer: Pubkey, enabled: bool) -> ProgramResult {
+        self.govern(signer, |program_id, accounts| {
+            Processor::process_set_stats_tracking(program_id, SetStatsTrackingArgs { enabled }, accounts)
+        })
+    }
+
+    fn get_stats(&mut self) -> DeploymentStats {
+        let mut lamports = 1;
+        let accounts = [AccountInfo::new(
+            &self.keys[4],
+            false,
+            false,
+            &mut lamports,
+            &mut self.data[4],
+            &self.program_id,
+            false,
+            0,
+        )];
+        Processor::process_get_stats(&self.program_id, &accounts).unwrap();
+        DeploymentStats::try_from_slice(&RETURN_DATA.lock().unwrap()).unwrap()
+    }
+}
+
+fn summary(epoch: u64, executions: u64, executor_volumes: Vec<(Pubkey, ExecutorVolume)>) -> EpochSummary {
+    EpochSummary {
+        epoch,
+        executions,
+        total_fees: executions * 50_000,
+        unique_consumers: executions.min(1),
+        executor_volumes,
+    }
+}
+
+#[test]
+fn test_set_stats_tracking_requires_governance() {
+    set_syscall_stubs(Box::new(Stubs));
+    let mut setup = Submit::new(|_, _, _| {});
+    assert_eq!(
+        setup.set_stats_tracking(Pubkey::new_unique(), true),
+        Err(GsnError::Unauthorized.into())
+    );
+    setup.set_stats_tracking(setup.authority, true).unwrap();
+    let stats = setup.state().stats;
+    assert!(stats.enabled);
+    assert_eq!(stats.current.executions, 0);
+}
+
+#[test]
+fn test_executions_are_counted_by_epoch() {
+    set_syscall_stubs(Box::new(Stubs));
+    let mut setup = Submit::new(|_, _, _| {});
+    // Nothing is counted until governance turns it on
+    EPOCH.store(3, Ordering::SeqCst);
+    setup.submit(0).unwrap();
+    assert_eq!(setup.state().stats, Stats::default());
+
+    setup.set_stats_tracking(setup.authority, true).unwrap();
+    setup.submit(1).unwrap();
+    setup.submit(2).unwrap();
+    let executor = setup.keys[3];
+    let epoch_3 = summary(
+        3,
+        2,
+        vec![(
+            executor,
+            ExecutorVolume {
+                executions: 2,
+                fees: 100_000,
+            },
+        )],
+    );
+    assert_eq!(
+        setup.get_stats(),
+        DeploymentStats {
+            enabled: true,
+            current: epoch_3.clone(),
+            previous: summary(0, 0, vec![]),
+        }
+    );
+
+    // The answer moves on with the clock before the next execution does
+    EPOCH.store(4, Ordering::SeqCst);
+    assert_eq!(setup.get_stats().previous, epoch_3);
+    setup.submit(3).unwrap();
+    let stats = setup.get_stats();
+    assert_eq!(stats.current.executions, 1);
+    assert_eq!(stats.previous, epoch_3);
+
+    // Turning it off drops the counters
+    setup.set_stats_tracking(setup.authority, false).unwrap();
+    assert_eq!(setup.state().stats, Stats::default());
+}
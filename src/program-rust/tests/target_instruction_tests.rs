@@ -0,0 +1,166 @@
+// Tests for relaying arbitrary target program instructions
+
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SubmitInstructionArgs},
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+/// `SubmitInstruction` data as the client encodes it
+fn encode(nonce: u64, lane: u64, instruction: &Instruction) -> Vec<u8> {
+    let mut data = vec![29];
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.extend_from_slice(&lane.to_le_bytes());
+    data.extend_from_slice(instruction.program_id.as_ref());
+    data.push(instruction.accounts.len() as u8);
+    for meta in &instruction.accounts {
+        data.extend_from_slice(meta.pubkey.as_ref());
+        data.push(meta.is_signer as u8);
+        data.push(meta.is_writable as u8);
+    }
+    data.extend_from_slice(&(instruction.data.len() as u16).to_le_bytes());
+    data.extend_from_slice(&instruction.data);
+    data
+}
+
+fn target_instruction(program_id: Pubkey, signer: Pubkey) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        ],
+        data: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn test_submit_instruction_decodes() {
+    let instruction = target_instruction(Pubkey::new_unique(), Pubkey::new_unique());
+    let data = encode(4, 2, &instruction);
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::SubmitInstruction(SubmitInstructionArgs {
+            nonce: 4,
+            lane: 2,
+            instruction,
+        })
+    );
+
+    // Truncated, or with bytes past the declared data
+    assert_eq!(
+        GsnInstruction::deserialize(&data[..data.len() - 1]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    let mut trailing = data.clone();
+    trailing.push(0);
+    assert_eq!(
+        GsnInstruction::deserialize(&trailing),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+/// Accounts of a `SubmitInstruction`: target program, sender, fee payer,
+/// GSN state, config, then one account of the relayed instruction
+struct Setup {
+    program_id: Pubkey,
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let program_id = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[4];
+        gsn.add_consumer(keys[1], 200_000);
+        let mut data = vec![
+            vec![],
+            vec![],
+            vec![],
+            vec![0u8; 1024],
+            vec![0u8; 1024],
+            vec![],
+        ];
+        gsn.serialize(&mut data[3]).unwrap();
+        GsnConfig::new(keys[3]).serialize(&mut data[4]).unwrap();
+        Self {
+            program_id,
+            keys,
+            lamports: vec![1, 1_000_000, 1_000_000, 1_000_000, 1_000_000, 0],
+            data,
+        }
+    }
+
+    fn submit(&mut self, args: SubmitInstructionArgs) -> Result<(), ProgramError> {
+        let program_id = self.program_id;
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 2;
+                AccountInfo::new(key, is_signer, true, lamports, data, &program_id, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_instruction(&program_id, args, &accounts)
+    }
+}
+
+#[test]
+fn test_submit_instruction_charges_fee_and_advances_nonce() {
+    let mut setup = Setup::new();
+    let instruction = target_instruction(setup.keys[0], setup.keys[1]);
+    setup
+        .submit(SubmitInstructionArgs {
+            nonce: 0,
+            lane: 0,
+            instruction,
+        })
+        .unwrap();
+
+    let gsn = GsnInfo::deserialize(&setup.data[3]).unwrap();
+    assert_eq!(gsn.get_next_lane_nonce(&setup.keys[1], 0), 1);
+    assert_eq!(gsn.executor.get(&setup.keys[2]), Some(&50_000));
+    assert_eq!(
+        gsn.get_transaction_executor(&setup.keys[1], 0),
+        Some(&setup.keys[2])
+    );
+}
+
+#[test]
+fn test_submit_instruction_rejects_unsafe_instructions() {
+    let mut setup = Setup::new();
+    let (target, sender, fee_payer) = (setup.keys[0], setup.keys[1], setup.keys[2]);
+    let program_id = setup.program_id;
+
+    for (target_account, instruction) in [
+        // Another program than the target account
+        (target, target_instruction(Pubkey::new_unique(), sender)),
+        // The GSN program itself
+        (program_id, target_instruction(program_id, sender)),
+        // The relayer's signature
+        (target, target_instruction(target, fee_payer)),
+    ] {
+        setup.keys[0] = target_account;
+        assert_eq!(
+            setup.submit(SubmitInstructionArgs {
+                nonce: 0,
+                lane: 0,
+                instruction,
+            }),
+            Err(GsnError::InvalidTargetInstruction.into())
+        );
+    }
+}
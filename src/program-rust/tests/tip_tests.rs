@@ -0,0 +1,128 @@
+// Tests for tips consumers add to SubmitTransaction
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SubmitArgs},
+    state::{FreeTier, GsnConfig, GsnInfo, SandboxConfig},
+    Processor,
+};
+
+#[test]
+fn test_submit_with_tip_decodes() {
+    let mut data = vec![2];
+    for value in [1_000u64, 3, 0, u64::MAX, 10_000] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::SubmitTransaction(SubmitArgs {
+            amount: 1_000,
+            nonce: 3,
+            expiry_slot: None,
+            max_fee: Some(u64::MAX),
+            tip: 10_000,
+            simulate: false,
+            quote: None,
+        })
+    );
+    for len in [data.len() - 1, data.len() + 8] {
+        data.resize(len, 0);
+        assert_eq!(
+            SubmitArgs::unpack(&data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config, with the default fee of 50,000 lamports
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new(configure: impl FnOnce(&mut GsnConfig)) -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 200_000);
+        let mut config = GsnConfig::new(keys[4]);
+        config.initialize_governance(Pubkey::new_unique());
+        configure(&mut config);
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self, max_fee: Option<u64>, tip: u64) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, 0, 0, None, max_fee, tip, None, &accounts)
+    }
+
+    fn gsn(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.data[4]).unwrap()
+    }
+}
+
+#[test]
+fn test_tip_goes_to_the_executor_outside_the_protocol_fee() {
+    let mut setup = Setup::new(|config| config.protocol_fee_bps = 1_000);
+    setup.submit(None, 10_000).unwrap();
+    let gsn = setup.gsn();
+    assert_eq!(gsn.treasury, 5_000);
+    assert_eq!(gsn.executor.get(&setup.keys[3]), Some(&55_000));
+}
+
+#[test]
+fn test_balance_must_cover_fee_and_tip() {
+    let mut setup = Setup::new(|_| {});
+    assert_eq!(
+        setup.submit(None, 150_001),
+        Err(GsnError::InsufficientBalance.into())
+    );
+    // The maximum fee bounds the fee without the tip
+    setup.submit(Some(50_000), 150_000).unwrap();
+}
+
+#[test]
+fn test_only_consumers_paying_their_fee_tip() {
+    let mut setup = Setup::new(|config| {
+        config.sandbox = Some(SandboxConfig {
+            subsidy: None,
+            reset_epoch: 0,
+        })
+    });
+    assert_eq!(setup.submit(None, 1), Err(GsnError::InvalidTip.into()));
+    setup.submit(None, 0).unwrap();
+
+    // A tipping consumer pays its own fee instead of using the free tier
+    let mut setup = Setup::new(|config| {
+        config.free_tier = Some(FreeTier {
+            free_tx_quota: 2,
+            subsidy: Pubkey::new_unique(),
+        })
+    });
+    setup.submit(None, 1).unwrap();
+    assert_eq!(setup.gsn().free_txs_used(&setup.keys[1]), 0);
+}
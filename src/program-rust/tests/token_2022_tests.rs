@@ -0,0 +1,225 @@
+// Tests for Token-2022 mints and their transfer fees
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::SUCCESS,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+};
+use solgsn::{
+    error::GsnError,
+    state::{GsnConfig, GsnInfo},
+    token::{self, unpack_mint, Mint, TransferFee, TransferFees, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID},
+    Processor,
+};
+
+/// Epoch of the stubbed clock
+const EPOCH: u64 = 10;
+
+struct ClockStubs;
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            epoch: EPOCH,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    data[..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data[108] = 1;
+    data
+}
+
+/// Initialized base mint layout with `decimals`
+fn mint_account(decimals: u8) -> Vec<u8> {
+    let mut data = vec![0u8; 82];
+    data[44] = decimals;
+    data[45] = 1;
+    data
+}
+
+/// Token-2022 mint charging `older` until `newer.epoch`, then `newer`
+fn fee_mint_account(decimals: u8, older: &TransferFee, newer: &TransferFee) -> Vec<u8> {
+    let mut data = mint_account(decimals);
+    data.resize(165, 0);
+    data.push(1);
+    // A mint close authority extension before the transfer fee config
+    data.extend_from_slice(&3u16.to_le_bytes());
+    data.extend_from_slice(&32u16.to_le_bytes());
+    data.extend_from_slice(&[7u8; 32]);
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&108u16.to_le_bytes());
+    data.extend_from_slice(&[0u8; 72]);
+    for fee in [older, newer].iter() {
+        data.extend_from_slice(&fee.epoch.to_le_bytes());
+        data.extend_from_slice(&fee.maximum_fee.to_le_bytes());
+        data.extend_from_slice(&fee.basis_points.to_le_bytes());
+    }
+    data
+}
+
+/// 0.5% capped at 5,000 base units from epoch 8, after a free epoch 0 fee
+fn fees() -> TransferFees {
+    TransferFees {
+        older: TransferFee {
+            epoch: 0,
+            maximum_fee: 0,
+            basis_points: 0,
+        },
+        newer: TransferFee {
+            epoch: 8,
+            maximum_fee: 5_000,
+            basis_points: 50,
+        },
+    }
+}
+
+#[test]
+fn test_unpack_mint() {
+    let legacy = mint_account(6);
+    assert_eq!(
+        unpack_mint(&TOKEN_PROGRAM_ID, &legacy),
+        Some(Mint {
+            decimals: 6,
+            transfer_fees: None,
+        })
+    );
+    assert_eq!(
+        unpack_mint(&TOKEN_2022_PROGRAM_ID, &legacy),
+        Some(Mint {
+            decimals: 6,
+            transfer_fees: None,
+        })
+    );
+    let fees = fees();
+    let data = fee_mint_account(9, &fees.older, &fees.newer);
+    assert_eq!(
+        unpack_mint(&TOKEN_2022_PROGRAM_ID, &data),
+        Some(Mint {
+            decimals: 9,
+            transfer_fees: Some(fees.clone()),
+        })
+    );
+
+    // Not owned by a token program, uninitialized, or a truncated extension
+    assert_eq!(unpack_mint(&Pubkey::new_unique(), &legacy), None);
+    let mut uninitialized = legacy;
+    uninitialized[45] = 0;
+    assert_eq!(unpack_mint(&TOKEN_PROGRAM_ID, &uninitialized), None);
+    assert_eq!(unpack_mint(&TOKEN_2022_PROGRAM_ID, &data[..data.len() - 1]), None);
+
+    // Rounded up, capped at the maximum fee, and free before the newer fee
+    assert_eq!(fees.fee(1_000, EPOCH), 5);
+    assert_eq!(fees.fee(101, EPOCH), 1);
+    assert_eq!(fees.fee(10_000_000, EPOCH), 5_000);
+    assert_eq!(fees.fee(1_000, 7), 0);
+}
+
+#[test]
+fn test_topup_token_credits_amount_net_of_transfer_fee() {
+    set_syscall_stubs(Box::new(ClockStubs));
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let consumer = Pubkey::new_unique();
+    let config_key = Pubkey::new_unique();
+    let keys = [
+        gsn_key,
+        consumer,
+        config_key,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        vault,
+        TOKEN_2022_PROGRAM_ID,
+        mint,
+    ];
+    let mut owners = [
+        program_id,
+        Pubkey::default(),
+        program_id,
+        TOKEN_2022_PROGRAM_ID,
+        TOKEN_2022_PROGRAM_ID,
+        Pubkey::default(),
+        Pubkey::default(),
+        TOKEN_2022_PROGRAM_ID,
+    ];
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    let fees = fees();
+    let mut data = [
+        vec![0u8; 1024],
+        vec![],
+        vec![0u8; 1024],
+        token_account(&mint, &consumer, 1_000_000),
+        token_account(&mint, &vault, 0),
+        vec![],
+        vec![],
+        fee_mint_account(6, &fees.older, &fees.newer),
+    ];
+    gsn.serialize(&mut data[0]).unwrap();
+    GsnConfig::new(Pubkey::new_unique()).serialize(&mut data[2]).unwrap();
+    let mut lamports = [1_000_000, 1_000_000, 1_000_000, 1, 1, 0, 1, 1];
+
+    let mut topup = |owners: &[Pubkey], data: &mut [Vec<u8>], len: usize| {
+        let accounts: Vec<AccountInfo> = keys[..len]
+            .iter()
+            .zip(owners.iter())
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, (((key, owner), lamports), data))| {
+                AccountInfo::new(key, index == 1, true, lamports, data, owner, false, 0)
+            })
+            .collect();
+        Processor::process_topup_token(&program_id, 300_000, None, &accounts)
+    };
+    topup(&owners, &mut data, 8).unwrap();
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.token_consumer.get(&(consumer, mint)), Some(&298_500));
+
+    // Without the mint the transfer isn't checked and the amount is credited
+    topup(&owners, &mut data, 7).unwrap();
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.token_consumer.get(&(consumer, mint)), Some(&598_500));
+
+    // A mint of the other token program
+    owners[7] = TOKEN_PROGRAM_ID;
+    assert_eq!(
+        topup(&owners, &mut data, 8),
+        Err(GsnError::InvalidTokenAccount.into())
+    );
+}
+
+#[test]
+fn test_transfer_checked_encoding() {
+    let (source, mint, destination, authority) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let transfer = token::transfer_checked(
+        &TOKEN_2022_PROGRAM_ID,
+        &source,
+        &mint,
+        &destination,
+        &authority,
+        42,
+        6,
+    );
+    assert_eq!(transfer.program_id, TOKEN_2022_PROGRAM_ID);
+    assert_eq!(transfer.data, [&[12][..], &42u64.to_le_bytes(), &[6]].concat());
+    let keys: Vec<Pubkey> = transfer.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, vec![source, mint, destination, authority]);
+    assert!(transfer.accounts[3].is_signer);
+}
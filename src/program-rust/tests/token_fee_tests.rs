@@ -0,0 +1,486 @@
+// Tests for fees paid in SPL tokens
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, system_program};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SubmitTokenFeeArgs},
+    sorted_map::SortedMap,
+    state::{FeeMode, GovernanceConfig, GsnConfig, GsnInfo, NATIVE_FEE_TOKEN},
+    token::{
+        associated_token_address, unpack_token_account, TokenAccount, ASSOCIATED_TOKEN_PROGRAM_ID, NATIVE_MINT,
+        TOKEN_PROGRAM_ID,
+    },
+    Processor,
+};
+
+/// Base token account layout of `amount` tokens of `mint` held by `owner`
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    data[..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data[108] = 1;
+    data
+}
+
+/// Accounts of `keys`, each owned by the program of the same index in
+/// `owners`, with the account at `signer` signing
+fn accounts<'a>(
+    keys: &'a [Pubkey],
+    owners: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    signer: usize,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(owners.iter())
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, (((key, owner), lamports), data))| {
+            AccountInfo::new(key, index == signer, true, lamports, data, owner, false, 0)
+        })
+        .collect()
+}
+
+/// Keys, owners and data of a `TopupToken` of `consumer_mint` tokens into a
+/// vault token account of `vault_mint`
+fn topup_token_setup(
+    program_id: &Pubkey,
+    config: &GsnConfig,
+    consumer_mint: &Pubkey,
+    vault_mint: &Pubkey,
+) -> (Vec<Pubkey>, Vec<Pubkey>, Vec<Vec<u8>>) {
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(program_id, &gsn_key);
+    let consumer = Pubkey::new_unique();
+    let config_key = Pubkey::new_unique();
+    let keys = vec![
+        gsn_key,
+        consumer,
+        config_key,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        vault,
+        TOKEN_PROGRAM_ID,
+    ];
+    let owners = vec![
+        *program_id,
+        Pubkey::default(),
+        *program_id,
+        TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        Pubkey::default(),
+        Pubkey::default(),
+    ];
+
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    let mut data = vec![
+        vec![0u8; 1024],
+        vec![],
+        vec![0u8; 1024],
+        token_account(consumer_mint, &consumer, 1_000_000),
+        token_account(vault_mint, &vault, 0),
+        vec![],
+        vec![],
+    ];
+    gsn.serialize(&mut data[0]).unwrap();
+    config.serialize(&mut data[2]).unwrap();
+    (keys, owners, data)
+}
+
+#[test]
+fn test_unpack_token_account() {
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut data = token_account(&mint, &owner, 42);
+    assert_eq!(
+        unpack_token_account(&TOKEN_PROGRAM_ID, &data),
+        Some(TokenAccount {
+            mint,
+            owner,
+            amount: 42,
+            is_frozen: false,
+        })
+    );
+
+    // Not owned by a token program, truncated, or uninitialized
+    assert_eq!(unpack_token_account(&Pubkey::new_unique(), &data), None);
+    assert_eq!(unpack_token_account(&TOKEN_PROGRAM_ID, &data[..164]), None);
+    data[108] = 0;
+    assert_eq!(unpack_token_account(&TOKEN_PROGRAM_ID, &data), None);
+}
+
+#[test]
+fn test_state_without_token_sections_decodes() {
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(Pubkey::new_unique(), 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // An account written before the token sections ends after the lane nonces
+    // (each of the four empty sections after them is its 4 byte length, then
+    // the 1 byte paused flag)
+    let legacy_len = gsn.serialized_size() - 229;
+    let decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.consumer, gsn.consumer);
+    assert!(decoded.token_consumer.is_empty());
+    assert!(decoded.token_executor.is_empty());
+}
+
+#[test]
+fn test_topup_token_credits_mint_balance() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let config = GsnConfig::new(Pubkey::new_unique());
+    let (keys, owners, mut data) = topup_token_setup(&program_id, &config, &mint, &mint);
+    let mut lamports = [1_000_000, 1_000_000, 1_000_000, 1, 1, 0, 1];
+
+    for _ in 0..2 {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        Processor::process_topup_token(&program_id, 300_000, None, &accounts).unwrap();
+    }
+
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.token_consumer.get(&(keys[1], mint)), Some(&600_000));
+    // Lamport balances are separate
+    assert_eq!(gsn.consumer.get(&keys[1]), None);
+}
+
+#[test]
+fn test_balances_by_mint() {
+    let (consumer, executor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (usdc, bonk) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut gsn = GsnInfo::new();
+    gsn.token_consumer.insert((consumer, usdc), 50);
+    gsn.token_consumer.insert((Pubkey::new_unique(), usdc), 9);
+    assert_eq!(gsn.consumer_balances(&consumer), vec![(usdc, 50)]);
+
+    // The same consumer tops up in lamports and two mints at once
+    gsn.add_consumer(consumer, 1_000);
+    gsn.token_consumer.insert((consumer, bonk), 7);
+    let mut expected = vec![(usdc, 50), (bonk, 7)];
+    expected.sort();
+    expected.insert(0, (NATIVE_FEE_TOKEN, 1_000));
+    assert_eq!(gsn.consumer_balances(&consumer), expected);
+    assert_eq!(gsn.consumer_balance(&consumer, &NATIVE_FEE_TOKEN), 1_000);
+    assert_eq!(gsn.consumer_balance(&consumer, &usdc), 50);
+    assert_eq!(gsn.consumer_balance(&executor, &usdc), 0);
+
+    gsn.add_executor(executor, 20);
+    gsn.token_executor.insert((executor, usdc), 3);
+    assert_eq!(
+        gsn.executor_balances(&executor),
+        vec![(NATIVE_FEE_TOKEN, 20), (usdc, 3)]
+    );
+    assert_eq!(gsn.executor_balance(&executor, &usdc), 3);
+}
+
+#[test]
+fn test_topup_token_rejects_disallowed_mint() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let mut allowed_tokens = SortedMap::new();
+    allowed_tokens.insert(Pubkey::new_unique(), true);
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.governance = Some(GovernanceConfig {
+        authority: Pubkey::new_unique(),
+        fee_mode: FeeMode::Fixed(5_000),
+        allowed_tokens,
+    });
+
+    // Neither a mint off the allowed list nor the native token key is accepted
+    for mint in [mint, NATIVE_FEE_TOKEN] {
+        let (keys, owners, mut data) = topup_token_setup(&program_id, &config, &mint, &mint);
+        let mut lamports = [1_000_000, 1_000_000, 1_000_000, 1, 1, 0, 1];
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        assert_eq!(
+            Processor::process_topup_token(&program_id, 300_000, None, &accounts),
+            Err(GsnError::TokenNotAllowed.into())
+        );
+    }
+}
+
+#[test]
+fn test_topup_token_checks_token_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let config = GsnConfig::new(Pubkey::new_unique());
+
+    // Vault token account of another mint
+    let (keys, owners, mut data) =
+        topup_token_setup(&program_id, &config, &mint, &Pubkey::new_unique());
+    let mut lamports = [1_000_000, 1_000_000, 1_000_000, 1, 1, 0, 1];
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        assert_eq!(
+            Processor::process_topup_token(&program_id, 300_000, None, &accounts),
+            Err(GsnError::InvalidTokenAccount.into())
+        );
+    }
+
+    // Vault token account held by someone else
+    let (keys, owners, mut data) = topup_token_setup(&program_id, &config, &mint, &mint);
+    data[4] = token_account(&mint, &Pubkey::new_unique(), 0);
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        assert_eq!(
+            Processor::process_topup_token(&program_id, 300_000, None, &accounts),
+            Err(GsnError::InvalidTokenAccount.into())
+        );
+    }
+
+    // A program other than a token program
+    let (mut keys, owners, mut data) = topup_token_setup(&program_id, &config, &mint, &mint);
+    keys[6] = Pubkey::new_unique();
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        assert_eq!(
+            Processor::process_topup_token(&program_id, 300_000, None, &accounts),
+            Err(GsnError::InvalidTokenAccount.into())
+        );
+    }
+
+    // Without the consumer's signature
+    let (keys, owners, mut data) = topup_token_setup(&program_id, &config, &mint, &mint);
+    let accounts = accounts(&keys, &owners, &mut lamports, &mut data, usize::MAX);
+    assert_eq!(
+        Processor::process_topup_token(&program_id, 300_000, None, &accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_topup_token_opens_vault_token_account() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let config = GsnConfig::new(Pubkey::new_unique());
+    let (mut keys, mut owners, mut data) = topup_token_setup(&program_id, &config, &mint, &mint);
+    // The vault's associated token account doesn't exist yet, then the
+    // mint, the programs opening it and an executor paying its rent follow
+    keys[4] = associated_token_address(&keys[5], &mint, &TOKEN_PROGRAM_ID);
+    owners[4] = system_program::id();
+    data[4] = vec![];
+    let mut mint_data = vec![0u8; 82];
+    mint_data[44] = 6;
+    mint_data[45] = 1;
+    keys.extend_from_slice(&[mint, system_program::id(), ASSOCIATED_TOKEN_PROGRAM_ID, Pubkey::new_unique()]);
+    owners.extend_from_slice(&[TOKEN_PROGRAM_ID, Pubkey::default(), Pubkey::default(), Pubkey::default()]);
+    data.extend(vec![mint_data, vec![], vec![], vec![]]);
+    let mut lamports = [1_000_000, 1_000_000, 1_000_000, 1, 0, 0, 1, 1, 1, 1, 1_000_000];
+
+    let mut topup = |len: usize, signers: &[usize], keys: &[Pubkey]| {
+        let accounts: Vec<AccountInfo> = keys[..len]
+            .iter()
+            .zip(owners.iter())
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, (((key, owner), lamports), data))| {
+                AccountInfo::new(key, signers.contains(&index), true, lamports, data, owner, false, 0)
+            })
+            .collect();
+        Processor::process_topup_token(&program_id, 300_000, None, &accounts)
+    };
+    // Without the mint, or without the programs opening the account
+    assert_eq!(topup(7, &[1], &keys), Err(GsnError::VaultTokenAccountMissing.into()));
+    assert_eq!(topup(8, &[1], &keys), Err(GsnError::VaultTokenAccountMissing.into()));
+    // The payer must sign
+    assert_eq!(topup(11, &[1], &keys), Err(ProgramError::MissingRequiredSignature));
+    // Only the associated token account is opened
+    let mut other_keys = keys.clone();
+    other_keys[4] = Pubkey::new_unique();
+    assert_eq!(topup(10, &[1], &other_keys), Err(GsnError::InvalidTokenAccount.into()));
+
+    // Paid by the consumer, or by the executor
+    topup(10, &[1], &keys).unwrap();
+    topup(11, &[1, 10], &keys).unwrap();
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.token_consumer.get(&(keys[1], mint)), Some(&600_000));
+}
+
+#[test]
+fn test_submit_token_fee_charges_token_balance() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    // target, sender, receiver, fee payer, GSN state, config
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let owners = vec![program_id; 6];
+    let mut config = GsnConfig::new(keys[4]);
+    config.governance = Some(GovernanceConfig {
+        authority: Pubkey::new_unique(),
+        fee_mode: FeeMode::Fixed(5_000),
+        allowed_tokens: SortedMap::new(),
+    });
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[5];
+    gsn.token_consumer.insert((keys[1], mint), 12_000);
+    let mut data = vec![
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![0u8; 2048],
+        vec![0u8; 1024],
+    ];
+    gsn.serialize(&mut data[4]).unwrap();
+    config.serialize(&mut data[5]).unwrap();
+    let mut lamports = [1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000];
+
+    let args = |nonce| SubmitTokenFeeArgs {
+        amount: 1_000,
+        nonce,
+        lane: 0,
+        mint: mint.to_bytes(),
+    };
+    for nonce in 0..2 {
+        let mut accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        // The target program
+        accounts[0].executable = true;
+        Processor::process_submit_token_fee(&program_id, args(nonce), &accounts).unwrap();
+    }
+
+    let gsn = GsnInfo::deserialize(&data[4]).unwrap();
+    assert_eq!(gsn.token_consumer.get(&(keys[1], mint)), Some(&2_000));
+    assert_eq!(gsn.token_executor.get(&(keys[3], mint)), Some(&10_000));
+    assert_eq!(gsn.executor.get(&keys[3]), None);
+
+    // The remaining balance doesn't cover another fee
+    let mut accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+    accounts[0].executable = true;
+    assert_eq!(
+        Processor::process_submit_token_fee(&program_id, args(2), &accounts),
+        Err(GsnError::InsufficientBalance.into())
+    );
+}
+
+#[test]
+fn test_claim_token_fees_pays_executor_once() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let executor = Pubkey::new_unique();
+    let keys = [
+        gsn_key,
+        executor,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        vault,
+        TOKEN_PROGRAM_ID,
+    ];
+    let owners = [
+        program_id,
+        Pubkey::default(),
+        TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        Pubkey::default(),
+        Pubkey::default(),
+    ];
+    let mut gsn = GsnInfo::new();
+    gsn.token_executor.insert((executor, mint), 10_000);
+    let mut data = [
+        vec![0u8; 1024],
+        vec![],
+        token_account(&mint, &Pubkey::new_unique(), 0),
+        token_account(&mint, &vault, 10_000),
+        vec![],
+        vec![],
+    ];
+    gsn.serialize(&mut data[0]).unwrap();
+    let mut lamports = [1_000_000, 1_000_000, 1, 1, 0, 1];
+
+    // Only to the executor's own token account
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        assert_eq!(
+            Processor::process_claim_token_fees(&program_id, &accounts),
+            Err(GsnError::InvalidTokenAccount.into())
+        );
+    }
+
+    data[2] = token_account(&mint, &executor, 0);
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        Processor::process_claim_token_fees(&program_id, &accounts).unwrap();
+    }
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.token_executor.get(&(executor, mint)), None);
+
+    let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+    assert_eq!(
+        Processor::process_claim_token_fees(&program_id, &accounts),
+        Err(ProgramError::InsufficientFunds)
+    );
+}
+
+#[test]
+fn test_claim_wrapped_sol_unwraps_to_destination() {
+    let program_id = Pubkey::new_unique();
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let executor = Pubkey::new_unique();
+    let claim = instruction::claim_wrapped_sol(&program_id, &gsn_key, &executor, &executor);
+    assert_eq!(
+        GsnInstruction::deserialize(&claim.data).unwrap(),
+        GsnInstruction::ClaimWrappedSol
+    );
+    let mut keys: Vec<Pubkey> = claim.accounts.iter().map(|meta| meta.pubkey).collect();
+    let owners = [
+        program_id,
+        Pubkey::default(),
+        TOKEN_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        Pubkey::default(),
+        Pubkey::default(),
+        Pubkey::default(),
+    ];
+    let mut gsn = GsnInfo::new();
+    gsn.token_executor.insert((executor, NATIVE_MINT), 10_000);
+    let mut data = [
+        vec![0u8; 1024],
+        vec![],
+        token_account(&NATIVE_MINT, &executor, 0),
+        token_account(&NATIVE_MINT, &vault, 10_000),
+        vec![],
+        vec![],
+        vec![],
+    ];
+    gsn.serialize(&mut data[0]).unwrap();
+    let mut lamports = [1_000_000, 1_000_000, 1, 1, 0, 1, 0];
+
+    // Only to the executor or its payout address
+    keys[6] = Pubkey::new_unique();
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        assert_eq!(
+            Processor::process_claim_wrapped_sol(&program_id, &accounts),
+            Err(GsnError::UnauthorizedFeeClaim.into())
+        );
+    }
+    keys[6] = executor;
+
+    // Only through a wrapped SOL account the executor holds
+    data[2] = token_account(&Pubkey::new_unique(), &executor, 0);
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        assert_eq!(
+            Processor::process_claim_wrapped_sol(&program_id, &accounts),
+            Err(GsnError::InvalidTokenAccount.into())
+        );
+    }
+    data[2] = token_account(&NATIVE_MINT, &executor, 0);
+    {
+        let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+        Processor::process_claim_wrapped_sol(&program_id, &accounts).unwrap();
+    }
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.token_executor.get(&(executor, NATIVE_MINT)), None);
+
+    let accounts = accounts(&keys, &owners, &mut lamports, &mut data, 1);
+    assert_eq!(
+        Processor::process_claim_wrapped_sol(&program_id, &accounts),
+        Err(ProgramError::InsufficientFunds)
+    );
+}
@@ -0,0 +1,204 @@
+// Tests for the protocol treasury cut
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey, system_program,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{ProtocolFeeArgs, WithdrawArgs},
+    state::{GsnConfig, GsnInfo, MAX_PROTOCOL_FEE_BPS},
+    Processor,
+};
+
+#[test]
+fn test_protocol_fee_shares_add_up() {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    assert_eq!(config.protocol_fee_shares(50_000), (0, 50_000));
+    config.protocol_fee_bps = 1_000;
+    assert_eq!(config.protocol_fee_shares(50_000), (5_000, 45_000));
+    assert_eq!(config.protocol_fee_shares(9), (0, 9));
+}
+
+#[test]
+fn test_treasury_round_trips() {
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(Pubkey::new_unique(), 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the treasury existed hold nothing
+    let legacy_len = gsn.serialized_size() - 204;
+    assert_eq!(
+        GsnInfo::deserialize(&data[..legacy_len]).unwrap().treasury,
+        0
+    );
+
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    decoded.credit_treasury(7_000);
+    decoded.serialize(&mut data).unwrap();
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(decoded.treasury, 7_000);
+    assert_eq!(decoded.consumer, gsn.consumer);
+    assert_eq!(decoded.debit_treasury(7_001), None);
+    assert_eq!(decoded.debit_treasury(2_000), Some(5_000));
+}
+
+/// Accounts of a `SubmitTransaction`: target program, sender, receiver, fee
+/// payer, GSN state and config, with a 10% protocol fee
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 200_000);
+        let mut config = GsnConfig::new(keys[4]);
+        config.initialize_governance(Pubkey::new_unique());
+        config.protocol_fee_bps = 1_000;
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, 0, 0, None, None, 0, None, &accounts)
+    }
+}
+
+#[test]
+fn test_submissions_credit_the_treasury_cut() {
+    let mut setup = Setup::new();
+    setup.submit().unwrap();
+    let gsn = GsnInfo::deserialize(&setup.data[4]).unwrap();
+    assert_eq!(gsn.treasury, 5_000);
+    assert_eq!(gsn.executor.get(&setup.keys[3]), Some(&45_000));
+}
+
+fn set_protocol_fee(signer: &Pubkey, config_data: &mut [u8], bps: u16) -> ProgramResult {
+    let (config_key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut config_lamports, mut signer_lamports) = (1, 1);
+    let mut signer_data = vec![];
+    let accounts = vec![
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            config_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            signer,
+            true,
+            false,
+            &mut signer_lamports,
+            &mut signer_data,
+            &owner,
+            false,
+            0,
+        ),
+    ];
+    Processor::process_set_protocol_fee(
+        &owner,
+        ProtocolFeeArgs {
+            protocol_fee_bps: bps,
+        },
+        &accounts,
+    )
+}
+
+#[test]
+fn test_protocol_fee_is_capped() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+
+    assert_eq!(
+        set_protocol_fee(&Pubkey::new_unique(), &mut data, 100),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        set_protocol_fee(&authority, &mut data, MAX_PROTOCOL_FEE_BPS + 1),
+        Err(GsnError::InvalidProtocolFee.into())
+    );
+    set_protocol_fee(&authority, &mut data, MAX_PROTOCOL_FEE_BPS).unwrap();
+    assert_eq!(
+        GsnConfig::deserialize(&data).unwrap().protocol_fee_bps,
+        MAX_PROTOCOL_FEE_BPS
+    );
+}
+
+#[test]
+fn test_authority_withdraws_the_treasury() {
+    let program_id = Pubkey::new_unique();
+    let (gsn_key, config_key, authority) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (vault_key, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let mut gsn = GsnInfo::new();
+    gsn.config = config_key;
+    gsn.credit_treasury(10_000);
+    let mut config = GsnConfig::new(gsn_key);
+    config.initialize_governance(authority);
+    let keys = [
+        gsn_key,
+        config_key,
+        authority,
+        Pubkey::new_unique(),
+        vault_key,
+        system_program::id(),
+    ];
+    let mut lamports = [1; 6];
+    let mut data = vec![vec![]; 6];
+    data[0] = vec![0u8; 1024];
+    data[1] = vec![0u8; 1024];
+    gsn.serialize(&mut data[0]).unwrap();
+    config.serialize(&mut data[1]).unwrap();
+
+    let mut withdraw = |amount: u64| {
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                AccountInfo::new(key, index == 2, true, lamports, data, &program_id, false, 0)
+            })
+            .collect();
+        Processor::process_withdraw_treasury(&program_id, WithdrawArgs { amount }, &accounts)
+    };
+    assert_eq!(withdraw(10_001), Err(ProgramError::InsufficientFunds));
+    withdraw(4_000).unwrap();
+    assert_eq!(GsnInfo::deserialize(&data[0]).unwrap().treasury, 6_000);
+}
@@ -0,0 +1,156 @@
+// Tests for the unordered nonce lane
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    state::{is_valid_nonce_lane, GsnConfig, GsnInfo, NonceWindow, UNORDERED_NONCE_LANE},
+    Processor,
+};
+
+#[test]
+fn test_window_accepts_nonces_in_any_order() {
+    let mut window = NonceWindow::default();
+    for nonce in [7, 0, 255, 3] {
+        assert!(!window.is_used(nonce));
+        window.mark_used(nonce);
+        assert!(window.is_used(nonce));
+    }
+    assert!(!window.is_used(1));
+    assert!(!window.is_used(256));
+    assert_eq!(window.base, 0);
+}
+
+#[test]
+fn test_window_slides_past_its_highest_nonce() {
+    let mut window = NonceWindow::default();
+    window.mark_used(100);
+    window.mark_used(200);
+
+    // 300 becomes the highest nonce of the window, burning those below 45
+    window.mark_used(300);
+    assert_eq!(window.base, 45);
+    assert!(window.is_used(44));
+    assert!(!window.is_used(45));
+    assert!(window.is_used(100));
+    assert!(window.is_used(200));
+    assert!(window.is_used(300));
+    assert!(!window.is_used(299));
+
+    // Sliding further than the window clears it
+    window.mark_used(10_000);
+    assert_eq!(window.base, 10_000 - 255);
+    assert!(window.is_used(300));
+    assert!(!window.is_used(9_999));
+    assert!(window.is_used(10_000));
+
+    window.mark_used(u64::MAX);
+    assert!(window.is_used(u64::MAX));
+    assert!(!window.is_used(u64::MAX - 1));
+}
+
+#[test]
+fn test_unordered_lane_is_valid() {
+    assert!(is_valid_nonce_lane(0));
+    assert!(is_valid_nonce_lane(15));
+    assert!(!is_valid_nonce_lane(16));
+    assert!(is_valid_nonce_lane(UNORDERED_NONCE_LANE));
+}
+
+/// Accounts of a `SubmitTransactionInLane`: target program, sender, receiver,
+/// fee payer, GSN state and config
+struct Setup {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        let mut gsn = GsnInfo::new();
+        gsn.config = keys[5];
+        gsn.add_consumer(keys[1], 1_000_000);
+        let config = GsnConfig::new(keys[4]);
+        let mut data = vec![vec![]; 6];
+        data[4] = vec![0u8; 1024];
+        data[5] = vec![0u8; 1024];
+        gsn.serialize(&mut data[4]).unwrap();
+        config.serialize(&mut data[5]).unwrap();
+        Self {
+            keys,
+            lamports: vec![1, 1_000_000, 0, 1_000_000, 1_000_000, 1_000_000],
+            data,
+        }
+    }
+
+    fn submit(&mut self, nonce: u64, lane: u64) -> Result<(), ProgramError> {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<AccountInfo> = self
+            .keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .enumerate()
+            .map(|(index, ((key, lamports), data))| {
+                let is_signer = index == 1 || index == 3;
+                AccountInfo::new(key, is_signer, true, lamports, data, &owner, index == 0, 0)
+            })
+            .collect();
+        Processor::process_submit_tx(&owner, 1_000, nonce, lane, None, None, 0, None, &accounts)
+    }
+
+    fn gsn(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.data[4]).unwrap()
+    }
+}
+
+#[test]
+fn test_submit_unordered_nonces() {
+    let mut setup = Setup::new();
+    setup.submit(5, UNORDERED_NONCE_LANE).unwrap();
+    setup.submit(2, UNORDERED_NONCE_LANE).unwrap();
+    assert_eq!(
+        setup.submit(5, UNORDERED_NONCE_LANE),
+        Err(GsnError::ReplayAttack.into())
+    );
+
+    let gsn = setup.gsn();
+    assert!(gsn.is_lane_nonce_used(&setup.keys[1], UNORDERED_NONCE_LANE, 2));
+    assert!(!gsn.is_lane_nonce_used(&setup.keys[1], UNORDERED_NONCE_LANE, 3));
+    assert_eq!(
+        gsn.get_lane_transaction_executor(&setup.keys[1], UNORDERED_NONCE_LANE, 5),
+        Some(&setup.keys[3])
+    );
+    // The counted lanes are untouched
+    assert_eq!(gsn.get_next_nonce(&setup.keys[1]), 0);
+
+    // A nonce past the window burns the unused ones left below it
+    setup.submit(1_000, UNORDERED_NONCE_LANE).unwrap();
+    assert_eq!(
+        setup.submit(3, UNORDERED_NONCE_LANE),
+        Err(GsnError::ReplayAttack.into())
+    );
+    setup.submit(999, UNORDERED_NONCE_LANE).unwrap();
+
+    assert_eq!(setup.submit(0, 16), Err(GsnError::InvalidNonceLane.into()));
+}
+
+#[test]
+fn test_nonce_windows_round_trip() {
+    let consumer = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before unordered nonces existed have no windows
+    let legacy_len = gsn.serialized_size() - 180;
+    let mut decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.nonce_window(&consumer), NonceWindow::default());
+
+    decoded.use_unordered_nonce(&consumer, 42);
+    decoded.serialize(&mut data).unwrap();
+    assert!(GsnInfo::deserialize(&data)
+        .unwrap()
+        .is_unordered_nonce_used(&consumer, 42));
+}
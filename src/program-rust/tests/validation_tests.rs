@@ -0,0 +1,143 @@
+// Tests for the owner, writability, system program and target program
+// checks of handlers
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey, system_program,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::ProtocolFeeArgs,
+    state::{GsnConfig, GsnInfo},
+    Processor,
+};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Accounts of `keys` owned by `owners`, the first one writable if
+/// `first_writable`, and the one at `signer` signing
+fn accounts<'a>(
+    keys: &'a [Pubkey],
+    owners: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    first_writable: bool,
+    signer: usize,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(owners)
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, (((key, owner), lamports), data))| {
+            let is_writable = index > 0 || first_writable;
+            AccountInfo::new(key, index == signer, is_writable, lamports, data, owner, false, 0)
+        })
+        .collect()
+}
+
+/// `CancelNonce` with the state account owned by `owner`
+fn cancel(owner: Pubkey, writable: bool) -> ProgramResult {
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut data = vec![vec![0u8; 1024], vec![]];
+    GsnInfo::new().serialize(&mut data[0]).unwrap();
+    let (owners, mut lamports) = ([owner; 2], [1; 2]);
+    let accounts = accounts(&keys, &owners, &mut lamports, &mut data, writable, 1);
+    Processor::process_cancel_nonce(&PROGRAM_ID, 0, 0, &accounts)
+}
+
+#[test]
+fn test_state_must_be_owned_and_writable() {
+    assert_eq!(
+        cancel(Pubkey::new_unique(), true),
+        Err(GsnError::InvalidAccountOwner.into())
+    );
+    assert_eq!(
+        cancel(PROGRAM_ID, false),
+        Err(GsnError::AccountNotWritable.into())
+    );
+    assert_eq!(cancel(PROGRAM_ID, true), Ok(()));
+}
+
+/// `SetProtocolFee` with the config account owned by `owner`
+fn set_protocol_fee(owner: Pubkey, writable: bool) -> ProgramResult {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let keys = [Pubkey::new_unique(), authority];
+    let mut data = vec![vec![0u8; 1024], vec![]];
+    config.serialize(&mut data[0]).unwrap();
+    let (owners, mut lamports) = ([owner; 2], [1; 2]);
+    let accounts = accounts(&keys, &owners, &mut lamports, &mut data, writable, 1);
+    let args = ProtocolFeeArgs {
+        protocol_fee_bps: 100,
+    };
+    Processor::process_set_protocol_fee(&PROGRAM_ID, args, &accounts)
+}
+
+#[test]
+fn test_config_must_be_owned_and_writable() {
+    assert_eq!(
+        set_protocol_fee(Pubkey::new_unique(), true),
+        Err(GsnError::InvalidAccountOwner.into())
+    );
+    assert_eq!(
+        set_protocol_fee(PROGRAM_ID, false),
+        Err(GsnError::AccountNotWritable.into())
+    );
+    assert_eq!(set_protocol_fee(PROGRAM_ID, true), Ok(()));
+}
+
+#[test]
+fn test_system_program_must_be_the_real_one() {
+    // `TopUp` accounts: state, consumer, config, vault, system program
+    let mut keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let mut data = vec![vec![]; 5];
+    let mut lamports = [1; 5];
+    let owners = [PROGRAM_ID; 5];
+    let topup = |keys: &[Pubkey], lamports: &mut [u64], data: &mut [Vec<u8>]| {
+        let accounts = accounts(keys, &owners, lamports, data, true, 1);
+        Processor::process_topup(&PROGRAM_ID, 1_000, None, &accounts)
+    };
+    assert_eq!(
+        topup(&keys, &mut lamports, &mut data),
+        Err(GsnError::InvalidSystemProgram.into())
+    );
+
+    // The real one gets the instruction past the check, to the state
+    keys[4] = system_program::id();
+    assert_ne!(
+        topup(&keys, &mut lamports, &mut data),
+        Err(GsnError::InvalidSystemProgram.into())
+    );
+
+    // `ClaimFees` accounts: state, executor, destination, vault, system program
+    keys[4] = Pubkey::new_unique();
+    let accounts = accounts(&keys, &owners, &mut lamports, &mut data, true, 1);
+    assert_eq!(
+        Processor::process_claim_fees(&PROGRAM_ID, &accounts),
+        Err(GsnError::InvalidSystemProgram.into())
+    );
+}
+
+#[test]
+fn test_target_program_must_be_executable() {
+    // `SubmitTransaction` accounts: target program, sender, receiver, fee
+    // payer, state, config
+    let keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let mut gsn = GsnInfo::new();
+    gsn.config = keys[5];
+    gsn.add_consumer(keys[1], 1_000_000);
+    let mut data = vec![vec![], vec![], vec![], vec![], vec![0u8; 1024], vec![0u8; 1024]];
+    gsn.serialize(&mut data[4]).unwrap();
+    GsnConfig::new(keys[4]).serialize(&mut data[5]).unwrap();
+    let mut lamports = [1, 1_000_000, 0, 1_000_000, 1, 1];
+    let owners = [PROGRAM_ID; 6];
+    let mut submit = |executable: bool| {
+        let mut accounts = accounts(&keys, &owners, &mut lamports, &mut data, true, 1);
+        accounts[0].executable = executable;
+        accounts[3].is_signer = true;
+        Processor::process_submit_tx(&PROGRAM_ID, 1_000, 0, 0, None, None, 0, None, &accounts)
+    };
+    assert_eq!(submit(false), Err(GsnError::TargetNotExecutable.into()));
+    assert_eq!(submit(true), Ok(()));
+}
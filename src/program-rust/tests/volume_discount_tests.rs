@@ -0,0 +1,169 @@
+// Tests for fee discounts by consumers' submissions in the epoch
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solgsn::{
+    error::GsnError,
+    instruction::{GsnInstruction, SetVolumeDiscountsArgs},
+    state::{GsnConfig, GsnInfo, VolumeDiscountTier},
+    Processor,
+};
+
+fn tier(min_txs: u64, discount_bps: u16) -> VolumeDiscountTier {
+    VolumeDiscountTier {
+        min_txs,
+        discount_bps,
+    }
+}
+
+#[test]
+fn test_volume_discounts() {
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(Pubkey::new_unique());
+    assert_eq!(config.apply_volume_discount(50_000, u64::MAX), 50_000);
+
+    config.volume_discounts = vec![tier(10, 1_000), tier(100, 2_500)];
+    assert_eq!(config.volume_discount_bps(9), 0);
+    assert_eq!(config.volume_discount_bps(10), 1_000);
+    assert_eq!(config.volume_discount_bps(99), 1_000);
+    assert_eq!(config.volume_discount_bps(100), 2_500);
+    assert_eq!(config.apply_volume_discount(50_000, 9), 50_000);
+    assert_eq!(config.apply_volume_discount(50_000, 10), 45_000);
+    assert_eq!(config.apply_volume_discount(50_000, 1_000), 37_500);
+    assert_eq!(
+        config.apply_volume_discount(u64::MAX, 100),
+        u64::MAX / 4 * 3 + 2
+    );
+}
+
+#[test]
+fn test_volume_discount_tiers_must_be_ordered() {
+    assert!(VolumeDiscountTier::are_valid(&[]));
+    assert!(VolumeDiscountTier::are_valid(&[tier(0, 10_000)]));
+    assert!(VolumeDiscountTier::are_valid(&[
+        tier(10, 500),
+        tier(20, 500)
+    ]));
+    assert!(!VolumeDiscountTier::are_valid(&[tier(0, 10_001)]));
+    assert!(!VolumeDiscountTier::are_valid(&[
+        tier(20, 500),
+        tier(10, 1_000)
+    ]));
+    assert!(!VolumeDiscountTier::are_valid(&[
+        tier(10, 500),
+        tier(10, 1_000)
+    ]));
+    assert!(!VolumeDiscountTier::are_valid(&[
+        tier(10, 1_000),
+        tier(20, 500)
+    ]));
+    let tiers: Vec<_> = (0..9).map(|index| tier(index, 100)).collect();
+    assert!(!VolumeDiscountTier::are_valid(&tiers));
+}
+
+#[test]
+fn test_set_volume_discounts_decodes() {
+    let mut data = vec![52, 2];
+    data.extend_from_slice(&10u64.to_le_bytes());
+    data.extend_from_slice(&1_000u16.to_le_bytes());
+    data.extend_from_slice(&100u64.to_le_bytes());
+    data.extend_from_slice(&2_500u16.to_le_bytes());
+    assert_eq!(
+        GsnInstruction::deserialize(&data).unwrap(),
+        GsnInstruction::SetVolumeDiscounts(SetVolumeDiscountsArgs {
+            tiers: vec![tier(10, 1_000), tier(100, 2_500)],
+        })
+    );
+    assert!(GsnInstruction::deserialize(&data[..data.len() - 1]).is_err());
+    assert_eq!(
+        GsnInstruction::deserialize(&[52, 0]).unwrap(),
+        GsnInstruction::SetVolumeDiscounts(SetVolumeDiscountsArgs { tiers: vec![] })
+    );
+}
+
+fn set(
+    config_data: &mut [u8],
+    authority: &Pubkey,
+    tiers: Vec<VolumeDiscountTier>,
+) -> ProgramResult {
+    let config_key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let (mut config_lamports, mut authority_lamports) = (1, 1);
+    let mut authority_data = vec![];
+    let accounts = vec![
+        AccountInfo::new(
+            &config_key,
+            false,
+            true,
+            &mut config_lamports,
+            config_data,
+            &owner,
+            false,
+            0,
+        ),
+        AccountInfo::new(
+            authority,
+            true,
+            false,
+            &mut authority_lamports,
+            &mut authority_data,
+            &owner,
+            false,
+            0,
+        ),
+    ];
+    Processor::process_set_volume_discounts(&owner, SetVolumeDiscountsArgs { tiers }, &accounts)
+}
+
+#[test]
+fn test_governance_sets_volume_discounts() {
+    let authority = Pubkey::new_unique();
+    let mut config = GsnConfig::new(Pubkey::new_unique());
+    config.initialize_governance(authority);
+    let mut data = vec![0u8; 1024];
+    config.serialize(&mut data).unwrap();
+
+    assert_eq!(
+        set(&mut data, &Pubkey::new_unique(), vec![tier(10, 1_000)]),
+        Err(GsnError::Unauthorized.into())
+    );
+    assert_eq!(
+        set(&mut data, &authority, vec![tier(10, 1_000), tier(5, 2_000)]),
+        Err(GsnError::InvalidVolumeDiscounts.into())
+    );
+
+    set(&mut data, &authority, vec![tier(10, 1_000)]).unwrap();
+    let decoded = GsnConfig::deserialize(&data).unwrap();
+    assert_eq!(decoded.volume_discounts, vec![tier(10, 1_000)]);
+    assert_eq!(decoded.apply_volume_discount(50_000, 10), 45_000);
+
+    set(&mut data, &authority, vec![]).unwrap();
+    assert!(GsnConfig::deserialize(&data)
+        .unwrap()
+        .volume_discounts
+        .is_empty());
+}
+
+#[test]
+fn test_epoch_volumes_round_trip() {
+    let consumer = Pubkey::new_unique();
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, 1_000);
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+
+    // Accounts written before the volumes existed count nothing
+    let legacy_len = gsn.serialized_size() - 188;
+    let mut decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.epoch_txs(&consumer, 7), 0);
+
+    decoded.record_epoch_tx(&consumer, 7);
+    decoded.record_epoch_tx(&consumer, 7);
+    decoded.serialize(&mut data).unwrap();
+    let mut decoded = GsnInfo::deserialize(&data).unwrap();
+    assert_eq!(decoded.epoch_txs(&consumer, 7), 2);
+
+    // A new epoch starts the count over
+    assert_eq!(decoded.epoch_txs(&consumer, 8), 0);
+    decoded.record_epoch_tx(&consumer, 8);
+    assert_eq!(decoded.epoch_txs(&consumer, 8), 1);
+}
@@ -0,0 +1,126 @@
+// Tests for consumer withdrawals
+
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solgsn::{error::GsnError, instruction::WithdrawArgs, state::GsnInfo, Processor};
+
+fn withdraw(amount: u64) -> WithdrawArgs {
+    WithdrawArgs { amount }
+}
+
+/// GSN state, consumer, vault and system program accounts of a withdrawal,
+/// signed by the consumer if `consumer_signs`
+fn withdraw_accounts<'a>(
+    keys: &'a [Pubkey],
+    lamports: &'a mut [u64],
+    data: &'a mut [Vec<u8>],
+    consumer_signs: bool,
+    owner: &'a Pubkey,
+) -> Vec<AccountInfo<'a>> {
+    keys.iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .enumerate()
+        .map(|(index, ((key, lamports), data))| {
+            let is_signer = index == 1 && consumer_signs;
+            AccountInfo::new(key, is_signer, true, lamports, data, owner, false, 0)
+        })
+        .collect()
+}
+
+#[test]
+fn test_withdraw_removes_emptied_balance() {
+    let program_id = Pubkey::new_unique();
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let keys = [gsn_key, Pubkey::new_unique(), vault, Pubkey::default()];
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(keys[1], 400_000);
+    gsn.increment_lane_nonce(&keys[1], 0);
+    let mut data = [vec![0u8; 1024], vec![], vec![], vec![]];
+    gsn.serialize(&mut data[0]).unwrap();
+    let mut lamports = [1_000_000, 0, 400_000, 1];
+
+    {
+        let accounts = withdraw_accounts(&keys, &mut lamports, &mut data, true, &program_id);
+        Processor::process_withdraw(&program_id, withdraw(400_000), &accounts).unwrap();
+    }
+
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.consumer.get(&keys[1]), None);
+    // Nonces survive so earlier submissions can't be replayed after a new top-up
+    assert_eq!(gsn.get_next_lane_nonce(&keys[1], 0), 1);
+}
+
+#[test]
+fn test_withdraw_keeps_remaining_balance() {
+    let program_id = Pubkey::new_unique();
+    let gsn_key = Pubkey::new_unique();
+    let (vault, _) = GsnInfo::find_vault_address(&program_id, &gsn_key);
+    let keys = [gsn_key, Pubkey::new_unique(), vault, Pubkey::default()];
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(keys[1], 400_000);
+    let mut data = [vec![0u8; 1024], vec![], vec![], vec![]];
+    gsn.serialize(&mut data[0]).unwrap();
+    let mut lamports = [1_000_000, 0, 400_000, 1];
+
+    {
+        let accounts = withdraw_accounts(&keys, &mut lamports, &mut data, true, &program_id);
+        Processor::process_withdraw(&program_id, withdraw(150_000), &accounts).unwrap();
+    }
+
+    let gsn = GsnInfo::deserialize(&data[0]).unwrap();
+    assert_eq!(gsn.consumer.get(&keys[1]), Some(&250_000));
+}
+
+#[test]
+fn test_withdraw_needs_consumer_signature_and_balance() {
+    let program_id = Pubkey::new_unique();
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::default(),
+    ];
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(keys[1], 400_000);
+    let mut data = [vec![0u8; 1024], vec![], vec![], vec![]];
+    gsn.serialize(&mut data[0]).unwrap();
+    let mut lamports = [1_000_000, 0, 400_000, 1];
+
+    let accounts = withdraw_accounts(&keys, &mut lamports, &mut data, false, &program_id);
+    assert_eq!(
+        Processor::process_withdraw(&program_id, withdraw(400_000), &accounts),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    let accounts = withdraw_accounts(&keys, &mut lamports, &mut data, true, &program_id);
+    assert_eq!(
+        Processor::process_withdraw(&program_id, withdraw(400_001), &accounts),
+        Err(ProgramError::InsufficientFunds)
+    );
+    assert_eq!(
+        Processor::process_withdraw(&program_id, withdraw(0), &accounts),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn test_withdraw_rejects_other_vault() {
+    let program_id = Pubkey::new_unique();
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::default(),
+    ];
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(keys[1], 400_000);
+    let mut data = [vec![0u8; 1024], vec![], vec![], vec![]];
+    gsn.serialize(&mut data[0]).unwrap();
+    let mut lamports = [1_000_000, 0, 400_000, 1];
+
+    let accounts = withdraw_accounts(&keys, &mut lamports, &mut data, true, &program_id);
+    assert_eq!(
+        Processor::process_withdraw(&program_id, withdraw(400_000), &accounts),
+        Err(GsnError::InvalidVaultAccount.into())
+    );
+}
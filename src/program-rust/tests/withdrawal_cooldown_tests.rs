@@ -0,0 +1,204 @@
+// Tests for the withdrawal cooldown and emergency withdrawals
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, GsnInstruction, SetWithdrawalCooldownArgs, WithdrawArgs},
+    state::{GsnConfig, GsnInfo, WithdrawalPolicy, WithdrawalRequest},
+    Processor,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Slot of the stubbed clock
+static SLOT: AtomicU64 = AtomicU64::new(0);
+
+struct SysvarStubs;
+
+impl SyscallStubs for SysvarStubs {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT.load(Ordering::SeqCst),
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+#[test]
+fn test_withdrawal_instructions_decode() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let (program_id, gsn, config, authority) = (&keys[0], &keys[1], &keys[2], &keys[3]);
+    let cooldown = instruction::set_withdrawal_cooldown(program_id, gsn, config, authority, 150);
+    assert_eq!(
+        GsnInstruction::deserialize(&cooldown.data).unwrap(),
+        GsnInstruction::SetWithdrawalCooldown(SetWithdrawalCooldownArgs { slots: 150 })
+    );
+    let request = instruction::request_withdrawal(program_id, gsn, authority, 400);
+    assert_eq!(
+        GsnInstruction::deserialize(&request.data).unwrap(),
+        GsnInstruction::RequestWithdrawal(WithdrawArgs { amount: 400 })
+    );
+    let emergency = instruction::enable_emergency_withdrawals(program_id, gsn, config, authority);
+    assert_eq!(
+        GsnInstruction::deserialize(&emergency.data).unwrap(),
+        GsnInstruction::EnableEmergencyWithdrawals
+    );
+
+    // Accounts written before withdrawal policies existed have no cooldown
+    let mut gsn = GsnInfo::new();
+    gsn.withdrawal_policy_mut().cooldown_slots = 150;
+    let mut data = vec![0u8; 1024];
+    gsn.serialize(&mut data).unwrap();
+    let legacy_len = gsn.serialized_size() - 162;
+    let decoded = GsnInfo::deserialize(&data[..legacy_len]).unwrap();
+    assert_eq!(decoded.withdrawal_policy, WithdrawalPolicy::default());
+    assert_eq!(GsnInfo::deserialize(&data).unwrap().withdrawal_policy.cooldown_slots, 150);
+}
+
+/// A state holding a consumer with a balance, and its governed config
+struct Deployment {
+    program_id: Pubkey,
+    gsn_key: Pubkey,
+    config_key: Pubkey,
+    consumer: Pubkey,
+    authority: Pubkey,
+    gsn: Vec<u8>,
+    config: Vec<u8>,
+}
+
+impl Deployment {
+    fn new(setup: impl FnOnce(&mut GsnInfo)) -> Self {
+        set_syscall_stubs(Box::new(SysvarStubs));
+        let (consumer, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut gsn = GsnInfo::new();
+        gsn.config = Pubkey::new_unique();
+        gsn.add_consumer(consumer, 400_000);
+        setup(&mut gsn);
+        let mut config = GsnConfig::new(Pubkey::new_unique());
+        config.initialize_governance(authority);
+
+        let (mut gsn_data, mut config_data) = (vec![0u8; 1024], vec![0u8; 1024]);
+        gsn.serialize(&mut gsn_data).unwrap();
+        config.serialize(&mut config_data).unwrap();
+        Self {
+            program_id: Pubkey::new_unique(),
+            gsn_key: Pubkey::new_unique(),
+            config_key: gsn.config,
+            consumer,
+            authority,
+            gsn: gsn_data,
+            config: config_data,
+        }
+    }
+
+    fn state(&self) -> GsnInfo {
+        GsnInfo::deserialize(&self.gsn).unwrap()
+    }
+
+    /// `Withdraw` of `amount` out of the vault, or `RequestWithdrawal` of it
+    fn withdraw(&mut self, amount: u64, request: bool) -> ProgramResult {
+        let (vault, _) = GsnInfo::find_vault_address(&self.program_id, &self.gsn_key);
+        let system_program = Pubkey::default();
+        let mut lamports = [1_000_000, 0, 400_000, 1];
+        let [gsn_lamports, consumer_lamports, vault_lamports, system_lamports] = &mut lamports;
+        let mut empty = [vec![], vec![], vec![]];
+        let [consumer_data, vault_data, system_data] = &mut empty;
+        let owner = self.program_id;
+        let accounts = [
+            AccountInfo::new(&self.gsn_key, false, true, gsn_lamports, &mut self.gsn, &owner, false, 0),
+            AccountInfo::new(&self.consumer, true, true, consumer_lamports, consumer_data, &owner, false, 0),
+            AccountInfo::new(&vault, false, true, vault_lamports, vault_data, &owner, false, 0),
+            AccountInfo::new(&system_program, false, false, system_lamports, system_data, &owner, true, 0),
+        ];
+        let args = WithdrawArgs { amount };
+        if request {
+            Processor::process_request_withdrawal(&self.program_id, args, &accounts[..2])
+        } else {
+            Processor::process_withdraw(&self.program_id, args, &accounts)
+        }
+    }
+
+    /// `EnableEmergencyWithdrawals` signed by `signer`
+    fn enable_emergency(&mut self, signer: Pubkey) -> ProgramResult {
+        let mut lamports = [1, 1, 0];
+        let [gsn_lamports, config_lamports, signer_lamports] = &mut lamports;
+        let mut signer_data = vec![];
+        let owner = self.program_id;
+        let accounts = [
+            AccountInfo::new(&self.gsn_key, false, true, gsn_lamports, &mut self.gsn, &owner, false, 0),
+            AccountInfo::new(&self.config_key, false, false, config_lamports, &mut self.config, &owner, false, 0),
+            AccountInfo::new(&signer, true, false, signer_lamports, &mut signer_data, &owner, false, 0),
+        ];
+        Processor::process_enable_emergency_withdrawals(&self.program_id, &accounts)
+    }
+}
+
+#[test]
+fn test_withdrawals_wait_out_the_cooldown() {
+    let mut deployment = Deployment::new(|gsn| gsn.withdrawal_policy_mut().cooldown_slots = 100);
+    SLOT.store(10, Ordering::SeqCst);
+    assert_eq!(deployment.withdraw(1_000, false), Err(GsnError::WithdrawalCooldown.into()));
+
+    // Requests can't exceed the balance
+    assert!(deployment.withdraw(400_001, true).is_err());
+    deployment.withdraw(300_000, true).unwrap();
+    assert_eq!(
+        deployment.state().withdrawal_requests.get(&deployment.consumer),
+        Some(&WithdrawalRequest {
+            amount: 300_000,
+            unlock_slot: 110,
+        })
+    );
+    assert_eq!(deployment.withdraw(1_000, false), Err(GsnError::WithdrawalCooldown.into()));
+
+    SLOT.store(110, Ordering::SeqCst);
+    assert_eq!(deployment.withdraw(300_001, false), Err(GsnError::WithdrawalCooldown.into()));
+    deployment.withdraw(100_000, false).unwrap();
+    deployment.withdraw(200_000, false).unwrap();
+    let gsn = deployment.state();
+    assert_eq!(gsn.consumer.get(&deployment.consumer), Some(&100_000));
+    assert!(gsn.withdrawal_requests.is_empty());
+}
+
+#[test]
+fn test_emergency_withdrawals_while_paused() {
+    let mut deployment = Deployment::new(|gsn| gsn.withdrawal_policy_mut().cooldown_slots = 100);
+    let authority = deployment.authority;
+    assert_eq!(deployment.enable_emergency(authority), Err(GsnError::NotPaused.into()));
+
+    let mut deployment = Deployment::new(|gsn| {
+        gsn.withdrawal_policy_mut().cooldown_slots = 100;
+        gsn.set_paused(true);
+    });
+    let authority = deployment.authority;
+    assert_eq!(deployment.withdraw(1_000, false), Err(GsnError::ProtocolPaused.into()));
+    assert_eq!(
+        deployment.enable_emergency(Pubkey::new_unique()),
+        Err(GsnError::Unauthorized.into())
+    );
+    deployment.enable_emergency(authority).unwrap();
+
+    // Neither the pause nor the cooldown hold the withdrawal back
+    deployment.withdraw(1_000, false).unwrap();
+    let mut gsn = deployment.state();
+    assert_eq!(gsn.consumer.get(&deployment.consumer), Some(&399_000));
+
+    // Unpausing ends the emergency
+    gsn.set_paused(false);
+    assert!(!gsn.withdrawal_policy.emergency);
+    gsn.set_paused(true);
+    assert!(!gsn.is_emergency_withdrawal());
+}
@@ -0,0 +1,50 @@
+use solana_client::client_error::ClientError as RpcError;
+use solgsn::GsnError;
+use solgsn_client::ClientError;
+use thiserror::Error;
+
+/// Errors that may be returned by the relayer.
+#[derive(Debug, Error)]
+pub enum RelayerError {
+    /// The payload isn't a meta-transaction the relayer executes
+    #[error("Invalid meta-transaction: {0}")]
+    InvalidTransaction(String),
+    /// The program would reject the meta-transaction
+    #[error("Rejected: {0}")]
+    Rejected(GsnError),
+    #[error("Simulation failed: {0}")]
+    Simulation(String),
+    /// The consumer's nonce is being relayed already
+    #[error("Nonce already queued by job {0}")]
+    AlreadyQueued(i64),
+    /// The fee credit doesn't cover the network fee by the relayer's margin
+    #[error("Unprofitable: credit of {credit} lamports for a network fee of {cost}")]
+    Unprofitable { credit: u64, cost: u64 },
+    #[error("Queue error: {0}")]
+    Queue(#[from] rusqlite::Error),
+    #[error("{0}")]
+    Client(ClientError),
+}
+
+impl From<ClientError> for RelayerError {
+    fn from(error: ClientError) -> Self {
+        match error {
+            ClientError::Program(error) => Self::Rejected(error),
+            error => Self::Client(error),
+        }
+    }
+}
+
+impl From<RpcError> for RelayerError {
+    fn from(error: RpcError) -> Self {
+        Self::Client(error.into())
+    }
+}
+
+impl From<GsnError> for RelayerError {
+    fn from(error: GsnError) -> Self {
+        Self::Rejected(error)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, RelayerError>;
@@ -0,0 +1,12 @@
+pub mod error;
+pub mod notify;
+pub mod profit;
+pub mod queue;
+pub mod relay;
+pub mod server;
+
+pub use error::RelayerError;
+pub use notify::{Event, Notifier};
+pub use profit::ProfitPolicy;
+pub use queue::JobQueue;
+pub use relay::{MetaTransaction, Relayer};
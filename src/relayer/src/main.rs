@@ -0,0 +1,100 @@
+use clap::Parser;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Signer},
+};
+use solgsn_client::GsnClient;
+use solgsn_relayer::{notify, server, JobQueue, ProfitPolicy, Relayer};
+use std::{net::SocketAddr, process::exit, sync::Arc};
+
+/// Accept signed meta-transactions over HTTP and execute them as fee payer.
+#[derive(Parser, Debug)]
+#[command(name = "solgsn-relayer", version)]
+struct Args {
+    /// RPC endpoint of the cluster
+    #[arg(long, default_value = "http://localhost:8899")]
+    url: String,
+
+    /// SolGSN program id
+    #[arg(long, env = "SOLGSN_PROGRAM_ID")]
+    program_id: Pubkey,
+
+    /// GSN state account of the deployment to relay for
+    #[arg(long, env = "SOLGSN_GSN_ACCOUNT")]
+    gsn: Pubkey,
+
+    /// Keypair of the executor paying for and earning the fees of relays
+    #[arg(long)]
+    keypair: String,
+
+    /// Address the HTTP API listens on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// SQLite file of the job queue, kept across restarts
+    #[arg(long, default_value = "solgsn-relayer.db")]
+    queue: String,
+
+    /// Fee floor: least lamports a relay must credit the executor
+    #[arg(long, default_value_t = 0)]
+    min_credit: u64,
+
+    /// Least lamports a relay must credit above its network fee, priority
+    /// fee included; negative to subsidize relays
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+    min_profit: i64,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let executor = match read_keypair_file(&args.keypair) {
+        Ok(executor) => executor,
+        Err(error) => {
+            eprintln!("[RELAYER] error=invalid keypair {}: {}", args.keypair, error);
+            exit(1);
+        }
+    };
+    let queue = match JobQueue::open(&args.queue) {
+        Ok(queue) => queue,
+        Err(error) => {
+            eprintln!("[RELAYER] error={}", error);
+            exit(1);
+        }
+    };
+    let rpc = RpcClient::new_with_commitment(args.url, CommitmentConfig::confirmed());
+    let client = GsnClient::new(rpc, args.program_id);
+    let policy = ProfitPolicy {
+        min_credit: args.min_credit,
+        min_profit: args.min_profit,
+    };
+    let relayer = Arc::new(Relayer::new(client, args.gsn, executor, queue).with_policy(policy));
+
+    // Finish what a previous run accepted before taking new relays
+    let recovering = relayer.clone();
+    if let Err(error) = tokio::task::spawn_blocking(move || recovering.recover())
+        .await
+        .expect("recovery panicked")
+    {
+        eprintln!("[RELAYER] error=recovery failed: {}", error);
+        exit(1);
+    }
+
+    println!(
+        "[RELAYER] listening address={} gsn={} executor={}",
+        args.listen,
+        args.gsn,
+        relayer.executor.pubkey()
+    );
+    tokio::spawn(notify::deliver_webhooks(relayer.notifier.clone()));
+    let app = server::router(relayer);
+    if let Err(error) = axum::Server::bind(&args.listen)
+        .serve(app.into_make_service())
+        .await
+    {
+        eprintln!("[RELAYER] error={}", error);
+        exit(1);
+    }
+}
@@ -0,0 +1,216 @@
+//! Notifications of the progress of relays.
+//!
+//! The relayer publishes an [`Event`] when it accepts a meta-transaction, sends
+//! it, sees it confirmed or rejects it. dApps follow their users' relays by
+//! subscribing over WebSocket or registering a webhook, either for a set of
+//! consumers or for all of them.
+
+use crate::{error::RelayerError, queue::Job};
+use serde_json::{json, Value};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use solgsn::GsnError;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Events kept for subscribers that fall behind
+const EVENT_CAPACITY: usize = 1024;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    /// Queued, not sent yet
+    Accepted,
+    /// Signed by the executor and sent
+    Submitted,
+    Confirmed,
+    /// Not executed; its nonce may be relayed again
+    Rejected,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Accepted => "accepted",
+            Self::Submitted => "submitted",
+            Self::Confirmed => "confirmed",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+/// Progress of a consumer's meta-transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub job: i64,
+    pub consumer: Pubkey,
+    pub lane: u64,
+    pub nonce: u64,
+    pub signature: Option<Signature>,
+    /// Why it was rejected
+    pub reason: Option<String>,
+    /// The program's error, when it rejected it
+    pub code: Option<GsnError>,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, job: &Job) -> Self {
+        Self {
+            kind,
+            job: job.id,
+            consumer: job.consumer,
+            lane: job.lane,
+            nonce: job.nonce,
+            signature: job.signature,
+            reason: None,
+            code: None,
+        }
+    }
+
+    pub fn with_signature(mut self, signature: Signature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
+    pub fn with_reason(mut self, reason: String, code: Option<GsnError>) -> Self {
+        self.reason = Some(reason);
+        self.code = code;
+        self
+    }
+
+    /// Rejected by the relayer with `error`
+    pub fn with_error(self, error: &RelayerError) -> Self {
+        let code = match error {
+            RelayerError::Rejected(error) => Some(error.clone()),
+            _ => None,
+        };
+        self.with_reason(error.to_string(), code)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "event": self.kind.as_str(),
+            "job": self.job,
+            "consumer": self.consumer.to_string(),
+            "lane": self.lane,
+            "nonce": self.nonce,
+            "signature": self.signature.map(|signature| signature.to_string()),
+            "reason": self.reason,
+            "code": self.code.clone().map(|code| code as u32),
+        })
+    }
+}
+
+/// Consumers a subscriber follows, all of them if empty.
+pub fn follows(consumers: &[Pubkey], event: &Event) -> bool {
+    consumers.is_empty() || consumers.contains(&event.consumer)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Webhook {
+    pub id: u64,
+    /// Events are `POST`ed to it as JSON
+    pub url: String,
+    pub consumers: Vec<Pubkey>,
+}
+
+/// Publishes events to WebSocket subscribers and webhooks.
+pub struct Notifier {
+    sender: broadcast::Sender<Event>,
+    webhooks: Mutex<(u64, Vec<Webhook>)>,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            sender,
+            webhooks: Mutex::new((0, Vec::new())),
+        }
+    }
+
+    pub fn publish(&self, event: Event) {
+        // Nobody listening isn't an error
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Deliver the events of `consumers` to `url`. Returns the webhook's id.
+    pub fn register_webhook(&self, url: String, consumers: Vec<Pubkey>) -> Result<u64, String> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("webhook url must be http(s): {}", url));
+        }
+        let mut webhooks = self.webhooks.lock().unwrap();
+        webhooks.0 += 1;
+        let id = webhooks.0;
+        webhooks.1.push(Webhook { id, url, consumers });
+        Ok(id)
+    }
+
+    /// Returns whether webhook `id` was registered.
+    pub fn remove_webhook(&self, id: u64) -> bool {
+        let mut webhooks = self.webhooks.lock().unwrap();
+        let count = webhooks.1.len();
+        webhooks.1.retain(|webhook| webhook.id != id);
+        webhooks.1.len() != count
+    }
+
+    /// Webhooks following `event`'s consumer
+    pub fn webhooks_for(&self, event: &Event) -> Vec<Webhook> {
+        let webhooks = self.webhooks.lock().unwrap();
+        webhooks
+            .1
+            .iter()
+            .filter(|webhook| follows(&webhook.consumers, event))
+            .cloned()
+            .collect()
+    }
+}
+
+/// `POST` every event to the webhooks following it, once and without waiting
+/// on slow endpoints.
+pub async fn deliver_webhooks(notifier: Arc<Notifier>) {
+    let client = match reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("[RELAYER] error=webhook client: {}", error);
+            return;
+        }
+    };
+    let mut events = notifier.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                println!("[RELAYER] webhooks lagged skipped={}", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let body = event.to_json().to_string();
+        for webhook in notifier.webhooks_for(&event) {
+            let request = client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            tokio::spawn(async move {
+                let result = request.send().await.and_then(|response| response.error_for_status());
+                if let Err(error) = result {
+                    println!("[RELAYER] webhook id={} error={}", webhook.id, error);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Whether relaying a meta-transaction pays for itself.
+//!
+//! Relaying costs the executor the transaction's network fee: the signature
+//! fees and the priority fee its compute budget instructions set. It earns the
+//! submission's fee, less the protocol's share, and its tip. Token fees aren't
+//! priced in lamports, so the policy only applies to lamport fees.
+
+use crate::relay::MetaTransaction;
+use solana_sdk::{compute_budget, message::Message};
+use solgsn::{instruction::LAMPORTS_PER_SIGNATURE, state::GsnConfig};
+use std::convert::TryInto;
+
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+/// Compute units an instruction may use without a `SetComputeUnitLimit`
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Smallest earnings the relayer executes a meta-transaction for.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProfitPolicy {
+    /// Fee floor: least credit per relay, whatever it costs
+    pub min_credit: u64,
+    /// Least credit above the network fee; negative subsidizes relays
+    pub min_profit: i64,
+}
+
+impl ProfitPolicy {
+    pub fn accepts(&self, estimate: &Estimate) -> bool {
+        match (estimate.credit, estimate.profit()) {
+            (Some(credit), Some(profit)) => credit >= self.min_credit && profit >= self.min_profit,
+            // Token fees aren't priced
+            _ => true,
+        }
+    }
+}
+
+/// Lamports a relay earns and costs the executor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    /// Credited to the executor, none for token fees
+    pub credit: Option<u64>,
+    /// Network fee of the transaction, priority fee included
+    pub cost: u64,
+}
+
+impl Estimate {
+    /// Relaying `meta`, charged `fee`, in a transaction of `message`.
+    pub fn new(meta: &MetaTransaction, fee: u64, config: &GsnConfig, message: &Message) -> Self {
+        let credit = match meta.fee_mint {
+            Some(_) => None,
+            None => {
+                let (_, executor_share) = config.protocol_fee_shares(fee);
+                Some(executor_share.saturating_add(meta.tip))
+            }
+        };
+        Self {
+            credit,
+            cost: network_fee(message),
+        }
+    }
+
+    pub fn profit(&self) -> Option<i64> {
+        self.credit
+            .map(|credit| credit as i128 - self.cost as i128)
+            .map(|profit| profit.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+/// Network fee of a transaction of `message`: its signature fees and the
+/// priority fee of the compute unit price and limit it sets.
+pub fn network_fee(message: &Message) -> u64 {
+    let signatures = message.header.num_required_signatures as u64;
+    let (mut limit, mut price) = (None, 0u64);
+    let mut instructions = 0u64;
+    for instruction in &message.instructions {
+        let program_id = message.account_keys.get(instruction.program_id_index as usize);
+        if program_id != Some(&compute_budget::id()) {
+            instructions += 1;
+            continue;
+        }
+        let data = &instruction.data;
+        match data.first() {
+            Some(&SET_COMPUTE_UNIT_LIMIT) if data.len() == 5 => {
+                limit = Some(u32::from_le_bytes(data[1..5].try_into().unwrap()) as u64);
+            }
+            Some(&SET_COMPUTE_UNIT_PRICE) if data.len() == 9 => {
+                price = u64::from_le_bytes(data[1..9].try_into().unwrap());
+            }
+            _ => {}
+        }
+    }
+    let limit = limit
+        .unwrap_or(instructions * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    // Rounded up like the runtime does
+    let priority_fee = (price as u128 * limit as u128).div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+    (signatures * LAMPORTS_PER_SIGNATURE).saturating_add(priority_fee.min(u64::MAX as u128) as u64)
+}
@@ -0,0 +1,245 @@
+//! Durable queue of the meta-transactions the relayer accepted.
+//!
+//! Every meta-transaction is stored before the executor signs it, and its
+//! signed transaction before it is sent, so a restarted relayer finishes what
+//! it accepted without executing anything twice. A consumer's nonce is only
+//! queued once, until its job failed.
+
+use crate::{error::Result, relay::MetaTransaction};
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use std::{path::Path, str::FromStr, sync::Mutex};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    consumer TEXT NOT NULL,
+    lane INTEGER NOT NULL,
+    nonce INTEGER NOT NULL,
+    transaction_data BLOB NOT NULL,
+    status TEXT NOT NULL,
+    signature TEXT,
+    error TEXT,
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    UNIQUE (consumer, lane, nonce)
+);
+CREATE INDEX IF NOT EXISTS jobs_status ON jobs (status);
+";
+
+/// Progress of a job.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobStatus {
+    /// Accepted, not signed by the executor yet
+    Pending,
+    /// Signed and sent, not confirmed yet
+    Submitted,
+    Confirmed,
+    /// Rejected, its nonce may be queued again
+    Failed,
+    /// Never landed before its blockhash expired, its nonce may be queued again
+    Expired,
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Submitted => "submitted",
+            Self::Confirmed => "confirmed",
+            Self::Failed => "failed",
+            Self::Expired => "expired",
+        }
+    }
+
+    /// Finished without executing, so the nonce is free again
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Failed | Self::Expired)
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "submitted" => Ok(Self::Submitted),
+            "confirmed" => Ok(Self::Confirmed),
+            "failed" => Ok(Self::Failed),
+            "expired" => Ok(Self::Expired),
+            _ => Err(format!("unknown job status `{}`", s)),
+        }
+    }
+}
+
+/// A queued meta-transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Job {
+    pub id: i64,
+    pub consumer: Pubkey,
+    pub lane: u64,
+    pub nonce: u64,
+    /// As the consumer sent it while pending, signed by the executor since
+    pub transaction: Transaction,
+    pub status: JobStatus,
+    pub signature: Option<Signature>,
+    pub error: Option<String>,
+}
+
+impl Job {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let text_column = |index: usize, error: String| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                error.into(),
+            )
+        };
+        let consumer: String = row.get(1)?;
+        let data: Vec<u8> = row.get(4)?;
+        let status: String = row.get(5)?;
+        let signature: Option<String> = row.get(6)?;
+        Ok(Self {
+            id: row.get(0)?,
+            consumer: consumer
+                .parse()
+                .map_err(|error: solana_sdk::pubkey::ParsePubkeyError| {
+                    text_column(1, error.to_string())
+                })?,
+            lane: row.get::<_, i64>(2)? as u64,
+            nonce: row.get::<_, i64>(3)? as u64,
+            transaction: bincode::deserialize(&data).map_err(|error| {
+                rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Blob, error)
+            })?,
+            status: status.parse().map_err(|error| text_column(5, error))?,
+            signature: signature
+                .map(|signature| signature.parse())
+                .transpose()
+                .map_err(|error: solana_sdk::signature::ParseSignatureError| {
+                    text_column(6, error.to_string())
+                })?,
+            error: row.get(7)?,
+        })
+    }
+}
+
+/// Outcome of queueing a meta-transaction.
+#[derive(Debug, PartialEq)]
+pub enum Enqueued {
+    New(Job),
+    /// The consumer's nonce is queued already, by this job
+    Existing(Job),
+}
+
+pub struct JobQueue {
+    conn: Mutex<Connection>,
+}
+
+impl JobQueue {
+    /// Open the queue stored at `path`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A queue that isn't persisted, for tests.
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Queue `meta`, sent as `transaction`, unless its nonce is queued
+    /// already by a job that may still execute.
+    pub fn enqueue(&self, meta: &MetaTransaction, transaction: &Transaction) -> Result<Enqueued> {
+        let conn = self.conn.lock().unwrap();
+        let key = params![meta.consumer.to_string(), meta.lane as i64, meta.nonce as i64];
+        let existing = conn
+            .query_row(
+                "SELECT * FROM jobs WHERE consumer = ?1 AND lane = ?2 AND nonce = ?3",
+                key,
+                Job::from_row,
+            )
+            .optional()?;
+        if let Some(job) = existing {
+            if !job.status.is_retryable() {
+                return Ok(Enqueued::Existing(job));
+            }
+            conn.execute("DELETE FROM jobs WHERE id = ?1", params![job.id])?;
+        }
+
+        let data = bincode::serialize(transaction).expect("transactions serialize");
+        conn.execute(
+            "INSERT INTO jobs (consumer, lane, nonce, transaction_data, status)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                meta.consumer.to_string(),
+                meta.lane as i64,
+                meta.nonce as i64,
+                data,
+                JobStatus::Pending.as_str()
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        drop(conn);
+        Ok(Enqueued::New(self.get(id)?.expect("inserted job")))
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<Job>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn
+            .query_row("SELECT * FROM jobs WHERE id = ?1", params![id], Job::from_row)
+            .optional()?)
+    }
+
+    /// Record the executor signed `transaction`, before it is sent.
+    pub fn mark_submitted(&self, id: i64, transaction: &Transaction) -> Result<()> {
+        let data = bincode::serialize(transaction).expect("transactions serialize");
+        let signature = transaction.signatures[0].to_string();
+        self.update(
+            "transaction_data = ?3, signature = ?4",
+            params![id, JobStatus::Submitted.as_str(), data, signature],
+        )
+    }
+
+    pub fn mark_confirmed(&self, id: i64) -> Result<()> {
+        self.update("error = NULL", params![id, JobStatus::Confirmed.as_str()])
+    }
+
+    pub fn mark_failed(&self, id: i64, error: &str) -> Result<()> {
+        self.update("error = ?3", params![id, JobStatus::Failed.as_str(), error])
+    }
+
+    pub fn mark_expired(&self, id: i64) -> Result<()> {
+        self.update("error = NULL", params![id, JobStatus::Expired.as_str()])
+    }
+
+    /// Set the status `?2` of job `?1`, and `columns`
+    fn update(&self, columns: &str, params: &[&dyn ToSql]) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            &format!(
+                "UPDATE jobs SET status = ?2, {}, updated_at = strftime('%s', 'now') WHERE id = ?1",
+                columns
+            ),
+            params,
+        )?;
+        Ok(())
+    }
+
+    /// Jobs a crash may have interrupted: pending or submitted, oldest first.
+    pub fn unfinished(&self) -> Result<Vec<Job>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT * FROM jobs WHERE status IN ('pending', 'submitted') ORDER BY id",
+        )?;
+        let jobs = statement
+            .query_map([], Job::from_row)?
+            .collect::<rusqlite::Result<Vec<Job>>>()?;
+        Ok(jobs)
+    }
+}
@@ -0,0 +1,435 @@
+//! Validation and execution of the meta-transactions consumers sign.
+//!
+//! A meta-transaction is a transaction holding a single SolGSN submission,
+//! and optionally compute budget instructions, signed by the consumer with the
+//! relayer's executor as fee payer. The relayer checks it against the
+//! deployment's state and its [`ProfitPolicy`], simulates it and only then
+//! signs and sends it. Accepted meta-transactions go through a
+//! [`JobQueue`], so restarts neither drop nor repeat them, and their progress
+//! is published through a [`Notifier`].
+
+use crate::{
+    error::{RelayerError, Result},
+    notify::{Event, EventKind, Notifier},
+    profit::{Estimate, ProfitPolicy},
+    queue::{Enqueued, Job, JobQueue, JobStatus},
+};
+use solana_sdk::{
+    compute_budget,
+    instruction::CompiledInstruction,
+    pubkey::Pubkey,
+    sanitize::Sanitize,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, SubmitArgs, SubmitLaneArgs},
+    state::{GsnConfig, CHANNEL_NONCE_LANE, UNORDERED_NONCE_LANE},
+    GsnInfo,
+};
+use solgsn_client::{error::gsn_error, ClientError, GsnClient};
+use std::{
+    convert::{TryFrom, TryInto},
+    sync::Arc,
+};
+
+const SUBMIT_TRANSACTION: u8 = 2;
+const SUBMIT_TRANSACTION_IN_LANE: u8 = 8;
+const SUBMIT_TOKEN_FEE: u8 = 24;
+
+/// The submission of a meta-transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetaTransaction {
+    pub consumer: Pubkey,
+    /// Program invoked with the consumer's transfer
+    pub target: Pubkey,
+    pub amount: u64,
+    pub nonce: u64,
+    pub lane: u64,
+    /// Mint of the token balance the fee is charged to, none for lamports
+    pub fee_mint: Option<Pubkey>,
+    /// Highest fee the consumer agreed to
+    pub max_fee: Option<u64>,
+    pub tip: u64,
+}
+
+/// Relayer executing meta-transactions of the deployment `gsn` with `executor`.
+pub struct Relayer {
+    pub client: GsnClient,
+    pub gsn: Pubkey,
+    pub executor: Keypair,
+    pub queue: JobQueue,
+    pub policy: ProfitPolicy,
+    pub notifier: Arc<Notifier>,
+}
+
+impl Relayer {
+    pub fn new(client: GsnClient, gsn: Pubkey, executor: Keypair, queue: JobQueue) -> Self {
+        Self {
+            client,
+            gsn,
+            executor,
+            queue,
+            policy: ProfitPolicy::default(),
+            notifier: Arc::new(Notifier::new()),
+        }
+    }
+
+    /// Only execute meta-transactions `policy` accepts.
+    pub fn with_policy(mut self, policy: ProfitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Queue `transaction` and execute it. A nonce queued already isn't
+    /// executed again: its signature is returned once it was sent.
+    pub fn relay(&self, transaction: Transaction) -> Result<Signature> {
+        let program_id = *self.client.program_id();
+        let meta = parse(&transaction, &program_id, &self.gsn, &self.executor.pubkey())?;
+        match self.queue.enqueue(&meta, &transaction)? {
+            Enqueued::New(job) => {
+                self.notifier.publish(Event::new(EventKind::Accepted, &job));
+                self.process(&job, &meta)
+            }
+            Enqueued::Existing(job) => job.signature.ok_or(RelayerError::AlreadyQueued(job.id)),
+        }
+    }
+
+    /// Execute the pending `job`, recording its progress in the queue.
+    fn process(&self, job: &Job, meta: &MetaTransaction) -> Result<Signature> {
+        let result = self.execute(job, meta);
+        match &result {
+            Ok(signature) => {
+                self.queue.mark_confirmed(job.id)?;
+                let event = Event::new(EventKind::Confirmed, job).with_signature(*signature);
+                self.notifier.publish(event);
+            }
+            // Sent, it may still land; `recover` resolves it
+            Err(RelayerError::Client(_)) if self.is_submitted(job.id)? => {}
+            Err(error) => {
+                self.queue.mark_failed(job.id, &error.to_string())?;
+                self.notifier.publish(Event::new(EventKind::Rejected, job).with_error(error));
+            }
+        }
+        result
+    }
+
+    /// Validate `job` against the current state and the profit policy,
+    /// simulate it, then sign it as fee payer and send it.
+    fn execute(&self, job: &Job, meta: &MetaTransaction) -> Result<Signature> {
+        let program_id = *self.client.program_id();
+        let gsn = self.client.get_state(&self.gsn)?;
+        let config = self.client.get_config(&gsn.config)?;
+        let epoch = self.client.rpc().get_epoch_info()?.epoch;
+        let fee = check_state(meta, &gsn, &config, &self.executor.pubkey(), epoch)?;
+        let estimate = Estimate::new(meta, fee, &config, &job.transaction.message);
+        let accepted = self.policy.accepts(&estimate);
+        println!(
+            "[RELAYER] profitability job={} consumer={} nonce={} credit={:?} cost={} decision={}",
+            job.id,
+            meta.consumer,
+            meta.nonce,
+            estimate.credit,
+            estimate.cost,
+            if accepted { "relay" } else { "skip" }
+        );
+        if !accepted {
+            return Err(RelayerError::Unprofitable {
+                credit: estimate.credit.unwrap_or(0),
+                cost: estimate.cost,
+            });
+        }
+
+        let mut transaction = job.transaction.clone();
+        let blockhash = transaction.message.recent_blockhash;
+        transaction
+            .try_partial_sign(&[&self.executor], blockhash)
+            .map_err(|error| RelayerError::InvalidTransaction(error.to_string()))?;
+        let simulation = self.client.rpc().simulate_transaction(&transaction)?.value;
+        if let Some(error) = simulation.err {
+            if failed_in_target(simulation.logs.as_deref().unwrap_or(&[]), &meta.target) {
+                self.report_failure(&gsn, meta);
+            }
+            return Err(match gsn_error(&error, &program_id, &transaction.message) {
+                Some(error) => RelayerError::Rejected(error),
+                None => RelayerError::Simulation(error.to_string()),
+            });
+        }
+        self.queue.mark_submitted(job.id, &transaction)?;
+        let event = Event::new(EventKind::Submitted, job).with_signature(transaction.signatures[0]);
+        self.notifier.publish(event);
+        Ok(self.client.send_transaction(&transaction)?)
+    }
+
+    /// Report the relay of `meta`, which failed in its target program, to the
+    /// circuit breaker while it is on and the executor is registered. The
+    /// relay failed either way, so a failed report is only logged.
+    fn report_failure(&self, gsn: &GsnInfo, meta: &MetaTransaction) {
+        let executor = self.executor.pubkey();
+        if !gsn.circuit_breaker.is_enabled()
+            || !gsn.is_executor_registered(&executor)
+            || meta.lane == UNORDERED_NONCE_LANE
+        {
+            return;
+        }
+        let report = instruction::report_failure(
+            self.client.program_id(),
+            &self.gsn,
+            &executor,
+            &meta.consumer,
+            meta.lane,
+            meta.nonce,
+        );
+        let result = self.client.send(&[report], &self.executor, &[&self.executor]);
+        println!(
+            "[RELAYER] failure report consumer={} lane={} nonce={} result={:?}",
+            meta.consumer, meta.lane, meta.nonce, result
+        );
+    }
+
+    fn is_submitted(&self, id: i64) -> Result<bool> {
+        let job = self.queue.get(id)?;
+        Ok(job.is_some_and(|job| job.status == JobStatus::Submitted))
+    }
+
+    /// Finish the jobs a crash interrupted. Pending ones are executed; sent
+    /// ones are confirmed or failed by their signature's status, resent while
+    /// their blockhash is valid and expired after.
+    pub fn recover(&self) -> Result<()> {
+        let program_id = *self.client.program_id();
+        for job in self.queue.unfinished()? {
+            if job.status == JobStatus::Pending {
+                let executor = self.executor.pubkey();
+                let result = match parse(&job.transaction, &program_id, &self.gsn, &executor) {
+                    Ok(meta) => self.process(&job, &meta),
+                    Err(error) => {
+                        self.queue.mark_failed(job.id, &error.to_string())?;
+                        let event = Event::new(EventKind::Rejected, &job).with_error(&error);
+                        self.notifier.publish(event);
+                        Err(error)
+                    }
+                };
+                println!("[RELAYER] recovered job={} result={:?}", job.id, result);
+                continue;
+            }
+
+            let rpc = self.client.rpc();
+            let signature = job.transaction.signatures[0];
+            let event = match rpc.get_signature_status(&signature)? {
+                Some(Ok(())) => {
+                    self.queue.mark_confirmed(job.id)?;
+                    Event::new(EventKind::Confirmed, &job)
+                }
+                Some(Err(error)) => {
+                    self.queue.mark_failed(job.id, &error.to_string())?;
+                    let code = gsn_error(&error, &program_id, &job.transaction.message);
+                    Event::new(EventKind::Rejected, &job).with_reason(error.to_string(), code)
+                }
+                None => {
+                    let blockhash = job.transaction.message.recent_blockhash;
+                    if !rpc.is_blockhash_valid(&blockhash, rpc.commitment())? {
+                        self.queue.mark_expired(job.id)?;
+                        let reason = "blockhash expired before it landed".to_string();
+                        Event::new(EventKind::Rejected, &job).with_reason(reason, None)
+                    } else {
+                        // The same signature executes at most once
+                        match self.client.send_transaction(&job.transaction) {
+                            Ok(_) => {
+                                self.queue.mark_confirmed(job.id)?;
+                                Event::new(EventKind::Confirmed, &job)
+                            }
+                            Err(ClientError::Program(error)) => {
+                                self.queue.mark_failed(job.id, &error.to_string())?;
+                                let reason = error.to_string();
+                                Event::new(EventKind::Rejected, &job)
+                                    .with_reason(reason, Some(error))
+                            }
+                            // Left submitted for the next recovery
+                            Err(error) => return Err(error.into()),
+                        }
+                    }
+                }
+            };
+            self.notifier.publish(event);
+            let status = self.queue.get(job.id)?.map(|job| job.status);
+            println!("[RELAYER] recovered job={} status={:?}", job.id, status);
+        }
+        Ok(())
+    }
+}
+
+/// Whether the simulation `logs` show `target` failing. The runtime aborts
+/// the submission that invoked it, so the program can't count the failure.
+pub fn failed_in_target(logs: &[String], target: &Pubkey) -> bool {
+    let failed = format!("Program {} failed", target);
+    logs.iter().any(|line| line.starts_with(&failed))
+}
+
+/// Decode the submission of `transaction`, checking it is a single SolGSN
+/// submission of `gsn` that `executor` pays for and the consumer signed.
+/// Compute budget instructions may set its compute unit limit and price.
+pub fn parse(
+    transaction: &Transaction,
+    program_id: &Pubkey,
+    gsn: &Pubkey,
+    executor: &Pubkey,
+) -> Result<MetaTransaction> {
+    let invalid = |reason: &str| RelayerError::InvalidTransaction(reason.to_string());
+    transaction
+        .sanitize()
+        .map_err(|error| RelayerError::InvalidTransaction(error.to_string()))?;
+    let message = &transaction.message;
+    let program = |instruction: &CompiledInstruction| {
+        message.account_keys.get(instruction.program_id_index as usize)
+    };
+    let (submissions, others): (Vec<_>, Vec<_>) = message
+        .instructions
+        .iter()
+        .partition(|instruction| program(instruction) == Some(program_id));
+    let is_compute_budget = |instruction: &&CompiledInstruction| {
+        program(instruction) == Some(&compute_budget::id()) && instruction.accounts.is_empty()
+    };
+    if submissions.len() != 1 || !others.iter().all(is_compute_budget) {
+        return Err(invalid("expected a single SolGSN instruction"));
+    }
+    if message.account_keys.first() != Some(executor) {
+        return Err(invalid("the executor must pay the fee"));
+    }
+
+    // Accounts: target program, sender, receiver, fee payer, GSN state, config
+    let instruction = submissions[0];
+    let key = |position: usize| {
+        instruction
+            .accounts
+            .get(position)
+            .and_then(|index| message.account_keys.get(*index as usize).copied())
+            .ok_or_else(|| invalid("missing submission accounts"))
+    };
+    let (target, consumer) = (key(0)?, key(1)?);
+    if key(3)? != *executor || key(4)? != *gsn {
+        return Err(invalid("not a submission of this relayer's deployment"));
+    }
+    // The executor's signature must only authorize paying the fee
+    let executor_index = instruction.accounts[3];
+    if instruction
+        .accounts
+        .iter()
+        .enumerate()
+        .any(|(position, index)| *index == executor_index && position != 3)
+    {
+        return Err(invalid("the executor may only be the fee payer"));
+    }
+
+    let meta = decode_submission(&instruction.data, consumer, target)
+        .ok_or_else(|| invalid("unsupported instruction"))?;
+    if meta.consumer == *executor {
+        return Err(invalid("the executor may only be the fee payer"));
+    }
+
+    // Every signature but the fee payer's must be there already
+    let verified = transaction.verify_with_results();
+    if verified.len() < 2 || !verified[1..].iter().all(|valid| *valid) {
+        return Err(invalid("missing or invalid consumer signature"));
+    }
+    Ok(meta)
+}
+
+/// The submission of the `SubmitTransaction`, `SubmitTransactionInLane` or
+/// `SubmitTokenFee` instruction `data`. Dry runs aren't relayed: they earn
+/// the executor nothing.
+fn decode_submission(data: &[u8], consumer: Pubkey, target: Pubkey) -> Option<MetaTransaction> {
+    let read_u64 = |offset: usize| {
+        data.get(offset..offset + 8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let mut meta = MetaTransaction {
+        consumer,
+        target,
+        amount: read_u64(1)?,
+        nonce: read_u64(9)?,
+        lane: 0,
+        fee_mint: None,
+        max_fee: None,
+        tip: 0,
+    };
+    match *data.first()? {
+        SUBMIT_TRANSACTION => {
+            let args = SubmitArgs::unpack(data).ok().filter(|args| !args.simulate)?;
+            meta.max_fee = args.max_fee;
+            meta.tip = args.tip;
+        }
+        SUBMIT_TRANSACTION_IN_LANE => {
+            let args = SubmitLaneArgs::unpack(data).ok()?;
+            meta.lane = args.lane;
+            meta.max_fee = args.max_fee;
+            meta.tip = args.tip;
+        }
+        SUBMIT_TOKEN_FEE if data.len() == 57 => {
+            meta.lane = read_u64(17)?;
+            meta.fee_mint = Some(Pubkey::try_from(&data[25..57]).ok()?);
+        }
+        _ => return None,
+    }
+    Some(meta)
+}
+
+/// Check `meta` against the deployment's state in `epoch`, rejecting what the
+/// program would: unknown programs, used nonces, tokens it doesn't accept and
+/// balances that don't cover the fee. Returns the fee it is charged.
+pub fn check_state(
+    meta: &MetaTransaction,
+    gsn: &GsnInfo,
+    config: &GsnConfig,
+    executor: &Pubkey,
+    epoch: u64,
+) -> std::result::Result<u64, GsnError> {
+    gsn.check_not_paused()?;
+    if !config.is_program_allowed(&meta.target) {
+        return Err(GsnError::ProgramNotAllowed);
+    }
+
+    let consumer = &meta.consumer;
+    if meta.lane == UNORDERED_NONCE_LANE {
+        if gsn.is_unordered_nonce_used(consumer, meta.nonce) {
+            return Err(GsnError::ReplayAttack);
+        }
+    } else {
+        let expected = if meta.lane == CHANNEL_NONCE_LANE {
+            gsn.get_next_channel_nonce(consumer, executor)
+        } else {
+            gsn.get_next_lane_nonce(consumer, meta.lane)
+        };
+        if meta.nonce < expected {
+            return Err(GsnError::ReplayAttack);
+        }
+        if meta.nonce != expected {
+            return Err(GsnError::InvalidNonce);
+        }
+    }
+
+    if let Some(mint) = &meta.fee_mint {
+        if !config.is_token_allowed(mint) {
+            return Err(GsnError::TokenNotAllowed);
+        }
+    }
+    let fee = config.apply_volume_discount(
+        config.calculate_fee(meta.amount),
+        gsn.epoch_txs(consumer, epoch),
+    );
+    // Sandbox and free tier submissions may not be charged to the consumer
+    if config.sandbox.is_some() || config.free_tier.is_some() {
+        return Ok(fee);
+    }
+    if meta.max_fee.is_some_and(|max_fee| fee > max_fee) {
+        return Err(GsnError::FeeTooHigh);
+    }
+    let balance = match &meta.fee_mint {
+        Some(mint) => gsn.token_consumer.get(&(*consumer, *mint)).copied(),
+        None => gsn.consumer.get(consumer).copied(),
+    };
+    if balance.unwrap_or(0) < fee.saturating_add(meta.tip) {
+        return Err(GsnError::InsufficientBalance);
+    }
+    Ok(fee)
+}
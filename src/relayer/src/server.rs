@@ -0,0 +1,219 @@
+//! HTTP API of the relayer.
+//!
+//! `GET /info` returns the program, deployment and executor a meta-transaction
+//! must use. `POST /relay` takes `{"transaction": <base64>}`, a bincode
+//! serialized transaction the consumer signed, and returns
+//! `{"signature": <signature>}` once it is confirmed, or `{"error": <message>}`
+//! along with the program's error `code` when the program would reject it.
+//!
+//! `GET /events?consumers=<pubkey>,...` upgrades to a WebSocket streaming the
+//! [`Event`]s of those consumers, or of all of them without the parameter.
+//! `POST /webhooks` takes `{"url": <url>, "consumers": [<pubkey>, ...]}` and
+//! returns the webhook's `{"id": <id>}`; `DELETE /webhooks/<id>` removes it.
+
+use crate::{
+    error::RelayerError,
+    notify::{follows, Event},
+    relay::Relayer,
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use std::sync::Arc;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+#[derive(Deserialize)]
+struct RelayRequest {
+    /// Base64 of the bincode serialized transaction
+    transaction: String,
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    /// Comma separated consumers to follow, all of them if absent
+    consumers: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WebhookRequest {
+    url: String,
+    /// Consumers to follow, all of them if empty
+    #[serde(default)]
+    consumers: Vec<String>,
+}
+
+pub fn router(relayer: Arc<Relayer>) -> Router {
+    Router::new()
+        .route("/info", get(info))
+        .route("/relay", post(relay))
+        .route("/events", get(events))
+        .route("/webhooks", post(register_webhook))
+        .route("/webhooks/:id", delete(remove_webhook))
+        .with_state(relayer)
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/json")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+fn error_response(error: RelayerError) -> Response {
+    let status = match &error {
+        RelayerError::InvalidTransaction(_) => StatusCode::BAD_REQUEST,
+        RelayerError::Rejected(_)
+        | RelayerError::Simulation(_)
+        | RelayerError::Unprofitable { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        RelayerError::AlreadyQueued(_) => StatusCode::CONFLICT,
+        RelayerError::Client(_) => StatusCode::BAD_GATEWAY,
+        RelayerError::Queue(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let mut body = json!({ "error": error.to_string() });
+    if let RelayerError::Rejected(gsn_error) = &error {
+        body["code"] = json!(gsn_error.clone() as u32);
+    }
+    json_response(status, body)
+}
+
+async fn info(State(relayer): State<Arc<Relayer>>) -> Response {
+    json_response(
+        StatusCode::OK,
+        json!({
+            "program_id": relayer.client.program_id().to_string(),
+            "gsn": relayer.gsn.to_string(),
+            "executor": relayer.executor.pubkey().to_string(),
+        }),
+    )
+}
+
+/// Decode the transaction of a `POST /relay` body.
+pub fn decode_request(body: &str) -> Result<Transaction, RelayerError> {
+    let invalid =
+        |error: &dyn std::fmt::Display| RelayerError::InvalidTransaction(error.to_string());
+    let request: RelayRequest = serde_json::from_str(body).map_err(|error| invalid(&error))?;
+    let bytes = STANDARD
+        .decode(request.transaction)
+        .map_err(|error| invalid(&error))?;
+    bincode::deserialize(&bytes).map_err(|error| invalid(&error))
+}
+
+async fn relay(State(relayer): State<Arc<Relayer>>, body: String) -> Response {
+    let transaction = match decode_request(&body) {
+        Ok(transaction) => transaction,
+        Err(error) => return error_response(error),
+    };
+    // The RPC client blocks, keep it off the async workers
+    let result = tokio::task::spawn_blocking(move || relayer.relay(transaction)).await;
+    match result {
+        Ok(Ok(signature)) => {
+            println!("[RELAYER] relayed signature={}", signature);
+            json_response(StatusCode::OK, json!({ "signature": signature.to_string() }))
+        }
+        Ok(Err(error)) => {
+            println!("[RELAYER] rejected error={}", error);
+            error_response(error)
+        }
+        Err(error) => json_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": error.to_string() }),
+        ),
+    }
+}
+
+/// Parse the consumers of a subscription.
+pub fn parse_consumers<S: AsRef<str>>(consumers: &[S]) -> Result<Vec<Pubkey>, String> {
+    consumers
+        .iter()
+        .map(|consumer| {
+            let consumer = consumer.as_ref().trim();
+            consumer
+                .parse()
+                .map_err(|_| format!("invalid consumer `{}`", consumer))
+        })
+        .collect()
+}
+
+fn bad_request(error: String) -> Response {
+    json_response(StatusCode::BAD_REQUEST, json!({ "error": error }))
+}
+
+async fn events(
+    State(relayer): State<Arc<Relayer>>,
+    Query(query): Query<EventsQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let consumers = match &query.consumers {
+        Some(consumers) => consumers.split(',').collect(),
+        None => vec![],
+    };
+    let consumers = match parse_consumers(&consumers) {
+        Ok(consumers) => consumers,
+        Err(error) => return bad_request(error),
+    };
+    let events = relayer.notifier.subscribe();
+    upgrade.on_upgrade(move |socket| stream_events(socket, events, consumers))
+}
+
+/// Forward the events of `consumers` to `socket` until it closes.
+async fn stream_events(mut socket: WebSocket, mut events: Receiver<Event>, consumers: Vec<Pubkey>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if follows(&consumers, &event) => {
+                    let message = Message::Text(event.to_json().to_string());
+                    if socket.send(message).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    println!("[RELAYER] subscriber lagged skipped={}", skipped);
+                }
+                Err(RecvError::Closed) => return,
+            },
+            // Subscribers only ever close the socket
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn register_webhook(State(relayer): State<Arc<Relayer>>, body: String) -> Response {
+    let request: WebhookRequest = match serde_json::from_str(&body) {
+        Ok(request) => request,
+        Err(error) => return bad_request(error.to_string()),
+    };
+    let registered = parse_consumers(&request.consumers)
+        .and_then(|consumers| relayer.notifier.register_webhook(request.url.clone(), consumers));
+    match registered {
+        Ok(id) => {
+            println!("[RELAYER] webhook registered id={} url={}", id, request.url);
+            json_response(StatusCode::OK, json!({ "id": id }))
+        }
+        Err(error) => bad_request(error),
+    }
+}
+
+async fn remove_webhook(State(relayer): State<Arc<Relayer>>, Path(id): Path<u64>) -> Response {
+    if relayer.notifier.remove_webhook(id) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        json_response(StatusCode::NOT_FOUND, json!({ "error": "unknown webhook" }))
+    }
+}
@@ -0,0 +1,115 @@
+// Tests for the relayer's notifications of relay progress
+
+use serde_json::json;
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use solgsn::GsnError;
+use solgsn_relayer::{
+    notify::{EventKind, Webhook},
+    queue::{Enqueued, Job},
+    server::parse_consumers,
+    Event, JobQueue, MetaTransaction, Notifier, RelayerError,
+};
+
+/// A queued job of `consumer`
+fn job(consumer: Pubkey) -> Job {
+    let meta = MetaTransaction {
+        consumer,
+        target: Pubkey::new_unique(),
+        amount: 1_000,
+        nonce: 3,
+        lane: 0,
+        fee_mint: None,
+        max_fee: None,
+        tip: 0,
+    };
+    let payer = Keypair::new();
+    let transfer = system_instruction::transfer(&payer.pubkey(), &consumer, 1);
+    let transaction = Transaction::new_signed_with_payer(
+        &[transfer],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::default(),
+    );
+    match JobQueue::in_memory().unwrap().enqueue(&meta, &transaction).unwrap() {
+        Enqueued::New(job) => job,
+        Enqueued::Existing(job) => panic!("job {} exists", job.id),
+    }
+}
+
+#[test]
+fn test_event_json_decodes_failures() {
+    let consumer = Pubkey::new_unique();
+    let job = job(consumer);
+    let error = RelayerError::Rejected(GsnError::InsufficientBalance);
+    let event = Event::new(EventKind::Rejected, &job).with_error(&error);
+    assert_eq!(
+        event.to_json(),
+        json!({
+            "event": "rejected",
+            "job": job.id,
+            "consumer": consumer.to_string(),
+            "lane": 0,
+            "nonce": 3,
+            "signature": null,
+            "reason": error.to_string(),
+            "code": GsnError::InsufficientBalance as u32,
+        })
+    );
+
+    let signature = Signature::new_unique();
+    let event = Event::new(EventKind::Confirmed, &job).with_signature(signature);
+    let value = event.to_json();
+    assert_eq!(value["event"], "confirmed");
+    assert_eq!(value["signature"], signature.to_string());
+    assert_eq!(value["code"], json!(null));
+}
+
+#[test]
+fn test_subscribers_receive_published_events() {
+    let notifier = Notifier::new();
+    let mut events = notifier.subscribe();
+    let event = Event::new(EventKind::Accepted, &job(Pubkey::new_unique()));
+    notifier.publish(event.clone());
+    assert_eq!(events.try_recv().unwrap(), event);
+}
+
+#[test]
+fn test_webhooks_follow_their_consumers() {
+    let notifier = Notifier::new();
+    let (followed, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    assert!(notifier
+        .register_webhook("ftp://example.com".to_string(), vec![])
+        .is_err());
+    let url = "https://example.com/hook".to_string();
+    let id = notifier.register_webhook(url.clone(), vec![followed]).unwrap();
+    let all = notifier
+        .register_webhook("http://localhost:9000".to_string(), vec![])
+        .unwrap();
+
+    let event = Event::new(EventKind::Submitted, &job(followed));
+    assert_eq!(notifier.webhooks_for(&event).len(), 2);
+    let event = Event::new(EventKind::Submitted, &job(other));
+    let webhooks = notifier.webhooks_for(&event);
+    assert_eq!(webhooks.iter().map(|webhook| webhook.id).collect::<Vec<_>>(), vec![all]);
+
+    assert!(notifier.remove_webhook(all));
+    assert!(!notifier.remove_webhook(all));
+    let event = Event::new(EventKind::Submitted, &job(followed));
+    assert_eq!(
+        notifier.webhooks_for(&event),
+        vec![Webhook {
+            id,
+            url,
+            consumers: vec![followed],
+        }]
+    );
+
+    assert_eq!(parse_consumers(&[followed.to_string()]), Ok(vec![followed]));
+    assert!(parse_consumers(&["nope"]).is_err());
+}
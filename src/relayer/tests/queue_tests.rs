@@ -0,0 +1,100 @@
+// Tests for the relayer's durable job queue
+
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use solgsn_relayer::{
+    queue::{Enqueued, JobStatus},
+    JobQueue, MetaTransaction,
+};
+
+fn meta(consumer: Pubkey, nonce: u64) -> MetaTransaction {
+    MetaTransaction {
+        consumer,
+        target: Pubkey::new_unique(),
+        amount: 1_000,
+        nonce,
+        lane: 0,
+        fee_mint: None,
+        max_fee: None,
+        tip: 0,
+    }
+}
+
+fn transaction(payer: &Keypair) -> Transaction {
+    let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+    let payer_key = Some(&payer.pubkey());
+    Transaction::new_signed_with_payer(&[transfer], payer_key, &[payer], Hash::default())
+}
+
+fn new_job(enqueued: Enqueued) -> i64 {
+    match enqueued {
+        Enqueued::New(job) => job.id,
+        Enqueued::Existing(job) => panic!("job {} exists", job.id),
+    }
+}
+
+#[test]
+fn test_nonce_is_queued_once() {
+    let queue = JobQueue::in_memory().unwrap();
+    let payer = Keypair::new();
+    let consumer = Pubkey::new_unique();
+    let id = new_job(queue.enqueue(&meta(consumer, 0), &transaction(&payer)).unwrap());
+    assert_eq!(queue.get(id).unwrap().unwrap().status, JobStatus::Pending);
+
+    // Resending the same nonce finds the job instead of executing it twice
+    let signed = transaction(&payer);
+    queue.mark_submitted(id, &signed).unwrap();
+    match queue.enqueue(&meta(consumer, 0), &transaction(&payer)).unwrap() {
+        Enqueued::Existing(job) => {
+            assert_eq!(job.status, JobStatus::Submitted);
+            assert_eq!(job.signature, Some(signed.signatures[0]));
+        }
+        Enqueued::New(_) => panic!("queued twice"),
+    }
+    // Other nonces and lanes are separate jobs
+    new_job(queue.enqueue(&meta(consumer, 1), &transaction(&payer)).unwrap());
+    let mut other_lane = meta(consumer, 0);
+    other_lane.lane = u64::MAX;
+    new_job(queue.enqueue(&other_lane, &transaction(&payer)).unwrap());
+
+    // A failed job frees its nonce
+    queue.mark_failed(id, "Rejected: InvalidNonce").unwrap();
+    assert_eq!(
+        queue.get(id).unwrap().unwrap().error.as_deref(),
+        Some("Rejected: InvalidNonce")
+    );
+    new_job(queue.enqueue(&meta(consumer, 0), &transaction(&payer)).unwrap());
+}
+
+#[test]
+fn test_queue_survives_restarts() {
+    let path = std::env::temp_dir().join(format!("solgsn-queue-{}.db", Pubkey::new_unique()));
+    let payer = Keypair::new();
+    let signed = transaction(&payer);
+    let (pending, submitted, confirmed) = {
+        let queue = JobQueue::open(&path).unwrap();
+        let enqueue = |nonce| {
+            new_job(queue.enqueue(&meta(Pubkey::new_unique(), nonce), &signed).unwrap())
+        };
+        let ids = (enqueue(0), enqueue(1), enqueue(2));
+        queue.mark_submitted(ids.1, &signed).unwrap();
+        queue.mark_submitted(ids.2, &signed).unwrap();
+        queue.mark_confirmed(ids.2).unwrap();
+        ids
+    };
+
+    // Reopened, the interrupted jobs are left to recover
+    let queue = JobQueue::open(&path).unwrap();
+    let unfinished = queue.unfinished().unwrap();
+    let ids: Vec<i64> = unfinished.iter().map(|job| job.id).collect();
+    assert_eq!(ids, vec![pending, submitted]);
+    assert_eq!(unfinished[1].transaction, signed);
+    assert_eq!(unfinished[1].lane, 0);
+    assert_eq!(queue.get(confirmed).unwrap().unwrap().status, JobStatus::Confirmed);
+    std::fs::remove_file(path).unwrap();
+}
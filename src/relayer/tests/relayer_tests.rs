@@ -0,0 +1,266 @@
+// Tests for the validation of meta-transactions by the SolGSN relayer
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use solgsn::{
+    error::GsnError,
+    instruction::{self, SubmitArgs, SubmitLaneArgs},
+    state::{FeeMode, GsnConfig},
+    GsnInfo,
+};
+use solgsn_relayer::{
+    profit::{network_fee, Estimate},
+    relay::{check_state, failed_in_target, parse},
+    server::decode_request,
+    MetaTransaction, ProfitPolicy, RelayerError,
+};
+
+const FEE: u64 = 5_000;
+
+struct Setup {
+    program_id: Pubkey,
+    gsn: Pubkey,
+    config: Pubkey,
+    consumer: Keypair,
+    executor: Keypair,
+}
+
+impl Setup {
+    fn new() -> Self {
+        Self {
+            program_id: Pubkey::new_unique(),
+            gsn: Pubkey::new_unique(),
+            config: Pubkey::new_unique(),
+            consumer: Keypair::new(),
+            executor: Keypair::new(),
+        }
+    }
+
+    fn submit(&self, sender: &Pubkey, nonce: u64) -> Instruction {
+        let args = SubmitArgs {
+            amount: 1_000,
+            nonce,
+            expiry_slot: None,
+            max_fee: Some(FEE),
+            tip: 0,
+            simulate: false,
+            quote: None,
+        };
+        instruction::submit_transaction(
+            &self.program_id,
+            &self.gsn,
+            &self.config,
+            sender,
+            &Pubkey::new_unique(),
+            &self.executor.pubkey(),
+            &args,
+        )
+    }
+
+    /// `instructions` paid by `fee_payer` and signed by the consumer only
+    fn signed(&self, instructions: &[Instruction], fee_payer: &Pubkey) -> Transaction {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(fee_payer));
+        transaction.partial_sign(&[&self.consumer], Hash::default());
+        transaction
+    }
+
+    fn parse(&self, transaction: &Transaction) -> Result<MetaTransaction, RelayerError> {
+        parse(transaction, &self.program_id, &self.gsn, &self.executor.pubkey())
+    }
+}
+
+fn invalid<T>(result: Result<T, RelayerError>) -> bool {
+    matches!(result, Err(RelayerError::InvalidTransaction(_)))
+}
+
+#[test]
+fn test_parse_meta_transaction() {
+    let setup = Setup::new();
+    let submit = setup.submit(&setup.consumer.pubkey(), 4);
+    let transaction = setup.signed(&[submit], &setup.executor.pubkey());
+    assert_eq!(
+        setup.parse(&transaction).unwrap(),
+        MetaTransaction {
+            consumer: setup.consumer.pubkey(),
+            target: system_program::id(),
+            amount: 1_000,
+            nonce: 4,
+            lane: 0,
+            fee_mint: None,
+            max_fee: Some(FEE),
+            tip: 0,
+        }
+    );
+
+    // Lane submissions carry the same maximum fee and tip
+    let args = SubmitLaneArgs {
+        amount: 1_000,
+        nonce: 4,
+        lane: 3,
+        expiry_slot: None,
+        max_fee: Some(FEE),
+        tip: 500,
+    };
+    let submit_in_lane = instruction::submit_transaction_in_lane(
+        &setup.program_id,
+        &setup.gsn,
+        &setup.config,
+        &setup.consumer.pubkey(),
+        &Pubkey::new_unique(),
+        &setup.executor.pubkey(),
+        &args,
+    );
+    let meta = setup
+        .parse(&setup.signed(&[submit_in_lane], &setup.executor.pubkey()))
+        .unwrap();
+    assert_eq!((meta.lane, meta.max_fee, meta.tip), (3, Some(FEE), 500));
+
+    // The relay request carries it as base64 of its bincode encoding
+    let encoded = STANDARD.encode(bincode::serialize(&transaction).unwrap());
+    let body = format!("{{\"transaction\": \"{}\"}}", encoded);
+    assert_eq!(decode_request(&body).unwrap(), transaction);
+    assert!(invalid(decode_request("{\"transaction\": \"???\"}")));
+}
+
+#[test]
+fn test_executor_signature_only_pays_the_fee() {
+    let setup = Setup::new();
+    let submit = setup.submit(&setup.consumer.pubkey(), 0);
+
+    // Someone else pays
+    let transaction = setup.signed(std::slice::from_ref(&submit), &setup.consumer.pubkey());
+    assert!(invalid(setup.parse(&transaction)));
+
+    // A second instruction would run on the executor's signature too
+    let other = Instruction::new_with_bytes(
+        system_program::id(),
+        &[],
+        vec![AccountMeta::new(setup.executor.pubkey(), true)],
+    );
+    let transaction = setup.signed(&[submit, other], &setup.executor.pubkey());
+    assert!(invalid(setup.parse(&transaction)));
+
+    // The executor as the sender would transfer its own lamports
+    let submit = setup.submit(&setup.executor.pubkey(), 0);
+    let transaction = Transaction::new_with_payer(&[submit], Some(&setup.executor.pubkey()));
+    assert!(invalid(setup.parse(&transaction)));
+}
+
+#[test]
+fn test_consumer_must_sign() {
+    let setup = Setup::new();
+    let submit = setup.submit(&setup.consumer.pubkey(), 0);
+    let transaction = Transaction::new_with_payer(&[submit], Some(&setup.executor.pubkey()));
+    assert!(invalid(setup.parse(&transaction)));
+}
+
+#[test]
+fn test_check_state() {
+    let setup = Setup::new();
+    let consumer = setup.consumer.pubkey();
+    let mut config = GsnConfig::new(setup.gsn);
+    config.initialize_governance(Pubkey::new_unique());
+    config.update_fee_params(FeeMode::Fixed(FEE));
+    let mut gsn = GsnInfo::new();
+    gsn.add_consumer(consumer, FEE);
+    gsn.increment_nonce(&consumer);
+
+    let transaction = setup.signed(&[setup.submit(&consumer, 1)], &setup.executor.pubkey());
+    let mut meta = setup.parse(&transaction).unwrap();
+    let check = |meta: &MetaTransaction, gsn: &GsnInfo, config: &GsnConfig| {
+        check_state(meta, gsn, config, &setup.executor.pubkey(), 0)
+    };
+    assert_eq!(check(&meta, &gsn, &config), Ok(FEE));
+
+    meta.nonce = 0;
+    assert_eq!(check(&meta, &gsn, &config), Err(GsnError::ReplayAttack));
+    meta.nonce = 2;
+    assert_eq!(check(&meta, &gsn, &config), Err(GsnError::InvalidNonce));
+    meta.nonce = 1;
+
+    meta.tip = 1;
+    assert_eq!(check(&meta, &gsn, &config), Err(GsnError::InsufficientBalance));
+    meta.tip = 0;
+    meta.max_fee = Some(FEE - 1);
+    assert_eq!(check(&meta, &gsn, &config), Err(GsnError::FeeTooHigh));
+    meta.max_fee = None;
+
+    // Token fees need a mint governance accepts
+    config.add_allowed_token(Pubkey::new_unique());
+    meta.fee_mint = Some(Pubkey::new_unique());
+    assert_eq!(check(&meta, &gsn, &config), Err(GsnError::TokenNotAllowed));
+}
+
+#[test]
+fn test_target_failures_are_told_from_the_logs() {
+    let (program_id, target) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let logs = vec![
+        format!("Program {} invoke [1]", program_id),
+        format!("Program {} invoke [2]", target),
+        format!("Program {} failed: custom program error: 0x1", target),
+        format!("Program {} failed: custom program error: 0x1", program_id),
+    ];
+    assert!(failed_in_target(&logs, &target));
+    // A check of the program failing isn't the target's
+    assert!(!failed_in_target(&logs[..1], &target));
+    assert!(!failed_in_target(&logs[3..], &target));
+}
+
+#[test]
+fn test_compute_budget_instructions_are_allowed() {
+    let setup = Setup::new();
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(50_000),
+        ComputeBudgetInstruction::set_compute_unit_price(1_000),
+        setup.submit(&setup.consumer.pubkey(), 0),
+    ];
+    let transaction = setup.signed(&instructions, &setup.executor.pubkey());
+    assert_eq!(setup.parse(&transaction).unwrap().nonce, 0);
+}
+
+#[test]
+fn test_profitability() {
+    let setup = Setup::new();
+    let submit = setup.submit(&setup.consumer.pubkey(), 0);
+    let transaction = setup.signed(std::slice::from_ref(&submit), &setup.executor.pubkey());
+    // Two signatures, no priority fee
+    assert_eq!(network_fee(&transaction.message), 10_000);
+
+    // 50_000 units at 20_000 micro-lamports
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(50_000),
+        ComputeBudgetInstruction::set_compute_unit_price(20_000),
+        submit,
+    ];
+    let priced = setup.signed(&instructions, &setup.executor.pubkey());
+    assert_eq!(network_fee(&priced.message), 11_000);
+
+    let mut config = GsnConfig::new(setup.gsn);
+    config.protocol_fee_bps = 1_000;
+    let mut meta = setup.parse(&transaction).unwrap();
+    meta.tip = 1_500;
+    let estimate = Estimate::new(&meta, 10_000, &config, &transaction.message);
+    assert_eq!(estimate.credit, Some(10_500));
+    assert_eq!(estimate.profit(), Some(500));
+
+    let policy = ProfitPolicy::default();
+    assert!(policy.accepts(&estimate));
+    assert!(!policy.accepts(&Estimate::new(&meta, 10_000, &config, &priced.message)));
+    let floor = ProfitPolicy {
+        min_credit: 20_000,
+        min_profit: -20_000,
+    };
+    assert!(!floor.accepts(&estimate));
+
+    // Token fees aren't priced in lamports
+    meta.fee_mint = Some(Pubkey::new_unique());
+    assert!(floor.accepts(&Estimate::new(&meta, 10_000, &config, &transaction.message)));
+}
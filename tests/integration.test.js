@@ -15,17 +15,26 @@ import {
     SystemProgram,
     LAMPORTS_PER_SOL,
     BpfLoader,
-    BPF_LOADER_DEPRECATED_PROGRAM_ID,
+    BPF_LOADER_PROGRAM_ID,
     Transaction,
     TransactionInstruction,
 } from '@solana/web3.js';
 import fs from 'mz/fs';
 import BN from 'bn.js';
 import * as BufferLayout from 'buffer-layout';
-import { topupWithParams, submitTxWithParams, claimFees, u64 } from '../src/client/index';
+import {
+    topupWithParams,
+    submitTxWithParams,
+    buildSubmitTxPayload,
+    relaySubmitPayload,
+    claimFees,
+    u64,
+} from '../src/client/index';
 import { newAccountWithLamports } from '../src/client/util/new-account-with-lamports';
 import { sendAndConfirmTransaction } from '../src/client/util/send-and-confirm-transaction';
+import { withdraw } from '../src/client/util/shutdown';
 import { Store } from '../src/client/util/store';
+import { deserializePayload, missingSigners } from '../src/client/util/versioned-transaction';
 
 // Test configuration
 const TEST_TIMEOUT = 300000; // 5 minutes
@@ -37,6 +46,7 @@ let connection;
 let payerAccount;
 let programId;
 let gsnAccount;
+let configAccount;
 let programAccount;
 
 /**
@@ -104,7 +114,7 @@ async function setup() {
             payerAccount,
             programAccount,
             programData,
-            BPF_LOADER_DEPRECATED_PROGRAM_ID,
+            BPF_LOADER_PROGRAM_ID,
         );
         
         programId = programAccount.publicKey;
@@ -119,6 +129,7 @@ async function setup() {
     // Initialize GSN account
     console.log('Initializing GSN account...');
     gsnAccount = new Account();
+    configAccount = new Account();
     
     const initTx = new Transaction().add(
         SystemProgram.createAccount({
@@ -128,6 +139,13 @@ async function setup() {
             space: 1024,
             programId,
         }),
+        SystemProgram.createAccount({
+            fromPubkey: payerAccount.publicKey,
+            newAccountPubkey: configAccount.publicKey,
+            lamports: 1000000,
+            space: 1024,
+            programId,
+        }),
     );
 
     await sendAndConfirmTransaction(
@@ -136,6 +154,7 @@ async function setup() {
         initTx,
         payerAccount,
         gsnAccount,
+        configAccount,
     );
 
     // Initialize instruction
@@ -147,6 +166,7 @@ async function setup() {
     const initInstruction = new TransactionInstruction({
         keys: [
             { pubkey: gsnAccount.publicKey, isSigner: false, isWritable: true },
+            { pubkey: configAccount.publicKey, isSigner: false, isWritable: true },
         ],
         programId,
         data: initData,
@@ -207,6 +227,7 @@ describe('SolGSN Integration Tests', () => {
         await topupWithParams(
             connection,
             gsnAccount,
+            configAccount.publicKey,
             consumerAccount,
             topupAmount,
             payerAccount,
@@ -232,6 +253,7 @@ describe('SolGSN Integration Tests', () => {
         await topupWithParams(
             connection,
             gsnAccount,
+            configAccount.publicKey,
             consumerAccount,
             topupAmount,
             payerAccount,
@@ -251,6 +273,7 @@ describe('SolGSN Integration Tests', () => {
         await topupWithParams(
             connection,
             gsnAccount,
+            configAccount.publicKey,
             consumerAccount,
             topupAmount,
             payerAccount,
@@ -283,6 +306,7 @@ describe('SolGSN Integration Tests', () => {
             receiverAccount,
             executorAccount,
             gsnAccount,
+            configAccount.publicKey,
             transferAmount,
             nonce,
             programId,
@@ -310,6 +334,7 @@ describe('SolGSN Integration Tests', () => {
         await topupWithParams(
             connection,
             gsnAccount,
+            configAccount.publicKey,
             consumerAccount,
             topupAmount,
             payerAccount,
@@ -331,6 +356,7 @@ describe('SolGSN Integration Tests', () => {
             receiverAccount,
             executorAccount,
             gsnAccount,
+            configAccount.publicKey,
             transferAmount,
             nonce,
             programId,
@@ -367,15 +393,13 @@ describe('SolGSN Integration Tests', () => {
     test('5. User/Dapp withdrawing remaining balance', async () => {
         console.log('\n=== Test 5: User withdrawal ===');
         
-        // Note: Withdrawal functionality is not yet implemented in the program
-        // This test documents the expected behavior
-        
         const consumerAccount = await newAccountWithLamports(connection, 2 * LAMPORTS_PER_SOL);
         const topupAmount = new u64(100000000);
         
         await topupWithParams(
             connection,
             gsnAccount,
+            configAccount.publicKey,
             consumerAccount,
             topupAmount,
             payerAccount,
@@ -395,6 +419,7 @@ describe('SolGSN Integration Tests', () => {
             receiverAccount,
             executorAccount,
             gsnAccount,
+            configAccount.publicKey,
             transferAmount,
             nonce,
             programId,
@@ -404,10 +429,66 @@ describe('SolGSN Integration Tests', () => {
         console.log('Initial top-up:', topupAmount.toString());
         console.log('Transaction executed, balance should be reduced by fee');
         
-        // Withdrawal instruction would be called here if implemented
-        // await withdraw(connection, gsnAccount, consumerAccount, amount, programId);
+        const consumerBalanceBefore = await connection.getBalance(consumerAccount.publicKey);
+        const withdrawAmount = 50000000;
+        await withdraw(
+            connection,
+            programId,
+            gsnAccount.publicKey,
+            consumerAccount,
+            withdrawAmount,
+        );
+        const consumerBalanceAfter = await connection.getBalance(consumerAccount.publicKey);
         
-        console.log('Note: Withdrawal functionality is planned but not yet implemented');
-        console.log('✓ Test completed (withdrawal not yet available)');
+        // The withdrawal's own transaction fee comes off the refund
+        expect(consumerBalanceAfter).toBeGreaterThan(consumerBalanceBefore);
+        expect(consumerBalanceAfter).toBeLessThanOrEqual(consumerBalanceBefore + withdrawAmount);
+        console.log('Withdrawn:', consumerBalanceAfter - consumerBalanceBefore);
+        console.log('✓ Withdrawal successful');
+    }, TEST_TIMEOUT);
+
+    test('6. Consumer-signed v0 payload relayed by the executor', async () => {
+        console.log('\n=== Test 6: Versioned payload relay ===');
+
+        const consumerAccount = await newAccountWithLamports(connection, 2 * LAMPORTS_PER_SOL);
+        await topupWithParams(
+            connection,
+            gsnAccount,
+            configAccount.publicKey,
+            consumerAccount,
+            new u64(100000000),
+            payerAccount,
+            programId,
+        );
+
+        const receiverAccount = await newAccountWithLamports(connection, 0);
+        const executorAccount = await newAccountWithLamports(connection, LAMPORTS_PER_SOL);
+
+        const payload = await buildSubmitTxPayload(
+            connection,
+            SystemProgram.programId,
+            consumerAccount,
+            receiverAccount.publicKey,
+            executorAccount.publicKey,
+            gsnAccount.publicKey,
+            configAccount.publicKey,
+            new u64(10000000),
+            new u64(0),
+            programId,
+        );
+
+        // Only the fee payer signature is missing
+        const transaction = deserializePayload(payload);
+        expect(transaction.version).toBe(0);
+        expect(missingSigners(transaction).map(key => key.toBase58())).toEqual([
+            executorAccount.publicKey.toBase58(),
+        ]);
+
+        const signature = await relaySubmitPayload(connection, payload, executorAccount);
+        console.log('Transaction signature:', signature);
+
+        const received = await connection.getBalance(receiverAccount.publicKey);
+        expect(received).toBeGreaterThan(0);
+        console.log('✓ Versioned payload relayed');
     }, TEST_TIMEOUT);
 });